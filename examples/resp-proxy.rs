@@ -0,0 +1,163 @@
+// A RESP proxy for debugging: it sits between clients (or a replica) and the server, decodes every
+// frame going either way with RespCodec and logs it in a readable form before passing it on.
+//
+//     cargo run --example resp-proxy -- --port 6380 --upstream 127.0.0.1:6379
+//     redis-cli -p 6380 ...
+//     cargo run -- --port 6381 --replicaof "127.0.0.1 6380"
+//
+// The RDB a master sends after +FULLRESYNC isn't a frame, it is read off the link as it is and
+// logged by its size. This is an example rather than a second binary so that `cargo run` still
+// starts the server.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Context;
+use clap::Parser;
+use futures::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::{FramedRead, FramedWrite};
+use tracing::{error, info, level_filters::LevelFilter};
+use tracing_subscriber::{prelude::*, EnvFilter};
+
+use redis_starter_rust::{
+    rdb::load::read_rdb_transfer,
+    resp::{
+        codec::{RespCodec, RespProtocol},
+        value::RespValue,
+    },
+};
+
+#[derive(Parser)]
+#[command(about = "Logs the RESP frames between clients and a server", long_about = None)]
+struct Args {
+    /// TCP port to listen on
+    #[arg(short, long, default_value = "6380")]
+    port: u16,
+
+    /// The server to pass the connections on to
+    #[arg(long, default_value = "127.0.0.1:6379", value_name = "ADDRESS")]
+    upstream: String,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy();
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::Layer::new())
+        .with(filter)
+        .init();
+
+    let listener = TcpListener::bind(("127.0.0.1", args.port)).await?;
+    info!("Proxying 127.0.0.1:{} to {}.", args.port, args.upstream);
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+    loop {
+        let (client, peer) = listener.accept().await?;
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let upstream = args.upstream.clone();
+
+        info!("[{}] {} connected.", id, peer);
+
+        tokio::spawn(async move {
+            match proxy(id, client, &upstream).await {
+                Ok(()) => info!("[{}] Closed.", id),
+                Err(e) => error!("[{}] {:#}", id, e),
+            }
+        });
+    }
+}
+
+// Pumps frames both ways until either side closes the connection.
+async fn proxy(id: u64, client: TcpStream, upstream: &str) -> anyhow::Result<()> {
+    let server = TcpStream::connect(upstream)
+        .await
+        .with_context(|| format!("Failed to connect to {}", upstream))?;
+
+    let (client_reader, client_writer) = client.into_split();
+    let (server_reader, server_writer) = server.into_split();
+
+    // frames are passed on as they were decoded, so neither side gets its RESP3 downgraded
+    let mut from_client = FramedRead::new(client_reader, RespCodec::new());
+    let mut to_server =
+        FramedWrite::new(server_writer, RespCodec::with_protocol(RespProtocol::Resp3));
+    let mut from_server = FramedRead::new(server_reader, RespCodec::new());
+    let mut to_client =
+        FramedWrite::new(client_writer, RespCodec::with_protocol(RespProtocol::Resp3));
+
+    let requests = async {
+        while let Some(frame) = from_client.next().await {
+            let frame = frame.context("Failed to decode a frame from the client")?;
+            info!("[{}] > {}", id, describe(&frame));
+            to_server.send(frame).await?;
+        }
+        anyhow::Ok(())
+    };
+
+    let replies = async {
+        while let Some(frame) = from_server.next().await {
+            let frame = frame.context("Failed to decode a frame from the server")?;
+            info!("[{}] < {}", id, describe(&frame));
+
+            let rdb_follows =
+                matches!(&frame, RespValue::SimpleString(reply) if reply.starts_with("FULLRESYNC"));
+            to_client.send(frame).await?;
+
+            if rdb_follows {
+                let mut buffered = std::mem::take(from_server.read_buffer_mut());
+                let rdb = read_rdb_transfer(&mut buffered, from_server.get_mut()).await?;
+                *from_server.read_buffer_mut() = buffered;
+
+                info!("[{}] < {}", id, describe(&rdb));
+                to_client.send(rdb).await?;
+            }
+        }
+        anyhow::Ok(())
+    };
+
+    tokio::select! {
+        result = requests => result,
+        result = replies => result,
+    }
+}
+
+// One line per frame, strings quoted and escaped the way MONITOR shows them.
+fn describe(value: &RespValue) -> String {
+    let join = |values: &[RespValue]| values.iter().map(describe).collect::<Vec<_>>().join(" ");
+    let join_pairs = |pairs: &[(RespValue, RespValue)]| {
+        pairs
+            .iter()
+            .map(|(key, value)| format!("{} => {}", describe(key), describe(value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match value {
+        RespValue::Null | RespValue::NullArray | RespValue::BulkString(None) => "(nil)".to_string(),
+        RespValue::SimpleString(s) => s.clone(),
+        RespValue::Error(e) => format!("(error) {}", e),
+        RespValue::Integer(n) => format!("(integer) {}", n),
+        RespValue::BulkString(Some(bytes)) => format!("\"{}\"", bytes.escape_ascii()),
+        RespValue::Array(values) if values.is_empty() => "(empty array)".to_string(),
+        RespValue::Array(values) => format!("[{}]", join(values)),
+        RespValue::Rdb(rdb) => format!("(RDB, {} bytes)", rdb.len()),
+        RespValue::RdbEof(mark, rdb) => format!("(RDB up to {}, {} bytes)", mark, rdb.len()),
+        RespValue::Double(d) => format!("(double) {}", d),
+        RespValue::Boolean(b) => format!("(boolean) {}", b),
+        RespValue::BigNumber(n) => format!("(big number) {}", n),
+        RespValue::VerbatimString(format, data) => {
+            format!("({}) \"{}\"", format, data.escape_ascii())
+        }
+        RespValue::Map(pairs) => format!("{{{}}}", join_pairs(pairs)),
+        RespValue::Set(values) => format!("(set) [{}]", join(values)),
+        RespValue::Push(values) => format!("(push) [{}]", join(values)),
+        RespValue::Attribute(pairs, value) => {
+            format!("(attribute) {{{}}} {}", join_pairs(pairs), describe(value))
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    actors::{
+        messages::{ClientsActorMessage, HostId},
+        supervisor::Supervised,
+    },
+    handlers::clients::{ClientInfo, OutputQueue, QueryBuffer},
+};
+
+use std::{collections::HashMap, time::Duration};
+
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{interval, Instant, MissedTickBehavior},
+};
+use tracing::{debug, warn};
+
+// How often client memory is checked against maxmemory-clients, redis does it from clientsCron.
+const EVICTION_CHECK_PERIOD: Duration = Duration::from_millis(100);
+
+struct Client {
+    connected_at: Instant,
+    output: OutputQueue,
+    query: QueryBuffer,
+    // replicas are never evicted, same as redis
+    replica: bool,
+}
+
+/// Keeps track of the connected clients for CLIENT LIST, and enforces maxmemory-clients.
+///
+/// The only client memory counted is the output buffer, what is queued for a client and not yet
+/// on its socket. Query buffers are kept small by the connections themselves, see QueryDecoder. Once the total goes over the limit the clients with the biggest buffers are
+/// disconnected until it is back under.
+pub struct ClientsActor {
+    // The receiver for incoming messages
+    receiver: mpsc::Receiver<ClientsActorMessage>,
+
+    clients: HashMap<HostId, Client>,
+
+    // in bytes, 0 turns eviction off
+    maxmemory_clients: usize,
+
+    // the clients dropped to get under maxmemory_clients, every connection listens for its own id
+    evicted_tx: broadcast::Sender<HostId>,
+}
+
+impl ClientsActor {
+    // Constructor for the actor, nobody is connected to begin with.
+    pub fn new(
+        receiver: mpsc::Receiver<ClientsActorMessage>,
+        maxmemory_clients: usize,
+        evicted_tx: broadcast::Sender<HostId>,
+    ) -> Self {
+        Self {
+            receiver,
+            clients: HashMap::new(),
+            maxmemory_clients,
+            evicted_tx,
+        }
+    }
+
+    // Run the actor
+    pub async fn run(&mut self) {
+        let mut eviction_check = interval(EVICTION_CHECK_PERIOD);
+        eviction_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                msg = self.receiver.recv() => match msg {
+                    Some(msg) => self.handle_message(msg),
+                    None => return,
+                },
+                _ = eviction_check.tick(), if self.maxmemory_clients > 0 => self.evict(),
+            }
+        }
+    }
+
+    // Handle a message.
+    pub fn handle_message(&mut self, msg: ClientsActorMessage) {
+        match msg {
+            ClientsActorMessage::Connect {
+                host_id,
+                output,
+                query,
+            } => {
+                self.clients.insert(
+                    host_id,
+                    Client {
+                        connected_at: Instant::now(),
+                        output,
+                        query,
+                        replica: false,
+                    },
+                );
+            }
+
+            ClientsActorMessage::MarkReplica { host_id } => {
+                if let Some(client) = self.clients.get_mut(&host_id) {
+                    client.replica = true;
+                }
+            }
+
+            ClientsActorMessage::Disconnect { host_id } => {
+                debug!("Forgetting client {:?}", host_id);
+                self.clients.remove(&host_id);
+            }
+
+            ClientsActorMessage::List { respond_to } => {
+                let mut clients: Vec<ClientInfo> = self
+                    .clients
+                    .iter()
+                    .map(|(host_id, client)| ClientInfo {
+                        host_id: host_id.clone(),
+                        age: client.connected_at.elapsed(),
+                        replica: client.replica,
+                        output: client.output.stats(),
+                        query: client.query.stats(),
+                    })
+                    .collect();
+
+                // connection ids go up with every accept, so this is oldest first
+                clients.sort_by_key(|client| match client.host_id {
+                    HostId::Host { id, .. } => id,
+                    HostId::Myself => 0,
+                });
+
+                let _ = respond_to.send(clients);
+            }
+        }
+    }
+
+    fn evict(&mut self) {
+        let mut buffers: Vec<(HostId, usize)> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| !client.replica)
+            .map(|(host_id, client)| (host_id.clone(), client.output.stats().omem))
+            .collect();
+
+        let mut total: usize = buffers.iter().map(|(_, omem)| omem).sum();
+
+        // biggest first
+        buffers.sort_by_key(|(_, omem)| std::cmp::Reverse(*omem));
+
+        for (host_id, omem) in buffers {
+            if total <= self.maxmemory_clients || omem == 0 {
+                break;
+            }
+
+            warn!(
+                "Evicting client {:?} with {} bytes of output, clients use {} bytes of maxmemory-clients {}",
+                host_id, omem, total, self.maxmemory_clients
+            );
+
+            total -= omem;
+            self.clients.remove(&host_id);
+            let _ = self.evicted_tx.send(host_id);
+        }
+    }
+}
+
+impl Supervised for ClientsActor {
+    const NAME: &'static str = "clients";
+
+    async fn serve(&mut self) {
+        self.run().await
+    }
+
+    // Starts over with nobody connected, and closes the connections it knew about so CLIENT LIST and
+    // maxmemory-clients don't miss them. Clients reconnect into the fresh registry.
+    fn restart(self) -> Option<Self> {
+        for host_id in self.clients.into_keys() {
+            let _ = self.evicted_tx.send(host_id);
+        }
+
+        Some(Self::new(
+            self.receiver,
+            self.maxmemory_clients,
+            self.evicted_tx,
+        ))
+    }
+}
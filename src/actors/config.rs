@@ -1,19 +1,20 @@
 use crate::{
-    actors::messages::ConfigActorMessage,
-    protocol::{ConfigCommandParameter, SetCommandParameter},
-    rdb::{codec::RdbCodec, format::Rdb::KeyValuePair},
+    actors::{messages::ConfigActorMessage, supervisor::Supervised},
+    protocol::ConfigCommandParameter,
+    rdb::{codec::encode_snapshot, load::load_rdb},
+    utils::glob_match,
 };
 
-use anyhow::{anyhow, ensure, Context};
-use futures::StreamExt;
+use anyhow::{ensure, Context};
 use tracing::{debug, error};
 // use resp::Value;
 use tokio::{fs::File, io::AsyncWriteExt};
-use tokio::{io::AsyncReadExt, sync::mpsc};
+use tokio::{io::AsyncReadExt, sync::mpsc, task::JoinHandle};
 
-use tokio_util::codec::FramedRead;
-
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 /// Handles CONFIG command. Receives message from the ConfigCommandActorHandle and processes them accordingly.
 pub struct ConfigCommandActor {
@@ -22,16 +23,27 @@ pub struct ConfigCommandActor {
 
     // The key-value hash map for storing data
     kv_hash: HashMap<ConfigCommandParameter, String>,
+
+    // The BGSAVE writing out its snapshot, or the last one to have
+    bgsave: Option<JoinHandle<()>>,
 }
 
 impl ConfigCommandActor {
     // Constructor for the actor
     pub fn new(receiver: mpsc::Receiver<ConfigActorMessage>) -> Self {
         // Initialize the key-value hash map. The key is an enum of two types, dir and dbfilename.
-        let kv_hash = HashMap::new();
+        let mut kv_hash = HashMap::new();
+
+        // There is no AOF and no master until main.rs says otherwise.
+        kv_hash.insert(ConfigCommandParameter::AppendOnly, "no".to_string());
+        kv_hash.insert(ConfigCommandParameter::Replicaof, String::new());
 
         // Return a new actor with the given receiver and an empty key-value hash map
-        Self { receiver, kv_hash }
+        Self {
+            receiver,
+            kv_hash,
+            bgsave: None,
+        }
     }
 
     // Run the actor
@@ -65,6 +77,51 @@ impl ConfigCommandActor {
                 Ok(())
             }
 
+            ConfigActorMessage::GetMatchingConfigValues {
+                patterns,
+                respond_to,
+            } => {
+                let mut matches: Vec<(String, String)> = Vec::new();
+                let mut seen = HashSet::new();
+
+                for pattern in &patterns {
+                    // Without glob characters the pattern is a plain lookup, answered under the
+                    // name it asked for even if that is an alias.
+                    let is_glob = pattern.contains(['*', '?', '[']);
+
+                    for config_key in ConfigCommandParameter::ALL {
+                        if seen.contains(&config_key) {
+                            continue;
+                        }
+
+                        let name = config_key.to_string();
+                        let mut names = std::iter::once(name.as_str())
+                            .chain(config_key.aliases().iter().copied());
+
+                        let reply_name = if is_glob {
+                            names
+                                .any(|n| glob_match(pattern, n, true))
+                                .then_some(name.clone())
+                        } else {
+                            names
+                                .find(|n| n.eq_ignore_ascii_case(pattern))
+                                .map(str::to_string)
+                        };
+
+                        if let (Some(reply_name), Some(value)) =
+                            (reply_name, self.kv_hash.get(&config_key))
+                        {
+                            seen.insert(config_key);
+                            matches.push((reply_name, value.clone()));
+                        }
+                    }
+                }
+
+                let _ = respond_to.send(matches);
+
+                Ok(())
+            }
+
             // Handle a SetValue message
             ConfigActorMessage::SetConfigValue {
                 config_key,
@@ -79,7 +136,6 @@ impl ConfigCommandActor {
             ConfigActorMessage::ImportRdb {
                 set_command_actor_handle,
                 import_from_memory,
-                expire_tx,
             } => {
                 // check if we are loading from memory or disk.
                 // let mut rdb_file_stream_reader;
@@ -87,54 +143,15 @@ impl ConfigCommandActor {
                 match import_from_memory {
                     Some(buffer) => {
                         debug!("Loading config from memory.");
-                        // Create a cursor over the Vec<u8>
-                        let cursor = std::io::Cursor::new(buffer);
-
-                        // stream the rdb file, decoding and parsing the saved entries.
-                        let mut rdb_memory_stream_reader = FramedRead::new(cursor, RdbCodec::new());
-
-                        while let Some(result) = rdb_memory_stream_reader.next().await {
-                            debug!("RDB decoder returned: {:?}", result);
-
-                            match result {
-                                Ok(KeyValuePair {
-                                    key_expiry_time,
-                                    value_type: _,
-                                    key,
-                                    value,
-                                }) => {
-                                    debug!(
-                                        "Loading {} {} {:?} from local db.",
-                                        key, value, key_expiry_time
-                                    );
-
-                                    let mut set_params = SetCommandParameter {
-                                        key: key.clone(),
-                                        value: value.clone(),
-                                        option: None,
-                                        get: None,
-                                        expire: None,
-                                    };
-
-                                    // Check to see if expiry was attached to this RDB entry
-                                    if let Some(expiry) = key_expiry_time {
-                                        set_params.expire = Some(expiry);
-                                        debug!("Set parameters: {:?}", set_params);
-                                    };
-
-                                    set_command_actor_handle
-                                        .set_value(expire_tx.clone(), set_params.clone())
-                                        .await;
-                                }
-                                Ok(_) => {
-                                    debug!("Ignoring other things.")
-                                }
-                                Err(e) => return Err(anyhow!("Error: {}", e)),
-                                // Ok(KeyValuePair { key_expiry_time, value_type, key, value }) => todo!,
-                                // Ok(_) => debug!("Skipping over OpCodes"),
-                                // Err(_) => error!("{}",e),
-                            }
-                        }
+
+                        // This is the snapshot of a full resync and it replaces the dataset, nothing we
+                        // had before may survive next to it. Done here rather than by the caller so it
+                        // is ordered after any import from disk that is still running.
+                        set_command_actor_handle.flush_all().await?;
+                        let loaded =
+                            load_rdb(std::io::Cursor::new(buffer), &set_command_actor_handle)
+                                .await?;
+                        debug!("Loaded {} keys from the master's RDB.", loaded);
 
                         Ok(())
                     }
@@ -183,50 +200,11 @@ impl ConfigCommandActor {
                                 .context("Failed to open RDB file.")?;
 
                             // stream the rdb file, decoding and parsing the saved entries.
-                            let mut rdb_file_stream_reader =
-                                FramedRead::new(rdb_file, RdbCodec::new());
-                            while let Some(result) = rdb_file_stream_reader.next().await {
-                                debug!("RDB decoder returned: {:?}", result);
-
-                                match result {
-                                    Ok(KeyValuePair {
-                                        key_expiry_time,
-                                        value_type: _,
-                                        key,
-                                        value,
-                                    }) => {
-                                        debug!(
-                                            "Loading {} {} {:?} from local db.",
-                                            key, value, key_expiry_time
-                                        );
-
-                                        let mut set_params = SetCommandParameter {
-                                            key: key.clone(),
-                                            value: value.clone(),
-                                            option: None,
-                                            get: None,
-                                            expire: None,
-                                        };
-
-                                        // Check to see if expiry was attached to this RDB entry
-                                        if let Some(expiry) = key_expiry_time {
-                                            set_params.expire = Some(expiry);
-                                            debug!("Set parameters: {:?}", set_params);
-                                        };
-
-                                        set_command_actor_handle
-                                            .set_value(expire_tx.clone(), set_params.clone())
-                                            .await;
-                                    }
-                                    Ok(_) => {
-                                        debug!("Ignoring other things.")
-                                    }
-                                    Err(e) => error!("Failure trying to load config: {}.", e),
-                                    // Ok(KeyValuePair { key_expiry_time, value_type, key, value }) => todo!,
-                                    // Ok(_) => debug!("Skipping over OpCodes"),
-                                    // Err(_) => error!("{}",e),
-                                }
+                            match load_rdb(rdb_file, &set_command_actor_handle).await {
+                                Ok(loaded) => debug!("Loaded {} keys from disk.", loaded),
+                                Err(e) => error!("Failure trying to load config: {:#}.", e),
                             }
+
                             Ok(())
                         }
                     }
@@ -272,8 +250,162 @@ impl ConfigCommandActor {
 
                 let _ = respond_to.send(Some(buffer));
 
+                Ok(())
+            }
+            // Writes the store's contents to dir/dbfilename, like SAVE.
+            // The file is written next to the target first and renamed over it, so a failed save
+            // never leaves a truncated RDB behind.
+            ConfigActorMessage::SaveRdb {
+                set_command_actor_handle,
+                respond_to,
+            } => {
+                let _ = respond_to.send(self.save_rdb(set_command_actor_handle).await);
+
+                Ok(())
+            }
+            // The snapshot is taken before the reply, so the file has every write before the
+            // BGSAVE and none after it. Encoding and writing it happen on a task of their own.
+            ConfigActorMessage::BgsaveRdb {
+                set_command_actor_handle,
+                respond_to,
+            } => {
+                let _ = respond_to.send(self.bgsave_rdb(set_command_actor_handle).await);
+
+                Ok(())
+            }
+            ConfigActorMessage::ReloadRdb {
+                set_command_actor_handle,
+                respond_to,
+            } => {
+                let _ = respond_to.send(self.reload_rdb(set_command_actor_handle).await);
+
                 Ok(())
             }
         }
     }
+
+    // DEBUG RELOAD, a SAVE and a load of the file it wrote, the way a restart would.
+    async fn reload_rdb(
+        &mut self,
+        set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
+    ) -> anyhow::Result<usize> {
+        self.save_rdb(set_command_actor_handle.clone()).await?;
+
+        let dir = self
+            .kv_hash
+            .get(&ConfigCommandParameter::Dir)
+            .context("Failed to retrieve hash value for dir.")?;
+
+        let dbfilename = self
+            .kv_hash
+            .get(&ConfigCommandParameter::DbFilename)
+            .context("Failed to retrieve hash value for dbfilename.")?;
+
+        let fullpath = format!("{}/{}", dir, dbfilename);
+
+        let rdb_file = File::open(&fullpath)
+            .await
+            .context("Failed to open RDB file.")?;
+
+        set_command_actor_handle.flush_all().await?;
+        let loaded = load_rdb(rdb_file, &set_command_actor_handle).await?;
+
+        debug!("Reloaded {} keys from {}", loaded, fullpath);
+
+        Ok(loaded)
+    }
+
+    async fn save_rdb(
+        &mut self,
+        set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
+    ) -> anyhow::Result<()> {
+        // a BGSAVE still writing would put its older snapshot in place after this one
+        if let Some(bgsave) = self.bgsave.take() {
+            let _ = bgsave.await;
+        }
+
+        let (fullpath, temp_path) = self.rdb_paths()?;
+        let rdb = encode_snapshot(set_command_actor_handle.get_snapshot().await?)?;
+
+        write_rdb(&rdb, &temp_path, &fullpath).await
+    }
+
+    async fn bgsave_rdb(
+        &mut self,
+        set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
+    ) -> anyhow::Result<bool> {
+        if self
+            .bgsave
+            .as_ref()
+            .is_some_and(|bgsave| !bgsave.is_finished())
+        {
+            return Ok(false);
+        }
+
+        let (fullpath, temp_path) = self.rdb_paths()?;
+        let snapshot = set_command_actor_handle.get_snapshot().await?;
+
+        self.bgsave = Some(tokio::spawn(async move {
+            let saved = match encode_snapshot(snapshot) {
+                Ok(rdb) => write_rdb(&rdb, &temp_path, &fullpath).await,
+                Err(e) => Err(e.into()),
+            };
+
+            if let Err(e) = saved {
+                error!("BGSAVE failed: {:#}", e);
+            }
+        }));
+
+        Ok(true)
+    }
+
+    // Where dir and dbfilename say the RDB goes, and the temporary file it is written to first.
+    fn rdb_paths(&self) -> anyhow::Result<(String, String)> {
+        let dir = self
+            .kv_hash
+            .get(&ConfigCommandParameter::Dir)
+            .context("Failed to retrieve hash value for dir.")?;
+
+        let dbfilename = self
+            .kv_hash
+            .get(&ConfigCommandParameter::DbFilename)
+            .context("Failed to retrieve hash value for dbfilename.")?;
+
+        let fullpath = format!("{}/{}", dir, dbfilename);
+        let temp_path = format!("{}/temp-{}.rdb", dir, std::process::id());
+
+        Ok((fullpath, temp_path))
+    }
+}
+
+// Writes an encoded RDB next to fullpath and renames it over it.
+async fn write_rdb(rdb: &[u8], temp_path: &str, fullpath: &str) -> anyhow::Result<()> {
+    let mut file = File::create(temp_path)
+        .await
+        .context("Failed to create temporary RDB file.")?;
+    file.write_all(rdb).await?;
+    file.sync_all().await?;
+
+    tokio::fs::rename(temp_path, fullpath)
+        .await
+        .context("Failed to move temporary RDB file into place.")?;
+
+    debug!("Saved {} bytes of RDB to {}", rdb.len(), fullpath);
+
+    Ok(())
+}
+
+impl Supervised for ConfigCommandActor {
+    const NAME: &'static str = "config";
+
+    async fn serve(&mut self) {
+        if let Err(e) = self.run().await {
+            error!("The config actor stopped: {:#}", e);
+        }
+    }
+
+    // The options main.rs set at startup, dir and port among them, are nowhere else to be had.
+    fn restart(self) -> Option<Self> {
+        None
+    }
 }
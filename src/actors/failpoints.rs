@@ -0,0 +1,97 @@
+use crate::{
+    actors::{
+        messages::{FailpointActorMessage, ReplicationFault},
+        supervisor::Supervised,
+    },
+    protocol::Failpoint,
+};
+
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Keeps the faults armed with DEBUG FAILPOINT and hands them out to the replica links, one frame at a time.
+pub struct FailpointActor {
+    // The receiver for incoming messages
+    receiver: mpsc::Receiver<FailpointActorMessage>,
+
+    // every frame is held back this long before it is written out
+    latency: Option<Duration>,
+
+    // how many of the upcoming frames are thrown away
+    drops_remaining: usize,
+
+    // the next replica link with a frame to send is closed instead
+    disconnect: bool,
+}
+
+impl FailpointActor {
+    // Constructor for the actor, nothing is armed to begin with.
+    pub fn new(receiver: mpsc::Receiver<FailpointActorMessage>) -> Self {
+        Self {
+            receiver,
+            latency: None,
+            drops_remaining: 0,
+            disconnect: false,
+        }
+    }
+
+    // Run the actor
+    pub async fn run(&mut self) {
+        // Continuously receive messages and handle them
+        while let Some(msg) = self.receiver.recv().await {
+            self.handle_message(msg);
+        }
+    }
+
+    // Handle a message.
+    pub fn handle_message(&mut self, msg: FailpointActorMessage) {
+        match msg {
+            FailpointActorMessage::Arm { failpoint } => {
+                debug!("Arming failpoint {:?}", failpoint);
+
+                match failpoint {
+                    Failpoint::Latency(0) => self.latency = None,
+                    Failpoint::Latency(ms) => self.latency = Some(Duration::from_millis(ms)),
+                    Failpoint::Drop(count) => self.drops_remaining = count,
+                    Failpoint::Disconnect => self.disconnect = true,
+                    Failpoint::Off => {
+                        self.latency = None;
+                        self.drops_remaining = 0;
+                        self.disconnect = false;
+                    }
+                }
+            }
+            FailpointActorMessage::NextFault { respond_to } => {
+                // Disconnect and drop are one-shot, they are used up by whichever link asks first.
+                let fault = if self.disconnect {
+                    self.disconnect = false;
+                    ReplicationFault::Disconnect
+                } else if self.drops_remaining > 0 {
+                    self.drops_remaining -= 1;
+                    ReplicationFault::Drop
+                } else if let Some(latency) = self.latency {
+                    ReplicationFault::Delay(latency)
+                } else {
+                    ReplicationFault::None
+                };
+
+                let _ = respond_to.send(fault);
+            }
+        }
+    }
+}
+
+impl Supervised for FailpointActor {
+    const NAME: &'static str = "failpoints";
+
+    async fn serve(&mut self) {
+        self.run().await
+    }
+
+    // Comes back with every fault disarmed.
+    fn restart(self) -> Option<Self> {
+        Some(Self::new(self.receiver))
+    }
+}
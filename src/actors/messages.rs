@@ -1,3 +1,11 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use bytes::Bytes;
+
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
@@ -5,11 +13,24 @@ use tokio::sync::oneshot;
 // use crate::protocol::WaitCommandParameter;
 use crate::resp::value::RespValue;
 use crate::{
+    collections::{Collection, ListEnd, StreamId},
+    commandstats::Call,
+    errors::RedisError,
+    eviction::MaxmemoryPolicy,
     handlers::{
-        config_command::ConfigCommandActorHandle, replication::ReplicationActorHandle,
+        clients::{ClientInfo, ClientsActorHandle, OutputQueue, QueryBuffer},
+        config_command::ConfigCommandActorHandle,
+        failpoints::FailpointActorHandle,
+        pubsub::PubSubActorHandle,
+        replication::ReplicationActorHandle,
         set_command::SetCommandActorHandle,
     },
-    protocol::{ConfigCommandParameter, ReplicationSectionData, SetCommandParameter},
+    protocol::{
+        ConfigCommandParameter, Failpoint, ReplicationSectionData, ServerRole,
+        SetCommandExpireOption, SetCommandParameter, XaddCommandParameter, ZaddCommandParameter,
+    },
+    resp::codec::RespProtocol,
+    storage::Kind,
 };
 
 /// The ActorMessage enum defines the kind of messages we can send to the actor.
@@ -19,25 +40,344 @@ use crate::{
 /// which is a message passing channel that allows sending exactly one message.
 #[derive(Debug)]
 pub enum SetActorMessage {
-    // the idea here is that values are stored in a String->Value HashMap.
-    // So, to get a Value back the client must supply a String key.
+    // the idea here is that values are stored in a String->Value HashMap, one per database.
+    // So, to get a Value back the client must supply the database number and a String key.
     GetValue {
+        db: usize,
         key: String,
-        respond_to: oneshot::Sender<Option<String>>,
+        // WrongType if the key holds something other than a string
+        respond_to: oneshot::Sender<Result<Option<Vec<u8>>, RedisError>>,
     },
+    // GetValue for each of keys in one message, the values in the same order
+    GetValues {
+        db: usize,
+        keys: Vec<String>,
+        respond_to: oneshot::Sender<Vec<Option<Vec<u8>>>>,
+    },
+    // writes are acknowledged, once the reply is in readers of the shared view see them
     SetValue {
+        db: usize,
         // SetCommandParameters is defined in protocol.rs
         input: SetCommandParameter,
+        // WrongType for a GET of a key holding something other than a string
+        respond_to: oneshot::Sender<Result<SetOutcome, RedisError>>,
+    },
+    // APPEND, in place, unless the result would be longer than max_len
+    AppendValue {
+        db: usize,
+        key: String,
+        value: Vec<u8>,
+        max_len: usize,
+        // the new length
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // SETRANGE, in place, zero padded up to offset
+    SetRangeValue {
+        db: usize,
+        key: String,
+        offset: usize,
+        value: Vec<u8>,
+        max_len: usize,
+        // the new length
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // INCRBY, in place, a missing key counts from 0
+    IncrValue {
+        db: usize,
+        key: String,
+        by: i64,
+        // the new value
+        respond_to: oneshot::Sender<Result<i64, RedisError>>,
+    },
+    // HSET, creating the hash if there is none
+    HsetValue {
+        db: usize,
+        key: String,
+        pairs: Vec<(String, String)>,
+        // how many of the fields are new
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // HDEL, the key goes with the last field
+    HdelValue {
+        db: usize,
+        key: String,
+        fields: Vec<String>,
+        // how many of the fields were there
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // LPUSH and RPUSH, creating the list if there is none
+    PushValue {
+        db: usize,
+        key: String,
+        end: ListEnd,
+        values: Vec<String>,
+        // the length of the list after the push, and the end each BLPOP or BRPOP it served took
+        // an element from
+        respond_to: oneshot::Sender<Result<(usize, Vec<ListEnd>), RedisError>>,
+    },
+    // LPOP and RPOP of up to count elements, the key goes with the last one
+    PopValue {
+        db: usize,
+        key: String,
+        end: ListEnd,
+        count: usize,
+        // None if there is no such key
+        respond_to: oneshot::Sender<Result<Option<Vec<String>>, RedisError>>,
+    },
+    // BLPOP and BRPOP: pops from the first of keys holding a list, or else leaves waiter for the
+    // next push to any of them, if there is a waiter
+    BlockingPopValue {
+        db: usize,
+        keys: Vec<String>,
+        end: ListEnd,
+        waiter: Option<oneshot::Sender<(String, String)>>,
+        // the key and the element, None if there was nothing to pop
+        respond_to: oneshot::Sender<Result<Option<(String, String)>, RedisError>>,
+    },
+    // SADD, creating the set if there is none
+    SaddValue {
+        db: usize,
+        key: String,
+        members: Vec<String>,
+        // how many of the members are new
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // SREM, the key goes with the last member
+    SremValue {
+        db: usize,
+        key: String,
+        members: Vec<String>,
+        // how many of the members were there
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // ZADD, creating the sorted set if a member gets added
+    ZaddValue {
+        db: usize,
+        input: ZaddCommandParameter,
+        // how many members were added and how many got a new score
+        respond_to: oneshot::Sender<Result<(usize, usize), RedisError>>,
+    },
+    // ZREM, the key goes with the last member
+    ZremValue {
+        db: usize,
+        key: String,
+        members: Vec<String>,
+        // how many of the members were there
+        respond_to: oneshot::Sender<Result<usize, RedisError>>,
+    },
+    // XADD, creating the stream if there is none
+    XaddValue {
+        db: usize,
+        input: XaddCommandParameter,
+        // the ID the entry was added with
+        respond_to: oneshot::Sender<Result<StreamId, RedisError>>,
+    },
+    // ZINCRBY, a missing member starts from 0 and a missing key is created
+    ZincrbyValue {
+        db: usize,
+        key: String,
+        member: String,
+        increment: f64,
+        // the member's new score
+        respond_to: oneshot::Sender<Result<f64, RedisError>>,
+    },
+    // a whole collection in place of whatever was at key, for loading an RDB
+    SetCollection {
+        db: usize,
+        key: String,
+        collection: Collection,
+        expire: Option<SetCommandExpireOption>,
+        respond_to: oneshot::Sender<()>,
+    },
+    // EXPIRE and friends: gives a key a new deadline in unix milliseconds, one already past
+    // deletes it
+    ExpireValue {
+        db: usize,
+        key: String,
+        deadline_ms: u64,
+        // whether there was a live key to expire
+        respond_to: oneshot::Sender<bool>,
+    },
+    // PERSIST: drops a key's deadline, leaving the key
+    PersistValue {
+        db: usize,
+        key: String,
+        // whether the key had a deadline to drop
+        respond_to: oneshot::Sender<bool>,
     },
     DeleteValue {
+        db: usize,
         // Deletes the value at a given interval
         value: String,
+        // whether there was a live key to delete
+        respond_to: oneshot::Sender<bool>,
+    },
+    // DeleteValue for each of keys in one message, whether each was there in the same order
+    DeleteValues {
+        db: usize,
+        keys: Vec<String>,
+        respond_to: oneshot::Sender<Vec<bool>>,
+    },
+    // COPY, checked and written in one go so no other write lands in between
+    CopyValue {
+        db: usize,
+        source: String,
+        destination_db: usize,
+        destination: String,
+        replace: bool,
+        // whether destination was written
+        respond_to: oneshot::Sender<bool>,
+    },
+    // one active expiry cycle, sampling keys with a deadline until time_limit is used up
+    ActiveExpireCycle {
+        time_limit: Duration,
+        respond_to: oneshot::Sender<()>,
+    },
+    // empties every database, e.g. before loading the RDB of a full resync
+    FlushAll {
+        respond_to: oneshot::Sender<()>,
+    },
+    // SWAPDB, the two databases trade places
+    SwapDb {
+        db: usize,
+        other_db: usize,
+        respond_to: oneshot::Sender<()>,
+    },
+    // evicts keys by policy until the dataset takes up no more than maxmemory bytes, or there is
+    // nothing left the policy may evict. The database and name of every key evicted.
+    Evict {
+        maxmemory: usize,
+        policy: MaxmemoryPolicy,
+        respond_to: oneshot::Sender<Vec<(usize, String)>>,
+    },
+    // DEBUG EVICT, count keys by policy whatever maxmemory is, fewer if it runs out
+    EvictKeys {
+        count: usize,
+        policy: MaxmemoryPolicy,
+        respond_to: oneshot::Sender<Vec<(usize, String)>>,
+    },
+    // bytes of keys and values in all the databases, what maxmemory is compared against
+    GetUsedMemory {
+        respond_to: oneshot::Sender<usize>,
     },
-    // returns a vector of all the keys in the HashMap
-    GetKeys {
-        pattern: String,
-        respond_to: oneshot::Sender<Option<Vec<String>>>,
+    // one SCAN step: the next cursor and the keys in between holding kind if there is one, MATCH
+    // is up to the caller
+    Scan {
+        db: usize,
+        cursor: u64,
+        count: usize,
+        kind: Option<Kind>,
+        respond_to: oneshot::Sender<(u64, Vec<String>)>,
     },
+    // number of keys in the database
+    DbSize {
+        db: usize,
+        respond_to: oneshot::Sender<usize>,
+    },
+    // copy of all the non-empty databases, used to write RDB files
+    GetSnapshot {
+        respond_to: oneshot::Sender<Vec<DatabaseSnapshot>>,
+    },
+    // per database counters for INFO keyspace, non-empty databases only
+    GetKeyspaceStats {
+        respond_to: oneshot::Sender<Vec<KeyspaceStats>>,
+    },
+    // the expiry and eviction counters for INFO stats
+    GetExpiryStats {
+        respond_to: oneshot::Sender<ExpiryStats>,
+    },
+    // CONFIG RESETSTAT, the expiry and eviction counters start over from zero
+    ResetStats {
+        respond_to: oneshot::Sender<()>,
+    },
+}
+
+/// What a SET did, decided in the same store step as the write.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetOutcome {
+    // false if NX found the key or XX didn't
+    pub written: bool,
+    // the string it had before, only looked up for SET ... GET
+    pub previous: Option<Vec<u8>>,
+}
+
+/// One dbN:keys=..,expires=..,avg_ttl=.. line of INFO keyspace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyspaceStats {
+    pub db: usize,
+    pub keys: usize,
+    pub expires: usize,
+    pub avg_ttl: u64, // milliseconds
+}
+
+/// How far a replica is behind its master.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplicaLag {
+    // bytes of the replication stream sent to it that it hasn't acknowledged yet
+    pub bytes: i64,
+    // since its last REPLCONF ACK, or since it synced if it hasn't sent one, the lag= of INFO replication
+    pub seconds: u64,
+}
+
+/// How a replica keeps up with applying its master's stream, the replica_repl_* fields of INFO
+/// replication.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ApplyBacklog {
+    // bytes read from the master and not applied yet
+    pub bytes: usize,
+    // requests waiting in the processor's queue, the master's ones among them
+    pub queued: usize,
+    // how long the last write from the master took to apply, and the longest one did
+    pub last_ms: u64,
+    pub max_ms: u64,
+    // writes that took repl-apply-warn-threshold or longer
+    pub slow: u64,
+}
+
+/// The expired_* and evicted_keys fields of INFO stats.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExpiryStats {
+    // every key removed for its deadline, by a read or by the active cycle
+    pub expired_keys: u64,
+    // over the last few seconds of active expiry cycles
+    pub expired_keys_per_sec: f64,
+    // percentage of the keys sampled by the last cycle that had expired
+    pub expired_stale_perc: f64,
+    // cycles that stopped at their time limit with expired keys left to remove
+    pub expired_time_cap_reached_count: u64,
+    // every key removed to get under maxmemory
+    pub evicted_keys: u64,
+}
+
+/// A change to a key, on its way to the __keyspace@<db>__ and __keyevent@<db>__ channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyspaceEvent {
+    // the database of the key, not the one the publishing connection has SELECTed
+    pub db: usize,
+    // the notify-keyspace-events letter of the event: g generic, $ string, l list, s set,
+    // h hash, x expired
+    pub class: char,
+    pub event: &'static str,
+    pub key: String,
+}
+
+/// A BLPOP or BRPOP the store had nothing for yet, handed to its connection to wait on. The store
+/// sends the key and the element once a push serves it.
+#[derive(Debug)]
+pub struct BlockedPop {
+    pub popped: oneshot::Receiver<(String, String)>,
+    // None to wait for as long as it takes
+    pub deadline: Option<tokio::time::Instant>,
+}
+
+/// The contents of one non-empty database: key, value and optional expiry deadline. The values
+/// are the store's own, which it copies before changing one a snapshot still has.
+#[derive(Debug)]
+pub struct DatabaseSnapshot {
+    pub db: usize,
+    pub entries: Vec<(String, Arc<Vec<u8>>, Option<SetCommandExpireOption>)>,
+    // the keys holding something other than a string, the same way
+    pub collections: Vec<(String, Arc<Collection>, Option<SetCommandExpireOption>)>,
 }
 
 #[derive(Debug)]
@@ -49,6 +389,11 @@ pub enum ConfigActorMessage {
         config_key: ConfigCommandParameter,
         respond_to: oneshot::Sender<Option<String>>,
     },
+    // CONFIG GET with globs, (name, value) pairs in reply order
+    GetMatchingConfigValues {
+        patterns: Vec<String>,
+        respond_to: oneshot::Sender<Vec<(String, String)>>,
+    },
     SetConfigValue {
         // should be either dir or dbfilename
         config_key: ConfigCommandParameter,
@@ -56,12 +401,27 @@ pub enum ConfigActorMessage {
     },
     ImportRdb {
         set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
-        import_from_memory: Option<Vec<u8>>,
-        expire_tx: mpsc::Sender<SetCommandParameter>,
+        import_from_memory: Option<Bytes>,
     },
     GetRdb {
         respond_to: oneshot::Sender<Option<Vec<u8>>>,
     },
+    // writes the store's contents to dir/dbfilename
+    SaveRdb {
+        set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
+        respond_to: oneshot::Sender<anyhow::Result<()>>,
+    },
+    // SaveRdb in the background, replying once the snapshot is taken. False if the last one is
+    // still being written.
+    BgsaveRdb {
+        set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
+        respond_to: oneshot::Sender<anyhow::Result<bool>>,
+    },
+    // SaveRdb, then replaces the store's contents with what was saved, replying how many keys that was
+    ReloadRdb {
+        set_command_actor_handle: crate::handlers::set_command::SetCommandActorHandle,
+        respond_to: oneshot::Sender<anyhow::Result<usize>>,
+    },
 }
 
 #[derive(Debug)]
@@ -86,25 +446,169 @@ pub enum ReplicatorActorMessage {
     },
     GetReplicaCount {
         respond_to: oneshot::Sender<usize>, // reply with total number of connected, synced up replicas
-        target_offset: i16,
+        target_offset: i64,
+    },
+
+    // every replica with what we know about it, for INFO replication and ROLE
+    GetReplicas {
+        respond_to: oneshot::Sender<Vec<(HostId, ReplicationSectionData)>>,
+    },
+
+    GetConnectedReplicaCount {
+        respond_to: oneshot::Sender<usize>, // replicas regardless of their offset
     },
 
     ResetReplicaOffset {
         host_id: HostId,
     },
+
+    // REPLCONF ACK from a replica, the offset it has applied the stream up to
+    Acknowledge {
+        host_id: HostId,
+        offset: i64,
+    },
+
+    // how far behind each replica is, in the same order as GetReplicas
+    GetReplicaLags {
+        respond_to: oneshot::Sender<Vec<(HostId, ReplicaLag)>>,
+    },
+
+    // a replica applied a write from its master, with what is still waiting behind it
+    RecordApply {
+        bytes: usize,
+        queued: usize,
+        took: Duration,
+        slow: bool,
+    },
+
+    GetApplyBacklog {
+        respond_to: oneshot::Sender<ApplyBacklog>,
+    },
+
+    // the host hung up, forget everything we knew about it
+    RemoveHost {
+        host_id: HostId,
+    },
+}
+
+#[derive(Debug)]
+pub enum FailpointActorMessage {
+    Arm {
+        failpoint: Failpoint,
+    },
+    NextFault {
+        respond_to: oneshot::Sender<ReplicationFault>,
+    },
+}
+
+#[derive(Debug)]
+pub enum PubSubActorMessage {
+    // adds channels or patterns to a client's subscriptions, replies with its counts after each one
+    Subscribe {
+        host_id: HostId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+        // where published messages go, the connection's output buffer
+        queue: OutputQueue,
+        respond_to: oneshot::Sender<Vec<SubscriptionCounts>>,
+    },
+    // no names means every channel, or every pattern, the client is subscribed to
+    Unsubscribe {
+        host_id: HostId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+        respond_to: oneshot::Sender<Vec<(String, SubscriptionCounts)>>,
+    },
+    // replies with how many clients the message was queued for
+    Publish {
+        channel: String,
+        message: String,
+        respond_to: oneshot::Sender<usize>,
+    },
+    // what INFO clients reports of pubsub
+    Stats {
+        respond_to: oneshot::Sender<PubSubStats>,
+    },
+    // the connection is gone, drop its subscriptions
+    Disconnect {
+        host_id: HostId,
+    },
+}
+
+/// Whether a subscription is to one channel by name, SUBSCRIBE, or to every channel matching a
+/// glob pattern, PSUBSCRIBE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    Channel,
+    Pattern,
+}
+
+/// How many channels and patterns a client is subscribed to, the sub= and psub= of CLIENT LIST.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubscriptionCounts {
+    pub channels: usize,
+    pub patterns: usize,
+}
+
+impl SubscriptionCounts {
+    /// The count (P)SUBSCRIBE and (P)UNSUBSCRIBE confirm with, channels and patterns together.
+    pub fn total(&self) -> usize {
+        self.channels + self.patterns
+    }
+}
+
+/// The pubsub fields of INFO clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PubSubStats {
+    // clients subscribed to at least one channel or pattern
+    pub clients: usize,
+    // channels and patterns with at least one subscriber
+    pub channels: usize,
+    pub patterns: usize,
+}
+
+#[derive(Debug)]
+pub enum ClientsActorMessage {
+    // a connection was accepted
+    Connect {
+        host_id: HostId,
+        output: OutputQueue,
+        query: QueryBuffer,
+    },
+    // the connection sent PSYNC, it is a replica from now on
+    MarkReplica {
+        host_id: HostId,
+    },
+    Disconnect {
+        host_id: HostId,
+    },
+    List {
+        respond_to: oneshot::Sender<Vec<ClientInfo>>,
+    },
+}
+
+/// What a replica link does with the next frame it was about to write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplicationFault {
+    None,
+    Delay(Duration),
+    Drop,
+    Disconnect,
 }
 
 #[derive(Clone, Hash, Eq, PartialEq)]
 pub enum HostId {
-    Host { ip: String, port: u16 },
+    // id is assigned at accept time and never reused, so it tells connections apart even when they share
+    // an address (a proxy, NAT rebinding). ip and port are the peer address, kept for display.
+    Host { id: u64, ip: String, port: u16 },
     Myself, // this is used to store this redis' instance own metadata, like its offset, etc.
 }
 
 impl std::fmt::Debug for HostId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            HostId::Host { ip, port } => {
-                write!(f, "{}:{}", ip, port)
+            HostId::Host { id, ip, port } => {
+                write!(f, "id={} addr={}:{}", id, ip, port)
             }
             HostId::Myself => write!(f, "self"),
         }
@@ -113,14 +617,19 @@ impl std::fmt::Debug for HostId {
 impl std::fmt::Display for HostId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            HostId::Host { ip, port } => {
-                write!(f, "{}:{}", ip, port)
+            HostId::Host { id, ip, port } => {
+                write!(f, "id={} addr={}:{}", id, ip, port)
             }
             HostId::Myself => write!(f, "HostId::Myself"),
         }
     }
 }
 
+/// The connections whose GET the connection answers itself, kept by the processor.
+pub type FastPathClients = Arc<RwLock<HashSet<HostId>>>;
+
+// nearly every message is a Process, boxing it would only add an allocation per request
+#[allow(clippy::large_enum_variant)]
 pub enum ProcessorActorMessage {
     // connection string to connect to master
     Process {
@@ -128,18 +637,48 @@ pub enum ProcessorActorMessage {
         set_command_actor_handle: SetCommandActorHandle,
         config_command_actor_handle: ConfigCommandActorHandle,
         replication_actor_handle: ReplicationActorHandle,
+        failpoint_actor_handle: FailpointActorHandle,
+        pubsub_actor_handle: PubSubActorHandle,
+        clients_actor_handle: ClientsActorHandle,
         host_id: HostId,
-        expire_tx: mpsc::Sender<SetCommandParameter>,
         master_tx: mpsc::Sender<String>,
         replica_tx: broadcast::Sender<RespValue>, // typically this is either +OK or offset
         client_or_replica_tx: Option<mpsc::Sender<bool>>,
         // NOTE: a single request like PSYNC can return multiple responses.
         // So, where a Vec<u8> is a single reponse, a Vec<Vec<u8>> is multiple responses.
         respond_to: oneshot::Sender<Option<Vec<RespValue>>>,
-        wait_sleep_tx: Option<mpsc::Sender<i16>>,
+        wait_sleep_tx: Option<mpsc::Sender<i64>>,
+        // where a BLPOP or BRPOP with nothing to pop yet is handed to its connection to wait on
+        blocked_tx: Option<mpsc::Sender<BlockedPop>>,
+        // The connection's output buffer, for out-of-band frames (RESP3 pushes) that don't answer a request.
+        push_tx: Option<OutputQueue>,
+    },
+    // the authenticator's answer to the credentials an AUTH or HELLO gave, worked out off the
+    // processor, for it to apply and reply with
+    Authenticated {
+        host_id: HostId,
+        username: String,
+        verified: bool,
+        // the rest of a HELLO, None for AUTH
+        hello: Option<PendingHello>,
+        push_tx: Option<OutputQueue>,
+        respond_to: Call,
+    },
+    // the connection is gone, forget its per-client state (selected database, etc.)
+    Disconnect {
+        host_id: HostId,
     },
 }
 
+/// What a HELLO does once its credentials are good, everything else about it already checked.
+#[derive(Debug)]
+pub struct PendingHello {
+    pub protocol: Option<RespProtocol>,
+    pub setname: Option<String>,
+    // this server's, for the reply
+    pub role: Option<ServerRole>,
+}
+
 // implement the debug trait for the ProcessorActorMessage enum
 impl std::fmt::Debug for ProcessorActorMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -149,13 +688,17 @@ impl std::fmt::Debug for ProcessorActorMessage {
                 set_command_actor_handle: _,
                 config_command_actor_handle: _,
                 replication_actor_handle: _,
+                failpoint_actor_handle: _,
+                pubsub_actor_handle: _,
+                clients_actor_handle: _,
                 host_id: _,
-                expire_tx: _,
                 master_tx: _,
                 replica_tx,
                 client_or_replica_tx: _,
                 respond_to: _,
-                wait_sleep_tx: _
+                wait_sleep_tx: _,
+                blocked_tx: _,
+                push_tx: _,
             } => {
                 write!(
                     f,
@@ -163,6 +706,18 @@ impl std::fmt::Debug for ProcessorActorMessage {
                     request, replica_tx
                 )
             }
+            ProcessorActorMessage::Authenticated {
+                host_id, verified, ..
+            } => {
+                write!(
+                    f,
+                    "ProcessorActorMessage::Authenticated host: {:?}, verified: {}",
+                    host_id, verified
+                )
+            }
+            ProcessorActorMessage::Disconnect { host_id } => {
+                write!(f, "ProcessorActorMessage::Disconnect host: {:?}", host_id)
+            }
         }
     }
 }
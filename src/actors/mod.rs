@@ -5,12 +5,29 @@
 /// The `set` module contains set actor implementations.
 ///
 /// The `process` module contains process actor implementations.
+///
+/// The `failpoints` module contains the fault injection actor behind DEBUG FAILPOINT.
+///
+/// The `pubsub` module contains the publish/subscribe actor.
+///
+/// The `clients` module contains the actor tracking connected clients and their output buffers.
+///
+/// The `supervisor` module restarts the actors above when they panic, or stops the server. The
+/// connections use it to keep a panic to the one it happened on.
+pub(crate) mod clients;
+
 pub(crate) mod config;
 
+pub(crate) mod failpoints;
+
+pub(crate) mod pubsub;
+
 pub(crate) mod set;
 
 pub(crate) mod replicator;
 
-pub(crate) mod messages;
+pub mod supervisor;
+
+pub mod messages;
 pub(crate) mod processor;
 // pub(crate) mod wait;
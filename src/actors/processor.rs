@@ -1,43 +1,1130 @@
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use crate::{
-    actors::messages::{HostId, ProcessorActorMessage},
-    parsers::parse_command,
+    actors::{
+        messages::{
+            BlockedPop, ExpiryStats, FastPathClients, HostId, KeyspaceStats, PendingHello,
+            ProcessorActorMessage, ReplicaLag, SubscriptionCounts, SubscriptionKind,
+        },
+        supervisor::{self, Supervised},
+    },
+    auth::SharedAuthenticator,
+    collections::{Collection, ListEnd, StreamFields, StreamId},
+    commandstats::{Call, CommandStatsTable},
+    digest,
+    errors::RedisError,
+    eviction::{self, MaxmemoryPolicy, MAXMEMORY_POLICIES},
+    handlers::{
+        clients::{ClientInfo, OutputQueue},
+        config_command::ConfigCommandActorHandle,
+        pubsub::PubSubActorHandle,
+        replication::ReplicationActorHandle,
+        set_command::SetCommandActorHandle,
+    },
+    monitor::{CommandEvent, CommandFeed},
+    parsers::{command_flags, command_name, parse_command},
     protocol::{
-        RedisCommand, ReplConfCommandParameter, ReplicationSectionData, ServerRole,
-        SetCommandParameter,
+        ClientCommandParameter, ClientListFilter, CommandFlag, ConfigCommandParameter,
+        DebugCommandParameter, InfoCommandParameter, MemoryCommandParameter, RedisCommand,
+        ReplConfCommandParameter, ReplicationSectionData, ServerRole, SetCommandExpireOption,
     },
-    resp::value::RespValue,
-    utils::sleeping_task,
+    rdb::codec::{encode_snapshot, serialized_collection_length, serialized_length},
+    read_pool::{Deadline, ReadPool, DEFAULT_READ_TIME_LIMIT},
+    resp::{codec::RespProtocol, value::RespValue},
+    storage::{Kind, Object},
+    units::{parse_memory, parse_nonzero_memory},
+    utils::{generate_replication_id, glob_match, sleeping_task},
 };
 
 use anyhow::{anyhow, Context};
-use tokio::sync::mpsc;
-use tracing::{debug, error, warn};
+use bytes::Bytes;
+use tokio::{
+    sync::{broadcast, mpsc, oneshot},
+    time::{sleep_until, Instant},
+};
+use tracing::{debug, error, info, warn};
+
+// How many keys each SCAN step takes when KEYS is answered by scanning.
+const KEYS_SCAN_STEP: usize = 1000;
+
+// Same as redis, 512MB, for when proto-max-bulk-len was never set.
+const DEFAULT_PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+// Same as redis, for when repl-diskless-sync-delay was never set.
+const DEFAULT_REPL_DISKLESS_SYNC_DELAY: u64 = 5;
+
+// Same as redis, for when shutdown-timeout was never set.
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 10;
+
+// How often SHUTDOWN looks whether the replicas have caught up.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// What a RESP2 client may run while it is subscribed, as the command table names them.
+const SUBSCRIBE_CONTEXT_COMMANDS: &[&str] = &[
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "PSUBSCRIBE",
+    "PUNSUBSCRIBE",
+    "SSUBSCRIBE",
+    "SUNSUBSCRIBE",
+    "PING",
+    "QUIT",
+    "RESET",
+];
+
+// Every key of a database, one SCAN step at a time, so the store serves everyone else in between.
+async fn scan_keys(
+    set_command_actor_handle: &SetCommandActorHandle,
+    db: usize,
+) -> Result<Vec<Arc<str>>, RedisError> {
+    let mut keys = Vec::new();
+    let mut cursor = 0;
+
+    loop {
+        let (next_cursor, step) = set_command_actor_handle
+            .scan(db, cursor, KEYS_SCAN_STEP, None)
+            .await?;
+        keys.extend(step.into_iter().map(Arc::from));
+
+        if next_cursor == 0 {
+            return Ok(keys);
+        }
+        cursor = next_cursor;
+    }
+}
+
+// proto-max-bulk-len, the longest value SET and APPEND may leave behind.
+async fn proto_max_bulk_len(
+    config_command_actor_handle: &ConfigCommandActorHandle,
+) -> Result<usize, RedisError> {
+    Ok(config_command_actor_handle
+        .get_value(ConfigCommandParameter::ProtoMaxBulkLen)
+        .await?
+        .and_then(|max_len| max_len.parse().ok())
+        .unwrap_or(DEFAULT_PROTO_MAX_BULK_LEN))
+}
+
+// read-time-limit, None if it is 0 and a read may take as long as it takes.
+async fn read_time_limit(
+    config_command_actor_handle: &ConfigCommandActorHandle,
+) -> Result<Option<Duration>, RedisError> {
+    let time_limit = config_command_actor_handle
+        .get_value(ConfigCommandParameter::ReadTimeLimit)
+        .await?
+        .and_then(|time_limit| time_limit.parse().ok())
+        .unwrap_or(DEFAULT_READ_TIME_LIMIT);
+
+    Ok(Some(Duration::from_millis(time_limit)).filter(|time_limit| !time_limit.is_zero()))
+}
+
+// Answers a read of the collections at keys on the read pool, as they are when it is called.
+// Unless inside EXEC, the reply goes out from a task of its own and the processor moves on to the
+// next request, like KEYS. A read that fails, WRONGTYPE or past read-time-limit, replies the error.
+#[allow(clippy::too_many_arguments)]
+async fn pooled_read<F>(
+    read_pool: &ReadPool,
+    set_command_actor_handle: &SetCommandActorHandle,
+    config_command_actor_handle: &ConfigCommandActorHandle,
+    db: usize,
+    keys: &[String],
+    in_exec: bool,
+    respond_to: Call,
+    read: F,
+) -> Result<(), RedisError>
+where
+    F: FnOnce(&[Option<Arc<Collection>>], &Deadline) -> Result<RespValue, RedisError>
+        + Send
+        + 'static,
+{
+    let time_limit = read_time_limit(config_command_actor_handle).await?;
+    let collections = match set_command_actor_handle.snapshot_collections(db, keys) {
+        Err(RedisError::WrongType) => {
+            let _ = respond_to.send(Some(vec![RedisError::WrongType.into()]));
+
+            return Ok(());
+        }
+        collections => collections?,
+    };
+
+    let read_pool = read_pool.clone();
+    let reply = async move {
+        let reply = read_pool
+            .run(time_limit, move |deadline| read(&collections, deadline))
+            .await
+            .unwrap_or_else(RespValue::from);
+        let _ = respond_to.send(Some(vec![reply]));
+    };
+
+    if in_exec {
+        reply.await;
+    } else {
+        tokio::spawn(reply);
+    }
+
+    Ok(())
+}
+
+// The sets SUNION, SINTER and SINTERCARD read, None for a key that doesn't exist. WRONGTYPE if
+// one of them holds anything else.
+fn snapshot_sets(
+    collections: &[Option<Arc<Collection>>],
+) -> Result<Vec<Option<&HashSet<String>>>, RedisError> {
+    collections
+        .iter()
+        .map(|collection| {
+            collection
+                .as_deref()
+                .map(|collection| collection.as_set().ok_or(RedisError::WrongType))
+                .transpose()
+        })
+        .collect()
+}
+
+// The members in every one of sets, up to limit of them unless it is 0. Goes through the smallest
+// set and looks for each member in the others, none at all if a key doesn't exist.
+fn intersection<'a>(
+    sets: &[Option<&'a HashSet<String>>],
+    limit: usize,
+    deadline: &Deadline,
+) -> Result<Vec<&'a String>, RedisError> {
+    let Some(mut sets) = sets.iter().copied().collect::<Option<Vec<_>>>() else {
+        return Ok(Vec::new());
+    };
+    sets.sort_by_key(|set| set.len());
+
+    let Some((smallest, others)) = sets.split_first() else {
+        return Ok(Vec::new());
+    };
+
+    let mut members = Vec::new();
+    for member in smallest.iter() {
+        deadline.tick()?;
+
+        if others.iter().all(|set| set.contains(member)) {
+            members.push(member);
+
+            if members.len() == limit {
+                break;
+            }
+        }
+    }
+
+    Ok(members)
+}
+
+// What CONFIG SET keeps for config_key, sizes in bytes whatever unit they came in, or what is
+// wrong with value. Only what is read again for every request can change at runtime.
+fn config_set_value(config_key: ConfigCommandParameter, value: &str) -> Result<String, String> {
+    match config_key {
+        ConfigCommandParameter::Maxmemory => parse_memory(value).map(|bytes| bytes.to_string()),
+        ConfigCommandParameter::ProtoMaxBulkLen => {
+            parse_nonzero_memory(value).map(|bytes| bytes.to_string())
+        }
+        ConfigCommandParameter::ReadTimeLimit => value
+            .parse::<u64>()
+            .map(|time_limit| time_limit.to_string())
+            .map_err(|_| "argument couldn't be parsed into an integer".to_string()),
+        ConfigCommandParameter::MaxmemoryPolicy => MAXMEMORY_POLICIES
+            .iter()
+            .find(|policy| policy.eq_ignore_ascii_case(value))
+            .map(|policy| policy.to_string())
+            .ok_or_else(|| {
+                format!(
+                    "argument(s) must be one of the following: {}",
+                    MAXMEMORY_POLICIES.join(", ")
+                )
+            }),
+        _ => Err("can't set immutable config".to_string()),
+    }
+}
+
+// maxmemory and maxmemory-policy, 0 and noeviction if they were never set.
+async fn maxmemory(
+    config_command_actor_handle: &ConfigCommandActorHandle,
+) -> Result<(usize, MaxmemoryPolicy), RedisError> {
+    let maxmemory = config_command_actor_handle
+        .get_value(ConfigCommandParameter::Maxmemory)
+        .await?
+        .and_then(|maxmemory| maxmemory.parse().ok())
+        .unwrap_or(0);
+    let policy = config_command_actor_handle
+        .get_value(ConfigCommandParameter::MaxmemoryPolicy)
+        .await?
+        .and_then(|policy| policy.parse().ok())
+        .unwrap_or_default();
+
+    Ok((maxmemory, policy))
+}
+
+// A sampled active expiry cycle finding more than this percentage of keys past their deadline is
+// worth a line in MEMORY DOCTOR, same as the share the cycle itself keeps going past.
+const MEMORY_DOCTOR_STALE_PERC: f64 = 25.0;
+
+// MEMORY DOCTOR's report, in the words redis uses to open and close it. The issues are the ones
+// this server can tell from its counters: expired keys piling up, and a maxmemory that the policy
+// can't keep to.
+fn memory_doctor(
+    used_memory: usize,
+    (maxmemory, policy): (usize, MaxmemoryPolicy),
+    keyspace: &[KeyspaceStats],
+    expiry: ExpiryStats,
+) -> String {
+    let mut issues = Vec::new();
+
+    if expiry.expired_stale_perc > MEMORY_DOCTOR_STALE_PERC {
+        issues.push(format!(
+            "Expired keys: {:.2}% of the keys with a deadline the last active expiry cycle sampled were past it. A higher hz runs the cycle more often and reclaims them sooner.",
+            expiry.expired_stale_perc
+        ));
+    }
+
+    if maxmemory > 0 && policy == MaxmemoryPolicy::NoEviction && used_memory * 10 >= maxmemory * 9 {
+        issues.push(format!(
+            "Near maxmemory: used_memory is {}% of maxmemory, and with maxmemory-policy noeviction no key goes to make room.",
+            used_memory * 100 / maxmemory
+        ));
+    }
+
+    let expires: usize = keyspace.iter().map(|stats| stats.expires).sum();
+    if maxmemory > 0 && used_memory > maxmemory && policy.volatile() && expires == 0 {
+        issues.push(format!(
+            "Nothing to evict: used_memory is over maxmemory, and maxmemory-policy {} only evicts keys with a deadline while no key has one.",
+            policy
+        ));
+    }
+
+    if issues.is_empty() {
+        return "Hi Sam, I can't find any memory issue in your instance. I can only account for what occurs on this base.".to_string();
+    }
+
+    let mut report =
+        String::from("Sam, I detected a few issues in this Redis instance memory implants:\n\n");
+    for issue in issues {
+        report.push_str(&format!(" * {}\n\n", issue));
+    }
+    report.push_str("I'm here to keep you safe, Sam. I want to help you.\n");
+
+    report
+}
+
+// The KEYS reply. The keys are a snapshot read without the store actor, or gathered by SCAN, and
+// matched on a blocking thread so neither the store nor the runtime's workers wait on it.
+async fn keys(
+    set_command_actor_handle: &SetCommandActorHandle,
+    db: usize,
+    pattern: String,
+    by_scan: bool,
+) -> RespValue {
+    let keys = if by_scan {
+        scan_keys(set_command_actor_handle, db).await
+    } else {
+        set_command_actor_handle.keys(db)
+    };
+    let keys = match keys {
+        Ok(keys) => keys,
+        Err(e) => return e.into(),
+    };
+
+    tokio::task::spawn_blocking(move || {
+        RespValue::Array(
+            keys.iter()
+                .filter(|key| glob_match(&pattern, key, false))
+                .map(|key| RespValue::BulkString(Some(Bytes::copy_from_slice(key.as_bytes()))))
+                .collect(),
+        )
+    })
+    .await
+    .unwrap_or_else(|e| RedisError::Internal(format!("KEYS failed: {}", e)).into())
+}
 
 // use rand::distributions::Alphanumeric;
 // use rand::Rng;
 // use std::io::Write;
 // use std::iter::{self};
 
+// The commands with an argument that may be any bytes, and where it is: ECHO's message, and the
+// values SET, APPEND and SETRANGE store.
+const BINARY_ARGUMENTS: [(&str, usize); 4] =
+    [("ECHO", 1), ("SET", 2), ("APPEND", 2), ("SETRANGE", 3)];
+
+// Where the argument that may be any bytes is in the elements of a request, if it has one.
+fn binary_argument_index(elements: &[RespValue]) -> Option<usize> {
+    let Some(RespValue::BulkString(Some(name))) = elements.first() else {
+        return None;
+    };
+
+    BINARY_ARGUMENTS
+        .iter()
+        .find(|(command, _)| name.eq_ignore_ascii_case(command.as_bytes()))
+        .map(|(_, index)| *index)
+}
+
+// The argument of a request that may be any bytes, as the client sent it. None for a request
+// without one.
+fn binary_argument(request: &RespValue) -> Option<Bytes> {
+    let RespValue::Array(elements) = request else {
+        return None;
+    };
+
+    match elements.get(binary_argument_index(elements)?) {
+        Some(RespValue::BulkString(Some(argument))) => Some(argument.clone()),
+        _ => None,
+    }
+}
+
+// The request as the nom parser reads it, a string. The argument that may be any bytes stands in
+// as lossy UTF-8, the arm takes it from the request as it came. Anything else has to be UTF-8.
+fn parseable(request: &RespValue) -> Result<String, RedisError> {
+    if let Ok(encoded) = request.to_encoded_string() {
+        return Ok(encoded);
+    }
+
+    let RespValue::Array(elements) = request else {
+        return Err(RedisError::NotUtf8);
+    };
+    let binary = binary_argument_index(elements);
+
+    let elements = elements
+        .iter()
+        .enumerate()
+        .map(|(index, element)| match element {
+            RespValue::BulkString(Some(argument)) if Some(index) == binary => {
+                RespValue::BulkString(Some(String::from_utf8_lossy(argument).into_owned().into()))
+            }
+            element => element.clone(),
+        })
+        .collect();
+
+    RespValue::Array(elements)
+        .to_encoded_string()
+        .map_err(|_| RedisError::NotUtf8)
+}
+
+// HELLO's protover, 2 or 3.
+fn protocol_version(protover: i64) -> Result<RespProtocol, RedisError> {
+    match protover {
+        2 => Ok(RespProtocol::Resp2),
+        3 => Ok(RespProtocol::Resp3),
+        _ => Err(RedisError::NoProto),
+    }
+}
+
+// Client names go in CLIENT LIST as they are, so they can't have spaces or newlines.
+fn check_client_name(name: &str) -> Result<(), RedisError> {
+    if name.chars().all(|c| ('!'..='~').contains(&c)) {
+        Ok(())
+    } else {
+        Err(RedisError::InvalidClientName)
+    }
+}
+
+// What HELLO tells a client about the server and its connection, a map in RESP3 and a flat
+// array of names and values in RESP2.
+fn hello_reply(host_id: &HostId, protocol: RespProtocol, role: Option<ServerRole>) -> RespValue {
+    let bulk = |s: &str| RespValue::BulkString(Some(s.to_string().into()));
+    let id = match host_id {
+        HostId::Host { id, .. } => *id as i64,
+        HostId::Myself => 0,
+    };
+
+    RespValue::Map(vec![
+        (bulk("server"), bulk("redis")),
+        (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+        (
+            bulk("proto"),
+            RespValue::Integer(match protocol {
+                RespProtocol::Resp2 => 2,
+                RespProtocol::Resp3 => 3,
+            }),
+        ),
+        (bulk("id"), RespValue::Integer(id)),
+        (bulk("mode"), bulk("standalone")),
+        (
+            bulk("role"),
+            bulk(if role == Some(ServerRole::Slave) {
+                "replica"
+            } else {
+                "master"
+            }),
+        ),
+        (bulk("modules"), RespValue::Array(vec![])),
+    ])
+}
+
+/// State redis keeps per connection rather than per server.
+#[derive(Debug, Default)]
+struct ClientState {
+    // the database SELECT switched to
+    db: usize,
+    // open from MULTI until EXEC or DISCARD
+    transaction: Option<Transaction>,
+    // channels SUBSCRIBEd and patterns PSUBSCRIBEd to, as the last confirmation counted them
+    subscriptions: SubscriptionCounts,
+    // HELLO ... SETNAME, empty if none was set
+    name: String,
+    // AUTH or HELLO ... AUTH went through
+    authenticated: bool,
+    // who they authenticated as, None for the default user
+    user: Option<String>,
+    // in the processor's fast_path set, see ProcessorActor::fast_path
+    fast_path: bool,
+}
+
+/// What CLIENT LIST TYPE selects.
+#[derive(Debug, Clone, Copy)]
+enum ClientType {
+    Normal,
+    Master,
+    Replica,
+    Pubsub,
+}
+
+impl FromStr for ClientType {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(ClientType::Normal),
+            "master" => Ok(ClientType::Master),
+            "replica" | "slave" => Ok(ClientType::Replica),
+            "pubsub" => Ok(ClientType::Pubsub),
+            _ => Err(RedisError::UnknownClientType(s.to_string())),
+        }
+    }
+}
+
+/// Requests queued between MULTI and EXEC.
+#[derive(Debug, Default)]
+struct Transaction {
+    queued: Vec<RespValue>,
+    // a command was refused while queueing, EXEC will discard the lot
+    aborted: bool,
+}
+
+/// A full resync about to go out. A diskless one waits for its delay to run out, and every replica
+/// that PSYNCs in the meantime gets the same snapshot.
+struct PendingSync {
+    due: Instant,
+    set_command_actor_handle: SetCommandActorHandle,
+    replication_actor_handle: ReplicationActorHandle,
+    replica_tx: broadcast::Sender<RespValue>,
+    // the replicas waiting, each one's offset is the master's as of the snapshot
+    replicas: Vec<HostId>,
+}
+
 /// Handles CONFIG command. Receives message from the ProcessorActorHandle and processes them accordingly.
 pub struct ProcessorActor {
     // The receiver for incoming messages
     receiver: mpsc::Receiver<ProcessorActorMessage>,
+
+    // Per connection state, dropped when the connection goes away.
+    clients: HashMap<HostId, ClientState>,
+
+    // The database the replicas are currently applying writes to.
+    // None means the next write must be preceded by a SELECT.
+    replication_db: Option<usize>,
+
+    // Some once a replica asked for a diskless full resync, until the snapshot goes out.
+    pending_sync: Option<PendingSync>,
+
+    // Whether the last write checked found the dataset over maxmemory under noeviction.
+    over_maxmemory: bool,
+
+    // Where the client whose request panicked is named, for its connection to close.
+    panicked_tx: broadcast::Sender<HostId>,
+
+    // INFO commandstats and latencystats, by the name in the command table.
+    command_stats: CommandStatsTable,
+
+    // Set once SHUTDOWN is waiting for the replicas, the dataset they are catching up with stays as it is.
+    shutting_down: bool,
+
+    // What AUTH and HELLO check credentials with.
+    authenticator: SharedAuthenticator,
+
+    // Where a task asking the authenticator sends its answer, weak so it doesn't keep the actor up.
+    self_tx: mpsc::WeakSender<ProcessorActorMessage>,
+
+    // Where LRANGE, SMEMBERS, SUNION, SINTER and SINTERCARD run, off the processor.
+    read_pool: ReadPool,
+
+    // Every command that ran and how it went, for MONITOR and the audit log, see monitor.rs.
+    command_feed: CommandFeed,
+
+    // The connections whose GET may skip the processor, those that are authenticated and not
+    // subscribed in RESP2. A connection is taken out before each of its requests runs and put back
+    // after, so no reply goes out before a change to either is known.
+    fast_path: FastPathClients,
 }
 
 impl ProcessorActor {
     // Constructor for the actor
-    pub fn new(receiver: mpsc::Receiver<ProcessorActorMessage>) -> Self {
-        // Return a new actor with the given receiver and an empty key-value hash map
-        Self { receiver }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        receiver: mpsc::Receiver<ProcessorActorMessage>,
+        self_tx: mpsc::WeakSender<ProcessorActorMessage>,
+        panicked_tx: broadcast::Sender<HostId>,
+        command_stats: CommandStatsTable,
+        authenticator: SharedAuthenticator,
+        command_feed: CommandFeed,
+        fast_path: FastPathClients,
+    ) -> Self {
+        // Return a new actor with the given receiver and no clients yet.
+        // Replicas start out in database 0, same as everyone else.
+        Self {
+            receiver,
+            clients: HashMap::new(),
+            replication_db: Some(0),
+            pending_sync: None,
+            over_maxmemory: false,
+            panicked_tx,
+            command_stats,
+            shutting_down: false,
+            authenticator,
+            self_tx,
+            read_pool: ReadPool::default(),
+            command_feed,
+            fast_path,
+        }
+    }
+
+    // Takes the client out of the fast path set while a request of its runs.
+    fn leave_fast_path(&mut self, host_id: &HostId) {
+        if let Some(state) = self
+            .clients
+            .get_mut(host_id)
+            .filter(|state| state.fast_path)
+        {
+            state.fast_path = false;
+            self.fast_path.write().unwrap().remove(host_id);
+        }
+    }
+
+    // Puts the client back once its request ran, if its GETs still don't need the processor.
+    fn enter_fast_path(&mut self, host_id: &HostId, push_tx: Option<&OutputQueue>) {
+        let allowed = self.authenticated(host_id) && !self.in_subscribe_context(host_id, push_tx);
+
+        if let Some(state) = self.clients.get_mut(host_id).filter(|_| allowed) {
+            state.fast_path = true;
+            self.fast_path.write().unwrap().insert(host_id.clone());
+        }
+    }
+
+    // Whether the client may run more than AUTH and HELLO, always unless the authenticator wants
+    // credentials first.
+    fn authenticated(&self, host_id: &HostId) -> bool {
+        !self.authenticator.required()
+            || self
+                .clients
+                .get(host_id)
+                .is_some_and(|client| client.authenticated)
+    }
+
+    // Asks the authenticator about the credentials from a task of its own, so a slow one doesn't hold
+    // up everybody else's requests, and the processor applies the answer when it comes back as an
+    // Authenticated message. The connection sends nothing else until it has its reply. Inside EXEC
+    // it is awaited in place, like pooled_read().
+    #[allow(clippy::too_many_arguments)]
+    async fn authenticate(
+        &mut self,
+        host_id: HostId,
+        username: String,
+        password: String,
+        hello: Option<PendingHello>,
+        push_tx: Option<OutputQueue>,
+        in_exec: bool,
+        respond_to: Call,
+    ) {
+        let authenticator = self.authenticator.clone();
+
+        if in_exec {
+            let verified = authenticator.authenticate(&username, &password).await;
+            self.authenticated_as(host_id, username, verified, hello, push_tx, respond_to);
+            return;
+        }
+
+        let self_tx = self.self_tx.clone();
+        tokio::spawn(async move {
+            let verified = authenticator.authenticate(&username, &password).await;
+
+            // the processor is gone, and the reply with it
+            if let Some(self_tx) = self_tx.upgrade() {
+                let msg = ProcessorActorMessage::Authenticated {
+                    host_id,
+                    username,
+                    verified,
+                    hello,
+                    push_tx,
+                    respond_to,
+                };
+                let _ = self_tx.send(msg).await;
+            }
+        });
+    }
+
+    // Replies to an AUTH or HELLO with what the authenticator said. A failed AUTH leaves an
+    // authenticated connection authenticated.
+    fn authenticated_as(
+        &mut self,
+        host_id: HostId,
+        username: String,
+        verified: bool,
+        hello: Option<PendingHello>,
+        push_tx: Option<OutputQueue>,
+        respond_to: Call,
+    ) {
+        let reply = match hello {
+            _ if !verified => RedisError::WrongPass.into(),
+            Some(hello) => self.hello(&host_id, Some(username), hello, push_tx.as_ref()),
+            None => {
+                // nothing to do for a connection that went in the meantime
+                if let Some(client) = self.clients.get_mut(&host_id) {
+                    client.authenticated = true;
+                    client.user = Some(username);
+                }
+                RespValue::SimpleString("OK".to_string())
+            }
+        };
+
+        let _ = respond_to.send(Some(vec![reply]));
+    }
+
+    // Applies a HELLO whose credentials, if it gave any, are good, and returns its reply. A name
+    // that won't do leaves everything as it was.
+    fn hello(
+        &mut self,
+        host_id: &HostId,
+        username: Option<String>,
+        hello: PendingHello,
+        push_tx: Option<&OutputQueue>,
+    ) -> RespValue {
+        if let Some(Err(e)) = hello.setname.as_deref().map(check_client_name) {
+            return e.into();
+        }
+
+        if let Some(client) = self.clients.get_mut(host_id) {
+            if let Some(username) = username {
+                client.authenticated = true;
+                client.user = Some(username);
+            }
+            if let Some(name) = hello.setname {
+                client.name = name;
+            }
+        }
+
+        // the reply is the first frame in the new protocol
+        if let (Some(protocol), Some(output)) = (hello.protocol, push_tx) {
+            output.set_protocol(protocol);
+        }
+
+        hello_reply(
+            host_id,
+            push_tx.map_or(RespProtocol::Resp2, OutputQueue::protocol),
+            hello.role,
+        )
+    }
+
+    // Whether the client is a RESP2 subscriber, which is sent pushes and little else. In RESP3
+    // replies and pushes can't be mixed up.
+    fn in_subscribe_context(&self, host_id: &HostId, push_tx: Option<&OutputQueue>) -> bool {
+        let subscribed = self
+            .clients
+            .get(host_id)
+            .is_some_and(|state| state.subscriptions.total() > 0);
+
+        subscribed && push_tx.is_none_or(|output| output.protocol() == RespProtocol::Resp2)
+    }
+
+    // Counts a request for a known command that was refused without running.
+    fn reject(&self, name: Option<&'static str>) {
+        if let Some(name) = name {
+            self.command_stats.reject(name);
+        }
+    }
+
+    // Sends a request on to the replicas if the command table flags it as a write, everything else
+    // (PING, INFO, CONFIG GET, ...) stays off the replication stream. Like redis, a SELECT goes out first
+    // whenever the write happened in a different database than the previous one on the stream.
+    fn propagate(
+        &mut self,
+        replica_tx: &broadcast::Sender<RespValue>,
+        db: usize,
+        request: RespValue,
+        flags: &[CommandFlag],
+    ) -> anyhow::Result<()> {
+        if !flags.contains(&CommandFlag::Write) {
+            return Ok(());
+        }
+
+        if self.replication_db != Some(db) {
+            feed_replicas(
+                replica_tx,
+                RespValue::array_from_slice(&["SELECT", &db.to_string()]),
+            )?;
+            self.replication_db = Some(db);
+        }
+
+        debug!("Forwarding {:?} to the replicas.", request);
+
+        feed_replicas(replica_tx, request)
+    }
+
+    // Makes room for a write, like redis does before running one: past maxmemory, the store evicts
+    // keys by maxmemory-policy and each of them goes to the replicas as a DEL ahead of the write.
+    // Replicas leave it to their master, see eviction::evicts.
+    async fn perform_evictions(
+        &mut self,
+        set_command_actor_handle: &SetCommandActorHandle,
+        config_command_actor_handle: &ConfigCommandActorHandle,
+        replication_actor_handle: &ReplicationActorHandle,
+        replica_tx: &broadcast::Sender<RespValue>,
+    ) -> anyhow::Result<()> {
+        let (maxmemory, policy) = maxmemory(config_command_actor_handle).await?;
+        if maxmemory == 0 || policy == MaxmemoryPolicy::NoEviction {
+            return Ok(());
+        }
+
+        let role = replication_actor_handle
+            .get_value(HostId::Myself)
+            .await?
+            .and_then(|myself| myself.role);
+        let replica_ignore_maxmemory = config_command_actor_handle
+            .get_value(ConfigCommandParameter::ReplicaIgnoreMaxmemory)
+            .await?
+            .is_none_or(|ignore| ignore != "no");
+
+        if !eviction::evicts(role, replica_ignore_maxmemory) {
+            return Ok(());
+        }
+
+        let evicted = set_command_actor_handle.evict(maxmemory, policy).await?;
+
+        self.evicted(set_command_actor_handle, replica_tx, evicted)
+    }
+
+    // Whether a write that may grow the dataset has to be refused: maxmemory is set, the policy is
+    // noeviction and the dataset is already over it. Going over and getting back under are logged
+    // once each, not once per refused write.
+    async fn out_of_memory(
+        &mut self,
+        set_command_actor_handle: &SetCommandActorHandle,
+        config_command_actor_handle: &ConfigCommandActorHandle,
+    ) -> anyhow::Result<bool> {
+        let (maxmemory, policy) = maxmemory(config_command_actor_handle).await?;
+        if maxmemory == 0 || policy != MaxmemoryPolicy::NoEviction {
+            return Ok(false);
+        }
+
+        let used_memory = set_command_actor_handle.get_used_memory().await?;
+        let over = used_memory > maxmemory;
+
+        if over && !self.over_maxmemory {
+            warn!(
+                "used_memory {} is over maxmemory {}, refusing writes until it is back under.",
+                used_memory, maxmemory
+            );
+        } else if !over && self.over_maxmemory {
+            info!(
+                "used_memory {} is back under maxmemory {}, accepting writes again.",
+                used_memory, maxmemory
+            );
+        }
+        self.over_maxmemory = over;
+
+        Ok(over)
+    }
+
+    // Tells keyspace notifications and the replicas about keys the store evicted.
+    fn evicted(
+        &mut self,
+        set_command_actor_handle: &SetCommandActorHandle,
+        replica_tx: &broadcast::Sender<RespValue>,
+        evicted: Vec<(usize, String)>,
+    ) -> anyhow::Result<()> {
+        for (db, key) in evicted {
+            set_command_actor_handle.notify(db, 'e', "evicted", &key);
+
+            self.propagate(
+                replica_tx,
+                db,
+                RespValue::array_from_slice(&["DEL", &key]),
+                &[CommandFlag::Write],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Same as redis' flagTransaction: a command refused inside MULTI makes the EXEC fail.
+    fn abort_transaction(&mut self, host_id: &HostId) {
+        if let Some(transaction) = self
+            .clients
+            .get_mut(host_id)
+            .and_then(|client| client.transaction.as_mut())
+        {
+            transaction.aborted = true;
+        }
+    }
+
+    // One CLIENT LIST line, the clients actor knows the connection, this actor what it has SELECTed
+    // and queued. tot-mem is the output buffer alone, what maxmemory-clients counts.
+    fn client_list_line(&self, client: &ClientInfo) -> String {
+        let (id, addr) = match &client.host_id {
+            HostId::Host { id, ip, port } => (*id, format!("{}:{}", ip, port)),
+            HostId::Myself => (0, String::new()),
+        };
+        let state = self.clients.get(&client.host_id);
+        let subscriptions = state.map(|state| state.subscriptions).unwrap_or_default();
+        let multi = state
+            .and_then(|state| state.transaction.as_ref())
+            .map_or(-1, |transaction| transaction.queued.len() as i64);
+
+        format!(
+            "id={} addr={} name={} age={} flags={} db={} sub={} psub={} multi={} qbuf={} qbuf-free={} obl={} oll={} omem={} tot-mem={}\n",
+            id,
+            addr,
+            state.map_or("", |state| state.name.as_str()),
+            client.age.as_secs(),
+            match (
+                client.replica,
+                subscriptions.total() > 0
+            ) {
+                (true, _) => "S",
+                (false, true) => "P",
+                (false, false) => "N",
+            },
+            state.map_or(0, |state| state.db),
+            subscriptions.channels,
+            subscriptions.patterns,
+            multi,
+            client.query.qbuf,
+            client.query.qbuf_free,
+            client.output.obl,
+            client.output.oll,
+            client.output.omem,
+            client.output.omem,
+        )
+    }
+
+    // (P)SUBSCRIBE, one confirmation per channel or pattern in the order they were given, each
+    // with the client's channels and patterns together as counted after it.
+    async fn subscribe(
+        &mut self,
+        kind: SubscriptionKind,
+        host_id: HostId,
+        names: Vec<String>,
+        push_tx: Option<OutputQueue>,
+        pubsub_actor_handle: &PubSubActorHandle,
+    ) -> Result<Vec<RespValue>, RedisError> {
+        // only client connections have somewhere to push messages to
+        let Some(queue) = push_tx else {
+            return Ok(vec![RedisError::SubscribeNotAllowed.into()]);
+        };
+
+        let counts = pubsub_actor_handle
+            .subscribe(host_id.clone(), kind, names.clone(), queue)
+            .await?;
+
+        if let Some(last) = counts.last() {
+            self.clients.entry(host_id).or_default().subscriptions = *last;
+        }
+
+        Ok(names
+            .into_iter()
+            .zip(counts)
+            .map(|(name, counts)| {
+                RespValue::Push(vec![
+                    RespValue::BulkString(Some(subscription_reply(kind, "subscribe").into())),
+                    RespValue::BulkString(Some(name.into())),
+                    RespValue::Integer(counts.total() as i64),
+                ])
+            })
+            .collect())
+    }
+
+    // (P)UNSUBSCRIBE, confirmed the same way.
+    async fn unsubscribe(
+        &mut self,
+        kind: SubscriptionKind,
+        host_id: HostId,
+        names: Vec<String>,
+        pubsub_actor_handle: &PubSubActorHandle,
+    ) -> Result<Vec<RespValue>, RedisError> {
+        let remaining = pubsub_actor_handle
+            .unsubscribe(host_id.clone(), kind, names)
+            .await?;
+        let reply = subscription_reply(kind, "unsubscribe");
+
+        let state = self.clients.entry(host_id).or_default();
+        if let Some((_, last)) = remaining.last() {
+            state.subscriptions = *last;
+        }
+
+        if remaining.is_empty() {
+            // not subscribed to any, redis still confirms with a nil channel and what is left
+            return Ok(vec![RespValue::Push(vec![
+                RespValue::BulkString(Some(reply.into())),
+                RespValue::Null,
+                RespValue::Integer(state.subscriptions.total() as i64),
+            ])]);
+        }
+
+        Ok(remaining
+            .into_iter()
+            .map(|(name, counts)| {
+                RespValue::Push(vec![
+                    RespValue::BulkString(Some(reply.clone().into())),
+                    RespValue::BulkString(Some(name.into())),
+                    RespValue::Integer(counts.total() as i64),
+                ])
+            })
+            .collect())
+    }
+
+    // Whether CLIENT LIST TYPE client_type lists the client. Nothing is listed as master, the link
+    // to our own master is not in the clients registry.
+    fn client_is_type(&self, client: &ClientInfo, client_type: ClientType) -> bool {
+        let pubsub = self
+            .clients
+            .get(&client.host_id)
+            .is_some_and(|state| state.subscriptions.total() > 0);
+
+        match client_type {
+            ClientType::Normal => !client.replica && !pubsub,
+            ClientType::Replica => client.replica,
+            ClientType::Pubsub => pubsub,
+            ClientType::Master => false,
+        }
     }
 
     // Run the actor
     pub async fn run(&mut self) -> anyhow::Result<()> {
-        // Continuously receive messages and handle them
-        while let Some(msg) = self.receiver.recv().await {
-            self.handle_message(msg).await.context("Unknown command")?;
+        // Continuously receive messages and handle them. A request that fails is dropped without a
+        // reply, its caller sees the error, and the actor goes on with the next one.
+        loop {
+            let msg = match self.pending_sync.as_ref().map(|sync| sync.due) {
+                Some(due) => tokio::select! {
+                    msg = self.receiver.recv() => msg,
+                    _ = sleep_until(due) => {
+                        if let Err(e) = self.send_snapshot().await {
+                            error!("Failed to send the diskless sync snapshot: {:#}", e);
+                        }
+
+                        continue;
+                    }
+                },
+                None => self.receiver.recv().await,
+            };
+
+            let Some(msg) = msg else {
+                return Ok(());
+            };
+
+            let requester = match &msg {
+                ProcessorActorMessage::Process {
+                    host_id, push_tx, ..
+                }
+                | ProcessorActorMessage::Authenticated {
+                    host_id, push_tx, ..
+                } => Some((host_id.clone(), push_tx.clone())),
+                ProcessorActorMessage::Disconnect { .. } => None,
+            };
+            if let Some((host_id, _)) = &requester {
+                self.leave_fast_path(host_id);
+            }
+
+            // A request that panics is dropped too, and its connection is told to close.
+            let Some(handled) = supervisor::isolate(Self::NAME, self.handle_message(msg)).await
+            else {
+                // it may have panicked between a SELECT sent down the replication stream and the write after it
+                self.replication_db = None;
+
+                if let Some((host_id, _)) = requester {
+                    let _ = self.panicked_tx.send(host_id);
+                }
+                continue;
+            };
+
+            if let Some((host_id, push_tx)) = &requester {
+                self.enter_fast_path(host_id, push_tx.as_ref());
+            }
+
+            if let Err(e) = handled {
+                error!("Failed to process a request: {:#}", e);
+            }
+        }
+    }
+
+    // The diskless sync whose delay has run out.
+    async fn send_snapshot(&mut self) -> anyhow::Result<()> {
+        match self.pending_sync.take() {
+            Some(sync) => self.sync_replicas(sync, None).await,
+            None => Ok(()),
+        }
+    }
+
+    // The RDB for every replica in sync, encoded once from the store and sent on the replication
+    // stream. It goes out between two writes, so a waiting replica has all writes before it in the
+    // snapshot and gets every write after it as a command.
+    //
+    // A disk-based sync, the one config is passed for, saves it as dir/dbfilename first and sends
+    // the file, like redis without repl-diskless-sync. With no dir to save to it goes from memory.
+    async fn sync_replicas(
+        &mut self,
+        sync: PendingSync,
+        config: Option<&ConfigCommandActorHandle>,
+    ) -> anyhow::Result<()> {
+        let on_disk = match config {
+            Some(config) => rdb_file_configured(config).await?.then_some(config),
+            None => None,
+        };
+
+        let rdb = match on_disk {
+            Some(config) => {
+                config
+                    .save_rdb(sync.set_command_actor_handle.clone())
+                    .await
+                    .context("Unable to save the RDB for a full resync")?;
+
+                Bytes::from(
+                    config
+                        .get_rdb()
+                        .await
+                        .context("Unable to load RDB file into memory")?,
+                )
+            }
+            _ => encode_snapshot(sync.set_command_actor_handle.get_snapshot().await?)?.freeze(),
+        };
+
+        // Like redis, the +FULLRESYNC waits for the snapshot: the offset it gives is the one the
+        // snapshot was taken at, and the replicas go on counting from there.
+        let (replid, offset) = master_replid_and_offset(&sync.replication_actor_handle).await?;
+
+        debug!(
+            "Sending a {} byte snapshot at offset {} to the waiting replicas.",
+            rdb.len(),
+            offset
+        );
+
+        feed_replicas(
+            &sync.replica_tx,
+            RespValue::SimpleString(format!("FULLRESYNC {} {}", replid, offset)),
+        )?;
+        feed_replicas(&sync.replica_tx, RespValue::Rdb(rdb))?;
+
+        for replica in sync.replicas {
+            set_replica_offset(&sync.replication_actor_handle, replica, offset).await?;
+        }
+
+        // the replicas start out in database 0, if the stream is elsewhere the next write needs a
+        // SELECT in front of it.
+        if self.replication_db != Some(0) {
+            self.replication_db = None;
         }
 
         Ok(())
@@ -54,14 +1141,21 @@ impl ProcessorActor {
                 set_command_actor_handle,
                 config_command_actor_handle,
                 replication_actor_handle,
+                failpoint_actor_handle,
+                pubsub_actor_handle,
+                clients_actor_handle,
                 host_id,
-                expire_tx,
                 master_tx,
                 replica_tx,
                 client_or_replica_tx,
                 respond_to,
                 wait_sleep_tx,
+                blocked_tx,
+                push_tx,
             } => {
+                // the database this connection has SELECTed
+                let db = self.clients.entry(host_id.clone()).or_default().db;
+
                 // Process the message from RESP Decoder
                 match request {
                     RespValue::Null => todo!(),
@@ -79,14 +1173,25 @@ impl ProcessorActor {
                         );
 
                         match parse_command(&request_as_encoded_string) {
-                            Ok((_remaining_bytes, RedisCommand::Fullresync(repl_id, offset))) => {
+                            Ok(RedisCommand::Fullresync(repl_id, offset)) => {
                                 // we got RDB mem dump, time to load it
                                 tracing::debug!(
                                     "Received FULLRESYNC repl_id: {} offset: {} from master.",
                                     repl_id,
                                     offset
                                 );
-                                let _ = master_tx.send(repl_id).await?;
+                                // the snapshot that follows is the master's dataset as of offset,
+                                // everything after it on the stream counts on from there
+                                replication_actor_handle
+                                    .reset_replica_offset(HostId::Myself)
+                                    .await?;
+                                let mut replication_data = ReplicationSectionData::new();
+                                replication_data.master_repl_offset = Some(offset);
+                                replication_actor_handle
+                                    .update_value(HostId::Myself, replication_data)
+                                    .await?;
+
+                                master_tx.send(repl_id).await?;
                                 let _ = respond_to.send(None);
 
                                 Ok(())
@@ -96,7 +1201,7 @@ impl ProcessorActor {
                                     "Unknown string {}, forwarding to replica.",
                                     request_as_encoded_string
                                 );
-                                let _ = master_tx.send(request_as_encoded_string).await?;
+                                master_tx.send(request_as_encoded_string).await?;
                                 let _ = respond_to.send(None);
 
                                 Ok(())
@@ -108,15 +1213,25 @@ impl ProcessorActor {
                         Ok(()) // NOTE: we are returning Ok here instead of Err because a RespValue::Error is not a program error.
                     }
                     RespValue::Integer(_) => todo!(),
+                    // Same as redis, an empty request (*0 or a blank inline line) gets no reply.
+                    RespValue::Array(ref elements) if elements.is_empty() => {
+                        let _ = respond_to.send(None);
+                        Ok(())
+                    }
                     RespValue::Array(_) => {
                         // it's a bit clunky here but we need the original request, not what's inside RespValue::Array().
                         // Reason is, nom parser operates on str not Vec<Value>, so sending request as an encoded string,
                         // we can avoid recreating the original RESP array and just encode the request.
                         //
                         // NOTE: array of arrays is not supported at this time.
-                        let request_as_encoded_string = request
-                            .to_encoded_string()
-                            .context("Failed to encode request as a string.")?;
+                        let request_as_encoded_string = match parseable(&request) {
+                            Ok(encoded) => encoded,
+                            Err(e) => {
+                                let _ = respond_to.send(Some(vec![e.into()]));
+
+                                return Ok(());
+                            }
+                        };
 
                         debug!("RESP request: {:?}", request_as_encoded_string);
 
@@ -125,54 +1240,294 @@ impl ProcessorActor {
                         //
                         // If it's something simple like PING, we handle it immediately and return.
                         // If not, we get an actor handle and send it to the actor to process.
-                        match parse_command(&request_as_encoded_string) {
-                            Ok((_remaining_bytes, RedisCommand::Ping)) => {
-                                // Send the RESP Value back to the handler, ignore send errors
-                                let _ = respond_to.send(Some(vec![
-                                    (RespValue::SimpleString("PONG".to_string())),
-                                ]));
+                        let parsed = parse_command(&request_as_encoded_string);
+                        let name = command_name(&request_as_encoded_string);
 
-                                Ok(())
-                            }
-                            Err(e) => {
-                                // let err_response =
-                                let _ =
-                                    respond_to.send(Some(vec![(RespValue::Error(e.to_string()))]));
+                        // Whether this goes to the replicas is down to the command table, not the arm below.
+                        // A request that didn't parse has nothing to act on.
+                        let flags = match parsed {
+                            Ok(_) => command_flags(&request_as_encoded_string),
+                            Err(_) => &[],
+                        };
+                        let writes = flags.contains(&CommandFlag::Write);
+
+                        // The master's stream comes in as Myself, everyone else AUTHs first if
+                        // the authenticator wants them to.
+                        if parsed.is_ok()
+                            && !flags.contains(&CommandFlag::NoAuth)
+                            && host_id != HostId::Myself
+                            && !self.authenticated(&host_id)
+                        {
+                            self.reject(name);
+
+                            let _ = respond_to.send(Some(vec![RedisError::NoAuth.into()]));
+
+                            return Ok(());
+                        }
+
+                        // Like redis, a RESP2 subscriber only gets to change its subscriptions and PING.
+                        if let (Ok(_), Some(name)) = (&parsed, name) {
+                            if !SUBSCRIBE_CONTEXT_COMMANDS.contains(&name)
+                                && self.in_subscribe_context(&host_id, push_tx.as_ref())
+                            {
+                                self.reject(Some(name));
+
+                                let _ = respond_to.send(Some(vec![RedisError::SubscribeContext(
+                                    name.to_lowercase(),
+                                )
+                                .into()]));
+
+                                return Ok(());
+                            }
+                        }
+
+                        if writes && self.shutting_down && host_id != HostId::Myself {
+                            self.abort_transaction(&host_id);
+                            self.reject(name);
+
+                            let _ = respond_to.send(Some(vec![RedisError::ShuttingDown.into()]));
+
+                            return Ok(());
+                        }
+
+                        // A replica takes writes from its master only, which come in as Myself.
+                        if writes && host_id != HostId::Myself {
+                            let role = replication_actor_handle
+                                .get_value(HostId::Myself)
+                                .await?
+                                .and_then(|myself| myself.role);
+
+                            if role == Some(ServerRole::Slave) {
+                                self.abort_transaction(&host_id);
+                                self.reject(name);
 
-                                Ok(()) // NOTE: a parsing errror is not a Rust error, so we are returning Ok here.
-                            }
-                            Ok((_, RedisCommand::Echo(message))) => {
-                                // Encode the value to RESP binary buffer.
                                 let _ =
-                                    respond_to.send(Some(vec![(RespValue::SimpleString(message))]));
+                                    respond_to.send(Some(vec![RedisError::ReadOnlyReplica.into()]));
+
+                                return Ok(());
+                            }
+                        }
+
+                        // Like redis, a write that may grow the dataset is refused once it is over
+                        // maxmemory with nothing to evict. Reads and DEL still go through, and
+                        // so does the master's stream, a replica holds whatever its master has.
+                        if flags.contains(&CommandFlag::DenyOom)
+                            && host_id != HostId::Myself
+                            && self
+                                .out_of_memory(
+                                    &set_command_actor_handle,
+                                    &config_command_actor_handle,
+                                )
+                                .await?
+                        {
+                            self.abort_transaction(&host_id);
+                            self.reject(name);
+
+                            let _ = respond_to.send(Some(vec![RedisError::OutOfMemory.into()]));
+
+                            return Ok(());
+                        }
+
+                        // Inside MULTI everything but the transaction commands themselves is queued for EXEC.
+                        // Errors are still reported right away, and they doom the transaction.
+                        let queueing = !matches!(
+                            parsed,
+                            Ok(RedisCommand::Multi | RedisCommand::Exec | RedisCommand::Discard)
+                        );
+
+                        if let Some(transaction) = self
+                            .clients
+                            .get_mut(&host_id)
+                            .and_then(|client| client.transaction.as_mut())
+                            .filter(|_| queueing)
+                        {
+                            let reply = match parsed {
+                                Err(e) => {
+                                    transaction.aborted = true;
+                                    e.into()
+                                }
+                                Ok(_) if flags.contains(&CommandFlag::NoMulti) => {
+                                    transaction.aborted = true;
+                                    RedisError::NotAllowedInTransaction.into()
+                                }
+                                Ok(_) => {
+                                    transaction.queued.push(request);
+                                    RespValue::SimpleString("QUEUED".to_string())
+                                }
+                            };
+
+                            // counted once EXEC runs them, unless they doomed the transaction
+                            if matches!(reply, RespValue::Error(_)) {
+                                self.reject(name);
+                            }
+
+                            let _ = respond_to.send(Some(vec![reply]));
+
+                            return Ok(());
+                        }
+
+                        if writes {
+                            self.perform_evictions(
+                                &set_command_actor_handle,
+                                &config_command_actor_handle,
+                                &replication_actor_handle,
+                                &replica_tx,
+                            )
+                            .await?;
+                        }
+
+                        // Like redis' server.dirty: the store counts what the command changes, and
+                        // a write that leaves the count where it was has nothing for the replicas.
+                        let dirty = set_command_actor_handle.dirty();
+
+                        // Every command that gets this far is timed and counted once, by its reply.
+                        // A wrong number of arguments never ran, other parse errors are it failing.
+                        let rejected = matches!(parsed, Err(RedisError::WrongArity(_)));
+                        let respond_to = self.command_stats.call(name, rejected, respond_to);
+
+                        // MONITOR and the audit log hear about it once its reply goes out
+                        let respond_to =
+                            if name.is_some() && !rejected && self.command_feed.is_read() {
+                                let client = self.clients.entry(host_id.clone()).or_default();
+                                let command = CommandEvent::new(
+                                    db,
+                                    host_id.clone(),
+                                    client.name.clone(),
+                                    client.user.clone().unwrap_or_else(|| "default".to_string()),
+                                    &request,
+                                    command_flags(&request_as_encoded_string),
+                                );
+                                let command_feed = self.command_feed.clone();
+
+                                respond_to.on_reply(move |reply| {
+                                    command_feed.send(command.outcome(reply));
+                                })
+                            } else {
+                                respond_to
+                            };
+
+                        let request = match &parsed {
+                            Ok(command) => with_absolute_ttl(request, command),
+                            Err(_) => request,
+                        };
+
+                        let outcome = match parsed {
+                            Ok(RedisCommand::Ping(message)) => {
+                                // https://redis.io/commands/ping/
+                                // A RESP2 subscriber can only be sent pushes, so like redis it gets
+                                // a pong message.
+                                let reply = if self.in_subscribe_context(&host_id, push_tx.as_ref())
+                                {
+                                    RespValue::Push(vec![
+                                        RespValue::BulkString(Some("pong".into())),
+                                        RespValue::BulkString(Some(
+                                            message.unwrap_or_default().into(),
+                                        )),
+                                    ])
+                                } else {
+                                    match message {
+                                        Some(message) => {
+                                            RespValue::BulkString(Some(message.into()))
+                                        }
+                                        None => RespValue::SimpleString("PONG".to_string()),
+                                    }
+                                };
+
+                                // Send the RESP Value back to the handler, ignore send errors
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Err(e) => {
+                                // let err_response =
+                                let _ = respond_to.send(Some(vec![e.into()]));
+
+                                Ok(()) // NOTE: a parsing errror is not a Rust error, so we are returning Ok here.
+                            }
+                            Ok(RedisCommand::Echo(message)) => {
+                                // Bulk string, the exact bytes received, \r\n and all.
+                                let message = binary_argument(&request).unwrap_or(message.into());
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::BulkString(Some(message))]));
 
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Command)) => {
+                            Ok(RedisCommand::Command) => {
                                 // Encode the value to RESP binary buffer.
                                 let _ = respond_to
                                     .send(Some(vec![(RespValue::SimpleString("OK".to_string()))]));
 
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Set(set_parameters))) => {
+                            Ok(RedisCommand::Set(mut set_parameters)) => {
                                 debug!("Set command parameters: {:?}", set_parameters);
 
+                                // the bytes the client sent, UTF-8 or not
+                                if let Some(value) = binary_argument(&request) {
+                                    set_parameters.value = value.to_vec();
+                                }
+
                                 // Sets the value for the key in the set parameters in the set command actor handle.
-                                // Awaits the result.
-                                set_command_actor_handle
-                                    .set_value(expire_tx.clone(), set_parameters.clone())
-                                    .await;
+                                // NX, XX and GET are decided by the store in the same step as the write.
+                                let outcome = if set_parameters.value.len()
+                                    > proto_max_bulk_len(&config_command_actor_handle).await?
+                                {
+                                    Err(RedisError::StringTooLong)
+                                } else {
+                                    set_command_actor_handle
+                                        .set_value(db, set_parameters.clone())
+                                        .await
+                                };
+
+                                let reply = match outcome {
+                                    // GET found a collection, nothing was written
+                                    Err(
+                                        e @ (RedisError::StringTooLong | RedisError::WrongType),
+                                    ) => e.into(),
+                                    Err(e) => return Err(e.into()),
+                                    Ok(outcome) => {
+                                        if outcome.written {
+                                            set_command_actor_handle.notify(
+                                                db,
+                                                '$',
+                                                "set",
+                                                &set_parameters.key,
+                                            );
+                                            if !matches!(
+                                                set_parameters.expire,
+                                                None | Some(SetCommandExpireOption::KEEPTTL)
+                                            ) {
+                                                set_command_actor_handle.notify(
+                                                    db,
+                                                    'g',
+                                                    "expire",
+                                                    &set_parameters.key,
+                                                );
+                                            }
+                                        }
+
+                                        // with GET the reply is what the key held before, written or not
+                                        match set_parameters.get {
+                                            Some(true) => {
+                                                outcome.previous.map_or(RespValue::Null, |value| {
+                                                    RespValue::BulkString(Some(value.into()))
+                                                })
+                                            }
+                                            _ if outcome.written => {
+                                                RespValue::SimpleString("OK".to_string())
+                                            }
+                                            _ => RespValue::Null,
+                                        }
+                                    }
+                                };
 
                                 // Encode the value to RESP binary buffer.
-                                let _ = respond_to
-                                    .send(Some(vec![(RespValue::SimpleString("OK".to_string()))]));
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                // forward this to the replicas
                                 debug!("Current subscriber count: {}", replica_tx.receiver_count());
 
                                 // calculate how many bytes are in the value_as_string
-                                // let request_num_bytes = request_as_encoded_string.len() as i16;
+                                // let request_num_bytes = request_as_encoded_string.len() as i64;
 
                                 // // we need to update master's offset because we are sending writeable commands to replicas
                                 // let mut updated_replication_data_master =
@@ -186,85 +1541,159 @@ impl ProcessorActor {
                                 //     .update_value(HostId::Myself, updated_replication_data_master)
                                 //     .await;
 
-                                let _active_client_count = replica_tx.send(request)?;
-
-                                tracing::debug!(
-                                    "Forwarding {:?} command to replicas.",
-                                    request_as_encoded_string
-                                );
-
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Get(key))) => {
+                            Ok(RedisCommand::Get(key)) => {
                                 // we may or may not get a value for the supplied key.
                                 // if we do, we return it. If not, we encode Null and send that back.
-                                if let Some(value) = set_command_actor_handle.get_value(&key).await
+                                let reply = match set_command_actor_handle.get_value(db, &key).await
                                 {
-                                    let _ = respond_to
-                                        .send(Some(vec![(RespValue::SimpleString(value))]));
-                                } else {
-                                    let _ = respond_to.send(Some(vec![(RespValue::Null)]));
-                                }
+                                    Ok(value) => value.map_or(RespValue::Null, |value| {
+                                        RespValue::BulkString(Some(value.into()))
+                                    }),
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Del(keys))) => {
-                                // iterate over all the keys, deleting them one by one
+                            Ok(RedisCommand::Del(keys)) => {
+                                // all the keys in one message to the store
                                 // https://redis.io/commands/del/
+                                let removed =
+                                    set_command_actor_handle.delete_values(db, &keys).await?;
 
-                                for key in &keys {
-                                    set_command_actor_handle.delete_value(key).await;
+                                // a key named twice is only removed the first time
+                                let mut count = 0;
+                                for (key, removed) in keys.iter().zip(removed) {
+                                    if removed {
+                                        set_command_actor_handle.notify(db, 'g', "del", key);
+                                        count += 1;
+                                    }
                                 }
 
-                                let _ = respond_to
-                                    .send(Some(vec![(RespValue::Integer(keys.len() as i64))]));
+                                let _ = respond_to.send(Some(vec![(RespValue::Integer(count))]));
 
-                                let _active_client_count = replica_tx.send(request)?;
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Copy(copy_params)) => {
+                                // https://redis.io/commands/copy/
+                                let destination_db = copy_params.db.unwrap_or(db as i64);
 
-                                tracing::debug!(
-                                    "Forwarding {:?} command to the replicas.",
-                                    request_as_encoded_string
-                                );
+                                let reply = if !(0..set_command_actor_handle.databases() as i64)
+                                    .contains(&destination_db)
+                                {
+                                    RedisError::DbIndexOutOfRange.into()
+                                } else if destination_db == db as i64
+                                    && copy_params.source == copy_params.destination
+                                {
+                                    RedisError::SameObject.into()
+                                } else {
+                                    let copied = set_command_actor_handle
+                                        .copy_value(
+                                            db,
+                                            &copy_params.source,
+                                            destination_db as usize,
+                                            &copy_params.destination,
+                                            copy_params.replace,
+                                        )
+                                        .await?;
+
+                                    // in the database the copy landed in, not the selected one
+                                    if copied {
+                                        set_command_actor_handle.notify(
+                                            destination_db as usize,
+                                            'g',
+                                            "copy_to",
+                                            &copy_params.destination,
+                                        );
+                                    }
+
+                                    RespValue::Integer(copied as i64)
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Mget(keys))) => {
+                            Ok(RedisCommand::Mget(keys)) => {
                                 // Returns the values of all specified keys.
                                 // For every key that does not hold a string value or does not exist,
                                 // the special value nil is returned.
                                 // Because of this, the operation never fails.
                                 // https://redis.io/commands/mget/
 
-                                let mut key_collection: Vec<RespValue> = Vec::new();
-
-                                for key in &keys {
-                                    if let Some(value) =
-                                        set_command_actor_handle.get_value(&key).await
-                                    {
-                                        let response = RespValue::SimpleString(value);
-                                        key_collection.push(response);
-                                    } else {
-                                        let response = RespValue::Null; // key does not exist, return nil
-                                        key_collection.push(response);
-                                    }
-                                }
+                                // one round trip to the store however many keys there are
+                                let key_collection: Vec<RespValue> = set_command_actor_handle
+                                    .get_values(db, &keys)
+                                    .await?
+                                    .into_iter()
+                                    // key does not exist, return nil
+                                    .map(|value| {
+                                        value.map_or(RespValue::Null, |value| {
+                                            RespValue::BulkString(Some(value.into()))
+                                        })
+                                    })
+                                    .collect();
                                 let _ =
                                     respond_to.send(Some(vec![(RespValue::Array(key_collection))]));
 
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Strlen(key))) => {
+                            Ok(RedisCommand::Exists(keys)) => {
+                                // https://redis.io/commands/exists/
+                                // keys past their deadline don't count, removed yet or not
+                                let existing =
+                                    set_command_actor_handle.count_existing(db, &keys)?;
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::Integer(existing as i64)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Strlen(key)) => {
                                 // we may or may not get a value for the supplied key.
                                 // if we do, we return the length. If not, we encode 0 and send that back.
                                 // https://redis.io/commands/strlen/
-                                if let Some(value) = set_command_actor_handle.get_value(&key).await
+                                let reply = match set_command_actor_handle.get_value(db, &key).await
                                 {
-                                    let _ = respond_to
-                                        .send(Some(vec![(RespValue::Integer(value.len() as i64))]));
-                                } else {
-                                    let _ =
-                                        respond_to.send(Some(vec![(RespValue::Integer(0 as i64))]));
-                                }
+                                    Ok(value) => RespValue::Integer(
+                                        value.map_or(0, |value| value.len()) as i64,
+                                    ),
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+
+                            Ok(RedisCommand::Memory(MemoryCommandParameter::Usage(key))) => {
+                                // the bytes used_memory counts for the key, nil if there is no such key
+                                // https://redis.io/commands/memory-usage/
+                                let usage = set_command_actor_handle
+                                    .memory_usage(db, &key)?
+                                    .map_or(RespValue::Null, |usage| {
+                                        RespValue::Integer(usage as i64)
+                                    });
+
+                                let _ = respond_to.send(Some(vec![usage]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Memory(MemoryCommandParameter::Doctor)) => {
+                                let report = memory_doctor(
+                                    set_command_actor_handle.get_used_memory().await?,
+                                    maxmemory(&config_command_actor_handle).await?,
+                                    &set_command_actor_handle.get_keyspace_stats().await?,
+                                    set_command_actor_handle.get_expiry_stats().await?,
+                                );
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::BulkString(Some(report.into()))]));
 
                                 Ok(())
                             }
@@ -272,199 +1701,1482 @@ impl ProcessorActor {
                             // If key already exists and is a string, this command appends the value at the end of the string.
                             // If key does not exist it is created and set as an empty string,
                             // so APPEND will be similar to SET in this special case.
-                            Ok((_, RedisCommand::Append(key, value_to_append))) => {
+                            Ok(RedisCommand::Append(key, value_to_append)) => {
                                 // we may or may not already have a value for the supplied key.
                                 // if we do, we append. If not, we create via a SET
                                 // https://redis.io/commands/append/
 
-                                // Initialize an empty string for the future.
-                                let new_value: String;
-                                if let Some(original_value) =
-                                    set_command_actor_handle.get_value(&key).await
+                                // The store appends in place, so a value built up by many
+                                // APPENDs isn't copied in full for every one of them.
+                                let max_len =
+                                    proto_max_bulk_len(&config_command_actor_handle).await?;
+
+                                // the bytes the client sent, UTF-8 or not
+                                let value_to_append = binary_argument(&request)
+                                    .map_or(value_to_append.into_bytes(), |value| value.to_vec());
+
+                                let reply = match set_command_actor_handle
+                                    .append_value(db, &key, value_to_append, max_len)
+                                    .await
                                 {
-                                    new_value = original_value + &value_to_append;
-                                } else {
-                                    new_value = value_to_append;
-                                }
+                                    Ok(length) => {
+                                        set_command_actor_handle.notify(db, '$', "append", &key);
 
-                                // populate the set parameters struct.
-                                // All the extraneous options are None since this is a pure APPEND op.
-                                let set_parameters = SetCommandParameter {
-                                    key,
-                                    value: new_value.clone(),
-                                    expire: None,
-                                    get: None,
-                                    option: None,
+                                        RespValue::Integer(length as i64)
+                                    }
+                                    Err(
+                                        e @ (RedisError::StringTooLong | RedisError::WrongType),
+                                    ) => e.into(),
+                                    Err(e) => return Err(e.into()),
                                 };
 
-                                set_command_actor_handle
-                                    .set_value(expire_tx.clone(), set_parameters)
-                                    .await;
-
-                                let _ = respond_to
-                                    .send(Some(vec![(RespValue::Integer(new_value.len() as i64))]));
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
                             }
-                            Ok((_, RedisCommand::Config(config_key))) => {
-                                // we may or may not get a value for the supplied key.
-                                // if we do, we return it. If not, we encode Null and send that back.
-                                if let Some(value) =
-                                    config_command_actor_handle.get_value(config_key).await
-                                {
-                                    // let response = RespValue::String(value).encode();
-                                    let mut response: Vec<RespValue> = Vec::new();
+                            Ok(RedisCommand::Setrange(key, offset, value)) => {
+                                // https://redis.io/commands/setrange/
+                                let outcome = match usize::try_from(offset) {
+                                    Ok(offset) => {
+                                        let max_len =
+                                            proto_max_bulk_len(&config_command_actor_handle)
+                                                .await?;
 
-                                    // convert enum variant to String
-                                    response.push(RespValue::SimpleString(config_key.to_string()));
+                                        // the bytes the client sent, UTF-8 or not
+                                        let value = binary_argument(&request)
+                                            .map_or(value.into_bytes(), |value| value.to_vec());
 
-                                    response.push(RespValue::SimpleString(value));
+                                        set_command_actor_handle
+                                            .set_range(db, &key, offset, value, max_len)
+                                            .await
+                                    }
+                                    Err(_) => Err(RedisError::OffsetOutOfRange),
+                                };
 
-                                    let _ =
-                                        respond_to.send(Some(vec![(RespValue::Array(response))]));
-                                } else {
-                                    let _ = respond_to.send(Some(vec![(RespValue::Null)]));
-                                }
+                                let reply = match outcome {
+                                    Ok(length) => {
+                                        set_command_actor_handle.notify(db, '$', "setrange", &key);
+
+                                        RespValue::Integer(length as i64)
+                                    }
+                                    Err(
+                                        e @ (RedisError::OffsetOutOfRange
+                                        | RedisError::StringTooLong
+                                        | RedisError::WrongType),
+                                    ) => e.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
                             }
+                            Ok(RedisCommand::Incrby(key, by)) => {
+                                // https://redis.io/commands/incrby/
+                                let reply =
+                                    match set_command_actor_handle.incr_value(db, &key, by).await {
+                                        Ok(counted) => {
+                                            set_command_actor_handle
+                                                .notify(db, '$', "incrby", &key);
 
-                            Ok((_, RedisCommand::Keys(pattern))) => {
-                                // Returns the values of all specified keys matching the pattern.
-                                //
-                                // https://redis.io/commands/keys/
+                                            RespValue::Integer(counted)
+                                        }
+                                        Err(
+                                            e @ (RedisError::NotAnInteger
+                                            | RedisError::Overflow
+                                            | RedisError::WrongType),
+                                        ) => e.into(),
+                                        Err(e) => return Err(e.into()),
+                                    };
 
-                                let mut keys_collection = Vec::new();
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                // see if there were any keys in the hashmap that match the pattern.
-                                if let Some(keys) =
-                                    set_command_actor_handle.get_keys(&pattern).await
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Getrange(key, start, end)) => {
+                                // https://redis.io/commands/getrange/
+                                // bytes, so a range may end in the middle of a character
+                                let reply = match set_command_actor_handle.get_value(db, &key).await
                                 {
-                                    for key in keys {
-                                        let response =
-                                            RespValue::BulkString(Some(key.into_bytes()));
-                                        keys_collection.push(response);
-                                    }
-                                    // we need to convert Vec<String> to &[&str]
-                                    // let slices = keys.as_slice();
-
-                                    // for slice_str in slices {
-                                    //     let response = slice_str.as_str();
-                                    //     keys_collection.push(response);
-                                    // }
-                                } else {
-                                    let response = RespValue::Null;
+                                    Ok(value) => {
+                                        let value = value.unwrap_or_default();
+                                        let range = index_range(value.len(), start, end)
+                                            .map_or(&[][..], |range| &value[range]);
 
-                                    keys_collection.push(response);
-                                }
+                                        RespValue::BulkString(Some(Bytes::copy_from_slice(range)))
+                                    }
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
 
-                                // debug!("Returning keys: {:?}", keys_collection);
-                                let _ = respond_to
-                                    .send(Some(vec![(RespValue::Array(keys_collection))]));
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
                             }
+                            Ok(RedisCommand::Hset(key, pairs)) => {
+                                // https://redis.io/commands/hset/
+                                let reply =
+                                    match set_command_actor_handle.hset(db, &key, pairs).await {
+                                        Ok(added) => {
+                                            set_command_actor_handle.notify(db, 'h', "hset", &key);
 
-                            Ok((_, RedisCommand::Info(info_parameter))) => {
-                                // we may or may not get a value for the INFO command.
+                                            RespValue::Integer(added as i64)
+                                        }
+                                        Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                        Err(e) => return Err(e.into()),
+                                    };
 
-                                // first, let's see if this INFO section exists.
-                                // For now, we are assuming it's a INFO REPLICATION query cmd but there may be others.
-                                if let Some(_param) = info_parameter {
-                                    // TODO: match on param
-                                    let replication_data =
-                                        replication_actor_handle.get_value(HostId::Myself).await;
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                    tracing::debug!(
-                                        "Retrieved INFO RespValue: {:?}",
-                                        replication_data
-                                    );
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Hdel(key, fields)) => {
+                                // https://redis.io/commands/hdel/
+                                let reply = match set_command_actor_handle
+                                    .hdel(db, &key, fields)
+                                    .await
+                                {
+                                    Ok(removed) => {
+                                        if removed > 0 {
+                                            set_command_actor_handle.notify(db, 'h', "hdel", &key);
 
-                                    // then, let's see if the section contains data.
-                                    if let Some(replication_section) = replication_data {
-                                        let _ =
-                                            respond_to.send(Some(vec![RespValue::SimpleString(
-                                                replication_section.to_string(),
-                                            )]));
-                                    } else {
-                                        let _ = respond_to.send(Some(vec![RespValue::Null]));
+                                            // the last field took the key with it
+                                            if set_command_actor_handle
+                                                .count_existing(db, std::slice::from_ref(&key))?
+                                                == 0
+                                            {
+                                                set_command_actor_handle
+                                                    .notify(db, 'g', "del", &key);
+                                            }
+                                        }
+
+                                        RespValue::Integer(removed as i64)
                                     }
-                                } else {
-                                    let _ = respond_to.send(Some(vec![RespValue::Null]));
-                                }
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
                             }
+                            Ok(RedisCommand::Hget(key, field)) => {
+                                // https://redis.io/commands/hget/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_hash(db, &key, |hash| {
+                                        hash.get(&field).map_or(RespValue::Null, |value| {
+                                            RespValue::BulkString(Some(value.clone().into()))
+                                        })
+                                    }),
+                                    RespValue::Null,
+                                )?;
 
-                            Ok((_, RedisCommand::ReplConf(replconf_params))) => {
-                                // initialize the reply of Vec<RespValue>
-                                // let mut response: Vec<RespValue> = Vec::new();
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                // a simple OK to start with.
-                                // response.push(RespValue::SimpleString("OK".to_string()));
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Hgetall(key)) => {
+                                // https://redis.io/commands/hgetall/
+                                // a map, which a RESP2 client gets as field, value, field, value
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_hash(db, &key, |hash| {
+                                        RespValue::Map(
+                                            hash.iter()
+                                                .map(|(field, value)| {
+                                                    (
+                                                        RespValue::BulkString(Some(
+                                                            field.clone().into(),
+                                                        )),
+                                                        RespValue::BulkString(Some(
+                                                            value.clone().into(),
+                                                        )),
+                                                    )
+                                                })
+                                                .collect(),
+                                        )
+                                    }),
+                                    RespValue::Map(Vec::new()),
+                                )?;
 
-                                // inform the handler_client that this is a replica
-                                if let Some(client_or_replica_tx_sender) = client_or_replica_tx {
-                                    let _ = client_or_replica_tx_sender.send(true).await?;
-                                }
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                // Check what replconf parameter we have and act accordingly
-                                // https://redis.io/commands/replconf
-                                match replconf_params {
-                                    ReplConfCommandParameter::Getack(ackvalue) => {
-                                        // typically this is FROM the master, and is received by the replica.
-                                        // So, if we are processing this, we are a replica.
-                                        // Replies to this will go back over the OUTBOUND tcp connection to the master.
-                                        // NOTE: Most replies are suppressed but these we need to send back to the master.
-                                        debug!("Replica received GETACK: {}", ackvalue);
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Hlen(key)) => {
+                                // https://redis.io/commands/hlen/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_hash(db, &key, |hash| {
+                                        RespValue::Integer(hash.len() as i64)
+                                    }),
+                                    RespValue::Integer(0),
+                                )?;
 
-                                        // check to make sure ackvalue is actually *
-                                        if ackvalue == "*" {
-                                            // We need to get the current offset value and send it back to the master.
-                                            //
-                                            // First, let's prepare the correct key message:
-                                            // let info_key = InfoCommandParameter::Replication;
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                            // get the current replication data.
-                                            if let Some(current_replication_data) =
-                                                replication_actor_handle
-                                                    .get_value(HostId::Myself)
-                                                    .await
-                                            {
-                                                debug!(
-                                                    "REPLICA: retrieving replication data {:?}",
-                                                    current_replication_data
-                                                );
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Hkeys(key)) => {
+                                // https://redis.io/commands/hkeys/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_hash(db, &key, |hash| {
+                                        RespValue::Array(
+                                            hash.keys()
+                                                .map(|field| {
+                                                    RespValue::BulkString(Some(
+                                                        field.clone().into(),
+                                                    ))
+                                                })
+                                                .collect(),
+                                        )
+                                    }),
+                                    RespValue::Array(Vec::new()),
+                                )?;
 
-                                                // extract the current offset value.
-                                                let current_offset = current_replication_data
-                                                    .master_repl_offset
-                                                    .expect("Expected to find an replication entry for the replica.");
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                                let repl_conf_ack = RespValue::array_from_slice(&[
-                                                    "REPLCONF",
-                                                    "ACK",
-                                                    &current_offset.to_string(),
-                                                ]);
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Hvals(key)) => {
+                                // https://redis.io/commands/hvals/, in the order HKEYS has the fields
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_hash(db, &key, |hash| {
+                                        RespValue::Array(
+                                            hash.values()
+                                                .map(|value| {
+                                                    RespValue::BulkString(Some(
+                                                        value.clone().into(),
+                                                    ))
+                                                })
+                                                .collect(),
+                                        )
+                                    }),
+                                    RespValue::Array(Vec::new()),
+                                )?;
 
-                                                // convert it to an encoded string purely for debugging purposes
-                                                let repl_conf_ack_encoded =
-                                                    repl_conf_ack.to_encoded_string()?;
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                                debug!(
-                                                    "REPLICA: returning {:?} from processor to main.rs loop.",
-                                                    repl_conf_ack_encoded
-                                                );
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Push(key, end, values)) => {
+                                // https://redis.io/commands/lpush/ and https://redis.io/commands/rpush/
+                                let (reply, served) = match set_command_actor_handle
+                                    .push(db, &key, end, values)
+                                    .await
+                                {
+                                    Ok((length, served)) => {
+                                        let event = match end {
+                                            ListEnd::Left => "lpush",
+                                            ListEnd::Right => "rpush",
+                                        };
+                                        set_command_actor_handle.notify(db, 'l', event, &key);
 
-                                                // response.push(repl_conf_ack);
+                                        (RespValue::Integer(length as i64), served)
+                                    }
+                                    Err(RedisError::WrongType) => {
+                                        (RedisError::WrongType.into(), Vec::new())
+                                    }
+                                    Err(e) => return Err(e.into()),
+                                };
 
-                                                // send the current offset value back to the master
-                                                // NOTE: this does NOT go over the master_tx channel, which is only for replies TO the master.
-                                                // NOTE: this is the offset BEFORE the latest
-                                                let _ = respond_to.send(Some(vec![repl_conf_ack]));
-                                            } else {
-                                                error!(
-                                                    "Unable to find replication data for {:?}",
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                // The blocked clients it served popped in the store, so the
+                                // replicas get those pops right after the push.
+                                if !served.is_empty() {
+                                    self.propagate(&replica_tx, db, request, flags)?;
+                                    for end in served {
+                                        notify_popped(&set_command_actor_handle, db, end, &key)?;
+                                        self.propagate(
+                                            &replica_tx,
+                                            db,
+                                            RespValue::array_from_slice(&[pop_name(end), &key]),
+                                            flags,
+                                        )?;
+                                    }
+
+                                    return Ok(());
+                                }
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Pop(key, end, count)) => {
+                                // https://redis.io/commands/lpop/ and https://redis.io/commands/rpop/
+                                let reply = match set_command_actor_handle
+                                    .pop(db, &key, end, count.unwrap_or(1))
+                                    .await
+                                {
+                                    Ok(popped) => {
+                                        if popped.as_ref().is_some_and(|popped| !popped.is_empty())
+                                        {
+                                            notify_popped(
+                                                &set_command_actor_handle,
+                                                db,
+                                                end,
+                                                &key,
+                                            )?;
+                                        }
+
+                                        let bulk = |value: String| {
+                                            RespValue::BulkString(Some(value.into()))
+                                        };
+                                        // a lone element without a count, an array of them with one
+                                        match (popped, count) {
+                                            (Some(popped), None) => popped
+                                                .into_iter()
+                                                .next()
+                                                .map_or(RespValue::Null, bulk),
+                                            (None, None) => RespValue::Null,
+                                            (Some(popped), Some(_)) => RespValue::Array(
+                                                popped.into_iter().map(bulk).collect(),
+                                            ),
+                                            (None, Some(_)) => RespValue::NullArray,
+                                        }
+                                    }
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::BlockingPop(keys, end, timeout)) => {
+                                // https://redis.io/commands/blpop/ and https://redis.io/commands/brpop/
+                                let timeout = match Duration::try_from_secs_f64(timeout) {
+                                    _ if timeout < 0.0 => Err(RedisError::NegativeTimeout),
+                                    Ok(timeout) => Ok(timeout),
+                                    Err(_) => Err(RedisError::TimeoutNotFloat),
+                                };
+                                let timeout = match timeout {
+                                    Ok(timeout) => timeout,
+                                    Err(e) => {
+                                        let _ = respond_to.send(Some(vec![e.into()]));
+
+                                        return Ok(());
+                                    }
+                                };
+
+                                // Inside EXEC, or on the link to the master, there is no connection
+                                // to park the request with, so it answers with what there is now.
+                                let (waiter, popped_rx) = match blocked_tx {
+                                    Some(_) => {
+                                        let (waiter, popped_rx) = oneshot::channel();
+                                        (Some(waiter), Some(popped_rx))
+                                    }
+                                    None => (None, None),
+                                };
+
+                                let popped = match set_command_actor_handle
+                                    .blocking_pop(db, keys, end, waiter)
+                                    .await
+                                {
+                                    Err(RedisError::WrongType) => {
+                                        let _ = respond_to
+                                            .send(Some(vec![RedisError::WrongType.into()]));
+
+                                        return Ok(());
+                                    }
+                                    popped => popped?,
+                                };
+
+                                match (popped, blocked_tx.zip(popped_rx)) {
+                                    (Some((key, value)), _) => {
+                                        notify_popped(&set_command_actor_handle, db, end, &key)?;
+
+                                        // the replicas get the pop it came down to, which never blocks
+                                        self.propagate(
+                                            &replica_tx,
+                                            db,
+                                            RespValue::array_from_slice(&[pop_name(end), &key]),
+                                            flags,
+                                        )?;
+
+                                        let _ = respond_to.send(Some(vec![
+                                            RespValue::array_from_slice(&[&key, &value]),
+                                        ]));
+                                    }
+                                    (None, Some((blocked_tx, popped))) => {
+                                        // The connection waits for the push that serves it, or
+                                        // the timeout, and replies itself. Until then it runs
+                                        // nothing else.
+                                        let deadline =
+                                            (!timeout.is_zero()).then(|| Instant::now() + timeout);
+                                        let _ =
+                                            blocked_tx.send(BlockedPop { popped, deadline }).await;
+
+                                        let _ = respond_to.send(None);
+                                    }
+                                    (None, None) => {
+                                        let _ = respond_to.send(Some(vec![RespValue::NullArray]));
+                                    }
+                                }
+
+                                // a pop went to the replicas above, if there was one
+                                return Ok(());
+                            }
+                            Ok(RedisCommand::Lrange(key, start, stop)) => {
+                                // https://redis.io/commands/lrange/, stop included
+                                pooled_read(
+                                    &self.read_pool,
+                                    &set_command_actor_handle,
+                                    &config_command_actor_handle,
+                                    db,
+                                    &[key],
+                                    wait_sleep_tx.is_none(),
+                                    respond_to,
+                                    move |collections, deadline| {
+                                        let Some(list) = collections[0].as_deref() else {
+                                            return Ok(RespValue::Array(Vec::new()));
+                                        };
+                                        let list = list.as_list().ok_or(RedisError::WrongType)?;
+
+                                        let Some(range) = index_range(list.len(), start, stop)
+                                        else {
+                                            return Ok(RespValue::Array(Vec::new()));
+                                        };
+
+                                        list.range(range)
+                                            .map(|value| {
+                                                deadline.tick()?;
+
+                                                Ok(RespValue::BulkString(Some(
+                                                    value.clone().into(),
+                                                )))
+                                            })
+                                            .collect::<Result<_, _>>()
+                                            .map(RespValue::Array)
+                                    },
+                                )
+                                .await?;
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Llen(key)) => {
+                                // https://redis.io/commands/llen/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_list(db, &key, |list| {
+                                        RespValue::Integer(list.len() as i64)
+                                    }),
+                                    RespValue::Integer(0),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Sadd(key, members)) => {
+                                // https://redis.io/commands/sadd/
+                                let reply =
+                                    match set_command_actor_handle.sadd(db, &key, members).await {
+                                        Ok(added) => {
+                                            if added > 0 {
+                                                set_command_actor_handle
+                                                    .notify(db, 's', "sadd", &key);
+                                            }
+
+                                            RespValue::Integer(added as i64)
+                                        }
+                                        Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                        Err(e) => return Err(e.into()),
+                                    };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Srem(key, members)) => {
+                                // https://redis.io/commands/srem/
+                                let reply = match set_command_actor_handle
+                                    .srem(db, &key, members)
+                                    .await
+                                {
+                                    Ok(removed) => {
+                                        if removed > 0 {
+                                            set_command_actor_handle.notify(db, 's', "srem", &key);
+
+                                            // the last member took the key with it
+                                            if set_command_actor_handle
+                                                .count_existing(db, std::slice::from_ref(&key))?
+                                                == 0
+                                            {
+                                                set_command_actor_handle
+                                                    .notify(db, 'g', "del", &key);
+                                            }
+                                        }
+
+                                        RespValue::Integer(removed as i64)
+                                    }
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Smembers(key)) => {
+                                // https://redis.io/commands/smembers/
+                                // a set, which a RESP2 client gets as an array
+                                pooled_read(
+                                    &self.read_pool,
+                                    &set_command_actor_handle,
+                                    &config_command_actor_handle,
+                                    db,
+                                    &[key],
+                                    wait_sleep_tx.is_none(),
+                                    respond_to,
+                                    |collections, deadline| {
+                                        snapshot_sets(collections)?[0]
+                                            .into_iter()
+                                            .flatten()
+                                            .map(|member| {
+                                                deadline.tick()?;
+
+                                                Ok(RespValue::BulkString(Some(
+                                                    member.clone().into(),
+                                                )))
+                                            })
+                                            .collect::<Result<_, _>>()
+                                            .map(RespValue::Set)
+                                    },
+                                )
+                                .await?;
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Sismember(key, member)) => {
+                                // https://redis.io/commands/sismember/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_set(db, &key, |set| {
+                                        RespValue::Integer(set.contains(&member) as i64)
+                                    }),
+                                    RespValue::Integer(0),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Scard(key)) => {
+                                // https://redis.io/commands/scard/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_set(db, &key, |set| {
+                                        RespValue::Integer(set.len() as i64)
+                                    }),
+                                    RespValue::Integer(0),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Sunion(keys)) => {
+                                // https://redis.io/commands/sunion/, a key that doesn't exist
+                                // counts as an empty set
+                                pooled_read(
+                                    &self.read_pool,
+                                    &set_command_actor_handle,
+                                    &config_command_actor_handle,
+                                    db,
+                                    &keys,
+                                    wait_sleep_tx.is_none(),
+                                    respond_to,
+                                    |collections, deadline| {
+                                        let mut union = HashSet::new();
+                                        for set in snapshot_sets(collections)?.into_iter().flatten()
+                                        {
+                                            for member in set {
+                                                deadline.tick()?;
+                                                union.insert(member);
+                                            }
+                                        }
+
+                                        Ok(RespValue::Set(
+                                            union
+                                                .into_iter()
+                                                .map(|member| {
+                                                    RespValue::BulkString(Some(
+                                                        member.clone().into(),
+                                                    ))
+                                                })
+                                                .collect(),
+                                        ))
+                                    },
+                                )
+                                .await?;
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Sinter(keys)) => {
+                                // https://redis.io/commands/sinter/
+                                pooled_read(
+                                    &self.read_pool,
+                                    &set_command_actor_handle,
+                                    &config_command_actor_handle,
+                                    db,
+                                    &keys,
+                                    wait_sleep_tx.is_none(),
+                                    respond_to,
+                                    |collections, deadline| {
+                                        let members = intersection(
+                                            &snapshot_sets(collections)?,
+                                            0,
+                                            deadline,
+                                        )?;
+
+                                        Ok(RespValue::Set(
+                                            members
+                                                .into_iter()
+                                                .map(|member| {
+                                                    RespValue::BulkString(Some(
+                                                        member.clone().into(),
+                                                    ))
+                                                })
+                                                .collect(),
+                                        ))
+                                    },
+                                )
+                                .await?;
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Sintercard(keys, limit)) => {
+                                // https://redis.io/commands/sintercard/, counting no further
+                                // than limit
+                                pooled_read(
+                                    &self.read_pool,
+                                    &set_command_actor_handle,
+                                    &config_command_actor_handle,
+                                    db,
+                                    &keys,
+                                    wait_sleep_tx.is_none(),
+                                    respond_to,
+                                    move |collections, deadline| {
+                                        let members = intersection(
+                                            &snapshot_sets(collections)?,
+                                            limit,
+                                            deadline,
+                                        )?;
+
+                                        Ok(RespValue::Integer(members.len() as i64))
+                                    },
+                                )
+                                .await?;
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zadd(zadd_params)) => {
+                                // https://redis.io/commands/zadd/
+                                let incompatible = if zadd_params.nx && zadd_params.xx {
+                                    Some("XX and NX")
+                                } else if [zadd_params.nx, zadd_params.gt, zadd_params.lt]
+                                    .into_iter()
+                                    .filter(|given| *given)
+                                    .count()
+                                    > 1
+                                {
+                                    Some("GT, LT, and/or NX")
+                                } else {
+                                    None
+                                };
+
+                                let (key, ch) = (zadd_params.key.clone(), zadd_params.ch);
+                                let counts = match incompatible {
+                                    Some(options) => Err(RedisError::IncompatibleOptions(options)),
+                                    None => set_command_actor_handle.zadd(db, zadd_params).await,
+                                };
+
+                                let reply = match counts {
+                                    Ok((added, updated)) => {
+                                        if added + updated > 0 {
+                                            set_command_actor_handle.notify(db, 'z', "zadd", &key);
+                                        }
+
+                                        // CH counts the members that got a new score too
+                                        let reply = if ch { added + updated } else { added };
+                                        RespValue::Integer(reply as i64)
+                                    }
+                                    Err(
+                                        e @ (RedisError::IncompatibleOptions(_)
+                                        | RedisError::WrongType),
+                                    ) => e.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zscore(key, member)) => {
+                                // https://redis.io/commands/zscore/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_sorted_set(
+                                        db,
+                                        &key,
+                                        |sorted_set| {
+                                            sorted_set
+                                                .score(&member)
+                                                .map_or(RespValue::Null, RespValue::Double)
+                                        },
+                                    ),
+                                    RespValue::Null,
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zrange(key, start, stop, withscores)) => {
+                                // https://redis.io/commands/zrange/, by index from the lowest
+                                // score, stop included and each score right after its member
+                                let protocol = push_tx
+                                    .as_ref()
+                                    .map_or(RespProtocol::Resp2, OutputQueue::protocol);
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_sorted_set(
+                                        db,
+                                        &key,
+                                        |sorted_set| {
+                                            let Some(range) =
+                                                index_range(sorted_set.len(), start, stop)
+                                            else {
+                                                return RespValue::Array(Vec::new());
+                                            };
+
+                                            scored_members(
+                                                sorted_set
+                                                    .iter()
+                                                    .skip(*range.start())
+                                                    .take(range.count()),
+                                                withscores,
+                                                protocol,
+                                            )
+                                        },
+                                    ),
+                                    RespValue::Array(Vec::new()),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zrank(key, member)) => {
+                                // https://redis.io/commands/zrank/, 0 for the lowest score
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_sorted_set(
+                                        db,
+                                        &key,
+                                        |sorted_set| {
+                                            sorted_set
+                                                .rank(&member)
+                                                .map_or(RespValue::Null, |rank| {
+                                                    RespValue::Integer(rank as i64)
+                                                })
+                                        },
+                                    ),
+                                    RespValue::Null,
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zcard(key)) => {
+                                // https://redis.io/commands/zcard/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_sorted_set(
+                                        db,
+                                        &key,
+                                        |sorted_set| RespValue::Integer(sorted_set.len() as i64),
+                                    ),
+                                    RespValue::Integer(0),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zrem(key, members)) => {
+                                // https://redis.io/commands/zrem/
+                                let reply = match set_command_actor_handle
+                                    .zrem(db, &key, members)
+                                    .await
+                                {
+                                    Ok(removed) => {
+                                        if removed > 0 {
+                                            set_command_actor_handle.notify(db, 'z', "zrem", &key);
+
+                                            // the last member took the key with it
+                                            if set_command_actor_handle
+                                                .count_existing(db, std::slice::from_ref(&key))?
+                                                == 0
+                                            {
+                                                set_command_actor_handle
+                                                    .notify(db, 'g', "del", &key);
+                                            }
+                                        }
+
+                                        RespValue::Integer(removed as i64)
+                                    }
+                                    Err(RedisError::WrongType) => RedisError::WrongType.into(),
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zincrby(key, increment, member)) => {
+                                // https://redis.io/commands/zincrby/
+                                let reply = match set_command_actor_handle
+                                    .zincrby(db, &key, &member, increment)
+                                    .await
+                                {
+                                    Ok(score) => {
+                                        set_command_actor_handle.notify(db, 'z', "zincr", &key);
+
+                                        RespValue::Double(score)
+                                    }
+                                    Err(error @ (RedisError::WrongType | RedisError::ScoreNaN)) => {
+                                        error.into()
+                                    }
+                                    Err(e) => return Err(e.into()),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zcount(key, min, max)) => {
+                                // https://redis.io/commands/zcount/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_sorted_set(
+                                        db,
+                                        &key,
+                                        |sorted_set| {
+                                            RespValue::Integer(
+                                                sorted_set.range_by_score(min, max).count() as i64,
+                                            )
+                                        },
+                                    ),
+                                    RespValue::Integer(0),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Zrangebyscore(key, min, max, withscores, limit)) => {
+                                // https://redis.io/commands/zrangebyscore/, from the lowest score
+                                // up, LIMIT skipping offset of them and taking count, all of the
+                                // rest if count is negative
+                                let (offset, count) = limit.unwrap_or((0, -1));
+                                let protocol = push_tx
+                                    .as_ref()
+                                    .map_or(RespProtocol::Resp2, OutputQueue::protocol);
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_sorted_set(
+                                        db,
+                                        &key,
+                                        |sorted_set| {
+                                            if offset < 0 {
+                                                return RespValue::Array(Vec::new());
+                                            }
+
+                                            scored_members(
+                                                sorted_set
+                                                    .range_by_score(min, max)
+                                                    .skip(offset as usize)
+                                                    .take(
+                                                        usize::try_from(count)
+                                                            .unwrap_or(usize::MAX),
+                                                    ),
+                                                withscores,
+                                                protocol,
+                                            )
+                                        },
+                                    ),
+                                    RespValue::Array(Vec::new()),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Xadd(input)) => {
+                                // https://redis.io/commands/xadd/
+                                let key = input.key.clone();
+                                let mut propagated = vec![
+                                    "XADD".to_string(),
+                                    key.clone(),
+                                    // the ID it went in with, so a replica doesn't generate its own
+                                    String::new(),
+                                ];
+                                for (field, value) in &input.fields {
+                                    propagated.extend([field.clone(), value.clone()]);
+                                }
+
+                                let id = match set_command_actor_handle.xadd(db, input).await {
+                                    Err(
+                                        error @ (RedisError::WrongType
+                                        | RedisError::StreamIdTooSmall
+                                        | RedisError::StreamIdZero),
+                                    ) => {
+                                        let _ = respond_to.send(Some(vec![error.into()]));
+
+                                        return Ok(());
+                                    }
+                                    id => id?,
+                                };
+
+                                set_command_actor_handle.notify(db, 't', "xadd", &key);
+
+                                let _ = respond_to.send(Some(vec![RespValue::BulkString(Some(
+                                    id.to_string().into(),
+                                ))]));
+
+                                propagated[2] = id.to_string();
+                                let propagated: Vec<&str> =
+                                    propagated.iter().map(String::as_str).collect();
+                                self.propagate(
+                                    &replica_tx,
+                                    db,
+                                    RespValue::array_from_slice(&propagated),
+                                    flags,
+                                )?;
+
+                                return Ok(());
+                            }
+                            Ok(RedisCommand::Xrange(key, start, end, count)) => {
+                                // https://redis.io/commands/xrange/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_stream(db, &key, |stream| {
+                                        RespValue::Array(
+                                            stream
+                                                .range(start, end)
+                                                .take(count.unwrap_or(usize::MAX))
+                                                .map(|(id, fields)| stream_entry(id, fields))
+                                                .collect(),
+                                        )
+                                    }),
+                                    RespValue::Array(Vec::new()),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Xlen(key)) => {
+                                // https://redis.io/commands/xlen/
+                                let reply = collection_reply(
+                                    set_command_actor_handle.read_stream(db, &key, |stream| {
+                                        RespValue::Integer(stream.len() as i64)
+                                    }),
+                                    RespValue::Integer(0),
+                                )?;
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Xread(count, streams)) => {
+                                // https://redis.io/commands/xread/, the entries after the ID given
+                                // for each key, none for $ as nothing can be added while it reads.
+                                // Only the keys with any make it into the reply, a null array if
+                                // none of them have.
+                                let mut read = Vec::new();
+
+                                for (key, id) in streams {
+                                    let entries = match set_command_actor_handle.read_stream(
+                                        db,
+                                        &key,
+                                        |stream| match id {
+                                            Some(id) => stream
+                                                .after(id)
+                                                .take(count.unwrap_or(usize::MAX))
+                                                .map(|(id, fields)| stream_entry(id, fields))
+                                                .collect(),
+                                            None => Vec::new(),
+                                        },
+                                    ) {
+                                        Err(RedisError::WrongType) => {
+                                            let _ = respond_to
+                                                .send(Some(vec![RedisError::WrongType.into()]));
+
+                                            return Ok(());
+                                        }
+                                        entries => entries?.unwrap_or_default(),
+                                    };
+
+                                    if !entries.is_empty() {
+                                        read.push(RespValue::Array(vec![
+                                            RespValue::BulkString(Some(key.into())),
+                                            RespValue::Array(entries),
+                                        ]));
+                                    }
+                                }
+
+                                let reply = if read.is_empty() {
+                                    RespValue::NullArray
+                                } else {
+                                    RespValue::Array(read)
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Expire(key, deadline)) => {
+                                // https://redis.io/commands/expire/
+                                // 1 if the key got the deadline, 0 if there is no such key
+                                let deleted = deadline <= set_command_actor_handle.now_ms() as i64;
+                                let exists = set_command_actor_handle
+                                    .expire_value(db, &key, deadline.max(0) as u64)
+                                    .await?;
+
+                                if exists {
+                                    let event = if deleted { "del" } else { "expire" };
+                                    set_command_actor_handle.notify(db, 'g', event, &key);
+                                }
+
+                                let _ =
+                                    respond_to.send(Some(vec![RespValue::Integer(exists as i64)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Persist(key)) => {
+                                // https://redis.io/commands/persist/
+                                // 1 if the deadline was dropped, 0 if there was none or no key
+                                let persisted =
+                                    set_command_actor_handle.persist_value(db, &key).await?;
+
+                                if persisted {
+                                    set_command_actor_handle.notify(db, 'g', "persist", &key);
+                                }
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::Integer(persisted as i64)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Ttl(key)) => {
+                                // https://redis.io/commands/ttl/, rounded to the nearest second
+                                let ttl = remaining_ttl(&set_command_actor_handle, db, &key)?;
+                                let ttl = if ttl < 0 { ttl } else { (ttl + 500) / 1000 };
+
+                                let _ = respond_to.send(Some(vec![RespValue::Integer(ttl)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Pttl(key)) => {
+                                // https://redis.io/commands/pttl/
+                                let ttl = remaining_ttl(&set_command_actor_handle, db, &key)?;
+
+                                let _ = respond_to.send(Some(vec![RespValue::Integer(ttl)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Config(patterns)) => {
+                                // https://redis.io/commands/config-get/
+                                // A flat array of name, value pairs, empty if nothing matched.
+                                let response = config_command_actor_handle
+                                    .get_matching_values(patterns)
+                                    .await?
+                                    .into_iter()
+                                    .flat_map(|(name, value)| {
+                                        [
+                                            RespValue::BulkString(Some(name.into())),
+                                            RespValue::BulkString(Some(value.into())),
+                                        ]
+                                    })
+                                    .collect();
+
+                                let _ = respond_to.send(Some(vec![RespValue::Array(response)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::ConfigSet(parameters)) => {
+                                // https://redis.io/commands/config-set/
+                                // All or nothing, like redis: every value is checked before any is set.
+                                let mut values = Vec::with_capacity(parameters.len());
+
+                                for (name, value) in parameters {
+                                    let checked = match ConfigCommandParameter::named(&name) {
+                                        None => Err(RedisError::UnknownConfig(name)),
+                                        Some(config_key)
+                                            if values
+                                                .iter()
+                                                .any(|(seen, _)| *seen == config_key) =>
+                                        {
+                                            Err(RedisError::ConfigSetFailed(
+                                                name,
+                                                "duplicate parameter".to_string(),
+                                            ))
+                                        }
+                                        Some(config_key) => config_set_value(config_key, &value)
+                                            .map(|value| (config_key, value))
+                                            .map_err(|e| RedisError::ConfigSetFailed(name, e)),
+                                    };
+
+                                    match checked {
+                                        Ok(checked) => values.push(checked),
+                                        Err(e) => {
+                                            let _ = respond_to.send(Some(vec![e.into()]));
+                                            return Ok(());
+                                        }
+                                    }
+                                }
+
+                                for (config_key, value) in values {
+                                    config_command_actor_handle
+                                        .set_value(config_key, &value)
+                                        .await?;
+                                }
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::SimpleString("OK".to_string())]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::ConfigResetstat) => {
+                                // https://redis.io/commands/config-resetstat/
+                                self.command_stats.reset();
+                                set_command_actor_handle.reset_stats().await?;
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::SimpleString("OK".to_string())]));
+
+                                Ok(())
+                            }
+
+                            Ok(RedisCommand::Keys(pattern)) => {
+                                // Returns the values of all specified keys matching the pattern.
+                                //
+                                // https://redis.io/commands/keys/
+                                let threshold: usize = config_command_actor_handle
+                                    .get_value(ConfigCommandParameter::KeysWarnThreshold)
+                                    .await?
+                                    .and_then(|threshold| threshold.parse().ok())
+                                    .unwrap_or_default();
+
+                                let size = set_command_actor_handle.db_size(db).await?;
+                                let large = threshold > 0 && size > threshold;
+
+                                if large {
+                                    warn!(
+                                        "KEYS {} went through all {} keys of db {}, SCAN 0 MATCH {} COUNT {} returns the same keys in steps",
+                                        pattern, size, db, pattern, KEYS_SCAN_STEP
+                                    );
+                                }
+
+                                let by_scan = large
+                                    && config_command_actor_handle
+                                        .get_value(ConfigCommandParameter::KeysByScan)
+                                        .await?
+                                        .as_deref()
+                                        == Some("yes");
+
+                                // Matching a big database and building its reply takes a while. Unless inside
+                                // EXEC, where nothing may get in between, that happens on a task of its own and
+                                // this actor moves on to the next request.
+                                if wait_sleep_tx.is_some() {
+                                    let set_command_actor_handle = set_command_actor_handle.clone();
+
+                                    tokio::spawn(async move {
+                                        let reply =
+                                            keys(&set_command_actor_handle, db, pattern, by_scan)
+                                                .await;
+                                        let _ = respond_to.send(Some(vec![reply]));
+                                    });
+                                } else {
+                                    let reply =
+                                        keys(&set_command_actor_handle, db, pattern, by_scan).await;
+                                    let _ = respond_to.send(Some(vec![reply]));
+                                }
+
+                                Ok(())
+                            }
+
+                            Ok(RedisCommand::Scan(scan_parameters)) => {
+                                // https://redis.io/commands/scan/
+                                // a type redis doesn't have matches no key, the cursor goes on all the same
+                                let kind =
+                                    scan_parameters.value_type.as_deref().map(Kind::from_name);
+                                let unknown_type = kind == Some(None);
+                                let (next_cursor, keys) = set_command_actor_handle
+                                    .scan(
+                                        db,
+                                        scan_parameters.cursor,
+                                        scan_parameters.count,
+                                        kind.flatten(),
+                                    )
+                                    .await?;
+
+                                // like redis, MATCH and TYPE filter what the step found, so a step may come back empty
+                                let keys =
+                                    keys.into_iter()
+                                        .filter(|_| !unknown_type)
+                                        .filter(|key| {
+                                            scan_parameters.pattern.as_deref().is_none_or(
+                                                |pattern| glob_match(pattern, key, false),
+                                            )
+                                        })
+                                        .map(|key| RespValue::BulkString(Some(key.into())))
+                                        .collect();
+
+                                let _ = respond_to.send(Some(vec![RespValue::Array(vec![
+                                    RespValue::BulkString(Some(next_cursor.to_string().into())),
+                                    RespValue::Array(keys),
+                                ])]));
+
+                                Ok(())
+                            }
+
+                            Ok(RedisCommand::Info(info_parameter)) => {
+                                // we may or may not get a value for the INFO command.
+
+                                // first, let's see if this INFO section exists.
+                                if info_parameter == Some(InfoCommandParameter::Keyspace) {
+                                    // https://redis.io/docs/latest/commands/info/#keyspace
+                                    let mut keyspace = String::from("# Keyspace\r\n");
+
+                                    for stats in
+                                        set_command_actor_handle.get_keyspace_stats().await?
+                                    {
+                                        keyspace.push_str(&format!(
+                                            "db{}:keys={},expires={},avg_ttl={}\r\n",
+                                            stats.db, stats.keys, stats.expires, stats.avg_ttl
+                                        ));
+                                    }
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(keyspace.into()),
+                                    )]));
+                                } else if info_parameter == Some(InfoCommandParameter::Stats) {
+                                    // https://redis.io/docs/latest/commands/info/#stats, the expiry and eviction fields so far
+                                    let expiry =
+                                        set_command_actor_handle.get_expiry_stats().await?;
+
+                                    let stats = format!(
+                                        "# Stats\r\nexpired_keys:{}\r\nexpired_keys_per_sec:{:.2}\r\nexpired_stale_perc:{:.2}\r\nexpired_time_cap_reached_count:{}\r\nevicted_keys:{}\r\n",
+                                        expiry.expired_keys,
+                                        expiry.expired_keys_per_sec,
+                                        expiry.expired_stale_perc,
+                                        expiry.expired_time_cap_reached_count,
+                                        expiry.evicted_keys
+                                    );
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(stats.into()),
+                                    )]));
+                                } else if info_parameter == Some(InfoCommandParameter::Memory) {
+                                    // https://redis.io/docs/latest/commands/info/#memory, where
+                                    // used_memory counts the keys and values only
+                                    let used_memory =
+                                        set_command_actor_handle.get_used_memory().await?;
+                                    let (maxmemory, policy) =
+                                        maxmemory(&config_command_actor_handle).await?;
+
+                                    let memory = format!(
+                                        "# Memory\r\nused_memory:{}\r\nmaxmemory:{}\r\nmaxmemory_policy:{}\r\n",
+                                        used_memory, maxmemory, policy
+                                    );
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(memory.into()),
+                                    )]));
+                                } else if info_parameter == Some(InfoCommandParameter::Clients) {
+                                    // https://redis.io/docs/latest/commands/info/#clients, replicas
+                                    // are not counted as connected clients
+                                    let connected = clients_actor_handle
+                                        .list()
+                                        .await?
+                                        .iter()
+                                        .filter(|client| !client.replica)
+                                        .count();
+                                    let pubsub = pubsub_actor_handle.stats().await?;
+
+                                    let clients = format!(
+                                        "# Clients\r\nconnected_clients:{}\r\npubsub_clients:{}\r\npubsub_channels:{}\r\npubsub_patterns:{}\r\n",
+                                        connected, pubsub.clients, pubsub.channels, pubsub.patterns
+                                    );
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(clients.into()),
+                                    )]));
+                                } else if info_parameter == Some(InfoCommandParameter::Actors) {
+                                    let mut actors = String::from("# Actors\r\n");
+
+                                    for (name, health) in supervisor::health() {
+                                        actors.push_str(&format!(
+                                            "actor_{}:running={},restarts={},last_panic={}\r\n",
+                                            name,
+                                            health.running,
+                                            health.restarts,
+                                            health.last_panic.as_deref().unwrap_or("none")
+                                        ));
+                                    }
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(actors.into()),
+                                    )]));
+                                } else if info_parameter == Some(InfoCommandParameter::Commandstats)
+                                {
+                                    let section = self.command_stats.commandstats_section();
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(section.into()),
+                                    )]));
+                                } else if info_parameter == Some(InfoCommandParameter::Latencystats)
+                                {
+                                    let section = self.command_stats.latencystats_section();
+
+                                    let _ = respond_to.send(Some(vec![RespValue::BulkString(
+                                        Some(section.into()),
+                                    )]));
+                                } else if let Some(_param) = info_parameter {
+                                    // Everything else gets the replication section.
+                                    // TODO: match on param
+                                    let replication_data =
+                                        replication_actor_handle.get_value(HostId::Myself).await?;
+
+                                    tracing::debug!(
+                                        "Retrieved INFO RespValue: {:?}",
+                                        replication_data
+                                    );
+
+                                    // then, let's see if the section contains data.
+                                    if let Some(replication_section) = replication_data {
+                                        let mut info = replication_section.to_string();
+
+                                        // a master also lists its replicas, by the port they listen on
+                                        if replication_section.role == Some(ServerRole::Master) {
+                                            let replicas =
+                                                replication_actor_handle.get_replicas().await?;
+                                            let lags: HashMap<HostId, ReplicaLag> =
+                                                replication_actor_handle
+                                                    .get_replica_lags()
+                                                    .await?
+                                                    .into_iter()
+                                                    .collect();
+
+                                            info.push_str(&format!(
+                                                "connected_slaves:{}:",
+                                                replicas.len()
+                                            ));
+
+                                            for (i, (replica, data)) in replicas.iter().enumerate()
+                                            {
+                                                if let HostId::Host { ip, port, .. } = replica {
+                                                    info.push_str(&format!(
+                                                        "slave{}:ip={},port={},state=online,offset={},lag={}:",
+                                                        i,
+                                                        ip,
+                                                        data.listening_port.unwrap_or(*port),
+                                                        data.master_repl_offset.unwrap_or(0),
+                                                        lags.get(replica).map_or(0, |lag| lag.seconds)
+                                                    ));
+                                                }
+                                            }
+                                        }
+
+                                        // a replica tells how it keeps up with applying its master's stream
+                                        if replication_section.role == Some(ServerRole::Slave) {
+                                            let backlog = replication_actor_handle
+                                                .get_apply_backlog()
+                                                .await?;
+
+                                            info.push_str(&format!(
+                                                "replica_repl_backlog_bytes:{}:replica_repl_queued_requests:{}:replica_repl_apply_ms:{}:replica_repl_apply_max_ms:{}:replica_repl_slow_applies:{}:",
+                                                backlog.bytes,
+                                                backlog.queued,
+                                                backlog.last_ms,
+                                                backlog.max_ms,
+                                                backlog.slow
+                                            ));
+                                        }
+
+                                        let _ = respond_to
+                                            .send(Some(vec![RespValue::SimpleString(info)]));
+                                    } else {
+                                        let _ = respond_to.send(Some(vec![RespValue::Null]));
+                                    }
+                                } else {
+                                    let _ = respond_to.send(Some(vec![RespValue::Null]));
+                                }
+
+                                Ok(())
+                            }
+
+                            Ok(RedisCommand::ReplConf(replconf_params)) => {
+                                // initialize the reply of Vec<RespValue>
+                                // let mut response: Vec<RespValue> = Vec::new();
+
+                                // a simple OK to start with.
+                                // response.push(RespValue::SimpleString("OK".to_string()));
+
+                                // inform the handler_client that this is a replica
+                                if let Some(client_or_replica_tx_sender) = client_or_replica_tx {
+                                    client_or_replica_tx_sender.send(true).await?;
+                                }
+
+                                // Check what replconf parameter we have and act accordingly
+                                // https://redis.io/commands/replconf
+                                match replconf_params {
+                                    ReplConfCommandParameter::Getack(ackvalue) => {
+                                        // typically this is FROM the master, and is received by the replica.
+                                        // So, if we are processing this, we are a replica.
+                                        // Replies to this will go back over the OUTBOUND tcp connection to the master.
+                                        // NOTE: Most replies are suppressed but these we need to send back to the master.
+                                        debug!("Replica received GETACK: {}", ackvalue);
+
+                                        // check to make sure ackvalue is actually *
+                                        if ackvalue == "*" {
+                                            // We need to get the current offset value and send it back to the master.
+                                            //
+                                            // First, let's prepare the correct key message:
+                                            // let info_key = InfoCommandParameter::Replication;
+
+                                            // get the current replication data.
+                                            if let Some(current_replication_data) =
+                                                replication_actor_handle
+                                                    .get_value(HostId::Myself)
+                                                    .await?
+                                            {
+                                                debug!(
+                                                    "REPLICA: retrieving replication data {:?}",
+                                                    current_replication_data
+                                                );
+
+                                                // extract the current offset value.
+                                                let current_offset = current_replication_data
+                                                    .master_repl_offset
+                                                    .expect("Expected to find an replication entry for the replica.");
+
+                                                let repl_conf_ack = RespValue::array_from_slice(&[
+                                                    "REPLCONF",
+                                                    "ACK",
+                                                    &current_offset.to_string(),
+                                                ]);
+
+                                                // convert it to an encoded string purely for debugging purposes
+                                                let repl_conf_ack_encoded =
+                                                    repl_conf_ack.to_encoded_string()?;
+
+                                                debug!(
+                                                    "REPLICA: returning {:?} from processor to main.rs loop.",
+                                                    repl_conf_ack_encoded
+                                                );
+
+                                                // response.push(repl_conf_ack);
+
+                                                // send the current offset value back to the master
+                                                // NOTE: this does NOT go over the master_tx channel, which is only for replies TO the master.
+                                                // NOTE: this is the offset BEFORE the latest
+                                                let _ = respond_to.send(Some(vec![repl_conf_ack]));
+                                            } else {
+                                                error!(
+                                                    "Unable to find replication data for {:?}",
                                                     host_id
                                                 );
                                             }
@@ -472,186 +3184,954 @@ impl ProcessorActor {
 
                                         Ok(())
                                     }
-                                    ReplConfCommandParameter::Ack(ack) => {
-                                        // These are received by the master from the replica slaves.
-                                        debug!("Received ACK: {} from {:?}", ack, host_id);
+                                    ReplConfCommandParameter::Ack(ack) => {
+                                        // These are received by the master from the replica slaves.
+                                        debug!("Received ACK: {} from {:?}", ack, host_id);
+
+                                        // where the replica has got to, and that it is still there.
+                                        replication_actor_handle
+                                            .acknowledge(host_id, ack as i64)
+                                            .await?;
+
+                                        // replicas send these every second and after REPLCONF GETACK *,
+                                        // neither of which expects a reply.
+                                        let _ = respond_to.send(None);
+
+                                        Ok(())
+                                    }
+                                    ReplConfCommandParameter::Capa(capabilities) => {
+                                        // kept with the replica, how a diskless sync is sent to it depends on them
+                                        let announced = ReplicationSectionData {
+                                            role: None,
+                                            master_replid: None,
+                                            master_repl_offset: None,
+                                            listening_port: None,
+                                            capabilities: Some(capabilities),
+                                        };
+
+                                        replication_actor_handle
+                                            .update_value(host_id, announced)
+                                            .await?;
+
+                                        let _ = respond_to.send(Some(vec![
+                                            (RespValue::SimpleString("OK".to_string())),
+                                        ]));
+
+                                        Ok(())
+                                    }
+                                    ReplConfCommandParameter::ListeningPort(port) => {
+                                        // the port this connection comes from is not the one the replica
+                                        // listens on, keep the announced one for INFO and ROLE.
+                                        let announced = ReplicationSectionData {
+                                            role: None,
+                                            master_replid: None,
+                                            master_repl_offset: None,
+                                            listening_port: Some(port),
+                                            capabilities: None,
+                                        };
+
+                                        replication_actor_handle
+                                            .update_value(host_id, announced)
+                                            .await?;
+
+                                        let _ = respond_to.send(Some(vec![
+                                            (RespValue::SimpleString("OK".to_string())),
+                                        ]));
+
+                                        Ok(())
+                                    }
+                                }
+                            }
+
+                            Ok(RedisCommand::Role) => {
+                                // https://redis.io/commands/role/
+                                let myself = replication_actor_handle
+                                    .get_value(HostId::Myself)
+                                    .await?
+                                    .expect(
+                                        "This should never fail because we always know ourselves.",
+                                    );
+
+                                let offset = myself.master_repl_offset.unwrap_or(0) as i64;
+                                let bulk = |s: String| RespValue::BulkString(Some(s.into()));
+
+                                let role = if myself.role == Some(ServerRole::Slave) {
+                                    // the master is whatever --replicaof was given, as "host port"
+                                    let replicaof = config_command_actor_handle
+                                        .get_value(ConfigCommandParameter::Replicaof)
+                                        .await?
+                                        .unwrap_or_default();
+                                    let (master_host, master_port) =
+                                        replicaof.split_once(' ').unwrap_or((&replicaof, "0"));
+
+                                    vec![
+                                        bulk("slave".to_string()),
+                                        bulk(master_host.to_string()),
+                                        RespValue::Integer(master_port.parse().unwrap_or(0)),
+                                        bulk("connected".to_string()),
+                                        RespValue::Integer(offset),
+                                    ]
+                                } else {
+                                    let mut replicas = Vec::new();
+
+                                    for (replica, data) in
+                                        replication_actor_handle.get_replicas().await?
+                                    {
+                                        if let HostId::Host { ip, port, .. } = replica {
+                                            replicas.push(RespValue::Array(vec![
+                                                bulk(ip),
+                                                bulk(
+                                                    data.listening_port.unwrap_or(port).to_string(),
+                                                ),
+                                                bulk(
+                                                    data.master_repl_offset
+                                                        .unwrap_or(0)
+                                                        .to_string(),
+                                                ),
+                                            ]));
+                                        }
+                                    }
+
+                                    vec![
+                                        bulk("master".to_string()),
+                                        RespValue::Integer(offset),
+                                        RespValue::Array(replicas),
+                                    ]
+                                };
+
+                                let _ = respond_to.send(Some(vec![RespValue::Array(role)]));
+
+                                Ok(())
+                            }
+
+                            Ok(RedisCommand::Replicaof(None)) => {
+                                // https://redis.io/commands/replicaof/
+                                // REPLICAOF NO ONE stops following the master but keeps the dataset.
+                                let role = replication_actor_handle
+                                    .get_value(HostId::Myself)
+                                    .await?
+                                    .and_then(|myself| myself.role);
+
+                                if role == Some(ServerRole::Slave) {
+                                    // Our history with the old master ends here, so a new replication ID.
+                                    // The link to the master sees the new role and closes itself.
+                                    let promotion = ReplicationSectionData {
+                                        role: Some(ServerRole::Master),
+                                        master_replid: Some(generate_replication_id()),
+                                        master_repl_offset: None,
+                                        listening_port: None,
+                                        capabilities: None,
+                                    };
+
+                                    replication_actor_handle
+                                        .update_value(HostId::Myself, promotion)
+                                        .await?;
+
+                                    config_command_actor_handle
+                                        .set_value(ConfigCommandParameter::Replicaof, "")
+                                        .await?;
+
+                                    tracing::info!("Promoted to master, keeping the dataset.");
+                                }
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::SimpleString("OK".to_string())]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Replicaof(Some((host, port)))) => {
+                                // Following a master takes a handshake on a new connection, which main.rs
+                                // only sets up at startup.
+                                debug!("REPLICAOF {host} {port} refused, not at startup.");
+
+                                let _ = respond_to
+                                    .send(Some(vec![RedisError::ReplicaOfAtRuntime.into()]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Psync(_replication_id, offset)) => {
+                                // ignore the _replication_id for now. There are actually two of them:
+                                // https://redis.io/docs/latest/operate/oss_and_stack/management/replication/#replication-id-explained
+
+                                debug!("PSYNC: Processing replication data for {host_id}");
+
+                                // Check if we've seen this replica before. REPLCONF listening-port already left
+                                // an entry for this host, so it only counts once it has the slave role.
+                                // TODO: move the common sections that always get executed out of the if let Some
+                                // conditional.
+                                if let Some(replication_section_data) = replication_actor_handle
+                                    .get_value(host_id.clone())
+                                    .await?
+                                    .filter(|data| data.role == Some(ServerRole::Slave))
+                                {
+                                    debug!("Known replica {replication_section_data}, proceeding.");
+                                } else {
+                                    warn!("Replica not seen before, adding.");
+                                    let mut replication_data = ReplicationSectionData::new();
+
+                                    // this is a replica we've not seen before, so let's initialize everything.
+                                    replication_data.role = Some(ServerRole::Slave);
+                                    replication_data.master_replid = replication_actor_handle
+                                        .get_value(HostId::Myself)
+                                        .await?
+                                        .expect("This should never fail because we always know our own replication ID.")
+                                        .master_replid;
+                                    replication_data.master_repl_offset = Some(0);
+
+                                    // store the replica's values in the replication actor
+                                    replication_actor_handle
+                                        .update_value(host_id.clone(), replication_data)
+                                        .await?;
+
+                                    // let _ = respond_to.send(None);
+                                }
+
+                                // There is no backlog to continue from, so whatever the replica asks for
+                                // (PSYNC ? -1 on a first sync) it gets a full resync.
+                                debug!("Full resync triggered with offset {}", offset);
+
+                                let diskless = config_command_actor_handle
+                                    .get_value(ConfigCommandParameter::ReplDisklessSync)
+                                    .await?
+                                    .is_some_and(|diskless| diskless == "yes");
+
+                                if diskless {
+                                    // the +FULLRESYNC and the RDB follow on the replication stream once
+                                    // the delay is up, a replica arriving before that joins the sync
+                                    // already waiting.
+                                    match self.pending_sync.as_mut() {
+                                        Some(sync) => sync.replicas.push(host_id),
+                                        None => {
+                                            let delay = config_command_actor_handle
+                                                .get_value(
+                                                    ConfigCommandParameter::ReplDisklessSyncDelay,
+                                                )
+                                                .await?
+                                                .and_then(|delay| delay.parse().ok())
+                                                .unwrap_or(DEFAULT_REPL_DISKLESS_SYNC_DELAY);
+
+                                            debug!("Diskless sync starts in {} seconds.", delay);
+
+                                            self.pending_sync = Some(PendingSync {
+                                                due: Instant::now() + Duration::from_secs(delay),
+                                                set_command_actor_handle: set_command_actor_handle
+                                                    .clone(),
+                                                replication_actor_handle: replication_actor_handle
+                                                    .clone(),
+                                                replica_tx: replica_tx.clone(),
+                                                replicas: vec![host_id],
+                                            });
+                                        }
+                                    }
+                                } else {
+                                    // Master got PSYNC ? -1, the replica is expecting
+                                    // +FULLRESYNC <REPL_ID> <OFFSET>\r\n back and an RDB of the
+                                    // store as it is now, saved to disk on the way. Both go on the
+                                    // replication stream right away rather than in this reply, so
+                                    // that the writes already on their way to this connection are
+                                    // known to be in the snapshot.
+                                    self.sync_replicas(
+                                        PendingSync {
+                                            due: Instant::now(),
+                                            set_command_actor_handle: set_command_actor_handle
+                                                .clone(),
+                                            replication_actor_handle: replication_actor_handle
+                                                .clone(),
+                                            replica_tx: replica_tx.clone(),
+                                            replicas: vec![host_id],
+                                        },
+                                        Some(&config_command_actor_handle),
+                                    )
+                                    .await?;
+                                }
+
+                                // nothing to reply, the connection waits for its +FULLRESYNC on the stream
+                                let _ = respond_to.send(Some(Vec::new()));
+
+                                Ok(())
+                            } // end of psync
+                            Ok(RedisCommand::Wait(numreplicas, timeout)) => {
+                                debug!("Processing WAIT {} {}", numreplicas, timeout);
+
+                                // WAIT 0 has nothing to wait for, it just reports how many replicas are connected.
+                                if numreplicas == 0 {
+                                    let connected_replicas = replication_actor_handle
+                                        .get_connected_replica_count()
+                                        .await?;
+
+                                    let _ = respond_to.send(Some(vec![RespValue::Integer(
+                                        connected_replicas as i64,
+                                    )]));
+
+                                    return Ok(());
+                                }
+
+                                let replconf_getack_star: RespValue =
+                                    RespValue::array_from_slice(&["REPLCONF", "GETACK", "*"]);
+                                // let _ = replica_tx.send(replconf_getack_star.clone())?;
+
+                                let current_master_offset = replication_actor_handle
+                                    .get_value(HostId::Myself)
+                                    .await?
+                                    .expect("Expected to always have self information.")
+                                    .master_repl_offset
+                                    .expect("Master always has offset.");
+
+                                // get the replica count
+                                let replicas_in_sync = replication_actor_handle
+                                    .get_synced_replica_count(current_master_offset) // -37 is REPLCONF GETACK *
+                                    .await?;
+
+                                tracing::info!("Target number of replicas: {numreplicas} and we have {replicas_in_sync} replicas in sync.");
+
+                                // let's implement the wait command
+                                // https://redis.io/commands/wait/
+                                //
+                                // The command takes two parameters:
+                                // 1. numreplicas: The number of replicas that must be connected and in sync.
+                                // 2. timeout: The maximum number of milliseconds to wait for the replicas to be connected and in sync.
+                                //
+                                // detailed OG implementation: https://github.com/redis/redis/blob/unstable/src/replication.c#L3548
+                                // Without a wait_sleep_tx (inside EXEC) there is no one to hear back from the
+                                // sleeping task, so WAIT reports the count it has instead of blocking.
+                                let wait_sleep_tx =
+                                    wait_sleep_tx.filter(|_| replicas_in_sync < numreplicas);
+
+                                if let Some(wait_sleep_tx) = wait_sleep_tx {
+                                    feed_replicas(&replica_tx, replconf_getack_star)?;
+
+                                    // let start_time = Instant::now();
+
+                                    let duration = Duration::from_millis(timeout.try_into()?);
+
+                                    let _sleeping_handle = sleeping_task(
+                                        wait_sleep_tx,
+                                        duration,
+                                        current_master_offset,
+                                    )
+                                    .await;
+
+                                    // yielding back to tokio
+
+                                    // sleeping_handle.await?;
+
+                                    // let replicas_in_sync =
+                                    //     replication_actor_handle.get_synced_replica_count().await?;
+
+                                    //     debug!("After REPLCONF ACK we have {replicas_in_sync} in sync replicas.");
+
+                                    let _ = respond_to.send(None); // no replies at this point, the sleeping_task fxn will reply
+                                } else {
+                                    // we can return immediately
+                                    let _ = respond_to.send(Some(vec![
+                                        (RespValue::Integer(replicas_in_sync as i64)),
+                                    ]));
+                                }
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::WaitAof(numlocal, numreplicas, timeout)) => {
+                                // https://redis.io/commands/waitaof/
+                                debug!("Processing WAITAOF {numlocal} {numreplicas} {timeout}");
+
+                                let role = replication_actor_handle
+                                    .get_value(HostId::Myself)
+                                    .await?
+                                    .expect("Expected to always have self information.")
+                                    .role;
+
+                                let reply = if role == Some(ServerRole::Slave) {
+                                    RedisError::WaitAofOnReplica.into()
+                                } else if numlocal > 0 {
+                                    RedisError::WaitAofWithoutAppendOnly.into()
+                                } else {
+                                    // There is no AOF, here or on the replicas, so no fsync is ever acknowledged
+                                    // and waiting out the timeout would only delay the same answer.
+                                    RespValue::Array(vec![
+                                        RespValue::Integer(0),
+                                        RespValue::Integer(0),
+                                    ])
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Debug(debug_parameter)) => {
+                                let enabled = config_command_actor_handle
+                                    .get_value(ConfigCommandParameter::EnableDebugCommand)
+                                    .await?;
+
+                                let reply = if enabled.as_deref() != Some("yes") {
+                                    RedisError::DebugNotAllowed.into()
+                                } else {
+                                    match debug_parameter {
+                                        DebugCommandParameter::Failpoint(failpoint) => {
+                                            failpoint_actor_handle.arm(failpoint).await;
+
+                                            RespValue::SimpleString("OK".to_string())
+                                        }
+                                        DebugCommandParameter::Object(key) => {
+                                            match set_command_actor_handle.read_object(db, &key) {
+                                                Ok(Some(object)) => match debug_object(&object) {
+                                                    Ok(object) => RespValue::SimpleString(object),
+                                                    Err(e) => e.into(),
+                                                },
+                                                Ok(None) => RedisError::KeyNotFound.into(),
+                                                Err(e) => return Err(e.into()),
+                                            }
+                                        }
+                                        DebugCommandParameter::Panic => {
+                                            // redis crashes here, this only costs the connection that sent it
+                                            panic!("DEBUG PANIC");
+                                        }
+                                        // panicked by the connection itself, once it has this reply
+                                        DebugCommandParameter::Segfault => {
+                                            RespValue::SimpleString("OK".to_string())
+                                        }
+                                        DebugCommandParameter::Sleep(duration) => {
+                                            tokio::time::sleep(duration).await;
+
+                                            RespValue::SimpleString("OK".to_string())
+                                        }
+                                        // No other command runs until the file is back in, only a GET
+                                        // read straight from the store can come across it half loaded.
+                                        DebugCommandParameter::Reload => {
+                                            match config_command_actor_handle
+                                                .reload_rdb(set_command_actor_handle.clone())
+                                                .await
+                                            {
+                                                Ok(loaded) => {
+                                                    debug!("DEBUG RELOAD loaded {} keys", loaded);
+                                                    RespValue::SimpleString("OK".to_string())
+                                                }
+                                                Err(e) => {
+                                                    error!("DEBUG RELOAD failed: {:#}", e);
+                                                    RedisError::Internal(format!("{:#}", e)).into()
+                                                }
+                                            }
+                                        }
+                                        // what eviction would do, but now and regardless of role
+                                        DebugCommandParameter::Evict(count) => {
+                                            let (_, policy) =
+                                                maxmemory(&config_command_actor_handle).await?;
+                                            let evicted = set_command_actor_handle
+                                                .evict_keys(count, policy)
+                                                .await?;
+                                            let evicted_count = evicted.len();
+
+                                            self.evicted(
+                                                &set_command_actor_handle,
+                                                &replica_tx,
+                                                evicted,
+                                            )?;
+
+                                            RespValue::Integer(evicted_count as i64)
+                                        }
+                                        // key, deadline pairs, to hold a replica's against its master's
+                                        DebugCommandParameter::Jmap => RespValue::Array(
+                                            set_command_actor_handle
+                                                .deadlines(db)?
+                                                .into_iter()
+                                                .flat_map(|(key, deadline)| {
+                                                    [
+                                                        RespValue::BulkString(Some(key.into())),
+                                                        RespValue::Integer(deadline as i64),
+                                                    ]
+                                                })
+                                                .collect(),
+                                        ),
+                                        // held against a replica's to tell whether it holds the same
+                                        DebugCommandParameter::Digest => RespValue::SimpleString(
+                                            digest::to_hex(set_command_actor_handle.digest()?),
+                                        ),
+                                        DebugCommandParameter::DigestValue(keys) => {
+                                            RespValue::Array(
+                                                set_command_actor_handle
+                                                    .value_digests(db, &keys)?
+                                                    .into_iter()
+                                                    .map(|value_digest| {
+                                                        RespValue::SimpleString(digest::to_hex(
+                                                            value_digest,
+                                                        ))
+                                                    })
+                                                    .collect(),
+                                            )
+                                        }
+                                    }
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Select(index)) => {
+                                // https://redis.io/commands/select/
+                                if (0..set_command_actor_handle.databases() as i64).contains(&index)
+                                {
+                                    self.clients.entry(host_id).or_default().db = index as usize;
+
+                                    let _ = respond_to.send(Some(vec![RespValue::SimpleString(
+                                        "OK".to_string(),
+                                    )]));
+                                } else {
+                                    let _ = respond_to
+                                        .send(Some(vec![RedisError::DbIndexOutOfRange.into()]));
+                                }
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Subscribe(channels)) => {
+                                // https://redis.io/commands/subscribe/
+                                let replies = self
+                                    .subscribe(
+                                        SubscriptionKind::Channel,
+                                        host_id,
+                                        channels,
+                                        push_tx,
+                                        &pubsub_actor_handle,
+                                    )
+                                    .await?;
+
+                                let _ = respond_to.send(Some(replies));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Unsubscribe(channels)) => {
+                                // https://redis.io/commands/unsubscribe/
+                                let replies = self
+                                    .unsubscribe(
+                                        SubscriptionKind::Channel,
+                                        host_id,
+                                        channels,
+                                        &pubsub_actor_handle,
+                                    )
+                                    .await?;
+
+                                let _ = respond_to.send(Some(replies));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Psubscribe(patterns)) => {
+                                // https://redis.io/commands/psubscribe/
+                                let replies = self
+                                    .subscribe(
+                                        SubscriptionKind::Pattern,
+                                        host_id,
+                                        patterns,
+                                        push_tx,
+                                        &pubsub_actor_handle,
+                                    )
+                                    .await?;
+
+                                let _ = respond_to.send(Some(replies));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Punsubscribe(patterns)) => {
+                                // https://redis.io/commands/punsubscribe/
+                                let replies = self
+                                    .unsubscribe(
+                                        SubscriptionKind::Pattern,
+                                        host_id,
+                                        patterns,
+                                        &pubsub_actor_handle,
+                                    )
+                                    .await?;
+
+                                let _ = respond_to.send(Some(replies));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Publish(channel, message)) => {
+                                // https://redis.io/commands/publish/
+                                let receivers =
+                                    pubsub_actor_handle.publish(channel, message).await?;
+
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::Integer(receivers as i64)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Swapdb(first, second)) => {
+                                // https://redis.io/commands/swapdb/
+                                let databases = set_command_actor_handle.databases();
+                                let index = |index: String, which| match index.parse::<i64>() {
+                                    Ok(index) if (0..databases as i64).contains(&index) => {
+                                        Ok(index as usize)
+                                    }
+                                    Ok(_) => Err(RedisError::DbIndexOutOfRange),
+                                    Err(_) => Err(RedisError::InvalidDbIndex(which)),
+                                };
+
+                                let reply = match index(first, "first").and_then(|first| {
+                                    index(second, "second").map(|second| (first, second))
+                                }) {
+                                    Ok((first, second)) => {
+                                        set_command_actor_handle.swap_db(first, second).await?;
+
+                                        RespValue::SimpleString("OK".to_string())
+                                    }
+                                    Err(e) => e.into(),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Dbsize) => {
+                                let db_size = set_command_actor_handle.db_size(db).await?;
+
+                                let _ =
+                                    respond_to.send(Some(vec![RespValue::Integer(db_size as i64)]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Hello(hello)) => {
+                                // https://redis.io/commands/hello/
+                                // Everything is checked before anything is applied, so a HELLO
+                                // that fails leaves the protocol, the user and the name as they were.
+                                let protocol =
+                                    match hello.protover.map(protocol_version).transpose() {
+                                        Ok(protocol) => protocol,
+                                        Err(e) => {
+                                            let _ = respond_to.send(Some(vec![e.into()]));
+
+                                            return Ok(());
+                                        }
+                                    };
+                                let role = replication_actor_handle
+                                    .get_value(HostId::Myself)
+                                    .await?
+                                    .and_then(|myself| myself.role);
+                                let pending = PendingHello {
+                                    protocol,
+                                    setname: hello.setname,
+                                    role,
+                                };
+
+                                match hello.auth {
+                                    Some((username, password)) => {
+                                        self.authenticate(
+                                            host_id,
+                                            username,
+                                            password,
+                                            Some(pending),
+                                            push_tx,
+                                            wait_sleep_tx.is_none(),
+                                            respond_to,
+                                        )
+                                        .await;
+                                    }
+                                    // without AUTH, only a connection that needs none says HELLO
+                                    None if !self.authenticated(&host_id) => {
+                                        let _ =
+                                            respond_to.send(Some(vec![RedisError::NoAuth.into()]));
+                                    }
+                                    None => {
+                                        let reply =
+                                            self.hello(&host_id, None, pending, push_tx.as_ref());
+                                        let _ = respond_to.send(Some(vec![reply]));
+                                    }
+                                }
 
-                                        // we got a new value, so let's reset the offset.
-                                        replication_actor_handle
-                                            .reset_replica_offset(host_id.clone())
-                                            .await;
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Auth(None, _))
+                                if !self.authenticator.default_user_has_password() =>
+                            {
+                                // https://redis.io/commands/auth/, nothing to check the password against
+                                let _ = respond_to
+                                    .send(Some(vec![RedisError::AuthWithoutPassword.into()]));
 
-                                        // create a new replication data struct.
-                                        let mut current_replication_data =
-                                            ReplicationSectionData::new();
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Auth(username, password)) => {
+                                // https://redis.io/commands/auth/
+                                let username = username.unwrap_or_else(|| "default".to_string());
 
-                                        // set the master_repl_offset to ack
-                                        current_replication_data.master_repl_offset =
-                                            Some(ack as i16);
+                                self.authenticate(
+                                    host_id,
+                                    username,
+                                    password,
+                                    None,
+                                    push_tx,
+                                    wait_sleep_tx.is_none(),
+                                    respond_to,
+                                )
+                                .await;
 
-                                        // update the offset value in the replication actor.
-                                        replication_actor_handle
-                                            .update_value(host_id, current_replication_data)
-                                            .await;
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Client(ClientCommandParameter::Id)) => {
+                                // https://redis.io/commands/client-id/
+                                let id = match host_id {
+                                    HostId::Host { id, .. } => id as i64,
+                                    HostId::Myself => 0,
+                                };
 
-                                        // this is only ever received by the master, after REPLCONF GETACK *,
-                                        // so we don't need to do anything here.
-                                        let _ = respond_to.send(None);
+                                let _ = respond_to.send(Some(vec![RespValue::Integer(id)]));
 
-                                        Ok(())
-                                    }
-                                    ReplConfCommandParameter::Capa => {
-                                        let _ = respond_to.send(Some(vec![
-                                            (RespValue::SimpleString("OK".to_string())),
-                                        ]));
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Client(ClientCommandParameter::Info)) => {
+                                // https://redis.io/commands/client-info/, the CLIENT LIST line of
+                                // this connection
+                                let line = clients_actor_handle
+                                    .list()
+                                    .await?
+                                    .iter()
+                                    .find(|client| client.host_id == host_id)
+                                    .map(|client| self.client_list_line(client))
+                                    .unwrap_or_default();
 
-                                        Ok(())
+                                let _ = respond_to
+                                    .send(Some(vec![RespValue::BulkString(Some(line.into()))]));
+
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Client(ClientCommandParameter::List(filter))) => {
+                                // https://redis.io/commands/client-list/
+                                // One snapshot of the registry, taken in a single message, so a
+                                // connect or disconnect lands either wholly before or after it.
+                                let clients = clients_actor_handle.list().await?;
+
+                                let listed: Result<Vec<&ClientInfo>, RedisError> = match &filter {
+                                    None => Ok(clients.iter().collect()),
+                                    Some(ClientListFilter::Type(client_type)) => {
+                                        client_type.parse().map(|client_type| {
+                                            clients
+                                                .iter()
+                                                .filter(|client| {
+                                                    self.client_is_type(client, client_type)
+                                                })
+                                                .collect()
+                                        })
                                     }
-                                    ReplConfCommandParameter::ListeningPort(_port) => {
-                                        let _ = respond_to.send(Some(vec![
-                                            (RespValue::SimpleString("OK".to_string())),
-                                        ]));
+                                    Some(ClientListFilter::Ids(ids)) => {
+                                        if ids.iter().any(|id| *id <= 0) {
+                                            Err(RedisError::InvalidClientId)
+                                        } else {
+                                            Ok(clients
+                                                .iter()
+                                                .filter(|client| match client.host_id {
+                                                    HostId::Host { id, .. } => {
+                                                        ids.contains(&(id as i64))
+                                                    }
+                                                    HostId::Myself => false,
+                                                })
+                                                .collect())
+                                        }
+                                    }
+                                };
 
-                                        Ok(())
+                                let reply = match listed {
+                                    Ok(listed) => {
+                                        let list: String = listed
+                                            .into_iter()
+                                            .map(|client| self.client_list_line(client))
+                                            .collect();
+                                        RespValue::BulkString(Some(list.into()))
                                     }
-                                }
+                                    Err(e) => e.into(),
+                                };
+
+                                let _ = respond_to.send(Some(vec![reply]));
+
+                                Ok(())
                             }
+                            Ok(RedisCommand::Multi) => {
+                                let client = self.clients.entry(host_id).or_default();
 
-                            Ok((_, RedisCommand::Psync(_replication_id, offset))) => {
-                                // ignore the _replication_id for now. There are actually two of them:
-                                // https://redis.io/docs/latest/operate/oss_and_stack/management/replication/#replication-id-explained
+                                let reply = if client.transaction.is_some() {
+                                    RedisError::NestedMulti.into()
+                                } else {
+                                    client.transaction = Some(Transaction::default());
+                                    RespValue::SimpleString("OK".to_string())
+                                };
 
-                                debug!("PSYNC: Processing replication data for {host_id}");
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                // initialize the reply of Vec<Vec<u8>>
-                                //
-                                let mut reply: Vec<RespValue> = Vec::new();
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Monitor) => {
+                                // the OK goes ahead of the first command it shows, then the
+                                // connection only gets those
+                                if let Some(output) = push_tx {
+                                    if output
+                                        .send(RespValue::SimpleString("OK".to_string()))
+                                        .is_ok()
+                                    {
+                                        self.command_feed.spawn_monitor(output);
+                                    }
+                                    let _ = respond_to.send(None);
+                                } else {
+                                    let _ = respond_to.send(Some(vec![RespValue::SimpleString(
+                                        "OK".to_string(),
+                                    )]));
+                                }
 
-                                // Check if we've seen this replica before.
-                                // TODO: move the common sections that always get executed out of the if let Some
-                                // conditional.
-                                if let Some(replication_section_data) =
-                                    replication_actor_handle.get_value(host_id.clone()).await
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Discard) => {
+                                let reply = match self
+                                    .clients
+                                    .entry(host_id)
+                                    .or_default()
+                                    .transaction
+                                    .take()
                                 {
-                                    debug!("Known replica {replication_section_data}, proceeding.");
-                                } else {
-                                    warn!("Replica not seen before, adding.");
-                                    let mut replication_data = ReplicationSectionData::new();
+                                    Some(_) => RespValue::SimpleString("OK".to_string()),
+                                    None => RedisError::DiscardWithoutMulti.into(),
+                                };
 
-                                    // this is a replica we've not seen before, so let's initialize everything.
-                                    replication_data.role = Some(ServerRole::Slave);
-                                    replication_data.master_replid = replication_actor_handle
-                                        .get_value(HostId::Myself)
-                                        .await
-                                        .expect("This should never fail because we always know our own replication ID.")
-                                        .master_replid;
-                                    replication_data.master_repl_offset = Some(0);
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                    // store the replica's values in the replication actor
-                                    replication_actor_handle
-                                        .update_value(host_id.clone(), replication_data)
-                                        .await;
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Exec) => {
+                                let transaction = self
+                                    .clients
+                                    .entry(host_id.clone())
+                                    .or_default()
+                                    .transaction
+                                    .take();
 
-                                    // let _ = respond_to.send(None);
-                                }
+                                let reply = match transaction {
+                                    None => RedisError::ExecWithoutMulti.into(),
+                                    Some(Transaction { aborted: true, .. }) => {
+                                        RedisError::ExecAbort.into()
+                                    }
+                                    Some(Transaction { queued, .. }) => {
+                                        let mut replies = Vec::with_capacity(queued.len());
 
-                                // check if the replica is asking for a full resync
-                                if offset == -1 {
-                                    // initial fullresync reply
-                                    debug!("Full resync triggered with offset {}", offset);
+                                        // Each queued request goes through this same function, so it is checked, run and
+                                        // propagated exactly as if it had been sent on its own. Nothing else gets in between,
+                                        // the actor only picks up its next message once EXEC is done.
+                                        //
+                                        // A transaction can't block: without a wait_sleep_tx there is nowhere to park a reply,
+                                        // so blocking commands answer with what they have right now, same as redis.
+                                        for request in queued {
+                                            let (send, recv) = oneshot::channel();
 
-                                    // Master got PSYNC ? -1
-                                    // replica is expecting +FULLRESYNC <REPL_ID> 0\r\n back
-                                    reply.push(RespValue::SimpleString(format!(
-                                        "FULLRESYNC {} 0",
-                                        replication_actor_handle
-                                        .get_value(HostId::Myself)
-                                        .await
-                                        .expect("This should never fail because the master knows about itself")
-                                        .master_replid.expect("We should know our own replid"),
-                                    )));
-
-                                    // master will then send a RDB file of its current state to the replica.
-                                    // The replica is expected to load the file into memory, replacing its current state.
-                                    let rdb_file_contents = config_command_actor_handle
-                                        .get_rdb()
-                                        .await
-                                        .context("Unable to load RDB file into memory")?;
+                                            let msg = ProcessorActorMessage::Process {
+                                                request,
+                                                set_command_actor_handle: set_command_actor_handle
+                                                    .clone(),
+                                                config_command_actor_handle:
+                                                    config_command_actor_handle.clone(),
+                                                replication_actor_handle: replication_actor_handle
+                                                    .clone(),
+                                                failpoint_actor_handle: failpoint_actor_handle
+                                                    .clone(),
+                                                pubsub_actor_handle: pubsub_actor_handle.clone(),
+                                                clients_actor_handle: clients_actor_handle.clone(),
+                                                host_id: host_id.clone(),
+                                                master_tx: master_tx.clone(),
+                                                replica_tx: replica_tx.clone(),
+                                                client_or_replica_tx: client_or_replica_tx.clone(),
+                                                respond_to: send,
+                                                wait_sleep_tx: None,
+                                                blocked_tx: None,
+                                                push_tx: push_tx.clone(),
+                                            };
 
-                                    tracing::debug!("For client {:?} storing offset 0", host_id);
+                                            Box::pin(self.handle_message(msg)).await?;
 
-                                    // update the offset
-                                    replication_actor_handle.reset_replica_offset(host_id).await;
+                                            replies.extend(recv.await?.unwrap_or_default());
+                                        }
 
-                                    // add the rdb file to the reply, at this point reply has 2 elements, each Vec<u8>
-                                    reply.push(RespValue::Rdb(rdb_file_contents));
-                                }
+                                        RespValue::Array(replies)
+                                    }
+                                };
 
-                                let _ = respond_to.send(Some(reply));
+                                let _ = respond_to.send(Some(vec![reply]));
 
                                 Ok(())
-                            } // end of psync
-                            Ok((_, RedisCommand::Wait(numreplicas, timeout))) => {
-                                debug!("Processing WAIT {} {}", numreplicas, timeout);
+                            }
+                            Ok(RedisCommand::Save) => {
+                                let reply = match config_command_actor_handle
+                                    .save_rdb(set_command_actor_handle.clone())
+                                    .await
+                                {
+                                    Ok(()) => RespValue::SimpleString("OK".to_string()),
+                                    Err(e) => {
+                                        error!("SAVE failed: {:#}", e);
+                                        RedisError::Internal(format!("{:#}", e)).into()
+                                    }
+                                };
 
-                                let replconf_getack_star: RespValue =
-                                    RespValue::array_from_slice(&["REPLCONF", "GETACK", "*"]);
-                                // let _ = replica_tx.send(replconf_getack_star.clone())?;
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                let current_master_offset = replication_actor_handle
-                                    .get_value(HostId::Myself)
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Bgsave) => {
+                                // https://redis.io/commands/bgsave/
+                                let reply = match config_command_actor_handle
+                                    .bgsave_rdb(set_command_actor_handle.clone())
                                     .await
-                                    .expect("Expected to always have self information.")
-                                    .master_repl_offset
-                                    .expect("Master always has offset.");
-
-                                // get the replica count
-                                let replicas_in_sync = replication_actor_handle
-                                    .get_synced_replica_count(current_master_offset) // -37 is REPLCONF GETACK *
-                                    .await;
+                                {
+                                    Ok(true) => RespValue::SimpleString(
+                                        "Background saving started".to_string(),
+                                    ),
+                                    Ok(false) => RedisError::BgsaveInProgress.into(),
+                                    Err(e) => {
+                                        error!("BGSAVE failed: {:#}", e);
+                                        RedisError::Internal(format!("{:#}", e)).into()
+                                    }
+                                };
 
-                                tracing::info!("Target number of replicas: {numreplicas} and we have {replicas_in_sync} replicas in sync.");
+                                let _ = respond_to.send(Some(vec![reply]));
 
-                                // let's implement the wait command
-                                // https://redis.io/commands/wait/
-                                //
-                                // The command takes two parameters:
-                                // 1. numreplicas: The number of replicas that must be connected and in sync.
-                                // 2. timeout: The maximum number of milliseconds to wait for the replicas to be connected and in sync.
-                                //
-                                // detailed OG implementation: https://github.com/redis/redis/blob/unstable/src/replication.c#L3548
-                                if replicas_in_sync >= numreplicas {
-                                    // we can return immediately
-                                    let _ = respond_to.send(Some(vec![
-                                        (RespValue::Integer(replicas_in_sync as i64)),
-                                    ]));
-                                } else {
-                                    let _ = replica_tx.send(replconf_getack_star)?;
+                                Ok(())
+                            }
+                            Ok(RedisCommand::Shutdown(shutdown)) => {
+                                // like redis, a save that fails keeps the server up
+                                if shutdown.save {
+                                    if let Err(e) = config_command_actor_handle
+                                        .save_rdb(set_command_actor_handle.clone())
+                                        .await
+                                    {
+                                        error!("SHUTDOWN could not save: {:#}", e);
 
-                                    // let start_time = Instant::now();
+                                        let _ = respond_to
+                                            .send(Some(vec![RedisError::ShutdownFailed.into()]));
 
-                                    let duration = Duration::from_millis(timeout.try_into()?);
+                                        return Ok(());
+                                    }
+                                }
 
-                                    let _sleeping_handle = sleeping_task(
-                                        wait_sleep_tx.expect(
-                                            "IF we are processing WAIT this must be present.",
-                                        ),
-                                        duration,
-                                        current_master_offset,
-                                    )
-                                    .await;
+                                let timeout = config_command_actor_handle
+                                    .get_value(ConfigCommandParameter::ShutdownTimeout)
+                                    .await?
+                                    .and_then(|timeout| timeout.parse().ok())
+                                    .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
 
-                                    // yielding back to tokio
+                                self.shutting_down = true;
 
-                                    // sleeping_handle.await?;
+                                // The ACKs come in through here, so the wait can't hold up the processor.
+                                let replication_actor_handle = replication_actor_handle.clone();
+                                let replica_tx = replica_tx.clone();
 
-                                    // let replicas_in_sync =
-                                    //     replication_actor_handle.get_synced_replica_count().await;
+                                tokio::spawn(async move {
+                                    if !shutdown.now {
+                                        if let Err(e) = drain_replicas(
+                                            &replication_actor_handle,
+                                            &replica_tx,
+                                            Duration::from_secs(timeout),
+                                        )
+                                        .await
+                                        {
+                                            error!("Unable to wait for the replicas: {:#}", e);
+                                        }
+                                    }
 
-                                    //     debug!("After REPLCONF ACK we have {replicas_in_sync} in sync replicas.");
+                                    info!("Shutting down.");
 
-                                    let _ = respond_to.send(None); // no replies at this point, the sleeping_task fxn will reply
-                                }
+                                    // never answered, the connection closes with the process
+                                    let _respond_to = respond_to;
+                                    std::process::exit(0);
+                                });
 
                                 Ok(())
                             }
@@ -660,16 +4140,34 @@ impl ProcessorActor {
 
                                 Err(anyhow!("Unsupported command."))
                             }
+                        };
+
+                        if outcome.is_ok() && set_command_actor_handle.dirty() != dirty {
+                            self.propagate(&replica_tx, db, request, flags)?;
                         }
+
+                        outcome
                     }
                     RespValue::BulkString(_) => todo!(),
-                    RespValue::Rdb(rdb) => {
+                    // Requests are always arrays, RESP3 aggregate and scalar types only flow server -> client.
+                    RespValue::Double(_)
+                    | RespValue::Boolean(_)
+                    | RespValue::BigNumber(_)
+                    | RespValue::VerbatimString(..)
+                    | RespValue::Map(_)
+                    | RespValue::Set(_)
+                    | RespValue::Push(_)
+                    | RespValue::Attribute(..) => {
+                        let _ = respond_to.send(Some(vec![RedisError::NotAnArray.into()]));
+                        Ok(())
+                    }
+                    RespValue::Rdb(rdb) | RespValue::RdbEof(_, rdb) => {
                         debug!("Received RDB file: {:?}", rdb);
 
                         // Import it into the config actor
                         config_command_actor_handle
-                            .import_config(set_command_actor_handle.clone(), Some(rdb), expire_tx)
-                            .await;
+                            .import_config(set_command_actor_handle.clone(), Some(rdb))
+                            .await?;
 
                         let _ = respond_to.send(None);
 
@@ -677,6 +4175,413 @@ impl ProcessorActor {
                     }
                 }
             }
+            ProcessorActorMessage::Authenticated {
+                host_id,
+                username,
+                verified,
+                hello,
+                push_tx,
+                respond_to,
+            } => {
+                self.authenticated_as(host_id, username, verified, hello, push_tx, respond_to);
+
+                Ok(())
+            }
+            ProcessorActorMessage::Disconnect { host_id } => {
+                debug!("Forgetting client state for {:?}", host_id);
+                self.clients.remove(&host_id);
+                self.fast_path.write().unwrap().remove(&host_id);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+// The kind of confirmation a (P)SUBSCRIBE or (P)UNSUBSCRIBE is answered with, "psubscribe" for
+// a pattern's "subscribe".
+fn subscription_reply(kind: SubscriptionKind, reply: &str) -> String {
+    match kind {
+        SubscriptionKind::Channel => reply.to_string(),
+        SubscriptionKind::Pattern => format!("p{}", reply),
+    }
+}
+
+// The replication ID and offset a +FULLRESYNC hands a replica, the master's own.
+async fn master_replid_and_offset(
+    replication_actor_handle: &ReplicationActorHandle,
+) -> Result<(String, i64), RedisError> {
+    let myself = replication_actor_handle
+        .get_value(HostId::Myself)
+        .await?
+        .expect("This should never fail because the master knows about itself");
+
+    Ok((
+        myself.master_replid.expect("We should know our own replid"),
+        myself.master_repl_offset.unwrap_or(0),
+    ))
+}
+
+// Where a replica's offset starts after a full resync, until its first ACK. Nothing is set for a
+// replica that went away in the meantime.
+async fn set_replica_offset(
+    replication_actor_handle: &ReplicationActorHandle,
+    host_id: HostId,
+    offset: i64,
+) -> Result<(), RedisError> {
+    if replication_actor_handle
+        .get_value(host_id.clone())
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    replication_actor_handle
+        .reset_replica_offset(host_id.clone())
+        .await?;
+
+    let mut replication_data = ReplicationSectionData::new();
+    replication_data.master_repl_offset = Some(offset);
+
+    replication_actor_handle
+        .update_value(host_id, replication_data)
+        .await
+}
+
+// Whether dir and dbfilename say where SAVE goes.
+async fn rdb_file_configured(config: &ConfigCommandActorHandle) -> Result<bool, RedisError> {
+    Ok(config
+        .get_value(ConfigCommandParameter::Dir)
+        .await?
+        .is_some()
+        && config
+            .get_value(ConfigCommandParameter::DbFilename)
+            .await?
+            .is_some())
+}
+
+// The only place that sends on replica_tx. Every frame sent here but a full resync's
+// +FULLRESYNC and RDB counts towards the master's replication offset (see update_master_offset), so nothing gets onto the
+// stream by accident.
+fn feed_replicas(
+    replica_tx: &broadcast::Sender<RespValue>,
+    frame: RespValue,
+) -> anyhow::Result<()> {
+    let _active_client_count = replica_tx.send(frame)?;
+
+    Ok(())
+}
+
+// What SHUTDOWN does before the process exits: a PING to every replica behind everything already on
+// the stream, and a REPLCONF GETACK * for them to say how far they've got. Once a replica has
+// acknowledged the PING it has applied everything before it. Gives up after timeout, whoever
+// hasn't caught up by then is left behind.
+async fn drain_replicas(
+    replication_actor_handle: &ReplicationActorHandle,
+    replica_tx: &broadcast::Sender<RespValue>,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let replicas = replication_actor_handle
+        .get_connected_replica_count()
+        .await?;
+    if replicas == 0 {
+        return Ok(());
+    }
+
+    let (_, offset) = master_replid_and_offset(replication_actor_handle).await?;
+
+    let ping = RespValue::array_from_slice(&["PING"]);
+    let target_offset = offset + ping.to_encoded_string()?.len() as i64;
+
+    feed_replicas(replica_tx, ping)?;
+    feed_replicas(
+        replica_tx,
+        RespValue::array_from_slice(&["REPLCONF", "GETACK", "*"]),
+    )?;
+
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let caught_up = replication_actor_handle
+            .get_synced_replica_count(target_offset)
+            .await?;
+
+        if caught_up >= replicas {
+            info!(
+                "All {} replicas caught up at offset {}.",
+                replicas, target_offset
+            );
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            warn!(
+                "Only {} of {} replicas caught up within shutdown-timeout.",
+                caught_up, replicas
+            );
+            return Ok(());
+        }
+
+        sleep_until(Instant::now() + SHUTDOWN_POLL_INTERVAL).await;
+    }
+}
+
+// The indexes of a value of `len` bytes or elements GETRANGE or LRANGE start end covers, redis'
+// rules: negative indexes count from the end, what falls outside the value is left out. None when
+// that leaves nothing.
+fn index_range(len: usize, start: i64, end: i64) -> Option<std::ops::RangeInclusive<usize>> {
+    let len = len as i64;
+
+    if len == 0 || (start < 0 && end < 0 && start > end) {
+        return None;
+    }
+
+    let from_end = |index: i64| {
+        if index < 0 {
+            (len + index).max(0)
+        } else {
+            index
+        }
+    };
+    let (start, end) = (from_end(start), from_end(end).min(len - 1));
+
+    (start <= end).then_some(start as usize..=end as usize)
+}
+
+// A stream entry the way XRANGE and XREAD reply with it, its ID and then its fields and values.
+fn stream_entry(id: StreamId, fields: &StreamFields) -> RespValue {
+    RespValue::Array(vec![
+        RespValue::BulkString(Some(id.to_string().into())),
+        RespValue::Array(
+            fields
+                .iter()
+                .flat_map(|(field, value)| {
+                    [field, value].map(|s| RespValue::BulkString(Some(s.clone().into())))
+                })
+                .collect(),
+        ),
+    ])
+}
+
+// What popping from end is called, the command and its keyspace event.
+fn pop_name(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "lpop",
+        ListEnd::Right => "rpop",
+    }
+}
+
+// The keyspace events of a pop from key, del too if it took the last element.
+fn notify_popped(
+    set_command_actor_handle: &SetCommandActorHandle,
+    db: usize,
+    end: ListEnd,
+    key: &str,
+) -> Result<(), RedisError> {
+    set_command_actor_handle.notify(db, 'l', pop_name(end), key);
+
+    if set_command_actor_handle.count_existing(db, &[key.to_string()])? == 0 {
+        set_command_actor_handle.notify(db, 'g', "del", key);
+    }
+
+    Ok(())
+}
+
+// The reply to a read of a hash or a list: what read made of it, missing if there is no such key
+// and WRONGTYPE if the key holds something else. Any other error fails the request.
+fn collection_reply(
+    read: Result<Option<RespValue>, RedisError>,
+    missing: RespValue,
+) -> Result<RespValue, RedisError> {
+    match read {
+        Ok(reply) => Ok(reply.unwrap_or(missing)),
+        Err(RedisError::WrongType) => Ok(RedisError::WrongType.into()),
+        Err(e) => Err(e),
+    }
+}
+
+// The members ZRANGE and ZRANGEBYSCORE reply with. WITHSCORES puts each score right after its
+// member, or pairs them up as [member, score] arrays on RESP3 like redis 7.
+fn scored_members<'a>(
+    members: impl Iterator<Item = (&'a str, f64)>,
+    withscores: bool,
+    protocol: RespProtocol,
+) -> RespValue {
+    let member = |member: &str| RespValue::BulkString(Some(member.to_string().into()));
+
+    RespValue::Array(match (withscores, protocol) {
+        (false, _) => members.map(|(name, _)| member(name)).collect(),
+        (true, RespProtocol::Resp2) => members
+            .flat_map(|(name, score)| [member(name), RespValue::Double(score)])
+            .collect(),
+        (true, RespProtocol::Resp3) => members
+            .map(|(name, score)| RespValue::Array(vec![member(name), RespValue::Double(score)]))
+            .collect(),
+    })
+}
+
+// The milliseconds a key has left for TTL and PTTL, -2 if there is no such key and -1 if it has
+// no deadline.
+fn remaining_ttl(
+    set_command_actor_handle: &SetCommandActorHandle,
+    db: usize,
+    key: &str,
+) -> Result<i64, RedisError> {
+    Ok(match set_command_actor_handle.deadline(db, key)? {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(deadline)) => deadline.saturating_sub(set_command_actor_handle.now_ms()) as i64,
+    })
+}
+
+// What the replicas are sent for a command that sets a TTL: the absolute deadline the master
+// worked out, so a replica expires the key when its master does however late the write reaches
+// it, and a restarted one reading it back doesn't give the key a fresh TTL. Like redis, SET goes
+// out with PXAT in place of EX, PX or EXAT, and EXPIRE, PEXPIRE and EXPIREAT as PEXPIREAT.
+// Everything else goes out as sent.
+fn with_absolute_ttl(request: RespValue, command: &RedisCommand) -> RespValue {
+    if let RedisCommand::Expire(key, deadline) = command {
+        return RespValue::array_from_slice(&["PEXPIREAT", key, &deadline.to_string()]);
+    }
+
+    let deadline = match command {
+        RedisCommand::Set(set_parameters) => set_parameters
+            .expire
+            .and_then(|expire| expire.deadline_ms()),
+        _ => None,
+    };
+
+    let (Some(deadline), RespValue::Array(args)) = (deadline, &request) else {
+        return request;
+    };
+
+    // SET key value, then its options, of which the TTL one and its argument are replaced
+    let mut args = args.iter().cloned();
+    let mut rewritten: Vec<RespValue> = args.by_ref().take(3).collect();
+
+    while let Some(arg) = args.next() {
+        let ttl = matches!(&arg, RespValue::BulkString(Some(option))
+            if ["EX", "PX", "EXAT", "PXAT"]
+                .iter()
+                .any(|ttl| option.eq_ignore_ascii_case(ttl.as_bytes())));
+
+        if ttl {
+            args.next();
+        } else {
+            rewritten.push(arg);
+        }
+    }
+
+    rewritten.push(RespValue::BulkString(Some("PXAT".into())));
+    rewritten.push(RespValue::BulkString(Some(deadline.to_string().into())));
+
+    RespValue::Array(rewritten)
+}
+
+// DEBUG OBJECT's line for a value of any type. There are no objects to point at or to count
+// references to, so those two are fixed, serializedlength is what SAVE would write for it.
+// https://redis.io/docs/latest/commands/debug/
+fn debug_object(object: &Object) -> Result<String, RedisError> {
+    let (encoding, serialized_length) = match object {
+        Object::String(value) => (string_encoding(value), serialized_length(value)?),
+        Object::Collection(collection) => (
+            collection_encoding(collection),
+            serialized_collection_length(collection)?,
+        ),
+    };
+
+    Ok(format!(
+        "Value at:0x0 refcount:1 encoding:{} serializedlength:{}",
+        encoding, serialized_length
+    ))
+}
+
+// Same rules as redis: a long integer written the way it would print, or up to 44 bytes embedded
+// in the object header.
+fn string_encoding(value: &[u8]) -> &'static str {
+    if std::str::from_utf8(value).is_ok_and(is_integer) {
+        "int"
+    } else if value.len() <= 44 {
+        "embstr"
+    } else {
+        "raw"
+    }
+}
+
+// The encoding redis 7.2 would keep a collection in with its default limits: a listpack while it
+// is small (hash-, set- and zset-max-listpack-entries 128 and -value 64, list-max-listpack-size -2
+// being 8kb), an intset for a set of at most 512 integers (set-max-intset-entries).
+fn collection_encoding(collection: &Collection) -> &'static str {
+    const MAX_LISTPACK_ENTRIES: usize = 128;
+    const MAX_LISTPACK_VALUE: usize = 64;
+    const MAX_LISTPACK_LIST_BYTES: usize = 8 * 1024;
+    const MAX_INTSET_ENTRIES: usize = 512;
+
+    fn small<'a>(len: usize, mut values: impl Iterator<Item = &'a str>) -> bool {
+        len <= MAX_LISTPACK_ENTRIES && values.all(|value| value.len() <= MAX_LISTPACK_VALUE)
+    }
+
+    match collection {
+        Collection::Hash(hash)
+            if small(
+                hash.len(),
+                hash.iter()
+                    .flat_map(|(field, value)| [field.as_str(), value]),
+            ) =>
+        {
+            "listpack"
+        }
+        Collection::Hash(_) => "hashtable",
+        Collection::List(list)
+            if list.iter().map(String::len).sum::<usize>() <= MAX_LISTPACK_LIST_BYTES =>
+        {
+            "listpack"
+        }
+        Collection::List(_) => "quicklist",
+        Collection::Set(set)
+            if set.len() <= MAX_INTSET_ENTRIES && set.iter().all(|member| is_integer(member)) =>
+        {
+            "intset"
+        }
+        Collection::Set(set) if small(set.len(), set.iter().map(String::as_str)) => "listpack",
+        Collection::Set(_) => "hashtable",
+        Collection::SortedSet(sorted_set)
+            if small(
+                sorted_set.len(),
+                sorted_set.iter().map(|(member, _)| member),
+            ) =>
+        {
+            "listpack"
+        }
+        Collection::SortedSet(_) => "skiplist",
+        Collection::Stream(_) => "stream",
+    }
+}
+
+// Whether value is a long integer written the way it would print.
+fn is_integer(value: &str) -> bool {
+    value
+        .parse::<i64>()
+        .is_ok_and(|number| number.to_string() == value)
+}
+
+impl Supervised for ProcessorActor {
+    const NAME: &'static str = "processor";
+
+    async fn serve(&mut self) {
+        if let Err(e) = self.run().await {
+            error!("The request processor stopped: {:#}", e);
         }
     }
+
+    // A request that panics is caught on its own in run(), what gets here panicked between two,
+    // sending a diskless sync's snapshot. Every connection keeps the database it SELECTed and its
+    // open MULTI, only the replication stream starts over with a SELECT, like after a request.
+    fn restart(self) -> Option<Self> {
+        Some(Self {
+            replication_db: None,
+            ..self
+        })
+    }
 }
@@ -0,0 +1,315 @@
+use crate::{
+    actors::{
+        messages::{HostId, PubSubActorMessage, PubSubStats, SubscriptionCounts, SubscriptionKind},
+        supervisor::Supervised,
+    },
+    handlers::clients::OutputQueue,
+    resp::value::RespValue,
+    utils::glob_match,
+};
+
+use std::collections::{HashMap, HashSet};
+
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, warn};
+
+// A client with at least one subscription.
+struct Subscriber {
+    // the connection's output buffer, published messages wait here until they are written out
+    queue: OutputQueue,
+
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+}
+
+impl Subscriber {
+    fn names(&mut self, kind: SubscriptionKind) -> &mut HashSet<String> {
+        match kind {
+            SubscriptionKind::Channel => &mut self.channels,
+            SubscriptionKind::Pattern => &mut self.patterns,
+        }
+    }
+
+    fn counts(&self) -> SubscriptionCounts {
+        SubscriptionCounts {
+            channels: self.channels.len(),
+            patterns: self.patterns.len(),
+        }
+    }
+}
+
+/// Keeps track of who is subscribed to what and fans PUBLISH out to them.
+///
+/// Messages are never waited on: a subscriber that lets queue_limit messages pile up is dropped and
+/// its connection told to close, redis's client-output-buffer-limit for pubsub clients. One slow
+/// consumer can't hold up PUBLISH or grow the server's memory without bound.
+pub struct PubSubActor {
+    // The receiver for incoming messages
+    receiver: mpsc::Receiver<PubSubActorMessage>,
+
+    // channel name -> the clients subscribed to it
+    channels: HashMap<String, HashSet<HostId>>,
+
+    // pattern -> the clients subscribed to it, every channel it matches is published to them
+    patterns: HashMap<String, HashSet<HostId>>,
+
+    subscribers: HashMap<HostId, Subscriber>,
+
+    // how many messages a subscriber may have waiting before it is dropped
+    queue_limit: usize,
+
+    // the clients dropped for falling behind, every connection listens for its own id
+    evicted_tx: broadcast::Sender<HostId>,
+}
+
+impl PubSubActor {
+    // Constructor for the actor, nobody is subscribed to begin with.
+    pub fn new(
+        receiver: mpsc::Receiver<PubSubActorMessage>,
+        queue_limit: usize,
+        evicted_tx: broadcast::Sender<HostId>,
+    ) -> Self {
+        Self {
+            receiver,
+            channels: HashMap::new(),
+            patterns: HashMap::new(),
+            subscribers: HashMap::new(),
+            queue_limit,
+            evicted_tx,
+        }
+    }
+
+    // Run the actor
+    pub async fn run(&mut self) {
+        // Continuously receive messages and handle them
+        while let Some(msg) = self.receiver.recv().await {
+            self.handle_message(msg);
+        }
+    }
+
+    // Handle a message.
+    pub fn handle_message(&mut self, msg: PubSubActorMessage) {
+        match msg {
+            PubSubActorMessage::Subscribe {
+                host_id,
+                kind,
+                names,
+                queue,
+                respond_to,
+            } => {
+                let subscriber =
+                    self.subscribers
+                        .entry(host_id.clone())
+                        .or_insert_with(|| Subscriber {
+                            queue,
+                            channels: HashSet::new(),
+                            patterns: HashSet::new(),
+                        });
+                let index = match kind {
+                    SubscriptionKind::Channel => &mut self.channels,
+                    SubscriptionKind::Pattern => &mut self.patterns,
+                };
+
+                let counts = names
+                    .into_iter()
+                    .map(|name| {
+                        subscriber.names(kind).insert(name.clone());
+                        index.entry(name).or_default().insert(host_id.clone());
+
+                        subscriber.counts()
+                    })
+                    .collect();
+
+                let _ = respond_to.send(counts);
+            }
+
+            PubSubActorMessage::Unsubscribe {
+                host_id,
+                kind,
+                names,
+                respond_to,
+            } => {
+                let names = if names.is_empty() {
+                    self.subscribers
+                        .get_mut(&host_id)
+                        .map(|subscriber| subscriber.names(kind).iter().cloned().collect())
+                        .unwrap_or_default()
+                } else {
+                    names
+                };
+
+                let remaining = names
+                    .into_iter()
+                    .map(|name| {
+                        self.unsubscribe(&host_id, kind, &name);
+
+                        let counts = self
+                            .subscribers
+                            .get(&host_id)
+                            .map(Subscriber::counts)
+                            .unwrap_or_default();
+
+                        (name, counts)
+                    })
+                    .collect();
+
+                let _ = respond_to.send(remaining);
+            }
+
+            PubSubActorMessage::Publish {
+                channel,
+                message,
+                respond_to,
+            } => {
+                // the subscribers to the channel get a message, the ones to a pattern matching it
+                // a pmessage for every such pattern
+                let mut deliveries: Vec<(&HostId, RespValue)> = Vec::new();
+
+                for host_id in self.channels.get(&channel).into_iter().flatten() {
+                    deliveries.push((
+                        host_id,
+                        RespValue::Push(vec![
+                            RespValue::BulkString(Some("message".into())),
+                            RespValue::BulkString(Some(channel.clone().into())),
+                            RespValue::BulkString(Some(message.clone().into())),
+                        ]),
+                    ));
+                }
+
+                for (pattern, host_ids) in &self.patterns {
+                    if !glob_match(pattern, &channel, false) {
+                        continue;
+                    }
+
+                    for host_id in host_ids {
+                        deliveries.push((
+                            host_id,
+                            RespValue::Push(vec![
+                                RespValue::BulkString(Some("pmessage".into())),
+                                RespValue::BulkString(Some(pattern.clone().into())),
+                                RespValue::BulkString(Some(channel.clone().into())),
+                                RespValue::BulkString(Some(message.clone().into())),
+                            ]),
+                        ));
+                    }
+                }
+
+                let mut receivers = 0;
+                let mut evicted = HashSet::new();
+                let mut gone = HashSet::new();
+
+                for (host_id, frame) in deliveries {
+                    if evicted.contains(host_id) || gone.contains(host_id) {
+                        continue;
+                    }
+
+                    let queue = &self.subscribers[host_id].queue;
+
+                    // what counts is everything already waiting for the client, replies included
+                    if queue.pending() >= self.queue_limit {
+                        evicted.insert(host_id.clone());
+                        continue;
+                    }
+
+                    match queue.send(frame) {
+                        Ok(()) => receivers += 1,
+                        Err(_) => {
+                            gone.insert(host_id.clone());
+                        }
+                    }
+                }
+
+                for host_id in evicted {
+                    warn!(
+                        "Dropping subscriber {:?}, it has {} messages waiting",
+                        host_id, self.queue_limit
+                    );
+                    self.remove_subscriber(&host_id);
+                    let _ = self.evicted_tx.send(host_id);
+                }
+
+                for host_id in gone {
+                    self.remove_subscriber(&host_id);
+                }
+
+                let _ = respond_to.send(receivers);
+            }
+
+            PubSubActorMessage::Stats { respond_to } => {
+                let _ = respond_to.send(PubSubStats {
+                    clients: self.subscribers.len(),
+                    channels: self.channels.len(),
+                    patterns: self.patterns.len(),
+                });
+            }
+
+            PubSubActorMessage::Disconnect { host_id } => {
+                debug!("Dropping the subscriptions of {:?}", host_id);
+                self.remove_subscriber(&host_id);
+            }
+        }
+    }
+
+    // the channels or the patterns, and who is subscribed to each
+    fn index(&mut self, kind: SubscriptionKind) -> &mut HashMap<String, HashSet<HostId>> {
+        match kind {
+            SubscriptionKind::Channel => &mut self.channels,
+            SubscriptionKind::Pattern => &mut self.patterns,
+        }
+    }
+
+    fn unsubscribe(&mut self, host_id: &HostId, kind: SubscriptionKind, name: &str) {
+        let index = self.index(kind);
+
+        if let Some(subscribers) = index.get_mut(name) {
+            subscribers.remove(host_id);
+            if subscribers.is_empty() {
+                index.remove(name);
+            }
+        }
+
+        if let Some(subscriber) = self.subscribers.get_mut(host_id) {
+            subscriber.names(kind).remove(name);
+            if subscriber.counts().total() == 0 {
+                self.subscribers.remove(host_id);
+            }
+        }
+    }
+
+    fn remove_subscriber(&mut self, host_id: &HostId) {
+        let Some(mut subscriber) = self.subscribers.remove(host_id) else {
+            return;
+        };
+
+        for kind in [SubscriptionKind::Channel, SubscriptionKind::Pattern] {
+            let index = self.index(kind);
+
+            for name in subscriber.names(kind).drain() {
+                if let Some(subscribers) = index.get_mut(&name) {
+                    subscribers.remove(host_id);
+                    if subscribers.is_empty() {
+                        index.remove(&name);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Supervised for PubSubActor {
+    const NAME: &'static str = "pubsub";
+
+    async fn serve(&mut self) {
+        self.run().await
+    }
+
+    // The subscriptions are not carried over. Their connections are closed, as if they had fallen
+    // behind, rather than left waiting for messages that will never come.
+    fn restart(self) -> Option<Self> {
+        for host_id in self.subscribers.into_keys() {
+            let _ = self.evicted_tx.send(host_id);
+        }
+
+        Some(Self::new(self.receiver, self.queue_limit, self.evicted_tx))
+    }
+}
@@ -1,14 +1,14 @@
 use crate::{
-    actors::messages::ReplicatorActorMessage,
+    actors::{messages::ReplicatorActorMessage, supervisor::Supervised},
     protocol::{ReplicationSectionData, ServerRole},
 };
 
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use tokio::sync::mpsc;
 use tracing::debug;
 
-use super::messages::HostId;
+use super::messages::{ApplyBacklog, HostId, ReplicaLag};
 
 /// Handles INFO command. Receives message from the InfoCommandActorHandle and processes them accordingly.
 pub struct ReplicatorActor {
@@ -17,6 +17,12 @@ pub struct ReplicatorActor {
 
     // Note the special value of HostId::Myself that stores server's own data.
     kv_hash: HashMap<HostId, ReplicationSectionData>,
+
+    // When each replica last sent REPLCONF ACK, or became one if it hasn't yet.
+    acked_at: HashMap<HostId, Instant>,
+
+    // As a replica, how applying the master's stream keeps up.
+    apply_backlog: ApplyBacklog,
 }
 
 impl ReplicatorActor {
@@ -35,7 +41,12 @@ impl ReplicatorActor {
         // kv_hash.insert(HostId::Myself, replication_data);
 
         // Return a new actor with the given receiver and an empty key-value hash map
-        Self { receiver, kv_hash }
+        Self {
+            receiver,
+            kv_hash,
+            acked_at: HashMap::new(),
+            apply_backlog: ApplyBacklog::default(),
+        }
     }
 
     // Run the actor
@@ -136,8 +147,40 @@ impl ReplicatorActor {
                     }
                 }
 
+                if let Some(listening_port) = replication_value.listening_port {
+                    debug!("Setting listening port {listening_port} for {host_id}");
+
+                    self.kv_hash
+                        .entry(host_id.clone())
+                        .or_default()
+                        .listening_port = Some(listening_port);
+                }
+
+                if let Some(capabilities) = replication_value.capabilities {
+                    debug!("Adding capabilities {:?} for {host_id}", capabilities);
+
+                    let known = self
+                        .kv_hash
+                        .entry(host_id.clone())
+                        .or_default()
+                        .capabilities
+                        .get_or_insert_with(Vec::new);
+
+                    for capability in capabilities {
+                        if !known.contains(&capability) {
+                            known.push(capability);
+                        }
+                    }
+                }
+
                 if let Some(new_role) = replication_value.role {
                     debug!("Setting role {new_role} for {host_id}");
+
+                    // lagging from here until its first ACK
+                    if new_role == ServerRole::Slave && host_id != HostId::Myself {
+                        self.acked_at.entry(host_id.clone()).or_insert_with(Instant::now);
+                    }
+
                     match self.kv_hash.get_mut(&host_id) {
                         Some(replication_data) => {
                             // We have an entry but whether we have a role already or not, doesn't matter,
@@ -205,8 +248,9 @@ impl ReplicatorActor {
                                     target_offset,
                                     slave_offset
                                 );
-                                // ok, this replica does have an offset, let's compare
-                                if slave_offset == target_offset {
+                                // ok, this replica does have an offset, let's compare. Replicas ACK every
+                                // second on their own too, so one may well have got past the target already.
+                                if slave_offset >= target_offset {
                                     replica_count += 1;
                                 }
                             }
@@ -217,6 +261,31 @@ impl ReplicatorActor {
                 tracing::debug!("Final replica count: {replica_count}");
                 let _ = respond_to.send(replica_count);
             }
+            ReplicatorActorMessage::GetReplicas { respond_to } => {
+                // in connection order, so slave0 is the replica that connected first
+                let mut replicas: Vec<(HostId, ReplicationSectionData)> = self
+                    .kv_hash
+                    .iter()
+                    .filter(|(_, v)| v.role == Some(ServerRole::Slave))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+
+                replicas.sort_by_key(|(host_id, _)| match host_id {
+                    HostId::Host { id, .. } => *id,
+                    HostId::Myself => 0,
+                });
+
+                let _ = respond_to.send(replicas);
+            }
+            ReplicatorActorMessage::GetConnectedReplicaCount { respond_to } => {
+                let replica_count = self
+                    .kv_hash
+                    .values()
+                    .filter(|v| v.role == Some(ServerRole::Slave))
+                    .count();
+
+                let _ = respond_to.send(replica_count);
+            }
             ReplicatorActorMessage::ResetReplicaOffset { host_id } => {
                 self.kv_hash
                     .entry(host_id)
@@ -224,6 +293,77 @@ impl ReplicatorActor {
                         replication_section_data.reset_replica_offset();
                     });
             }
+            ReplicatorActorMessage::Acknowledge { host_id, offset } => {
+                self.kv_hash.entry(host_id.clone()).or_default().master_repl_offset = Some(offset);
+                self.acked_at.insert(host_id, Instant::now());
+            }
+            ReplicatorActorMessage::GetReplicaLags { respond_to } => {
+                let master_offset = self
+                    .kv_hash
+                    .get(&HostId::Myself)
+                    .and_then(|myself| myself.master_repl_offset)
+                    .unwrap_or(0);
+
+                let mut lags: Vec<(HostId, ReplicaLag)> = self
+                    .kv_hash
+                    .iter()
+                    .filter(|(_, v)| v.role == Some(ServerRole::Slave))
+                    .map(|(k, v)| {
+                        let lag = ReplicaLag {
+                            bytes: (master_offset - v.master_repl_offset.unwrap_or(0)).max(0),
+                            seconds: self
+                                .acked_at
+                                .get(k)
+                                .map_or(0, |acked_at| acked_at.elapsed().as_secs()),
+                        };
+
+                        (k.clone(), lag)
+                    })
+                    .collect();
+
+                // same order as GetReplicas
+                lags.sort_by_key(|(host_id, _)| match host_id {
+                    HostId::Host { id, .. } => *id,
+                    HostId::Myself => 0,
+                });
+
+                let _ = respond_to.send(lags);
+            }
+            ReplicatorActorMessage::RecordApply {
+                bytes,
+                queued,
+                took,
+                slow,
+            } => {
+                let took_ms = took.as_millis() as u64;
+                let backlog = &mut self.apply_backlog;
+
+                backlog.bytes = bytes;
+                backlog.queued = queued;
+                backlog.last_ms = took_ms;
+                backlog.max_ms = backlog.max_ms.max(took_ms);
+                backlog.slow += slow as u64;
+            }
+            ReplicatorActorMessage::GetApplyBacklog { respond_to } => {
+                let _ = respond_to.send(self.apply_backlog);
+            }
+            ReplicatorActorMessage::RemoveHost { host_id } => {
+                self.kv_hash.remove(&host_id);
+                self.acked_at.remove(&host_id);
+            }
         }
     }
 }
+
+impl Supervised for ReplicatorActor {
+    const NAME: &'static str = "replication";
+
+    async fn serve(&mut self) {
+        self.run().await
+    }
+
+    // A fresh actor would not know the replication id, the offset or who the replicas are.
+    fn restart(self) -> Option<Self> {
+        None
+    }
+}
@@ -1,7 +1,651 @@
 // Import necessary modules and types
-use crate::actors::messages::SetActorMessage;
-use std::collections::HashMap;
-use tokio::sync::mpsc;
+use crate::{
+    actors::{
+        messages::{
+            DatabaseSnapshot, ExpiryStats, KeyspaceEvent, KeyspaceStats, SetActorMessage,
+            SetOutcome,
+        },
+        supervisor::Supervised,
+    },
+    clock::SharedClock,
+    collections::{Collection, ListEnd, SortedSet, Stream, StreamId},
+    digest,
+    errors::RedisError,
+    eviction::MaxmemoryPolicy,
+    protocol::{SetCommandExpireOption, SetCommandSetOption, XaddId},
+    storage::{KeyValueStore, Kind, OpenStore},
+};
+use rand::{thread_rng, Rng};
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Bound,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, oneshot};
+
+// Where a key sits in SCAN order. DefaultHasher::new() always starts from the same keys,
+// so the position of a key never changes while the server runs.
+fn scan_position(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+
+    // reversed like redis cursors, which count from the high bits of the bucket index
+    hasher.finish().reverse_bits()
+}
+
+// Keys with a deadline an active expiry cycle looks at in a database before deciding whether to go on.
+const ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP: usize = 20;
+
+// The cycle moves on to the next database once no more than this percentage of a sample had expired.
+const ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE: usize = 25;
+
+// Keys with a deadline volatile-ttl looks at in each database to pick the one to evict, redis'
+// maxmemory-samples default.
+const MAXMEMORY_SAMPLES: usize = 5;
+
+// How far back expired_keys_per_sec looks.
+const EXPIRED_KEYS_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Where the keyspace sampler is in a database's keys with a deadline, the last one it looked at.
+pub(crate) type VolatileCursor = (u64, Arc<str>);
+
+/// One of the numbered databases SELECT switches between.
+#[derive(Debug)]
+pub(crate) struct Database {
+    // The keys and values, strings and collections, kept by whichever backend was configured
+    store: Box<dyn KeyValueStore>,
+
+    // Expiry deadlines for the keys that have one, as unix timestamps (same as SET and the RDB loader produce).
+    expires: HashMap<String, SetCommandExpireOption>,
+
+    // avg_ttl of INFO keyspace in milliseconds, estimated by the keyspace sampler. It only holds
+    // the read lock, so this is the one thing here it changes.
+    avg_ttl: AtomicU64,
+
+    // Every key of the store by scan_position(), so a SCAN step starts where the cursor points
+    // instead of sorting the whole keyspace. Shared, so listing every key copies no strings.
+    scan_order: BTreeSet<(u64, Arc<str>)>,
+
+    // The keys of expires in the same order, and the last one the active expiry cycle sampled.
+    // Each cycle picks up after the previous one, so every key with a deadline gets looked at.
+    volatile_order: BTreeSet<(u64, Arc<str>)>,
+    expire_cursor: Option<(u64, Arc<str>)>,
+
+    // Bytes of the keys and values in store, what maxmemory is held against. Kept up to date on
+    // every change so INFO gets used_memory without walking the keys.
+    used_memory: usize,
+}
+
+impl Database {
+    // A backend that keeps its keys may not start out empty, those go into the SCAN order too.
+    fn new(store: Box<dyn KeyValueStore>) -> Self {
+        let sizes: Vec<(Arc<str>, usize)> = store
+            .scan()
+            .map(|(key, value)| (Arc::from(key.as_ref()), value.len()))
+            .chain(
+                store
+                    .scan_collections()
+                    .map(|(key, collection)| (Arc::from(key.as_ref()), collection.memory())),
+            )
+            .collect();
+        let scan_order = sizes
+            .iter()
+            .map(|(key, _)| (scan_position(key), Arc::clone(key)))
+            .collect();
+        let used_memory = sizes.iter().map(|(key, size)| key.len() + size).sum();
+
+        Self {
+            store,
+            expires: HashMap::new(),
+            avg_ttl: AtomicU64::new(0),
+            scan_order,
+            volatile_order: BTreeSet::new(),
+            expire_cursor: None,
+            used_memory,
+        }
+    }
+
+    // Whether key has a deadline and it has passed, there or not.
+    fn past_deadline(&self, key: &str, now_ms: u64) -> bool {
+        self.expires
+            .get(key)
+            .and_then(|expire| expire.deadline_ms())
+            .is_some_and(|deadline| deadline <= now_ms)
+    }
+
+    /// The value of a key that has not reached its deadline yet, None if there is no such key or
+    /// it isn't a string.
+    pub(crate) fn live_value(&self, key: &str, now_ms: u64) -> Option<Cow<'_, [u8]>> {
+        if self.past_deadline(key, now_ms) {
+            None
+        } else {
+            self.store.get(key)
+        }
+    }
+
+    /// The collection at a key that has not reached its deadline yet, None if there is no such
+    /// key or it holds a string. Shared, so it can be read after the lock is gone.
+    pub(crate) fn live_collection(&self, key: &str, now_ms: u64) -> Option<Arc<Collection>> {
+        if self.past_deadline(key, now_ms) {
+            None
+        } else {
+            self.store.get_collection(key)
+        }
+    }
+
+    /// What a key that has not reached its deadline yet holds, None if there is no such key.
+    pub(crate) fn kind(&self, key: &str, now_ms: u64) -> Option<Kind> {
+        if self.past_deadline(key, now_ms) {
+            None
+        } else {
+            self.store.kind(key)
+        }
+    }
+
+    /// The lookup every command makes before it touches a key: whether there is a key that has
+    /// not reached its deadline yet, WrongType if it holds something other than kind.
+    pub(crate) fn typed(&self, key: &str, kind: Kind, now_ms: u64) -> Result<bool, RedisError> {
+        match self.kind(key, now_ms) {
+            Some(found) if found == kind => Ok(true),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether there is a key that has not reached its deadline yet, whatever it holds.
+    pub(crate) fn is_live(&self, key: &str, now_ms: u64) -> bool {
+        self.contains(key) && !self.past_deadline(key, now_ms)
+    }
+
+    /// Bytes used_memory counts for a key that has not reached its deadline yet, the key's own
+    /// included.
+    pub(crate) fn live_size(&self, key: &str, now_ms: u64) -> Option<usize> {
+        if self.past_deadline(key, now_ms) {
+            return None;
+        }
+
+        Some(key.len() + self.stored_size(key)?)
+    }
+
+    // Bytes used_memory counts for the value at key, whatever it holds.
+    fn stored_size(&self, key: &str) -> Option<usize> {
+        match self.store.get_collection(key) {
+            Some(collection) => Some(collection.memory()),
+            None => self.store.get(key).map(|value| value.len()),
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.store.contains(key)
+    }
+
+    // How many keys there are, whatever they hold.
+    fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    /// The deadline of a key that has not reached it yet, None if there is no such key and
+    /// Some(None) if it has no deadline.
+    pub(crate) fn live_deadline(&self, key: &str, now_ms: u64) -> Option<Option<u64>> {
+        if !self.is_live(key, now_ms) {
+            return None;
+        }
+
+        Some(
+            self.expires
+                .get(key)
+                .and_then(|expire| expire.deadline_ms()),
+        )
+    }
+
+    /// Lazy expiry: removes the key if its deadline has passed, so a read never depends on the
+    /// sleeping task for that key having run yet. Returns whether the key was removed.
+    fn expire_if_needed(&mut self, key: &str, now_ms: u64) -> bool {
+        let expired = self.contains(key) && !self.is_live(key, now_ms);
+
+        if expired {
+            tracing::debug!("Lazily expiring {}", key);
+            self.remove(key);
+        }
+
+        expired
+    }
+
+    fn set_expire(&mut self, key: &str, expire: Option<SetCommandExpireOption>) {
+        let position = (scan_position(key), Arc::from(key));
+
+        match expire {
+            Some(expire) => {
+                self.volatile_order.insert(position);
+                self.expires.insert(key.to_string(), expire)
+            }
+            None => {
+                self.volatile_order.remove(&position);
+                self.expires.remove(key)
+            }
+        };
+
+        self.store
+            .expire(key, expire.and_then(|expire| expire.deadline_ms()));
+    }
+
+    /// One SCAN step, keys in order of scan_position() starting at the cursor.
+    ///
+    /// Same idea as the reverse binary cursor redis uses over its hash table buckets, taken to a
+    /// table with a bucket per 64 bit hash: a key's place in the order depends on nothing but the
+    /// key, so no amount of growing or shrinking moves a key behind the cursor. Every key that is
+    /// there for the whole scan is returned, exactly once. With a kind, the step covers as many
+    /// keys but only returns those holding it.
+    fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        kind: Option<Kind>,
+        now_ms: u64,
+    ) -> (u64, Vec<String>) {
+        let mut keys = Vec::new();
+        let mut covered = 0;
+        let mut last = None;
+
+        let mut ahead = self
+            .scan_order
+            .range((cursor, Arc::from(""))..)
+            .filter(|(_, key)| self.is_live(key, now_ms))
+            .peekable();
+
+        while let Some((position, key)) = ahead.next() {
+            if kind.is_none_or(|kind| self.store.kind(key) == Some(kind)) {
+                keys.push(key.to_string());
+            }
+            covered += 1;
+            last = Some(*position);
+
+            // the next cursor starts right after last, so keys sharing its position go in this step
+            let full = covered >= count;
+            if full && ahead.peek().is_none_or(|(next, _)| next != position) {
+                break;
+            }
+        }
+
+        match (last, ahead.peek()) {
+            // there are keys past last, so it is below u64::MAX
+            (Some(last), Some(_)) => (last + 1, keys),
+            _ => (0, keys),
+        }
+    }
+
+    /// The next count keys with a deadline after the ones sampled last time, wrapping around to
+    /// the first once the last is behind.
+    fn sample_volatile(&mut self, count: usize) -> Vec<Arc<str>> {
+        let after = match &self.expire_cursor {
+            Some(cursor) => Bound::Excluded(cursor.clone()),
+            None => Bound::Unbounded,
+        };
+
+        let mut sample: Vec<(u64, Arc<str>)> = self
+            .volatile_order
+            .range((after, Bound::Unbounded))
+            .take(count)
+            .cloned()
+            .collect();
+
+        // only the keys up to the cursor are left to wrap around to, none of them twice
+        let wrapped = count.min(self.volatile_order.len()) - sample.len();
+        sample.extend(self.volatile_order.iter().take(wrapped).cloned());
+
+        self.expire_cursor = sample.last().cloned();
+
+        sample.into_iter().map(|(_, key)| key).collect()
+    }
+
+    /// Every key GET would find, for KEYS. Matching them is up to the caller, off the store.
+    pub(crate) fn live_keys(&self, now_ms: u64) -> Vec<Arc<str>> {
+        self.scan_order
+            .iter()
+            .filter(|(_, key)| self.is_live(key, now_ms))
+            .map(|(_, key)| Arc::clone(key))
+            .collect()
+    }
+
+    /// Every key with a deadline and the deadline, in unix milliseconds, ordered by key. Keys
+    /// past theirs are in until something removes them.
+    pub(crate) fn deadlines(&self) -> Vec<(String, u64)> {
+        let mut deadlines: Vec<(String, u64)> = self
+            .expires
+            .iter()
+            .filter_map(|(key, expire)| Some((key.clone(), expire.deadline_ms()?)))
+            .collect();
+        deadlines.sort();
+
+        deadlines
+    }
+
+    /// The DEBUG DIGEST-VALUE of a key that has not reached its deadline yet, None if there is no
+    /// such key.
+    pub(crate) fn value_digest(&self, key: &str, now_ms: u64) -> Option<u64> {
+        match self.live_collection(key, now_ms) {
+            Some(collection) => Some(digest::collection_digest(&collection)),
+            None => Some(digest::string_digest(&self.live_value(key, now_ms)?)),
+        }
+    }
+
+    /// The xor of the digests of every key GET would find, EMPTY_DIGEST if there is none.
+    pub(crate) fn keys_digest(&self, now_ms: u64) -> u64 {
+        self.scan_order
+            .iter()
+            .filter_map(|(_, key)| {
+                let value_digest = self.value_digest(key, now_ms)?;
+                let deadline = self
+                    .expires
+                    .get(key.as_ref())
+                    .and_then(|expire| expire.deadline_ms());
+
+                Some(digest::key_digest(key, value_digest, deadline))
+            })
+            .fold(digest::EMPTY_DIGEST, |digest, key_digest| {
+                digest ^ key_digest
+            })
+    }
+
+    fn insert(&mut self, key: String, value: Vec<u8>) {
+        if let Some(old) = self.stored_size(&key) {
+            self.used_memory -= key.len() + old;
+        }
+        self.used_memory += key.len() + value.len();
+
+        self.scan_order
+            .insert((scan_position(&key), Arc::from(key.as_str())));
+        self.store.set(key, value);
+    }
+
+    // insert() for a collection, in place of whatever was at key and its deadline.
+    fn insert_collection(&mut self, key: String, collection: Collection) {
+        self.remove(&key);
+
+        self.used_memory += key.len() + collection.memory();
+        self.scan_order
+            .insert((scan_position(&key), Arc::from(key.as_str())));
+        self.store.set_collection(key, collection);
+    }
+
+    // Changes the collection typed() found at key where it is, as_kind being the accessor for
+    // that kind, keeping count of what it grew or shrank by. A collection left empty is removed
+    // along with its key, deadline and all.
+    fn update_collection<C, T>(
+        &mut self,
+        key: &str,
+        as_kind: impl Fn(&mut Collection) -> Option<&mut C>,
+        update: impl FnOnce(&mut C) -> T,
+    ) -> T {
+        let (mut before, mut after, mut empty) = (0, 0, false);
+        let mut update = Some(update);
+        let mut updated = None;
+
+        self.store.update_collection(key, &mut |collection| {
+            before = collection.memory();
+            let kind = as_kind(collection).expect("typed() found this kind at key");
+            updated = update.take().map(|update| update(kind));
+            (after, empty) = (collection.memory(), collection.is_empty());
+        });
+
+        self.used_memory = self.used_memory + after - before;
+        if empty {
+            self.remove(key);
+        }
+
+        updated.expect("only called for a key holding a collection")
+    }
+
+    // KeyValueStore::update(), keeping count of what the value grew by.
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Vec<u8>)) -> bool {
+        let (mut before, mut after) = (0, 0);
+
+        let updated = self.store.update(key, &mut |value| {
+            before = value.len();
+            update(value);
+            after = value.len();
+        });
+
+        self.used_memory = self.used_memory + after - before;
+
+        updated
+    }
+
+    // Returns whether the key was there.
+    fn remove(&mut self, key: &str) -> bool {
+        let size = self.stored_size(key).map(|size| key.len() + size);
+
+        let removed = self.store.del(key);
+        if removed {
+            self.scan_order
+                .remove(&(scan_position(key), Arc::from(key)));
+            self.used_memory -= size.unwrap_or_default();
+        }
+        self.set_expire(key, None);
+
+        removed
+    }
+
+    /// A key policy may evict from this database, ranked so the lowest goes first: keys picked at
+    /// random all rank the same, volatile-ttl ranks a key by its deadline. None if the policy
+    /// leaves nothing here to evict.
+    ///
+    /// A random position in SCAN order lands on a random key, the positions being hashes.
+    fn eviction_candidate(&self, policy: MaxmemoryPolicy) -> Option<(u64, Arc<str>)> {
+        let keys = if policy.volatile() {
+            &self.volatile_order
+        } else {
+            &self.scan_order
+        };
+
+        let mut rng = thread_rng();
+        let mut random_key = || {
+            keys.range((rng.gen::<u64>(), Arc::from(""))..)
+                .next()
+                .or_else(|| keys.first())
+                .map(|(_, key)| Arc::clone(key))
+        };
+
+        match policy {
+            MaxmemoryPolicy::NoEviction => None,
+            MaxmemoryPolicy::AllkeysRandom | MaxmemoryPolicy::VolatileRandom => {
+                random_key().map(|key| (0, key))
+            }
+            MaxmemoryPolicy::VolatileTtl => (0..MAXMEMORY_SAMPLES)
+                .filter_map(|_| random_key())
+                .map(|key| {
+                    let deadline = self
+                        .expires
+                        .get(key.as_ref())
+                        .and_then(|expire| expire.deadline_ms())
+                        .unwrap_or(u64::MAX);
+                    (deadline, key)
+                })
+                .min(),
+        }
+    }
+
+    // Empty, the way FLUSHALL leaves it.
+    fn clear(&mut self) {
+        self.store.clear();
+        self.expires.clear();
+        self.avg_ttl.store(0, Ordering::Relaxed);
+        self.scan_order.clear();
+        self.volatile_order.clear();
+        self.expire_cursor = None;
+        self.used_memory = 0;
+    }
+
+    fn stats(&self, db: usize) -> KeyspaceStats {
+        let expires = self.expires.len();
+
+        KeyspaceStats {
+            db,
+            keys: self.len(),
+            expires,
+            avg_ttl: match expires {
+                0 => 0,
+                _ => self.avg_ttl.load(Ordering::Relaxed),
+            },
+        }
+    }
+
+    /// One step of the keyspace sampler: up to count keys with a deadline after `after`, the
+    /// average ttl of those still live folded into avg_ttl. Returns the last key looked at for the
+    /// next step to start after, None once the last one is behind.
+    ///
+    /// Like redis, the first sample sets the estimate and every later one moves it a fiftieth of
+    /// the way, so a pass over a large database doesn't swing it with each step.
+    pub(crate) fn sample_avg_ttl(
+        &self,
+        after: Option<VolatileCursor>,
+        count: usize,
+        now_ms: u64,
+    ) -> Option<VolatileCursor> {
+        let after = match after {
+            Some(cursor) => Bound::Excluded(cursor),
+            None => Bound::Unbounded,
+        };
+
+        let sample: Vec<&VolatileCursor> = self
+            .volatile_order
+            .range((after, Bound::Unbounded))
+            .take(count)
+            .collect();
+
+        // the keys past their deadline are on their way out, they'd only pull the average down
+        let ttls: Vec<u64> = sample
+            .iter()
+            .filter_map(|(_, key)| self.expires.get(key.as_ref())?.deadline_ms())
+            .filter_map(|deadline| deadline.checked_sub(now_ms))
+            .filter(|ttl| *ttl > 0)
+            .collect();
+
+        if self.volatile_order.is_empty() {
+            self.avg_ttl.store(0, Ordering::Relaxed);
+        } else if !ttls.is_empty() {
+            let sampled = ttls.iter().sum::<u64>() / ttls.len() as u64;
+            let _ = self
+                .avg_ttl
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg_ttl| {
+                    Some(match avg_ttl {
+                        0 => sampled,
+                        avg_ttl => avg_ttl / 50 * 49 + sampled / 50,
+                    })
+                });
+        }
+
+        match sample.len() {
+            len if len < count => None,
+            _ => sample.last().map(|cursor| (*cursor).clone()),
+        }
+    }
+}
+
+// What maxmemory is held against, the keys and values of every database.
+fn used_memory(databases: &[Database]) -> usize {
+    databases.iter().map(|database| database.used_memory).sum()
+}
+
+// The ID of an entry XADD puts after last, the top of the stream. A generated one takes the clock
+// unless that went back or the stream is already ahead of it, then it counts up from last.
+fn next_stream_id(last: StreamId, id: XaddId, now_ms: u64) -> Result<StreamId, RedisError> {
+    let next = |ms: u64| match ms.cmp(&last.ms) {
+        std::cmp::Ordering::Greater => Some(StreamId { ms, seq: 0 }),
+        std::cmp::Ordering::Equal => last.seq.checked_add(1).map(|seq| StreamId { ms, seq }),
+        std::cmp::Ordering::Less => None,
+    };
+
+    match id {
+        XaddId::Auto => next(now_ms.max(last.ms))
+            .or_else(|| last.ms.checked_add(1).map(|ms| StreamId { ms, seq: 0 }))
+            .ok_or(RedisError::StreamIdTooSmall),
+        XaddId::Sequence(ms) => next(ms).ok_or(RedisError::StreamIdTooSmall),
+        XaddId::Explicit(id) if id == StreamId::MIN => Err(RedisError::StreamIdZero),
+        XaddId::Explicit(id) if id <= last => Err(RedisError::StreamIdTooSmall),
+        XaddId::Explicit(id) => Ok(id),
+    }
+}
+
+// A BLPOP or BRPOP waiting for an element on any of its keys.
+struct Waiter {
+    db: usize,
+    keys: Vec<String>,
+    end: ListEnd,
+    popped: oneshot::Sender<(String, String)>,
+}
+
+// The BLPOPs and BRPOPs waiting on each key, in the order they blocked, so a push serves the one
+// that has waited the longest. A waiter on several keys is queued on each and served by whichever
+// gets an element first.
+#[derive(Default)]
+struct Waiters {
+    next_id: u64,
+    waiters: HashMap<u64, Waiter>,
+    queues: HashMap<(usize, String), VecDeque<u64>>,
+}
+
+impl Waiters {
+    fn block(&mut self, waiter: Waiter) {
+        // the connections that stopped waiting, timed out or gone, are forgotten here rather than
+        // whenever one of them goes
+        self.waiters.retain(|_, waiter| !waiter.popped.is_closed());
+        self.queues.retain(|_, queue| {
+            queue.retain(|id| self.waiters.contains_key(id));
+            !queue.is_empty()
+        });
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        for key in &waiter.keys {
+            self.queues
+                .entry((waiter.db, key.clone()))
+                .or_default()
+                .push_back(id);
+        }
+        self.waiters.insert(id, waiter);
+    }
+
+    // The longest waiting on key that is still waiting, taken off its other keys as well.
+    fn next(&mut self, db: usize, key: &str) -> Option<Waiter> {
+        let queue_key = (db, key.to_string());
+        let queue = self.queues.get_mut(&queue_key)?;
+
+        let mut next = None;
+        while let Some(id) = queue.pop_front() {
+            match self.waiters.remove(&id) {
+                Some(waiter) if !waiter.popped.is_closed() => {
+                    next = Some((id, waiter));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if queue.is_empty() {
+            self.queues.remove(&queue_key);
+        }
+
+        let (id, waiter) = next?;
+        for other in waiter.keys.iter().filter(|other| *other != key) {
+            let other = (db, other.clone());
+            if let Some(queue) = self.queues.get_mut(&other) {
+                queue.retain(|queued| *queued != id);
+                if queue.is_empty() {
+                    self.queues.remove(&other);
+                }
+            }
+        }
+
+        Some(waiter)
+    }
+}
 
 /// Handles redis SET command. Receives message from the SetCommandActorHandle and processes them accordingly.
 pub struct SetCommandActor {
@@ -11,22 +655,243 @@ pub struct SetCommandActor {
     // // channel for key expiration
     // expiry_channel: mpsc::Receiver<String>,
 
-    // The key-value hash map for storing data
-    kv_hash: HashMap<String, String>,
+    // Indexed by database number, sized by the databases config.
+    // Only this actor writes, the handles read through it directly, see SetCommandActorHandle::read_value().
+    databases: Arc<RwLock<Vec<Database>>>,
+
+    // the keyspace notification bus, for the keys only this actor sees expire
+    notifications: Option<mpsc::UnboundedSender<KeyspaceEvent>>,
+
+    // what deadlines are compared against, shared with the handles
+    clock: SharedClock,
+
+    // INFO stats, and expired_keys as of each active expiry cycle in the last rate window
+    expiry_stats: ExpiryStats,
+    expired_keys_history: VecDeque<(Instant, u64)>,
+
+    // the database the next active expiry cycle starts with
+    next_expire_db: usize,
+
+    // the database a random eviction policy looks at first, the one after the last it evicted from
+    next_evict_db: usize,
+
+    // the BLPOPs and BRPOPs that found nothing to pop
+    waiters: Waiters,
+
+    // Changes the commands made to the keys so far, redis' server.dirty. Keys that expire or are
+    // evicted don't count, those go to the replicas on their own.
+    dirty: Arc<AtomicU64>,
 }
 
 impl SetCommandActor {
     // Constructor for the actor
-    pub fn new(receiver: mpsc::Receiver<SetActorMessage>) -> Self {
-        // Initialize the key-value hash map
-        let kv_hash = HashMap::new();
+    pub fn new(
+        receiver: mpsc::Receiver<SetActorMessage>,
+        databases: usize,
+        notifications: Option<mpsc::UnboundedSender<KeyspaceEvent>>,
+        open_store: OpenStore,
+        clock: SharedClock,
+    ) -> Self {
+        // Open an empty store per database
+        let databases = Arc::new(RwLock::new(
+            (0..databases)
+                .map(|db| Database::new(open_store(db)))
+                .collect(),
+        ));
 
-        // Return a new actor with the given receiver and an empty key-value hash map
+        // Return a new actor with the given receiver and the empty databases
         Self {
             receiver,
             // expiry_channel,
-            kv_hash,
+            databases,
+            notifications,
+            clock,
+            expiry_stats: ExpiryStats::default(),
+            expired_keys_history: VecDeque::new(),
+            next_expire_db: 0,
+            next_evict_db: 0,
+            waiters: Waiters::default(),
+            dirty: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // Counts changes a message made to the keys.
+    fn changed(&self, changes: usize) {
+        self.dirty.fetch_add(changes as u64, Ordering::Relaxed);
+    }
+
+    // Database::expire_if_needed(), plus counting the key and the expired notification when it goes.
+    fn expire_if_needed(
+        &mut self,
+        database: &mut Database,
+        db: usize,
+        key: &str,
+        now_ms: u64,
+    ) -> bool {
+        if !database.expire_if_needed(key, now_ms) {
+            return false;
+        }
+
+        self.expiry_stats.expired_keys += 1;
+
+        if let Some(notifications) = &self.notifications {
+            let _ = notifications.send(KeyspaceEvent {
+                db,
+                class: 'x',
+                event: "expired",
+                key: key.to_string(),
+            });
         }
+
+        true
+    }
+
+    /// Removes the expired keys reads haven't come across, redis' activeExpireCycle.
+    ///
+    /// A database is sampled ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP keys with a deadline at a time, for
+    /// as long as more than ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE percent of a sample had expired.
+    /// Past time_limit the cycle stops where it is and the next one starts from that database.
+    fn active_expire_cycle(&mut self, databases: &mut [Database], time_limit: Duration) {
+        let started = Instant::now();
+        let mut sampled = 0;
+        let mut expired = 0;
+        let mut time_cap_reached = false;
+
+        for offset in 0..databases.len() {
+            let db = (self.next_expire_db + offset) % databases.len();
+            let database = &mut databases[db];
+
+            loop {
+                let sample = database.sample_volatile(ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP);
+                let now = self.clock.now_ms();
+
+                let mut expired_in_sample = 0;
+                for key in &sample {
+                    if self.expire_if_needed(database, db, key, now) {
+                        expired_in_sample += 1;
+                    }
+                }
+
+                sampled += sample.len();
+                expired += expired_in_sample;
+
+                if expired_in_sample * 100 <= sample.len() * ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE {
+                    break;
+                }
+
+                if started.elapsed() >= time_limit {
+                    time_cap_reached = true;
+                    break;
+                }
+            }
+
+            if time_cap_reached {
+                self.next_expire_db = db;
+                self.expiry_stats.expired_time_cap_reached_count += 1;
+                break;
+            }
+        }
+
+        if sampled > 0 {
+            self.expiry_stats.expired_stale_perc = expired as f64 * 100.0 / sampled as f64;
+        }
+
+        let now = Instant::now();
+        self.expired_keys_history
+            .push_back((now, self.expiry_stats.expired_keys));
+        while self
+            .expired_keys_history
+            .front()
+            .is_some_and(|(at, _)| now - *at > EXPIRED_KEYS_RATE_WINDOW)
+        {
+            self.expired_keys_history.pop_front();
+        }
+    }
+
+    /// Evicts keys by policy until the databases take up no more than maxmemory bytes, redis'
+    /// performEvictions. The random policies take a key from each database in turn, volatile-ttl
+    /// the one closest to its deadline of those sampled across all of them.
+    fn evict(
+        &mut self,
+        databases: &mut [Database],
+        maxmemory: usize,
+        policy: MaxmemoryPolicy,
+    ) -> Vec<(usize, String)> {
+        let mut evicted = Vec::new();
+
+        while used_memory(databases) > maxmemory {
+            let Some(key) = self.evict_one(databases, policy) else {
+                break;
+            };
+            evicted.push(key);
+        }
+
+        evicted
+    }
+
+    // DEBUG EVICT, count keys by policy however little memory is used.
+    fn evict_keys(
+        &mut self,
+        databases: &mut [Database],
+        count: usize,
+        policy: MaxmemoryPolicy,
+    ) -> Vec<(usize, String)> {
+        (0..count)
+            .map_while(|_| self.evict_one(databases, policy))
+            .collect()
+    }
+
+    // Evicts the next key policy picks, None once it has nothing left to evict.
+    fn evict_one(
+        &mut self,
+        databases: &mut [Database],
+        policy: MaxmemoryPolicy,
+    ) -> Option<(usize, String)> {
+        // the first of the lowest ranked, so ties go to the database whose turn it is
+        let candidate = (0..databases.len())
+            .map(|offset| (self.next_evict_db + offset) % databases.len())
+            .filter_map(|db| {
+                databases[db]
+                    .eviction_candidate(policy)
+                    .map(|(rank, key)| (rank, db, key))
+            })
+            .min_by_key(|(rank, _, _)| *rank);
+
+        let Some((_, db, key)) = candidate else {
+            tracing::debug!("{} has nothing left to evict", policy);
+            return None;
+        };
+
+        tracing::debug!("Evicting {} from db {}", key, db);
+        databases[db].remove(&key);
+
+        self.next_evict_db = (db + 1) % databases.len();
+        self.expiry_stats.evicted_keys += 1;
+
+        Some((db, key.to_string()))
+    }
+
+    // expired_keys over the rate window, per second.
+    fn expired_keys_per_sec(&self) -> f64 {
+        match (
+            self.expired_keys_history.front(),
+            self.expired_keys_history.back(),
+        ) {
+            (Some((first_at, first)), Some((last_at, last))) if last_at > first_at => {
+                (last - first) as f64 / (*last_at - *first_at).as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The databases, for reading without a message to the actor.
+    pub(crate) fn shared_databases(&self) -> Arc<RwLock<Vec<Database>>> {
+        Arc::clone(&self.databases)
+    }
+
+    /// The count of changes to the keys, for reading without a message to the actor.
+    pub(crate) fn shared_dirty(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.dirty)
     }
 
     // Run the actor
@@ -39,55 +904,957 @@ impl SetCommandActor {
 
     // Handle a message
     pub fn handle_message(&mut self, msg: SetActorMessage) {
+        // Held for the whole message, so a reader of the shared view never sees a write half done.
+        let shared_databases = Arc::clone(&self.databases);
+        let mut databases = shared_databases.write().expect("store lock poisoned");
+
         // Match on the type of the message
         match msg {
             // Handle a GetValue message
-            SetActorMessage::GetValue { key, respond_to } => {
-                // If the key exists in the hash map, send the value back
-                if let Some(value) = self.kv_hash.get(&key) {
-                    let _ = respond_to.send(Some(value.clone()));
-                } else {
-                    // If the key does not exist in the hash map, send None
-                    let _ = respond_to.send(None);
-                }
+            SetActorMessage::GetValue {
+                db,
+                key,
+                respond_to,
+            } => {
+                let now = self.clock.now_ms();
+                self.expire_if_needed(&mut databases[db], db, &key, now);
+
+                let database = &databases[db];
+                let value = database
+                    .typed(&key, Kind::String, now)
+                    .map(|_| database.live_value(&key, now).map(Cow::into_owned));
+
+                let _ = respond_to.send(value);
+            }
+
+            // the whole batch under one write lock, so MGET sees every key as of the same moment
+            SetActorMessage::GetValues {
+                db,
+                keys,
+                respond_to,
+            } => {
+                let now = self.clock.now_ms();
+
+                let values = keys
+                    .iter()
+                    .map(|key| {
+                        self.expire_if_needed(&mut databases[db], db, key, now);
+                        // anything but a string is missing here, like redis
+                        databases[db].live_value(key, now).map(Cow::into_owned)
+                    })
+                    .collect();
+
+                let _ = respond_to.send(values);
             }
 
             // Handle a SetValue message
-            SetActorMessage::SetValue { input } => {
-                tracing::debug!("Inserting key: {} value: {}.", input.key, input.value);
-                // Insert the key-value pair into the hash map
-                self.kv_hash.insert(input.key, input.value);
+            SetActorMessage::SetValue {
+                db,
+                input,
+                respond_to,
+            } => {
+                tracing::debug!(
+                    "Inserting key: {} value: {} into db {}.",
+                    input.key,
+                    String::from_utf8_lossy(&input.value),
+                    db
+                );
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+
+                // KEEPTTL must not carry over a deadline that already passed to the new value,
+                // nor NX see a key that is already gone
+                self.expire_if_needed(database, db, &input.key, now);
+
+                let get = input.get == Some(true);
+                // GET only works on strings, and like redis the SET doesn't happen either
+                let checked = match get {
+                    true => database.typed(&input.key, Kind::String, now).map(|_| ()),
+                    false => Ok(()),
+                };
+                let previous = database
+                    .live_value(&input.key, now)
+                    .map(|value| value.into_owned());
+                let exists = database.contains(&input.key);
+
+                let written = checked.is_ok()
+                    && match input.option {
+                        Some(SetCommandSetOption::NX) => !exists,
+                        Some(SetCommandSetOption::XX) => exists,
+                        None => true,
+                    };
+
+                if written {
+                    // A SET without an expiry makes the key persistent again, KEEPTTL leaves it as it was.
+                    if input.expire != Some(SetCommandExpireOption::KEEPTTL) {
+                        database.set_expire(&input.key, input.expire);
+                    }
+
+                    // Insert the key-value pair into the hash map
+                    database.insert(input.key, input.value);
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(checked.map(|()| SetOutcome {
+                    written,
+                    previous: previous.filter(|_| get),
+                }));
+            }
+
+            SetActorMessage::AppendValue {
+                db,
+                key,
+                value,
+                max_len,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let existing = database.typed(&key, Kind::String, now).map(|_| {
+                    database
+                        .live_value(&key, now)
+                        .map(|existing| existing.len())
+                });
+
+                let length = match existing {
+                    Err(e) => Err(e),
+                    Ok(Some(length)) if length + value.len() > max_len => {
+                        Err(RedisError::StringTooLong)
+                    }
+                    // Vec grows its buffer geometrically, so appending n times copies O(n) bytes in
+                    // all rather than the whole value every time. The deadline stays, as in redis.
+                    Ok(Some(length)) => {
+                        database.update(&key, &mut |existing| existing.extend_from_slice(&value));
+                        Ok(length + value.len())
+                    }
+                    Ok(None) if value.len() > max_len => Err(RedisError::StringTooLong),
+                    Ok(None) => {
+                        let length = value.len();
+                        database.insert(key, value);
+                        Ok(length)
+                    }
+                };
+                if length.is_ok() {
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(length);
+            }
+
+            SetActorMessage::SetRangeValue {
+                db,
+                key,
+                offset,
+                value,
+                max_len,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let end = offset + value.len();
+                let existing = database.typed(&key, Kind::String, now).map(|_| {
+                    database
+                        .live_value(&key, now)
+                        .map(|existing| existing.len())
+                });
+
+                let length = match existing {
+                    Err(e) => Err(e),
+                    // an empty value leaves the key as it is, a missing one isn't created
+                    Ok(existing) if value.is_empty() => Ok(existing.unwrap_or(0)),
+                    _ if end > max_len => Err(RedisError::StringTooLong),
+                    // overwritten where it is, the deadline stays
+                    Ok(Some(length)) => {
+                        database.update(&key, &mut |existing| {
+                            if existing.len() < end {
+                                existing.resize(end, 0);
+                            }
+                            existing[offset..end].copy_from_slice(&value);
+                        });
+                        self.changed(1);
+                        Ok(length.max(end))
+                    }
+                    Ok(None) => {
+                        let mut padded = vec![0; offset];
+                        padded.extend_from_slice(&value);
+                        database.insert(key, padded);
+                        self.changed(1);
+                        Ok(end)
+                    }
+                };
+
+                let _ = respond_to.send(length);
+            }
+
+            SetActorMessage::IncrValue {
+                db,
+                key,
+                by,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                // only what an integer prints as counts, no spaces, signs or leading zeroes
+                let current = database
+                    .typed(&key, Kind::String, now)
+                    .and_then(|_| match database.live_value(&key, now) {
+                        Some(value) => std::str::from_utf8(&value)
+                            .ok()
+                            .and_then(|value| value.parse::<i64>().ok())
+                            .filter(|number| number.to_string().as_bytes() == value.as_ref())
+                            .ok_or(RedisError::NotAnInteger),
+                        None => Ok(0),
+                    });
+
+                let counted = current.and_then(|current| {
+                    let counted = current.checked_add(by).ok_or(RedisError::Overflow)?;
+
+                    // the deadline stays, as in redis
+                    if !database
+                        .update(&key, &mut |value| *value = counted.to_string().into_bytes())
+                    {
+                        database.insert(key, counted.to_string().into_bytes());
+                    }
+                    self.changed(1);
+
+                    Ok(counted)
+                });
+
+                let _ = respond_to.send(counted);
+            }
+
+            SetActorMessage::HsetValue {
+                db,
+                key,
+                pairs,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let added = database.typed(&key, Kind::Hash, now).map(|exists| {
+                    if !exists {
+                        database.insert_collection(key.clone(), Collection::Hash(HashMap::new()));
+                    }
+                    // every pair, a value written over with itself too, like redis
+                    self.changed(pairs.len());
+
+                    database.update_collection(&key, Collection::as_hash_mut, |hash| {
+                        pairs
+                            .into_iter()
+                            .filter(|(field, value)| {
+                                hash.insert(field.clone(), value.clone()).is_none()
+                            })
+                            .count()
+                    })
+                });
+
+                let _ = respond_to.send(added);
+            }
+
+            SetActorMessage::HdelValue {
+                db,
+                key,
+                fields,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let removed = database.typed(&key, Kind::Hash, now).map(|exists| {
+                    if !exists {
+                        return 0;
+                    }
+
+                    database.update_collection(&key, Collection::as_hash_mut, |hash| {
+                        fields
+                            .iter()
+                            .filter(|field| hash.remove(*field).is_some())
+                            .count()
+                    })
+                });
+
+                if let Ok(removed) = removed {
+                    self.changed(removed);
+                }
+
+                let _ = respond_to.send(removed);
+            }
+
+            SetActorMessage::SaddValue {
+                db,
+                key,
+                members,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let added = database.typed(&key, Kind::Set, now).map(|exists| {
+                    if !exists {
+                        database.insert_collection(key.clone(), Collection::Set(HashSet::new()));
+                    }
+
+                    database.update_collection(&key, Collection::as_set_mut, |set| {
+                        members
+                            .into_iter()
+                            .filter(|member| set.insert(member.clone()))
+                            .count()
+                    })
+                });
+
+                if let Ok(added) = added {
+                    self.changed(added);
+                }
+
+                let _ = respond_to.send(added);
+            }
+
+            SetActorMessage::SremValue {
+                db,
+                key,
+                members,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let removed = database.typed(&key, Kind::Set, now).map(|exists| {
+                    if !exists {
+                        return 0;
+                    }
+
+                    database.update_collection(&key, Collection::as_set_mut, |set| {
+                        members.iter().filter(|member| set.remove(*member)).count()
+                    })
+                });
+
+                if let Ok(removed) = removed {
+                    self.changed(removed);
+                }
+
+                let _ = respond_to.send(removed);
+            }
+
+            SetActorMessage::ZaddValue {
+                db,
+                input,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &input.key, now);
+
+                let counts = database
+                    .typed(&input.key, Kind::SortedSet, now)
+                    .map(|exists| {
+                        // left empty, it goes again right away
+                        if !exists {
+                            database.insert_collection(
+                                input.key.clone(),
+                                Collection::SortedSet(SortedSet::default()),
+                            );
+                        }
+
+                        database.update_collection(
+                            &input.key,
+                            Collection::as_sorted_set_mut,
+                            |sorted_set| {
+                                let (mut added, mut updated) = (0, 0);
+
+                                for (score, member) in input.members {
+                                    match sorted_set.score(&member) {
+                                        None if !input.xx => {
+                                            sorted_set.insert(member, score);
+                                            added += 1;
+                                        }
+                                        // NX leaves it be, GT and LT let it move their way only
+                                        Some(current)
+                                            if !(input.nx
+                                                || score == current
+                                                || input.gt && score < current
+                                                || input.lt && score > current) =>
+                                        {
+                                            sorted_set.insert(member, score);
+                                            updated += 1;
+                                        }
+                                        _ => {}
+                                    }
+                                }
+
+                                (added, updated)
+                            },
+                        )
+                    });
+
+                if let Ok((added, updated)) = counts {
+                    self.changed(added + updated);
+                }
+
+                let _ = respond_to.send(counts);
+            }
+
+            SetActorMessage::ZremValue {
+                db,
+                key,
+                members,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let removed = database.typed(&key, Kind::SortedSet, now).map(|exists| {
+                    if !exists {
+                        return 0;
+                    }
+
+                    database.update_collection(&key, Collection::as_sorted_set_mut, |sorted_set| {
+                        members
+                            .iter()
+                            .filter(|member| sorted_set.remove(member).is_some())
+                            .count()
+                    })
+                });
+
+                if let Ok(removed) = removed {
+                    self.changed(removed);
+                }
+
+                let _ = respond_to.send(removed);
+            }
+
+            SetActorMessage::ZincrbyValue {
+                db,
+                key,
+                member,
+                increment,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let score = database
+                    .typed(&key, Kind::SortedSet, now)
+                    .and_then(|exists| {
+                        // left empty by a NaN, it goes again right away
+                        if !exists {
+                            database.insert_collection(
+                                key.clone(),
+                                Collection::SortedSet(SortedSet::default()),
+                            );
+                        }
+
+                        database.update_collection(
+                            &key,
+                            Collection::as_sorted_set_mut,
+                            |sorted_set| {
+                                // inf plus -inf, the member keeps the score it had
+                                let score = sorted_set.score(&member).unwrap_or(0.0) + increment;
+                                if score.is_nan() {
+                                    return Err(RedisError::ScoreNaN);
+                                }
+
+                                sorted_set.insert(member, score);
+
+                                Ok(score)
+                            },
+                        )
+                    });
+
+                if score.is_ok() {
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(score);
+            }
+
+            SetActorMessage::XaddValue {
+                db,
+                input,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &input.key, now);
+
+                let id = database
+                    .typed(&input.key, Kind::Stream, now)
+                    .and_then(|exists| {
+                        // left empty by an ID that is too small, it goes again right away
+                        if !exists {
+                            database.insert_collection(
+                                input.key.clone(),
+                                Collection::Stream(Stream::default()),
+                            );
+                        }
+
+                        database.update_collection(
+                            &input.key,
+                            Collection::as_stream_mut,
+                            |stream| {
+                                let id = next_stream_id(stream.last_id(), input.id, now)?;
+                                stream.insert(id, input.fields);
+
+                                Ok(id)
+                            },
+                        )
+                    });
+
+                if id.is_ok() {
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(id);
+            }
+
+            SetActorMessage::PushValue {
+                db,
+                key,
+                end,
+                values,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let pushed = values.len();
+                let waiters = &mut self.waiters;
+                let length = database.typed(&key, Kind::List, now).map(|exists| {
+                    if !exists {
+                        database.insert_collection(key.clone(), Collection::List(VecDeque::new()));
+                    }
+
+                    database.update_collection(&key, Collection::as_list_mut, |list| {
+                        // one at a time, so LPUSH a b c leaves c at the head
+                        for value in values {
+                            match end {
+                                ListEnd::Left => list.push_front(value),
+                                ListEnd::Right => list.push_back(value),
+                            }
+                        }
+                        let length = list.len();
 
-                // Log a success message
+                        // then each blocked client in turn gets an element, for as long as there
+                        // are both
+                        let mut served = Vec::new();
+                        while !list.is_empty() {
+                            let Some(waiter) = waiters.next(db, &key) else {
+                                break;
+                            };
+
+                            let value = match waiter.end {
+                                ListEnd::Left => list.pop_front(),
+                                ListEnd::Right => list.pop_back(),
+                            }
+                            .expect("the list isn't empty");
+
+                            match waiter.popped.send((key.clone(), value)) {
+                                Ok(()) => served.push(waiter.end),
+                                // it stopped waiting since next() looked, the element goes back
+                                Err((_, value)) => match waiter.end {
+                                    ListEnd::Left => list.push_front(value),
+                                    ListEnd::Right => list.push_back(value),
+                                },
+                            }
+                        }
+
+                        (length, served)
+                    })
+                });
+
+                if length.is_ok() {
+                    self.changed(pushed);
+                }
+
+                let _ = respond_to.send(length);
+            }
+
+            SetActorMessage::PopValue {
+                db,
+                key,
+                end,
+                count,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let popped: Result<Option<Vec<String>>, _> =
+                    database.typed(&key, Kind::List, now).map(|exists| {
+                        if !exists {
+                            return None;
+                        }
+
+                        database.update_collection(&key, Collection::as_list_mut, |list| {
+                            let count = count.min(list.len());
+                            Some(match end {
+                                ListEnd::Left => list.drain(..count).collect(),
+                                ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+                            })
+                        })
+                    });
+
+                if let Ok(Some(popped)) = &popped {
+                    self.changed(popped.len());
+                }
+
+                let _ = respond_to.send(popped);
             }
 
-            // Handle an ExpireValue message
-            SetActorMessage::DeleteValue { value } => {
+            SetActorMessage::BlockingPopValue {
+                db,
+                keys,
+                end,
+                waiter,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+
+                // the first of keys that exists has to be a list, and a list is never empty
+                let mut popped = Ok(None);
+                for key in &keys {
+                    self.expire_if_needed(database, db, key, now);
+
+                    popped = match database.typed(key, Kind::List, now) {
+                        Ok(false) => continue,
+                        Ok(true) => {
+                            Ok(
+                                database.update_collection(key, Collection::as_list_mut, |list| {
+                                    let value = match end {
+                                        ListEnd::Left => list.pop_front(),
+                                        ListEnd::Right => list.pop_back(),
+                                    };
+
+                                    value.map(|value| (key.clone(), value))
+                                }),
+                            )
+                        }
+                        Err(e) => Err(e),
+                    };
+                    break;
+                }
+
+                if let Ok(Some(_)) = &popped {
+                    self.changed(1);
+                }
+                if let (Ok(None), Some(popped)) = (&popped, waiter) {
+                    self.waiters.block(Waiter {
+                        db,
+                        keys,
+                        end,
+                        popped,
+                    });
+                }
+
+                let _ = respond_to.send(popped);
+            }
+
+            SetActorMessage::SetCollection {
+                db,
+                key,
+                collection,
+                expire,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+
+                database.insert_collection(key.clone(), collection);
+                database.set_expire(&key, expire);
+                self.changed(1);
+
+                let _ = respond_to.send(());
+            }
+
+            SetActorMessage::ExpireValue {
+                db,
+                key,
+                deadline_ms,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                let now = self.clock.now_ms();
+                self.expire_if_needed(database, db, &key, now);
+
+                let exists = database.contains(&key);
+                if exists && deadline_ms <= now {
+                    database.remove(&key);
+                } else if exists {
+                    database.set_expire(
+                        &key,
+                        Some(SetCommandExpireOption::PXAT(deadline_ms as usize)),
+                    );
+                }
+
+                if exists {
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(exists);
+            }
+
+            SetActorMessage::PersistValue {
+                db,
+                key,
+                respond_to,
+            } => {
+                let database = &mut databases[db];
+                self.expire_if_needed(database, db, &key, self.clock.now_ms());
+
+                let had_deadline = database.expires.contains_key(&key);
+                if had_deadline {
+                    database.set_expire(&key, None);
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(had_deadline);
+            }
+
+            SetActorMessage::DeleteValue {
+                db,
+                value,
+                respond_to,
+            } => {
                 // Log the expiry
-                tracing::debug!("Expiring {:?}", value);
+                tracing::debug!("Expiring {:?} from db {}", value, db);
+
+                // a key past its deadline is expired rather than deleted, and doesn't count
+                self.expire_if_needed(&mut databases[db], db, &value, self.clock.now_ms());
 
                 // Remove the key-value pair from the hash map.
                 //
-                self.kv_hash.remove(&value);
+                let removed = databases[db].remove(&value);
+
+                if removed {
+                    self.changed(1);
+                }
+
+                let _ = respond_to.send(removed);
+            }
+
+            SetActorMessage::DeleteValues {
+                db,
+                keys,
+                respond_to,
+            } => {
+                let now = self.clock.now_ms();
+
+                let removed: Vec<bool> = keys
+                    .iter()
+                    .map(|key| {
+                        self.expire_if_needed(&mut databases[db], db, key, now);
+                        databases[db].remove(key)
+                    })
+                    .collect();
+
+                self.changed(removed.iter().filter(|removed| **removed).count());
+
+                let _ = respond_to.send(removed);
             }
 
-            // Handle a GetKeys message
-            SetActorMessage::GetKeys {
-                pattern,
+            SetActorMessage::CopyValue {
+                db,
+                source,
+                destination_db,
+                destination,
+                replace,
                 respond_to,
             } => {
-                // check to see if there are keys in the hashmap
-                tracing::debug!("Getting all the keys that match the pattern: {}", pattern);
+                let now = self.clock.now_ms();
+                self.expire_if_needed(&mut databases[db], db, &source, now);
+                self.expire_if_needed(
+                    &mut databases[destination_db],
+                    destination_db,
+                    &destination,
+                    now,
+                );
+
+                let source_database = &databases[db];
+                let value = match source_database.live_collection(&source, now) {
+                    Some(collection) => Some(Err(Collection::clone(&collection))),
+                    None => source_database
+                        .live_value(&source, now)
+                        .map(|value| Ok(value.into_owned())),
+                };
 
-                if !self.kv_hash.is_empty() {
-                    // Send the keys back
-                    let _ = respond_to
-                        .send(Some(self.kv_hash.keys().cloned().collect::<Vec<String>>()));
-                } else {
-                    // If the hash map is empty, send None
-                    let _ = respond_to.send(None);
+                let copy = match value {
+                    // without REPLACE an existing destination is left alone
+                    Some(_) if !replace && databases[destination_db].contains(&destination) => None,
+                    // the copy expires when the source does
+                    Some(value) => Some((value, databases[db].expires.get(&source).copied())),
+                    None => None,
+                };
+
+                let copied = copy.is_some();
+                if let Some((value, expire)) = copy {
+                    let database = &mut databases[destination_db];
+                    database.remove(&destination);
+                    match value {
+                        Ok(value) => database.insert(destination.clone(), value),
+                        Err(collection) => {
+                            database.insert_collection(destination.clone(), collection)
+                        }
+                    }
+                    database.set_expire(&destination, expire);
+                    self.changed(1);
                 }
+
+                let _ = respond_to.send(copied);
+            }
+
+            SetActorMessage::ActiveExpireCycle {
+                time_limit,
+                respond_to,
+            } => {
+                self.active_expire_cycle(&mut databases, time_limit);
+
+                let _ = respond_to.send(());
+            }
+
+            SetActorMessage::FlushAll { respond_to } => {
+                tracing::debug!("Flushing all {} databases", databases.len());
+
+                for database in databases.iter_mut() {
+                    database.clear();
+                }
+
+                // even when there was nothing to flush, like redis
+                self.changed(1);
+
+                let _ = respond_to.send(());
+            }
+
+            // the deadlines, SCAN order and expiry cursor of each database go along with it
+            SetActorMessage::SwapDb {
+                db,
+                other_db,
+                respond_to,
+            } => {
+                databases.swap(db, other_db);
+                self.changed(1);
+
+                let _ = respond_to.send(());
+            }
+
+            SetActorMessage::Evict {
+                maxmemory,
+                policy,
+                respond_to,
+            } => {
+                let _ = respond_to.send(self.evict(&mut databases, maxmemory, policy));
+            }
+
+            SetActorMessage::EvictKeys {
+                count,
+                policy,
+                respond_to,
+            } => {
+                let _ = respond_to.send(self.evict_keys(&mut databases, count, policy));
+            }
+
+            SetActorMessage::GetUsedMemory { respond_to } => {
+                let _ = respond_to.send(used_memory(&databases));
+            }
+
+            SetActorMessage::Scan {
+                db,
+                cursor,
+                count,
+                kind,
+                respond_to,
+            } => {
+                let _ =
+                    respond_to.send(databases[db].scan(cursor, count, kind, self.clock.now_ms()));
+            }
+
+            SetActorMessage::DbSize { db, respond_to } => {
+                let _ = respond_to.send(databases[db].len());
+            }
+
+            // Every non-empty database as it is now, for the RDB encoder. The values are shared
+            // rather than copied, so this holds up the writes for no longer than it takes to list
+            // the keys.
+            SetActorMessage::GetSnapshot { respond_to } => {
+                let snapshot = databases
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, database)| database.len() > 0)
+                    .map(|(db, database)| DatabaseSnapshot {
+                        db,
+                        entries: database
+                            .store
+                            .snapshot()
+                            .into_iter()
+                            .map(|(key, value)| {
+                                let expire = database.expires.get(&key).copied();
+                                (key, value, expire)
+                            })
+                            .collect(),
+                        collections: database
+                            .store
+                            .scan_collections()
+                            .map(|(key, collection)| {
+                                let expire = database.expires.get(key.as_ref()).copied();
+                                (key.into_owned(), collection, expire)
+                            })
+                            .collect(),
+                    })
+                    .collect();
+
+                let _ = respond_to.send(snapshot);
+            }
+
+            SetActorMessage::GetKeyspaceStats { respond_to } => {
+                let stats = databases
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, database)| database.len() > 0)
+                    .map(|(db, database)| database.stats(db))
+                    .collect();
+
+                let _ = respond_to.send(stats);
+            }
+
+            SetActorMessage::GetExpiryStats { respond_to } => {
+                let _ = respond_to.send(ExpiryStats {
+                    expired_keys_per_sec: self.expired_keys_per_sec(),
+                    ..self.expiry_stats
+                });
+            }
+
+            SetActorMessage::ResetStats { respond_to } => {
+                self.expiry_stats = ExpiryStats::default();
+                self.expired_keys_history.clear();
+
+                let _ = respond_to.send(());
             }
         }
     }
 }
+
+impl Supervised for SetCommandActor {
+    const NAME: &'static str = "store";
+
+    async fn serve(&mut self) {
+        self.run().await
+    }
+
+    // Starting over empty would lose every key, and the panic may have left a write half done.
+    fn restart(self) -> Option<Self> {
+        None
+    }
+}
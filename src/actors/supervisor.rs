@@ -0,0 +1,135 @@
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    collections::BTreeMap,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{LazyLock, Mutex},
+};
+
+use futures::FutureExt;
+use tracing::{error, warn};
+
+/// An actor the supervisor can bring back after it panics.
+pub trait Supervised: Send + Sized + 'static {
+    /// How INFO and the logs refer to the actor.
+    const NAME: &'static str;
+
+    /// Serves the mailbox until every handle is gone.
+    fn serve(&mut self) -> impl Future<Output = ()> + Send + '_;
+
+    /// The actor that takes over the mailbox after a panic, or None if the state it held can't be
+    /// rebuilt and the server has to stop instead.
+    fn restart(self) -> Option<Self>;
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ActorHealth {
+    // instances currently serving their mailbox
+    pub running: usize,
+    pub restarts: u64,
+    pub last_panic: Option<String>,
+}
+
+static HEALTH: LazyLock<Mutex<BTreeMap<&'static str, ActorHealth>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+fn update_health(name: &'static str, update: impl FnOnce(&mut ActorHealth)) {
+    let mut health = HEALTH
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    update(health.entry(name).or_default());
+}
+
+/// Every actor started so far by name, what INFO actors reports.
+pub fn health() -> BTreeMap<&'static str, ActorHealth> {
+    HEALTH
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Logs every panic along with its backtrace, in place of the default hook printing to stderr.
+/// Set once at startup, the panics caught below only have their message left to go on.
+pub fn log_panics() {
+    std::panic::set_hook(Box::new(|info| {
+        error!("{}\n{}", info, Backtrace::force_capture());
+    }));
+}
+
+/// What a caught panic was about, on one line so it fits in an INFO field.
+pub fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown panic"
+    };
+
+    message.replace(['\r', '\n'], " ")
+}
+
+/// Runs the handling of one message, so a panic in it costs that message only. The actor keeps
+/// its state as the panic left it and goes on with the next message, INFO actors shows the panic
+/// as last_panic without counting a restart. None if it panicked.
+pub async fn isolate<F: Future>(name: &'static str, handling: F) -> Option<F::Output> {
+    match AssertUnwindSafe(handling).catch_unwind().await {
+        Ok(output) => Some(output),
+        Err(panic) => {
+            let message = panic_message(panic.as_ref());
+
+            warn!(
+                "The {} actor panicked ({}) handling a message, dropping it",
+                name, message
+            );
+            update_health(name, |health| health.last_panic = Some(message));
+
+            None
+        }
+    }
+}
+
+/// Spawns the actor in place of a bare tokio::spawn.
+///
+/// The panic is caught around serve() rather than observed on a JoinHandle, because the actor
+/// owns its mailbox: catching it keeps the receiver alive, so the senders every handle already
+/// holds go on working with whatever restart() puts in its place.
+pub fn spawn<A: Supervised>(mut actor: A) {
+    update_health(A::NAME, |health| health.running += 1);
+
+    tokio::spawn(async move {
+        loop {
+            let Err(panic) = AssertUnwindSafe(actor.serve()).catch_unwind().await else {
+                // the handles are all gone, nothing left to serve
+                update_health(A::NAME, |health| health.running -= 1);
+                return;
+            };
+
+            let message = panic_message(panic.as_ref());
+
+            match actor.restart() {
+                Some(restarted) => {
+                    warn!(
+                        "The {} actor panicked ({}), restarting it",
+                        A::NAME,
+                        message
+                    );
+                    update_health(A::NAME, |health| {
+                        health.restarts += 1;
+                        health.last_panic = Some(message);
+                    });
+                    actor = restarted;
+                }
+                None => {
+                    error!(
+                        "The {} actor panicked ({}) and can't be restarted, shutting down",
+                        A::NAME,
+                        message
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,51 @@
+// Who a connection is, https://redis.io/docs/latest/operate/oss_and_stack/management/security/
+//
+// AUTH and HELLO ... AUTH ask an Authenticator whether a username and password go together. The
+// server has no requirepass or ACL users, so the one main.rs uses is redis' default user without
+// a password. Something embedding the server can check credentials anywhere else, a token service
+// say, by running server::serve() with its own, or by handing it to
+// RequestProcessorActorHandle::with_authenticator() if it wires up the actors itself.
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+
+/// Checks credentials for AUTH and HELLO.
+pub trait Authenticator: Send + Sync {
+    /// Whether password is username's. AUTH with a password only asks about "default".
+    ///
+    /// It runs off the processor, other connections' requests go on meanwhile. The connection that
+    /// asked waits for it, up to --request-timeout.
+    fn authenticate<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool>;
+
+    /// Whether a new connection gets NOAUTH for everything but AUTH and HELLO until one of them
+    /// authenticates it.
+    fn required(&self) -> bool;
+
+    /// Whether the default user has a password at all. Without one, AUTH with only a password is an
+    /// error rather than a question for authenticate().
+    fn default_user_has_password(&self) -> bool {
+        true
+    }
+}
+
+pub type SharedAuthenticator = Arc<dyn Authenticator>;
+
+/// redis without requirepass: the default user takes any password, and nobody has to AUTH. AUTH
+/// has to name it though, a bare AUTH <password> is refused like redis does.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultUser;
+
+impl Authenticator for DefaultUser {
+    fn authenticate<'a>(&'a self, username: &'a str, _password: &'a str) -> BoxFuture<'a, bool> {
+        Box::pin(async move { username == "default" })
+    }
+
+    fn required(&self) -> bool {
+        false
+    }
+
+    fn default_user_has_password(&self) -> bool {
+        false
+    }
+}
@@ -2,6 +2,12 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::{
+    eviction::MAXMEMORY_POLICIES,
+    storage::STORAGE_BACKENDS,
+    units::{parse_memory, parse_nonzero_memory},
+};
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
@@ -13,11 +19,117 @@ pub struct Cli {
     #[arg(long, default_value = "empty.rdb", value_name = "FILE")]
     pub dbfilename: Option<PathBuf>,
 
+    /// A file of RESP commands, an AOF fragment or redis-cli --pipe input, applied after the RDB file and before any client connects
+    #[arg(long, value_name = "FILE")]
+    pub import: Option<PathBuf>,
+
     /// TCP port to listen on
     #[arg(short, long, value_parser=clap::value_parser!(u16))]
     #[clap(default_value = "6379")]
     pub port: u16,
 
+    /// Number of databases, SELECT accepts 0 through databases - 1
+    #[arg(long, default_value = "16", value_parser = clap::value_parser!(u32).range(1..))]
+    pub databases: u32,
+
+    /// Frames a subscriber may have waiting in its output buffer before it is disconnected,
+    /// like redis's client-output-buffer-limit for pubsub clients but counted in frames
+    #[arg(long, default_value = "1024", value_parser = clap::value_parser!(u32).range(1..))]
+    pub pubsub_queue_limit: u32,
+
+    /// Bytes all clients' output buffers may add up to before the biggest ones are disconnected, 0 for no limit
+    #[arg(long, default_value = "0", value_parser = parse_memory)]
+    pub maxmemory_clients: u64,
+
+    /// Bytes a connection's query buffer starts out with, and shrinks back to after a burst of requests
+    #[arg(long, default_value = "16384", value_parser = parse_nonzero_memory)]
+    pub client_query_buffer_initial_size: u64,
+
+    /// Bytes of unfinished requests a client may send before it is disconnected
+    #[arg(long, default_value = "1gb", value_parser = parse_nonzero_memory)]
+    pub client_query_buffer_limit: u64,
+
+    /// Bytes the keys and values may take up before maxmemory-policy evicts some, 0 for no limit.
+    /// Sizes may be given with a unit, like 100mb or 2gb
+    #[arg(long, default_value = "0", value_parser = parse_memory)]
+    pub maxmemory: u64,
+
+    /// Which keys are evicted once the dataset is over maxmemory
+    #[arg(long, default_value = "noeviction", value_parser = MAXMEMORY_POLICIES)]
+    pub maxmemory_policy: String,
+
+    /// Whether a replica leaves evicting to its master, which sends it a DEL for every key it evicts
+    #[arg(long, default_value = "yes", value_parser = ["yes", "no"])]
+    pub replica_ignore_maxmemory: String,
+
+    /// KEYS on a database with more keys than this logs a warning pointing at SCAN, 0 never warns
+    #[arg(long, default_value = "100000")]
+    pub keys_warn_threshold: u64,
+
+    /// Past keys-warn-threshold, answer KEYS from SCAN steps so other clients get served in between
+    #[arg(long)]
+    pub keys_by_scan: bool,
+
+    /// Milliseconds a request may wait for its reply before it gets an error instead, 0 waits forever
+    #[arg(long, default_value = "30000")]
+    pub request_timeout: u64,
+
+    /// Milliseconds LRANGE, SMEMBERS, SUNION, SINTER or SINTERCARD may spend on a big key before it gets an error instead, 0 for no limit
+    #[arg(long, default_value = "5000")]
+    pub read_time_limit: u64,
+
+    /// A file every write and administrative command is appended to, a JSON object a line with its client and outcome
+    #[arg(long, value_name = "FILE")]
+    pub auditlog: Option<PathBuf>,
+
+    /// Bytes the audit log may grow to before it is renamed to <auditlog>.<unix ms> and a new one started, 0 never rotates
+    #[arg(long, default_value = "100mb", value_parser = parse_memory)]
+    pub auditlog_max_size: u64,
+
+    /// Which changes to keys are published to __keyspace@<db>__ and __keyevent@<db>__, same letters as redis, empty for none
+    #[arg(long, default_value = "")]
+    pub notify_keyspace_events: String,
+
+    /// Active expiry cycles per second, each one removes what it can of the expired keys in a quarter of its period
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..=500))]
+    pub hz: u64,
+
+    /// Longest value in bytes SET and APPEND may leave behind
+    #[arg(long, default_value = "512mb", value_parser = parse_nonzero_memory)]
+    pub proto_max_bulk_len: u64,
+
+    /// Where the keys and values of each database are kept
+    #[arg(long, default_value = "memory", value_parser = STORAGE_BACKENDS)]
+    pub storage_backend: String,
+
+    /// Send the RDB for a full resync straight from memory to the replica's socket, without SAVE going to disk first
+    #[arg(long)]
+    pub repl_diskless_sync: bool,
+
+    /// Seconds a diskless sync waits for more replicas to share its snapshot, 0 starts it right away
+    #[arg(long, default_value = "5")]
+    pub repl_diskless_sync_delay: u64,
+
+    /// Warn about a replica that hasn't acknowledged the replication stream for this many seconds, 0 never does
+    #[arg(long, default_value = "10")]
+    pub repl_lag_warn_threshold: u64,
+
+    /// As a replica, warn once a write from the master takes this many milliseconds to apply, 0 never does
+    #[arg(long, default_value = "100")]
+    pub repl_apply_warn_threshold: u64,
+
+    /// As a replica, milliseconds between the REPLCONF ACKs that tell the master how far it has got
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+    pub repl_ack_interval: u64,
+
+    /// Seconds SHUTDOWN waits for the replicas to acknowledge everything they were sent
+    #[arg(long, default_value = "10")]
+    pub shutdown_timeout: u64,
+
+    /// Allow the DEBUG command, which can inject faults into replication (DEBUG FAILPOINT)
+    #[arg(long)]
+    pub enable_debug_command: bool,
+
     /// Assume the "slave" role instead
     #[arg(long, value_name = "MASTER_HOST MASTER_PORT")]
     pub replicaof: Option<String>,
@@ -0,0 +1,71 @@
+// Where the store reads the time when it compares deadlines.
+//
+// The server runs on the system clock. An embedded store can be given a ManualClock instead,
+// which only moves when it is told to, so expiry can be tested without sleeping. Relative TTLs
+// sent over the wire (SET EX, EXPIRE) are still worked out against the system clock by the parser.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The time deadlines are held against.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Unix time in milliseconds.
+    fn now_ms(&self) -> u64;
+}
+
+/// A clock shared by the store actor and every handle to it.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The wall clock, what the server uses.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+/// A clock that stands still until advance() or set() moves it.
+#[derive(Debug)]
+pub struct ManualClock {
+    now_ms: AtomicU64,
+}
+
+impl ManualClock {
+    /// Starts out at `now_ms`, unix time in milliseconds.
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            now_ms: AtomicU64::new(now_ms),
+        }
+    }
+
+    /// Starts out at the system time, so deadlines worked out against either are comparable.
+    pub fn starting_now() -> Self {
+        Self::new(SystemClock.now_ms())
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now_ms
+            .fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now_ms: u64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,352 @@
+// Values other than strings, https://redis.io/docs/latest/develop/data-types/
+//
+// A storage backend holds these by key next to the strings, a key holding one or the other.
+
+use crate::storage::Kind;
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+    ops::Bound,
+};
+
+/// A value that isn't a string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Collection {
+    /// Fields and their values, https://redis.io/docs/latest/develop/data-types/hashes/
+    Hash(HashMap<String, String>),
+    /// Elements in the order they are in, https://redis.io/docs/latest/develop/data-types/lists/
+    List(VecDeque<String>),
+    /// Members in no particular order, https://redis.io/docs/latest/develop/data-types/sets/
+    Set(HashSet<String>),
+    /// Members ordered by score, https://redis.io/docs/latest/develop/data-types/sorted-sets/
+    SortedSet(SortedSet),
+    /// Entries in the order they were added, https://redis.io/docs/latest/develop/data-types/streams/
+    Stream(Stream),
+}
+
+/// Either end of a list, left being the head as in LPUSH and LPOP.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl Collection {
+    /// Which of the types it is.
+    pub fn kind(&self) -> Kind {
+        match self {
+            Collection::Hash(_) => Kind::Hash,
+            Collection::List(_) => Kind::List,
+            Collection::Set(_) => Kind::Set,
+            Collection::SortedSet(_) => Kind::SortedSet,
+            Collection::Stream(_) => Kind::Stream,
+        }
+    }
+
+    /// The fields of a hash, None if this is something else.
+    pub fn as_hash(&self) -> Option<&HashMap<String, String>> {
+        match self {
+            Collection::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Option<&mut HashMap<String, String>> {
+        match self {
+            Collection::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    /// The elements of a list, None if this is something else.
+    pub fn as_list(&self) -> Option<&VecDeque<String>> {
+        match self {
+            Collection::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut VecDeque<String>> {
+        match self {
+            Collection::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// The members of a set, None if this is something else.
+    pub fn as_set(&self) -> Option<&HashSet<String>> {
+        match self {
+            Collection::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Option<&mut HashSet<String>> {
+        match self {
+            Collection::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    /// The members of a sorted set, None if this is something else.
+    pub fn as_sorted_set(&self) -> Option<&SortedSet> {
+        match self {
+            Collection::SortedSet(sorted_set) => Some(sorted_set),
+            _ => None,
+        }
+    }
+
+    pub fn as_sorted_set_mut(&mut self) -> Option<&mut SortedSet> {
+        match self {
+            Collection::SortedSet(sorted_set) => Some(sorted_set),
+            _ => None,
+        }
+    }
+
+    /// The entries of a stream, None if this is something else.
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Collection::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream_mut(&mut self) -> Option<&mut Stream> {
+        match self {
+            Collection::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    /// Bytes of what it holds, counted the way used_memory counts a string value.
+    pub fn memory(&self) -> usize {
+        match self {
+            Collection::Hash(hash) => hash
+                .iter()
+                .map(|(field, value)| field.len() + value.len())
+                .sum(),
+            Collection::List(list) => list.iter().map(String::len).sum(),
+            Collection::Set(set) => set.iter().map(String::len).sum(),
+            Collection::SortedSet(sorted_set) => sorted_set
+                .iter()
+                .map(|(member, score)| member.len() + std::mem::size_of_val(&score))
+                .sum(),
+            Collection::Stream(stream) => stream
+                .iter()
+                .map(|(id, fields)| {
+                    std::mem::size_of_val(&id)
+                        + fields
+                            .iter()
+                            .map(|(field, value)| field.len() + value.len())
+                            .sum::<usize>()
+                })
+                .sum(),
+        }
+    }
+
+    /// Redis never keeps an empty collection, the key goes with its last element.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Collection::Hash(hash) => hash.is_empty(),
+            Collection::List(list) => list.is_empty(),
+            Collection::Set(set) => set.is_empty(),
+            Collection::SortedSet(sorted_set) => sorted_set.is_empty(),
+            Collection::Stream(stream) => stream.is_empty(),
+        }
+    }
+}
+
+/// One end of a score range as ZRANGEBYSCORE and ZCOUNT take it: a score, -inf and +inf included,
+/// that is in the range itself unless it came with a '(' in front.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub score: f64,
+    pub exclusive: bool,
+}
+
+/// Members with a score each, kept in order of score and, between equal scores, of member. Like
+/// redis' skiplist and dict pair: a member's score is a lookup away, and so are the members in
+/// order. Scores are never NaN, ZADD refuses them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    ordered: BTreeSet<(Score, String)>,
+}
+
+// A score that orders, which an f64 doesn't on its own because of NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// The score of member, None if it isn't one.
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds member with score, or moves it there if it is already in. Returns the score it had.
+    pub fn insert(&mut self, member: String, score: f64) -> Option<f64> {
+        // -0 and 0 are the same score, as they are in redis
+        let score = score + 0.0;
+        let previous = self.scores.insert(member.clone(), score);
+
+        if let Some(previous) = previous {
+            self.ordered.remove(&(Score(previous), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+
+        previous
+    }
+
+    /// Takes member out, returning the score it had.
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let (member, score) = self.scores.remove_entry(member)?;
+        self.ordered.remove(&(Score(score), member));
+
+        Some(score)
+    }
+
+    /// The members and their scores from min to max, from the lowest score.
+    pub fn range_by_score(
+        &self,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> impl Iterator<Item = (&str, f64)> {
+        // the first member with min's score, the empty string sorts before any other
+        let from = (Score(min.score), String::new());
+
+        self.ordered
+            .range((Bound::Included(from), Bound::Unbounded))
+            .map(|(score, member)| (member.as_str(), score.0))
+            .skip_while(move |(_, score)| min.exclusive && *score == min.score)
+            .take_while(move |(_, score)| {
+                *score < max.score || (!max.exclusive && *score == max.score)
+            })
+    }
+
+    /// Where member is in score order from the lowest, None if it isn't one.
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let score = self.score(member)?;
+
+        Some(
+            self.ordered
+                .range(..(Score(score), member.to_string()))
+                .count(),
+        )
+    }
+
+    /// The members and their scores from the lowest score to the highest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> + ExactSizeIterator {
+        self.ordered
+            .iter()
+            .map(|(score, member)| (member.as_str(), score.0))
+    }
+}
+
+/// A stream entry's ID: the unix time in milliseconds it was added at, and a sequence number
+/// telling apart the entries added in the same millisecond. Written as ms-seq.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    /// 0-0, which no entry can have. XRANGE's -.
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    /// XRANGE's +.
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// The field value pairs of an entry, in the order XADD was given them.
+pub type StreamFields = Vec<(String, String)>;
+
+/// Entries in order of ID, which is the order they were added in: XADD only ever adds one with a
+/// greater ID than the last.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The ID of the last entry, 0-0 while there is none.
+    pub fn last_id(&self) -> StreamId {
+        self.entries
+            .last_key_value()
+            .map_or(StreamId::MIN, |(id, _)| *id)
+    }
+
+    /// Adds an entry. Keeping IDs increasing is up to the caller, one that isn't greater than
+    /// last_id() would take the place of an entry or go in the middle.
+    pub fn insert(&mut self, id: StreamId, fields: StreamFields) {
+        self.entries.insert(id, fields);
+    }
+
+    /// The entries from start to end, both included.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+    ) -> impl Iterator<Item = (StreamId, &StreamFields)> {
+        // BTreeMap::range() panics on a range that ends before it starts
+        let range = if start <= end {
+            Some(self.entries.range(start..=end))
+        } else {
+            None
+        };
+
+        range.into_iter().flatten().map(|(id, fields)| (*id, fields))
+    }
+
+    /// The entries with an ID greater than id.
+    pub fn after(&self, id: StreamId) -> impl Iterator<Item = (StreamId, &StreamFields)> {
+        self.entries
+            .range((Bound::Excluded(id), Bound::Unbounded))
+            .map(|(id, fields)| (*id, fields))
+    }
+
+    /// Every entry from the first one added.
+    pub fn iter(&self) -> impl Iterator<Item = (StreamId, &StreamFields)> {
+        self.entries.iter().map(|(id, fields)| (*id, fields))
+    }
+}
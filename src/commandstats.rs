@@ -0,0 +1,277 @@
+// Per command counters behind INFO commandstats and INFO latencystats,
+// https://redis.io/docs/latest/commands/info/#commandstats
+//
+// The processor counts every command once, where it dispatches them, EXEC and the commands it
+// runs each on their own like redis, and the GET fast path counts the GETs it answers. A call
+// lasts until its reply goes out. Latencies are kept in a histogram rather than one by one: up
+// to 8 microseconds every value has a bucket, above that each power of two is split in 8, so a
+// percentile is never more than an eighth over the latency it stands for.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use tokio::sync::oneshot;
+
+use crate::resp::value::RespValue;
+
+const SUB_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+const BUCKETS: usize = (u64::BITS - SUB_BITS + 1) as usize * SUB_BUCKETS;
+
+/// The percentiles INFO latencystats gives, redis' latency-tracking-info-percentiles plus 100,
+/// the slowest call.
+pub const LATENCY_PERCENTILES: [f64; 4] = [50.0, 99.0, 99.9, 100.0];
+
+fn bucket(usec: u64) -> usize {
+    if usec < SUB_BUCKETS as u64 {
+        return usec as usize;
+    }
+
+    let shift = usec.ilog2() - SUB_BITS;
+    let sub = (usec >> shift) as usize - SUB_BUCKETS;
+
+    (shift as usize + 1) * SUB_BUCKETS + sub
+}
+
+// The slowest latency that lands in bucket.
+fn bucket_max(bucket: usize) -> u64 {
+    if bucket < SUB_BUCKETS {
+        return bucket as u64;
+    }
+
+    let shift = (bucket / SUB_BUCKETS - 1) as u32;
+    let sub = (bucket % SUB_BUCKETS) as u128;
+    let max = ((SUB_BUCKETS as u128 + sub + 1) << shift) - 1;
+
+    max.min(u64::MAX as u128) as u64
+}
+
+/// What one command has been through since the server started or CONFIG RESETSTAT.
+#[derive(Debug, Clone)]
+pub struct CommandStats {
+    /// Calls that ran, failed or not.
+    pub calls: u64,
+    /// Microseconds spent in all of them.
+    pub usec: u64,
+    /// The slowest one.
+    pub usec_max: u64,
+    /// Refused before they ran: wrong number of arguments, READONLY, OOM and the like.
+    pub rejected_calls: u64,
+    /// Ran and replied with an error.
+    pub failed_calls: u64,
+    latencies: Vec<u64>,
+}
+
+impl Default for CommandStats {
+    fn default() -> Self {
+        Self {
+            calls: 0,
+            usec: 0,
+            usec_max: 0,
+            rejected_calls: 0,
+            failed_calls: 0,
+            latencies: vec![0; BUCKETS],
+        }
+    }
+}
+
+impl CommandStats {
+    /// A call that ran for usec microseconds.
+    fn record(&mut self, usec: u64, failed: bool) {
+        self.calls += 1;
+        self.usec += usec;
+        self.usec_max = self.usec_max.max(usec);
+        self.latencies[bucket(usec)] += 1;
+
+        if failed {
+            self.failed_calls += 1;
+        }
+    }
+
+    fn reject(&mut self) {
+        self.rejected_calls += 1;
+    }
+
+    /// The latency percentile of the calls are at or under, in microseconds. Never more than the
+    /// slowest call, so the 100th is exactly that.
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        let rank = ((percentile / 100.0 * self.calls as f64).ceil() as u64).max(1);
+
+        let mut seen = 0;
+        for (bucket, count) in self.latencies.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_max(bucket).min(self.usec_max);
+            }
+        }
+
+        self.usec_max
+    }
+}
+
+type Reply = Option<Vec<RespValue>>;
+type OnReply = Box<dyn FnOnce(&Reply) + Send>;
+
+/// Every command called so far by its name in the command table, shared with the replies still
+/// on their way out, each of which counts its call as it goes.
+#[derive(Debug, Clone, Default)]
+pub struct CommandStatsTable(Arc<Mutex<BTreeMap<&'static str, CommandStats>>>);
+
+impl CommandStatsTable {
+    fn update(&self, name: &'static str, update: impl FnOnce(&mut CommandStats)) {
+        let mut table = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        update(table.entry(name).or_default());
+    }
+
+    /// A call to name that ran for usec microseconds.
+    pub fn record(&self, name: &'static str, usec: u64, failed: bool) {
+        self.update(name, |stats| stats.record(usec, failed));
+    }
+
+    /// A call to name refused before it ran.
+    pub fn reject(&self, name: &'static str) {
+        self.update(name, CommandStats::reject);
+    }
+
+    /// Forgets every call, CONFIG RESETSTAT.
+    pub fn reset(&self) {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+
+    /// Starts timing a call to name, None for a command we don't know, which isn't counted.
+    /// The call is counted as rejected instead if it never got to run.
+    pub fn call(
+        &self,
+        name: Option<&'static str>,
+        rejected: bool,
+        respond_to: oneshot::Sender<Reply>,
+    ) -> Call {
+        Call {
+            respond_to: Some(respond_to),
+            name,
+            rejected,
+            started: Instant::now(),
+            table: self.clone(),
+            on_reply: None,
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<&'static str, CommandStats> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// The INFO commandstats section.
+    pub fn commandstats_section(&self) -> String {
+        let mut section = String::from("# Commandstats\r\n");
+
+        for (name, stats) in self.snapshot() {
+            let usec_per_call = match stats.calls {
+                0 => 0.0,
+                calls => stats.usec as f64 / calls as f64,
+            };
+
+            section.push_str(&format!(
+                "cmdstat_{}:calls={},usec={},usec_per_call={:.2},rejected_calls={},failed_calls={}\r\n",
+                name.to_lowercase(),
+                stats.calls,
+                stats.usec,
+                usec_per_call,
+                stats.rejected_calls,
+                stats.failed_calls
+            ));
+        }
+
+        section
+    }
+
+    /// The INFO latencystats section, for the commands that ran at least once.
+    pub fn latencystats_section(&self) -> String {
+        let mut section = String::from("# Latencystats\r\n");
+
+        for (name, stats) in self.snapshot().iter().filter(|(_, stats)| stats.calls > 0) {
+            let percentiles: Vec<String> = LATENCY_PERCENTILES
+                .iter()
+                .map(|percentile| {
+                    format!(
+                        "p{}={:.3}",
+                        percentile,
+                        stats.percentile(*percentile) as f64
+                    )
+                })
+                .collect();
+
+            section.push_str(&format!(
+                "latency_percentiles_usec_{}:{}\r\n",
+                name.to_lowercase(),
+                percentiles.join(",")
+            ));
+        }
+
+        section
+    }
+}
+
+/// Where the reply to a running command goes, in place of the connection's oneshot. The call is
+/// counted when the reply is sent, as failed if it is an error, or when this is dropped without
+/// one, which only happens when the request couldn't be handled at all.
+pub struct Call {
+    respond_to: Option<oneshot::Sender<Reply>>,
+    name: Option<&'static str>,
+    rejected: bool,
+    started: Instant,
+    table: CommandStatsTable,
+    // told about the reply before it goes, see on_reply()
+    on_reply: Option<OnReply>,
+}
+
+impl Call {
+    /// Has on_reply look at the reply when it is sent, MONITOR and the audit log learn how the
+    /// command went from it. Nothing is called for a call dropped without a reply.
+    pub fn on_reply(mut self, on_reply: impl FnOnce(&Reply) + Send + 'static) -> Self {
+        self.on_reply = Some(Box::new(on_reply));
+        self
+    }
+
+    pub fn send(mut self, reply: Reply) -> Result<(), Reply> {
+        self.count(matches!(reply.as_deref(), Some([RespValue::Error(_)])));
+        if let Some(on_reply) = self.on_reply.take() {
+            on_reply(&reply);
+        }
+
+        match self.respond_to.take() {
+            Some(respond_to) => respond_to.send(reply),
+            None => Err(reply),
+        }
+    }
+
+    fn count(&mut self, failed: bool) {
+        let Some(name) = self.name.take() else {
+            return;
+        };
+
+        if self.rejected {
+            self.table.reject(name);
+        } else {
+            let usec = self.started.elapsed().as_micros() as u64;
+            self.table.record(name, usec, failed);
+        }
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.count(true);
+    }
+}
@@ -0,0 +1,61 @@
+// DEBUG DIGEST and DEBUG DIGEST-VALUE, https://redis.io/docs/latest/commands/debug/
+//
+// A hash of what a dataset holds that doesn't depend on the order anything is kept in, so a
+// replica that caught up with its master has the same one. Like redis the keys are xored together
+// and each one mixes its name, its value and its deadline. The hashing is DefaultHasher::new()'s,
+// the same on every run of a build but not between builds, so only compare servers built alike.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::collections::Collection;
+
+/// What DEBUG DIGEST replies for an empty dataset and DEBUG DIGEST-VALUE for a missing key.
+pub const EMPTY_DIGEST: u64 = 0;
+
+/// A digest the way DEBUG prints it, fixed width hex.
+pub fn to_hex(digest: u64) -> String {
+    format!("{:016x}", digest)
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// The digest of a string value, which DEBUG DIGEST-VALUE gives for its key.
+pub fn string_digest(value: &[u8]) -> u64 {
+    hash_of(("string", value))
+}
+
+/// The digest of a collection: the fields of a hash and the members of a set or a sorted set in any
+/// order, the elements of a list and the entries of a stream in theirs.
+pub fn collection_digest(collection: &Collection) -> u64 {
+    match collection {
+        Collection::Hash(hash) => hash
+            .iter()
+            .fold(hash_of("hash"), |digest, field| digest ^ hash_of(field)),
+        Collection::List(list) => hash_of(("list", list)),
+        Collection::Set(set) => set
+            .iter()
+            .fold(hash_of("set"), |digest, member| digest ^ hash_of(member)),
+        Collection::SortedSet(sorted_set) => sorted_set
+            .iter()
+            .fold(hash_of("zset"), |digest, (member, score)| {
+                digest ^ hash_of((member, score.to_bits()))
+            }),
+        Collection::Stream(stream) => hash_of(("stream", stream.iter().collect::<Vec<_>>())),
+    }
+}
+
+/// The digest of a key, the one DEBUG DIGEST xors with the others of its database.
+pub fn key_digest(key: &str, value_digest: u64, deadline_ms: Option<u64>) -> u64 {
+    hash_of((key, value_digest, deadline_ms))
+}
+
+/// The digest of a database from the xor of its keys' digests. The number is mixed in so two
+/// databases swapped by SWAPDB don't give the same dataset digest.
+pub fn database_digest(db: usize, keys_digest: u64) -> u64 {
+    hash_of((db, keys_digest))
+}
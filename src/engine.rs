@@ -0,0 +1,376 @@
+// Embedded mode: the same store, expiry and persistence actors main.rs wires up,
+// driven through plain method calls instead of a TCP listener.
+//
+// Useful for tests and for using the store as an in-process cache:
+//
+//     let engine = Engine::open(None, None).await?;
+//     engine.set("foo", "bar").await?;
+//     assert_eq!(engine.get("foo").await?, Some(b"bar".to_vec()));
+//
+// NOTE: the actors are tokio tasks, so the Engine must be opened from inside a tokio runtime.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::ensure;
+
+use crate::{
+    clock::{SharedClock, SystemClock},
+    collections::{ListEnd, StreamId},
+    handlers::{
+        config_command::ConfigCommandActorHandle,
+        set_command::{SetCommandActorHandle, DEFAULT_DATABASES},
+    },
+    protocol::{
+        ConfigCommandParameter, SetCommandParameter, XaddCommandParameter, XaddId,
+        ZaddCommandParameter,
+    },
+    storage::{self, OpenStore},
+    utils::{glob_match, spawn_expiry_cycle, spawn_keyspace_sampler},
+};
+
+/// In-process handle to the key-value store. Cheap to clone, every clone talks to the same actors.
+/// Everything goes to database 0.
+#[derive(Clone, Debug)]
+pub struct Engine {
+    set_command_actor_handle: SetCommandActorHandle,
+    config_command_actor_handle: ConfigCommandActorHandle,
+}
+
+impl Engine {
+    /// Starts the store, config and expiry actors without binding a listener.
+    ///
+    /// If `dbfilename` is passed, `dir/dbfilename` is loaded into the store before this returns,
+    /// the same way `--dir` and `--dbfilename` work for the server.
+    pub async fn open(dir: Option<&str>, dbfilename: Option<&str>) -> anyhow::Result<Self> {
+        let memory = storage::backend("memory").expect("the memory backend is always there");
+
+        Self::open_with_backend(memory, dir, dbfilename).await
+    }
+
+    /// Same, with the keys and values kept in the store open_store opens instead of in memory.
+    pub async fn open_with_backend(
+        open_store: OpenStore,
+        dir: Option<&str>,
+        dbfilename: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        Self::open_with(open_store, Arc::new(SystemClock), dir, dbfilename).await
+    }
+
+    /// Same as open(), with deadlines held against clock. A ManualClock lets a test move time on
+    /// past a deadline instead of sleeping until it.
+    pub async fn open_with_clock(
+        clock: SharedClock,
+        dir: Option<&str>,
+        dbfilename: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let memory = storage::backend("memory").expect("the memory backend is always there");
+
+        Self::open_with(memory, clock, dir, dbfilename).await
+    }
+
+    async fn open_with(
+        open_store: OpenStore,
+        clock: SharedClock,
+        dir: Option<&str>,
+        dbfilename: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let set_command_actor_handle =
+            SetCommandActorHandle::with_clock(DEFAULT_DATABASES, None, open_store, clock);
+        let config_command_actor_handle = ConfigCommandActorHandle::new();
+
+        // Same as the server's default hz.
+        let _expiry_cycle = spawn_expiry_cycle(10, set_command_actor_handle.clone());
+        let _keyspace_sampler = spawn_keyspace_sampler(set_command_actor_handle.clone());
+
+        if let Some(dir) = dir {
+            ensure!(Path::new(dir).exists(), "Directory {} not found.", dir);
+
+            config_command_actor_handle
+                .set_value(ConfigCommandParameter::Dir, dir)
+                .await?;
+        }
+
+        if let Some(dbfilename) = dbfilename {
+            config_command_actor_handle
+                .set_value(ConfigCommandParameter::DbFilename, dbfilename)
+                .await?;
+
+            config_command_actor_handle
+                .import_config(set_command_actor_handle.clone(), None)
+                .await?;
+
+            // The config actor handles one message at a time, so this reply only comes back
+            // once every key from the RDB file has been handed to the set actor.
+            let _ = config_command_actor_handle
+                .get_value(ConfigCommandParameter::DbFilename)
+                .await?;
+        }
+
+        Ok(Self {
+            set_command_actor_handle,
+            config_command_actor_handle,
+        })
+    }
+
+    /// GET key
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.set_command_actor_handle.get_value(0, key).await?)
+    }
+
+    /// SET key value, without any options.
+    pub async fn set(&self, key: &str, value: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let set_parameters = SetCommandParameter {
+            key: key.to_string(),
+            value: value.as_ref().to_vec(),
+            option: None,
+            get: None,
+            expire: None,
+        };
+
+        self.set_command_actor_handle
+            .set_value(0, set_parameters)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the keys, returning how many of them existed.
+    pub async fn del(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let mut deleted = 0;
+
+        for key in keys {
+            if self
+                .set_command_actor_handle
+                .delete_value(0, &key.to_string())
+                .await?
+            {
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Expires the key after `ttl`. Returns false if the key does not exist.
+    pub async fn expire(&self, key: &str, ttl: Duration) -> anyhow::Result<bool> {
+        // deadlines are unix timestamps, from the store's own clock
+        let deadline = self.set_command_actor_handle.now_ms() + ttl.as_millis() as u64;
+
+        Ok(self
+            .set_command_actor_handle
+            .expire_value(0, key, deadline)
+            .await?)
+    }
+
+    /// EXISTS key [key ...], how many of keys there are.
+    pub async fn exists(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+
+        Ok(self.set_command_actor_handle.count_existing(0, &keys)?)
+    }
+
+    /// HSET key field value [field value ...], returning how many of the fields are new.
+    pub async fn hset(&self, key: &str, pairs: &[(&str, &str)]) -> anyhow::Result<usize> {
+        let pairs = pairs
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect();
+
+        Ok(self.set_command_actor_handle.hset(0, key, pairs).await?)
+    }
+
+    /// HGET key field
+    pub async fn hget(&self, key: &str, field: &str) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .set_command_actor_handle
+            .read_hash(0, key, |hash| hash.get(field).cloned())?
+            .flatten())
+    }
+
+    /// HGETALL key, empty if there is no such key.
+    pub async fn hgetall(&self, key: &str) -> anyhow::Result<HashMap<String, String>> {
+        Ok(self
+            .set_command_actor_handle
+            .read_hash(0, key, HashMap::clone)?
+            .unwrap_or_default())
+    }
+
+    /// LPUSH key element [element ...], returning the length of the list after.
+    pub async fn lpush(&self, key: &str, values: &[&str]) -> anyhow::Result<usize> {
+        self.push(key, ListEnd::Left, values).await
+    }
+
+    /// RPUSH key element [element ...], returning the length of the list after.
+    pub async fn rpush(&self, key: &str, values: &[&str]) -> anyhow::Result<usize> {
+        self.push(key, ListEnd::Right, values).await
+    }
+
+    async fn push(&self, key: &str, end: ListEnd, values: &[&str]) -> anyhow::Result<usize> {
+        let values = values.iter().map(|value| value.to_string()).collect();
+
+        let (length, _served) = self
+            .set_command_actor_handle
+            .push(0, key, end, values)
+            .await?;
+
+        Ok(length)
+    }
+
+    /// LPOP key, None if there is no such key.
+    pub async fn lpop(&self, key: &str) -> anyhow::Result<Option<String>> {
+        self.pop(key, ListEnd::Left).await
+    }
+
+    /// RPOP key, None if there is no such key.
+    pub async fn rpop(&self, key: &str) -> anyhow::Result<Option<String>> {
+        self.pop(key, ListEnd::Right).await
+    }
+
+    async fn pop(&self, key: &str, end: ListEnd) -> anyhow::Result<Option<String>> {
+        let popped = self.set_command_actor_handle.pop(0, key, end, 1).await?;
+
+        Ok(popped.and_then(|popped| popped.into_iter().next()))
+    }
+
+    /// LLEN key, 0 if there is no such key.
+    pub async fn llen(&self, key: &str) -> anyhow::Result<usize> {
+        Ok(self
+            .set_command_actor_handle
+            .read_list(0, key, |list| list.len())?
+            .unwrap_or(0))
+    }
+
+    /// SADD key member [member ...], returning how many of the members are new.
+    pub async fn sadd(&self, key: &str, members: &[&str]) -> anyhow::Result<usize> {
+        let members = members.iter().map(|member| member.to_string()).collect();
+
+        Ok(self.set_command_actor_handle.sadd(0, key, members).await?)
+    }
+
+    /// SREM key member [member ...], returning how many of the members were there.
+    pub async fn srem(&self, key: &str, members: &[&str]) -> anyhow::Result<usize> {
+        let members = members.iter().map(|member| member.to_string()).collect();
+
+        Ok(self.set_command_actor_handle.srem(0, key, members).await?)
+    }
+
+    /// SISMEMBER key member
+    pub async fn sismember(&self, key: &str, member: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .set_command_actor_handle
+            .read_set(0, key, |set| set.contains(member))?
+            .unwrap_or(false))
+    }
+
+    /// SMEMBERS key, empty if there is no such key.
+    pub async fn smembers(&self, key: &str) -> anyhow::Result<HashSet<String>> {
+        Ok(self
+            .set_command_actor_handle
+            .read_set(0, key, HashSet::clone)?
+            .unwrap_or_default())
+    }
+
+    /// ZADD key score member [score member ...], returning how many of the members are new.
+    pub async fn zadd(&self, key: &str, members: &[(f64, &str)]) -> anyhow::Result<usize> {
+        let input = ZaddCommandParameter {
+            key: key.to_string(),
+            members: members
+                .iter()
+                .map(|(score, member)| (*score, member.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+
+        let (added, _updated) = self.set_command_actor_handle.zadd(0, input).await?;
+
+        Ok(added)
+    }
+
+    /// ZINCRBY key increment member, returning the member's new score.
+    pub async fn zincrby(&self, key: &str, increment: f64, member: &str) -> anyhow::Result<f64> {
+        Ok(self
+            .set_command_actor_handle
+            .zincrby(0, key, member, increment)
+            .await?)
+    }
+
+    /// ZREM key member [member ...], returning how many of the members were there.
+    pub async fn zrem(&self, key: &str, members: &[&str]) -> anyhow::Result<usize> {
+        let members = members.iter().map(|member| member.to_string()).collect();
+
+        Ok(self.set_command_actor_handle.zrem(0, key, members).await?)
+    }
+
+    /// ZSCORE key member
+    pub async fn zscore(&self, key: &str, member: &str) -> anyhow::Result<Option<f64>> {
+        Ok(self
+            .set_command_actor_handle
+            .read_sorted_set(0, key, |sorted_set| sorted_set.score(member))?
+            .flatten())
+    }
+
+    /// ZRANGE key 0 -1 WITHSCORES, every member and its score from the lowest.
+    pub async fn zrange(&self, key: &str) -> anyhow::Result<Vec<(String, f64)>> {
+        Ok(self
+            .set_command_actor_handle
+            .read_sorted_set(0, key, |sorted_set| {
+                sorted_set
+                    .iter()
+                    .map(|(member, score)| (member.to_string(), score))
+                    .collect()
+            })?
+            .unwrap_or_default())
+    }
+
+    /// XADD key * field value [field value ...], returning the ID the entry was given.
+    pub async fn xadd(&self, key: &str, fields: &[(&str, &str)]) -> anyhow::Result<StreamId> {
+        let input = XaddCommandParameter {
+            key: key.to_string(),
+            id: XaddId::Auto,
+            fields: fields
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.to_string()))
+                .collect(),
+        };
+
+        Ok(self.set_command_actor_handle.xadd(0, input).await?)
+    }
+
+    /// XLEN key
+    pub async fn xlen(&self, key: &str) -> anyhow::Result<usize> {
+        Ok(self
+            .set_command_actor_handle
+            .read_stream(0, key, |stream| stream.len())?
+            .unwrap_or(0))
+    }
+
+    /// What is left of the key's TTL, None if it doesn't exist or has no deadline.
+    pub async fn ttl(&self, key: &str) -> anyhow::Result<Option<Duration>> {
+        let deadline = self.set_command_actor_handle.deadline(0, key)?.flatten();
+
+        Ok(deadline.map(|deadline| {
+            Duration::from_millis(deadline.saturating_sub(self.set_command_actor_handle.now_ms()))
+        }))
+    }
+
+    /// KEYS pattern
+    pub async fn keys(&self, pattern: &str) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .set_command_actor_handle
+            .keys(0)?
+            .iter()
+            .filter(|key| glob_match(pattern, key, false))
+            .map(|key| key.to_string())
+            .collect())
+    }
+
+    /// Access to the config actor, e.g. to read back dir and dbfilename.
+    pub fn config(&self) -> &ConfigCommandActorHandle {
+        &self.config_command_actor_handle
+    }
+}
@@ -2,34 +2,324 @@ use std::num::ParseIntError;
 
 use thiserror::Error;
 
+use crate::resp::value::RespValue;
+
 /// RedisError enumerates all possible errors returned by this library.
+///
+/// Each one displays as the whole error line a client gets, starting with its class(): ERR for
+/// most, or one of the prefixes redis gives errors a client is expected to act on.
 #[derive(Error, Debug)]
 pub enum RedisError {
     /// Nom parser was unable to parse the in-bound resp message
-    #[error("Unable to parse message")]
+    #[error("ERR unable to parse message")]
     ParseFailure,
 
     /// Redis got an incorrect number of parameters
-    #[error("Incorrect number of parameters")]
+    #[error("ERR incorrect number of parameters")]
     InputFailure,
 
     /// Key not found
-    #[error("Invalid key passed")]
+    #[error("ERR no such key")]
     KeyNotFound,
 
     /// Handshake did not complete
-    #[error("Master failed to reply to handshake")]
+    #[error("ERR master failed to reply to handshake")]
     HandshakeError,
 
     /// Config file not found
-    #[error("Failed to open config file: {0}")]
+    #[error("ERR failed to open config file: {0}")]
     ConfigFileOpenError(String),
 
+    /// The store's contents could not be written out as RDB
+    #[error("ERR failed to encode RDB: {0}")]
+    RdbEncodeError(String),
+
+    /// A key in an RDB of a value type that can't be loaded
+    #[error("ERR unknown RDB value type {0:#04x}")]
+    UnknownRdbType(u8),
+
     /// Represents all other cases of `ParseIntError`.
-    #[error("Invalid digit parsing")]
+    #[error("ERR value is not an integer or out of range")]
     ParseIntError(#[from] ParseIntError),
 
+    /// The command name is not in the command table
+    #[error("ERR unknown command '{0}', with args beginning with: {args}", args = quote_args(.1))]
+    UnknownCommand(String, Vec<String>),
+
+    /// The command was called with the wrong number of arguments
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+
+    /// The arguments don't match the command's syntax
+    #[error("ERR syntax error")]
+    SyntaxError,
+
+    /// An argument that should have been a number wasn't
+    #[error("ERR value is not an integer or out of range")]
+    NotAnInteger,
+
+    /// SET's EX, PX, EXAT or PXAT isn't positive, or its deadline is past what an i64 of
+    /// milliseconds holds
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(String),
+
+    /// INCR and friends would take a counter past what an i64 holds
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+
+    /// A bulk string's declared length doesn't match its contents
+    #[error("ERR Protocol error: invalid bulk length")]
+    InvalidBulkLength,
+
+    /// A client sent a write to a replica
+    #[error("READONLY You can't write against a read only replica.")]
+    ReadOnlyReplica,
+
+    /// The command can't be queued in a MULTI
+    #[error("ERR Command not allowed inside a transaction")]
+    NotAllowedInTransaction,
+
+    /// EXEC after a command failed to queue
+    #[error("EXECABORT Transaction discarded because of previous errors.")]
+    ExecAbort,
+
+    /// An actor stopped, or dropped a request without answering it
+    #[error("ERR internal error, {0} is not running")]
+    ActorGone(&'static str),
+
+    /// A write that may grow the dataset while it is over maxmemory under noeviction
+    #[error("OOM command not allowed when used memory > 'maxmemory'.")]
+    OutOfMemory,
+
+    /// A request got no reply within request-timeout
+    #[error("ERR internal timeout")]
+    Timeout,
+
+    /// REPLICAOF sent to a running server
+    #[error("ERR REPLICAOF <host> <port> is only supported at startup, use --replicaof")]
+    ReplicaOfAtRuntime,
+
+    /// SHUTDOWN SAVE couldn't save, the server keeps running
+    #[error("ERR Errors trying to SHUTDOWN. Check logs.")]
+    ShutdownFailed,
+
+    /// A BGSAVE while the last one is still writing its file
+    #[error("ERR Background save already in progress")]
+    BgsaveInProgress,
+
+    /// A write that came in while SHUTDOWN waits for the replicas
+    #[error("ERR The server is shutting down")]
+    ShuttingDown,
+
+    /// WAITAOF sent to a replica
+    #[error("ERR WAITAOF cannot be used with replica instances. Please also note that writes to replicas are just local and are not propagated.")]
+    WaitAofOnReplica,
+
+    /// WAITAOF asked for local fsyncs, there is no AOF to fsync
+    #[error("ERR WAITAOF cannot be used when numlocal is set but appendonly is disabled.")]
+    WaitAofWithoutAppendOnly,
+
+    /// DEBUG without --enable-debug-command
+    #[error("ERR DEBUG command not allowed. If the enable-debug-command option is set to \"local\", you can run it from a local connection, otherwise you need to set this option in the configuration file, and then restart the server.")]
+    DebugNotAllowed,
+
+    /// SELECT past the last database
+    #[error("ERR DB index is out of range")]
+    DbIndexOutOfRange,
+
+    /// A SWAPDB index that isn't a number, "first" or "second"
+    #[error("ERR invalid {0} DB index")]
+    InvalidDbIndex(&'static str),
+
+    /// COPY onto itself
+    #[error("ERR source and destination objects are the same")]
+    SameObject,
+
+    /// CONFIG SET of a parameter this server doesn't have
+    #[error("ERR Unknown option or number of arguments for CONFIG SET - '{0}'")]
+    UnknownConfig(String),
+
+    /// CONFIG SET with a value the parameter can't take, or of one that is only set at startup
+    #[error("ERR CONFIG SET failed (possibly related to argument '{0}') - {1}")]
+    ConfigSetFailed(String, String),
+
+    /// CLIENT LIST TYPE with a type redis doesn't have
+    #[error("ERR Unknown client type '{0}'")]
+    UnknownClientType(String),
+
+    /// CLIENT LIST ID with an id no client could have
+    #[error("ERR Invalid client ID")]
+    InvalidClientId,
+
+    /// SUBSCRIBE from a connection with nowhere to push messages, the master link
+    #[error("ERR SUBSCRIBE is not allowed on this connection")]
+    SubscribeNotAllowed,
+
+    /// Anything else from a RESP2 client that is subscribed, its replies would be taken for messages
+    #[error("ERR Can't execute '{0}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context")]
+    SubscribeContext(String),
+
+    /// MULTI inside a MULTI
+    #[error("ERR MULTI calls can not be nested")]
+    NestedMulti,
+
+    /// DISCARD outside a MULTI
+    #[error("ERR DISCARD without MULTI")]
+    DiscardWithoutMulti,
+
+    /// EXEC outside a MULTI
+    #[error("ERR EXEC without MULTI")]
+    ExecWithoutMulti,
+
+    /// A request that isn't an array, RESP3 types included
+    #[error("ERR Protocol error: expected '*', got RESP3 type")]
+    NotAnArray,
+
+    /// The key holds a value of another type than the command works on
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    /// The connection has to AUTH first
+    #[error("NOAUTH Authentication required.")]
+    NoAuth,
+
+    /// HELLO with a protocol version other than 2 or 3
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+
+    /// No such user, or the wrong password for it
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+
+    /// AUTH with only a password when the default user has none
+    #[error("ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?")]
+    AuthWithoutPassword,
+
+    /// A client name with a space, a newline or anything else outside '!' to '~'
+    #[error("ERR Client names cannot contain spaces, newlines or special characters.")]
+    InvalidClientName,
+
+    /// The target key of a copy or restore is taken
+    #[error("BUSYKEY Target key name already exists.")]
+    BusyKey,
+
+    /// EVALSHA of a script that was never loaded
+    #[error("NOSCRIPT No matching script. Please use EVAL.")]
+    NoScript,
+
+    /// The slot is served by another node, at addr
+    #[error("MOVED {slot} {addr}")]
+    Moved { slot: u16, addr: String },
+
+    /// A multi-key command across slots
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+
+    /// SET, APPEND or SETRANGE would leave a value longer than proto-max-bulk-len
+    #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
+    StringTooLong,
+
+    /// A negative SETRANGE offset
+    #[error("ERR offset is out of range")]
+    OffsetOutOfRange,
+
+    /// Bytes that aren't UTF-8 anywhere but in a string value or ECHO's message. Keys, names and
+    /// the members of collections are kept as text.
+    #[error("ERR invalid UTF-8 in an argument, only string values may hold any bytes")]
+    NotUtf8,
+
+    /// A BLPOP or BRPOP timeout that isn't a number of seconds
+    #[error("ERR timeout is not a float or out of range")]
+    TimeoutNotFloat,
+
+    /// A ZADD score that isn't a number
+    #[error("ERR value is not a valid float")]
+    NotAFloat,
+
+    /// A ZRANGEBYSCORE or ZCOUNT bound that isn't a score
+    #[error("ERR min or max is not a float")]
+    ScoreBoundNotFloat,
+
+    /// A stream ID that isn't ms-seq, or one of the forms the command takes in its place
+    #[error("ERR Invalid stream ID specified as stream command argument")]
+    InvalidStreamId,
+
+    /// XADD with an ID that doesn't come after the last one in the stream
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+
+    /// XREAD STREAMS with a key or an ID too many
+    #[error("ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.")]
+    UnbalancedXread,
+
+    /// An expensive read still going past read-time-limit
+    #[error("ERR operation timed out")]
+    OperationTimedOut,
+
+    /// SINTERCARD numkeys of 0 or less
+    #[error("ERR numkeys should be greater than 0")]
+    NumkeysNotPositive,
+
+    /// SINTERCARD numkeys past the arguments there are
+    #[error("ERR Number of keys can't be greater than number of args")]
+    NumkeysPastArgs,
+
+    /// ZINCRBY adding inf to -inf, or the other way round
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNaN,
+
+    /// ZADD flags that contradict each other, the message names them
+    #[error("ERR {0} options at the same time are not compatible")]
+    IncompatibleOptions(&'static str),
+
+    /// A BLPOP or BRPOP timeout below zero
+    #[error("ERR timeout is negative")]
+    NegativeTimeout,
+
+    /// A client sent more than client-query-buffer-limit bytes without finishing a request
+    #[error("ERR client reached max query buffer length ({0} bytes)")]
+    QueryBufferLimit(usize),
+
+    /// A failure with nothing more specific to say than its message
+    #[error("ERR {0}")]
+    Internal(String),
+
     /// Represents all other cases of `std::io::Error`.
-    #[error("Failed to read line")]
+    #[error("ERR failed to read line")]
     IOError(#[from] std::io::Error),
 }
+
+impl RedisError {
+    /// The prefix a client reads to tell errors apart, the first word of the error line.
+    pub fn class(&self) -> &'static str {
+        match self {
+            RedisError::ReadOnlyReplica => "READONLY",
+            RedisError::ExecAbort => "EXECABORT",
+            RedisError::OutOfMemory => "OOM",
+            RedisError::WrongType => "WRONGTYPE",
+            RedisError::NoAuth => "NOAUTH",
+            RedisError::NoProto => "NOPROTO",
+            RedisError::WrongPass => "WRONGPASS",
+            RedisError::BusyKey => "BUSYKEY",
+            RedisError::NoScript => "NOSCRIPT",
+            RedisError::Moved { .. } => "MOVED",
+            RedisError::CrossSlot => "CROSSSLOT",
+            _ => "ERR",
+        }
+    }
+}
+
+/// The one way an error becomes a reply.
+impl From<RedisError> for RespValue {
+    fn from(error: RedisError) -> Self {
+        RespValue::Error(error.to_string())
+    }
+}
+
+// Formats the arguments the way redis lists them in an unknown command error: 'a' 'b'
+fn quote_args(args: &[String]) -> String {
+    args.iter().map(|arg| format!("'{}' ", arg)).collect()
+}
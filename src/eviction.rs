@@ -0,0 +1,78 @@
+// Evicting keys to keep the dataset under maxmemory, https://redis.io/docs/latest/develop/reference/eviction/
+//
+// The store picks which keys go (see SetCommandActor::evict), the processor asks it to before
+// every write and sends a DEL down the replication stream for each key it dropped. What is here
+// decides whether a server evicts at all, and by which policy.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+
+use crate::protocol::ServerRole;
+
+/// The policies --maxmemory-policy can name. The LRU and LFU ones need an access clock per key
+/// the store doesn't keep.
+pub const MAXMEMORY_POLICIES: [&str; 4] = [
+    "noeviction",
+    "allkeys-random",
+    "volatile-random",
+    "volatile-ttl",
+];
+
+/// maxmemory-policy, which keys go once the dataset is over maxmemory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaxmemoryPolicy {
+    /// Nothing is evicted.
+    #[default]
+    NoEviction,
+    /// Any key, picked at random.
+    AllkeysRandom,
+    /// A key with a deadline, picked at random.
+    VolatileRandom,
+    /// Of a few keys with a deadline picked at random, the one closest to it.
+    VolatileTtl,
+}
+
+impl MaxmemoryPolicy {
+    /// Whether only keys with a deadline may be evicted.
+    pub fn volatile(&self) -> bool {
+        matches!(
+            self,
+            MaxmemoryPolicy::VolatileRandom | MaxmemoryPolicy::VolatileTtl
+        )
+    }
+}
+
+impl FromStr for MaxmemoryPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "noeviction" => Ok(MaxmemoryPolicy::NoEviction),
+            "allkeys-random" => Ok(MaxmemoryPolicy::AllkeysRandom),
+            "volatile-random" => Ok(MaxmemoryPolicy::VolatileRandom),
+            "volatile-ttl" => Ok(MaxmemoryPolicy::VolatileTtl),
+            _ => Err(anyhow!("invalid maxmemory-policy '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for MaxmemoryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaxmemoryPolicy::NoEviction => write!(f, "noeviction"),
+            MaxmemoryPolicy::AllkeysRandom => write!(f, "allkeys-random"),
+            MaxmemoryPolicy::VolatileRandom => write!(f, "volatile-random"),
+            MaxmemoryPolicy::VolatileTtl => write!(f, "volatile-ttl"),
+        }
+    }
+}
+
+/// Whether a server in this role evicts keys by itself.
+///
+/// A replica holds what its master has. The keys the master evicts come down the replication
+/// stream as DELs, so a replica evicting on its own would only drop keys its master still has,
+/// and the two would go on disagreeing. Like redis, replica-ignore-maxmemory no lets it anyway.
+pub fn evicts(role: Option<ServerRole>, replica_ignore_maxmemory: bool) -> bool {
+    role != Some(ServerRole::Slave) || !replica_ignore_maxmemory
+}
@@ -0,0 +1,332 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bytes::BytesMut;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_util::codec::Decoder;
+
+use crate::{
+    actors::{
+        clients::ClientsActor,
+        messages::{ClientsActorMessage, HostId},
+        supervisor,
+    },
+    errors::RedisError,
+    resp::{
+        codec::{RespCodec, RespProtocol},
+        value::RespValue,
+    },
+};
+
+// Counters behind a connection's OutputQueue, shared between whoever queues frames and the
+// task writing them out, so the clients actor can read them without asking either one.
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    queued_frames: AtomicUsize,
+    queued_bytes: AtomicUsize,
+    // the frames the writer has taken off the queue but not finished sending
+    writing_bytes: AtomicUsize,
+    // what frames queued from now on are encoded in, HELLO 3 sets it
+    resp3: AtomicBool,
+}
+
+/// A connection's output buffer as CLIENT LIST reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputStats {
+    pub obl: usize,  // bytes of the frames being written to the socket
+    pub oll: usize,  // frames waiting behind it
+    pub omem: usize, // bytes of all of the above
+}
+
+/// Everything a connection sends goes through here: replies, pushes and the replication stream.
+/// Queueing never waits on the client, like redis's reply list it grows until a limit
+/// (pubsub-queue-limit, maxmemory-clients) has the client disconnected.
+///
+/// Every frame goes out in the protocol the connection spoke when it was queued, so a HELLO
+/// switching protocols doesn't change what was already waiting.
+#[derive(Clone, Debug)]
+pub struct OutputQueue {
+    sender: mpsc::UnboundedSender<(RespValue, usize, RespProtocol)>,
+    buffer: Arc<OutputBuffer>,
+}
+
+/// The writing end of an [`OutputQueue`], owned by the task that writes to the socket.
+#[derive(Debug)]
+pub struct OutputReceiver {
+    receiver: mpsc::UnboundedReceiver<(RespValue, usize, RespProtocol)>,
+    buffer: Arc<OutputBuffer>,
+}
+
+impl OutputQueue {
+    pub fn new() -> (Self, OutputReceiver) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let buffer = Arc::new(OutputBuffer::default());
+
+        (
+            Self {
+                sender,
+                buffer: buffer.clone(),
+            },
+            OutputReceiver { receiver, buffer },
+        )
+    }
+
+    /// Queues a frame for the client. Fails only once the connection's writer is gone.
+    pub fn send(&self, frame: RespValue) -> Result<(), RespValue> {
+        let bytes = frame.encode().len();
+
+        // counted before it is queued, so the writer never takes off more than was put on
+        self.buffer.queued_frames.fetch_add(1, Ordering::Relaxed);
+        self.buffer.queued_bytes.fetch_add(bytes, Ordering::Relaxed);
+
+        self.sender
+            .send((frame, bytes, self.protocol()))
+            .map_err(|rejected| {
+                let (frame, bytes, _) = rejected.0;
+                self.buffer.queued_frames.fetch_sub(1, Ordering::Relaxed);
+                self.buffer.queued_bytes.fetch_sub(bytes, Ordering::Relaxed);
+                frame
+            })
+    }
+
+    /// The protocol the connection speaks, RESP2 until HELLO 3.
+    pub fn protocol(&self) -> RespProtocol {
+        if self.buffer.resp3.load(Ordering::Relaxed) {
+            RespProtocol::Resp3
+        } else {
+            RespProtocol::Resp2
+        }
+    }
+
+    /// Frames queued after this are encoded in protocol.
+    pub fn set_protocol(&self, protocol: RespProtocol) {
+        self.buffer
+            .resp3
+            .store(protocol == RespProtocol::Resp3, Ordering::Relaxed);
+    }
+
+    /// Frames waiting to be written, same as CLIENT LIST's oll.
+    pub fn pending(&self) -> usize {
+        self.buffer.queued_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn stats(&self) -> OutputStats {
+        let obl = self.buffer.writing_bytes.load(Ordering::Relaxed);
+
+        OutputStats {
+            obl,
+            oll: self.pending(),
+            omem: obl + self.buffer.queued_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl OutputReceiver {
+    /// The next frame to write and the protocol to encode it in, None once every OutputQueue
+    /// for this connection is dropped. Its bytes count as being written until [`OutputReceiver::written`].
+    pub async fn recv(&mut self) -> Option<(RespValue, RespProtocol)> {
+        let (frame, bytes, protocol) = self.receiver.recv().await?;
+
+        self.buffer
+            .writing_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+        self.buffer.queued_frames.fetch_sub(1, Ordering::Relaxed);
+        self.buffer.queued_bytes.fetch_sub(bytes, Ordering::Relaxed);
+
+        Some((frame, protocol))
+    }
+
+    /// The frames from every recv so far are on the socket.
+    pub fn written(&self) {
+        self.buffer.writing_bytes.store(0, Ordering::Relaxed);
+    }
+
+    /// Whether nothing is queued behind the frames taken off so far.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.queued_frames.load(Ordering::Relaxed) == 0
+    }
+}
+
+/// How big a connection's query buffer is to begin with, client-query-buffer-initial-size, and
+/// how big a request may get it, client-query-buffer-limit. Both in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryBufferLimits {
+    pub initial_size: usize,
+    pub limit: usize,
+}
+
+// Counters behind a connection's QueryBuffer, kept up to date by its QueryDecoder.
+#[derive(Debug, Default)]
+struct QueryCounters {
+    len: AtomicUsize,
+    free: AtomicUsize,
+}
+
+/// A connection's query buffer as CLIENT LIST reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    pub qbuf: usize,      // bytes read and not yet parsed into a request
+    pub qbuf_free: usize, // room left before the buffer has to grow
+}
+
+/// The clients actor's view of a connection's query buffer, see [`QueryDecoder`].
+#[derive(Clone, Debug)]
+pub struct QueryBuffer {
+    counters: Arc<QueryCounters>,
+}
+
+impl QueryBuffer {
+    pub fn stats(&self) -> QueryStats {
+        QueryStats {
+            qbuf: self.counters.len.load(Ordering::Relaxed),
+            qbuf_free: self.counters.free.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Decodes a client's requests with a [`RespCodec`], minding the buffer they are read into.
+///
+/// A pipelined burst grows the buffer to whatever it takes, and a BytesMut keeps its memory once
+/// the burst is parsed. So once every request has been taken off a buffer that grew past the
+/// initial size, the decoder swaps it for a new one of the initial size. A client whose buffer
+/// holds more than the limit of requests it hasn't finished sending is disconnected, the way
+/// redis closes clients over client-query-buffer-limit.
+#[derive(Debug)]
+pub struct QueryDecoder {
+    codec: RespCodec,
+    limits: QueryBufferLimits,
+    // the most the buffer has held since it was last replaced
+    peak: usize,
+    counters: Arc<QueryCounters>,
+}
+
+impl QueryDecoder {
+    pub fn new(limits: QueryBufferLimits) -> (Self, QueryBuffer) {
+        let counters = Arc::new(QueryCounters::default());
+
+        (
+            Self {
+                codec: RespCodec::new(),
+                limits,
+                peak: 0,
+                counters: counters.clone(),
+            },
+            QueryBuffer { counters },
+        )
+    }
+
+    /// What the connection's read buffer should start out with.
+    pub fn initial_size(&self) -> usize {
+        self.limits.initial_size
+    }
+}
+
+impl Decoder for QueryDecoder {
+    type Error = RedisError;
+
+    type Item = RespValue;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.peak = self.peak.max(src.len());
+
+        let decoded = self.codec.decode(src)?;
+
+        if decoded.is_none() && src.len() > self.limits.limit {
+            return Err(RedisError::QueryBufferLimit(src.len()));
+        }
+
+        // the burst is over and its requests own what they took from the buffer, the rest can go
+        if src.is_empty() && self.peak > self.limits.initial_size {
+            *src = BytesMut::with_capacity(self.limits.initial_size);
+            self.peak = 0;
+        }
+
+        self.counters.len.store(src.len(), Ordering::Relaxed);
+        self.counters
+            .free
+            .store(src.capacity() - src.len(), Ordering::Relaxed);
+
+        Ok(decoded)
+    }
+}
+
+/// One line of CLIENT LIST, minus what only the processor knows about the client.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub host_id: HostId,
+    pub age: Duration,
+    pub replica: bool,
+    pub output: OutputStats,
+    pub query: QueryStats,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientsActorHandle {
+    sender: mpsc::Sender<ClientsActorMessage>,
+    evicted_tx: broadcast::Sender<HostId>,
+}
+
+// Gives you access to the underlying actor.
+impl ClientsActorHandle {
+    /// maxmemory_clients is in bytes, 0 means no limit.
+    pub fn new(maxmemory_clients: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(8);
+        let (evicted_tx, _) = broadcast::channel(64);
+        let actor = ClientsActor::new(receiver, maxmemory_clients, evicted_tx.clone());
+
+        supervisor::spawn(actor);
+
+        Self { sender, evicted_tx }
+    }
+
+    /// Starts tracking a newly accepted connection and its buffers.
+    pub async fn connect(&self, host_id: HostId, output: OutputQueue, query: QueryBuffer) {
+        let msg = ClientsActorMessage::Connect {
+            host_id,
+            output,
+            query,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+    }
+
+    /// The connection turned out to be a replica, these are never evicted.
+    pub async fn mark_replica(&self, host_id: HostId) {
+        let msg = ClientsActorMessage::MarkReplica { host_id };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+    }
+
+    /// Stops tracking a closed connection.
+    pub async fn disconnect(&self, host_id: HostId) {
+        let msg = ClientsActorMessage::Disconnect { host_id };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+    }
+
+    /// Every connected client, oldest first.
+    /// https://redis.io/commands/client-list/
+    pub async fn list(&self) -> Result<Vec<ClientInfo>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = ClientsActorMessage::List { respond_to: send };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the clients actor"))
+    }
+
+    /// The clients dropped to get back under maxmemory-clients, each connection closes when it sees its own id.
+    pub fn evictions(&self) -> broadcast::Receiver<HostId> {
+        self.evicted_tx.subscribe()
+    }
+}
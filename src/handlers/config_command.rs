@@ -1,8 +1,10 @@
+use bytes::Bytes;
 use tokio::sync::{mpsc, oneshot};
 use tracing::debug;
 
 use crate::{
-    actors::{config::ConfigCommandActor, messages::ConfigActorMessage},
+    actors::{config::ConfigCommandActor, messages::ConfigActorMessage, supervisor},
+    errors::RedisError,
     protocol::ConfigCommandParameter,
 };
 
@@ -17,16 +19,19 @@ use anyhow::anyhow;
 impl ConfigCommandActorHandle {
     pub fn new() -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut actor = ConfigCommandActor::new(receiver);
+        let actor = ConfigCommandActor::new(receiver);
 
-        tokio::spawn(async move { actor.run().await });
+        supervisor::spawn(actor);
 
         Self { sender }
     }
 
     /// implements the redis CONFIG GET command, taking a key as input and returning a value.
     /// https://redis.io/commands/config-get/
-    pub async fn get_value(&self, config_key: ConfigCommandParameter) -> Option<String> {
+    pub async fn get_value(
+        &self,
+        config_key: ConfigCommandParameter,
+    ) -> Result<Option<String>, RedisError> {
         debug!("Getting value for key: {:?}", config_key);
         let (send, recv) = oneshot::channel();
         let msg = ConfigActorMessage::GetConfigValue {
@@ -41,16 +46,35 @@ impl ConfigCommandActorHandle {
 
         // this is going back once the msg comes back from the actor.
         // NOTE: we might get None back, i.e. no value for the given key.
-        if let Some(value) = recv.await.expect("Actor task has been killed") {
-            Some(value)
-        } else {
-            None
-        }
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the config actor"))
+    }
+
+    /// CONFIG GET with one or more names, aliases or globs.
+    /// Returns (name, value) pairs with every parameter listed once, in the order the patterns matched them.
+    pub async fn get_matching_values(
+        &self,
+        patterns: Vec<String>,
+    ) -> Result<Vec<(String, String)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = ConfigActorMessage::GetMatchingConfigValues {
+            patterns,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the config actor"))
     }
 
     /// implements the redis CONFIG SET command, taking a key, value pair as input. Returns nothing.
     /// https://redis.io/commands/config-set/
-    pub async fn set_value(&self, config_key: ConfigCommandParameter, config_value: &str) {
+    pub async fn set_value(
+        &self,
+        config_key: ConfigCommandParameter,
+        config_value: &str,
+    ) -> Result<(), RedisError> {
         let msg = ConfigActorMessage::SetConfigValue {
             config_key,
             config_value: config_value.to_string(),
@@ -60,25 +84,89 @@ impl ConfigCommandActorHandle {
             "Setting value for key: {:?}, value: {}",
             config_key, config_value
         );
-        // Ignore send errors.
-        let _ = self.sender.send(msg).await.expect("Failed to set value.");
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| RedisError::ActorGone("the config actor"))
     }
 
     /// Loads the config file on startup
     pub async fn import_config(
         &self,
         set_command_actor_handle: super::set_command::SetCommandActorHandle,
-        import_from_memory: Option<Vec<u8>>, // if None, load from disk. Otherwise, load from memory.
-        expire_tx: mpsc::Sender<crate::protocol::SetCommandParameter>,
-    ) {
+        import_from_memory: Option<Bytes>, // if None, load from disk. Otherwise, load from memory.
+    ) -> Result<(), RedisError> {
         let msg = ConfigActorMessage::ImportRdb {
             set_command_actor_handle,
             import_from_memory,
-            expire_tx, // this is a channel back to main.rs expiry loop
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| RedisError::ActorGone("the config actor"))
+    }
+
+    /// implements the redis SAVE command, writing the store's contents to dir/dbfilename once a
+    /// BGSAVE still writing is done.
+    /// https://redis.io/commands/save/
+    pub async fn save_rdb(
+        &self,
+        set_command_actor_handle: super::set_command::SetCommandActorHandle,
+    ) -> anyhow::Result<()> {
+        let (send, recv) = oneshot::channel();
+
+        let msg = ConfigActorMessage::SaveRdb {
+            set_command_actor_handle,
+            respond_to: send,
         };
 
         // Ignore send errors.
-        let _ = self.sender.send(msg).await.expect("Failed to set value.");
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the config actor"))?
+    }
+
+    /// implements the redis BGSAVE command: takes a snapshot of the store and writes it to
+    /// dir/dbfilename in the background, like SAVE. False if the last BGSAVE is still writing.
+    /// https://redis.io/commands/bgsave/
+    pub async fn bgsave_rdb(
+        &self,
+        set_command_actor_handle: super::set_command::SetCommandActorHandle,
+    ) -> anyhow::Result<bool> {
+        let (send, recv) = oneshot::channel();
+
+        let msg = ConfigActorMessage::BgsaveRdb {
+            set_command_actor_handle,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the config actor"))?
+    }
+
+    /// implements redis' DEBUG RELOAD: saves the store to dir/dbfilename, empties it and loads the
+    /// file back in. Returns how many keys were loaded.
+    pub async fn reload_rdb(
+        &self,
+        set_command_actor_handle: super::set_command::SetCommandActorHandle,
+    ) -> anyhow::Result<usize> {
+        let (send, recv) = oneshot::channel();
+
+        let msg = ConfigActorMessage::ReloadRdb {
+            set_command_actor_handle,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the config actor"))?
     }
 
     /// Tells the config actor to load rdb file into memory, and return it as a Vec<u8>
@@ -98,10 +186,19 @@ impl ConfigCommandActorHandle {
 
         // this is going back once the msg comes back from the actor.
         // NOTE: we might get None back, i.e. something bad happened trying to load config into memory.
-        if let Some(config_file) = recv.await.expect("Actor task has been killed") {
+        if let Some(config_file) = recv
+            .await
+            .map_err(|_| RedisError::ActorGone("the config actor"))?
+        {
             Ok(config_file)
         } else {
             Err(anyhow!("Failed to load config into memory."))
         }
     }
 }
+
+impl Default for ConfigCommandActorHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
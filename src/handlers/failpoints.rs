@@ -0,0 +1,56 @@
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    actors::{
+        failpoints::FailpointActor,
+        messages::{FailpointActorMessage, ReplicationFault},
+        supervisor,
+    },
+    errors::RedisError,
+    protocol::Failpoint,
+};
+
+#[derive(Clone, Debug)]
+pub struct FailpointActorHandle {
+    sender: mpsc::Sender<FailpointActorMessage>,
+}
+
+// Gives you access to the underlying actor.
+impl FailpointActorHandle {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel(8);
+        let actor = FailpointActor::new(receiver);
+
+        supervisor::spawn(actor);
+
+        Self { sender }
+    }
+
+    /// Arms (or with Failpoint::Off, clears) a fault on the master -> replica stream.
+    pub async fn arm(&self, failpoint: Failpoint) {
+        let msg = FailpointActorMessage::Arm { failpoint };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+    }
+
+    /// Asked by a replica link before every frame it writes.
+    pub async fn next_fault(&self) -> Result<ReplicationFault, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = FailpointActorMessage::NextFault { respond_to: send };
+
+        // Ignore send errors. If this send fails, so does the
+        // recv.await below. There's no reason to check the
+        // failure twice.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the failpoint actor"))
+    }
+}
+
+impl Default for FailpointActorHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
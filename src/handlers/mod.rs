@@ -1,5 +1,8 @@
-pub(crate) mod config_command;
-pub(crate) mod replication;
-pub(crate) mod request_processor;
-pub(crate) mod set_command;
+pub mod clients;
+pub mod config_command;
+pub mod failpoints;
+pub mod pubsub;
+pub mod replication;
+pub mod request_processor;
+pub mod set_command;
 // pub(crate) mod wait_command;
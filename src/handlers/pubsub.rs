@@ -0,0 +1,124 @@
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::{
+    actors::{
+        messages::{HostId, PubSubActorMessage, PubSubStats, SubscriptionCounts, SubscriptionKind},
+        pubsub::PubSubActor,
+        supervisor,
+    },
+    errors::RedisError,
+    handlers::clients::OutputQueue,
+};
+
+#[derive(Clone, Debug)]
+pub struct PubSubActorHandle {
+    sender: mpsc::Sender<PubSubActorMessage>,
+    evicted_tx: broadcast::Sender<HostId>,
+}
+
+// Gives you access to the underlying actor.
+impl PubSubActorHandle {
+    pub fn new(queue_limit: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(8);
+        let (evicted_tx, _) = broadcast::channel(64);
+        let actor = PubSubActor::new(receiver, queue_limit, evicted_tx.clone());
+
+        supervisor::spawn(actor);
+
+        Self { sender, evicted_tx }
+    }
+
+    /// implements the redis SUBSCRIBE and PSUBSCRIBE commands, returning the client's subscription
+    /// counts after each channel or pattern. Published messages are pushed onto queue.
+    /// https://redis.io/commands/subscribe/
+    /// https://redis.io/commands/psubscribe/
+    pub async fn subscribe(
+        &self,
+        host_id: HostId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+        queue: OutputQueue,
+    ) -> Result<Vec<SubscriptionCounts>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = PubSubActorMessage::Subscribe {
+            host_id,
+            kind,
+            names,
+            queue,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the pubsub actor"))
+    }
+
+    /// implements the redis UNSUBSCRIBE and PUNSUBSCRIBE commands, returning each channel or
+    /// pattern with the subscriptions left after it.
+    /// https://redis.io/commands/unsubscribe/
+    /// https://redis.io/commands/punsubscribe/
+    pub async fn unsubscribe(
+        &self,
+        host_id: HostId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+    ) -> Result<Vec<(String, SubscriptionCounts)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = PubSubActorMessage::Unsubscribe {
+            host_id,
+            kind,
+            names,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the pubsub actor"))
+    }
+
+    /// implements the redis PUBLISH command, returning how many clients got the message.
+    /// https://redis.io/commands/publish/
+    pub async fn publish(&self, channel: String, message: String) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = PubSubActorMessage::Publish {
+            channel,
+            message,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the pubsub actor"))
+    }
+
+    /// How many clients are subscribed, and to how many channels and patterns, for INFO clients.
+    pub async fn stats(&self) -> Result<PubSubStats, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = PubSubActorMessage::Stats { respond_to: send };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the pubsub actor"))
+    }
+
+    /// Forgets the connection's subscriptions once it is gone.
+    pub async fn disconnect(&self, host_id: HostId) {
+        let msg = PubSubActorMessage::Disconnect { host_id };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+    }
+
+    /// The clients dropped for falling too far behind, each connection closes when it sees its own id.
+    pub fn evictions(&self) -> broadcast::Receiver<HostId> {
+        self.evicted_tx.subscribe()
+    }
+}
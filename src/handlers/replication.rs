@@ -1,11 +1,15 @@
+use std::time::Duration;
+
 use tokio::sync::{mpsc, oneshot};
 use tracing::debug;
 
 use crate::{
     actors::{
-        messages::{HostId, ReplicatorActorMessage},
+        messages::{ApplyBacklog, HostId, ReplicaLag, ReplicatorActorMessage},
         replicator::ReplicatorActor,
+        supervisor,
     },
+    errors::RedisError,
     protocol::ReplicationSectionData,
 };
 
@@ -18,9 +22,9 @@ pub struct ReplicationActorHandle {
 impl ReplicationActorHandle {
     pub fn new() -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut actor = ReplicatorActor::new(receiver);
+        let actor = ReplicatorActor::new(receiver);
 
-        tokio::spawn(async move { actor.run().await });
+        supervisor::spawn(actor);
 
         Self { sender }
     }
@@ -30,7 +34,7 @@ impl ReplicationActorHandle {
     pub async fn get_value(
         &self,
         host_id: HostId, //hostIP:port combo
-    ) -> Option<ReplicationSectionData> {
+    ) -> Result<Option<ReplicationSectionData>, RedisError> {
         debug!("Getting info value for key: {:?}", host_id);
         let (send, recv) = oneshot::channel();
         let msg = ReplicatorActorMessage::GetReplicationValue {
@@ -46,22 +50,18 @@ impl ReplicationActorHandle {
 
         // this is going back once the msg comes back from the actor.
         // NOTE: we might get None back, i.e. no value for the given key.
-        if let Some(value) = recv.await.expect("Actor task has been killed") {
-            Some(value)
-        } else {
-            None
-        }
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
     }
 
     /// Resets the master's current replica tracked offset to 0.
-    pub async fn reset_replica_offset(&self, host_id: HostId) {
+    pub async fn reset_replica_offset(&self, host_id: HostId) -> Result<(), RedisError> {
         let msg = ReplicatorActorMessage::ResetReplicaOffset { host_id };
-        // Ignore send errors.
-        let _ = self
-            .sender
+
+        self.sender
             .send(msg)
             .await
-            .expect("Should have reset the replication value.");
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
     }
 
     /// Updates sections for redis REPLICATION command, taking a key, value pair as input. Returns nothing.
@@ -71,7 +71,7 @@ impl ReplicationActorHandle {
         // info_key: InfoCommandParameter,
         host_id: HostId,
         replication_value: ReplicationSectionData,
-    ) {
+    ) -> Result<(), RedisError> {
         debug!(
             "HANDLER: Setting REPLICATION key: {:?}, value: {}",
             host_id, replication_value
@@ -82,12 +82,14 @@ impl ReplicationActorHandle {
             replication_value,
         };
 
-        // Ignore send errors.
-        let _ = self.sender.send(msg).await.expect("Failed to set value.");
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
     }
 
     /// Returns the number of replicas that are in sync.
-    pub async fn get_synced_replica_count(&self, target_offset: i16) -> usize {
+    pub async fn get_synced_replica_count(&self, target_offset: i64) -> Result<usize, RedisError> {
         let (send, recv) = oneshot::channel();
         let msg = ReplicatorActorMessage::GetReplicaCount { respond_to: send, target_offset };
 
@@ -98,6 +100,87 @@ impl ReplicationActorHandle {
 
         // this is going back once the msg comes back from the actor.
         // NOTE: we might get None back, i.e. no value for the given key.
-        recv.await.expect("Actor task has been killed")
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
+    }
+
+    /// Returns every connected replica, in the order they connected.
+    pub async fn get_replicas(&self) -> Result<Vec<(HostId, ReplicationSectionData)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = ReplicatorActorMessage::GetReplicas { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
+    }
+
+    /// Returns the number of connected replicas, in sync or not.
+    pub async fn get_connected_replica_count(&self) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = ReplicatorActorMessage::GetConnectedReplicaCount { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
+    }
+
+    /// Records a replica's REPLCONF ACK: the offset it has applied, and that it was heard from now.
+    pub async fn acknowledge(&self, host_id: HostId, offset: i64) -> Result<(), RedisError> {
+        let msg = ReplicatorActorMessage::Acknowledge { host_id, offset };
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
+    }
+
+    /// How far behind each connected replica is, in the order they connected.
+    pub async fn get_replica_lags(&self) -> Result<Vec<(HostId, ReplicaLag)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = ReplicatorActorMessage::GetReplicaLags { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
+    }
+
+    /// Records that a replica applied a write from its master in took, with bytes of the stream and
+    /// queued requests still waiting behind it.
+    pub async fn record_apply(&self, bytes: usize, queued: usize, took: Duration, slow: bool) {
+        let msg = ReplicatorActorMessage::RecordApply {
+            bytes,
+            queued,
+            took,
+            slow,
+        };
+
+        let _ = self.sender.send(msg).await;
+    }
+
+    /// How this replica keeps up with its master's stream.
+    pub async fn get_apply_backlog(&self) -> Result<ApplyBacklog, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = ReplicatorActorMessage::GetApplyBacklog { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await
+            .map_err(|_| RedisError::ActorGone("the replication actor"))
+    }
+
+    /// Forgets a host once its connection is closed.
+    pub async fn remove_host(&self, host_id: HostId) {
+        let msg = ReplicatorActorMessage::RemoveHost { host_id };
+
+        let _ = self.sender.send(msg).await;
+    }
+}
+
+impl Default for ReplicationActorHandle {
+    fn default() -> Self {
+        Self::new()
     }
 }
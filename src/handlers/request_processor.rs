@@ -1,10 +1,14 @@
 use crate::{
     actors::{
-        messages::{HostId, ProcessorActorMessage},
+        messages::{BlockedPop, FastPathClients, HostId, ProcessorActorMessage},
         processor::ProcessorActor,
+        supervisor,
     },
+    auth::{DefaultUser, SharedAuthenticator},
+    commandstats::CommandStatsTable,
+    errors::RedisError,
     handlers::set_command::SetCommandActorHandle,
-    protocol::SetCommandParameter,
+    monitor::{CommandEvent, CommandFeed},
     resp::value::RespValue,
 };
 
@@ -12,42 +16,97 @@ use crate::{
 
 // use tracing::debug;
 // use resp::Value;
-use tokio::sync::{broadcast, mpsc, oneshot};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot},
+    time::timeout,
+};
+
+use super::{
+    clients::{ClientsActorHandle, OutputQueue},
+    config_command::ConfigCommandActorHandle,
+    failpoints::FailpointActorHandle,
+    pubsub::PubSubActorHandle,
+    replication::ReplicationActorHandle,
+};
 
-use super::{config_command::ConfigCommandActorHandle, replication::ReplicationActorHandle};
+/// How long a request waits for its reply unless --request-timeout says otherwise.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Debug)]
 pub struct RequestProcessorActorHandle {
     sender: mpsc::Sender<ProcessorActorMessage>,
+    // None waits as long as it takes
+    request_timeout: Option<Duration>,
+    panicked_tx: broadcast::Sender<HostId>,
+    command_stats: CommandStatsTable,
+    command_feed: CommandFeed,
+    fast_path: FastPathClients,
 }
 
 // Gives you access to the underlying actor.
 impl RequestProcessorActorHandle {
     pub fn new() -> Self {
+        Self::with_request_timeout(Some(DEFAULT_REQUEST_TIMEOUT))
+    }
+
+    /// Starts the actor. A request without a reply after request_timeout gets an error instead.
+    pub fn with_request_timeout(request_timeout: Option<Duration>) -> Self {
+        Self::with_authenticator(request_timeout, Arc::new(DefaultUser))
+    }
+
+    /// Same, with AUTH and HELLO checking credentials with authenticator, see auth.rs.
+    pub fn with_authenticator(
+        request_timeout: Option<Duration>,
+        authenticator: SharedAuthenticator,
+    ) -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut actor = ProcessorActor::new(receiver);
+        let (panicked_tx, _) = broadcast::channel(64);
+        let command_stats = CommandStatsTable::default();
+        let command_feed = CommandFeed::default();
+        let fast_path = FastPathClients::default();
+        let actor = ProcessorActor::new(
+            receiver,
+            sender.downgrade(),
+            panicked_tx.clone(),
+            command_stats.clone(),
+            authenticator,
+            command_feed.clone(),
+            fast_path.clone(),
+        );
 
-        // This isn't going to fail, so we are ignoring errors here.
-        let _handle = tokio::spawn(async move { actor.run().await });
+        supervisor::spawn(actor);
 
-        Self { sender }
+        Self {
+            sender,
+            request_timeout,
+            panicked_tx,
+            command_stats,
+            command_feed,
+            fast_path,
+        }
     }
 
     /// Takes RESP frames, parses them into Redis commands and returns proper replies back to the requestor.
     /// https://redis.io/commands/
+    #[allow(clippy::too_many_arguments)]
     pub async fn process_request(
         &self,
         request: RespValue,
         set_command_actor_handle: SetCommandActorHandle,
         config_command_actor_handle: ConfigCommandActorHandle,
         replication_actor_handle: ReplicationActorHandle,
+        failpoint_actor_handle: FailpointActorHandle,
+        pubsub_actor_handle: PubSubActorHandle,
+        clients_actor_handle: ClientsActorHandle,
         host_id: HostId,
-        expire_tx: mpsc::Sender<SetCommandParameter>,
         master_tx: mpsc::Sender<String>,
         replica_tx: broadcast::Sender<RespValue>, // we get this from master handler only
         client_or_replica_tx: Option<mpsc::Sender<bool>>,
-        wait_sleep_tx: Option<mpsc::Sender<i16>>,
-    ) -> Option<Vec<RespValue>> {
+        wait_sleep_tx: Option<mpsc::Sender<i64>>,
+        blocked_tx: Option<mpsc::Sender<BlockedPop>>,
+        push_tx: Option<OutputQueue>,
+    ) -> Result<Option<Vec<RespValue>>, RedisError> {
         tracing::debug!("Processing request: {:?}", request);
         // create a multiple producer, single consumer channel
         let (send, recv) = oneshot::channel();
@@ -57,28 +116,86 @@ impl RequestProcessorActorHandle {
             set_command_actor_handle,
             config_command_actor_handle,
             replication_actor_handle,
+            failpoint_actor_handle,
+            pubsub_actor_handle,
+            clients_actor_handle,
             host_id,
-            expire_tx,
             master_tx,
             replica_tx,
             client_or_replica_tx,
             respond_to: send,
             wait_sleep_tx,
+            blocked_tx,
+            push_tx,
         };
 
-        // Ignore send errors. If this send fails, so does the
-        // recv.await below. There's no reason to check the
-        // failure twice.
+        let reply = async {
+            // Ignore send errors. If this send fails, so does the
+            // recv.await below. There's no reason to check the
+            // failure twice.
+            let _ = self.sender.send(msg).await;
+
+            recv.await
+                .map_err(|_| RedisError::ActorGone("the request processor"))
+        };
+
+        // NOTE: a request that times out may still run later, only its reply is given up on.
+        let value = match self.request_timeout {
+            Some(request_timeout) => timeout(request_timeout, reply)
+                .await
+                .map_err(|_| RedisError::Timeout)??,
+            None => reply.await?,
+        };
+
+        tracing::debug!("Processor actor returns {:?}", value);
+        Ok(value)
+    }
+
+    /// Requests waiting for the processor, every connection's together.
+    pub fn queued(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// The clients whose request the processor panicked on, each connection closes when it sees its
+    /// own id. What the processor keeps for it may be half updated.
+    pub fn panics(&self) -> broadcast::Receiver<HostId> {
+        self.panicked_tx.subscribe()
+    }
+
+    /// What INFO commandstats reports, for the commands answered without the processor to count
+    /// themselves in.
+    pub fn command_stats(&self) -> &CommandStatsTable {
+        &self.command_stats
+    }
+
+    /// Every command the processor runs from now on with its outcome, what MONITOR and the audit
+    /// log read.
+    pub fn monitor(&self) -> broadcast::Receiver<CommandEvent> {
+        self.command_feed.subscribe()
+    }
+
+    /// Whether a MONITOR is running, which has to see every command go through the processor.
+    pub fn is_monitored(&self) -> bool {
+        self.command_feed.is_monitored()
+    }
+
+    /// Whether the connection may answer a GET itself, once the processor has seen it is
+    /// authenticated and not subscribed in RESP2. Nothing the processor would refuse gets through.
+    pub fn may_skip_processor(&self, host_id: &HostId) -> bool {
+        !self.is_monitored() && self.fast_path.read().unwrap().contains(host_id)
+    }
+
+    /// Lets the processor drop the per-client state it keeps for a closed connection.
+    pub async fn disconnect(&self, host_id: HostId) {
+        let msg = ProcessorActorMessage::Disconnect { host_id };
+
+        // Ignore send errors.
         let _ = self.sender.send(msg).await;
+    }
+}
 
-        if let Some(value) = recv
-            .await
-            .expect("Request processor actor task has been killed")
-        {
-            tracing::info!("Processor actor returns {:?}", value);
-            Some(value)
-        } else {
-            None
-        }
+impl Default for RequestProcessorActorHandle {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -1,31 +1,148 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
 use tokio::sync::{mpsc, oneshot};
 // pub mod actors;
 
 use crate::{
-    actors::{messages::SetActorMessage, set::SetCommandActor},
-    protocol::SetCommandParameter,
+    actors::{
+        messages::{
+            DatabaseSnapshot, ExpiryStats, KeyspaceEvent, KeyspaceStats, SetActorMessage,
+            SetOutcome,
+        },
+        set::{Database, SetCommandActor, VolatileCursor},
+        supervisor,
+    },
+    clock::{SharedClock, SystemClock},
+    collections::{Collection, ListEnd, SortedSet, Stream, StreamId},
+    digest,
+    errors::RedisError,
+    eviction::MaxmemoryPolicy,
+    protocol::{
+        SetCommandExpireOption, SetCommandParameter, XaddCommandParameter, ZaddCommandParameter,
+    },
+    storage::{self, Kind, Object, OpenStore},
 };
 
+/// Same default as redis.
+pub const DEFAULT_DATABASES: usize = 16;
+
 #[derive(Clone, Debug)]
 pub struct SetCommandActorHandle {
     sender: mpsc::Sender<SetActorMessage>,
+    databases: usize,
+    // the actor's databases, read directly by read_value()
+    shared_databases: Arc<RwLock<Vec<Database>>>,
+    // the actor's count of changes to the keys, read by dirty()
+    dirty: Arc<AtomicU64>,
+    // the keyspace notification bus, None when notify-keyspace-events is off
+    notifications: Option<mpsc::UnboundedSender<KeyspaceEvent>>,
+    // what the actor compares deadlines against
+    clock: SharedClock,
 }
 
 // Gives you access to the underlying actor.
 impl SetCommandActorHandle {
     pub fn new() -> Self {
+        Self::with_databases(DEFAULT_DATABASES)
+    }
+
+    /// Starts the actor with `databases` numbered databases, 0 through databases - 1.
+    pub fn with_databases(databases: usize) -> Self {
+        Self::with_notifications(databases, None)
+    }
+
+    /// Same, and every change to a key is also sent to notifications as a KeyspaceEvent.
+    pub fn with_notifications(
+        databases: usize,
+        notifications: Option<mpsc::UnboundedSender<KeyspaceEvent>>,
+    ) -> Self {
+        let memory = storage::backend("memory").expect("the memory backend is always there");
+
+        Self::with_backend(databases, notifications, memory)
+    }
+
+    /// Same, with every database kept in the store open_store opens for it instead of in memory.
+    pub fn with_backend(
+        databases: usize,
+        notifications: Option<mpsc::UnboundedSender<KeyspaceEvent>>,
+        open_store: OpenStore,
+    ) -> Self {
+        Self::with_clock(databases, notifications, open_store, Arc::new(SystemClock))
+    }
+
+    /// Same, with deadlines held against clock instead of the system time.
+    pub fn with_clock(
+        databases: usize,
+        notifications: Option<mpsc::UnboundedSender<KeyspaceEvent>>,
+        open_store: OpenStore,
+        clock: SharedClock,
+    ) -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut actor = SetCommandActor::new(receiver);
-        tokio::spawn(async move { actor.run().await });
+        let actor = SetCommandActor::new(
+            receiver,
+            databases,
+            notifications.clone(),
+            open_store,
+            Arc::clone(&clock),
+        );
+        let shared_databases = actor.shared_databases();
+        let dirty = actor.shared_dirty();
+        supervisor::spawn(actor);
 
-        Self { sender }
+        Self {
+            sender,
+            databases,
+            shared_databases,
+            dirty,
+            notifications,
+            clock,
+        }
+    }
+
+    /// Puts a keyspace notification on the bus, if notify-keyspace-events is on. The commands
+    /// call this, the store itself only reports keys that expired.
+    pub fn notify(&self, db: usize, class: char, event: &'static str, key: &str) {
+        if let Some(notifications) = &self.notifications {
+            let _ = notifications.send(KeyspaceEvent {
+                db,
+                class,
+                event,
+                key: key.to_string(),
+            });
+        }
+    }
+
+    /// How many changes the commands made to the keys so far, redis' server.dirty. A write that
+    /// leaves it where it was changed nothing, and has nothing to tell the replicas.
+    pub fn dirty(&self) -> u64 {
+        self.dirty.load(Ordering::Relaxed)
     }
 
-    /// implements the redis GET command, taking a key as input and returning a value.
+    /// The store's time, unix milliseconds, what deadlines are compared against.
+    pub fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
+    }
+
+    /// How many databases the store was started with. Valid indexes are below this.
+    pub fn databases(&self) -> usize {
+        self.databases
+    }
+
+    /// implements the redis GET command, taking a key as input and returning a value. WrongType if
+    /// the key holds something other than a string.
     /// https://redis.io/commands/get/
-    pub async fn get_value(&self, key: &str) -> Option<String> {
+    pub async fn get_value(&self, db: usize, key: &str) -> Result<Option<Vec<u8>>, RedisError> {
         let (send, recv) = oneshot::channel();
         let msg = SetActorMessage::GetValue {
+            db,
             key: key.to_string(),
             respond_to: send,
         };
@@ -37,65 +154,940 @@ impl SetCommandActorHandle {
 
         // this is going back once the msg comes back from the actor.
         // NOTE: we might get None back, i.e. no value for the given key.
-        if let Some(value) = recv.await.expect("Actor task has been killed") {
-            Some(value)
-        } else {
-            None
-        }
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
     }
 
-    /// implements the redis KEYS command, taking a pattern as input and returning a list of keys.
-    /// https://redis.io/commands/keys/
-    pub async fn get_keys(&self, pattern: &str) -> Option<Vec<String>> {
+    /// get_value() for every key in one round trip to the actor, for MGET. The values come back
+    /// in the order of keys, None for the missing ones.
+    pub async fn get_values(
+        &self,
+        db: usize,
+        keys: &[String],
+    ) -> Result<Vec<Option<Vec<u8>>>, RedisError> {
         let (send, recv) = oneshot::channel();
-        let msg = SetActorMessage::GetKeys {
-            pattern: pattern.to_string(),
+        let msg = SetActorMessage::GetValues {
+            db,
+            keys: keys.to_vec(),
             respond_to: send,
         };
 
-        // Ignore send errors. If this send fails, so does the
-        // recv.await below. There's no reason to check the
-        // failure twice.
         let _ = self.sender.send(msg).await;
 
-        if let Some(keys) = recv.await.expect("Actor task has been killed") {
-            Some(keys)
-        } else {
-            None
-        }
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// GET without going through the actor: reads the store's databases under a read lock.
+    /// A key past its deadline is missing here even if the active expiry cycle hasn't removed it yet.
+    pub fn read_value(&self, db: usize, key: &str) -> Result<Option<Vec<u8>>, RedisError> {
+        // poisoned once the actor panics in the middle of a write, and the actor is gone with it
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        let Some(database) = databases.get(db) else {
+            return Ok(None);
+        };
+        let now_ms = self.clock.now_ms();
+
+        database.typed(key, Kind::String, now_ms)?;
+
+        Ok(database.live_value(key, now_ms).map(Cow::into_owned))
+    }
+
+    /// Reads the hash at key with read, under the read lock like read_value(). None if there is no
+    /// such key, WrongType if it holds something else.
+    pub fn read_hash<T>(
+        &self,
+        db: usize,
+        key: &str,
+        read: impl FnOnce(&HashMap<String, String>) -> T,
+    ) -> Result<Option<T>, RedisError> {
+        self.read_collection(db, key, Kind::Hash, Collection::as_hash, read)
+    }
+
+    /// read_hash() for the list at key.
+    pub fn read_list<T>(
+        &self,
+        db: usize,
+        key: &str,
+        read: impl FnOnce(&VecDeque<String>) -> T,
+    ) -> Result<Option<T>, RedisError> {
+        self.read_collection(db, key, Kind::List, Collection::as_list, read)
+    }
+
+    /// read_hash() for the set at key.
+    pub fn read_set<T>(
+        &self,
+        db: usize,
+        key: &str,
+        read: impl FnOnce(&HashSet<String>) -> T,
+    ) -> Result<Option<T>, RedisError> {
+        self.read_collection(db, key, Kind::Set, Collection::as_set, read)
+    }
+
+    /// read_hash() for the sorted set at key.
+    pub fn read_sorted_set<T>(
+        &self,
+        db: usize,
+        key: &str,
+        read: impl FnOnce(&SortedSet) -> T,
+    ) -> Result<Option<T>, RedisError> {
+        self.read_collection(db, key, Kind::SortedSet, Collection::as_sorted_set, read)
     }
-    /// implements the redis SET command, taking a key, value pair as input. Returns nothing.
+
+    /// read_hash() for the stream at key.
+    pub fn read_stream<T>(
+        &self,
+        db: usize,
+        key: &str,
+        read: impl FnOnce(&Stream) -> T,
+    ) -> Result<Option<T>, RedisError> {
+        self.read_collection(db, key, Kind::Stream, Collection::as_stream, read)
+    }
+
+    /// The collections at keys as they are now, all taken at once, None for a key that doesn't
+    /// exist. The store goes on without waiting for whatever reads them, and copies one that
+    /// changes in the meantime. WrongType if one of the keys holds a string.
+    pub fn snapshot_collections(
+        &self,
+        db: usize,
+        keys: &[String],
+    ) -> Result<Vec<Option<Arc<Collection>>>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        let Some(database) = databases.get(db) else {
+            return Ok(vec![None; keys.len()]);
+        };
+        let now_ms = self.clock.now_ms();
+
+        keys.iter()
+            .map(|key| match database.kind(key, now_ms) {
+                Some(Kind::String) => Err(RedisError::WrongType),
+                _ => Ok(database.live_collection(key, now_ms)),
+            })
+            .collect()
+    }
+
+    // Reads the collection at key with read if it is of kind, as_kind being the accessor for it.
+    fn read_collection<C, T>(
+        &self,
+        db: usize,
+        key: &str,
+        kind: Kind,
+        as_kind: impl FnOnce(&Collection) -> Option<&C>,
+        read: impl FnOnce(&C) -> T,
+    ) -> Result<Option<T>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        let Some(database) = databases.get(db) else {
+            return Ok(None);
+        };
+        let now_ms = self.clock.now_ms();
+
+        database.typed(key, kind, now_ms)?;
+
+        Ok(database
+            .live_collection(key, now_ms)
+            .as_deref()
+            .and_then(as_kind)
+            .map(read))
+    }
+
+    /// The value at key whatever its type, read like read_value(), for DEBUG OBJECT. None if there
+    /// is no such key.
+    pub fn read_object(&self, db: usize, key: &str) -> Result<Option<Object>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        let Some(database) = databases.get(db) else {
+            return Ok(None);
+        };
+        let now_ms = self.clock.now_ms();
+
+        Ok(match database.kind(key, now_ms) {
+            None => None,
+            Some(Kind::String) => database
+                .live_value(key, now_ms)
+                .map(|value| Object::String(value.into_owned())),
+            Some(_) => database
+                .live_collection(key, now_ms)
+                .map(Object::Collection),
+        })
+    }
+
+    /// The bytes used_memory counts for key and its value, read like read_value(), for MEMORY USAGE.
+    pub fn memory_usage(&self, db: usize, key: &str) -> Result<Option<usize>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        Ok(databases
+            .get(db)
+            .and_then(|database| database.live_size(key, self.clock.now_ms())))
+    }
+
+    /// How many of keys are in the database, read like read_value(), for EXISTS. A key in keys
+    /// more than once is counted as often.
+    pub fn count_existing(&self, db: usize, keys: &[String]) -> Result<usize, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+        let now_ms = self.clock.now_ms();
+
+        Ok(databases.get(db).map_or(0, |database| {
+            keys.iter()
+                .filter(|key| database.is_live(key, now_ms))
+                .count()
+        }))
+    }
+
+    /// Every key of a database, read like read_value() without going through the actor.
+    /// The keys are shared with the store, matching them against a KEYS pattern is up to the caller.
+    pub fn keys(&self, db: usize) -> Result<Vec<Arc<str>>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        Ok(databases
+            .get(db)
+            .map(|database| database.live_keys(self.clock.now_ms()))
+            .unwrap_or_default())
+    }
+
+    /// The deadline of a key for TTL and PTTL, read like read_value(). None if there is no such
+    /// key, Some(None) if it doesn't expire.
+    pub fn deadline(&self, db: usize, key: &str) -> Result<Option<Option<u64>>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        Ok(databases
+            .get(db)
+            .and_then(|database| database.live_deadline(key, self.clock.now_ms())))
+    }
+
+    /// The keys of a database that have a deadline, with it, read like read_value() for DEBUG JMAP.
+    pub fn deadlines(&self, db: usize) -> Result<Vec<(String, u64)>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        Ok(databases
+            .get(db)
+            .map(|database| database.deadlines())
+            .unwrap_or_default())
+    }
+
+    /// DEBUG DIGEST, a digest of every key of every database, read like read_value(). Databases
+    /// with nothing GET would find leave it alone, so an empty dataset is EMPTY_DIGEST.
+    pub fn digest(&self) -> Result<u64, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+        let now_ms = self.clock.now_ms();
+
+        Ok(databases
+            .iter()
+            .enumerate()
+            .map(|(db, database)| (db, database.keys_digest(now_ms)))
+            .filter(|(_, keys_digest)| *keys_digest != digest::EMPTY_DIGEST)
+            .fold(digest::EMPTY_DIGEST, |digest, (db, keys_digest)| {
+                digest ^ digest::database_digest(db, keys_digest)
+            }))
+    }
+
+    /// DEBUG DIGEST-VALUE, the digest of the value at each of keys, read like read_value().
+    /// EMPTY_DIGEST for a key that isn't there.
+    pub fn value_digests(&self, db: usize, keys: &[String]) -> Result<Vec<u64>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+        let now_ms = self.clock.now_ms();
+
+        Ok(keys
+            .iter()
+            .map(|key| {
+                databases
+                    .get(db)
+                    .and_then(|database| database.value_digest(key, now_ms))
+                    .unwrap_or(digest::EMPTY_DIGEST)
+            })
+            .collect())
+    }
+
+    /// One step of the keyspace sampler over a database, under the read lock like read_value().
+    /// Returns where the next step picks up, None to start the next pass from the first key.
+    pub(crate) fn sample_avg_ttl(
+        &self,
+        db: usize,
+        after: Option<VolatileCursor>,
+        count: usize,
+    ) -> Result<Option<VolatileCursor>, RedisError> {
+        let databases = self
+            .shared_databases
+            .read()
+            .map_err(|_| RedisError::ActorGone("the store"))?;
+
+        Ok(databases
+            .get(db)
+            .and_then(|database| database.sample_avg_ttl(after, count, self.clock.now_ms())))
+    }
+
+    /// One step of the redis SCAN command, returning the next cursor and the keys it covered.
+    /// https://redis.io/commands/scan/
+    /// With a kind, only the keys holding it are returned, as with TYPE. The step covers count keys
+    /// either way, so it may come back empty.
+    pub async fn scan(
+        &self,
+        db: usize,
+        cursor: u64,
+        count: usize,
+        kind: Option<Kind>,
+    ) -> Result<(u64, Vec<String>), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::Scan {
+            db,
+            cursor,
+            count,
+            kind,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// implements the redis DBSIZE command, returning the number of keys in the database.
+    /// https://redis.io/commands/dbsize/
+    pub async fn db_size(&self, db: usize) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::DbSize {
+            db,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Copies the contents of every non-empty database, for writing them out as RDB.
+    pub async fn get_snapshot(&self) -> Result<Vec<DatabaseSnapshot>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::GetSnapshot { respond_to: send };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Counters behind INFO keyspace, for every database that has keys.
+    pub async fn get_keyspace_stats(&self) -> Result<Vec<KeyspaceStats>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::GetKeyspaceStats { respond_to: send };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// implements the redis SET command, taking a key, value pair as input. Returns whether NX or
+    /// XX let it write, and the value it replaced for GET.
     pub async fn set_value(
         &self,
-        expire_tx: mpsc::Sender<SetCommandParameter>,
+        db: usize,
         set_parameters: SetCommandParameter,
-    ) {
+    ) -> Result<SetOutcome, RedisError> {
+        let (send, recv) = oneshot::channel();
         let msg = SetActorMessage::SetValue {
-            input: set_parameters.clone(),
+            db,
+            input: set_parameters,
+            respond_to: send,
+        };
+
+        // Ignore send errors. If this send fails, so does the
+        // recv.await below.
+        let _ = self.sender.send(msg).await;
+
+        // wait for the write to land, so whoever reads next sees it
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis APPEND command, appending to the stored value where it is instead of
+    /// writing back a copy. Returns the length of the value, or StringTooLong if it would get
+    /// past max_len, in which case it stays as it was. WrongType if the key isn't a string.
+    /// https://redis.io/commands/append/
+    pub async fn append_value(
+        &self,
+        db: usize,
+        key: &str,
+        value: Vec<u8>,
+        max_len: usize,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::AppendValue {
+            db,
+            key: key.to_string(),
+            value,
+            max_len,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis SETRANGE command, overwriting the stored value from offset on, zero
+    /// padded if it was shorter. Returns the length of the value, or StringTooLong if it would get
+    /// past max_len, in which case it stays as it was.
+    /// https://redis.io/commands/setrange/
+    pub async fn set_range(
+        &self,
+        db: usize,
+        key: &str,
+        offset: usize,
+        value: Vec<u8>,
+        max_len: usize,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::SetRangeValue {
+            db,
+            key: key.to_string(),
+            offset,
+            value,
+            max_len,
+            respond_to: send,
+        };
+
+        // Ignore send errors.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// Adds by to the integer stored at key, 0 if there is none, and returns what it comes to.
+    /// NotAnInteger if the value isn't one, Overflow if the sum doesn't fit, either way the value
+    /// stays as it was.
+    /// https://redis.io/commands/incrby/
+    pub async fn incr_value(&self, db: usize, key: &str, by: i64) -> Result<i64, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::IncrValue {
+            db,
+            key: key.to_string(),
+            by,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis HSET command, creating the hash if there is none. Returns how many of
+    /// the fields are new, WrongType if the key holds something else.
+    /// https://redis.io/commands/hset/
+    pub async fn hset(
+        &self,
+        db: usize,
+        key: &str,
+        pairs: Vec<(String, String)>,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::HsetValue {
+            db,
+            key: key.to_string(),
+            pairs,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis HDEL command, removing the key along with its last field. Returns how
+    /// many of the fields were there, WrongType if the key holds something else.
+    /// https://redis.io/commands/hdel/
+    pub async fn hdel(
+        &self,
+        db: usize,
+        key: &str,
+        fields: Vec<String>,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::HdelValue {
+            db,
+            key: key.to_string(),
+            fields,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis SADD command, creating the set if there is none. Returns how many of
+    /// the members are new, WrongType if the key holds something else.
+    /// https://redis.io/commands/sadd/
+    pub async fn sadd(
+        &self,
+        db: usize,
+        key: &str,
+        members: Vec<String>,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::SaddValue {
+            db,
+            key: key.to_string(),
+            members,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis SREM command, removing the key along with its last member. Returns how
+    /// many of the members were there, WrongType if the key holds something else.
+    /// https://redis.io/commands/srem/
+    pub async fn srem(
+        &self,
+        db: usize,
+        key: &str,
+        members: Vec<String>,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::SremValue {
+            db,
+            key: key.to_string(),
+            members,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis ZADD command, creating the sorted set if a member gets added. Returns
+    /// how many members were added and how many got a new score, WrongType if the key holds
+    /// something else. The flags are taken as they are, refusing the ones that can't go together
+    /// is up to the caller.
+    /// https://redis.io/commands/zadd/
+    pub async fn zadd(
+        &self,
+        db: usize,
+        input: ZaddCommandParameter,
+    ) -> Result<(usize, usize), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::ZaddValue {
+            db,
+            input,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis ZREM command, removing the key along with its last member. Returns how
+    /// many of the members were there, WrongType if the key holds something else.
+    /// https://redis.io/commands/zrem/
+    pub async fn zrem(
+        &self,
+        db: usize,
+        key: &str,
+        members: Vec<String>,
+    ) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::ZremValue {
+            db,
+            key: key.to_string(),
+            members,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis ZINCRBY command, creating the sorted set and the member as needed.
+    /// Returns the member's new score, WrongType if the key holds something else and ScoreNaN if
+    /// the sum isn't a number.
+    /// https://redis.io/commands/zincrby/
+    pub async fn zincrby(
+        &self,
+        db: usize,
+        key: &str,
+        member: &str,
+        increment: f64,
+    ) -> Result<f64, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::ZincrbyValue {
+            db,
+            key: key.to_string(),
+            member: member.to_string(),
+            increment,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis XADD command, creating the stream as needed. Returns the ID the entry
+    /// went in with, WrongType if the key holds something else, StreamIdZero for 0-0 and
+    /// StreamIdTooSmall for an ID that isn't past the last one of the stream.
+    /// https://redis.io/commands/xadd/
+    pub async fn xadd(
+        &self,
+        db: usize,
+        input: XaddCommandParameter,
+    ) -> Result<StreamId, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::XaddValue {
+            db,
+            input,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis LPUSH and RPUSH commands, pushing values one after the other onto end
+    /// of the list, WrongType if the key holds something else. Blocked BLPOPs and BRPOPs on the
+    /// key are served from it right after. Returns the length of the list after the push, and the
+    /// end each of them popped from.
+    /// https://redis.io/commands/lpush/
+    pub async fn push(
+        &self,
+        db: usize,
+        key: &str,
+        end: ListEnd,
+        values: Vec<String>,
+    ) -> Result<(usize, Vec<ListEnd>), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::PushValue {
+            db,
+            key: key.to_string(),
+            end,
+            values,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis LPOP and RPOP commands, taking up to count elements off end of the list
+    /// and removing the key along with the last one. None if there is no such key.
+    /// https://redis.io/commands/lpop/
+    pub async fn pop(
+        &self,
+        db: usize,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<String>>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::PopValue {
+            db,
+            key: key.to_string(),
+            end,
+            count,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// implements the redis BLPOP and BRPOP commands, popping from the first of keys holding a
+    /// list. With nothing to pop the store keeps waiter, if given, for the next push to any of keys
+    /// to send the key and the element to.
+    /// https://redis.io/commands/blpop/
+    pub async fn blocking_pop(
+        &self,
+        db: usize,
+        keys: Vec<String>,
+        end: ListEnd,
+        waiter: Option<oneshot::Sender<(String, String)>>,
+    ) -> Result<Option<(String, String)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::BlockingPopValue {
+            db,
+            keys,
+            end,
+            waiter,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))?
+    }
+
+    /// Stores a whole collection at key in place of whatever was there, for loading an RDB.
+    pub async fn set_collection(
+        &self,
+        db: usize,
+        key: String,
+        collection: Collection,
+        expire: Option<SetCommandExpireOption>,
+    ) -> Result<(), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::SetCollection {
+            db,
+            key,
+            collection,
+            expire,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// implements the redis COPY command. Returns whether anything was copied: not if source is
+    /// missing, or destination exists and replace is false.
+    /// https://redis.io/commands/copy/
+    pub async fn copy_value(
+        &self,
+        db: usize,
+        source: &str,
+        destination_db: usize,
+        destination: &str,
+        replace: bool,
+    ) -> Result<bool, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::CopyValue {
+            db,
+            source: source.to_string(),
+            destination_db,
+            destination: destination.to_string(),
+            replace,
+            respond_to: send,
         };
 
         // Ignore send errors.
-        let _ = self.sender.send(msg).await.expect("Failed to set value.");
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Gives a key a new deadline, a unix timestamp in milliseconds. One that has already passed
+    /// deletes the key. Returns whether there was a key, the value is left as it is.
+    /// https://redis.io/commands/expire/
+    pub async fn expire_value(
+        &self,
+        db: usize,
+        key: &str,
+        deadline_ms: u64,
+    ) -> Result<bool, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::ExpireValue {
+            db,
+            key: key.to_string(),
+            deadline_ms,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
 
-        // let parameters = set_parameters.clone();
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Drops the deadline of a key, so it stays until it is deleted. Returns whether it had one.
+    /// https://redis.io/commands/persist/
+    pub async fn persist_value(&self, db: usize, key: &str) -> Result<bool, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::PersistValue {
+            db,
+            key: key.to_string(),
+            respond_to: send,
+        };
 
-        expire_tx
-            .send(set_parameters)
-            .await
-            .expect("Unable to start the expiry thread.");
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
     }
 
-    /// implements immediate removal of keys. This is triggered by a tokio::spawn sleep thread in main.rs
-    pub async fn delete_value(&self, key: &String) {
+    /// implements immediate removal of keys, whatever their deadline. Returns whether the key was
+    /// there, a key past its deadline was not.
+    pub async fn delete_value(&self, db: usize, key: &String) -> Result<bool, RedisError> {
+        let (send, recv) = oneshot::channel();
         let msg = SetActorMessage::DeleteValue {
+            db,
             value: key.to_string(),
+            respond_to: send,
+        };
+
+        // Ignore send errors. If this send fails, so does the
+        // recv.await below.
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// delete_value() for every key in one round trip to the actor, for DEL. Whether each key
+    /// was there comes back in the order of keys.
+    pub async fn delete_values(&self, db: usize, keys: &[String]) -> Result<Vec<bool>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::DeleteValues {
+            db,
+            keys: keys.to_vec(),
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Removes expired keys nobody has read, for at most time_limit. Returns once the cycle is over.
+    pub async fn active_expire_cycle(&self, time_limit: Duration) -> Result<(), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::ActiveExpireCycle {
+            time_limit,
+            respond_to: send,
         };
 
         // Ignore send errors.
-        let _ = self
-            .sender
-            .send(msg)
-            .await
-            .expect("Failed to expire value.");
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Evicts keys by policy until the dataset fits in maxmemory bytes again, or the policy
+    /// has nothing left to evict. Returns the database and name of each key evicted.
+    pub async fn evict(
+        &self,
+        maxmemory: usize,
+        policy: MaxmemoryPolicy,
+    ) -> Result<Vec<(usize, String)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::Evict {
+            maxmemory,
+            policy,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Evicts count keys by policy however much memory is used, for DEBUG EVICT. Fewer once
+    /// the policy has nothing left to evict, none under noeviction.
+    pub async fn evict_keys(
+        &self,
+        count: usize,
+        policy: MaxmemoryPolicy,
+    ) -> Result<Vec<(usize, String)>, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::EvictKeys {
+            count,
+            policy,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Bytes of keys and values in every database, INFO memory's used_memory.
+    pub async fn get_used_memory(&self) -> Result<usize, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::GetUsedMemory { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// The expired_* and evicted_keys counters of INFO stats.
+    pub async fn get_expiry_stats(&self) -> Result<ExpiryStats, RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::GetExpiryStats { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Zeroes the counters of INFO stats, for CONFIG RESETSTAT.
+    pub async fn reset_stats(&self) -> Result<(), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::ResetStats { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// implements the redis SWAPDB command. Everything in db is in other_db once this returns,
+    /// and the other way around, with no moment in between where either is half swapped.
+    /// https://redis.io/commands/swapdb/
+    pub async fn swap_db(&self, db: usize, other_db: usize) -> Result<(), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::SwapDb {
+            db,
+            other_db,
+            respond_to: send,
+        };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+
+    /// Empties every database. Returns once the store is empty.
+    pub async fn flush_all(&self) -> Result<(), RedisError> {
+        let (send, recv) = oneshot::channel();
+        let msg = SetActorMessage::FlushAll { respond_to: send };
+
+        let _ = self.sender.send(msg).await;
+
+        recv.await.map_err(|_| RedisError::ActorGone("the store"))
+    }
+}
+
+impl Default for SetCommandActorHandle {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,25 @@
+// Library half of the crate: everything main.rs wires together lives here,
+// so the same actors can be driven without a TCP listener (see engine.rs).
+pub mod actors;
+pub mod auth;
+pub mod cli;
+pub mod clock;
+pub mod collections;
+pub mod commandstats;
+pub mod digest;
+pub mod engine;
+pub mod errors;
+pub mod eviction;
+pub mod handlers;
+pub mod intervals;
+pub mod monitor;
+pub mod notifications;
+pub mod parsers;
+pub mod protocol;
+pub mod rdb;
+pub mod read_pool;
+pub mod resp;
+pub mod server;
+pub mod storage;
+pub mod units;
+pub mod utils;
@@ -0,0 +1,430 @@
+// What MONITOR and the audit log see of the commands the processor runs,
+// https://redis.io/docs/latest/commands/monitor/
+//
+// Once a command has its reply the processor sends a CommandEvent down a broadcast channel, with
+// the connection it came from and how it went, and builds none while nobody listens. MONITOR
+// shows every command but the administrative ones, like redis does. The audit log keeps the
+// writes and the administrative ones, a JSON object a line, and moves on to a new file once the
+// one it writes to gets past auditlog-max-size. Neither ever sees a password.
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use bytes::Bytes;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tracing::{error, warn};
+
+use crate::{
+    actors::messages::HostId, handlers::clients::OutputQueue, protocol::CommandFlag,
+    resp::value::RespValue,
+};
+
+/// Commands that can wait for MONITOR and the audit log before the oldest are dropped.
+pub const COMMAND_FEED_CAPACITY: usize = 16384;
+
+/// Where the processor sends the commands it ran, and what MONITOR and the audit log read them from.
+#[derive(Clone, Debug)]
+pub struct CommandFeed {
+    sender: broadcast::Sender<CommandEvent>,
+    // MONITORs still running, GET skips the processor only while there are none
+    monitors: Arc<AtomicUsize>,
+}
+
+impl Default for CommandFeed {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(COMMAND_FEED_CAPACITY);
+
+        Self {
+            sender,
+            monitors: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl CommandFeed {
+    /// Every command sent from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<CommandEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Whether anything reads the feed, no point building events otherwise.
+    pub fn is_read(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Whether a MONITOR is running.
+    pub fn is_monitored(&self) -> bool {
+        self.monitors.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn send(&self, command: CommandEvent) {
+        // nobody reading it is fine
+        let _ = self.sender.send(command);
+    }
+
+    /// Sends output a line for every command MONITOR shows, until the connection it belongs to
+    /// goes.
+    pub fn spawn_monitor(&self, output: OutputQueue) -> JoinHandle<()> {
+        let mut commands = self.subscribe();
+        let running = MonitorRunning::new(self.monitors.clone());
+
+        tokio::spawn(async move {
+            let _running = running;
+
+            loop {
+                match commands.recv().await {
+                    Ok(command) if command.monitored() => {
+                        if output
+                            .send(RespValue::SimpleString(command.monitor_line()))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    // like a redis monitor that can't keep up, it misses some
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+}
+
+// Counts a MONITOR in for as long as it runs.
+struct MonitorRunning(Arc<AtomicUsize>);
+
+impl MonitorRunning {
+    fn new(monitors: Arc<AtomicUsize>) -> Self {
+        monitors.fetch_add(1, Ordering::Relaxed);
+        Self(monitors)
+    }
+}
+
+impl Drop for MonitorRunning {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A command the processor ran, for MONITOR and the audit log.
+#[derive(Clone, Debug)]
+pub struct CommandEvent {
+    /// When it was called
+    pub time: SystemTime,
+    /// The database it ran in
+    pub db: usize,
+    pub client: HostId,
+    /// The name CLIENT SETNAME or HELLO ... SETNAME gave the connection, empty if none
+    pub client_name: String,
+    /// The user the connection authenticated as
+    pub user: String,
+    /// The command and its arguments, the secret ones "(redacted)"
+    pub args: Vec<Bytes>,
+    pub flags: &'static [CommandFlag],
+    /// The error it replied with, None if it went through
+    pub error: Option<String>,
+}
+
+impl CommandEvent {
+    /// A call of request just now, gone through until outcome() says otherwise.
+    pub fn new(
+        db: usize,
+        client: HostId,
+        client_name: String,
+        user: String,
+        request: &RespValue,
+        flags: &'static [CommandFlag],
+    ) -> Self {
+        let args = match request {
+            RespValue::Array(elements) => elements
+                .iter()
+                .map(|element| match element {
+                    RespValue::BulkString(Some(arg)) => arg.clone(),
+                    RespValue::SimpleString(arg) => Bytes::from(arg.clone()),
+                    _ => Bytes::new(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            time: SystemTime::now(),
+            db,
+            client,
+            client_name,
+            user,
+            args: redacted(args),
+            flags,
+            error: None,
+        }
+    }
+
+    /// The same call with the reply it got, failed if that is an error.
+    pub fn outcome(mut self, reply: &Option<Vec<RespValue>>) -> Self {
+        if let Some([RespValue::Error(e)]) = reply.as_deref() {
+            self.error = Some(e.clone());
+        }
+        self
+    }
+
+    /// Whether MONITOR shows it, everything but the administrative commands.
+    pub fn monitored(&self) -> bool {
+        !self.flags.contains(&CommandFlag::Admin)
+    }
+
+    /// Whether the audit log keeps it, the writes and the administrative commands.
+    pub fn audited(&self) -> bool {
+        self.flags.contains(&CommandFlag::Write) || self.flags.contains(&CommandFlag::Admin)
+    }
+
+    /// The line MONITOR sends for it, the way redis writes them:
+    /// 1339518083.107412 [0 127.0.0.1:60866] "set" "key" "value"
+    pub fn monitor_line(&self) -> String {
+        let mut line = format!(
+            "{} [{} {}]",
+            unix_time(self.time),
+            self.db,
+            address(&self.client)
+        );
+
+        for arg in &self.args {
+            line.push(' ');
+            quote(&mut line, arg);
+        }
+
+        line
+    }
+
+    /// The audit log's line for it, without the newline:
+    /// {"time":1339518083.107412,"id":7,"addr":"127.0.0.1:60866","name":"","user":"default",
+    /// "db":0,"command":["SET","key","value"],"outcome":"ok"}
+    /// A failed command has "outcome":"error" and the error it replied with.
+    pub fn audit_record(&self) -> String {
+        let id = match self.client {
+            HostId::Host { id, .. } => id,
+            HostId::Myself => 0,
+        };
+
+        let mut record = format!(
+            "{{\"time\":{},\"id\":{},\"addr\":{},\"name\":{},\"user\":{},\"db\":{},\"command\":[",
+            unix_time(self.time),
+            id,
+            json_string(&address(&self.client)),
+            json_string(&self.client_name),
+            json_string(&self.user),
+            self.db
+        );
+
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                record.push(',');
+            }
+            record.push_str(&json_string(&String::from_utf8_lossy(arg)));
+        }
+        record.push(']');
+
+        match &self.error {
+            None => record.push_str(",\"outcome\":\"ok\"}"),
+            Some(e) => {
+                let _ = write!(
+                    record,
+                    ",\"outcome\":\"error\",\"error\":{}}}",
+                    json_string(e)
+                );
+            }
+        }
+
+        record
+    }
+}
+
+// AUTH's arguments and HELLO's username and password after AUTH, which redis doesn't show either.
+fn redacted(mut args: Vec<Bytes>) -> Vec<Bytes> {
+    let secret = |arg: &mut Bytes| *arg = Bytes::from_static(b"(redacted)");
+
+    match args.first() {
+        Some(name) if name.eq_ignore_ascii_case(b"AUTH") => {
+            args.iter_mut().skip(1).for_each(secret)
+        }
+        Some(name) if name.eq_ignore_ascii_case(b"HELLO") => {
+            if let Some(auth) = args
+                .iter()
+                .position(|arg| arg.eq_ignore_ascii_case(b"AUTH"))
+            {
+                args.iter_mut().skip(auth + 1).take(2).for_each(secret);
+            }
+        }
+        _ => {}
+    }
+
+    args
+}
+
+// Seconds since the epoch with the microseconds, 1339518083.107412.
+fn unix_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    format!(
+        "{}.{:06}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_micros()
+    )
+}
+
+// ip:port, or "self" for the master's stream and --import.
+fn address(client: &HostId) -> String {
+    match client {
+        HostId::Host { ip, port, .. } => format!("{}:{}", ip, port),
+        HostId::Myself => "self".to_string(),
+    }
+}
+
+// arg in double quotes with anything that isn't printable escaped, like redis' sdscatrepr().
+fn quote(line: &mut String, arg: &[u8]) {
+    line.push('"');
+
+    for &byte in arg {
+        match byte {
+            b'\\' => line.push_str("\\\\"),
+            b'"' => line.push_str("\\\""),
+            b'\n' => line.push_str("\\n"),
+            b'\r' => line.push_str("\\r"),
+            b'\t' => line.push_str("\\t"),
+            0x07 => line.push_str("\\a"),
+            0x08 => line.push_str("\\b"),
+            b' '..=b'~' => line.push(byte as char),
+            _ => {
+                let _ = write!(line, "\\x{:02x}", byte);
+            }
+        }
+    }
+
+    line.push('"');
+}
+
+// s as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+/// Appends the audited commands to the file at path. Past max_size bytes the file is renamed to
+/// path.<unix milliseconds> and a new one started, 0 keeps writing to the same one. Commands
+/// missed because the file couldn't keep up are counted in a line of their own. Fails if the
+/// file can't be opened.
+pub async fn spawn_audit_log(
+    path: PathBuf,
+    max_size: u64,
+    mut commands: broadcast::Receiver<CommandEvent>,
+) -> anyhow::Result<JoinHandle<()>> {
+    let (mut file, mut size) = open_audit_log(&path).await?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let line = match commands.recv().await {
+                Ok(command) if command.audited() => command.audit_record(),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("The audit log missed {} commands.", missed);
+                    format!(
+                        "{{\"time\":{},\"missed\":{}}}",
+                        unix_time(SystemTime::now()),
+                        missed
+                    )
+                }
+                Err(RecvError::Closed) => return,
+            };
+
+            let written = async {
+                file.write_all(line.as_bytes()).await?;
+                file.write_all(b"\n").await?;
+                file.flush().await
+            };
+            if let Err(e) = written.await {
+                error!("Failed to write to the audit log {}: {}", path.display(), e);
+                continue;
+            }
+            size += line.len() as u64 + 1;
+
+            if max_size > 0 && size >= max_size {
+                match rotate_audit_log(&path).await {
+                    Ok((rotated, rotated_size)) => (file, size) = (rotated, rotated_size),
+                    Err(e) => error!("{:#}", e),
+                }
+            }
+        }
+    }))
+}
+
+// The audit log at path for appending, and how big it already is.
+async fn open_audit_log(path: &Path) -> anyhow::Result<(File, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open the audit log {}", path.display()))?;
+    let size = file.metadata().await?.len();
+
+    Ok((file, size))
+}
+
+// Moves the full audit log out of the way, to a name no earlier one has, and opens a new one.
+async fn rotate_audit_log(path: &Path) -> anyhow::Result<(File, u64)> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let mut ms = now.as_millis();
+    let rotated = loop {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(format!(".{}", ms));
+        let rotated = PathBuf::from(rotated);
+
+        if !tokio::fs::try_exists(&rotated).await.unwrap_or(false) {
+            break rotated;
+        }
+        ms += 1;
+    };
+
+    tokio::fs::rename(path, &rotated).await.with_context(|| {
+        format!(
+            "Failed to move the audit log {} to {}",
+            path.display(),
+            rotated.display()
+        )
+    })?;
+
+    open_audit_log(path).await
+}
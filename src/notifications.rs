@@ -0,0 +1,103 @@
+// Keyspace notifications, https://redis.io/docs/latest/develop/use/keyspace-notifications/
+//
+// The store and the processor put a KeyspaceEvent on the bus for every change to a key, the task
+// here turns the ones notify-keyspace-events asks for into PUBLISHes. Every event carries the
+// database of the key it is about, so the channel names always match where the key lives.
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::error;
+
+use crate::{actors::messages::KeyspaceEvent, handlers::pubsub::PubSubActorHandle};
+
+// The event classes redis knows, A stands for all of them but m and n.
+const CLASSES: &str = "g$lshzxetmdn";
+const ALL_CLASSES: &str = "g$lshzxetd";
+
+/// notify-keyspace-events, parsed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NotifyKeyspaceEvents {
+    // K, publish to __keyspace@<db>__:<key>
+    keyspace: bool,
+    // E, publish to __keyevent@<db>__:<event>
+    keyevent: bool,
+    classes: String,
+}
+
+impl NotifyKeyspaceEvents {
+    /// Nothing is published unless K or E is given along with at least one class.
+    pub fn enabled(&self) -> bool {
+        (self.keyspace || self.keyevent) && !self.classes.is_empty()
+    }
+
+    fn wants(&self, event: &KeyspaceEvent) -> bool {
+        self.classes.contains(event.class)
+    }
+}
+
+impl FromStr for NotifyKeyspaceEvents {
+    type Err = anyhow::Error;
+
+    fn from_str(flags: &str) -> Result<Self, Self::Err> {
+        let mut parsed = NotifyKeyspaceEvents::default();
+
+        for flag in flags.chars() {
+            match flag {
+                'K' => parsed.keyspace = true,
+                'E' => parsed.keyevent = true,
+                'A' => parsed.classes.push_str(ALL_CLASSES),
+                class if CLASSES.contains(class) => parsed.classes.push(class),
+                other => return Err(anyhow!("invalid notify-keyspace-events flag '{}'", other)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Starts publishing the events sent on the returned bus. None if flags turn notifications off,
+/// and then nothing needs to be sent at all.
+pub fn spawn_keyspace_notifier(
+    flags: NotifyKeyspaceEvents,
+    pubsub_actor_handle: PubSubActorHandle,
+) -> Option<(mpsc::UnboundedSender<KeyspaceEvent>, JoinHandle<()>)> {
+    if !flags.enabled() {
+        return None;
+    }
+
+    // Unbounded like redis, which never drops a notification: the store must not wait on subscribers.
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel::<KeyspaceEvent>();
+
+    let handle = tokio::spawn(async move {
+        while let Some(event) = events_rx.recv().await {
+            if !flags.wants(&event) {
+                continue;
+            }
+
+            let mut notifications = Vec::with_capacity(2);
+            if flags.keyspace {
+                notifications.push((
+                    format!("__keyspace@{}__:{}", event.db, event.key),
+                    event.event.to_string(),
+                ));
+            }
+            if flags.keyevent {
+                notifications.push((
+                    format!("__keyevent@{}__:{}", event.db, event.event),
+                    event.key.clone(),
+                ));
+            }
+
+            for (channel, message) in notifications {
+                if let Err(e) = pubsub_actor_handle.publish(channel, message).await {
+                    error!("Stopped publishing keyspace notifications: {}", e);
+                    return;
+                }
+            }
+        }
+    });
+
+    Some((events_tx, handle))
+}
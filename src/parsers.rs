@@ -1,7 +1,7 @@
 use std::{
-    num::ParseIntError,
-    time::{SystemTime, UNIX_EPOCH},
-    usize,
+    mem::discriminant,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use nom::{
@@ -11,17 +11,593 @@ use nom::{
         complete::{crlf, not_line_ending},
         streaming::alphanumeric1,
     },
-    combinator::{cut, map, map_res, opt, value, verify},
-    multi::count,
-    sequence::{terminated, tuple},
+    combinator::{map, map_res, opt, value, verify},
+    error::{Error, ErrorKind},
+    multi::{many0, many1, many_m_n},
+    sequence::{pair, preceded, terminated},
     IResult,
 };
 
-use crate::protocol::{
-    ConfigCommandParameter, ExpiryOption, InfoCommandParameter, RedisCommand,
-    ReplConfCommandParameter, SetCommandExpireOption, SetCommandParameter, SetCommandSetOption,
+use crate::{
+    collections::{ListEnd, ScoreBound},
+    errors::RedisError,
+    protocol::{
+        ClientCommandParameter, ClientListFilter, CommandFlag, CopyCommandParameter,
+        DebugCommandParameter, Failpoint, HelloCommandParameter, InfoCommandParameter,
+        MemoryCommandParameter, RedisCommand, ReplConfCommandParameter, ReplicaCapability,
+        ScanCommandParameter, SetCommandExpireOption, SetCommandParameter, SetCommandSetOption,
+        ShutdownCommandParameter, ZaddCommandParameter,
+    },
 };
 
+mod streams;
+
+use streams::{parse_xadd, parse_xlen, parse_xrange, parse_xread};
+
+/// An entry in the command table.
+struct CommandSpec {
+    /// Matched case-insensitively against the first element of the request array.
+    name: &'static str,
+    /// Number of arguments including the command name, same convention as COMMAND INFO:
+    /// N means exactly N, -N means N or more.
+    arity: i64,
+    /// Parses the arguments after the command name. Anything it leaves unconsumed is a syntax error.
+    parser: fn(&str) -> IResult<&str, RedisCommand>,
+    /// What the command does to the server, see command_flags().
+    flags: &'static [CommandFlag],
+}
+
+// Every client command we understand. parse_command() looks the name up here,
+// instead of trying each parser in turn until one happens to match.
+const COMMAND_TABLE: &[CommandSpec] = &[
+    CommandSpec {
+        name: "PING",
+        arity: -1,
+        parser: parse_ping,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "ECHO",
+        arity: 2,
+        parser: parse_echo,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "COMMAND",
+        arity: -1,
+        parser: parse_command_docs,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "SET",
+        arity: -3,
+        parser: parse_set_command,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "GET",
+        arity: 2,
+        parser: parse_get,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "DEL",
+        arity: -2,
+        parser: parse_del,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "COPY",
+        arity: -3,
+        parser: parse_copy,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "STRLEN",
+        arity: 2,
+        parser: parse_strlen,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "MGET",
+        arity: -2,
+        parser: parse_mget,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "EXISTS",
+        arity: -2,
+        parser: parse_exists,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "APPEND",
+        arity: 3,
+        parser: parse_append,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "SETRANGE",
+        arity: 4,
+        parser: parse_setrange,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "INCR",
+        arity: 2,
+        parser: parse_incr,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "DECR",
+        arity: 2,
+        parser: parse_decr,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "INCRBY",
+        arity: 3,
+        parser: parse_incrby,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "DECRBY",
+        arity: 3,
+        parser: parse_decrby,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "GETRANGE",
+        arity: 4,
+        parser: parse_getrange,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "HSET",
+        arity: -4,
+        parser: parse_hset,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "HGET",
+        arity: 3,
+        parser: parse_hget,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "HDEL",
+        arity: -3,
+        parser: parse_hdel,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "HGETALL",
+        arity: 2,
+        parser: parse_hgetall,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "HLEN",
+        arity: 2,
+        parser: parse_hlen,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "HKEYS",
+        arity: 2,
+        parser: parse_hkeys,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "HVALS",
+        arity: 2,
+        parser: parse_hvals,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "LPUSH",
+        arity: -3,
+        parser: parse_lpush,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "RPUSH",
+        arity: -3,
+        parser: parse_rpush,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "LPOP",
+        arity: -2,
+        parser: parse_lpop,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "RPOP",
+        arity: -2,
+        parser: parse_rpop,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "BLPOP",
+        arity: -3,
+        parser: parse_blpop,
+        flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    },
+    CommandSpec {
+        name: "BRPOP",
+        arity: -3,
+        parser: parse_brpop,
+        flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    },
+    CommandSpec {
+        name: "LRANGE",
+        arity: 4,
+        parser: parse_lrange,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "LLEN",
+        arity: 2,
+        parser: parse_llen,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SADD",
+        arity: -3,
+        parser: parse_sadd,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "SREM",
+        arity: -3,
+        parser: parse_srem,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "SMEMBERS",
+        arity: 2,
+        parser: parse_smembers,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SISMEMBER",
+        arity: 3,
+        parser: parse_sismember,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SCARD",
+        arity: 2,
+        parser: parse_scard,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SUNION",
+        arity: -2,
+        parser: parse_sunion,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SINTER",
+        arity: -2,
+        parser: parse_sinter,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SINTERCARD",
+        arity: -3,
+        parser: parse_sintercard,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "ZADD",
+        arity: -4,
+        parser: parse_zadd,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "ZSCORE",
+        arity: 3,
+        parser: parse_zscore,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "ZRANGE",
+        arity: -4,
+        parser: parse_zrange,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "ZRANK",
+        arity: 3,
+        parser: parse_zrank,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "ZCARD",
+        arity: 2,
+        parser: parse_zcard,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "ZREM",
+        arity: -3,
+        parser: parse_zrem,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "ZINCRBY",
+        arity: 4,
+        parser: parse_zincrby,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "ZCOUNT",
+        arity: 4,
+        parser: parse_zcount,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "ZRANGEBYSCORE",
+        arity: -4,
+        parser: parse_zrangebyscore,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "XADD",
+        arity: -5,
+        parser: parse_xadd,
+        flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    },
+    CommandSpec {
+        name: "XRANGE",
+        arity: -4,
+        parser: parse_xrange,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "XLEN",
+        arity: 2,
+        parser: parse_xlen,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "XREAD",
+        arity: -4,
+        parser: parse_xread,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "EXPIRE",
+        arity: 3,
+        parser: parse_expire,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "PEXPIRE",
+        arity: 3,
+        parser: parse_pexpire,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "EXPIREAT",
+        arity: 3,
+        parser: parse_expireat,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "PEXPIREAT",
+        arity: 3,
+        parser: parse_pexpireat,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "PERSIST",
+        arity: 2,
+        parser: parse_persist,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "TTL",
+        arity: 2,
+        parser: parse_ttl,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "PTTL",
+        arity: 2,
+        parser: parse_pttl,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "CONFIG",
+        arity: -2,
+        parser: parse_config,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "KEYS",
+        arity: 2,
+        parser: parse_keys,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SCAN",
+        arity: -2,
+        parser: parse_scan,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "INFO",
+        arity: -1,
+        parser: parse_info,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "REPLCONF",
+        arity: -1,
+        parser: parse_replconf,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "PSYNC",
+        arity: 3,
+        parser: parse_psync,
+        flags: &[CommandFlag::Admin, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "REPLICAOF",
+        arity: 3,
+        parser: parse_replicaof,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "SLAVEOF",
+        arity: 3,
+        parser: parse_replicaof,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "ROLE",
+        arity: 1,
+        parser: parse_role,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "WAIT",
+        arity: 3,
+        parser: parse_wait,
+        flags: &[CommandFlag::Blocking],
+    },
+    CommandSpec {
+        name: "WAITAOF",
+        arity: 4,
+        parser: parse_waitaof,
+        flags: &[CommandFlag::Blocking],
+    },
+    CommandSpec {
+        name: "SELECT",
+        arity: 2,
+        parser: parse_select,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "SWAPDB",
+        arity: 3,
+        parser: parse_swapdb,
+        flags: &[CommandFlag::Write],
+    },
+    CommandSpec {
+        name: "DBSIZE",
+        arity: 1,
+        parser: parse_dbsize,
+        flags: &[CommandFlag::Readonly],
+    },
+    CommandSpec {
+        name: "SUBSCRIBE",
+        arity: -2,
+        parser: parse_subscribe,
+        flags: &[CommandFlag::Pubsub, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "UNSUBSCRIBE",
+        arity: -1,
+        parser: parse_unsubscribe,
+        flags: &[CommandFlag::Pubsub, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "PSUBSCRIBE",
+        arity: -2,
+        parser: parse_psubscribe,
+        flags: &[CommandFlag::Pubsub, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "PUNSUBSCRIBE",
+        arity: -1,
+        parser: parse_punsubscribe,
+        flags: &[CommandFlag::Pubsub, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "PUBLISH",
+        arity: 3,
+        parser: parse_publish,
+        flags: &[CommandFlag::Pubsub],
+    },
+    CommandSpec {
+        name: "SAVE",
+        arity: 1,
+        parser: parse_save,
+        flags: &[CommandFlag::Admin, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "BGSAVE",
+        arity: 1,
+        parser: parse_bgsave,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "SHUTDOWN",
+        arity: -1,
+        parser: parse_shutdown,
+        flags: &[CommandFlag::Admin, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "CLIENT",
+        arity: -2,
+        parser: parse_client,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "HELLO",
+        arity: -1,
+        parser: parse_hello,
+        flags: &[CommandFlag::NoAuth],
+    },
+    CommandSpec {
+        name: "AUTH",
+        arity: -2,
+        parser: parse_auth,
+        flags: &[CommandFlag::NoAuth],
+    },
+    CommandSpec {
+        name: "MULTI",
+        arity: 1,
+        parser: parse_multi,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "EXEC",
+        arity: 1,
+        parser: parse_exec,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "DISCARD",
+        arity: 1,
+        parser: parse_discard,
+        flags: &[],
+    },
+    CommandSpec {
+        name: "MONITOR",
+        arity: 1,
+        parser: parse_monitor,
+        flags: &[CommandFlag::Admin, CommandFlag::NoMulti],
+    },
+    CommandSpec {
+        name: "DEBUG",
+        arity: -2,
+        parser: parse_debug,
+        flags: &[CommandFlag::Admin],
+    },
+    CommandSpec {
+        name: "MEMORY",
+        arity: -2,
+        parser: parse_memory,
+        flags: &[CommandFlag::Readonly],
+    },
+];
+
 fn length(input: &str) -> IResult<&str, usize> {
     nom::combinator::map_res(terminated(not_line_ending, crlf), |len_str: &str| {
         len_str
@@ -30,181 +606,752 @@ fn length(input: &str) -> IResult<&str, usize> {
     })(input)
 }
 
-// RESP bulk string format: $<length>\r\n<data>\r\n
-fn parse_resp_string(input: &str) -> IResult<&str, String> {
-    let (input, _) = tag("$")(input)?;
-    let (input, _len) = length(input)?;
+// RESP bulk string format: $<length>\r\n<data>\r\n
+//
+// The declared length is authoritative, exactly that many bytes are taken so the data may contain \r\n.
+// A length that doesn't line up with the trailing \r\n fails with ErrorKind::LengthValue.
+fn parse_resp_string(input: &str) -> IResult<&str, String> {
+    let (input, _) = tag("$")(input)?;
+    let (input, len) = length(input)?;
+
+    // NOTE: nom's take() counts chars on &str, but the declared length is in bytes.
+    if input.len() < len || !input.is_char_boundary(len) {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::LengthValue)));
+    }
+    let (value, input) = input.split_at(len);
+
+    let (input, _) = crlf(input).map_err(|_: nom::Err<Error<&str>>| {
+        nom::Err::Failure(Error::new(input, ErrorKind::LengthValue))
+    })?;
+
+    Ok((input, value.to_string()))
+}
+
+// Matches a bulk string argument against a keyword, ignoring case.
+// Used for options and subcommands instead of tags like "$2\r\nNX\r\n".
+fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, String> {
+    verify(parse_resp_string, move |arg: &str| {
+        arg.eq_ignore_ascii_case(word)
+    })
+}
+
+// A bulk string argument holding a number.
+// Fails with ErrorKind::Digit, which parse_command() reports as "value is not an integer or out of range".
+fn parse_integer<T: FromStr>(input: &str) -> IResult<&str, T> {
+    let (remaining, number) = parse_resp_string(input)?;
+
+    match number.parse() {
+        Ok(number) => Ok((remaining, number)),
+        Err(_) => Err(nom::Err::Failure(Error::new(input, ErrorKind::Digit))),
+    }
+}
+
+// A bulk string argument holding one end of a score range, a score with an optional '(' in front.
+// Fails with ErrorKind::MapOpt, which parse_command() reports as "min or max is not a float".
+fn parse_score_bound(input: &str) -> IResult<&str, ScoreBound> {
+    let (remaining, bound) = parse_resp_string(input)?;
+
+    let (score, exclusive) = match bound.strip_prefix('(') {
+        Some(score) => (score, true),
+        None => (bound.as_str(), false),
+    };
+
+    match score.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok((remaining, ScoreBound { score, exclusive })),
+        _ => Err(nom::Err::Failure(Error::new(input, ErrorKind::MapOpt))),
+    }
+}
+
+// A bulk string argument holding a sorted set score, inf and -inf included but not nan.
+// Fails with ErrorKind::Satisfy, which parse_command() reports as "value is not a valid float".
+fn parse_score(input: &str) -> IResult<&str, f64> {
+    let (remaining, score) = parse_resp_string(input)?;
+
+    match score.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok((remaining, score)),
+        _ => Err(nom::Err::Failure(Error::new(input, ErrorKind::Satisfy))),
+    }
+}
+
+/// PING [message]
+/// The arity only says at least one argument, so more than two is caught here, like redis does.
+fn parse_ping(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, message) = opt(parse_resp_string)(input)?;
+
+    if !input.is_empty() {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+
+    Ok((input, RedisCommand::Ping(message)))
+}
+
+/// COMMAND [subcommand ...], redis-cli sends COMMAND DOCS on connect.
+fn parse_command_docs(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, _subcommand) = many0(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Command))
+}
+
+fn parse_echo(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, echo_string) = (parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Echo(echo_string)))
+}
+
+/// https://redis.io/commands/strlen/
+/// STRLEN key
+fn parse_strlen(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key_string) = (parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Strlen(key_string)))
+}
+
+fn parse_append(input: &str) -> IResult<&str, RedisCommand> {
+    // let's get the key to append to, first
+    let (input, key) = (parse_resp_string)(input)?;
+
+    // now let's grab the value we are appending
+    let (input, value) = (parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Append(key, value)))
+}
+
+/// https://redis.io/commands/setrange/
+/// SETRANGE key offset value
+fn parse_setrange(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = (parse_resp_string)(input)?;
+    let (input, offset) = (parse_integer::<i64>)(input)?;
+    let (input, value) = (parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Setrange(key, offset, value)))
+}
+
+/// EXPIRE key seconds
+/// https://redis.io/commands/expire/
+fn parse_expire(input: &str) -> IResult<&str, RedisCommand> {
+    parse_expire_deadline(input, 1000, false)
+}
+
+/// PEXPIRE key milliseconds
+fn parse_pexpire(input: &str) -> IResult<&str, RedisCommand> {
+    parse_expire_deadline(input, 1, false)
+}
+
+/// EXPIREAT key unix-time-seconds
+fn parse_expireat(input: &str) -> IResult<&str, RedisCommand> {
+    parse_expire_deadline(input, 1000, true)
+}
+
+/// PEXPIREAT key unix-time-milliseconds, also what a master sends its replicas for all four
+fn parse_pexpireat(input: &str) -> IResult<&str, RedisCommand> {
+    parse_expire_deadline(input, 1, true)
+}
+
+// The key and its new deadline in unix milliseconds, from a time in units of `unit_ms` that is
+// either absolute or counted from now. A time that doesn't fit in milliseconds is out of range.
+fn parse_expire_deadline(input: &str, unit_ms: i64, absolute: bool) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (remaining, time) = parse_integer::<i64>(input)?;
+
+    let now_ms = if absolute {
+        0
+    } else {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64
+    };
+
+    match time
+        .checked_mul(unit_ms)
+        .and_then(|time| time.checked_add(now_ms))
+    {
+        Some(deadline) => Ok((remaining, RedisCommand::Expire(key, deadline))),
+        None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Digit))),
+    }
+}
+
+fn parse_persist(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Persist(key)))
+}
+
+fn parse_ttl(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Ttl(key)))
+}
+
+fn parse_pttl(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Pttl(key)))
+}
+
+/// INCR key
+/// https://redis.io/commands/incr/
+fn parse_incr(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Incrby(key, 1)))
+}
+
+/// DECR key
+fn parse_decr(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Incrby(key, -1)))
+}
+
+/// INCRBY key increment
+fn parse_incrby(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, increment) = parse_integer::<i64>(input)?;
+
+    Ok((input, RedisCommand::Incrby(key, increment)))
+}
+
+/// DECRBY key decrement, a decrement of i64::MIN has nothing to add in its place
+fn parse_decrby(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (remaining, decrement) = parse_integer::<i64>(input)?;
+
+    match decrement.checked_neg() {
+        Some(increment) => Ok((remaining, RedisCommand::Incrby(key, increment))),
+        None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Digit))),
+    }
+}
+
+/// https://redis.io/commands/getrange/
+/// GETRANGE key start end
+fn parse_getrange(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, start) = parse_integer::<i64>(input)?;
+    let (input, end) = parse_integer::<i64>(input)?;
+
+    Ok((input, RedisCommand::Getrange(key, start, end)))
+}
+
+/// HSET key field value [field value ...]
+/// A field without a value is an argument short, like redis says, rather than a syntax error.
+fn parse_hset(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, pairs) = many1(pair(parse_resp_string, parse_resp_string))(input)?;
+
+    if !input.is_empty() {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+
+    Ok((input, RedisCommand::Hset(key, pairs)))
+}
+
+/// HGET key field
+fn parse_hget(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, field) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Hget(key, field)))
+}
+
+/// HDEL key field [field ...]
+fn parse_hdel(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, fields) = many1(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Hdel(key, fields)))
+}
+
+fn parse_hgetall(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Hgetall(key)))
+}
+
+fn parse_hlen(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Hlen(key)))
+}
+
+fn parse_hkeys(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Hkeys(key)))
+}
+
+fn parse_hvals(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Hvals(key)))
+}
+
+/// LPUSH key element [element ...]
+fn parse_lpush(input: &str) -> IResult<&str, RedisCommand> {
+    parse_push(input, ListEnd::Left)
+}
+
+/// RPUSH key element [element ...]
+fn parse_rpush(input: &str) -> IResult<&str, RedisCommand> {
+    parse_push(input, ListEnd::Right)
+}
+
+fn parse_push(input: &str, end: ListEnd) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, values) = many1(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Push(key, end, values)))
+}
+
+/// LPOP key [count]
+fn parse_lpop(input: &str) -> IResult<&str, RedisCommand> {
+    parse_pop(input, ListEnd::Left)
+}
+
+/// RPOP key [count]
+fn parse_rpop(input: &str) -> IResult<&str, RedisCommand> {
+    parse_pop(input, ListEnd::Right)
+}
+
+// A count that isn't a number, or is negative, is not an integer. More than one is an argument too
+// many, like redis says.
+fn parse_pop(input: &str, end: ListEnd) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, count) = opt(parse_integer::<usize>)(input)?;
+
+    if !input.is_empty() {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+
+    Ok((input, RedisCommand::Pop(key, end, count)))
+}
+
+/// BLPOP key [key ...] timeout
+fn parse_blpop(input: &str) -> IResult<&str, RedisCommand> {
+    parse_blocking_pop(input, ListEnd::Left)
+}
+
+/// BRPOP key [key ...] timeout
+fn parse_brpop(input: &str) -> IResult<&str, RedisCommand> {
+    parse_blocking_pop(input, ListEnd::Right)
+}
+
+// The timeout is the last argument, seconds with a fraction if need be. One that isn't a finite
+// number fails with ErrorKind::Float, a negative one is left for the processor to refuse.
+fn parse_blocking_pop(input: &str, end: ListEnd) -> IResult<&str, RedisCommand> {
+    let (remaining, mut keys) = many1(parse_resp_string)(input)?;
+
+    let timeout = keys
+        .pop()
+        .filter(|_| !keys.is_empty())
+        .ok_or(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)))?;
+
+    match timeout.parse::<f64>() {
+        Ok(timeout) if timeout.is_finite() => {
+            Ok((remaining, RedisCommand::BlockingPop(keys, end, timeout)))
+        }
+        _ => Err(nom::Err::Failure(Error::new(input, ErrorKind::Float))),
+    }
+}
+
+/// LRANGE key start stop
+fn parse_lrange(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, start) = parse_integer::<i64>(input)?;
+    let (input, stop) = parse_integer::<i64>(input)?;
+
+    Ok((input, RedisCommand::Lrange(key, start, stop)))
+}
+
+fn parse_llen(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Llen(key)))
+}
+
+/// SADD key member [member ...]
+fn parse_sadd(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, members) = many1(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Sadd(key, members)))
+}
+
+/// SREM key member [member ...]
+fn parse_srem(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, members) = many1(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Srem(key, members)))
+}
+
+fn parse_smembers(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Smembers(key)))
+}
+
+/// SISMEMBER key member
+fn parse_sismember(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, member) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Sismember(key, member)))
+}
+
+fn parse_scard(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Scard(key)))
+}
+
+/// SUNION key [key ...]
+fn parse_sunion(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, keys) = many1(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Sunion(keys)))
+}
+
+/// SINTER key [key ...]
+fn parse_sinter(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, keys) = many1(parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Sinter(keys)))
+}
+
+/// SINTERCARD numkeys key [key ...] [LIMIT limit]
+/// A numkeys that isn't positive fails with ErrorKind::Fix, one past the arguments there are
+/// with ErrorKind::Eof, which parse_command() reports the way redis does.
+fn parse_sintercard(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, numkeys) = parse_integer::<i64>(input)?;
+
+    if numkeys <= 0 {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::Fix)));
+    }
+
+    let (input, keys) = many_m_n(0, numkeys as usize, parse_resp_string)(input)?;
+    if keys.len() < numkeys as usize {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::Eof)));
+    }
+
+    let (input, limit) = opt(preceded(keyword("LIMIT"), parse_integer::<usize>))(input)?;
+
+    Ok((input, RedisCommand::Sintercard(keys, limit.unwrap_or(0))))
+}
+
+// The flags that may come before ZADD's scores and members, in any order.
+#[derive(Clone)]
+enum ZaddArgument {
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+    Ch,
+}
+
+/// ZADD key [NX | XX] [GT | LT] [CH] score member [score member ...]
+fn parse_zadd(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, zadd_arguments) = many0(alt((
+        value(ZaddArgument::Nx, keyword("NX")),
+        value(ZaddArgument::Xx, keyword("XX")),
+        value(ZaddArgument::Gt, keyword("GT")),
+        value(ZaddArgument::Lt, keyword("LT")),
+        value(ZaddArgument::Ch, keyword("CH")),
+    )))(input)?;
+    // a score without a member is left over, which is a syntax error
+    let (input, members) = many1(pair(parse_score, parse_resp_string))(input)?;
+
+    let mut zadd_params = ZaddCommandParameter {
+        key,
+        members,
+        ..Default::default()
+    };
+
+    for zadd_argument in zadd_arguments {
+        match zadd_argument {
+            ZaddArgument::Nx => zadd_params.nx = true,
+            ZaddArgument::Xx => zadd_params.xx = true,
+            ZaddArgument::Gt => zadd_params.gt = true,
+            ZaddArgument::Lt => zadd_params.lt = true,
+            ZaddArgument::Ch => zadd_params.ch = true,
+        }
+    }
+
+    Ok((input, RedisCommand::Zadd(zadd_params)))
+}
+
+/// ZSCORE key member
+fn parse_zscore(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, member) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Zscore(key, member)))
+}
+
+/// ZRANGE key start stop [WITHSCORES]
+fn parse_zrange(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, start) = parse_integer::<i64>(input)?;
+    let (input, stop) = parse_integer::<i64>(input)?;
+    let (input, withscores) = opt(keyword("WITHSCORES"))(input)?;
+
+    Ok((
+        input,
+        RedisCommand::Zrange(key, start, stop, withscores.is_some()),
+    ))
+}
+
+/// ZRANK key member
+fn parse_zrank(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, member) = parse_resp_string(input)?;
 
-    let (input, value) = terminated(not_line_ending, crlf)(input)?;
+    Ok((input, RedisCommand::Zrank(key, member)))
+}
 
-    Ok((input, value.to_string()))
+fn parse_zcard(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Zcard(key)))
 }
 
-fn parse_echo(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$4\r\nECHO\r\n")(input)?;
-    // let (input, _echo_length) = (length)(input)?;
-    let (input, echo_string) = (parse_resp_string)(input)?;
+/// ZREM key member [member ...]
+fn parse_zrem(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, members) = many1(parse_resp_string)(input)?;
 
-    Ok((input, RedisCommand::Echo(echo_string.to_string())))
+    Ok((input, RedisCommand::Zrem(key, members)))
 }
 
-/// https://redis.io/commands/strlen/
-/// STRLEN key
-fn parse_strlen(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$6\r\nSTRLEN\r\n")(input)?;
-    // let (input, _echo_length) = (length)(input)?;
-    let (input, key_string) = (parse_resp_string)(input)?;
+/// ZINCRBY key increment member
+fn parse_zincrby(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, increment) = parse_score(input)?;
+    let (input, member) = parse_resp_string(input)?;
 
-    Ok((input, RedisCommand::Strlen(key_string.to_string())))
+    Ok((input, RedisCommand::Zincrby(key, increment, member)))
 }
 
-fn parse_append(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$6\r\nAPPEND\r\n")(input)?;
+/// ZCOUNT key min max
+fn parse_zcount(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, min) = parse_score_bound(input)?;
+    let (input, max) = parse_score_bound(input)?;
 
-    // let's get the key to append to, first
-    let (input, key) = (parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Zcount(key, min, max)))
+}
 
-    // now let's grab the value we are appending
-    let (input, value) = (parse_resp_string)(input)?;
+/// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+fn parse_zrangebyscore(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, min) = parse_score_bound(input)?;
+    let (input, max) = parse_score_bound(input)?;
+    let (input, options) = many0(alt((
+        map(keyword("WITHSCORES"), |_| None),
+        map(
+            preceded(
+                keyword("LIMIT"),
+                pair(parse_integer::<i64>, parse_integer::<i64>),
+            ),
+            Some,
+        ),
+    )))(input)?;
+
+    let withscores = options.iter().any(Option::is_none);
+    let limit = options.into_iter().flatten().last();
 
     Ok((
         input,
-        RedisCommand::Append(key.to_string(), value.to_string()),
+        RedisCommand::Zrangebyscore(key, min, max, withscores, limit),
     ))
 }
 
 fn parse_del(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$3\r\nDEL\r\n")(input)?;
-
     // many1 runs the embedded parser, gathering the results in a Vec.
     // This stops on Err::Error if there is at least one result,
     // and returns the results that were accumulated.
-    let (input, keys_to_delete) = nom::multi::many1(parse_resp_string)(input)?;
+    let (input, keys_to_delete) = many1(parse_resp_string)(input)?;
     Ok((input, RedisCommand::Del(keys_to_delete)))
 }
 
-fn parse_mget(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$4\r\nMGET\r\n")(input)?;
+// The options that may follow COPY source destination, in any order.
+#[derive(Clone)]
+enum CopyArgument {
+    Db(i64),
+    Replace,
+}
 
+/// COPY source destination [DB destination-db] [REPLACE]
+fn parse_copy(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, source) = parse_resp_string(input)?;
+    let (input, destination) = parse_resp_string(input)?;
+
+    let (input, copy_arguments) = many0(alt((
+        map(
+            preceded(keyword("DB"), parse_integer::<i64>),
+            CopyArgument::Db,
+        ),
+        value(CopyArgument::Replace, keyword("REPLACE")),
+    )))(input)?;
+
+    let mut copy_params = CopyCommandParameter {
+        source,
+        destination,
+        db: None,
+        replace: false,
+    };
+
+    for copy_argument in copy_arguments {
+        match copy_argument {
+            CopyArgument::Db(db) => copy_params.db = Some(db),
+            CopyArgument::Replace => copy_params.replace = true,
+        }
+    }
+
+    Ok((input, RedisCommand::Copy(copy_params)))
+}
+
+fn parse_mget(input: &str) -> IResult<&str, RedisCommand> {
     // many1 runs the embedded parser, gathering the results in a Vec.
     // This stops on Err::Error if there is at least one result,
     // and returns the results that were accumulated.
-    let (input, keys_to_get) = nom::multi::many1(parse_resp_string)(input)?;
+    let (input, keys_to_get) = many1(parse_resp_string)(input)?;
     Ok((input, RedisCommand::Mget(keys_to_get)))
 }
 
-fn expiry_to_timestamp(expiry: ExpiryOption) -> anyhow::Result<u64> {
-    // u64 always since u32 secs fits into u64
-    // get the current system time
-    let now = SystemTime::now();
+/// EXISTS key [key ...], a key given twice is counted twice
+fn parse_exists(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, keys) = many1(parse_resp_string)(input)?;
 
-    // how many seconds have elapsed since beginning of time
-    let duration_since_epoch = now.duration_since(UNIX_EPOCH)?;
-    // .expect("Failed to calculate duration since epoch"); // Handle potential error
+    Ok((input, RedisCommand::Exists(keys)))
+}
 
-    // we don't want to lose precision between seconds & milliseconds
-    match expiry {
-        ExpiryOption::Seconds(seconds) => Ok(seconds as u64 + duration_since_epoch.as_secs()),
-        ExpiryOption::Milliseconds(milliseconds) => {
-            Ok(milliseconds + duration_since_epoch.as_millis() as u64) // nominally millis are u128
+// An EX, PX, EXAT or PXAT time, in units of unit_ms and from now if relative, as the unix
+// timestamp in milliseconds it stands for. Like redis, the time has to be positive and the deadline
+// has to fit in an i64 of milliseconds, else it fails with ErrorKind::Escaped, which
+// parse_command() reports as "invalid expire time".
+fn parse_expire_time(unit_ms: i64, relative: bool) -> impl Fn(&str) -> IResult<&str, u64> {
+    move |input| {
+        let (remaining, time) = parse_integer::<i64>(input)?;
+
+        let now_ms = if relative {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_millis() as i64)
+        } else {
+            0
+        };
+
+        let deadline = Some(time)
+            .filter(|time| *time > 0)
+            .and_then(|time| time.checked_mul(unit_ms))
+            .and_then(|milliseconds| milliseconds.checked_add(now_ms));
+
+        match deadline {
+            Some(deadline) => Ok((remaining, deadline as u64)),
+            None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Escaped))),
         }
     }
 }
 
-fn parse_expire_option(input: &str) -> IResult<&str, SetCommandExpireOption> {
+// The expiry option and the keyword it was given with, EX and PX already made absolute.
+fn parse_expire_option(input: &str) -> IResult<&str, (&'static str, SetCommandExpireOption)> {
     alt((
-        map_res(
-            tuple((tag_no_case("$2\r\nEX\r\n"), cut(parse_resp_string))),
-            |(_, seconds_str)| {
-                seconds_str
-                    .parse::<u32>()
-                    .map_err(|_: ParseIntError| {
-                        nom::Err::Failure(nom::error::Error::new(
-                            input,
-                            nom::error::ErrorKind::Digit,
-                        ))
-                    })
-                    .and_then(|seconds| {
-                        expiry_to_timestamp(ExpiryOption::Seconds(seconds))
-                            .map(|timestamp| SetCommandExpireOption::EX(timestamp as u32))
-                            .map_err(|_| {
-                                nom::Err::Failure(nom::error::Error::new(
-                                    input,
-                                    nom::error::ErrorKind::Verify,
-                                ))
-                            })
-                    })
-            },
+        map(
+            preceded(keyword("EX"), parse_expire_time(1000, true)),
+            |deadline| ("EX", SetCommandExpireOption::PX(deadline)),
         ),
-        map_res(
-            tuple((tag_no_case("$2\r\nPX\r\n"), cut(parse_resp_string))),
-            |(_, milliseconds_str)| {
-                milliseconds_str
-                    .parse::<u64>()
-                    .map_err(|_: ParseIntError| {
-                        nom::Err::Failure(nom::error::Error::new(
-                            input,
-                            nom::error::ErrorKind::Digit,
-                        ))
-                    })
-                    .and_then(|milliseconds| {
-                        expiry_to_timestamp(ExpiryOption::Milliseconds(milliseconds))
-                            .map(SetCommandExpireOption::PX)
-                            .map_err(|_| {
-                                nom::Err::Failure(nom::error::Error::new(
-                                    input,
-                                    nom::error::ErrorKind::Verify,
-                                ))
-                            })
-                    })
+        map(
+            preceded(keyword("PX"), parse_expire_time(1, true)),
+            |deadline| ("PX", SetCommandExpireOption::PX(deadline)),
+        ),
+        // already absolute, what a master sends its replicas in place of EX and PX
+        map(
+            preceded(keyword("EXAT"), parse_expire_time(1000, false)),
+            |deadline| {
+                (
+                    "EXAT",
+                    SetCommandExpireOption::EXAT(deadline as usize / 1000),
+                )
             },
         ),
+        map(
+            preceded(keyword("PXAT"), parse_expire_time(1, false)),
+            |deadline| ("PXAT", SetCommandExpireOption::PXAT(deadline as usize)),
+        ),
+        map(keyword("KEEPTTL"), |_| {
+            ("KEEPTTL", SetCommandExpireOption::KEEPTTL)
+        }),
+    ))(input)
+}
+
+// The options that may follow SET key value, in any order.
+#[derive(Clone)]
+enum SetArgument {
+    Condition(SetCommandSetOption),
+    Get,
+    // the keyword too, EX and PX both come out as a PX deadline
+    Expire(&'static str, SetCommandExpireOption),
+}
+
+fn parse_set_argument(input: &str) -> IResult<&str, SetArgument> {
+    alt((
+        // value: The value combinator is used to map the result of a parser to a specific value.
+        value(
+            SetArgument::Condition(SetCommandSetOption::NX),
+            keyword("NX"),
+        ),
+        value(
+            SetArgument::Condition(SetCommandSetOption::XX),
+            keyword("XX"),
+        ),
+        value(SetArgument::Get, keyword("GET")),
+        map(parse_expire_option, |(keyword, expire)| {
+            SetArgument::Expire(keyword, expire)
+        }),
     ))(input)
 }
 
 /// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
 fn parse_set_command(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$3\r\nSET\r\n")(input)?;
     let (input, key) = parse_resp_string(input)?;
     let (input, val) = parse_resp_string(input)?;
-    let (input, set_option) = opt(alt((
-        // value: The value combinator is used to map the result of a parser to a specific value.
-        //
-        // In this case, it's used to map the result of the tag_no_case combinator to SetCommandSetOption::NX or
-        // SetCommandSetOption::XX for the option.
-        value(SetCommandSetOption::NX, tag_no_case("$2\r\nNX\r\n")),
-        value(SetCommandSetOption::XX, tag_no_case("$2\r\nXX\r\n")),
-    )))(input)?;
 
-    // optional GET
-    let (input, set_get_option) = opt(map(tag_no_case("$3\r\nGET\r\n"), |_| true))(input)?;
-
-    // EX seconds | PX milliseconds
-    // let (input, expire_option) = opt(parse_expire_option)(input)?;
-
-    // PROBLEM: opt converts Error to None, so if ex is missing OR ex foo = invalid input, it all looks the same. :(
-    // Something we need to fix still.
-    let (input, expire_option) = opt(parse_expire_option)(input)?;
-
-    let set_params = SetCommandParameter {
+    let mut set_params = SetCommandParameter {
         key,
-        value: val,
-        option: set_option,
-        get: set_get_option,
-        expire: expire_option,
+        value: val.into_bytes(),
+        option: None,
+        get: None,
+        expire: None,
     };
+
+    // Same rules as redis: repeating an option is fine, combining NX with XX or EX with PX is not.
+    let (input, set_arguments) = many0(parse_set_argument)(input)?;
+    let mut expire_keyword = None;
+
+    for set_argument in set_arguments {
+        let conflicting = match set_argument {
+            SetArgument::Condition(option) => set_params
+                .option
+                .replace(option)
+                .is_some_and(|previous| discriminant(&previous) != discriminant(&option)),
+            SetArgument::Get => {
+                set_params.get = Some(true);
+                false
+            }
+            SetArgument::Expire(keyword, expire) => {
+                set_params.expire = Some(expire);
+                expire_keyword
+                    .replace(keyword)
+                    .is_some_and(|previous| previous != keyword)
+            }
+        };
+
+        if conflicting {
+            return Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify)));
+        }
+    }
     tracing::debug!("Parsed SET: {:?}", set_params);
 
     Ok((input, RedisCommand::Set(set_params)))
@@ -290,155 +1437,170 @@ fn parse_set_command(input: &str) -> IResult<&str, RedisCommand> {
 // }
 
 fn parse_get(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$3\r\nGET\r\n")(input)?;
-
     let (input, key) = (parse_resp_string)(input)?;
 
-    Ok((input, RedisCommand::Get(key.to_string())))
+    Ok((input, RedisCommand::Get(key)))
 }
 
 fn parse_config(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$6\r\nCONFIG\r\n$3\r\nGET\r\n")(input)?;
-
-    let (input, key) = (alt((
-        // value: The value combinator is used to map the result of a parser to a specific value.
-        // In this case, it's used to map the result of the tag_no_case combinator to ConfigCommandParameters::Dir or
-        // ConfigCommandParameters::Dbfilename for the option.
-        //
-        value(ConfigCommandParameter::Dir, tag_no_case("$3\r\ndir\r\n")),
-        value(
-            ConfigCommandParameter::DbFilename,
-            tag_no_case("$10\r\ndbfilename\r\n"),
+    // CONFIG GET parameter [parameter ...], each one a name, an alias or a glob.
+    // CONFIG SET parameter value [parameter value ...]
+    // CONFIG RESETSTAT
+    alt((
+        map(
+            preceded(keyword("GET"), many1(parse_resp_string)),
+            RedisCommand::Config,
         ),
-    )))(input)?;
-
-    Ok((input, RedisCommand::Config(key)))
+        map(
+            preceded(
+                keyword("SET"),
+                many1(pair(parse_resp_string, parse_resp_string)),
+            ),
+            RedisCommand::ConfigSet,
+        ),
+        map(keyword("RESETSTAT"), |_| RedisCommand::ConfigResetstat),
+    ))(input)
 }
 
 fn parse_keys(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$4\r\nKEYS\r\n")(input)?;
-
     let (input, pattern) = (parse_resp_string)(input)?;
 
-    Ok((input, RedisCommand::Keys(pattern.to_string())))
+    Ok((input, RedisCommand::Keys(pattern)))
 }
 
-fn parse_info(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$4\r\nINFO\r\n")(input)?;
+// The options that may follow SCAN cursor, in any order. The last one of a kind wins.
+#[derive(Clone)]
+enum ScanArgument {
+    Match(String),
+    Count(usize),
+    Type(String),
+}
 
-    let (input, option) = (opt(alt((
-        // value: The value combinator is used to map the result of a parser to a specific value.
-        //
-        value(InfoCommandParameter::All, tag_no_case("$3\r\nall\r\n")),
-        value(
-            InfoCommandParameter::Default,
-            tag_no_case("$7\r\ndefault\r\n"),
+/// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+fn parse_scan(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, cursor) = parse_integer::<u64>(input)?;
+
+    let (input, scan_arguments) = many0(alt((
+        map(
+            preceded(keyword("MATCH"), parse_resp_string),
+            ScanArgument::Match,
         ),
-        value(
-            InfoCommandParameter::Replication,
-            tag_no_case("$11\r\nreplication\r\n"),
+        map(
+            preceded(
+                keyword("COUNT"),
+                verify(parse_integer::<usize>, |count| *count > 0),
+            ),
+            ScanArgument::Count,
+        ),
+        map(
+            preceded(keyword("TYPE"), parse_resp_string),
+            ScanArgument::Type,
         ),
-    ))))(input)?;
+    )))(input)?;
+
+    // same default as redis
+    let mut scan_params = ScanCommandParameter {
+        cursor,
+        pattern: None,
+        value_type: None,
+        count: 10,
+    };
+
+    for scan_argument in scan_arguments {
+        match scan_argument {
+            ScanArgument::Match(pattern) => scan_params.pattern = Some(pattern),
+            ScanArgument::Count(count) => scan_params.count = count,
+            ScanArgument::Type(value_type) => scan_params.value_type = Some(value_type),
+        }
+    }
+
+    Ok((input, RedisCommand::Scan(scan_params)))
+}
+
+fn parse_info(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, section) = opt(parse_resp_string)(input)?;
+
+    // Sections we don't know about are not an error, they just have nothing to report.
+    let option = section.and_then(|section| match section.to_ascii_lowercase().as_str() {
+        "all" => Some(InfoCommandParameter::All),
+        "default" => Some(InfoCommandParameter::Default),
+        "replication" => Some(InfoCommandParameter::Replication),
+        "keyspace" => Some(InfoCommandParameter::Keyspace),
+        "stats" => Some(InfoCommandParameter::Stats),
+        "memory" => Some(InfoCommandParameter::Memory),
+        "clients" => Some(InfoCommandParameter::Clients),
+        "actors" => Some(InfoCommandParameter::Actors),
+        "commandstats" => Some(InfoCommandParameter::Commandstats),
+        "latencystats" => Some(InfoCommandParameter::Latencystats),
+        _ => None,
+    });
 
     Ok((input, RedisCommand::Info(option)))
 }
 
 fn parse_replconf(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$8\r\nREPLCONF\r\n")(input)?;
-
     // REPLCONF listening-port <PORT>
     // REPLCONF capa psync2 | *3\r\n$8\r\nREPLCONF\r\n$4\r\ncapa\r\n$6\r\npsync2\r\n
+    // REPLCONF capa eof capa psync2, one capa in front of each capability
     // REPLCONF getack <ACK>
     // REPLCONF ack <ACK>
     // alt: The alt combinator is used to try multiple parsers in order until one succeeds.
     // In this case, it's used to parse the various REPLCONF parameters.
     //
     let (input, replconf_params) = alt((
-        // value: The value combinator is used to map the result of a parser to a specific value.
-        // In this case, it's used to map the result of the tag_no_case combinator to ReplConfCommandParameter::ListeningPort,
-        // ReplConfCommandParameter::Capa, ReplConfCommandParameter::Getack, ReplConfCommandParameter::Ack for the option.
-        //
-        nom::combinator::map_res(
-            tuple((tag_no_case("$14\r\nlistening-port\r\n"), parse_resp_string)),
-            |(_, port_str)| {
-                port_str
-                    .parse::<u16>()
-                    .map(ReplConfCommandParameter::ListeningPort)
-                    .map_err(|_| nom::error::Error::new(port_str, nom::error::ErrorKind::Digit))
-            },
+        map(
+            preceded(keyword("listening-port"), parse_integer::<u16>),
+            ReplConfCommandParameter::ListeningPort,
         ),
         map(
-            tuple((
-                tag_no_case("$4\r\ncapa\r\n"),
-                count(parse_resp_string, len - 2), // Run parse_resp_string LEN - 1 (replconf) - 1 (capa) times.
+            many1(preceded(
+                keyword("capa"),
+                alt((
+                    value(Some(ReplicaCapability::Eof), keyword("eof")),
+                    value(Some(ReplicaCapability::Psync2), keyword("psync2")),
+                    // one this server doesn't know
+                    value(None, parse_resp_string),
+                )),
             )),
-            |(_, _capabilities)| {
-                ReplConfCommandParameter::Capa //
+            |capabilities| {
+                ReplConfCommandParameter::Capa(capabilities.into_iter().flatten().collect())
             },
         ),
-        // tuple with a tag_no_case "foo" and 5 parse_resp_string
         map(
-            tuple((tag_no_case("$6\r\ngetack\r\n"), parse_resp_string)),
-            |(_, ackvalue)| {
-                ReplConfCommandParameter::Getack(ackvalue) //
-            },
+            preceded(keyword("getack"), parse_resp_string),
+            ReplConfCommandParameter::Getack,
         ),
-        map_res(
-            tuple((tag_no_case("$3\r\nack\r\n"), parse_resp_string)),
-            |(_, offset)| {
-                offset
-                    .parse::<usize>()
-                    .map(ReplConfCommandParameter::Ack)
-                    .map_err(|_| nom::error::Error::new(offset, nom::error::ErrorKind::Digit))
-            },
+        map(
+            preceded(keyword("ack"), parse_integer::<usize>),
+            ReplConfCommandParameter::Ack,
         ),
-        // map_res(
-        //     tuple((tag_no_case("$3\r\nack\r\n"), parse_resp_string)),
-        //     |(_, offset)| {
-        //         ReplConfCommandParameter::Ack(
-        //             offset
-        //                 .parse::<u32>()
-        //                 .map(ReplConfCommandParameter::ListeningPort)
-        //                 .map_err(|_| nom::error::Error::new(offset, nom::error::ErrorKind::Digit)),
-        //         )
-        //     },
-        // ),
     ))(input)?;
 
     Ok((input, RedisCommand::ReplConf(replconf_params)))
 }
 
 fn parse_psync(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$5\r\nPSYNC\r\n")(input)?;
-
     // first argument is the replication ID of the master
     let (input, replication_id) = (parse_resp_string)(input)?;
 
     // second argument is the offset of the master
-    let (input, offset_string) = (parse_resp_string)(input)?;
-
-    // Attempt to parse the string as i16
-    let offset = offset_string
-        .parse()
-        .expect("Failed to convert offset to i16");
+    let (input, offset) = (parse_integer::<i64>)(input)?;
 
     Ok((input, RedisCommand::Psync(replication_id, offset)))
 }
 
+/// REPLICAOF host port | NO ONE, SLAVEOF is the old name for it.
+fn parse_replicaof(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, master) = alt((
+        value(None, pair(keyword("NO"), keyword("ONE"))),
+        map(pair(parse_resp_string, parse_integer::<u16>), Some),
+    ))(input)?;
+
+    Ok((input, RedisCommand::Replicaof(master)))
+}
+
 fn parse_fullresync(input: &str) -> IResult<&str, RedisCommand> {
-    // +FULLRESYNC <REPL_ID> 0\r\n
+    // +FULLRESYNC <REPL_ID> <OFFSET>\r\n
     let (input, _) = tag_no_case("+FULLRESYNC ")(input)?; // note trailing space
 
     // next, we need to grab the replica ID, an alphanumeric string of 40 characters
@@ -447,8 +1609,8 @@ fn parse_fullresync(input: &str) -> IResult<&str, RedisCommand> {
     // nom parse empty space
     let (input, _) = nom::character::streaming::space1(input)?;
 
-    // next is the offset which is an integer
-    let (input, offset_string) = nom::character::streaming::digit1(input)?;
+    // next is the master's offset as of the snapshot that follows
+    let (input, offset) = map_res(nom::character::streaming::digit1, str::parse::<i64>)(input)?;
 
     // crlf next
     let (input, _) = crlf(input)?;
@@ -460,11 +1622,6 @@ fn parse_fullresync(input: &str) -> IResult<&str, RedisCommand> {
     // // take the len bytes
     // let (input, rdb_contents) = nom::bytes::streaming::take(len)(input)?;
 
-    // Attempt to parse the string as i16
-    let offset = offset_string
-        .parse()
-        .expect("Failed to convert offset to i16");
-
     Ok((
         input,
         // RedisCommand::Fullresync(repl_id.to_string(), offset, rdb_contents.bytes().collect()),
@@ -488,44 +1645,376 @@ fn parse_rdb(input: &str) -> IResult<&str, RedisCommand> {
 
 /// Parse https://redis.io/docs/latest/commands/wait/
 fn parse_wait(input: &str) -> IResult<&str, RedisCommand> {
-    let (input, _) = tag("*")(input)?;
-    let (input, _len) = (length)(input)?; // length eats crlf
-    let (input, _) = tag_no_case("$4\r\nWAIT\r\n")(input)?;
+    let (input, numreplicas) = (parse_integer::<usize>)(input)?;
 
-    let (input, numreplicas_as_string) = (parse_resp_string)(input)?;
+    let (input, timeout) = (parse_integer::<usize>)(input)?;
 
-    let (input, timeout_as_string) = (parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Wait(numreplicas, timeout)))
+}
 
-    let numreplicas = numreplicas_as_string
-        .parse()
-        .expect("Unable to parse numreplicas in WAIT as u16");
-    let timeout = timeout_as_string
-        .parse()
-        .expect("Unable to parse timeout in WAIT as u16");
+/// Parse https://redis.io/docs/latest/commands/waitaof/
+fn parse_waitaof(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, numlocal) = (parse_integer::<usize>)(input)?;
 
-    Ok((input, RedisCommand::Wait(numreplicas, timeout)))
+    let (input, numreplicas) = (parse_integer::<usize>)(input)?;
+
+    let (input, timeout) = (parse_integer::<usize>)(input)?;
+
+    Ok((input, RedisCommand::WaitAof(numlocal, numreplicas, timeout)))
 }
-pub fn parse_command(input: &str) -> IResult<&str, RedisCommand> {
-    tracing::debug!("Parsing command: {}", input);
+
+/// SELECT index, the range is checked against the databases config by the processor.
+fn parse_select(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, index) = (parse_integer::<i64>)(input)?;
+
+    Ok((input, RedisCommand::Select(index)))
+}
+
+// Not parse_integer, redis says which of the two indexes isn't a number.
+fn parse_swapdb(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, first) = (parse_resp_string)(input)?;
+    let (input, second) = (parse_resp_string)(input)?;
+
+    Ok((input, RedisCommand::Swapdb(first, second)))
+}
+
+fn parse_dbsize(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Dbsize))
+}
+
+fn parse_subscribe(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, channels) = many1(parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Subscribe(channels)))
+}
+
+fn parse_unsubscribe(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, channels) = many0(parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Unsubscribe(channels)))
+}
+
+fn parse_psubscribe(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, patterns) = many1(parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Psubscribe(patterns)))
+}
+
+fn parse_punsubscribe(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, patterns) = many0(parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Punsubscribe(patterns)))
+}
+
+fn parse_publish(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, (channel, message)) = pair(parse_resp_string, parse_resp_string)(input)?;
+    Ok((input, RedisCommand::Publish(channel, message)))
+}
+
+fn parse_save(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Save))
+}
+
+fn parse_bgsave(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Bgsave))
+}
+
+// The options of SHUTDOWN, in any order.
+#[derive(Clone)]
+enum ShutdownArgument {
+    Save(bool),
+    Now,
+}
+
+/// SHUTDOWN [NOSAVE | SAVE] [NOW]
+fn parse_shutdown(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, shutdown_arguments) = many0(alt((
+        value(ShutdownArgument::Save(false), keyword("NOSAVE")),
+        value(ShutdownArgument::Save(true), keyword("SAVE")),
+        value(ShutdownArgument::Now, keyword("NOW")),
+    )))(input)?;
+
+    let mut shutdown_params = ShutdownCommandParameter::default();
+    let mut save = None;
+
+    for shutdown_argument in shutdown_arguments {
+        match shutdown_argument {
+            // NOSAVE and SAVE together is a syntax error, like in redis
+            ShutdownArgument::Save(_) if save.is_some() => {
+                return Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify)));
+            }
+            ShutdownArgument::Save(saving) => save = Some(saving),
+            ShutdownArgument::Now => shutdown_params.now = true,
+        }
+    }
+    shutdown_params.save = save.unwrap_or(false);
+
+    Ok((input, RedisCommand::Shutdown(shutdown_params)))
+}
+
+/// CLIENT ID | INFO | LIST [TYPE type | ID client-id [client-id ...]]
+fn parse_client(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, subcommand) = alt((
+        map(
+            preceded(
+                keyword("LIST"),
+                opt(alt((
+                    map(
+                        preceded(keyword("TYPE"), parse_resp_string),
+                        ClientListFilter::Type,
+                    ),
+                    map(
+                        preceded(keyword("ID"), many1(parse_integer::<i64>)),
+                        ClientListFilter::Ids,
+                    ),
+                ))),
+            ),
+            ClientCommandParameter::List,
+        ),
+        value(ClientCommandParameter::Id, keyword("ID")),
+        value(ClientCommandParameter::Info, keyword("INFO")),
+    ))(input)?;
+
+    Ok((input, RedisCommand::Client(subcommand)))
+}
+
+// The options of HELLO, in the order they were given
+#[derive(Debug, Clone)]
+enum HelloArgument {
+    Auth(String, String),
+    Setname(String),
+}
+
+/// HELLO [protover [AUTH username password] [SETNAME clientname]]
+/// The options need a protover in front of them, a repeated option goes by its last value.
+fn parse_hello(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, protover) = opt(parse_integer::<i64>)(input)?;
+
+    let mut hello = HelloCommandParameter {
+        protover,
+        ..Default::default()
+    };
+
+    if protover.is_none() {
+        return Ok((input, RedisCommand::Hello(hello)));
+    }
+
+    let (input, hello_arguments) = many0(alt((
+        map(
+            preceded(keyword("AUTH"), pair(parse_resp_string, parse_resp_string)),
+            |(username, password)| HelloArgument::Auth(username, password),
+        ),
+        map(
+            preceded(keyword("SETNAME"), parse_resp_string),
+            HelloArgument::Setname,
+        ),
+    )))(input)?;
+
+    for hello_argument in hello_arguments {
+        match hello_argument {
+            HelloArgument::Auth(username, password) => hello.auth = Some((username, password)),
+            HelloArgument::Setname(name) => hello.setname = Some(name),
+        }
+    }
+
+    Ok((input, RedisCommand::Hello(hello)))
+}
+
+/// AUTH [username] password
+fn parse_auth(input: &str) -> IResult<&str, RedisCommand> {
+    let (remaining, mut credentials) = many1(parse_resp_string)(input)?;
+
+    let password = credentials.pop().expect("many1 parsed at least one");
+    let username = credentials.pop();
+
+    if !credentials.is_empty() {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+
+    Ok((remaining, RedisCommand::Auth(username, password)))
+}
+
+fn parse_multi(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Multi))
+}
+
+fn parse_monitor(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Monitor))
+}
+
+fn parse_exec(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Exec))
+}
+
+fn parse_discard(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Discard))
+}
+
+fn parse_role(input: &str) -> IResult<&str, RedisCommand> {
+    Ok((input, RedisCommand::Role))
+}
+
+/// DEBUG FAILPOINT LATENCY <ms> | DROP <count> | DISCONNECT | OFF
+fn parse_debug(input: &str) -> IResult<&str, RedisCommand> {
+    alt((
+        map(preceded(keyword("OBJECT"), parse_resp_string), |key| {
+            RedisCommand::Debug(DebugCommandParameter::Object(key))
+        }),
+        map(
+            preceded(
+                keyword("SLEEP"),
+                map_res(parse_resp_string, |seconds| {
+                    seconds
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .ok_or(())
+                }),
+            ),
+            |duration| RedisCommand::Debug(DebugCommandParameter::Sleep(duration)),
+        ),
+        map(keyword("PANIC"), |_| {
+            RedisCommand::Debug(DebugCommandParameter::Panic)
+        }),
+        map(keyword("SEGFAULT"), |_| {
+            RedisCommand::Debug(DebugCommandParameter::Segfault)
+        }),
+        map(keyword("RELOAD"), |_| {
+            RedisCommand::Debug(DebugCommandParameter::Reload)
+        }),
+        map(keyword("JMAP"), |_| {
+            RedisCommand::Debug(DebugCommandParameter::Jmap)
+        }),
+        map(keyword("DIGEST"), |_| {
+            RedisCommand::Debug(DebugCommandParameter::Digest)
+        }),
+        map(
+            preceded(keyword("DIGEST-VALUE"), many0(parse_resp_string)),
+            |keys| RedisCommand::Debug(DebugCommandParameter::DigestValue(keys)),
+        ),
+        map(
+            preceded(keyword("EVICT"), parse_integer::<usize>),
+            |count| RedisCommand::Debug(DebugCommandParameter::Evict(count)),
+        ),
+        parse_debug_failpoint,
+    ))(input)
+}
+
+fn parse_debug_failpoint(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, _) = keyword("FAILPOINT")(input)?;
+
+    let (input, failpoint) = alt((
+        map(
+            preceded(keyword("LATENCY"), parse_integer::<u64>),
+            Failpoint::Latency,
+        ),
+        map(
+            preceded(keyword("DROP"), parse_integer::<usize>),
+            Failpoint::Drop,
+        ),
+        value(Failpoint::Disconnect, keyword("DISCONNECT")),
+        value(Failpoint::Off, keyword("OFF")),
+    ))(input)?;
+
+    Ok((
+        input,
+        RedisCommand::Debug(DebugCommandParameter::Failpoint(failpoint)),
+    ))
+}
+
+/// MEMORY USAGE key [SAMPLES count] | DOCTOR
+fn parse_memory(input: &str) -> IResult<&str, RedisCommand> {
     alt((
-        map(tag_no_case("*1\r\n$4\r\nPING\r\n"), |_| RedisCommand::Ping),
-        map(tag_no_case("*2\r\n$7\r\nCOMMAND\r\n$4\r\nDOCS\r\n"), |_| {
-            RedisCommand::Command
+        parse_memory_usage,
+        map(keyword("DOCTOR"), |_| {
+            RedisCommand::Memory(MemoryCommandParameter::Doctor)
         }),
-        parse_echo,
-        parse_set_command,
-        parse_get,
-        parse_del,
-        parse_strlen,
-        parse_mget,
-        parse_append,
-        parse_config,
-        parse_keys,
-        parse_info,
-        parse_replconf,
-        parse_psync,
-        parse_fullresync,
-        parse_rdb,
-        parse_wait,
     ))(input)
 }
+
+fn parse_memory_usage(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = preceded(keyword("USAGE"), parse_resp_string)(input)?;
+    let (input, _samples) = opt(preceded(keyword("SAMPLES"), parse_integer::<usize>))(input)?;
+
+    Ok((
+        input,
+        RedisCommand::Memory(MemoryCommandParameter::Usage(key)),
+    ))
+}
+
+/// Parses an encoded RESP request into a RedisCommand.
+///
+/// Client requests are arrays of bulk strings: the first one names the command, which is looked up
+/// in COMMAND_TABLE regardless of case, the arity is checked and the rest is handed to the command's parser.
+/// The errors carry the exact message redis would reply with.
+fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    COMMAND_TABLE
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+/// The flags of the command in a RESP encoded request, none if it isn't one we know.
+/// The processor goes by these to decide what reaches the replicas and what a replica refuses.
+pub fn command_flags(input: &str) -> &'static [CommandFlag] {
+    let name = preceded(pair(tag("*"), length), parse_resp_string)(input);
+
+    match name {
+        Ok((_, name)) => lookup(&name).map_or(&[], |spec| spec.flags),
+        Err(_) => &[],
+    }
+}
+
+/// The name of the command in a RESP encoded request as the command table spells it, none if it
+/// isn't one we know. INFO commandstats counts calls under it whatever case they came in.
+pub fn command_name(input: &str) -> Option<&'static str> {
+    let (_, name) = preceded(pair(tag("*"), length), parse_resp_string)(input).ok()?;
+
+    lookup(&name).map(|spec| spec.name)
+}
+
+pub fn parse_command(input: &str) -> Result<RedisCommand, RedisError> {
+    tracing::debug!("Parsing command: {}", input);
+
+    // Replies from the master during the handshake are not arrays.
+    if let Ok((_, command)) = alt((parse_fullresync, parse_rdb))(input) {
+        return Ok(command);
+    }
+
+    let (input, argc) = preceded(tag("*"), length)(input).map_err(|_| RedisError::ParseFailure)?;
+    let (input, name) = parse_resp_string(input).map_err(|_| RedisError::InvalidBulkLength)?;
+
+    let Some(spec) = lookup(&name) else {
+        let args = many0(parse_resp_string)(input)
+            .map(|(_, args)| args)
+            .unwrap_or_default();
+        return Err(RedisError::UnknownCommand(name, args));
+    };
+
+    let argc = argc as i64;
+    let arity_matches = if spec.arity >= 0 {
+        argc == spec.arity
+    } else {
+        argc >= -spec.arity
+    };
+
+    if !arity_matches {
+        return Err(RedisError::WrongArity(spec.name.to_lowercase()));
+    }
+
+    match (spec.parser)(input) {
+        Ok(("", command)) => Ok(command),
+        Ok(_) => Err(RedisError::SyntaxError),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => match e.code {
+            ErrorKind::Digit => Err(RedisError::NotAnInteger),
+            ErrorKind::LengthValue => Err(RedisError::InvalidBulkLength),
+            ErrorKind::Float => Err(RedisError::TimeoutNotFloat),
+            ErrorKind::Satisfy => Err(RedisError::NotAFloat),
+            ErrorKind::MapOpt => Err(RedisError::ScoreBoundNotFloat),
+            ErrorKind::Permutation => Err(RedisError::InvalidStreamId),
+            ErrorKind::Count => Err(RedisError::UnbalancedXread),
+            ErrorKind::Fix => Err(RedisError::NumkeysNotPositive),
+            ErrorKind::Eof => Err(RedisError::NumkeysPastArgs),
+            ErrorKind::Escaped => Err(RedisError::InvalidExpireTime(spec.name.to_lowercase())),
+            // more arguments than a command with a variable arity takes
+            ErrorKind::TooLarge => Err(RedisError::WrongArity(spec.name.to_lowercase())),
+            _ => Err(RedisError::SyntaxError),
+        },
+        Err(nom::Err::Incomplete(_)) => Err(RedisError::ParseFailure),
+    }
+}
@@ -0,0 +1,137 @@
+// The stream commands' parsers, https://redis.io/docs/latest/develop/data-types/streams/
+//
+// Besides ms-seq, an ID can be given in a handful of shorter forms, and which of them a command
+// takes depends on where the ID goes: * and ms-* only make sense to XADD, - and + to XRANGE and
+// $ to XREAD. One that is none of those fails with ErrorKind::Permutation, which parse_command()
+// reports as "Invalid stream ID".
+
+use nom::{
+    combinator::opt,
+    error::{Error, ErrorKind},
+    multi::many1,
+    sequence::{pair, preceded},
+    IResult,
+};
+
+use super::{keyword, parse_integer, parse_resp_string};
+use crate::{
+    collections::StreamId,
+    protocol::{RedisCommand, XaddCommandParameter, XaddId},
+};
+
+/// XADD key id field value [field value ...]
+pub(super) fn parse_xadd(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, id) = parse_xadd_id(input)?;
+    let (input, fields) = many1(pair(parse_resp_string, parse_resp_string))(input)?;
+
+    // a field without its value, which redis counts as the wrong number of arguments
+    if !input.is_empty() {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+
+    Ok((
+        input,
+        RedisCommand::Xadd(XaddCommandParameter { key, id, fields }),
+    ))
+}
+
+/// XRANGE key start end [COUNT count]
+/// A negative count is taken as 0, like redis does.
+pub(super) fn parse_xrange(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+    let (input, start) = parse_range_id(input, 0)?;
+    let (input, end) = parse_range_id(input, u64::MAX)?;
+    let (input, count) = opt(preceded(keyword("COUNT"), parse_integer::<i64>))(input)?;
+
+    let count = count.map(|count| count.max(0) as usize);
+
+    Ok((input, RedisCommand::Xrange(key, start, end, count)))
+}
+
+/// XLEN key
+pub(super) fn parse_xlen(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, key) = parse_resp_string(input)?;
+
+    Ok((input, RedisCommand::Xlen(key)))
+}
+
+/// XREAD [COUNT count] STREAMS key [key ...] id [id ...]
+/// The keys come first and then an ID for each of them, in the same order. A count that isn't
+/// positive is no count at all. There is no BLOCK, an XREAD never waits for an entry.
+pub(super) fn parse_xread(input: &str) -> IResult<&str, RedisCommand> {
+    let (input, count) = opt(preceded(keyword("COUNT"), parse_integer::<i64>))(input)?;
+    let (input, _) = keyword("STREAMS")(input)?;
+    let (remaining, mut keys) = many1(parse_resp_string)(input)?;
+
+    // Fails with ErrorKind::Count, which parse_command() reports as an unbalanced list of streams.
+    if keys.len() % 2 != 0 {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::Count)));
+    }
+
+    let ids = keys.split_off(keys.len() / 2);
+    let ids = ids
+        .iter()
+        .map(|id| match id.as_str() {
+            "$" => Ok(None),
+            id => stream_id(id, 0)
+                .map(Some)
+                .ok_or(nom::Err::Failure(Error::new(input, ErrorKind::Permutation))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let count = count.filter(|count| *count > 0).map(|count| count as usize);
+
+    Ok((
+        remaining,
+        RedisCommand::Xread(count, keys.into_iter().zip(ids).collect()),
+    ))
+}
+
+// XADD's ID: *, ms-* or ms-seq, and ms alone for ms-0.
+fn parse_xadd_id(input: &str) -> IResult<&str, XaddId> {
+    let (remaining, id) = parse_resp_string(input)?;
+
+    let id = match id.as_str() {
+        "*" => Some(XaddId::Auto),
+        id => match id.strip_suffix("-*") {
+            Some(ms) => ms.parse().ok().map(XaddId::Sequence),
+            None => stream_id(id, 0).map(XaddId::Explicit),
+        },
+    };
+
+    match id {
+        Some(id) => Ok((remaining, id)),
+        None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Permutation))),
+    }
+}
+
+// An end of an XRANGE: - for the first ID there can be, + for the last, or ms alone for ms with
+// missing_seq, which is 0 for the start and the greatest sequence number for the end.
+fn parse_range_id(input: &str, missing_seq: u64) -> IResult<&str, StreamId> {
+    let (remaining, id) = parse_resp_string(input)?;
+
+    let id = match id.as_str() {
+        "-" => Some(StreamId::MIN),
+        "+" => Some(StreamId::MAX),
+        id => stream_id(id, missing_seq),
+    };
+
+    match id {
+        Some(id) => Ok((remaining, id)),
+        None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Permutation))),
+    }
+}
+
+// ms-seq, or ms alone with missing_seq for the sequence number.
+fn stream_id(id: &str, missing_seq: u64) -> Option<StreamId> {
+    let (ms, seq) = match id.split_once('-') {
+        Some((ms, seq)) => (ms, seq.parse().ok()?),
+        None => (id, missing_seq),
+    };
+
+    Some(StreamId {
+        ms: ms.parse().ok()?,
+        seq,
+    })
+}
@@ -1,25 +1,210 @@
 // This file stores the various commands and their options currently supported.
 use core::fmt;
+use std::time::Duration;
 
-#[derive(Debug)]
+use crate::collections::{ListEnd, ScoreBound, StreamFields, StreamId};
+
+#[derive(Debug, PartialEq)]
 pub enum RedisCommand {
-    Ping,
+    Ping(Option<String>), // https://redis.io/commands/ping/, the message to echo back if any
     Echo(String),
     Command,
     Set(SetCommandParameter),
     Get(String),
     Del(Vec<String>),
-    Strlen(String),                 // https://redis.io/commands/strlen
-    Mget(Vec<String>),              // https://redis.io/commands/mget
-    Append(String, String),         // https://redis.io/commands/append/
-    Config(ConfigCommandParameter), // CONFIG GET
+    Copy(CopyCommandParameter), // https://redis.io/commands/copy/
+    Strlen(String),             // https://redis.io/commands/strlen
+    Mget(Vec<String>),          // https://redis.io/commands/mget
+    Exists(Vec<String>),        // https://redis.io/commands/exists/
+    Append(String, String),     // https://redis.io/commands/append/
+    Config(Vec<String>),        // CONFIG GET parameter [parameter ...]
+    ConfigResetstat,            // https://redis.io/commands/config-resetstat/
+    // CONFIG SET parameter value [parameter value ...]
+    ConfigSet(Vec<(String, String)>),
+    // https://redis.io/commands/setrange/
+    Setrange(String, i64, String),
+    // https://redis.io/commands/getrange/, start and end as given, both inclusive
+    Getrange(String, i64, i64),
+    // INCR, DECR, INCRBY and DECRBY, as what they add to the key
+    Incrby(String, i64),
+    // EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT, as the deadline they work out to in unix
+    // milliseconds. One that has already passed deletes the key.
+    Expire(String, i64),
+    Ttl(String),     // https://redis.io/commands/ttl/
+    Pttl(String),    // https://redis.io/commands/pttl/
+    Persist(String), // https://redis.io/commands/persist/
     Keys(String),
+    Scan(ScanCommandParameter), // https://redis.io/commands/scan/
     Info(Option<InfoCommandParameter>),
     ReplConf(ReplConfCommandParameter),
-    Psync(String, i16),      // client (master_replid, master_repl_offset)
-    Fullresync(String, i16), // master's (master_replid, master_repl_offset)
-    Rdb(Vec<u8>),            // RDB file in memory representation
+    Replicaof(Option<(String, u16)>), // REPLICAOF host port, None is REPLICAOF NO ONE
+    Psync(String, i64),               // client (master_replid, master_repl_offset)
+    Fullresync(String, i64),          // master's (master_replid, master_repl_offset)
+    Rdb(Vec<u8>),                     // RDB file in memory representation
     Wait(usize, usize),
+    WaitAof(usize, usize, usize), // https://redis.io/commands/waitaof/
+    Role,                         // https://redis.io/commands/role/
+    Select(i64),                  // https://redis.io/commands/select/
+    Dbsize,                       // https://redis.io/commands/dbsize/
+    Subscribe(Vec<String>),       // https://redis.io/commands/subscribe/
+    Unsubscribe(Vec<String>),     // no channels is every channel
+    Psubscribe(Vec<String>),      // https://redis.io/commands/psubscribe/
+    Punsubscribe(Vec<String>),    // no patterns is every pattern
+    Publish(String, String),      // PUBLISH channel message
+    Save,                         // https://redis.io/commands/save/
+    Bgsave,                       // https://redis.io/commands/bgsave/
+    Shutdown(ShutdownCommandParameter), // https://redis.io/commands/shutdown/
+    Multi,                        // https://redis.io/commands/multi/
+    Exec,                         // https://redis.io/commands/exec/
+    Discard,                      // https://redis.io/commands/discard/
+    Monitor,                      // https://redis.io/commands/monitor/
+    Client(ClientCommandParameter),
+    Hello(HelloCommandParameter),
+    // AUTH [username] password, https://redis.io/commands/auth/, None for the default user
+    Auth(Option<String>, String),
+    Debug(DebugCommandParameter),
+    Memory(MemoryCommandParameter),
+    // https://redis.io/commands/swapdb/, the indexes as given, each is checked on its own
+    Swapdb(String, String),
+    // HSET key field value [field value ...], https://redis.io/commands/hset/
+    Hset(String, Vec<(String, String)>),
+    // HGET key field, https://redis.io/commands/hget/
+    Hget(String, String),
+    // HDEL key field [field ...], https://redis.io/commands/hdel/
+    Hdel(String, Vec<String>),
+    // HGETALL, HLEN, HKEYS and HVALS key, the whole hash or what it has
+    Hgetall(String),
+    Hlen(String),
+    Hkeys(String),
+    Hvals(String),
+    // LPUSH and RPUSH key element [element ...], https://redis.io/commands/lpush/
+    Push(String, ListEnd, Vec<String>),
+    // LPOP and RPOP key [count], None when there is no count and the reply is a lone element
+    Pop(String, ListEnd, Option<usize>),
+    // BLPOP and BRPOP key [key ...] timeout, the timeout in seconds and 0 for none
+    BlockingPop(Vec<String>, ListEnd, f64),
+    // LRANGE key start stop, https://redis.io/commands/lrange/
+    Lrange(String, i64, i64),
+    // LLEN key, https://redis.io/commands/llen/
+    Llen(String),
+    // SADD key member [member ...], https://redis.io/commands/sadd/
+    Sadd(String, Vec<String>),
+    // SREM key member [member ...], https://redis.io/commands/srem/
+    Srem(String, Vec<String>),
+    // SMEMBERS key, https://redis.io/commands/smembers/
+    Smembers(String),
+    // SISMEMBER key member, https://redis.io/commands/sismember/
+    Sismember(String, String),
+    // SCARD key, https://redis.io/commands/scard/
+    Scard(String),
+    // SUNION key [key ...], https://redis.io/commands/sunion/
+    Sunion(Vec<String>),
+    // SINTER key [key ...], https://redis.io/commands/sinter/
+    Sinter(Vec<String>),
+    // SINTERCARD numkeys key [key ...] [LIMIT limit], https://redis.io/commands/sintercard/
+    // A limit of 0 is no limit.
+    Sintercard(Vec<String>, usize),
+    // https://redis.io/commands/zadd/
+    Zadd(ZaddCommandParameter),
+    // ZSCORE key member, https://redis.io/commands/zscore/
+    Zscore(String, String),
+    // ZRANGE key start stop [WITHSCORES], https://redis.io/commands/zrange/
+    Zrange(String, i64, i64, bool),
+    // ZRANK key member, https://redis.io/commands/zrank/
+    Zrank(String, String),
+    // ZCARD key, https://redis.io/commands/zcard/
+    Zcard(String),
+    // ZREM key member [member ...], https://redis.io/commands/zrem/
+    Zrem(String, Vec<String>),
+    // ZINCRBY key increment member, https://redis.io/commands/zincrby/
+    Zincrby(String, f64, String),
+    // ZCOUNT key min max, https://redis.io/commands/zcount/
+    Zcount(String, ScoreBound, ScoreBound),
+    // ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+    // https://redis.io/commands/zrangebyscore/, a negative count is no limit
+    Zrangebyscore(String, ScoreBound, ScoreBound, bool, Option<(i64, i64)>),
+    // https://redis.io/commands/xadd/
+    Xadd(XaddCommandParameter),
+    // XRANGE key start end [COUNT count], https://redis.io/commands/xrange/
+    Xrange(String, StreamId, StreamId, Option<usize>),
+    // XLEN key, https://redis.io/commands/xlen/
+    Xlen(String),
+    // XREAD [COUNT count] STREAMS key [key ...] id [id ...], https://redis.io/commands/xread/
+    // Each key with the ID to read after, None for $: only what is added from now on.
+    Xread(Option<usize>, Vec<(String, Option<StreamId>)>),
+}
+
+// What a command does, kept per command in the parser's command table.
+// https://redis.io/commands/command/#flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,    // modifies the dataset, so it is propagated and refused by a replica
+    Readonly, // only reads the dataset
+    Admin,    // server administration and replication internals
+    Pubsub,   // publish/subscribe
+    Blocking, // may block the client
+    NoMulti,  // refused while a MULTI is open
+    DenyOom,  // may grow the dataset, so it is refused once noeviction can't keep to maxmemory
+    NoAuth,   // runs before the connection has authenticated
+}
+
+// CLIENT subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientCommandParameter {
+    List(Option<ClientListFilter>), // https://redis.io/commands/client-list/
+    Id,                             // https://redis.io/commands/client-id/
+    Info,                           // https://redis.io/commands/client-info/
+}
+
+// HELLO [protover [AUTH username password] [SETNAME clientname]]
+// https://redis.io/commands/hello/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HelloCommandParameter {
+    pub protover: Option<i64>, // None only answers with what the connection has
+    pub auth: Option<(String, String)>, // username, password
+    pub setname: Option<String>, // an empty name clears it
+}
+
+// CLIENT LIST TYPE type | ID client-id [client-id ...]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientListFilter {
+    Type(String), // normal, master, replica (or slave) or pubsub, checked when it runs
+    Ids(Vec<i64>),
+}
+
+// DEBUG subcommands, refused unless the server runs with --enable-debug-command
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugCommandParameter {
+    Failpoint(Failpoint),
+    Object(String),  // DEBUG OBJECT key
+    Sleep(Duration), // DEBUG SLEEP seconds, stalls the request processor
+    Panic,           // DEBUG PANIC, panics the request processor while it handles the request
+    Segfault,        // DEBUG SEGFAULT, panics the connection's own task
+    Reload,          // DEBUG RELOAD, SAVE and load the file back in place
+    Evict(usize), // DEBUG EVICT count, evicts count keys by maxmemory-policy whatever maxmemory is
+    Jmap,         // DEBUG JMAP, the deadline of every key in the database that has one
+    Digest,       // DEBUG DIGEST, a digest of every key in every database
+    // DEBUG DIGEST-VALUE key [key ...], a digest of each value
+    DigestValue(Vec<String>),
+}
+
+// MEMORY subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryCommandParameter {
+    // https://redis.io/commands/memory-usage/, SAMPLES is checked and has nothing to sample in a string
+    Usage(String),
+    // https://redis.io/commands/memory-doctor/
+    Doctor,
+}
+
+// DEBUG FAILPOINT LATENCY <ms> | DROP <count> | DISCONNECT | OFF
+// Faults injected into the master -> replica stream, to exercise replication under a bad network.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failpoint {
+    Latency(u64), // hold back every frame this many milliseconds, 0 turns it off
+    Drop(usize),  // silently drop the next n frames
+    Disconnect,   // close the next replica link that has a frame to send
+    Off,          // clear everything above
 }
 
 // REPLCONF parameters
@@ -28,10 +213,20 @@ pub enum RedisCommand {
 pub enum ReplConfCommandParameter {
     Getack(String),
     Ack(usize),
-    Capa,
+    Capa(Vec<ReplicaCapability>),
     ListeningPort(u16),
 }
 
+/// What a replica announces it can handle with REPLCONF capa. Anything else it announces is
+/// ignored, same as redis does.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum ReplicaCapability {
+    // a diskless transfer may be sent as $EOF:<mark>\r\n<rdb><mark>, without knowing its length
+    Eof,
+    // it understands +CONTINUE <replid> for a partial resync after a failover
+    Psync2,
+}
+
 // INFO [section [section ...]]
 // The optional parameter can be used to select a specific section of information:
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -39,6 +234,13 @@ pub enum InfoCommandParameter {
     All,
     Default,
     Replication,
+    Keyspace,
+    Stats,
+    Memory,
+    Clients,
+    Actors, // not in redis, how the supervised actors are doing
+    Commandstats,
+    Latencystats,
 }
 
 /// Replication section https://redis.io/docs/latest/commands/info/
@@ -57,7 +259,12 @@ pub struct ReplicationSectionData {
     // which risks race conditions in cases of multiple threads trying to update the same value at the same time.
     pub role: Option<ServerRole>,
     pub master_replid: Option<String>,
-    pub master_repl_offset: Option<i16>, // cannot be u16 because initial offset is -1
+    pub master_repl_offset: Option<i64>, // cannot be u16 because initial offset is -1
+    // The port a replica announced with REPLCONF listening-port. Its connection comes from some
+    // other, ephemeral port, this is the one it serves clients on and the one INFO and ROLE report.
+    pub listening_port: Option<u16>,
+    // What a replica announced with REPLCONF capa, added to whatever it announced before.
+    pub capabilities: Option<Vec<ReplicaCapability>>,
 }
 
 impl fmt::Display for ReplicationSectionData {
@@ -84,12 +291,20 @@ impl fmt::Display for ReplicationSectionData {
     }
 }
 
+impl Default for ReplicationSectionData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ReplicationSectionData {
     pub fn new() -> Self {
         ReplicationSectionData {
             role: None,          // Default role is Master
             master_replid: None, // Empty string by default
             master_repl_offset: Some(0),
+            listening_port: None,
+            capabilities: None,
         }
     }
 
@@ -155,10 +370,10 @@ impl fmt::Display for ServerRole {
 }
 
 // SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SetCommandParameter {
     pub key: String,
-    pub value: String,
+    pub value: Vec<u8>,
     pub option: Option<SetCommandSetOption>,
     // GET: Return the old string stored at key, or nil if key did not exist.
     // An error is returned and SET aborted if the value stored at key is not a string.
@@ -166,25 +381,54 @@ pub struct SetCommandParameter {
     pub expire: Option<SetCommandExpireOption>,
 }
 
+// ZADD key [NX | XX] [GT | LT] [CH] score member [score member ...]
+// The flags are kept as given, which of them can't go together is the processor's to refuse.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ZaddCommandParameter {
+    pub key: String,
+    // NX: only add new members. XX: only update the scores of members already in.
+    pub nx: bool,
+    pub xx: bool,
+    // GT and LT: only move a member's score up, or down. New members are added all the same.
+    pub gt: bool,
+    pub lt: bool,
+    // CH: reply with how many members were added or got a new score, not only added
+    pub ch: bool,
+    pub members: Vec<(f64, String)>,
+}
+
+// XADD key id field value [field value ...]
+#[derive(Clone, Debug, PartialEq)]
+pub struct XaddCommandParameter {
+    pub key: String,
+    pub id: XaddId,
+    pub fields: StreamFields,
+}
+
+/// The ID XADD is given for the new entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XaddId {
+    /// *, the time now and the next sequence number for it
+    Auto,
+    /// ms-*, the next sequence number for ms
+    Sequence(u64),
+    /// ms-seq, or ms for ms-0
+    Explicit(StreamId),
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct WaitCommandParameter {
     pub numreplicas: u16,
     pub timeout: u16,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SetCommandSetOption {
     NX,
     XX,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum ExpiryOption {
-    Seconds(u32),
-    Milliseconds(u64),
-}
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SetCommandExpireOption {
     EX(u32), // unix timestamp seconds
     PX(u64), // unix timestamp milliseconds
@@ -193,11 +437,132 @@ pub enum SetCommandExpireOption {
     KEEPTTL,
 }
 
+impl SetCommandExpireOption {
+    /// The deadline as a unix timestamp in milliseconds, None for KEEPTTL which has no deadline of its own.
+    pub fn deadline_ms(&self) -> Option<u64> {
+        match *self {
+            SetCommandExpireOption::EX(seconds) => Some(seconds as u64 * 1000),
+            SetCommandExpireOption::PX(milliseconds) => Some(milliseconds),
+            // SET's parser has checked that it fits, see parse_expire_time()
+            SetCommandExpireOption::EXAT(seconds) => Some((seconds as u64).saturating_mul(1000)),
+            SetCommandExpireOption::PXAT(milliseconds) => Some(milliseconds as u64),
+            SetCommandExpireOption::KEEPTTL => None,
+        }
+    }
+}
+
+// COPY source destination [DB destination-db] [REPLACE]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyCommandParameter {
+    pub source: String,
+    pub destination: String,
+    pub db: Option<i64>, // the destination database, the selected one if not given
+    pub replace: bool,
+}
+
+// SHUTDOWN [NOSAVE | SAVE] [NOW]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShutdownCommandParameter {
+    pub save: bool, // SAVE, there are no save points to save by otherwise
+    pub now: bool,  // don't wait for the replicas to catch up
+}
+
+// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanCommandParameter {
+    pub cursor: u64,
+    pub pattern: Option<String>,
+    pub value_type: Option<String>, // only keys holding this type, as TYPE names it
+    // how much work a call does, not how many keys it returns
+    pub count: usize,
+}
+
 // these are passed from the command line
 #[derive(Debug, Clone, PartialEq, Copy, Eq, Hash)]
 pub enum ConfigCommandParameter {
     Dir,
     DbFilename,
+    Databases,
+    EnableDebugCommand,
+    Replicaof,
+    AppendOnly,
+    PubsubQueueLimit,
+    MaxmemoryClients,
+    ClientQueryBufferInitialSize,
+    ClientQueryBufferLimit,
+    KeysWarnThreshold,
+    KeysByScan,
+    RequestTimeout,
+    ReadTimeLimit,
+    NotifyKeyspaceEvents,
+    Hz,
+    ProtoMaxBulkLen,
+    ReplDisklessSync,
+    ReplDisklessSyncDelay,
+    ReplLagWarnThreshold,
+    ReplApplyWarnThreshold,
+    ReplAckInterval,
+    ShutdownTimeout,
+    StorageBackend,
+    Maxmemory,
+    MaxmemoryPolicy,
+    ReplicaIgnoreMaxmemory,
+    Auditlog,
+    AuditlogMaxSize,
+}
+
+impl ConfigCommandParameter {
+    /// Every parameter CONFIG GET can report, in the order a glob lists them.
+    pub const ALL: [ConfigCommandParameter; 29] = [
+        ConfigCommandParameter::Dir,
+        ConfigCommandParameter::DbFilename,
+        ConfigCommandParameter::Databases,
+        ConfigCommandParameter::EnableDebugCommand,
+        ConfigCommandParameter::Replicaof,
+        ConfigCommandParameter::AppendOnly,
+        ConfigCommandParameter::PubsubQueueLimit,
+        ConfigCommandParameter::MaxmemoryClients,
+        ConfigCommandParameter::ClientQueryBufferInitialSize,
+        ConfigCommandParameter::ClientQueryBufferLimit,
+        ConfigCommandParameter::KeysWarnThreshold,
+        ConfigCommandParameter::KeysByScan,
+        ConfigCommandParameter::RequestTimeout,
+        ConfigCommandParameter::ReadTimeLimit,
+        ConfigCommandParameter::NotifyKeyspaceEvents,
+        ConfigCommandParameter::Hz,
+        ConfigCommandParameter::ProtoMaxBulkLen,
+        ConfigCommandParameter::ReplDisklessSync,
+        ConfigCommandParameter::ReplDisklessSyncDelay,
+        ConfigCommandParameter::ReplLagWarnThreshold,
+        ConfigCommandParameter::ReplApplyWarnThreshold,
+        ConfigCommandParameter::ReplAckInterval,
+        ConfigCommandParameter::ShutdownTimeout,
+        ConfigCommandParameter::StorageBackend,
+        ConfigCommandParameter::Maxmemory,
+        ConfigCommandParameter::MaxmemoryPolicy,
+        ConfigCommandParameter::ReplicaIgnoreMaxmemory,
+        ConfigCommandParameter::Auditlog,
+        ConfigCommandParameter::AuditlogMaxSize,
+    ];
+
+    /// The parameter called name, or one of its aliases, in any case.
+    pub fn named(name: &str) -> Option<ConfigCommandParameter> {
+        ConfigCommandParameter::ALL.into_iter().find(|config_key| {
+            config_key.to_string().eq_ignore_ascii_case(name)
+                || config_key
+                    .aliases()
+                    .iter()
+                    .any(|alias| alias.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Old names redis still accepts after a parameter was renamed, slaveof became replicaof in 5.0.
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            ConfigCommandParameter::Replicaof => &["slaveof"],
+            _ => &[],
+        }
+    }
 }
 
 // this is needed to convert the enum variants to strings
@@ -206,6 +571,43 @@ impl fmt::Display for ConfigCommandParameter {
         match self {
             ConfigCommandParameter::Dir => write!(f, "dir"),
             ConfigCommandParameter::DbFilename => write!(f, "dbfilename"),
+            ConfigCommandParameter::Databases => write!(f, "databases"),
+            ConfigCommandParameter::EnableDebugCommand => write!(f, "enable-debug-command"),
+            ConfigCommandParameter::Replicaof => write!(f, "replicaof"),
+            ConfigCommandParameter::AppendOnly => write!(f, "appendonly"),
+            ConfigCommandParameter::PubsubQueueLimit => write!(f, "pubsub-queue-limit"),
+            ConfigCommandParameter::MaxmemoryClients => write!(f, "maxmemory-clients"),
+            ConfigCommandParameter::ClientQueryBufferInitialSize => {
+                write!(f, "client-query-buffer-initial-size")
+            }
+            ConfigCommandParameter::ClientQueryBufferLimit => {
+                write!(f, "client-query-buffer-limit")
+            }
+            ConfigCommandParameter::KeysWarnThreshold => write!(f, "keys-warn-threshold"),
+            ConfigCommandParameter::KeysByScan => write!(f, "keys-by-scan"),
+            ConfigCommandParameter::RequestTimeout => write!(f, "request-timeout"),
+            ConfigCommandParameter::ReadTimeLimit => write!(f, "read-time-limit"),
+            ConfigCommandParameter::NotifyKeyspaceEvents => write!(f, "notify-keyspace-events"),
+            ConfigCommandParameter::Hz => write!(f, "hz"),
+            ConfigCommandParameter::ProtoMaxBulkLen => write!(f, "proto-max-bulk-len"),
+            ConfigCommandParameter::ReplDisklessSync => write!(f, "repl-diskless-sync"),
+            ConfigCommandParameter::ReplDisklessSyncDelay => {
+                write!(f, "repl-diskless-sync-delay")
+            }
+            ConfigCommandParameter::ReplLagWarnThreshold => write!(f, "repl-lag-warn-threshold"),
+            ConfigCommandParameter::ReplApplyWarnThreshold => {
+                write!(f, "repl-apply-warn-threshold")
+            }
+            ConfigCommandParameter::ReplAckInterval => write!(f, "repl-ack-interval"),
+            ConfigCommandParameter::ShutdownTimeout => write!(f, "shutdown-timeout"),
+            ConfigCommandParameter::StorageBackend => write!(f, "storage-backend"),
+            ConfigCommandParameter::Maxmemory => write!(f, "maxmemory"),
+            ConfigCommandParameter::MaxmemoryPolicy => write!(f, "maxmemory-policy"),
+            ConfigCommandParameter::ReplicaIgnoreMaxmemory => {
+                write!(f, "replica-ignore-maxmemory")
+            }
+            ConfigCommandParameter::Auditlog => write!(f, "auditlog"),
+            ConfigCommandParameter::AuditlogMaxSize => write!(f, "auditlog-max-size"),
         }
     }
 }
@@ -1,11 +1,19 @@
+use std::sync::Arc;
+
 use nom::{Err, Needed};
-use tokio_util::codec::Decoder;
+use tokio_util::codec::{Decoder, Encoder};
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 
-use crate::errors::RedisError;
+use crate::{
+    actors::messages::DatabaseSnapshot, collections::Collection, errors::RedisError,
+    protocol::SetCommandExpireOption,
+};
 
-use super::{format::Rdb, parsers::parse_rdb_file};
+use super::{
+    format::{Rdb, RdbOpCode, ValueType, STREAM_VALUE_TYPE, VALUE_TYPES},
+    parsers::parse_rdb_file,
+};
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct RdbCodec {}
@@ -44,26 +52,307 @@ impl Decoder for RdbCodec {
             }
             Err(Err::Incomplete(Needed::Size(_))) => Ok(None),
 
-            Err(_) => Err(RedisError::ParseFailure),
+            Err(_) => Err(match unknown_value_type(src) {
+                Some(value_type) => RedisError::UnknownRdbType(value_type),
+                None => RedisError::ParseFailure,
+            }),
+        }
+    }
+}
+
+// The value type of the key at the front of src if it's none the parsers know, redis' own streams
+// or modules say. An expiry in front of the key is skipped. Headers and opcodes are known.
+fn unknown_value_type(src: &[u8]) -> Option<u8> {
+    let value_type = match *src.first()? {
+        0xFC => *src.get(9)?,
+        0xFD => *src.get(5)?,
+        b'R' | 0xFA..=0xFF => return None,
+        value_type => value_type,
+    };
+
+    (!VALUE_TYPES.contains(&value_type)).then_some(value_type)
+}
+
+impl Encoder<Rdb> for RdbCodec {
+    type Error = RedisError;
+
+    fn encode(&mut self, item: Rdb, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match item {
+            Rdb::RdbHeader { magic, version } => {
+                dst.extend_from_slice(magic.as_bytes());
+                dst.extend_from_slice(version.as_bytes());
+            }
+            Rdb::OpCode { opcode } => match opcode {
+                RdbOpCode::Eof() => {
+                    dst.put_u8(0xFF);
+                    // a zero checksum tells the loader not to verify it
+                    dst.put_u64_le(0);
+                }
+                RdbOpCode::Selectdb { db_number } => {
+                    dst.put_u8(0xFE);
+                    encode_length(db_number, dst);
+                }
+                RdbOpCode::ResizeDb {
+                    db_hash_table_length,
+                    expiry_hash_table_length,
+                } => {
+                    dst.put_u8(0xFB);
+                    encode_length(db_hash_table_length, dst);
+                    encode_length(expiry_hash_table_length, dst);
+                }
+                // the parser doesn't keep aux fields, so there is nothing to write back out
+                RdbOpCode::Aux => {}
+            },
+            Rdb::KeyValuePair {
+                key_expiry_time,
+                value_type: _, // strings are the only value type we store
+                key,
+                value,
+            } => {
+                encode_expiry(key_expiry_time, dst)?;
+
+                dst.put_u8(0x00); // string value type
+                encode_string(&key, dst)?;
+                encode_string(&value, dst)?;
+            }
+            Rdb::KeyCollectionPair {
+                key_expiry_time,
+                key,
+                collection,
+            } => {
+                encode_expiry(key_expiry_time, dst)?;
+
+                dst.put_u8(collection_value_type(&collection));
+                encode_string(&key, dst)?;
+                encode_collection(&collection, dst)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The value type byte a collection is written with. Streams get one of their own, see format.rs.
+fn collection_value_type(collection: &Collection) -> u8 {
+    match collection {
+        Collection::Hash(_) => 0x04,
+        Collection::List(_) => 0x01,
+        Collection::Set(_) => 0x02,
+        Collection::SortedSet(_) => 0x05, // ZSET_2
+        Collection::Stream(_) => STREAM_VALUE_TYPE,
+    }
+}
+
+// A collection's value, the part that follows its type byte and key.
+fn encode_collection(collection: &Collection, dst: &mut BytesMut) -> Result<(), RedisError> {
+    match collection {
+        Collection::Hash(hash) => {
+            encode_length(count_length(hash.len())?, dst);
+
+            for (field, value) in hash {
+                encode_string(field, dst)?;
+                encode_string(value, dst)?;
+            }
+        }
+        Collection::List(list) => {
+            encode_length(count_length(list.len())?, dst);
+
+            for value in list {
+                encode_string(value, dst)?;
+            }
+        }
+        Collection::Set(set) => {
+            encode_length(count_length(set.len())?, dst);
+
+            for member in set {
+                encode_string(member, dst)?;
+            }
+        }
+        Collection::SortedSet(sorted_set) => {
+            encode_length(count_length(sorted_set.len())?, dst);
+
+            for (member, score) in sorted_set.iter() {
+                encode_string(member, dst)?;
+                dst.put_f64_le(score);
+            }
+        }
+        Collection::Stream(stream) => {
+            encode_length(count_length(stream.len())?, dst);
+
+            for (id, fields) in stream.iter() {
+                dst.put_u64_le(id.ms);
+                dst.put_u64_le(id.seq);
+                encode_length(count_length(fields.len())?, dst);
+
+                for (field, value) in fields {
+                    encode_string(field, dst)?;
+                    encode_string(value, dst)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The deadline in front of a key, if it has one. EX is written as seconds, everything else in
+// milliseconds.
+fn encode_expiry(
+    key_expiry_time: Option<SetCommandExpireOption>,
+    dst: &mut BytesMut,
+) -> Result<(), RedisError> {
+    match key_expiry_time {
+        Some(SetCommandExpireOption::EX(seconds)) => {
+            dst.put_u8(0xFD);
+            dst.put_u32_le(seconds);
         }
+        Some(SetCommandExpireOption::PX(milliseconds)) => {
+            dst.put_u8(0xFC);
+            dst.put_u64_le(milliseconds);
+        }
+        // what a replica is sent in place of EX and PX, kept in milliseconds
+        Some(SetCommandExpireOption::EXAT(seconds)) => {
+            dst.put_u8(0xFC);
+            dst.put_u64_le(seconds as u64 * 1000);
+        }
+        Some(SetCommandExpireOption::PXAT(milliseconds)) => {
+            dst.put_u8(0xFC);
+            dst.put_u64_le(milliseconds as u64);
+        }
+        Some(other) => {
+            return Err(RedisError::RdbEncodeError(format!(
+                "{:?} is not an expiry deadline",
+                other
+            )))
+        }
+        None => {}
     }
+
+    Ok(())
+}
+
+// The number of elements of a collection, as a length.
+fn count_length(count: usize) -> Result<u32, RedisError> {
+    u32::try_from(count).map_err(|_| RedisError::RdbEncodeError("too many elements".to_string()))
+}
+
+// Length encoding, the inverse of parse_string_length():
+// 00 + 6 bits, 01 + 14 bits, or 0x80 followed by a 32 bit big endian length.
+fn encode_length(length: u32, dst: &mut BytesMut) {
+    if length < 1 << 6 {
+        dst.put_u8(length as u8);
+    } else if length < 1 << 14 {
+        dst.put_u16(0x4000 | length as u16);
+    } else {
+        dst.put_u8(0x80);
+        dst.put_u32(length);
+    }
+}
+
+// Keys and the strings in collections are UTF-8, string values any bytes.
+fn encode_string(string: impl AsRef<[u8]>, dst: &mut BytesMut) -> Result<(), RedisError> {
+    let string = string.as_ref();
+    let length = u32::try_from(string.len())
+        .map_err(|_| RedisError::RdbEncodeError("string too long".to_string()))?;
+
+    encode_length(length, dst);
+    dst.extend_from_slice(string);
+
+    Ok(())
 }
 
-// impl Encoder<Rdb> for RdbCodec {
-//     type Error = RedisError;
-
-//     fn encode(&mut self, item: Rdb, dst: &mut BytesMut) -> Result<(), Self::Error> {
-//         match item {
-//             Rdb::KeyValuePair {
-//                 key_expiry_time,
-//                 value_type,
-//                 key,
-//                 value,
-//             } => {
-
-//             },
-//             _ => {}
-//         }
-//         Ok(())
-//     }
-// }
+/// The size of a value in an RDB file, what DEBUG OBJECT reports as serializedlength.
+pub fn serialized_length(value: &[u8]) -> Result<usize, RedisError> {
+    let mut dst = BytesMut::new();
+    encode_string(value, &mut dst)?;
+
+    Ok(dst.len())
+}
+
+/// The size of a collection in an RDB file, without its type byte and key, like [`serialized_length`].
+pub fn serialized_collection_length(collection: &Collection) -> Result<usize, RedisError> {
+    let mut dst = BytesMut::new();
+    encode_collection(collection, &mut dst)?;
+
+    Ok(dst.len())
+}
+
+/// Serializes the store's contents into an RDB file.
+/// Only non-empty databases are in the snapshot, so those are the only ones that get a SELECTDB section.
+pub fn encode_snapshot(snapshot: Vec<DatabaseSnapshot>) -> Result<BytesMut, RedisError> {
+    let mut codec = RdbCodec::new();
+    let mut dst = BytesMut::new();
+
+    codec.encode(
+        Rdb::RdbHeader {
+            magic: "REDIS".to_string(),
+            version: "0011".to_string(),
+        },
+        &mut dst,
+    )?;
+
+    for database in snapshot {
+        let db_number = u32::try_from(database.db)
+            .map_err(|_| RedisError::RdbEncodeError("database index too large".to_string()))?;
+
+        let expiry_hash_table_length = database
+            .entries
+            .iter()
+            .filter(|(_, _, expiry)| expiry.is_some())
+            .count()
+            + database
+                .collections
+                .iter()
+                .filter(|(_, _, expiry)| expiry.is_some())
+                .count();
+
+        codec.encode(
+            Rdb::OpCode {
+                opcode: RdbOpCode::Selectdb { db_number },
+            },
+            &mut dst,
+        )?;
+        codec.encode(
+            Rdb::OpCode {
+                opcode: RdbOpCode::ResizeDb {
+                    db_hash_table_length: count_length(
+                        database.entries.len() + database.collections.len(),
+                    )?,
+                    expiry_hash_table_length: count_length(expiry_hash_table_length)?,
+                },
+            },
+            &mut dst,
+        )?;
+
+        for (key, value, key_expiry_time) in database.entries {
+            codec.encode(
+                Rdb::KeyValuePair {
+                    key_expiry_time,
+                    value_type: ValueType::StringEncoding,
+                    key,
+                    value: Arc::unwrap_or_clone(value),
+                },
+                &mut dst,
+            )?;
+        }
+
+        for (key, collection, key_expiry_time) in database.collections {
+            codec.encode(
+                Rdb::KeyCollectionPair {
+                    key_expiry_time,
+                    key,
+                    collection: Arc::unwrap_or_clone(collection),
+                },
+                &mut dst,
+            )?;
+        }
+    }
+
+    codec.encode(
+        Rdb::OpCode {
+            opcode: RdbOpCode::Eof(),
+        },
+        &mut dst,
+    )?;
+
+    Ok(dst)
+}
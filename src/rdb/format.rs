@@ -4,9 +4,18 @@
 
 // use clap::builder::Str;
 
-use crate::protocol::SetCommandExpireOption;
+use crate::{collections::Collection, protocol::SetCommandExpireOption};
 
-#[allow(unused)]
+/// The value type streams are written with. redis has its own streams as 0x0F, 0x13 and 0x15 and
+/// numbers its types up from 0 and its opcodes down from 0xFF, so this is well clear of both: redis
+/// refuses the file rather than reading our layout as its listpacks.
+pub const STREAM_VALUE_TYPE: u8 = 0x80;
+
+/// The value types the parsers know, after an optional expiry. Anything else is refused as
+/// RedisError::UnknownRdbType.
+pub const VALUE_TYPES: [u8; 6] = [0x00, 0x01, 0x02, 0x04, 0x05, STREAM_VALUE_TYPE];
+
+#[allow(unused, clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Rdb {
     RdbHeader {
@@ -28,16 +37,29 @@ pub enum Rdb {
         key_expiry_time: Option<SetCommandExpireOption>,
         value_type: ValueType,
         key: String,
-        value: String,
+        value: Vec<u8>,
+    },
+    // A key holding something other than a string, laid out the same way. A hash is value type
+    // 0x04: the number of fields, then every field followed by its value. A list is 0x01: the
+    // number of elements, then each of them from the head. A set is 0x02, laid out like a list.
+    // A sorted set is 0x05: the number of members, then each member followed by its score as an
+    // 8 byte little endian double. A stream is STREAM_VALUE_TYPE, not laid out as the listpacks
+    // redis writes: the number of entries, then for each of them its ID as two 8 byte little
+    // endian numbers, milliseconds first, the number of fields and every field followed by its
+    // value.
+    KeyCollectionPair {
+        key_expiry_time: Option<SetCommandExpireOption>,
+        key: String,
+        collection: Collection,
     },
     //    End,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum ValueType {
     LengthEncoding { length: u32, special: bool },
     StringEncoding,
-    ListEncoding,
     // SetEncoding,
 }
 
@@ -64,7 +86,9 @@ impl ValueType {
 #[derive(Debug)]
 pub enum RdbOpCode {
     Eof(), //checksum
-    Selectdb,
+    Selectdb {
+        db_number: u32,
+    },
     // Expiretime(u32),
     // ExpiretimeMs(u64),
     ResizeDb {
@@ -0,0 +1,289 @@
+// Loading an RDB into the store, from a file, from memory or straight off the replication link.
+
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use anyhow::{anyhow, ensure, Context as _};
+use bytes::BytesMut;
+use futures::StreamExt;
+use tokio::io::{self, AsyncRead, AsyncReadExt, ReadBuf};
+use tokio_util::{codec::FramedRead, io::poll_read_buf};
+use tracing::{debug, error};
+
+use crate::{
+    handlers::set_command::SetCommandActorHandle, protocol::SetCommandParameter,
+    resp::value::RespValue,
+};
+
+use super::{
+    codec::RdbCodec,
+    format::{Rdb, RdbOpCode},
+};
+
+/// Decodes the RDB as it is read and stores every key into its database, up to the EOF opcode.
+/// Returns how many keys were loaded.
+///
+/// Only what the decoder hasn't got to yet is buffered, so the whole file is never in memory.
+pub async fn load_rdb<R: AsyncRead + Unpin>(
+    reader: R,
+    set_command_actor_handle: &SetCommandActorHandle,
+) -> anyhow::Result<usize> {
+    let mut entries = FramedRead::new(reader, RdbCodec::new());
+
+    // keys belong to database 0 until a SELECTDB says otherwise
+    let mut db = 0;
+    let mut loaded = 0;
+
+    while let Some(entry) = entries.next().await {
+        debug!("RDB decoder returned: {:?}", entry);
+
+        match entry.map_err(|e| anyhow!("Failed to decode RDB: {}", e))? {
+            Rdb::KeyValuePair {
+                key_expiry_time,
+                value_type: _,
+                key,
+                value,
+            } => {
+                if db >= set_command_actor_handle.databases() {
+                    error!("Skipping {}, db {} is out of range.", key, db);
+                    continue;
+                }
+
+                let set_params = SetCommandParameter {
+                    key,
+                    value,
+                    option: None,
+                    get: None,
+                    expire: key_expiry_time,
+                };
+
+                set_command_actor_handle.set_value(db, set_params).await?;
+                loaded += 1;
+            }
+            Rdb::KeyCollectionPair {
+                key_expiry_time,
+                key,
+                collection,
+            } => {
+                if db >= set_command_actor_handle.databases() {
+                    error!("Skipping {}, db {} is out of range.", key, db);
+                    continue;
+                }
+
+                set_command_actor_handle
+                    .set_collection(db, key, collection, key_expiry_time)
+                    .await?;
+                loaded += 1;
+            }
+            Rdb::OpCode {
+                opcode: RdbOpCode::Selectdb { db_number },
+            } => {
+                db = db_number as usize;
+            }
+            Rdb::OpCode {
+                opcode: RdbOpCode::Eof(),
+            } => break,
+            _ => {
+                debug!("Ignoring other things.")
+            }
+        }
+    }
+
+    Ok(loaded)
+}
+
+/// Diskless load of the RDB a master sends after +FULLRESYNC: $<length>\r\n and then the file,
+/// without a trailing \r\n. To a replica that announced REPLCONF capa eof it may instead send
+/// $EOF:<mark>\r\n, the file and the same 40 byte mark again.
+///
+/// `buffered` is what was read off the link past the last frame, which is where the transfer
+/// starts. The rest is read from `link` as the decoder needs it, and anything read past the end of
+/// the transfer is left in `buffered`, so whatever the master sends after the file is still there
+/// to be read as commands.
+pub async fn load_rdb_transfer<R: AsyncRead + Unpin>(
+    buffered: &mut BytesMut,
+    link: &mut R,
+    set_command_actor_handle: &SetCommandActorHandle,
+) -> anyhow::Result<usize> {
+    let header = read_transfer_header(buffered, link).await?;
+
+    if let TransferHeader::Mark(mark) = header {
+        debug!("Loading an RDB from the master up to the mark {}.", mark);
+
+        let mut transfer = UntilMark {
+            buffered,
+            link,
+            mark: mark.as_bytes().to_vec(),
+            done: false,
+        };
+
+        let loaded = load_rdb(&mut transfer, set_command_actor_handle).await?;
+        io::copy(&mut transfer, &mut io::sink()).await?;
+
+        return Ok(loaded);
+    }
+
+    let TransferHeader::Length(length) = header else {
+        unreachable!("a transfer is delimited by a mark or a length");
+    };
+
+    debug!("Loading a {} byte RDB from the master.", length);
+
+    let already_read = buffered
+        .split_to(buffered.len().min(length as usize))
+        .freeze();
+    let mut transfer = (&already_read[..]).chain(link.take(length - already_read.len() as u64));
+
+    let loaded = load_rdb(&mut transfer, set_command_actor_handle).await?;
+
+    // anything between the EOF opcode and the end of the transfer is not for the command stream
+    io::copy(&mut transfer, &mut io::sink()).await?;
+
+    Ok(loaded)
+}
+
+/// Reads the RDB a master sends after +FULLRESYNC off the link as it is, without loading it, into
+/// the `RespValue::Rdb` or `RespValue::RdbEof` that would encode it again. As with
+/// `load_rdb_transfer`, the transfer starts in `buffered` and whatever follows it is left there.
+pub async fn read_rdb_transfer<R: AsyncRead + Unpin>(
+    buffered: &mut BytesMut,
+    link: &mut R,
+) -> anyhow::Result<RespValue> {
+    let mut rdb = Vec::new();
+
+    match read_transfer_header(buffered, link).await? {
+        TransferHeader::Mark(mark) => {
+            let mut transfer = UntilMark {
+                buffered,
+                link,
+                mark: mark.as_bytes().to_vec(),
+                done: false,
+            };
+            transfer.read_to_end(&mut rdb).await?;
+
+            Ok(RespValue::RdbEof(mark, rdb.into()))
+        }
+        TransferHeader::Length(length) => {
+            let already_read = buffered.split_to(buffered.len().min(length as usize));
+            rdb.extend_from_slice(&already_read);
+            link.take(length - already_read.len() as u64)
+                .read_to_end(&mut rdb)
+                .await?;
+
+            ensure!(
+                rdb.len() as u64 == length,
+                "The master closed the link before the end of the RDB."
+            );
+
+            Ok(RespValue::Rdb(rdb.into()))
+        }
+    }
+}
+
+// How the end of a transfer is found, from its header.
+enum TransferHeader {
+    Length(u64),
+    Mark(String),
+}
+
+// Reads $<length>\r\n or $EOF:<mark>\r\n off the front of a transfer.
+async fn read_transfer_header<R: AsyncRead + Unpin>(
+    buffered: &mut BytesMut,
+    link: &mut R,
+) -> anyhow::Result<TransferHeader> {
+    // the header is short, but may still come in pieces
+    let header_end = loop {
+        if let Some(end) = buffered.windows(2).position(|window| window == b"\r\n") {
+            break end;
+        }
+
+        if link.read_buf(buffered).await? == 0 {
+            return Err(anyhow!(
+                "The master closed the link before sending the RDB."
+            ));
+        }
+    };
+
+    let header = buffered.split_to(header_end + 2);
+    let header = header
+        .strip_prefix(b"$")
+        .and_then(|header| std::str::from_utf8(&header[..header.len() - 2]).ok())
+        .with_context(|| format!("Expected an RDB transfer, got {:?}", header))?;
+
+    if let Some(mark) = header.strip_prefix("EOF:") {
+        ensure!(
+            mark.len() == EOF_MARK_LEN,
+            "Expected a {} byte EOF mark, got {:?}",
+            EOF_MARK_LEN,
+            mark
+        );
+
+        return Ok(TransferHeader::Mark(mark.to_string()));
+    }
+
+    let length = header
+        .parse()
+        .with_context(|| format!("Expected an RDB transfer, got ${}", header))?;
+
+    Ok(TransferHeader::Length(length))
+}
+
+// Same length as redis' RDB_EOF_MARK_SIZE.
+const EOF_MARK_LEN: usize = 40;
+
+// The file of an EOF delimited transfer, read through `buffered`. Up to the last bytes that could
+// still turn out to be the start of the mark, what is read is handed on. Once the mark is found it
+// is dropped and the file ends there, what came after it stays in `buffered`.
+struct UntilMark<'a, R> {
+    buffered: &'a mut BytesMut,
+    link: &'a mut R,
+    mark: Vec<u8>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for UntilMark<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+
+            let found = this
+                .buffered
+                .windows(this.mark.len())
+                .position(|window| window == this.mark);
+
+            let ready = match found {
+                Some(0) => {
+                    let _mark = this.buffered.split_to(this.mark.len());
+                    this.done = true;
+                    continue;
+                }
+                Some(at) => at,
+                None => this.buffered.len().saturating_sub(this.mark.len() - 1),
+            };
+
+            if ready > 0 {
+                let ready = ready.min(buf.remaining());
+                buf.put_slice(&this.buffered.split_to(ready));
+
+                return Poll::Ready(Ok(()));
+            }
+
+            if ready!(poll_read_buf(Pin::new(&mut *this.link), cx, this.buffered))? == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The master closed the link before the end of the RDB.",
+                )));
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub(crate) mod codec;
 pub(crate) mod format;
+pub mod load;
 pub(crate) mod parsers;
@@ -1,19 +1,24 @@
 use nom::{
     branch::alt,
     bytes::{complete::tag, streaming::take},
-    combinator::value,
-    number::streaming::{le_u16, le_u32, le_u64, le_u8},
-    sequence::tuple,
+    combinator::{map_res, opt, value},
+    multi::count,
+    number::streaming::{be_u32, le_f64, le_u16, le_u32, le_u64, le_u8},
+    sequence::{pair, tuple},
     IResult,
 };
 use tracing::{debug, error};
 
-use crate::protocol::SetCommandExpireOption;
+use crate::{
+    collections::{Collection, SortedSet, Stream, StreamFields, StreamId},
+    protocol::SetCommandExpireOption,
+};
 
-use super::format::{Rdb, RdbOpCode, ValueType};
+use super::format::{Rdb, RdbOpCode, ValueType, STREAM_VALUE_TYPE};
 
 fn parse_rdb_header(input: &[u8]) -> IResult<&[u8], Rdb> {
-    let (input, _magic) = tag("REDIS")(input)?;
+    // streaming, so the start of a file read in small pieces is waited for rather than rejected
+    let (input, _magic) = nom::bytes::streaming::tag("REDIS")(input)?;
     let (input, version) = take(4usize)(input)?;
     let version = String::from_utf8_lossy(version).to_string();
 
@@ -45,18 +50,18 @@ fn parse_eof(input: &[u8]) -> IResult<&[u8], Rdb> {
 
 // A Redis instance can have multiple databases.
 // A single byte 0xFE flags the start of the database selector.
-// After this byte, a variable length field indicates the database number.
+// After this byte, a length encoded integer is the database number.
 fn parse_selectdb(input: &[u8]) -> IResult<&[u8], Rdb> {
     let (input, _dbselector) = tag([0xFE])(input)?;
     let (input, value_type) = (parse_string_length)(input)?;
 
-    let (input, _db_number) = (take(value_type.get_length()))(input)?;
+    let db_number = value_type.get_length();
 
-    debug!("SELECTDB OpCode detected.");
+    debug!("SELECTDB OpCode detected, db {}.", db_number);
     Ok((
         input,
         Rdb::OpCode {
-            opcode: RdbOpCode::Selectdb,
+            opcode: RdbOpCode::Selectdb { db_number },
         },
     ))
 }
@@ -92,10 +97,9 @@ fn parse_string_length(input: &[u8]) -> IResult<&[u8], ValueType> {
             (input, value_type)
         }
         0b10 => {
-            // 10: Discard the remaining 6 bits. The next 4 bytes from the stream represent the length
-            let (input, length) = le_u32(input)?;
-            let length = length as u32;
-            let value_type = ValueType::LengthEncoding {
+            // 10: Discard the remaining 6 bits. The next 4 bytes from the stream represent the length, big endian
+            let (input, length) = be_u32(input)?;
+                        let value_type = ValueType::LengthEncoding {
                 length,
                 special: false,
             };
@@ -151,15 +155,14 @@ fn parse_rdb_aux(input: &[u8]) -> IResult<&[u8], Rdb> {
     ))
 }
 
+// Only strings, the other value types are laid out differently and go to parse_rdb_collection.
 fn parse_value_type(input: &[u8]) -> IResult<&[u8], ValueType> {
-    alt((
-        // value: The value combinator is used to map the result of a parser to a specific value.
-        value(ValueType::StringEncoding, tag([0x0])),
-        value(ValueType::ListEncoding, tag([0x1])),
-    ))(input)
+    // value: The value combinator is used to map the result of a parser to a specific value.
+    value(ValueType::StringEncoding, tag([0x0]))(input)
 }
 
-fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
+// A string value as it was stored, any bytes at all.
+fn parse_bytes(input: &[u8]) -> IResult<&[u8], Vec<u8>> {
     let (input, string_type) = (parse_string_length)(input)?;
     // let (input, parsed_string) = take(string_length)(input)?;
 
@@ -174,16 +177,9 @@ fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
         debug!(
             "Parsed string type: {:?} string: {}",
             string_type,
-            std::str::from_utf8(parsed_string)
-                .expect("Key [u8] to str conversion failed")
-                .to_string(),
+            String::from_utf8_lossy(parsed_string),
         );
-        Ok((
-            input,
-            std::str::from_utf8(parsed_string)
-                .expect("Key [u8] to str conversion failed")
-                .to_string(),
-        ))
+        Ok((input, parsed_string.to_vec()))
     } else {
         // special format, most likely integers as strings
         // https://rdb.fnordig.de/file_format.html#string-encoding
@@ -197,7 +193,7 @@ fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
                     parsed_string,
                     format!("{}", parsed_string),
                 );
-                Ok((input, format!("{}", parsed_string)))
+                Ok((input, parsed_string.to_string().into_bytes()))
             }
             1 => {
                 let (input, parsed_string) = (le_u16)(input)?;
@@ -207,7 +203,7 @@ fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
                     parsed_string,
                     format!("{}", parsed_string),
                 );
-                Ok((input, format!("{}", parsed_string)))
+                Ok((input, parsed_string.to_string().into_bytes()))
             }
             2 => {
                 let (input, parsed_string) = (le_u32)(input)?;
@@ -217,7 +213,7 @@ fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
                     parsed_string,
                     format!("{}", parsed_string),
                 );
-                Ok((input, format!("{}", parsed_string)))
+                Ok((input, parsed_string.to_string().into_bytes()))
             }
             _ => Err(nom::Err::Failure(nom::error::Error::new(
                 input,
@@ -227,13 +223,18 @@ fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
     }
 }
 
+// Keys, and everything in a collection, are UTF-8.
+fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
+    map_res(parse_bytes, String::from_utf8)(input)
+}
+
 fn parse_rdb_key_value_without_expiry(input: &[u8]) -> IResult<&[u8], Rdb> {
     let (input, (value_type, key, value)) =
-        tuple((parse_value_type, parse_string, parse_string))(input)?;
+        tuple((parse_value_type, parse_string, parse_bytes))(input)?;
 
     debug!(
         "Parsed kv pair type: {:?} key: {} value: {}",
-        value_type, key, value
+        value_type, key, String::from_utf8_lossy(&value)
     );
 
     Ok((
@@ -281,7 +282,7 @@ fn parse_rdb_value_with_expiry(input: &[u8]) -> IResult<&[u8], Rdb> {
         alt((parse_expire_option_px, parse_expire_option_ex)),
         parse_value_type,
         parse_string,
-        parse_string,
+        parse_bytes,
     ))(input)?;
 
     let rdb_value_with_expiry = Rdb::KeyValuePair {
@@ -295,6 +296,101 @@ fn parse_rdb_value_with_expiry(input: &[u8]) -> IResult<&[u8], Rdb> {
     Ok((input, rdb_value_with_expiry))
 }
 
+// A hash, a list, a set, a sorted set or a stream, with or without a deadline in front of it.
+fn parse_rdb_collection(input: &[u8]) -> IResult<&[u8], Rdb> {
+    let (input, key_expiry_time) = opt(alt((parse_expire_option_px, parse_expire_option_ex)))(input)?;
+    let (input, (key, collection)) = alt((parse_hash, parse_list, parse_set, parse_sorted_set, parse_stream))(input)?;
+
+    Ok((
+        input,
+        Rdb::KeyCollectionPair {
+            key_expiry_time,
+            key,
+            collection,
+        },
+    ))
+}
+
+// Value type 0x04, https://rdb.fnordig.de/file_format.html#hash-encoding
+fn parse_hash(input: &[u8]) -> IResult<&[u8], (String, Collection)> {
+    let (input, _hash_type) = tag([0x04])(input)?;
+    let (input, key) = parse_string(input)?;
+    let (input, fields) = parse_string_length(input)?;
+    let (input, pairs) = count(pair(parse_string, parse_string), fields.get_length() as usize)(input)?;
+
+    debug!("Parsed hash {} with {} fields", key, pairs.len());
+
+    Ok((input, (key, Collection::Hash(pairs.into_iter().collect()))))
+}
+
+// Value type 0x01, https://rdb.fnordig.de/file_format.html#list-encoding
+fn parse_list(input: &[u8]) -> IResult<&[u8], (String, Collection)> {
+    let (input, _list_type) = tag([0x01])(input)?;
+    let (input, key) = parse_string(input)?;
+    let (input, elements) = parse_string_length(input)?;
+    let (input, list) = count(parse_string, elements.get_length() as usize)(input)?;
+
+    debug!("Parsed list {} with {} elements", key, list.len());
+
+    Ok((input, (key, Collection::List(list.into()))))
+}
+
+// Value type 0x02, https://rdb.fnordig.de/file_format.html#set-encoding
+fn parse_set(input: &[u8]) -> IResult<&[u8], (String, Collection)> {
+    let (input, _set_type) = tag([0x02])(input)?;
+    let (input, key) = parse_string(input)?;
+    let (input, members) = parse_string_length(input)?;
+    let (input, set) = count(parse_string, members.get_length() as usize)(input)?;
+
+    debug!("Parsed set {} with {} members", key, set.len());
+
+    Ok((input, (key, Collection::Set(set.into_iter().collect()))))
+}
+
+// Value type 0x05, ZSET_2: each member followed by its score as a binary double. The older 0x03
+// with scores as strings isn't written since redis 4.0.
+fn parse_sorted_set(input: &[u8]) -> IResult<&[u8], (String, Collection)> {
+    let (input, _sorted_set_type) = tag([0x05])(input)?;
+    let (input, key) = parse_string(input)?;
+    let (input, members) = parse_string_length(input)?;
+    let (input, scored) = count(pair(parse_string, le_f64), members.get_length() as usize)(input)?;
+
+    debug!("Parsed sorted set {} with {} members", key, scored.len());
+
+    let mut sorted_set = SortedSet::default();
+    for (member, score) in scored {
+        sorted_set.insert(member, score);
+    }
+
+    Ok((input, (key, Collection::SortedSet(sorted_set))))
+}
+
+// Value type STREAM_VALUE_TYPE in a layout of our own, see Rdb::KeyCollectionPair.
+fn parse_stream(input: &[u8]) -> IResult<&[u8], (String, Collection)> {
+    let (input, _stream_type) = tag([STREAM_VALUE_TYPE])(input)?;
+    let (input, key) = parse_string(input)?;
+    let (input, entries) = parse_string_length(input)?;
+    let (input, entries) = count(parse_stream_entry, entries.get_length() as usize)(input)?;
+
+    debug!("Parsed stream {} with {} entries", key, entries.len());
+
+    let mut stream = Stream::default();
+    for (id, fields) in entries {
+        stream.insert(id, fields);
+    }
+
+    Ok((input, (key, Collection::Stream(stream))))
+}
+
+// An entry's ID, then its fields each followed by its value.
+fn parse_stream_entry(input: &[u8]) -> IResult<&[u8], (StreamId, StreamFields)> {
+    let (input, (ms, seq)) = pair(le_u64, le_u64)(input)?;
+    let (input, fields) = parse_string_length(input)?;
+    let (input, fields) = count(pair(parse_string, parse_string), fields.get_length() as usize)(input)?;
+
+    Ok((input, (StreamId { ms, seq }, fields)))
+}
+
 fn parse_resize_db(input: &[u8]) -> IResult<&[u8], Rdb> {
     // 0xFB means resize db
     // It encodes two values to speed up RDB loading by avoiding additional resizes and rehashing.
@@ -333,6 +429,7 @@ pub fn parse_rdb_file(input: &[u8]) -> IResult<&[u8], Rdb> {
         parse_rdb_aux,
         parse_rdb_key_value_without_expiry,
         parse_rdb_value_with_expiry,
+        parse_rdb_collection,
         parse_resize_db,
     ))(input)
 }
@@ -0,0 +1,108 @@
+// Where the expensive reads run: LRANGE over a whole list, SMEMBERS, SUNION and SINTER over big
+// sets. The processor takes the collections they read out of the store under the read lock,
+// which only clones an Arc, and hands them to a blocking thread here. A write to one of them in
+// the meantime copies it for the store (see Database::update_collection), so the read goes on
+// over the values as they were and neither the store actor nor the processor waits for it.
+//
+// A read may take read-time-limit at most. It looks at the clock every so often as it goes, and
+// gives up with "operation timed out" once it is past its deadline.
+
+use std::{
+    cell::Cell,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Semaphore;
+
+use crate::errors::RedisError;
+
+/// Milliseconds an expensive read may run for unless --read-time-limit says otherwise.
+pub const DEFAULT_READ_TIME_LIMIT: u64 = 5000;
+
+// Elements a read goes through between two looks at the clock.
+const TICKS_PER_CHECK: u32 = 1024;
+
+/// The blocking threads expensive reads run on, at most one per CPU core at a time.
+#[derive(Clone, Debug)]
+pub struct ReadPool {
+    permits: Arc<Semaphore>,
+}
+
+impl Default for ReadPool {
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+
+        Self::new(threads)
+    }
+}
+
+impl ReadPool {
+    /// A pool running up to threads reads at once, the others wait for their turn.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(threads.max(1))),
+        }
+    }
+
+    /// Runs read on one of the pool's threads. With a time_limit, read is given a deadline that
+    /// far from when it starts, past which Deadline::tick() fails with OperationTimedOut.
+    pub async fn run<T, F>(&self, time_limit: Option<Duration>, read: F) -> Result<T, RedisError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Deadline) -> Result<T, RedisError> + Send + 'static,
+    {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| RedisError::ActorGone("the read pool"))?;
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+
+            read(&Deadline::after(time_limit))
+        })
+        .await
+        .map_err(|e| RedisError::Internal(format!("read failed: {}", e)))?
+    }
+}
+
+/// When a read running on the pool has to be done by.
+#[derive(Debug)]
+pub struct Deadline {
+    // None for no limit
+    at: Option<Instant>,
+    // elements gone through since the clock was last looked at
+    ticks: Cell<u32>,
+}
+
+impl Deadline {
+    fn after(time_limit: Option<Duration>) -> Self {
+        Self {
+            at: time_limit.map(|time_limit| Instant::now() + time_limit),
+            ticks: Cell::new(0),
+        }
+    }
+
+    /// Counts one more element gone through, OperationTimedOut if the deadline has passed.
+    pub fn tick(&self) -> Result<(), RedisError> {
+        let Some(at) = self.at else {
+            return Ok(());
+        };
+
+        let ticks = self.ticks.get() + 1;
+        if ticks < TICKS_PER_CHECK {
+            self.ticks.set(ticks);
+            return Ok(());
+        }
+        self.ticks.set(0);
+
+        if Instant::now() > at {
+            Err(RedisError::OperationTimedOut)
+        } else {
+            Ok(())
+        }
+    }
+}
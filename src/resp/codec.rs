@@ -1,21 +1,52 @@
 use tokio_util::codec::{Decoder, Encoder};
 // use tracing::info;
 
-use bytes::{Buf, BytesMut};
+use std::fmt::Write;
+
+use bytes::BytesMut;
 use nom::{Err, Needed};
 use tracing::error;
 
 use crate::errors::RedisError;
 
-use super::{parsers::parse_resp, value::RespValue};
+use super::{
+    parsers::{parse_frame, Payloads},
+    shared,
+    value::{format_double, RespValue},
+};
+
+/// Which protocol version the peer speaks. Connections start out as RESP2.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RespProtocol {
+    Resp2,
+    Resp3,
+}
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct RespCodec {}
+pub struct RespCodec {
+    // RESP3-only frames are downgraded on the way out to RESP2 peers.
+    protocol: RespProtocol,
+}
 
 impl RespCodec {
     /// Creates a new [`RespCodec`].
     pub fn new() -> Self {
-        Self {}
+        Self {
+            protocol: RespProtocol::Resp2,
+        }
+    }
+
+    /// Creates a [`RespCodec`] encoding for the given protocol version.
+    pub fn with_protocol(protocol: RespProtocol) -> Self {
+        Self { protocol }
+    }
+
+    pub fn protocol(&self) -> RespProtocol {
+        self.protocol
+    }
+
+    pub fn set_protocol(&mut self, protocol: RespProtocol) {
+        self.protocol = protocol;
     }
 }
 
@@ -38,31 +69,61 @@ impl Decoder for RespCodec {
         // convert decimal ascii to string
         tracing::debug!("Decoding: {:?}", src);
 
-        match parse_resp(src) {
-            Ok((remaining_bytes, parsed_message)) => {
-                // advance the cursor by the difference between what we read
-                // and what we parsed
-                src.advance(src.len() - remaining_bytes.len());
+        // First find where the frame ends, leaving the payloads where they are.
+        let frame_length = match parse_frame(src, Payloads(None)) {
+            Ok((remaining_bytes, _)) => src.len() - remaining_bytes.len(),
+            Err(Err::Incomplete(Needed::Size(_))) => return Ok(None),
 
-                // return the parsed message
-                Ok(Some(parsed_message))
+            Err(e) => {
+                error!("Error {} parsing RESP message: {:?}", e, src);
+                return Err(RedisError::ParseFailure);
             }
-            Err(Err::Incomplete(Needed::Size(_))) => Ok(None),
+        };
+
+        // Then take the frame off the buffer without copying it and parse it again,
+        // this time with the bulk payloads sliced out of the frame itself.
+        let frame = src.split_to(frame_length).freeze();
 
+        match parse_frame(&frame, Payloads(Some(&frame))) {
+            Ok((_, parsed_message)) => Ok(Some(parsed_message)),
             Err(e) => {
-                error!("Error {} parsing RESP message: {:?}", e, src);
+                error!("Error {} parsing RESP message: {:?}", e, frame);
                 Err(RedisError::ParseFailure)
             }
         }
     }
 } // end of impl Decoder for RespCodec
 
+// Lengths and integers are formatted straight into the output buffer, without a String in between.
+fn put_number(dst: &mut BytesMut, n: impl std::fmt::Display) {
+    // writing to a BytesMut grows it as needed and never fails
+    let _ = write!(dst, "{}", n);
+}
+
 // now let's implement the Encoder
 impl Encoder<RespValue> for RespCodec {
     type Error = RedisError;
 
     fn encode(&mut self, item: RespValue, dst: &mut BytesMut) -> Result<(), Self::Error> {
         tracing::debug!("Encoding: {:?}", item);
+
+        // RESP3 has one null for everything, RESP2 the null bulk string and the null array.
+        if self.protocol == RespProtocol::Resp3
+            && matches!(
+                item,
+                RespValue::Null | RespValue::NullArray | RespValue::BulkString(None)
+            )
+        {
+            dst.extend_from_slice(b"_\r\n");
+            return Ok(());
+        }
+
+        // +OK, +PONG, $-1, small integers and the like are copied as they are.
+        if let Some(encoded) = shared::encoded(&item) {
+            dst.extend_from_slice(encoded);
+            return Ok(());
+        }
+
         match item {
             RespValue::SimpleString(s) => {
                 dst.extend_from_slice(b"+");
@@ -76,13 +137,20 @@ impl Encoder<RespValue> for RespCodec {
             }
             RespValue::Integer(i) => {
                 dst.extend_from_slice(b":");
-                dst.extend_from_slice(i.to_string().as_bytes());
+                put_number(dst, i);
                 dst.extend_from_slice(b"\r\n");
             }
             RespValue::BulkString(Some(data)) => {
-                dst.extend_from_slice(b"$");
-                dst.extend_from_slice(data.len().to_string().as_bytes());
-                dst.extend_from_slice(b"\r\n");
+                // one allocation for the header, the payload and the trailer
+                dst.reserve(data.len() + 24);
+                match shared::bulk_header(data.len()) {
+                    Some(header) => dst.extend_from_slice(header),
+                    None => {
+                        dst.extend_from_slice(b"$");
+                        put_number(dst, data.len());
+                        dst.extend_from_slice(b"\r\n");
+                    }
+                }
                 dst.extend_from_slice(&data);
                 dst.extend_from_slice(b"\r\n");
             }
@@ -90,9 +158,14 @@ impl Encoder<RespValue> for RespCodec {
                 dst.extend_from_slice(b"$-1\r\n");
             }
             RespValue::Array(arr) => {
-                dst.extend_from_slice(b"*");
-                dst.extend_from_slice(arr.len().to_string().as_bytes());
-                dst.extend_from_slice(b"\r\n");
+                match shared::array_header(arr.len()) {
+                    Some(header) => dst.extend_from_slice(header),
+                    None => {
+                        dst.extend_from_slice(b"*");
+                        put_number(dst, arr.len());
+                        dst.extend_from_slice(b"\r\n");
+                    }
+                }
                 for item in arr {
                     self.encode(item, dst)?;
                 }
@@ -110,9 +183,89 @@ impl Encoder<RespValue> for RespCodec {
             // (This is similar to how Bulk Strings are encoded, but without the trailing \r\n)
             RespValue::Rdb(rdb) => {
                 dst.extend_from_slice(b"$");
-                dst.extend_from_slice(rdb.len().to_string().as_bytes());
+                put_number(dst, rdb.len());
+                dst.extend_from_slice(b"\r\n");
+                dst.extend_from_slice(&rdb);
+            }
+            RespValue::RdbEof(mark, rdb) => {
+                dst.extend_from_slice(b"$EOF:");
+                dst.extend_from_slice(mark.as_bytes());
                 dst.extend_from_slice(b"\r\n");
                 dst.extend_from_slice(&rdb);
+                dst.extend_from_slice(mark.as_bytes());
+            }
+
+            // RESP3 types. RESP2 peers get the equivalent RESP2 reply instead.
+            item @ (RespValue::Double(_)
+            | RespValue::Boolean(_)
+            | RespValue::BigNumber(_)
+            | RespValue::VerbatimString(..)
+            | RespValue::Map(_)
+            | RespValue::Set(_)
+            | RespValue::Push(_)
+            | RespValue::Attribute(..))
+                if self.protocol == RespProtocol::Resp2 =>
+            {
+                self.encode(item.into_resp2(), dst)?;
+            }
+            RespValue::Double(d) => {
+                dst.extend_from_slice(b",");
+                dst.extend_from_slice(format_double(d).as_bytes());
+                dst.extend_from_slice(b"\r\n");
+            }
+            RespValue::Boolean(b) => {
+                dst.extend_from_slice(if b { b"#t\r\n" } else { b"#f\r\n" });
+            }
+            RespValue::BigNumber(n) => {
+                dst.extend_from_slice(b"(");
+                dst.extend_from_slice(n.as_bytes());
+                dst.extend_from_slice(b"\r\n");
+            }
+            RespValue::VerbatimString(format, data) => {
+                // =<length>\r\n<format>:<data>\r\n, where length includes the format and the colon.
+                dst.extend_from_slice(b"=");
+                put_number(dst, format.len() + 1 + data.len());
+                dst.extend_from_slice(b"\r\n");
+                dst.extend_from_slice(format.as_bytes());
+                dst.extend_from_slice(b":");
+                dst.extend_from_slice(&data);
+                dst.extend_from_slice(b"\r\n");
+            }
+            RespValue::Map(pairs) => {
+                dst.extend_from_slice(b"%");
+                put_number(dst, pairs.len());
+                dst.extend_from_slice(b"\r\n");
+                for (key, value) in pairs {
+                    self.encode(key, dst)?;
+                    self.encode(value, dst)?;
+                }
+            }
+            RespValue::Set(items) => {
+                dst.extend_from_slice(b"~");
+                put_number(dst, items.len());
+                dst.extend_from_slice(b"\r\n");
+                for item in items {
+                    self.encode(item, dst)?;
+                }
+            }
+            RespValue::Push(items) => {
+                dst.extend_from_slice(b">");
+                put_number(dst, items.len());
+                dst.extend_from_slice(b"\r\n");
+                for item in items {
+                    self.encode(item, dst)?;
+                }
+            }
+            RespValue::Attribute(attributes, reply) => {
+                // |<number of pairs>\r\n<key><value>...<reply>
+                dst.extend_from_slice(b"|");
+                put_number(dst, attributes.len());
+                dst.extend_from_slice(b"\r\n");
+                for (key, value) in attributes {
+                    self.encode(key, dst)?;
+                    self.encode(value, dst)?;
+                }
+                self.encode(*reply, dst)?;
             }
         }
         Ok(())
@@ -1,3 +1,4 @@
-pub(crate) mod codec;
+pub mod codec;
 pub(crate) mod parsers;
-pub(crate) mod value;
+pub mod shared;
+pub mod value;
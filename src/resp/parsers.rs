@@ -1,19 +1,33 @@
 use super::value::RespValue;
 
+use bytes::Bytes;
 use nom::{
     branch::alt,
     bytes::{
         complete::{tag, tag_no_case},
         streaming::{take, take_while},
     },
-    character::{complete::crlf, streaming::digit1},
-    combinator::{map, map_res},
+    character::streaming::{crlf, digit1},
+    combinator::{map, map_res, value, verify},
     multi::count,
-    sequence::{preceded, terminated},
-    IResult,
+    sequence::{pair, preceded, terminated},
+    Err, IResult, Needed,
 };
 use tracing::debug;
 
+/// Where bulk payloads come from: slices of the frame being decoded, so they share its buffer
+/// instead of being copied out. Without a frame they come out empty, which is enough for a pass
+/// that only needs to know where the frame ends.
+#[derive(Clone, Copy)]
+pub struct Payloads<'b>(pub Option<&'b Bytes>);
+
+impl Payloads<'_> {
+    fn slice(self, data: &[u8]) -> Bytes {
+        self.0
+            .map_or_else(Bytes::new, |frame| frame.slice_ref(data))
+    }
+}
+
 // strings are encoded as a plus (+) character, followed by a string.
 fn parse_simple_string(input: &[u8]) -> IResult<&[u8], RespValue> {
     debug!("Parsing simple string: {:?}", input);
@@ -23,16 +37,17 @@ fn parse_simple_string(input: &[u8]) -> IResult<&[u8], RespValue> {
     )(input)
 }
 
-// integers are encoded as a colon (:) character, followed by a number.
+// integers are encoded as a colon (:) character, followed by an optional sign and a number.
 fn parse_integer(input: &[u8]) -> IResult<&[u8], RespValue> {
     debug!("Parsing integer: {:?}", input);
     map(
         terminated(
             preceded(
                 tag(":"),
-                map_res(take_while(|c: u8| c.is_ascii_digit()), |s| {
-                    String::from_utf8_lossy(s).parse::<i64>()
-                }),
+                map_res(
+                    take_while(|c: u8| c.is_ascii_digit() || c == b'-' || c == b'+'),
+                    |s| String::from_utf8_lossy(s).parse::<i64>(),
+                ),
             ),
             crlf,
         ),
@@ -52,7 +67,7 @@ fn parse_error(input: &[u8]) -> IResult<&[u8], RespValue> {
 // bulk strings are encoded as a dollar sign ($) character,
 // followed by the number of bytes in the string, followed by CRLF,
 // followed by the string itself.
-fn parse_bulk_string(input: &[u8]) -> IResult<&[u8], RespValue> {
+fn parse_bulk_string<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
     debug!("Parsing bulk string: {:?}", input);
     let (input, length) = preceded(
         tag("$"),
@@ -67,11 +82,11 @@ fn parse_bulk_string(input: &[u8]) -> IResult<&[u8], RespValue> {
     } else {
         let (input, data) = take(length as usize)(input)?;
         let (input, _) = crlf(input)?;
-        Ok((input, RespValue::BulkString(Some(data.to_vec()))))
+        Ok((input, RespValue::BulkString(Some(payloads.slice(data)))))
     }
 }
 
-fn parse_array(input: &[u8]) -> IResult<&[u8], RespValue> {
+fn parse_array<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
     let (input, array_size) = preceded(
         tag("*"),
         map_res(digit1, |s: &[u8]| {
@@ -83,16 +98,154 @@ fn parse_array(input: &[u8]) -> IResult<&[u8], RespValue> {
     if array_size < 0 {
         Ok((input, RespValue::NullArray))
     } else {
-        let (input, elements) = count(parse_resp, array_size as usize)(input)?;
+        let (input, elements) = count(|i| parse_resp(i, payloads), array_size as usize)(input)?;
         Ok((input, RespValue::Array(elements)))
     }
 }
 
+// RESP3 doubles are encoded as a comma (,) character, followed by the number.
+// inf, -inf and nan are spelled out.
+fn parse_double(input: &[u8]) -> IResult<&[u8], RespValue> {
+    debug!("Parsing double: {:?}", input);
+    map(
+        terminated(
+            preceded(
+                tag(","),
+                map_res(take_while(|c| c != b'\r'), |s| {
+                    String::from_utf8_lossy(s).parse::<f64>()
+                }),
+            ),
+            crlf,
+        ),
+        RespValue::Double,
+    )(input)
+}
+
+// RESP3 booleans are #t\r\n or #f\r\n
+fn parse_boolean(input: &[u8]) -> IResult<&[u8], RespValue> {
+    alt((
+        value(RespValue::Boolean(true), tag("#t\r\n")),
+        value(RespValue::Boolean(false), tag("#f\r\n")),
+    ))(input)
+}
+
+// RESP3 big numbers are encoded as an open parenthesis, followed by an optional sign and the digits.
+fn parse_big_number(input: &[u8]) -> IResult<&[u8], RespValue> {
+    debug!("Parsing big number: {:?}", input);
+    map(
+        terminated(
+            preceded(
+                tag("("),
+                take_while(|c: u8| c.is_ascii_digit() || c == b'-' || c == b'+'),
+            ),
+            crlf,
+        ),
+        |s: &[u8]| RespValue::BigNumber(String::from_utf8_lossy(s).to_string()),
+    )(input)
+}
+
+// RESP3 verbatim strings look like bulk strings, =<length>\r\n<format>:<data>\r\n,
+// where the first three bytes of the payload are the format.
+fn parse_verbatim_string<'a>(
+    input: &'a [u8],
+    payloads: Payloads<'_>,
+) -> IResult<&'a [u8], RespValue> {
+    debug!("Parsing verbatim string: {:?}", input);
+    let (input, length) = preceded(
+        tag("="),
+        map_res(digit1, |s: &[u8]| {
+            String::from_utf8_lossy(s).parse::<usize>()
+        }),
+    )(input)?;
+    let (input, _) = crlf(input)?;
+
+    let (input, (format, data)) = terminated(
+        pair(
+            terminated(take(3usize), tag(":")),
+            take(length.saturating_sub(4)),
+        ),
+        crlf,
+    )(input)?;
+
+    Ok((
+        input,
+        RespValue::VerbatimString(
+            String::from_utf8_lossy(format).to_string(),
+            payloads.slice(data),
+        ),
+    ))
+}
+
+// RESP3 maps are encoded as %<number of pairs>\r\n followed by alternating keys and values.
+fn parse_map<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
+    let (input, map_size) = preceded(
+        tag("%"),
+        map_res(digit1, |s: &[u8]| {
+            String::from_utf8_lossy(s).parse::<usize>()
+        }),
+    )(input)?;
+    let (input, _) = crlf(input)?;
+
+    let (input, pairs) = count(
+        pair(|i| parse_resp(i, payloads), |i| parse_resp(i, payloads)),
+        map_size,
+    )(input)?;
+    Ok((input, RespValue::Map(pairs)))
+}
+
+// RESP3 sets are encoded like arrays, with a tilde (~) instead of the asterisk.
+fn parse_set<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
+    let (input, set_size) = preceded(
+        tag("~"),
+        map_res(digit1, |s: &[u8]| {
+            String::from_utf8_lossy(s).parse::<usize>()
+        }),
+    )(input)?;
+    let (input, _) = crlf(input)?;
+
+    let (input, elements) = count(|i| parse_resp(i, payloads), set_size)(input)?;
+    Ok((input, RespValue::Set(elements)))
+}
+
+// RESP3 pushes are encoded like arrays, with a greater-than sign (>) instead of the asterisk.
+fn parse_push<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
+    let (input, push_size) = preceded(
+        tag(">"),
+        map_res(digit1, |s: &[u8]| {
+            String::from_utf8_lossy(s).parse::<usize>()
+        }),
+    )(input)?;
+    let (input, _) = crlf(input)?;
+
+    let (input, elements) = count(|i| parse_resp(i, payloads), push_size)(input)?;
+    Ok((input, RespValue::Push(elements)))
+}
+
+// RESP3 attributes are encoded like maps, with a pipe (|) instead of the percent sign.
+// They are always followed by the actual reply, which we parse right away and keep together with them.
+fn parse_attribute<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
+    let (input, attribute_count) = preceded(
+        tag("|"),
+        map_res(digit1, |s: &[u8]| {
+            String::from_utf8_lossy(s).parse::<usize>()
+        }),
+    )(input)?;
+    let (input, _) = crlf(input)?;
+
+    let (input, attributes) = count(
+        pair(|i| parse_resp(i, payloads), |i| parse_resp(i, payloads)),
+        attribute_count,
+    )(input)?;
+    let (input, reply) = parse_resp(input, payloads)?;
+
+    Ok((input, RespValue::Attribute(attributes, Box::new(reply))))
+}
+
 // parse in-bound RDB file in memory representation. This follows FULLRESYNC redis command.
 // The file is sent using the following format:
 // $<length_of_file>\r\n<contents_of_file>
 // (This is similar to how Bulk Strings are encoded, but without the trailing \r\n)
-fn parse_rdb(input: &[u8]) -> IResult<&[u8], RespValue> {
+fn parse_rdb<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
     let (input, length) = preceded(
         tag("$"),
         map_res(take_while(|c: u8| c.is_ascii_digit()), |s| {
@@ -102,19 +255,81 @@ fn parse_rdb(input: &[u8]) -> IResult<&[u8], RespValue> {
     let (input, _) = crlf(input)?;
 
     let (input, data) = take(length as usize)(input)?;
-    Ok((input, RespValue::Rdb(data.to_vec())))
+    Ok((input, RespValue::Rdb(payloads.slice(data))))
+}
+
+// Inline commands are what you type into telnet: PING\r\n or SET  foo   bar\r\n.
+// Arguments are separated by any run of spaces or tabs and the line becomes an array of bulk strings,
+// same as if the client had sent the RESP encoding. Only tried for lines that don't start with a type marker.
+fn parse_inline<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
+    debug!("Parsing inline command: {:?}", input);
+    map(
+        verify(
+            terminated(take_while(|c| c != b'\n'), tag("\n")),
+            |line: &[u8]| {
+                line.first()
+                    .is_none_or(|first| !b"+-:$*,#(=%~>|_".contains(first))
+            },
+        ),
+        |line: &[u8]| {
+            RespValue::Array(
+                line.split(|c| c.is_ascii_whitespace())
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| RespValue::BulkString(Some(payloads.slice(arg))))
+                    .collect(),
+            )
+        },
+    )(input)
 }
 
-pub fn parse_resp(input: &[u8]) -> IResult<&[u8], RespValue> {
+// The RDB payload after FULLRESYNC reads just like a bulk string that is missing its trailing CRLF.
+// Only a frame can be the RDB, so here, and only here, a bulk string waiting on nothing but that CRLF
+// is taken as the RDB. Nested in an array it is a bulk string whose CRLF hasn't arrived yet.
+fn parse_bulk_string_or_rdb<'a>(
+    input: &'a [u8],
+    payloads: Payloads<'_>,
+) -> IResult<&'a [u8], RespValue> {
+    match parse_bulk_string(input, payloads) {
+        Err(Err::Incomplete(_)) => parse_rdb(input, payloads),
+        parsed => parsed,
+    }
+}
+
+/// Entry point for the codec: a RESP value or, failing that, an inline command.
+pub fn parse_frame<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
+    alt((
+        |i| parse_bulk_string_or_rdb(i, payloads),
+        |i| parse_resp(i, payloads),
+        |i| parse_inline(i, payloads),
+    ))(input)
+}
+
+pub fn parse_resp<'a>(input: &'a [u8], payloads: Payloads<'_>) -> IResult<&'a [u8], RespValue> {
     debug!("Parsing resp: {:?}", input);
+
+    // the type markers are all matched with complete tags, which fail on nothing at all
+    // instead of asking for more, e.g. when a read ends right after an array header
+    if input.is_empty() {
+        return Err(Err::Incomplete(Needed::new(1)));
+    }
+
     alt((
         map(tag_no_case("$-1\r\n"), |_| RespValue::Null),
         map(tag_no_case("*-1\r\n"), |_| RespValue::NullArray),
+        map(tag("_\r\n"), |_| RespValue::Null),
         parse_simple_string,
         parse_error,
         parse_integer,
-        parse_bulk_string,
-        parse_array,
-        parse_rdb,
+        |i| parse_bulk_string(i, payloads),
+        |i| parse_array(i, payloads),
+        |i| parse_rdb(i, payloads),
+        parse_double,
+        parse_boolean,
+        parse_big_number,
+        |i| parse_verbatim_string(i, payloads),
+        |i| parse_map(i, payloads),
+        |i| parse_set(i, payloads),
+        |i| parse_push(i, payloads),
+        |i| parse_attribute(i, payloads),
     ))(input)
 }
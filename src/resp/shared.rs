@@ -0,0 +1,53 @@
+// Replies that go out all the time, encoded once instead of on every response.
+// Same idea as the shared objects redis creates in createSharedObjects().
+use std::sync::LazyLock;
+
+use super::value::RespValue;
+
+/// Integer replies 0 through SHARED_INTEGERS - 1 come from the table, same bound as redis OBJ_SHARED_INTEGERS.
+pub const SHARED_INTEGERS: i64 = 10000;
+
+/// $<len>\r\n and *<len>\r\n headers below this length come from the table, redis OBJ_SHARED_BULKHDR_LEN.
+pub const SHARED_HEADERS: usize = 32;
+
+static INTEGERS: LazyLock<Vec<Vec<u8>>> = LazyLock::new(|| {
+    (0..SHARED_INTEGERS)
+        .map(|i| format!(":{}\r\n", i).into_bytes())
+        .collect()
+});
+
+static BULK_HEADERS: LazyLock<Vec<Vec<u8>>> = LazyLock::new(|| headers('$'));
+
+static ARRAY_HEADERS: LazyLock<Vec<Vec<u8>>> = LazyLock::new(|| headers('*'));
+
+fn headers(prefix: char) -> Vec<Vec<u8>> {
+    (0..SHARED_HEADERS)
+        .map(|len| format!("{}{}\r\n", prefix, len).into_bytes())
+        .collect()
+}
+
+/// The complete encoding of a value, if it is one of the shared replies.
+pub fn encoded(value: &RespValue) -> Option<&'static [u8]> {
+    match value {
+        RespValue::SimpleString(s) => match s.as_str() {
+            "OK" => Some(b"+OK\r\n"),
+            "PONG" => Some(b"+PONG\r\n"),
+            "QUEUED" => Some(b"+QUEUED\r\n"),
+            _ => None,
+        },
+        RespValue::Null | RespValue::BulkString(None) => Some(b"$-1\r\n"),
+        RespValue::NullArray => Some(b"*-1\r\n"),
+        RespValue::Integer(i) if (0..SHARED_INTEGERS).contains(i) => Some(&INTEGERS[*i as usize]),
+        _ => None,
+    }
+}
+
+/// $<len>\r\n for short bulk strings.
+pub fn bulk_header(len: usize) -> Option<&'static [u8]> {
+    BULK_HEADERS.get(len).map(Vec::as_slice)
+}
+
+/// *<len>\r\n for short arrays.
+pub fn array_header(len: usize) -> Option<&'static [u8]> {
+    ARRAY_HEADERS.get(len).map(Vec::as_slice)
+}
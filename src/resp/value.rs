@@ -1,4 +1,4 @@
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use std::io::{Error, ErrorKind};
 use tokio_util::codec::Encoder;
 use tracing::debug;
@@ -6,11 +6,12 @@ use tracing::debug;
 use super::codec::RespCodec;
 
 /// Represents a RESP value, see [Redis Protocol specification](http://redis.io/topics/protocol).
-#[derive(Clone, Eq, PartialEq, Debug)]
+/// NOTE: not Eq because of the RESP3 Double.
+#[derive(Clone, PartialEq, Debug)]
 pub enum RespValue {
-    /// Null bulk reply, `$-1\r\n`
+    /// Null bulk reply, `$-1\r\n`, or the RESP3 null `_\r\n` to RESP3 peers.
     Null,
-    /// Null array reply, `*-1\r\n`
+    /// Null array reply, `*-1\r\n`, or the RESP3 null `_\r\n` to RESP3 peers.
     NullArray,
     /// For Simple Strings the first byte of the reply is "+".
     SimpleString(String),
@@ -19,14 +20,41 @@ pub enum RespValue {
     /// For Integers the first byte of the reply is ":".
     Integer(i64),
     /// For Bulk Strings the first byte of the reply is "$".
-    BulkString(Option<Vec<u8>>),
+    /// Decoded payloads share the buffer the frame was read into, see RespCodec::decode().
+    BulkString(Option<Bytes>),
     /// For Bulk <binary> Strings the first byte of the reply is "$".
     // BufBulk(Vec<u8>),
     /// For Arrays the first byte of the reply is "*".
     Array(Vec<RespValue>),
     /// $<length_of_file>\r\n<contents_of_file>
     /// This is similar to how Bulk Strings are encoded, but without the trailing \r\n
-    Rdb(Vec<u8>),
+    Rdb(Bytes),
+    /// $EOF:<mark>\r\n<contents_of_file><mark>
+    /// The diskless transfer for replicas that announced REPLCONF capa eof, (mark, contents)
+    /// where the mark is 40 random characters the file is not expected to contain.
+    RdbEof(String, Bytes),
+
+    // RESP3 types, https://github.com/redis/redis-specification/blob/master/protocol/RESP3.md
+    // RESP2 connections get these downgraded by the codec, see RespValue::into_resp2().
+    /// For Doubles the first byte of the reply is ",".
+    Double(f64),
+    /// For Booleans the first byte of the reply is "#".
+    Boolean(bool),
+    /// For Big Numbers the first byte of the reply is "(". Stored as the decimal digits.
+    BigNumber(String),
+    /// For Verbatim Strings the first byte of the reply is "=".
+    /// (format, data) where format is exactly three bytes, e.g. "txt" or "mkd".
+    VerbatimString(String, Bytes),
+    /// For Maps the first byte of the reply is "%".
+    Map(Vec<(RespValue, RespValue)>),
+    /// For Sets the first byte of the reply is "~".
+    Set(Vec<RespValue>),
+    /// For Pushes the first byte of the frame is ">".
+    /// Out-of-band data (pub/sub messages, invalidations), not a reply to any request.
+    Push(Vec<RespValue>),
+    /// For Attributes the first byte of the frame is "|".
+    /// Auxiliary key/value data sent right before the reply it describes, which is kept alongside.
+    Attribute(Vec<(RespValue, RespValue)>, Box<RespValue>),
 }
 
 impl RespValue {
@@ -35,11 +63,37 @@ impl RespValue {
         RespValue::Array(
             slice
                 .iter()
-                .map(|&s| RespValue::BulkString(Some(s.as_bytes().to_vec())))
+                .map(|&s| RespValue::BulkString(Some(Bytes::copy_from_slice(s.as_bytes()))))
                 .collect(),
         )
     }
 
+    /// Converts RESP3-only types into the shape a RESP2 client expects,
+    /// following what redis does for HELLO 2 connections:
+    /// doubles and big numbers become bulk strings, booleans become 1/0 integers,
+    /// maps are flattened into key, value, key, value arrays, sets and pushes become arrays
+    /// and attributes are dropped.
+    pub fn into_resp2(self) -> Self {
+        match self {
+            RespValue::Double(d) => RespValue::BulkString(Some(format_double(d).into())),
+            RespValue::Boolean(b) => RespValue::Integer(b as i64),
+            RespValue::BigNumber(n) => RespValue::BulkString(Some(n.into())),
+            RespValue::VerbatimString(_format, data) => RespValue::BulkString(Some(data)),
+            RespValue::Map(pairs) => RespValue::Array(
+                pairs
+                    .into_iter()
+                    .flat_map(|(k, v)| [k.into_resp2(), v.into_resp2()])
+                    .collect(),
+            ),
+            RespValue::Set(items) | RespValue::Push(items) | RespValue::Array(items) => {
+                RespValue::Array(items.into_iter().map(RespValue::into_resp2).collect())
+            }
+            // RESP2 has no way to carry attributes, only the reply itself goes out.
+            RespValue::Attribute(_attributes, reply) => reply.into_resp2(),
+            other => other,
+        }
+    }
+
     /// Encodes a RespValue into RESP protocol format.
     pub fn encode(&self) -> Vec<u8> {
         let mut buffer = BytesMut::new();
@@ -62,3 +116,18 @@ impl RespValue {
         }
     }
 }
+
+/// Doubles are sent the way redis prints them: shortest representation, inf/-inf/nan spelled out.
+pub fn format_double(d: f64) -> String {
+    if d.is_nan() {
+        "nan".to_string()
+    } else if d.is_infinite() {
+        if d.is_sign_positive() {
+            "inf".to_string()
+        } else {
+            "-inf".to_string()
+        }
+    } else {
+        d.to_string()
+    }
+}
@@ -0,0 +1,1368 @@
+// The server main.rs runs: the actors wired together behind a TCP listener, the link to the
+// master on a replica, and a task per client connection. Something embedding it can pass an
+// Authenticator of its own, see auth.rs.
+
+use std::{
+    collections::VecDeque,
+    panic::AssertUnwindSafe,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::resp::value::RespValue;
+
+use anyhow::{anyhow, ensure, Result};
+use crate::actors::messages::{BlockedPop, HostId, ReplicationFault};
+use crate::actors::supervisor;
+
+use futures::{FutureExt, SinkExt, StreamExt};
+use crate::resp::codec::RespCodec;
+use crate::utils::{
+    generate_replication_id, handshake, spawn_expiry_cycle, spawn_keyspace_sampler,
+    spawn_replication_lag_monitor, update_master_offset,
+};
+// use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_util::{
+    codec::{FramedRead, FramedWrite},
+    sync::CancellationToken,
+};
+
+use crate::protocol::{ReplicaCapability, ReplicationSectionData, ServerRole};
+use tracing::{debug, error, info, warn};
+
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tokio::time::{interval_at, sleep, timeout_at, MissedTickBehavior};
+// use tokio::time::{sleep, Duration};
+
+use crate::auth::SharedAuthenticator;
+use crate::cli::Cli;
+use crate::errors::RedisError;
+
+use crate::handlers::{
+    clients::{ClientsActorHandle, OutputQueue, OutputReceiver, QueryBufferLimits, QueryDecoder},
+    config_command::ConfigCommandActorHandle,
+    failpoints::FailpointActorHandle,
+    pubsub::PubSubActorHandle,
+    replication::ReplicationActorHandle,
+    request_processor::RequestProcessorActorHandle,
+    set_command::SetCommandActorHandle,
+};
+
+use crate::monitor::spawn_audit_log;
+use crate::notifications::{spawn_keyspace_notifier, NotifyKeyspaceEvents};
+use crate::protocol::ConfigCommandParameter;
+use crate::rdb::load::load_rdb_transfer;
+use crate::storage;
+
+// use env_logger::Env;
+// use log::{debug, info};
+// use resp::{encode_slice, Decoder};
+
+// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{tcp::{OwnedReadHalf, OwnedWriteHalf}, TcpListener, TcpStream};
+
+/// Runs the server cli describes until it fails to accept a connection, with AUTH and HELLO
+/// checking credentials with authenticator. main.rs runs it with DefaultUser.
+///
+/// NOTE: SHUTDOWN exits the process, embedded or not.
+pub async fn serve(cli: Cli, authenticator: SharedAuthenticator) -> anyhow::Result<()> {
+    // let ip_listen = "0.0.0.0".to_string();
+
+    // cli.port comes from cli.rs; default is 6379
+    let socket_address = std::net::SocketAddr::from(([0, 0, 0, 0], cli.port));
+
+    let listener = TcpListener::bind(socket_address).await?;
+
+    tracing::debug!("Redis is running on port {}.", cli.port);
+
+    // Get a handle to the pubsub actor, one per redis. This starts the actor.
+    let pubsub_actor_handle = PubSubActorHandle::new(cli.pubsub_queue_limit as usize);
+
+    // Keyspace notifications are published through the pubsub actor, as long as any are asked for.
+    let notify_keyspace_events: NotifyKeyspaceEvents = cli.notify_keyspace_events.parse()?;
+    let notifications =
+        spawn_keyspace_notifier(notify_keyspace_events, pubsub_actor_handle.clone())
+            .map(|(events_tx, _notifier)| events_tx);
+
+    // clap only lets through the names of backends there are
+    let open_store = storage::backend(&cli.storage_backend)
+        .ok_or_else(|| anyhow!("No storage backend named {}.", cli.storage_backend))?;
+
+    // Get a handle to the set actor, one per redis. This starts the actor.
+    let set_command_actor_handle =
+        SetCommandActorHandle::with_backend(cli.databases as usize, notifications, open_store);
+
+    // Get a handle to the info actor, one per redis. This starts the actor.
+    let replication_actor_handle = ReplicationActorHandle::new();
+
+    // Get a handle to the config actor, one per redis. This starts the actor.
+    let config_command_actor_handle = ConfigCommandActorHandle::new();
+
+    // Get a handle to the failpoint actor, one per redis. Nothing is armed until DEBUG FAILPOINT says so.
+    let failpoint_actor_handle = FailpointActorHandle::new();
+
+    // Get a handle to the clients actor, one per redis. It keeps every connection's output buffer in check.
+    let clients_actor_handle = ClientsActorHandle::new(cli.maxmemory_clients as usize);
+
+    // this is where decoded resp values are sent for processing
+    let request_processor_actor_handle = RequestProcessorActorHandle::with_authenticator(
+        Some(Duration::from_millis(cli.request_timeout)).filter(|timeout| !timeout.is_zero()),
+        authenticator,
+    );
+
+    // An async multi-producer multi-consumer channel,
+    // where each message can be received by only one of all existing consumers.
+    let (tcp_msgs_tx, tcp_msgs_rx) = async_channel::unbounded();
+
+    // Create a multi-producer, single-consumer channel to recv messages from the master.
+    // NOTE: these messages are replies coming back from the master, not commands to the master.
+    // Used by handshake() to forward replies from the master, from replica to itself.
+    // Typically, these are +OK and FULLRESYNC messages.
+    let (master_tx, master_rx) = mpsc::channel::<String>(9600);
+
+    // Setup a tokio broadcast channel to communicate all writeable updates to all the replicas.
+    // This is a multi-producer, multi-consumer channel.
+    // The replica_tx Sender is cloned and passed to the client handler.
+    // The replica_tx is given to request_processor_actor_handle.process_request() to send writeable updates to the replica,
+    // via the same initial connection that the replica used to connect to the master.
+    //
+    // NOTE: the master handler that got created as part of the outbound connection from the replica to the master,
+    // does not handle replication messages. It only sends commands to the master and receives replies.
+    // Basically, from master's POV, a replica is just a client. But from replica's POV, it acts as a client to the master,
+    // receiving replies from the master via the master_rx channel.
+    let (replica_tx, _replica_rx) = broadcast::channel::<RespValue>(9600);
+
+    // we have a special consumer that gets the payload destined to the replicas and updates master's own offset calculations
+
+    // Check the value provided by the arguments.
+    // Store the config values if they are valid.
+    // NOTE: If nothing is passed, cli.rs has the default values for clap.
+    if let Some(dir) = cli.dir.as_deref() {
+        // This macro is equivalent to if !$cond { return Err(anyhow!($args...)); }.
+        // https://docs.rs/anyhow/latest/anyhow/macro.ensure.html
+        // NOTE: we cannot use ensure! because this exits; instead we need to create the file if it
+        // doesn't exist.
+        ensure!(Path::new(&dir).exists(), "Directory {} not found.", dir);
+
+        config_command_actor_handle
+            .set_value(ConfigCommandParameter::Dir, dir)
+            .await?;
+    }
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::PubsubQueueLimit,
+            &cli.pubsub_queue_limit.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::MaxmemoryClients,
+            &cli.maxmemory_clients.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ClientQueryBufferInitialSize,
+            &cli.client_query_buffer_initial_size.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ClientQueryBufferLimit,
+            &cli.client_query_buffer_limit.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::Maxmemory,
+            &cli.maxmemory.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::MaxmemoryPolicy,
+            &cli.maxmemory_policy,
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReplicaIgnoreMaxmemory,
+            &cli.replica_ignore_maxmemory,
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::RequestTimeout,
+            &cli.request_timeout.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReadTimeLimit,
+            &cli.read_time_limit.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::Auditlog,
+            &cli
+                .auditlog
+                .as_deref()
+                .map(|auditlog| auditlog.to_string_lossy())
+                .unwrap_or_default(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::AuditlogMaxSize,
+            &cli.auditlog_max_size.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::NotifyKeyspaceEvents,
+            &cli.notify_keyspace_events,
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(ConfigCommandParameter::Hz, &cli.hz.to_string())
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ProtoMaxBulkLen,
+            &cli.proto_max_bulk_len.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(ConfigCommandParameter::StorageBackend, &cli.storage_backend)
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReplDisklessSync,
+            if cli.repl_diskless_sync { "yes" } else { "no" },
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReplDisklessSyncDelay,
+            &cli.repl_diskless_sync_delay.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReplLagWarnThreshold,
+            &cli.repl_lag_warn_threshold.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReplApplyWarnThreshold,
+            &cli.repl_apply_warn_threshold.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ReplAckInterval,
+            &cli.repl_ack_interval.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::ShutdownTimeout,
+            &cli.shutdown_timeout.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::KeysWarnThreshold,
+            &cli.keys_warn_threshold.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::KeysByScan,
+            if cli.keys_by_scan { "yes" } else { "no" },
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::Databases,
+            &cli.databases.to_string(),
+        )
+        .await?;
+
+    config_command_actor_handle
+        .set_value(
+            ConfigCommandParameter::EnableDebugCommand,
+            if cli.enable_debug_command {
+                "yes"
+            } else {
+                "no"
+            },
+        )
+        .await?;
+
+    if let Some(dbfilename) = cli.dbfilename.as_deref() {
+        config_command_actor_handle
+            .set_value(
+                ConfigCommandParameter::DbFilename,
+                &dbfilename.to_string_lossy(),
+            )
+            .await?;
+
+        // let config_dbfilename = dbfilename.to_string_lossy().to_string();
+
+        config_command_actor_handle
+            .import_config(
+                set_command_actor_handle.clone(), // need to pass this to get direct access to the redis db
+                None,                             // load from disk
+            )
+            .await?;
+    }
+
+    // writes from --import on are in it
+    let _audit_log = match cli.auditlog.clone() {
+        Some(auditlog) => Some(
+            spawn_audit_log(
+                auditlog,
+                cli.auditlog_max_size,
+                request_processor_actor_handle.monitor(),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    if let Some(import) = cli.import.as_deref() {
+        // The commands go in on top of the RDB file, so that has to be loaded first. The config
+        // actor imports one file at a time, so its next reply means it is.
+        config_command_actor_handle
+            .get_value(ConfigCommandParameter::DbFilename)
+            .await?;
+
+        let (commands, failed) = import_commands(
+            import,
+            set_command_actor_handle.clone(),
+            config_command_actor_handle.clone(),
+            replication_actor_handle.clone(),
+            failpoint_actor_handle.clone(),
+            pubsub_actor_handle.clone(),
+            clients_actor_handle.clone(),
+            request_processor_actor_handle.clone(),
+            master_tx.clone(),
+            replica_tx.clone(),
+        )
+        .await?;
+
+        info!(
+            "Imported {} commands from {}, {} of them failed.",
+            commands,
+            import.display(),
+            failed
+        );
+    }
+
+    // A replica is one from the start, not only once the handshake is done: the link to the master
+    // closes as soon as the role turns to master (REPLICAOF NO ONE).
+    let role = if cli.replicaof.is_some() {
+        ServerRole::Slave
+    } else {
+        ServerRole::Master
+    };
+
+    let replication_data: ReplicationSectionData = ReplicationSectionData {
+        role: Some(role),
+        master_replid: Some(generate_replication_id()),
+        master_repl_offset: None,
+        listening_port: None,
+        capabilities: None,
+    };
+
+    replication_actor_handle
+        .update_value(HostId::Myself, replication_data)
+        .await?;
+
+    debug!(
+        "Just set the value: {}",
+        replication_actor_handle
+            .get_value(HostId::Myself)
+            .await?
+            .expect("Should have found the self value.")
+    );
+
+    // see if we need to override it
+    if let Some(replica) = cli.replicaof.as_deref() {
+        config_command_actor_handle
+            .set_value(ConfigCommandParameter::Replicaof, replica)
+            .await?;
+
+        let master_host_port_combo = replica.replace(" ", ":");
+
+        // We can pass a string to TcpStream::connect, so no need to create SocketAddr
+        let stream = TcpStream::connect(&master_host_port_combo)
+            .await
+            .expect("Failed to establish connection to master."); // panic is ok here since this is not a recoverable error.
+
+        // Must clone the actors handlers because tokio::spawn move will grab everything.
+        let set_command_handler_clone = set_command_actor_handle.clone();
+        let config_command_handler_clone = config_command_actor_handle.clone();
+        let replication_actor_handle_clone = replication_actor_handle.clone();
+        let failpoint_actor_handle_clone = failpoint_actor_handle.clone();
+        let pubsub_actor_handle_clone = pubsub_actor_handle.clone();
+        let clients_actor_handle_clone = clients_actor_handle.clone();
+        let request_processor_actor_handle_clone = request_processor_actor_handle.clone();
+
+        let tcp_msgs_rx_clone = tcp_msgs_rx.clone();
+        let master_tx_clone = master_tx.clone();
+        let replica_tx_clone = replica_tx.clone();
+
+        // 0 never warns
+        let apply_warn_threshold = (cli.repl_apply_warn_threshold > 0)
+            .then(|| Duration::from_millis(cli.repl_apply_warn_threshold));
+        let ack_interval = Duration::from_millis(cli.repl_ack_interval);
+
+        tokio::spawn(async move {
+            handle_connection_to_master(
+                stream,
+                set_command_handler_clone,
+                config_command_handler_clone,
+                replication_actor_handle_clone,
+                failpoint_actor_handle_clone,
+                pubsub_actor_handle_clone,
+                clients_actor_handle_clone,
+                request_processor_actor_handle_clone,
+                tcp_msgs_rx_clone,
+                master_tx_clone,
+                replica_tx_clone, // used to send replication messages to the replica
+                apply_warn_threshold,
+                ack_interval,
+            )
+            .await
+        });
+
+        // handshake sets the replica replid based on the value it gets from the master.
+        handshake(
+            tcp_msgs_tx.clone(),
+            master_rx,
+            cli.port,
+            replication_actor_handle.clone(),
+        )
+        .await?;
+    } else {
+        // we master, we no replica!
+        debug!("We are a master, cool.");
+
+        // one more round of cloning
+        let replication_actor_handle_clone = replication_actor_handle.clone();
+        let replica_tx_offset_clone = replica_tx.clone();
+
+        // kick off a never ending subscriber to calculate and update master's offset
+        tokio::spawn(async move {
+            if let Err(e) =
+                update_master_offset(replica_tx_offset_clone, replication_actor_handle_clone).await
+            {
+                error!("Stopped counting the replication offset: {}", e);
+            }
+        });
+    }
+
+    // keys nobody reads again are only ever removed by the active expiry cycle
+    let _expiry_cycle = spawn_expiry_cycle(cli.hz, set_command_actor_handle.clone());
+
+    // INFO keyspace's avg_ttl, estimated in the background like redis does
+    let _keyspace_sampler = spawn_keyspace_sampler(set_command_actor_handle.clone());
+
+    // the lag= INFO replication shows for each replica, in the log once it gets too long
+    let _lag_monitor = spawn_replication_lag_monitor(
+        cli.repl_lag_warn_threshold,
+        replication_actor_handle.clone(),
+    );
+
+    // Every client connection reads its requests into a buffer of its own, these keep them in check.
+    let query_buffer_limits = QueryBufferLimits {
+        initial_size: cli.client_query_buffer_initial_size as usize,
+        limit: cli.client_query_buffer_limit as usize,
+    };
+
+    // Every accepted connection gets the next id, the same way redis numbers its clients.
+    let mut connection_id: u64 = 0;
+
+    loop {
+        // Asynchronously wait for an inbound TcpStream.
+        let (stream, socket_address) = listener.accept().await?;
+
+        connection_id += 1;
+
+        debug!(
+            "Received connection {} from {}",
+            connection_id, socket_address
+        );
+
+        // Must clone the actors handlers because tokio::spawn move will grab everything.
+        let set_command_handler_clone = set_command_actor_handle.clone();
+        let config_command_handler_clone = config_command_actor_handle.clone();
+        let info_command_actor_handle_clone = replication_actor_handle.clone();
+        let failpoint_actor_handle_clone = failpoint_actor_handle.clone();
+        let pubsub_actor_handle_clone = pubsub_actor_handle.clone();
+        let clients_actor_handle_clone = clients_actor_handle.clone();
+        let request_processor_actor_handle_clone = request_processor_actor_handle.clone();
+
+        let master_tx_clone = master_tx.clone();
+
+        let replica_tx_clone = replica_tx.clone();
+        // let replica_rx_subscriber = replica_tx.subscribe();
+
+        let host_id = HostId::Host {
+            id: connection_id,
+            ip: socket_address.ip().to_string(),
+            port: socket_address.port(),
+        };
+
+        // Spawn our handler to be run asynchronously.
+        // A new task is spawned for each inbound socket.  The socket is moved to the new task and processed there.
+        tokio::spawn(async move {
+            let connection = handle_connection_from_clients(
+                stream,
+                host_id.clone(),
+                set_command_handler_clone,
+                config_command_handler_clone,
+                info_command_actor_handle_clone.clone(),
+                failpoint_actor_handle_clone,
+                pubsub_actor_handle_clone.clone(),
+                clients_actor_handle_clone.clone(),
+                request_processor_actor_handle_clone.clone(),
+                query_buffer_limits,
+                master_tx_clone,
+                replica_tx_clone,
+                // replica_rx_subscriber,
+            );
+
+            // A panic ends this connection only. Its socket closes as the task unwinds, what the
+            // actors keep for it has to go the same way as after a hang up.
+            if let Err(panic) = AssertUnwindSafe(connection).catch_unwind().await {
+                error!(
+                    "Closing {:?}, its connection panicked ({}).",
+                    host_id,
+                    supervisor::panic_message(panic.as_ref())
+                );
+                info_command_actor_handle_clone
+                    .remove_host(host_id.clone())
+                    .await;
+                pubsub_actor_handle_clone.disconnect(host_id.clone()).await;
+                clients_actor_handle_clone.disconnect(host_id.clone()).await;
+                request_processor_actor_handle_clone
+                    .disconnect(host_id)
+                    .await;
+            }
+        });
+    }
+}
+
+// Replays the RESP commands in path through the request processor, one at a time and in order,
+// the way the master's stream is applied on a replica. A command that fails is logged and the rest
+// still go in, a file that isn't RESP all the way through stops the server from starting. Returns
+// how many commands there were and how many of them failed.
+#[allow(clippy::too_many_arguments)]
+async fn import_commands(
+    path: &Path,
+    set_command_actor_handle: SetCommandActorHandle,
+    config_command_actor_handle: ConfigCommandActorHandle,
+    replication_actor_handle: ReplicationActorHandle,
+    failpoint_actor_handle: FailpointActorHandle,
+    pubsub_actor_handle: PubSubActorHandle,
+    clients_actor_handle: ClientsActorHandle,
+    request_processor_actor_handle: RequestProcessorActorHandle,
+    master_tx: mpsc::Sender<String>,
+    replica_tx: broadcast::Sender<RespValue>,
+) -> Result<(usize, usize)> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| anyhow!("Unable to open {}: {}", path.display(), e))?;
+    let mut reader = FramedRead::new(file, RespCodec::new());
+
+    let (mut commands, mut failed) = (0, 0);
+
+    while let Some(request) = reader.next().await {
+        let request = request.map_err(|e| {
+            anyhow!("{} isn't RESP from command {} on: {}", path.display(), commands + 1, e)
+        })?;
+        commands += 1;
+
+        // Trusted like the master's stream: no AUTH, and no maxmemory or read only replica refusals.
+        let processed = request_processor_actor_handle
+            .process_request(
+                request,
+                set_command_actor_handle.clone(),
+                config_command_actor_handle.clone(),
+                replication_actor_handle.clone(),
+                failpoint_actor_handle.clone(),
+                pubsub_actor_handle.clone(),
+                clients_actor_handle.clone(),
+                HostId::Myself,
+                master_tx.clone(),
+                replica_tx.clone(),
+                None, // nothing to report back to
+                None, // no WAIT
+                None, // nor BLPOP
+                None, // nor pushes
+            )
+            .await;
+
+        let error = match processed {
+            Ok(replies) => replies.into_iter().flatten().find_map(|reply| match reply {
+                RespValue::Error(message) => Some(message),
+                _ => None,
+            }),
+            Err(e) => Some(e.to_string()),
+        };
+
+        if let Some(error) = error {
+            warn!("Command {} of {} failed: {}", commands, path.display(), error);
+            failed += 1;
+        }
+    }
+
+    // the master's stream comes in as Myself too, and starts from database 0 outside of a MULTI
+    request_processor_actor_handle.disconnect(HostId::Myself).await;
+
+    Ok((commands, failed))
+}
+
+// This function will handle the connection from the client.
+// A request's arguments, command name first, when it is an array of UTF-8 bulk strings.
+fn request_args(request: &RespValue) -> Option<Vec<&str>> {
+    let RespValue::Array(elements) = request else {
+        return None;
+    };
+
+    elements
+        .iter()
+        .map(|element| match element {
+            RespValue::BulkString(Some(bytes)) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+// The reason why we need two separate functions, one for clients and one for master,
+// is because the replica will be acting as a client, sending commands to the master and receiving replies.
+//
+// But the handle_connection_from_clients() function will only be receiving commands from clients and sending replies.
+// In other words, a redis instance can be both, a replica client to the master, and a server to its own clients.
+// So, this is the "server" part of the redis instance.
+// #[tracing::instrument]
+#[allow(clippy::too_many_arguments)]
+async fn handle_connection_from_clients(
+    stream: TcpStream,
+    host_id: HostId,
+    set_command_actor_handle: SetCommandActorHandle,
+    config_command_actor_handle: ConfigCommandActorHandle,
+    replication_actor_handle: ReplicationActorHandle,
+    failpoint_actor_handle: FailpointActorHandle,
+    pubsub_actor_handle: PubSubActorHandle,
+    clients_actor_handle: ClientsActorHandle,
+    request_processor_actor_handle: RequestProcessorActorHandle,
+    query_buffer_limits: QueryBufferLimits,
+    master_tx: mpsc::Sender<String>, // passthrough to request_processor_actor_handle
+    replica_tx: broadcast::Sender<RespValue>, // used to send replication messages to the replica
+) -> anyhow::Result<()> {
+    debug!("Handling connection from {:?}", host_id);
+
+    let mut replica_rx = replica_tx.subscribe();
+
+    // the pubsub actor names the subscribers it drops for falling behind, this one closes if it is among them
+    let mut evicted_rx = pubsub_actor_handle.evictions();
+
+    // same for the clients dropped to get back under maxmemory-clients
+    let mut clients_evicted_rx = clients_actor_handle.evictions();
+
+    // and for a request the processor panicked on
+    let mut panicked_rx = request_processor_actor_handle.panics();
+
+    debug!("Subscribed to replica updates {:?}", replica_rx);
+
+    // Split the TCP stream into a reader and writer.
+    let (reader, writer) = stream.into_split();
+
+    // Requests are read into a buffer of client-query-buffer-initial-size, which the decoder shrinks
+    // back after a burst and CLIENT LIST reports on.
+    let (decoder, query) = QueryDecoder::new(query_buffer_limits);
+    let mut reader = FramedRead::with_capacity(reader, decoder, query_buffer_limits.initial_size);
+
+    // Replies, RESP3 pushes and the replication stream all go through the output queue, in order, and a task
+    // of its own writes them out. A client that stops reading holds up that task only, while its output
+    // buffer grows where the pubsub and clients actors can see it.
+    let (output, output_rx) = OutputQueue::new();
+    let stop_writing = CancellationToken::new();
+    let writer_guard = stop_writing.clone().drop_guard();
+
+    tokio::spawn(write_output(
+        FramedWrite::new(writer, RespCodec::new()),
+        output_rx,
+        stop_writing,
+    ));
+
+    clients_actor_handle
+        .connect(host_id.clone(), output.clone(), query)
+        .await;
+
+    // This is a channel to let the thread know whether the client is a replica or not.
+    // We need to know because replication messages are only sent to replicas, not to redis-cli clients.
+    let (client_or_replica_tx, mut client_or_replica_rx) = mpsc::channel::<bool>(3);
+
+    // Create a channel for notifying the main loop when WAIT N NNN is done waiting
+    let (wait_sleep_tx, mut wait_sleep_rx) = mpsc::channel::<i64>(10); // i64 here is the target_offset
+
+    // A BLPOP or BRPOP with nothing to pop yet comes back on blocked_rx and stays parked until a push
+    // serves it or it times out. The requests that arrive meanwhile are held back, in order, like
+    // redis does for a blocked client, but the socket is still read so a hangup is noticed.
+    let (blocked_tx, mut blocked_rx) = mpsc::channel::<BlockedPop>(1);
+    let mut parked: Option<BlockedPop> = None;
+    let mut held: VecDeque<RespValue> = VecDeque::new();
+
+    let mut am_i_replica: bool = false;
+
+    // Set once this replica's PSYNC got no reply, its +FULLRESYNC and RDB come later on the
+    // replication stream. Until then the writes it would be sent are in the snapshot it is waiting for.
+    let mut awaiting_snapshot: bool = false;
+
+    // Set once this replica has its snapshot. Before that, it is still going through the handshake
+    // and the writes on the replication stream are in the snapshot it will get.
+    let mut synced: bool = false;
+
+    // The database this connection has SELECTed, for the GET fast path below.
+    // The processor keeps its own copy for everything else.
+    let mut db: usize = 0;
+
+    // Some while a MULTI is open, holding what each queued command would SELECT.
+    // A queued GET has to go through the processor, and a queued SELECT only lands once EXEC runs it.
+    let mut transaction: Option<Vec<Option<usize>>> = None;
+
+    loop {
+        tokio::select! {
+            msg = next_request(&mut reader, &mut held, parked.is_some()) => {
+                match msg {
+                    Some(Ok(request)) if parked.is_some() => held.push_back(request),
+                    Some(Ok(request)) => {
+                        let args = request_args(&request);
+
+                        // GET is answered from the store's databases directly, skipping the processor,
+                        // unless a MONITOR has to see it or the processor would refuse it.
+                        if let (Some([name, key]), None, true) = (
+                            args.as_deref(),
+                            &transaction,
+                            request_processor_actor_handle.may_skip_processor(&host_id),
+                        ) {
+                            if name.eq_ignore_ascii_case("GET") {
+                                let started = Instant::now();
+                                let reply = match set_command_actor_handle.read_value(db, key) {
+                                    Ok(value) => value.map_or(RespValue::Null, |value| {
+                                        RespValue::BulkString(Some(value.into()))
+                                    }),
+                                    Err(e) => e.into(),
+                                };
+
+                                // counted here, the processor never sees it
+                                request_processor_actor_handle.command_stats().record(
+                                    "GET",
+                                    started.elapsed().as_micros() as u64,
+                                    matches!(reply, RespValue::Error(_)),
+                                );
+
+                                queue(&output, reply)?;
+
+                                continue;
+                            }
+                        }
+
+                        let name = args
+                            .as_deref()
+                            .and_then(|args| args.first())
+                            .map(|name| name.to_ascii_uppercase());
+
+                        // only known once the processor accepts it
+                        let selected = match args.as_deref() {
+                            Some([name, index]) if name.eq_ignore_ascii_case("SELECT") => {
+                                index.parse::<usize>().ok()
+                            }
+                            _ => None,
+                        };
+
+                        // DEBUG SEGFAULT takes the connection down once the processor lets it through
+                        let segfault = matches!(
+                            args.as_deref(),
+                            Some([name, subcommand])
+                                if name.eq_ignore_ascii_case("DEBUG") && subcommand.eq_ignore_ascii_case("SEGFAULT")
+                        );
+
+                        // send the request to the request processor actor.
+                        // debug!("Received {:?} from client: {:?}", request.to_encoded_string()?, host_id);
+                        if let Some(processed_values) = request_processor_actor_handle
+                            .process_request(
+                                request,
+                                set_command_actor_handle.clone(),
+                                config_command_actor_handle.clone(),
+                                replication_actor_handle.clone(),
+                                failpoint_actor_handle.clone(),
+                                pubsub_actor_handle.clone(),
+                                clients_actor_handle.clone(),
+                                host_id.clone(),
+                                master_tx.clone(), // these are ack +OK replies from the master back to handshake()
+                                replica_tx.clone(), // used to send replication messages to the replica
+                                Some(client_or_replica_tx.clone()), // used to update replica status
+                                Some(wait_sleep_tx.clone()), // we need this to hear back once WAIT is done
+                                Some(blocked_tx.clone()), // and this to park a BLPOP or BRPOP
+                                Some(output.clone()), // RESP3 pushes, published messages
+                            )
+                            .await
+                            // a stopped actor or a timeout fails this request, not the connection
+                            .unwrap_or_else(|e| Some(vec![e.into()]))
+                        {
+                            tracing::debug!("Preparing to send to client: {:?}", processed_values);
+
+                            match (name.as_deref(), processed_values.as_slice(), transaction.as_mut()) {
+                                (Some("MULTI"), [RespValue::SimpleString(ok)], None) if ok == "OK" => {
+                                    transaction = Some(Vec::new());
+                                }
+                                (Some("DISCARD"), _, Some(_)) => transaction = None,
+                                (Some("EXEC"), replies, Some(queued)) => {
+                                    if let [RespValue::Array(replies)] = replies {
+                                        for (index, reply) in queued.iter().zip(replies) {
+                                            if let (Some(index), RespValue::SimpleString(ok)) = (index, reply) {
+                                                if ok == "OK" {
+                                                    db = *index;
+                                                }
+                                            }
+                                        }
+                                    }
+
+                                    transaction = None;
+                                }
+                                (_, [RespValue::SimpleString(reply)], Some(queued))
+                                    if reply == "QUEUED" =>
+                                {
+                                    queued.push(selected);
+                                }
+                                (_, [RespValue::SimpleString(reply)], None) if reply == "OK" && segfault => {
+                                    panic!("DEBUG SEGFAULT");
+                                }
+                                (_, [RespValue::SimpleString(reply)], None) if reply == "OK" => {
+                                    if let Some(index) = selected {
+                                        db = index;
+                                    }
+                                }
+                                (Some("PSYNC"), [], _) => awaiting_snapshot = true,
+                                _ => {}
+                            }
+
+                            // iterate over processed_value and send each one to the client
+                            for value in processed_values {
+                                // debug!("Sending response {:?} to client: {:?}", value.to_encoded_string()?, host_id);
+                                queue(&output, value)?;
+
+                                // tracing::debug!("Done sending, moving to the next value.");
+                            }
+                            // debug!("Done sending to {host_id}, moving to the next value.");
+                        }
+
+                        // handed over before the processor replied, so it is already there
+                        if let Ok(blocked) = blocked_rx.try_recv() {
+                            parked = Some(blocked);
+                        }
+                    }
+                    Some(Err(RedisError::QueryBufferLimit(len))) => {
+                        warn!("Closing {:?}, its query buffer of {} bytes is over client-query-buffer-limit.", host_id, len);
+                        replication_actor_handle.remove_host(host_id.clone()).await;
+                        pubsub_actor_handle.disconnect(host_id.clone()).await;
+                        clients_actor_handle.disconnect(host_id.clone()).await;
+                        request_processor_actor_handle.disconnect(host_id).await;
+
+                        return Ok(());
+                    }
+                    Some(Err(e)) => {
+                        error!("Unable to decode request from client: {e}");
+                    }
+                    None => {
+                        // the client hung up
+                        debug!("Connection from {:?} closed.", host_id);
+                        replication_actor_handle.remove_host(host_id.clone()).await;
+                        pubsub_actor_handle.disconnect(host_id.clone()).await;
+                        clients_actor_handle.disconnect(host_id.clone()).await;
+                        request_processor_actor_handle.disconnect(host_id).await;
+
+                        // a client may stop sending and still read, what's queued for it goes out before the socket closes
+                        writer_guard.disarm();
+
+                        return Ok(());
+                    }
+                }
+            }
+
+         msg = replica_rx.recv() => { // from processor.rs replica_tx
+            tracing::debug!("replica_rx channel received {:?} for {:?}", String::from_utf8_lossy(&msg.clone()?.encode()), host_id);
+            match msg {
+                Ok(RespValue::Rdb(rdb)) => {
+                    // a full resync's snapshot, only for the replicas still waiting on one
+                    if awaiting_snapshot {
+                        debug!("Sending the {} byte snapshot to replica {:?}.", rdb.len(), host_id);
+                        awaiting_snapshot = false;
+                        synced = true;
+
+                        // a replica that announced capa eof gets it delimited by a random mark, like
+                        // redis sends a transfer whose length it doesn't know up front
+                        let eof = replication_actor_handle
+                            .get_value(host_id.clone())
+                            .await?
+                            .and_then(|replica| replica.capabilities)
+                            .is_some_and(|capabilities| capabilities.contains(&ReplicaCapability::Eof));
+
+                        if eof {
+                            queue(&output, RespValue::RdbEof(generate_replication_id(), rdb))?;
+                        } else {
+                            queue(&output, RespValue::Rdb(rdb))?;
+                        }
+                    }
+                }
+                Ok(RespValue::SimpleString(reply)) if reply.starts_with("FULLRESYNC") => {
+                    // the full resync's offset, ahead of its snapshot
+                    if awaiting_snapshot {
+                        queue(&output, RespValue::SimpleString(reply))?;
+                    }
+                }
+                Ok(msg) if awaiting_snapshot => {
+                    debug!("Not forwarding {:?} to {:?}, it is in the snapshot.", msg, host_id);
+                }
+                Ok(msg) => {
+                    // Send replication messages only to replicas, not to other clients.
+                    if am_i_replica && synced {
+                        match failpoint_actor_handle.next_fault().await? {
+                            ReplicationFault::None => queue(&output, msg)?,
+                            ReplicationFault::Delay(latency) => {
+                                sleep(latency).await;
+                                queue(&output, msg)?;
+                            }
+                            ReplicationFault::Drop => {
+                                warn!("Failpoint: dropping {:?} to replica {:?}", msg, host_id);
+                            }
+                            ReplicationFault::Disconnect => {
+                                warn!("Failpoint: disconnecting replica {:?}", host_id);
+                                replication_actor_handle.remove_host(host_id.clone()).await;
+                                pubsub_actor_handle.disconnect(host_id.clone()).await;
+                                clients_actor_handle.disconnect(host_id.clone()).await;
+                                request_processor_actor_handle.disconnect(host_id).await;
+
+                                return Ok(());
+                            }
+                        }
+                    } else if am_i_replica {
+                        debug!("Not forwarding {:?} to {:?}, it has no snapshot yet.", msg, host_id);
+                    } else {
+                        debug!("Not forwarding message to non-replica client {:?}.", host_id);
+                    }
+                }
+                Err(e) => {
+                    error!("Something unexpected happened during replica_rx.recv(): {e}");
+                }
+            }
+         }
+         Some(msg) = client_or_replica_rx.recv() => {
+            // // if let Some(client_type) = msg {
+                // check to make sure this client is a replica, not a redis-cli client.
+                // if it is a redis-cli client, we don't want to send replication messages to it.
+                // we only want to send replication messages to replicas.
+                am_i_replica  = msg;
+
+                if am_i_replica {
+                    clients_actor_handle.mark_replica(host_id.clone()).await;
+                }
+
+                debug!("Updated client {:?} replica status to {}", host_id, am_i_replica);
+            // // }
+         }
+         reply = unparked(&mut parked) => {
+            parked = None;
+
+            queue(&output, reply)?;
+         }
+         Some(target_offset) = wait_sleep_rx.recv() => { // - 37 to account for replconf getack * we had sent out earlier
+            let replicas_in_sync = replication_actor_handle.get_synced_replica_count(target_offset).await?;
+
+            queue(&output, RespValue::Integer(replicas_in_sync as i64))?;
+
+        }
+         _ = evicted(&mut evicted_rx, &host_id) => {
+            // its subscriptions are already gone
+            warn!("Closing {:?}, it fell too far behind on published messages.", host_id);
+            replication_actor_handle.remove_host(host_id.clone()).await;
+            clients_actor_handle.disconnect(host_id.clone()).await;
+            request_processor_actor_handle.disconnect(host_id).await;
+
+            return Ok(());
+         }
+         _ = evicted(&mut clients_evicted_rx, &host_id) => {
+            // the clients actor has already forgotten it
+            warn!("Closing {:?}, its output buffer was the biggest over maxmemory-clients.", host_id);
+            replication_actor_handle.remove_host(host_id.clone()).await;
+            pubsub_actor_handle.disconnect(host_id.clone()).await;
+            request_processor_actor_handle.disconnect(host_id).await;
+
+            return Ok(());
+         }
+         _ = evicted(&mut panicked_rx, &host_id) => {
+            // Whatever the processor left of its MULTI, subscriptions or name can't be trusted.
+            // The error its request got goes out before the socket closes.
+            warn!("Closing {:?}, the request processor panicked on its request.", host_id);
+            replication_actor_handle.remove_host(host_id.clone()).await;
+            pubsub_actor_handle.disconnect(host_id.clone()).await;
+            clients_actor_handle.disconnect(host_id.clone()).await;
+            request_processor_actor_handle.disconnect(host_id).await;
+            writer_guard.disarm();
+
+            return Ok(());
+         }
+        } // end tokio::select
+    }
+}
+
+// The next request for a client connection to run. The ones held back while it was parked come
+// first, and while it is parked whatever is read is only to be held back too.
+async fn next_request(
+    reader: &mut FramedRead<OwnedReadHalf, QueryDecoder>,
+    held: &mut VecDeque<RespValue>,
+    parked: bool,
+) -> Option<Result<RespValue, RedisError>> {
+    if !parked {
+        if let Some(request) = held.pop_front() {
+            return Some(Ok(request));
+        }
+    }
+
+    reader.next().await
+}
+
+// The reply to the BLPOP or BRPOP a connection is parked on, once a push serves it or its timeout
+// passes. Never resolves while nothing is parked.
+async fn unparked(parked: &mut Option<BlockedPop>) -> RespValue {
+    let Some(BlockedPop { popped, deadline }) = parked else {
+        return std::future::pending().await;
+    };
+
+    let served = match deadline {
+        Some(deadline) => match timeout_at(*deadline, &mut *popped).await {
+            Ok(served) => served.ok(),
+            // a push may have served it just as the timeout passed, none can once it is closed
+            Err(_) => {
+                popped.close();
+                popped.try_recv().ok()
+            }
+        },
+        None => popped.await.ok(),
+    };
+
+    match served {
+        Some((key, value)) => RespValue::array_from_slice(&[&key, &value]),
+        None => RespValue::NullArray,
+    }
+}
+
+// Queues a frame for the client. This only fails once write_output has given up on the socket.
+fn queue(output: &OutputQueue, frame: RespValue) -> anyhow::Result<()> {
+    output
+        .send(frame)
+        .map_err(|_| anyhow!("The connection's writer is gone."))
+}
+
+// Writes a client connection's output queue to its socket, until every sender is gone or the connection
+// stops it. Stopping drops whatever was still queued, the client is being disconnected anyway.
+async fn write_output(
+    mut writer: FramedWrite<OwnedWriteHalf, RespCodec>,
+    mut output_rx: OutputReceiver,
+    stop_writing: CancellationToken,
+) {
+    let write_all = async {
+        while let Some((frame, protocol)) = output_rx.recv().await {
+            writer.encoder_mut().set_protocol(protocol);
+
+            // The replies to a pipelined burst go out in as few writes as they fit in, the socket
+            // is only flushed once nothing more is queued.
+            writer.feed(frame).await?;
+
+            if output_rx.is_empty() {
+                writer.flush().await?;
+                output_rx.written();
+            }
+        }
+
+        anyhow::Ok(())
+    };
+
+    tokio::select! {
+        _ = stop_writing.cancelled() => {}
+        written = write_all => {
+            if let Err(e) = written {
+                debug!("Stopped writing to the client: {e}");
+            }
+        }
+    }
+}
+
+// Resolves once the actor behind evicted_rx (pubsub, clients or the processor) has dropped this connection.
+async fn evicted(evicted_rx: &mut broadcast::Receiver<HostId>, host_id: &HostId) {
+    loop {
+        match evicted_rx.recv().await {
+            Ok(evicted) if evicted == *host_id => return,
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
+
+// This is the "client" part of the redis instance.
+// #[tracing::instrument]
+#[allow(clippy::too_many_arguments)]
+async fn handle_connection_to_master(
+    stream: TcpStream,
+    set_command_actor_handle: SetCommandActorHandle,
+    config_command_actor_handle: ConfigCommandActorHandle,
+    replication_actor_handle: ReplicationActorHandle,
+    failpoint_actor_handle: FailpointActorHandle,
+    pubsub_actor_handle: PubSubActorHandle,
+    clients_actor_handle: ClientsActorHandle,
+    request_processor_actor_handle: RequestProcessorActorHandle,
+    tcp_msgs_rx: async_channel::Receiver<RespValue>,
+    master_tx: mpsc::Sender<String>, // passthrough to request_processor_actor_handle
+    replica_tx: broadcast::Sender<RespValue>, // used to send replication messages to the replica
+    apply_warn_threshold: Option<Duration>,
+    ack_interval: Duration,
+) -> Result<()> {
+    // Split the TCP stream into a reader and writer.
+    let (reader, writer) = stream.into_split();
+
+    let mut reader = FramedRead::new(reader, RespCodec::new());
+    let mut writer = FramedWrite::new(writer, RespCodec::new());
+
+    // set by +FULLRESYNC, the RDB comes next
+    let mut rdb_follows = false;
+
+    // Set while writes from the master take repl-apply-warn-threshold or longer to apply, so the
+    // log says so once when it starts and once when it's over. Nothing more is read from the
+    // socket until a write is applied, a replica that can't keep up holds its master back instead
+    // of buffering what it hasn't applied.
+    let mut slow_to_apply = false;
+
+    // Once synced the master hears how far we've got every ack_interval, which also tells it we're
+    // still there. A tick that finds an ACK already went out since the last one has nothing to add.
+    // A burst of GETACKs read in one go gets one ACK, the reply to the last of them, once the burst
+    // has been applied, rather than one per GETACK.
+    let mut synced = false;
+    let mut ack_timer = interval_at(tokio::time::Instant::now() + ack_interval, ack_interval);
+    ack_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut acked_since_tick = false;
+    let mut pending_ack: Option<RespValue> = None;
+
+    loop {
+        if std::mem::take(&mut rdb_follows) {
+            // Diskless load: the RDB goes from the socket through the decoder into the store as it
+            // arrives, instead of being framed as one RespValue holding the whole file.
+            //
+            // It replaces the dataset, so anything still loading from disk has to be done first.
+            // The config actor imports one file at a time, so its next reply means it is.
+            config_command_actor_handle
+                .get_value(ConfigCommandParameter::DbFilename)
+                .await?;
+            set_command_actor_handle.flush_all().await?;
+
+            // what was read past +FULLRESYNC, handed back afterwards with whatever followed the RDB
+            let mut buffered = std::mem::take(reader.read_buffer_mut());
+            let loaded =
+                load_rdb_transfer(&mut buffered, reader.get_mut(), &set_command_actor_handle)
+                    .await?;
+            *reader.read_buffer_mut() = buffered;
+
+            info!("Loaded {} keys from the master's RDB.", loaded);
+
+            // the first ACK a period in, the stream after the RDB has only just started
+            synced = true;
+            ack_timer.reset();
+            continue;
+        }
+
+        tokio::select! {
+            // Read data from the stream, these are commands from the master to the replica
+            Some(msg) = reader.next() => {
+                match msg {
+                    Ok(request) => {
+                        rdb_follows = matches!(&request, RespValue::SimpleString(reply) if reply.starts_with("FULLRESYNC"));
+
+                        // Promoted by REPLICAOF NO ONE, the master's stream is no longer ours to apply.
+                        let role = replication_actor_handle
+                            .get_value(HostId::Myself)
+                            .await?
+                            .and_then(|myself| myself.role);
+
+                        if role == Some(ServerRole::Master) {
+                            info!("No longer a replica, closing the connection to the master.");
+                            return Ok(());
+                        }
+
+                        let apply_started = Instant::now();
+
+                        // send the request to the request processor actor
+                        let processed = request_processor_actor_handle
+                            .process_request(
+                                request.clone(),
+                                set_command_actor_handle.clone(),
+                                config_command_actor_handle.clone(),
+                                replication_actor_handle.clone(),
+                                failpoint_actor_handle.clone(),
+                                pubsub_actor_handle.clone(),
+                                clients_actor_handle.clone(),
+                                HostId::Myself, // we are a replica, creating outbound connections, so we are Myself
+                                master_tx.clone(), // these are ack +OK replies from the master back to handshake()
+                                replica_tx.clone(), // this enables daisy chaining of replicas to other replicas
+                                None, // connections to master cannot update replica status
+                                None, // connections to master do not handle WAIT commands
+                                None, // nor do they block
+                                None, // nothing is pushed back to the master
+                            )
+                            .await
+                            .unwrap_or_else(|e| {
+                                error!("Failed to apply {:?} from the master: {}", request, e);
+                                None
+                            });
+
+                        // the replication stream received and not applied yet, and the requests
+                        // this one waited behind
+                        let took = apply_started.elapsed();
+                        let backlog = reader.read_buffer().len();
+                        let queued = request_processor_actor_handle.queued();
+                        let slow = apply_warn_threshold.is_some_and(|threshold| took >= threshold);
+
+                        if slow && !slow_to_apply {
+                            warn!(
+                                "Falling behind the master: a write took {}ms to apply, {} bytes of the stream and {} requests are waiting",
+                                took.as_millis(), backlog, queued
+                            );
+                        } else if !slow && slow_to_apply {
+                            info!("Keeping up with the master again, {} bytes of the stream are waiting", backlog);
+                        }
+                        slow_to_apply = slow;
+
+                        replication_actor_handle.record_apply(backlog, queued, took, slow).await;
+
+                        if let Some(processed_value) = processed {
+                                // This is replica's own offset calculations.
+                                // we need to encode the request to count the bytes, values may be any bytes.
+                                let value_as_bytes = request.encode();
+
+                                // calculate how many bytes are in value_as_bytes
+                                let value_as_string_num_bytes = value_as_bytes.len() as i64;
+
+                                debug!("REPLICA: {:?} has {value_as_string_num_bytes} bytes.", String::from_utf8_lossy(&value_as_bytes));
+
+                                // we need to update replica's offset because we are sending writeable commands to replicas
+                                let mut updated_replication_data = ReplicationSectionData::new();
+
+                                // remember, this is an INCREMENT not a total new value
+                                updated_replication_data.master_repl_offset =Some(value_as_string_num_bytes);
+
+                                // Myself from replica's POV
+                                replication_actor_handle.update_value(HostId::Myself,updated_replication_data).await?;
+
+                                // iterate over processed_value and send each one to the client
+
+                                // only strings containing REPLCONF go back to master
+                                let strings_to_reply = "REPLCONF";
+
+                                for value in processed_value.iter() {
+                                    // check to see if processed_value contains REPLCONF in the encoded string
+                                    if String::from_utf8_lossy(&value.encode()).contains(strings_to_reply) {
+                                        // a later GETACK's reply covers this one's offset too
+                                        pending_ack = Some(value.clone());
+                                    }
+                                }
+                        }
+
+                        // nothing more read from the master, so no later GETACK to wait for
+                        if reader.read_buffer().is_empty() {
+                            if let Some(ack) = pending_ack.take() {
+                                writer.send(ack).await?;
+                                acked_since_tick = true;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("Unable to decode request from master: {e}");
+                    }
+                } // end match
+         } // end reader
+         // see if we have any message to send to master.
+         // handshake() is the only function communicating on this channel.
+         // NOTE: this channel is async_channel::unbounded(), which means only 1 msg will be processed by all consumers, like AWS SQS.
+         // However, we only have 1 consumer, the master, so this is fine. This is because a replica only connects to 1 master.
+         msg = tcp_msgs_rx.recv() => {
+            match msg {
+                Ok(msg) => {
+                    tracing::debug!("Sending message to master: {:?}", msg.to_encoded_string()?);
+                    writer.send(msg).await?;
+                    // writer.flush().await?;
+                }
+                Err(e) => {
+                    error!("Something unexpected happened: {e}");
+                }
+            }
+         }
+         _ = ack_timer.tick(), if synced => {
+            if std::mem::take(&mut acked_since_tick) {
+                continue;
+            }
+
+            let Some(myself) = replication_actor_handle.get_value(HostId::Myself).await? else {
+                continue;
+            };
+
+            // REPLICAOF NO ONE, there is no master to tell anymore
+            if myself.role == Some(ServerRole::Master) {
+                info!("No longer a replica, closing the connection to the master.");
+                return Ok(());
+            }
+
+            // the offset now, at least as far as any GETACK still waiting for its reply
+            if let Some(offset) = myself.master_repl_offset {
+                debug!("Sending REPLCONF ACK {} to master", offset);
+                writer
+                    .send(RespValue::array_from_slice(&["REPLCONF", "ACK", &offset.to_string()]))
+                    .await?;
+                pending_ack = None;
+            }
+         }
+        } // end tokio::select
+    }
+}
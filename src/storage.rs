@@ -0,0 +1,266 @@
+// Where the store actor keeps the keys and values of a database.
+//
+// The actor still does everything redis semantics need on top: deadlines and lazy expiry, the SCAN
+// order, keyspace stats. A backend only has to hold values by key, so one that persists them or
+// reads through to something else can stand in for the HashMap without touching the commands. Keys
+// are UTF-8. A key holds a string, bytes whatever the client sent, or a collection: a hash, a
+// list, a set, a sorted set or a stream.
+
+use std::{borrow::Cow, collections::HashMap, fmt, sync::Arc};
+
+use crate::collections::Collection;
+
+/// Every key of a store holding a string with its value, what KeyValueStore::scan() goes through.
+pub type Entries<'a> = Box<dyn Iterator<Item = (Cow<'a, str>, Cow<'a, [u8]>)> + 'a>;
+
+/// Every key of a store holding a collection with it, what KeyValueStore::scan_collections() goes
+/// through.
+pub type CollectionEntries<'a> = Box<dyn Iterator<Item = (Cow<'a, str>, Arc<Collection>)> + 'a>;
+
+/// What a key holds, the types TYPE and SCAN TYPE name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    String,
+    Hash,
+    List,
+    Set,
+    SortedSet,
+    Stream,
+}
+
+impl Kind {
+    /// The type redis gives name to, whatever the case, None if it has none of that name.
+    pub fn from_name(name: &str) -> Option<Kind> {
+        [
+            Kind::String,
+            Kind::Hash,
+            Kind::List,
+            Kind::Set,
+            Kind::SortedSet,
+            Kind::Stream,
+        ]
+        .into_iter()
+        .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The name redis gives the type.
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::String => "string",
+            Kind::Hash => "hash",
+            Kind::List => "list",
+            Kind::Set => "set",
+            Kind::SortedSet => "zset",
+            Kind::Stream => "stream",
+        }
+    }
+}
+
+/// The value a key holds, whichever type it is, as DEBUG OBJECT looks at it.
+#[derive(Debug, Clone)]
+pub enum Object {
+    String(Vec<u8>),
+    Collection(Arc<Collection>),
+}
+
+/// The keys and values of one database.
+///
+/// Every call comes from the store actor, one at a time, while it holds the write lock of the
+/// databases. Reads that skip the actor (GET, KEYS) call `get` under the read lock, so that one
+/// may run on several threads at once.
+pub trait KeyValueStore: fmt::Debug + Send + Sync {
+    /// The string stored at key, None if there is no such key or it holds a collection.
+    fn get(&self, key: &str) -> Option<Cow<'_, [u8]>>;
+
+    /// Stores value at key, replacing what was there, string or collection.
+    fn set(&mut self, key: String, value: Vec<u8>);
+
+    /// Changes the value at key where it is, for APPEND and SETRANGE. Returns false and leaves
+    /// `update` uncalled if there is no such key.
+    ///
+    /// A backend that can't change a value in place reads it, updates it and sets it back.
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Vec<u8>)) -> bool;
+
+    /// The collection stored at key, None if there is no such key or it holds a string. Shared
+    /// with the caller, who may keep it after the lock is gone, so a backend that decodes its
+    /// collections hands out a new one.
+    fn get_collection(&self, key: &str) -> Option<Arc<Collection>>;
+
+    /// Stores collection at key, replacing what was there, string or collection.
+    fn set_collection(&mut self, key: String, collection: Collection);
+
+    /// update() for the collection at key. Returns false and leaves `update` uncalled if there is
+    /// no such key or it holds a string.
+    fn update_collection(&mut self, key: &str, update: &mut dyn FnMut(&mut Collection)) -> bool;
+
+    /// What key holds, None if there is no such key.
+    fn kind(&self, key: &str) -> Option<Kind> {
+        match self.get_collection(key) {
+            Some(collection) => Some(collection.kind()),
+            None => self.get(key).map(|_| Kind::String),
+        }
+    }
+
+    /// Removes key, whatever it holds, returning whether it was there.
+    fn del(&mut self, key: &str) -> bool;
+
+    /// Every key holding a string and its value, in no particular order.
+    fn scan(&self) -> Entries<'_>;
+
+    /// Every key holding a collection and the collection, in no particular order. Also what an RDB
+    /// written out while writes go on has of them, so the same sharing as get_collection() goes.
+    fn scan_collections(&self) -> CollectionEntries<'_>;
+
+    /// Every key holding a string and its value as they are now, for an RDB that is written out
+    /// while writes go on. Taken under the write lock, so this copies every value unless the
+    /// backend can hand out ones it shares with the snapshot and copies before changing.
+    fn snapshot(&self) -> Vec<(String, Arc<Vec<u8>>)> {
+        self.scan()
+            .map(|(key, value)| (key.into_owned(), Arc::new(value.into_owned())))
+            .collect()
+    }
+
+    /// How many keys there are, whatever they hold.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Told the deadline of key whenever it changes, a unix timestamp in milliseconds or None
+    /// once the key is persistent or gone. The actor expires keys by itself, this is for a
+    /// backend that wants to keep deadlines along with the values.
+    fn expire(&mut self, _key: &str, _deadline_ms: Option<u64>) {}
+
+    /// Removes every key, for FLUSHALL and a full resync.
+    fn clear(&mut self);
+
+    fn contains(&self, key: &str) -> bool {
+        self.kind(key).is_some()
+    }
+}
+
+/// Opens the store of a database, given its number. Called once per database when the store
+/// actor starts.
+pub type OpenStore = Arc<dyn Fn(usize) -> Box<dyn KeyValueStore> + Send + Sync>;
+
+/// The backends --storage-backend can name.
+pub const STORAGE_BACKENDS: [&str; 1] = ["memory"];
+
+/// The backend --storage-backend names, None if there is no such backend.
+pub fn backend(name: &str) -> Option<OpenStore> {
+    match name {
+        "memory" => Some(Arc::new(|_db| Box::<MemoryStore>::default())),
+        _ => None,
+    }
+}
+
+/// Everything in a HashMap, the default. A value is shared with the snapshots that have it, and
+/// copied by the first change while one of them is still being written out.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    kv_hash: HashMap<String, Stored>,
+}
+
+// A value of a MemoryStore.
+#[derive(Debug)]
+enum Stored {
+    String(Arc<Vec<u8>>),
+    Collection(Arc<Collection>),
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<Cow<'_, [u8]>> {
+        match self.kv_hash.get(key)? {
+            Stored::String(value) => Some(Cow::Borrowed(value.as_slice())),
+            Stored::Collection(_) => None,
+        }
+    }
+
+    fn set(&mut self, key: String, value: Vec<u8>) {
+        self.kv_hash.insert(key, Stored::String(Arc::new(value)));
+    }
+
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Vec<u8>)) -> bool {
+        match self.kv_hash.get_mut(key) {
+            Some(Stored::String(value)) => {
+                update(Arc::make_mut(value));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn get_collection(&self, key: &str) -> Option<Arc<Collection>> {
+        match self.kv_hash.get(key)? {
+            Stored::Collection(collection) => Some(Arc::clone(collection)),
+            Stored::String(_) => None,
+        }
+    }
+
+    fn set_collection(&mut self, key: String, collection: Collection) {
+        self.kv_hash
+            .insert(key, Stored::Collection(Arc::new(collection)));
+    }
+
+    fn update_collection(&mut self, key: &str, update: &mut dyn FnMut(&mut Collection)) -> bool {
+        match self.kv_hash.get_mut(key) {
+            Some(Stored::Collection(collection)) => {
+                update(Arc::make_mut(collection));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn kind(&self, key: &str) -> Option<Kind> {
+        match self.kv_hash.get(key)? {
+            Stored::String(_) => Some(Kind::String),
+            Stored::Collection(collection) => Some(collection.kind()),
+        }
+    }
+
+    fn del(&mut self, key: &str) -> bool {
+        self.kv_hash.remove(key).is_some()
+    }
+
+    fn scan(&self) -> Entries<'_> {
+        Box::new(self.kv_hash.iter().filter_map(|(key, value)| match value {
+            Stored::String(value) => {
+                Some((Cow::Borrowed(key.as_str()), Cow::Borrowed(value.as_slice())))
+            }
+            Stored::Collection(_) => None,
+        }))
+    }
+
+    fn scan_collections(&self) -> CollectionEntries<'_> {
+        Box::new(self.kv_hash.iter().filter_map(|(key, value)| match value {
+            Stored::Collection(collection) => {
+                Some((Cow::Borrowed(key.as_str()), Arc::clone(collection)))
+            }
+            Stored::String(_) => None,
+        }))
+    }
+
+    fn snapshot(&self) -> Vec<(String, Arc<Vec<u8>>)> {
+        self.kv_hash
+            .iter()
+            .filter_map(|(key, value)| match value {
+                Stored::String(value) => Some((key.clone(), Arc::clone(value))),
+                Stored::Collection(_) => None,
+            })
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.kv_hash.len()
+    }
+
+    fn clear(&mut self) {
+        self.kv_hash.clear();
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.kv_hash.contains_key(key)
+    }
+}
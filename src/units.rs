@@ -0,0 +1,42 @@
+// Memory sizes the way redis.conf writes them, https://redis.io/docs/latest/operate/oss_and_stack/management/config-file/
+//
+// A number of bytes, or a number with a unit after it, in any case: 1k is 1000 bytes and 1kb
+// is 1024, the same for m/mb and g/gb. Whatever it was given as, a size is kept and reported in
+// bytes.
+
+/// What is wrong with a size, the same for every malformed one.
+pub const MEMORY_VALUE_ERROR: &str = "argument must be a memory value";
+
+/// A size in bytes from "100", "100b", "64k", "100mb", "2GB" and the like.
+pub fn parse_memory(value: &str) -> Result<u64, String> {
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(MEMORY_VALUE_ERROR.to_string()),
+    };
+
+    // digits only, so no sign or fraction, and nothing past what a u64 holds
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| MEMORY_VALUE_ERROR.to_string())
+}
+
+/// A size that may not be 0, for the buffers that have to hold something.
+pub fn parse_nonzero_memory(value: &str) -> Result<u64, String> {
+    match parse_memory(value)? {
+        0 => Err("0 is not allowed, the size has to be at least 1 byte".to_string()),
+        bytes => Ok(bytes),
+    }
+}
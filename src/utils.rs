@@ -2,38 +2,43 @@
 
 // Key functions and their purposes:
 
-// expire_value: Handles delayed expiration of values based on specified EX or PX options.
-// It schedules a task to delete the value after the specified duration.
-//
 // handshake: Manages the replication handshake process between a master and slave node.
 // It sends and receives necessary commands to establish the connection and synchronize replication data.
 //
+// spawn_expiry_cycle: Asks the store for an active expiry cycle hz times a second.
+// Shared by main.rs and the embedded Engine.
+//
+// spawn_keyspace_sampler: Estimates avg_ttl for INFO keyspace a few keys at a time, off the store.
+//
+// spawn_replication_lag_monitor: Warns about replicas that haven't acknowledged in a while.
+//
 // generate_replication_id: Generates a random 40-character alphanumeric string to be used as a replication ID.
+//
+// glob_match: redis flavoured glob matching, for KEYS and CONFIG GET patterns.
 
 // Additional details:
 
 // The code uses tokio for asynchronous operations and anyhow for error handling.
 
 // It leverages tracing for logging and debugging.
-// The code includes functions to handle different expiration options (EX, PX, EXAT, PXAT, KEEPTTL) but currently only implements the EX and PX options.
 // The handshake function sends commands to establish a replication connection, including PING, REPLCONF, and PSYNC.
 // The generate_replication_id function uses rand to generate a random string for the replication ID.
 
 use crate::{
     actors::messages::HostId,
     handlers::{replication::ReplicationActorHandle, set_command::SetCommandActorHandle},
-    protocol::{self, ReplicationSectionData, ServerRole, SetCommandParameter},
+    protocol::{ReplicationSectionData, ServerRole},
     resp::value::RespValue,
 };
 use anyhow::{Context, Result};
 
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::time::sleep;
+use std::{collections::HashSet, time::Duration};
+use tokio::time::{interval, sleep, MissedTickBehavior};
 use tokio::{
     sync::{broadcast, mpsc},
     task::JoinHandle,
 };
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
 // for master repl id generation
 use rand::distributions::Alphanumeric;
@@ -41,7 +46,7 @@ use rand::{thread_rng, Rng};
 use std::iter;
 // ----------
 
-pub async fn sleeping_task(wait_sleep_tx: mpsc::Sender<i16>, duration: Duration, target_offset: i16) -> JoinHandle<()> {
+pub async fn sleeping_task(wait_sleep_tx: mpsc::Sender<i64>, duration: Duration, target_offset: i64) -> JoinHandle<()> {
     let handle = tokio::spawn(async move {
         tracing::info!("Sleeping thread started.");
         sleep(duration).await;
@@ -54,31 +59,34 @@ pub async fn sleeping_task(wait_sleep_tx: mpsc::Sender<i16>, duration: Duration,
     handle
 }
 
+/// Runs until the replication actor is gone.
 pub async fn update_master_offset(
     replica_tx: broadcast::Sender<RespValue>,
     replication_actor_handle: ReplicationActorHandle,
-) {
+) -> Result<()> {
     let mut replica_rx = replica_tx.subscribe();
     // Start receiving messages from the channel by calling the recv method of the Receiver endpoint.
     // This method blocks until a message is received.
     loop {
         let msg = replica_rx.recv().await;
         match msg {
+            // the +FULLRESYNC and RDB of a full resync go out on the stream too, but they are not
+            // part of what the offset counts
+            Ok(RespValue::Rdb(_)) => {}
+            Ok(RespValue::SimpleString(reply)) if reply.starts_with("FULLRESYNC") => {}
             Ok(payload) => {
-                // we need to convert the command to a RESP string to count the bytes.
-                let value_as_string = payload
-                    .to_encoded_string()
-                    .expect("Expected to easily convert RESP to string");
+                // we need to encode the command to count the bytes, values may be any bytes.
+                let value_as_bytes = payload.encode();
 
-                // calculate how many bytes are in the value_as_string
-                let value_as_string_num_bytes = value_as_string.len() as i16;
+                // calculate how many bytes are in value_as_bytes
+                let value_as_string_num_bytes = value_as_bytes.len() as i64;
 
                 // these should never fail, so expect is ok.
                 debug!(
                     "MASTER: current offset: {} bytes",
                     replication_actor_handle
                         .get_value(HostId::Myself)
-                        .await
+                        .await?
                         .expect("Expected to get master replication info.")
                         .master_repl_offset
                         .expect("Expected to get master offset.")
@@ -94,13 +102,13 @@ pub async fn update_master_offset(
                 // updating master offset as a master
                 replication_actor_handle
                     .update_value(HostId::Myself, updated_replication_data_master)
-                    .await;
+                    .await?;
 
                 debug!(
                     "MASTER: updated offset: {}",
                     replication_actor_handle
                         .get_value(HostId::Myself)
-                        .await
+                        .await?
                         .expect("Expected to get master replication info.")
                         .master_repl_offset
                         .expect("Expected to get master offset.")
@@ -112,76 +120,102 @@ pub async fn update_master_offset(
         }
     }
 }
-pub async fn expire_value(
-    msg: SetCommandParameter,
+/// Runs the active expiry cycle hz times a second, like serverCron does in redis.
+///
+/// Reads remove expired keys on their own, the cycle is for the ones nobody reads again. Each
+/// cycle may hold the store for a quarter of its period at most, so however many keys expire at
+/// once, the store is never taken from the clients for longer than that.
+pub fn spawn_expiry_cycle(
+    hz: u64,
     set_command_actor_handle: SetCommandActorHandle,
-) -> anyhow::Result<()> {
-    // We may or may not need to expire a value. If not, no big deal, just wait again.
-    if let Some(duration) = msg.expire {
-        match duration {
-            // reminder: seconds are Unix timestamps
-            protocol::SetCommandExpireOption::EX(seconds) => {
-                // Must clone again because we're about to move this into a dedicated sleep thread.
-                let expire_command_handler_clone = set_command_actor_handle.clone();
-
-                // NOTE: type annotations are needed here
-                let _expiry_handle: tokio::task::JoinHandle<Result<()>> =
-                    tokio::spawn(async move {
-                        // get the current system time
-                        let now = SystemTime::now();
-
-                        // how many seconds have elapsed since beginning of time
-                        let duration_since_epoch = now.duration_since(UNIX_EPOCH)?;
-
-                        // i64 since it is possible for this to be negative, i.e. past time expiration
-                        let expiry_time = seconds as i64 - duration_since_epoch.as_secs() as i64;
-
-                        // we sleep if this is NON negative
-                        if !expiry_time < 0 {
-                            debug!("Sleeping for {} seconds.", expiry_time);
-                            sleep(Duration::from_secs(expiry_time as u64)).await;
-                        }
-
-                        // Fire off a command to the handler to remove the value immediately.
-                        expire_command_handler_clone.delete_value(&msg.key).await;
+) -> JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let period = Duration::from_millis(1000 / hz.clamp(1, 500));
+        let mut cycles = interval(period);
+        // a cycle that ran long pushes the next one back instead of bunching them up
+        cycles.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            cycles.tick().await;
+            set_command_actor_handle
+                .active_expire_cycle(period / 4)
+                .await?;
+        }
+    })
+}
 
-                        Ok(())
-                    });
+// How often the keyspace sampler takes a step, and how many keys with a deadline it looks at in
+// each database when it does.
+const KEYSPACE_SAMPLER_PERIOD: Duration = Duration::from_millis(100);
+const KEYSPACE_SAMPLER_KEYS_PER_STEP: usize = 20;
+
+/// Walks the keys with a deadline of every database in the background, estimating the avg_ttl
+/// INFO keyspace reports.
+///
+/// The store isn't asked for anything: each step reads a few keys of one database at a time under
+/// the read lock, the way GET does, so a write never waits on more than that. A pass over a
+/// database starts over from its first key once the last is behind.
+pub fn spawn_keyspace_sampler(
+    set_command_actor_handle: SetCommandActorHandle,
+) -> JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let mut cursors = vec![None; set_command_actor_handle.databases()];
+        let mut steps = interval(KEYSPACE_SAMPLER_PERIOD);
+        steps.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            steps.tick().await;
+
+            for (db, cursor) in cursors.iter_mut().enumerate() {
+                *cursor = set_command_actor_handle.sample_avg_ttl(
+                    db,
+                    cursor.take(),
+                    KEYSPACE_SAMPLER_KEYS_PER_STEP,
+                )?;
             }
-            protocol::SetCommandExpireOption::PX(milliseconds) => {
-                // Must clone again because we're about to move this into a dedicated sleep thread.
-                let command_handler_expire_clone = set_command_actor_handle.clone();
-                let _expiry_handle: tokio::task::JoinHandle<Result<()>> =
-                    tokio::spawn(async move {
-                        // get the current system time
-                        let now = SystemTime::now();
-
-                        // how many milliseconds have elapsed since beginning of time
-                        let duration_since_epoch = now.duration_since(UNIX_EPOCH)?;
-
-                        // i64 since it is possible for this to be negative, i.e. past time expiration
-                        let expiry_time =
-                            milliseconds as i64 - duration_since_epoch.as_millis() as i64;
-
-                        // we sleep if this is NON negative
-                        if !expiry_time < 0 {
-                            debug!("Sleeping for {} milliseconds.", expiry_time);
-                            sleep(Duration::from_millis(expiry_time as u64)).await;
-                        }
+        }
+    })
+}
+
+/// Checks once a second how far behind every replica is, and warns when one hasn't sent
+/// REPLCONF ACK for threshold seconds or more. A replica is warned about once until it catches up
+/// again. A threshold of 0 turns this off.
+pub fn spawn_replication_lag_monitor(
+    threshold: u64,
+    replication_actor_handle: ReplicationActorHandle,
+) -> Option<JoinHandle<Result<()>>> {
+    if threshold == 0 {
+        return None;
+    }
 
-                        // Fire off a command to the handler to remove the value immediately.
-                        command_handler_expire_clone.delete_value(&msg.key).await;
+    Some(tokio::spawn(async move {
+        let mut checks = interval(Duration::from_secs(1));
+        checks.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
-                        Ok(())
-                    });
+        let mut lagging = HashSet::new();
+
+        loop {
+            checks.tick().await;
+
+            let lags = replication_actor_handle.get_replica_lags().await?;
+
+            // the ones that went away are no longer lagging
+            lagging.retain(|host_id| lags.iter().any(|(replica, _)| replica == host_id));
+
+            for (host_id, lag) in lags {
+                if lag.seconds >= threshold {
+                    if lagging.insert(host_id.clone()) {
+                        warn!(
+                            "Replica {host_id} is lagging: no ACK for {}s, {} bytes behind",
+                            lag.seconds, lag.bytes
+                        );
+                    }
+                } else if lagging.remove(&host_id) {
+                    info!("Replica {host_id} caught up, {} bytes behind", lag.bytes);
+                }
             }
-            protocol::SetCommandExpireOption::EXAT(_) => todo!(),
-            protocol::SetCommandExpireOption::PXAT(_) => todo!(),
-            protocol::SetCommandExpireOption::KEEPTTL => todo!(),
         }
-    }
-
-    Ok(())
+    }))
 }
 
 pub async fn handshake(
@@ -199,9 +233,10 @@ pub async fn handshake(
     let replconf_listening_port =
         RespValue::array_from_slice(&["REPLCONF", "listening-port", &port.to_string()]);
 
-    // STEP 3: REPLCONF capa psync2
-    // initialize the empty array
-    let repl_conf_capa = RespValue::array_from_slice(&["REPLCONF", "capa", "psync2"]);
+    // STEP 3: REPLCONF capa eof capa psync2
+    // eof: a diskless sync may come delimited by a mark instead of its length, see load_rdb_transfer
+    let repl_conf_capa =
+        RespValue::array_from_slice(&["REPLCONF", "capa", "eof", "capa", "psync2"]);
 
     // STEP 4: send the PSYNC ? -1
     let psync = RespValue::array_from_slice(&["PSYNC", "?", "-1"]);
@@ -228,14 +263,14 @@ pub async fn handshake(
         reply
     );
 
-    // send the REPLCONF capa psync2
+    // send the REPLCONF capa eof capa psync2
     tcp_msgs_tx.send(repl_conf_capa).await?;
     // wait for a reply from the master before proceeding
     let reply = master_rx
         .recv()
         .await
-        .context("Failed to receive a reply from master after sending REPLCONF capa psync2.")?;
-    debug!("HANDSHAKE REPLCONF capa psync2: master replied {:?}", reply);
+        .context("Failed to receive a reply from master after sending REPLCONF capa.")?;
+    debug!("HANDSHAKE REPLCONF capa eof capa psync2: master replied {:?}", reply);
 
     // send the PSYNC ? -1
     /*
@@ -259,12 +294,14 @@ pub async fn handshake(
                 .context("Failed to receive a reply from master after sending PSYNC ? -1.")?,
         ), // master will reply with its repl id
         master_repl_offset: None,
+        listening_port: None,
+        capabilities: None,
     };
 
     // my own replication data, i.e. slave's own replication data
     replication_actor_handle
         .update_value(HostId::Myself, replication_data)
-        .await;
+        .await?;
 
     // We are done with the handshake!
     debug!("Handshake completed.");
@@ -289,3 +326,96 @@ pub fn generate_replication_id() -> String {
 
     repl_id
 }
+
+/// Glob-style matching, same rules as redis' stringmatchlen() used by KEYS and CONFIG GET:
+/// `*` matches any run, `?` any single byte, `[abc]`, `[^abc]` and `[a-z]` match classes and `\` escapes.
+pub fn glob_match(pattern: &str, string: &str, nocase: bool) -> bool {
+    glob_match_bytes(pattern.as_bytes(), string.as_bytes(), nocase)
+}
+
+fn glob_match_bytes(mut pattern: &[u8], mut string: &[u8], nocase: bool) -> bool {
+    let fold = |c: u8| if nocase { c.to_ascii_lowercase() } else { c };
+
+    while let Some(&p) = pattern.first() {
+        match p {
+            b'*' => {
+                // consecutive stars are the same as one
+                while pattern.get(1) == Some(&b'*') {
+                    pattern = &pattern[1..];
+                }
+
+                if pattern.len() == 1 {
+                    return true;
+                }
+
+                return (0..=string.len())
+                    .any(|skip| glob_match_bytes(&pattern[1..], &string[skip..], nocase));
+            }
+            b'?' => {
+                if string.is_empty() {
+                    return false;
+                }
+                string = &string[1..];
+                pattern = &pattern[1..];
+            }
+            b'[' => {
+                let Some(&c) = string.first() else {
+                    return false;
+                };
+                let c = fold(c);
+
+                pattern = &pattern[1..];
+                let negate = pattern.first() == Some(&b'^');
+                if negate {
+                    pattern = &pattern[1..];
+                }
+
+                // an unterminated class runs to the end of the pattern
+                let mut matched = false;
+                loop {
+                    match pattern {
+                        [] => break,
+                        [b']', rest @ ..] => {
+                            pattern = rest;
+                            break;
+                        }
+                        [b'\\', escaped, rest @ ..] => {
+                            matched |= fold(*escaped) == c;
+                            pattern = rest;
+                        }
+                        [start, b'-', end, rest @ ..] => {
+                            let (start, end) = (fold(*start), fold(*end));
+                            matched |= (start.min(end)..=start.max(end)).contains(&c);
+                            pattern = rest;
+                        }
+                        [literal, rest @ ..] => {
+                            matched |= fold(*literal) == c;
+                            pattern = rest;
+                        }
+                    }
+                }
+
+                if matched == negate {
+                    return false;
+                }
+                string = &string[1..];
+            }
+            _ => {
+                // a trailing backslash is taken literally
+                if p == b'\\' && pattern.len() >= 2 {
+                    pattern = &pattern[1..];
+                }
+
+                match string.first() {
+                    Some(&c) if fold(c) == fold(pattern[0]) => {
+                        string = &string[1..];
+                        pattern = &pattern[1..];
+                    }
+                    _ => return false,
+                }
+            }
+        }
+    }
+
+    string.is_empty()
+}
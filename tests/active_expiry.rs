@@ -0,0 +1,70 @@
+// Keys nobody reads again are removed by the active expiry cycle, in every database, and INFO
+// stats counts them.
+
+mod common;
+
+use common::{ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn stats_field(client: &mut common::Client, field: &str) -> String {
+    let RespValue::BulkString(Some(section)) = client.call(&["INFO", "stats"]) else {
+        panic!("INFO stats replies with a bulk string");
+    };
+
+    let section = String::from_utf8(section.to_vec()).expect("INFO is text");
+
+    section
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{}:", field)))
+        .unwrap_or_else(|| panic!("INFO stats has {}", field))
+        .to_string()
+}
+
+// Pipelined, so the keys don't take a round trip each.
+fn set_expiring_keys(client: &mut common::Client, count: usize) {
+    for n in 0..count {
+        client.send(&["SET", &format!("key:{}", n), "value", "PX", "50"]);
+    }
+
+    for _ in 0..count {
+        assert_eq!(client.receive(), ok());
+    }
+}
+
+#[test]
+fn unread_keys_are_removed() {
+    let server = Server::start(&["--hz", "50"]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "persistent", "value"]), ok());
+    set_expiring_keys(&mut client, 300);
+
+    assert_eq!(client.call(&["SELECT", "3"]), ok());
+    set_expiring_keys(&mut client, 100);
+
+    client.wait_for(&["DBSIZE"], RespValue::Integer(0));
+
+    assert_eq!(client.call(&["SELECT", "0"]), ok());
+    client.wait_for(&["DBSIZE"], RespValue::Integer(1));
+
+    assert_eq!(stats_field(&mut client, "expired_keys"), "400");
+}
+
+#[test]
+fn nothing_expired_nothing_counted() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar", "EX", "100"]), ok());
+
+    assert_eq!(stats_field(&mut client, "expired_keys"), "0");
+    assert_eq!(stats_field(&mut client, "expired_keys_per_sec"), "0.00");
+    assert_eq!(
+        stats_field(&mut client, "expired_time_cap_reached_count"),
+        "0"
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "hz"]),
+        RespValue::Array(vec![common::bulk("hz"), common::bulk("10")])
+    );
+}
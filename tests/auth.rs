@@ -0,0 +1,256 @@
+// AUTH and HELLO ... AUTH. The server takes the default user with any password, when AUTH names
+// it, and asks nobody to AUTH; an embedder's own Authenticator can check credentials elsewhere and
+// turn NOAUTH on, for the actors on their own or for the whole server.
+
+mod common;
+
+use clap::Parser;
+use common::{bulk, ok, Client, Server};
+use futures::future::BoxFuture;
+use redis_starter_rust::{
+    actors::messages::HostId,
+    auth::Authenticator,
+    cli::Cli,
+    handlers::{
+        clients::ClientsActorHandle, config_command::ConfigCommandActorHandle,
+        failpoints::FailpointActorHandle, pubsub::PubSubActorHandle,
+        replication::ReplicationActorHandle, request_processor::RequestProcessorActorHandle,
+        set_command::SetCommandActorHandle,
+    },
+    resp::value::RespValue,
+    server,
+};
+use std::{net::TcpListener, sync::Arc, thread::sleep, time::Duration};
+use tokio::{
+    sync::{broadcast, mpsc, Notify},
+    time::timeout,
+};
+
+fn wrong_pass() -> RespValue {
+    RespValue::Error("WRONGPASS invalid username-password pair or user is disabled.".to_string())
+}
+
+fn no_auth() -> RespValue {
+    RespValue::Error("NOAUTH Authentication required.".to_string())
+}
+
+#[test]
+fn the_default_user_takes_any_password() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // nothing to AUTH for first
+    assert_eq!(client.call(&["SET", "key", "value"]), ok());
+
+    // there's no password to check a bare one against
+    assert_eq!(
+        client.call(&["AUTH", "whatever"]),
+        RespValue::Error(
+            "ERR AUTH <password> called without any password configured for the default user. \
+             Are you sure your configuration is correct?"
+                .to_string()
+        )
+    );
+    assert_eq!(client.call(&["AUTH", "default", "whatever"]), ok());
+    assert_eq!(client.call(&["AUTH", "someone", "whatever"]), wrong_pass());
+    assert_eq!(
+        client.call(&["HELLO", "2", "AUTH", "someone", "whatever"]),
+        wrong_pass()
+    );
+    assert_eq!(
+        client.call(&["AUTH", "a", "b", "c"]),
+        RespValue::Error("ERR wrong number of arguments for 'auth' command".to_string())
+    );
+}
+
+// One service account, and everybody has to AUTH as it.
+struct ServiceAccount;
+
+impl Authenticator for ServiceAccount {
+    fn authenticate<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool> {
+        Box::pin(async move { username == "service" && password == "token" })
+    }
+
+    fn required(&self) -> bool {
+        true
+    }
+}
+
+// The actors main.rs wires up, with ServiceAccount checking credentials.
+struct Actors {
+    processor: RequestProcessorActorHandle,
+    set_command: SetCommandActorHandle,
+    config_command: ConfigCommandActorHandle,
+    replication: ReplicationActorHandle,
+    failpoints: FailpointActorHandle,
+    pubsub: PubSubActorHandle,
+    clients: ClientsActorHandle,
+}
+
+impl Actors {
+    fn start() -> Self {
+        Self::with(Arc::new(ServiceAccount))
+    }
+
+    fn with(authenticator: Arc<dyn Authenticator>) -> Self {
+        Self {
+            processor: RequestProcessorActorHandle::with_authenticator(None, authenticator),
+            set_command: SetCommandActorHandle::new(),
+            config_command: ConfigCommandActorHandle::new(),
+            replication: ReplicationActorHandle::new(),
+            failpoints: FailpointActorHandle::new(),
+            pubsub: PubSubActorHandle::new(1024),
+            clients: ClientsActorHandle::new(0),
+        }
+    }
+
+    async fn call(&self, id: u64, args: &[&str]) -> RespValue {
+        let host_id = HostId::Host {
+            id,
+            ip: "127.0.0.1".to_string(),
+            port: 6380,
+        };
+
+        let mut replies = self
+            .processor
+            .process_request(
+                RespValue::array_from_slice(args),
+                self.set_command.clone(),
+                self.config_command.clone(),
+                self.replication.clone(),
+                self.failpoints.clone(),
+                self.pubsub.clone(),
+                self.clients.clone(),
+                host_id,
+                mpsc::channel(1).0,
+                broadcast::channel(1).0,
+                None,
+                // like a client's connection, EXEC runs without one
+                Some(mpsc::channel(1).0),
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        replies.remove(0)
+    }
+}
+
+#[tokio::test]
+async fn an_authenticator_of_its_own() {
+    let actors = Actors::start();
+
+    assert_eq!(actors.call(1, &["PING"]).await, no_auth());
+    assert_eq!(actors.call(1, &["SET", "key", "value"]).await, no_auth());
+    assert_eq!(actors.call(1, &["HELLO", "3"]).await, no_auth());
+    assert_eq!(actors.call(1, &["AUTH", "token"]).await, wrong_pass());
+    assert_eq!(
+        actors.call(1, &["AUTH", "service", "wrong"]).await,
+        wrong_pass()
+    );
+    assert_eq!(actors.call(1, &["GET", "key"]).await, no_auth());
+
+    // HELLO can authenticate too
+    assert!(matches!(
+        actors
+            .call(1, &["HELLO", "2", "AUTH", "service", "token"])
+            .await,
+        RespValue::Map(_)
+    ));
+    assert_eq!(actors.call(1, &["SET", "key", "value"]).await, ok());
+
+    // every connection on its own
+    assert_eq!(actors.call(2, &["GET", "key"]).await, no_auth());
+    assert_eq!(actors.call(2, &["AUTH", "service", "token"]).await, ok());
+    assert_eq!(actors.call(2, &["GET", "key"]).await, bulk("value"));
+
+    // and a wrong password afterwards doesn't undo it
+    assert_eq!(
+        actors.call(2, &["AUTH", "service", "wrong"]).await,
+        wrong_pass()
+    );
+    assert_eq!(
+        actors.call(2, &["PING"]).await,
+        RespValue::SimpleString("PONG".to_string())
+    );
+}
+
+// Asks a service that doesn't answer until the test lets it.
+struct SlowService(Arc<Notify>);
+
+impl Authenticator for SlowService {
+    fn authenticate<'a>(&'a self, _username: &'a str, _password: &'a str) -> BoxFuture<'a, bool> {
+        Box::pin(async move {
+            self.0.notified().await;
+            true
+        })
+    }
+
+    fn required(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn a_slow_authenticator_holds_up_only_its_own_connection() {
+    let answer = Arc::new(Notify::new());
+    let actors = Arc::new(Actors::with(Arc::new(SlowService(answer.clone()))));
+
+    let waiting = tokio::spawn({
+        let actors = actors.clone();
+        async move { actors.call(1, &["AUTH", "service", "token"]).await }
+    });
+
+    // everybody else is served while the service thinks it over
+    for id in 2..10 {
+        assert_eq!(
+            timeout(Duration::from_secs(1), actors.call(id, &["PING"]))
+                .await
+                .expect("the processor is not waiting on the authenticator"),
+            no_auth()
+        );
+    }
+    assert!(!waiting.is_finished());
+
+    answer.notify_one();
+    assert_eq!(waiting.await.unwrap(), ok());
+    assert_eq!(
+        actors.call(1, &["PING"]).await,
+        RespValue::SimpleString("PONG".to_string())
+    );
+}
+
+#[test]
+fn an_embedded_server_with_an_authenticator_of_its_own() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let cli = Cli::parse_from(["redis", "--port", &port.to_string()]);
+
+    // runs until the test binary exits
+    std::thread::spawn(move || {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(server::serve(cli, Arc::new(ServiceAccount)))
+    });
+    let mut client = loop {
+        match std::net::TcpStream::connect(("127.0.0.1", port)) {
+            Ok(_) => break Client::connect(port),
+            Err(_) => sleep(Duration::from_millis(20)),
+        }
+    };
+
+    // GET included, which the connection would otherwise answer itself
+    assert_eq!(client.call(&["GET", "key"]), no_auth());
+    assert_eq!(client.call(&["SET", "key", "value"]), no_auth());
+    assert_eq!(client.call(&["AUTH", "service", "wrong"]), wrong_pass());
+    assert_eq!(client.call(&["AUTH", "service", "token"]), ok());
+    assert_eq!(client.call(&["SET", "key", "value"]), ok());
+    assert_eq!(client.call(&["GET", "key"]), bulk("value"));
+
+    let mut other = Client::connect(port);
+    assert_eq!(other.call(&["GET", "key"]), no_auth());
+}
@@ -0,0 +1,95 @@
+// BGSAVE: the dataset as it was when the BGSAVE came in, written out while writes go on.
+
+mod common;
+
+use std::{path::Path, thread::sleep, time::Duration};
+
+use common::{bulk, ok, simple, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// Until the file a BGSAVE renames into place is there.
+fn wait_for_file(path: &Path) {
+    for _ in 0..100 {
+        if path.exists() {
+            return;
+        }
+        sleep(Duration::from_millis(50));
+    }
+
+    panic!("{} never showed up", path.display());
+}
+
+#[test]
+fn bgsave_writes_the_dataset_as_it_was() {
+    let dir = temp_dir("bgsave");
+    let args = ["--dir", dir.to_str().unwrap(), "--dbfilename", "dump.rdb"];
+
+    {
+        let server = Server::start(&args);
+        let mut client = server.connect();
+
+        assert_eq!(client.call(&["SET", "a", "1"]), ok());
+        assert_eq!(client.call(&["SET", "b", "2"]), ok());
+        assert_eq!(
+            client.call(&["HSET", "h", "field", "value"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(client.call(&["RPUSH", "l", "x"]), RespValue::Integer(1));
+
+        assert_eq!(
+            client.call(&["BGSAVE"]),
+            simple("Background saving started")
+        );
+
+        // none of these are in the file, whenever it gets written
+        assert_eq!(client.call(&["APPEND", "a", "0"]), RespValue::Integer(2));
+        assert_eq!(client.call(&["DEL", "b"]), RespValue::Integer(1));
+        assert_eq!(
+            client.call(&["HSET", "h", "field", "changed"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(client.call(&["RPUSH", "l", "y"]), RespValue::Integer(2));
+        assert_eq!(client.call(&["SET", "c", "3"]), ok());
+
+        wait_for_file(&dir.join("dump.rdb"));
+
+        // and the server has them all the same
+        assert_eq!(client.call(&["GET", "a"]), bulk("10"));
+        assert_eq!(client.call(&["LLEN", "l"]), RespValue::Integer(2));
+    }
+
+    let server = Server::start(&args);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["GET", "a"]), bulk("1"));
+    assert_eq!(client.call(&["GET", "b"]), bulk("2"));
+    assert_eq!(client.call(&["HGET", "h", "field"]), bulk("value"));
+    assert_eq!(client.call(&["LLEN", "l"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["EXISTS", "c"]), RespValue::Integer(0));
+}
+
+#[test]
+fn a_save_after_a_bgsave_has_the_last_word() {
+    let dir = temp_dir("bgsave-then-save");
+    let args = ["--dir", dir.to_str().unwrap(), "--dbfilename", "dump.rdb"];
+
+    {
+        let server = Server::start(&args);
+        let mut client = server.connect();
+
+        assert_eq!(client.call(&["SET", "a", "old"]), ok());
+        assert_eq!(
+            client.call(&["BGSAVE"]),
+            simple("Background saving started")
+        );
+        assert_eq!(client.call(&["SET", "a", "new"]), ok());
+
+        // waits for the BGSAVE, so its older snapshot can't land on top
+        assert_eq!(client.call(&["SAVE"]), ok());
+    }
+
+    let server = Server::start(&args);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["GET", "a"]), bulk("new"));
+}
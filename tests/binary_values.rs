@@ -0,0 +1,117 @@
+// String values are bytes, whatever the client sent: SET, APPEND and SETRANGE store them as they
+// came and every read gives them back the same way. Keys, names and the members of collections
+// are text, and bytes that aren't UTF-8 there are refused.
+
+mod common;
+
+use common::{bulk, bulk_bytes, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn values_that_are_not_utf8_come_back_as_sent() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call_bytes(&[b"SET", b"bin", b"\xff\xfe"]), ok());
+    assert_eq!(client.call(&["GET", "bin"]), bulk_bytes(b"\xff\xfe"));
+    assert_eq!(client.call(&["STRLEN", "bin"]), RespValue::Integer(2));
+    assert_eq!(
+        client.call(&["MGET", "bin", "missing"]),
+        RespValue::Array(vec![bulk_bytes(b"\xff\xfe"), RespValue::Null])
+    );
+
+    assert_eq!(
+        client.call_bytes(&[b"APPEND", b"bin", b"\x00\x80"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(
+        client.call_bytes(&[b"SETRANGE", b"bin", b"1", b"\xc3"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(
+        client.call(&["GET", "bin"]),
+        bulk_bytes(b"\xff\xc3\x00\x80")
+    );
+    assert_eq!(
+        client.call(&["GETRANGE", "bin", "1", "2"]),
+        bulk_bytes(b"\xc3\x00")
+    );
+
+    // with GET, the value it replaced
+    assert_eq!(
+        client.call_bytes(&[b"SET", b"bin", b"\xfa", b"GET"]),
+        bulk_bytes(b"\xff\xc3\x00\x80")
+    );
+    assert_eq!(
+        client.call(&["INCR", "bin"]),
+        RespValue::Error("ERR value is not an integer or out of range".to_string())
+    );
+}
+
+#[test]
+fn ranges_are_counted_in_bytes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // two bytes each
+    assert_eq!(client.call(&["SET", "word", "\u{e9}t\u{e9}"]), ok());
+    assert_eq!(client.call(&["STRLEN", "word"]), RespValue::Integer(5));
+
+    assert_eq!(
+        client.call(&["SETRANGE", "word", "1", "x"]),
+        RespValue::Integer(5)
+    );
+    assert_eq!(client.call(&["GET", "word"]), bulk_bytes(b"\xc3xt\xc3\xa9"));
+    assert_eq!(client.call(&["APPEND", "word", "!"]), RespValue::Integer(6));
+}
+
+#[test]
+fn values_in_a_transaction() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(
+        client.call_bytes(&[b"SET", b"bin", b"\xff"]),
+        simple("QUEUED")
+    );
+    assert_eq!(
+        client.call_bytes(&[b"APPEND", b"bin", b"\xfe"]),
+        simple("QUEUED")
+    );
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Array(vec![ok(), RespValue::Integer(2)])
+    );
+
+    assert_eq!(client.call(&["GET", "bin"]), bulk_bytes(b"\xff\xfe"));
+}
+
+#[test]
+fn everything_else_has_to_be_utf8() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    let refused = RespValue::Error(
+        "ERR invalid UTF-8 in an argument, only string values may hold any bytes".to_string(),
+    );
+
+    assert_eq!(client.call_bytes(&[b"SET", b"\xff", b"value"]), refused);
+    assert_eq!(client.call_bytes(&[b"GET", b"\xff"]), refused);
+    assert_eq!(
+        client.call_bytes(&[b"SETRANGE", b"key", b"\xff", b"value"]),
+        refused
+    );
+    assert_eq!(
+        client.call_bytes(&[b"HSET", b"hash", b"field", b"\xff"]),
+        refused
+    );
+
+    // the same bytes as the value are fine, only the key was refused
+    assert_eq!(client.call_bytes(&[b"SET", b"\xff", b"\xff"]), refused);
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(0));
+
+    // the connection goes on
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["GET", "foo"]), bulk("bar"));
+}
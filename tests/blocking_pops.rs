@@ -0,0 +1,182 @@
+// BLPOP and BRPOP: a pop that waits for a push when there is nothing to pop. The longest waiting
+// client is served first, a blocked client runs nothing else meanwhile, and the replicas get the
+// pops it came down to.
+
+mod common;
+
+use std::{thread::sleep, time::Duration};
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn popped(key: &str, value: &str) -> RespValue {
+    RespValue::Array(vec![bulk(key), bulk(value)])
+}
+
+// Long enough for a request sent on another connection to have blocked.
+fn let_it_block() {
+    sleep(Duration::from_millis(100));
+}
+
+#[test]
+fn pops_right_away_when_there_is_something_to_pop() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["RPUSH", "list", "a", "b", "c"]),
+        RespValue::Integer(3)
+    );
+
+    // the first of the keys that has a list
+    assert_eq!(
+        client.call(&["BLPOP", "missing", "list", "0"]),
+        popped("list", "a")
+    );
+    assert_eq!(client.call(&["BRPOP", "list", "0"]), popped("list", "c"));
+    assert_eq!(client.call(&["BLPOP", "list", "0.5"]), popped("list", "b"));
+    assert_eq!(client.call(&["EXISTS", "list"]), RespValue::Integer(0));
+
+    assert_eq!(client.call(&["SET", "string", "value"]), ok());
+    assert_eq!(
+        client.call(&["BLPOP", "string", "0"]),
+        RespValue::Error(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["BLPOP", "list", "-1"]),
+        RespValue::Error("ERR timeout is negative".to_string())
+    );
+    assert_eq!(
+        client.call(&["BLPOP", "list", "soon"]),
+        RespValue::Error("ERR timeout is not a float or out of range".to_string())
+    );
+    assert_eq!(
+        client.call(&["BLPOP", "list"]),
+        RespValue::Error("ERR wrong number of arguments for 'blpop' command".to_string())
+    );
+}
+
+#[test]
+fn a_push_serves_the_longest_waiting_client() {
+    let server = Server::start(&[]);
+    let mut first = server.connect();
+    let mut second = server.connect();
+    let mut pusher = server.connect();
+
+    first.send(&["BLPOP", "queue", "0"]);
+    let_it_block();
+    second.send(&["BRPOP", "other", "queue", "0"]);
+    let_it_block();
+
+    // the push is answered with the length before the waiters took their elements
+    assert_eq!(pusher.call(&["RPUSH", "queue", "a"]), RespValue::Integer(1));
+    assert_eq!(first.receive(), popped("queue", "a"));
+    assert_eq!(pusher.call(&["EXISTS", "queue"]), RespValue::Integer(0));
+
+    assert_eq!(
+        pusher.call(&["RPUSH", "queue", "b", "c"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(second.receive(), popped("queue", "c"));
+    assert_eq!(
+        pusher.call(&["LRANGE", "queue", "0", "-1"]),
+        RespValue::Array(vec![bulk("b")])
+    );
+
+    // served once, so a push to its other key is left alone
+    assert_eq!(pusher.call(&["RPUSH", "other", "d"]), RespValue::Integer(1));
+    assert_eq!(pusher.call(&["LLEN", "other"]), RespValue::Integer(1));
+}
+
+#[test]
+fn a_blocked_client_runs_nothing_else_until_it_is_served() {
+    let server = Server::start(&[]);
+    let mut blocked = server.connect();
+    let mut pusher = server.connect();
+
+    blocked.send(&["BLPOP", "queue", "0"]);
+    blocked.send(&["PING"]);
+    blocked.send(&["RPUSH", "mine", "x"]);
+    let_it_block();
+
+    // nothing it sent after the BLPOP has run yet
+    assert_eq!(pusher.call(&["EXISTS", "mine"]), RespValue::Integer(0));
+
+    assert_eq!(pusher.call(&["LPUSH", "queue", "a"]), RespValue::Integer(1));
+    assert_eq!(blocked.receive(), popped("queue", "a"));
+    assert_eq!(blocked.receive(), simple("PONG"));
+    assert_eq!(blocked.receive(), RespValue::Integer(1));
+}
+
+#[test]
+fn a_timeout_replies_with_nil() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    client.send(&["BLPOP", "queue", "0.1"]);
+    client.send(&["PING"]);
+    assert_eq!(client.receive(), RespValue::NullArray);
+    assert_eq!(client.receive(), simple("PONG"));
+
+    // it stopped waiting, so the element stays where it is
+    assert_eq!(client.call(&["RPUSH", "queue", "a"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["LLEN", "queue"]), RespValue::Integer(1));
+
+    // inside a transaction it can't block, there is nothing to pop right now
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["BRPOP", "missing", "0"]), simple("QUEUED"));
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Array(vec![RespValue::NullArray])
+    );
+}
+
+#[test]
+fn a_client_that_hung_up_is_not_served() {
+    let server = Server::start(&[]);
+    let mut pusher = server.connect();
+
+    {
+        let mut gone = server.connect();
+        gone.send(&["BLPOP", "queue", "0"]);
+        let_it_block();
+    }
+    let_it_block();
+
+    assert_eq!(pusher.call(&["RPUSH", "queue", "a"]), RespValue::Integer(1));
+    assert_eq!(pusher.call(&["LLEN", "queue"]), RespValue::Integer(1));
+}
+
+#[test]
+fn replicas_get_the_pops() {
+    let master = Server::start(&[]);
+    let mut blocked = master.connect();
+    let mut pusher = master.connect();
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["PING"], simple("PONG"));
+
+    blocked.send(&["BLPOP", "queue", "0"]);
+    let_it_block();
+
+    assert_eq!(
+        pusher.call(&["RPUSH", "queue", "a", "b", "c"]),
+        RespValue::Integer(3)
+    );
+    assert_eq!(blocked.receive(), popped("queue", "a"));
+    assert_eq!(blocked.call(&["BRPOP", "queue", "0"]), popped("queue", "c"));
+
+    to_replica.wait_for(
+        &["LRANGE", "queue", "0", "-1"],
+        RespValue::Array(vec![bulk("b")]),
+    );
+
+    // a replica takes no writes of its own, blocking or not
+    assert!(matches!(
+        to_replica.call(&["BLPOP", "queue", "0"]),
+        RespValue::Error(e) if e.starts_with("READONLY")
+    ));
+}
@@ -0,0 +1,187 @@
+// CLIENT LIST shows what is waiting in each connection's output buffer, and maxmemory-clients
+// disconnects the client with the biggest one once they add up to more than the limit.
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use common::{simple, Client, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// CLIENT LIST as field maps, keyed by client id.
+fn client_list(client: &mut Client) -> HashMap<String, HashMap<String, String>> {
+    filtered_client_list(client, &[])
+}
+
+// CLIENT LIST followed by a filter, TYPE or ID.
+fn filtered_client_list(
+    client: &mut Client,
+    filter: &[&str],
+) -> HashMap<String, HashMap<String, String>> {
+    let args: Vec<&str> = ["CLIENT", "LIST"].iter().chain(filter).copied().collect();
+    let RespValue::BulkString(Some(list)) = client.call(&args) else {
+        panic!("CLIENT LIST replies with a bulk string");
+    };
+
+    std::str::from_utf8(&list)
+        .expect("CLIENT LIST is text")
+        .lines()
+        .map(|line| {
+            let fields: HashMap<String, String> = line
+                .split(' ')
+                .filter_map(|field| field.split_once('='))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+
+            (fields["id"].clone(), fields)
+        })
+        .collect()
+}
+
+fn client_id(client: &mut Client) -> String {
+    match client.call(&["CLIENT", "ID"]) {
+        RespValue::Integer(id) => id.to_string(),
+        other => panic!("unexpected CLIENT ID reply: {:?}", other),
+    }
+}
+
+#[test]
+fn client_list_has_a_line_per_connection() {
+    let server = Server::start(&[]);
+    let mut first = server.connect();
+    let mut second = server.connect();
+
+    let first_id = client_id(&mut first);
+    let second_id = client_id(&mut second);
+    assert!(first_id.parse::<u64>().unwrap() < second_id.parse::<u64>().unwrap());
+
+    assert_eq!(second.call(&["SELECT", "3"]), simple("OK"));
+
+    let clients = client_list(&mut first);
+    for (id, db) in [(&first_id, "0"), (&second_id, "3")] {
+        let fields = &clients[id];
+        assert_eq!(fields["db"], db);
+        assert_eq!(fields["flags"], "N");
+        assert_eq!(fields["multi"], "-1");
+        assert_eq!(fields["oll"], "0");
+        assert_eq!(fields["omem"], "0");
+    }
+    assert!(clients[&first_id]["addr"].starts_with("127.0.0.1:"));
+
+    // gone once the server notices the hangup
+    drop(second);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client_list(&mut first).contains_key(&second_id) {
+        assert!(
+            Instant::now() < deadline,
+            "client {second_id} is still listed"
+        );
+        sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn client_list_filters_by_type_and_id() {
+    let server = Server::start(&[]);
+    let mut normal = server.connect();
+    let mut subscriber = server.connect();
+    let mut other = server.connect();
+
+    let normal_id = client_id(&mut normal);
+    let subscriber_id = client_id(&mut subscriber);
+    let other_id = client_id(&mut other);
+
+    subscriber.call(&["SUBSCRIBE", "news"]);
+
+    let pubsub = filtered_client_list(&mut normal, &["TYPE", "pubsub"]);
+    assert_eq!(pubsub.keys().collect::<Vec<_>>(), vec![&subscriber_id]);
+    assert_eq!(pubsub[&subscriber_id]["flags"], "P");
+
+    let mut normals: Vec<String> = filtered_client_list(&mut normal, &["TYPE", "Normal"])
+        .into_keys()
+        .collect();
+    normals.sort();
+    let mut expected = vec![normal_id.clone(), other_id.clone()];
+    expected.sort();
+    assert_eq!(normals, expected);
+
+    assert!(filtered_client_list(&mut normal, &["TYPE", "replica"]).is_empty());
+    assert!(filtered_client_list(&mut normal, &["TYPE", "master"]).is_empty());
+
+    let by_id = filtered_client_list(&mut normal, &["ID", &other_id, &subscriber_id, "999999"]);
+    let mut ids: Vec<String> = by_id.into_keys().collect();
+    ids.sort();
+    let mut expected = vec![other_id, subscriber_id];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    assert_eq!(
+        normal.call(&["CLIENT", "LIST", "TYPE", "bogus"]),
+        RespValue::Error("ERR Unknown client type 'bogus'".to_string())
+    );
+    assert_eq!(
+        normal.call(&["CLIENT", "LIST", "ID", "0"]),
+        RespValue::Error("ERR Invalid client ID".to_string())
+    );
+}
+
+#[test]
+fn output_that_is_not_read_shows_up_in_client_list() {
+    let server = Server::start(&["--pubsub-queue-limit", "100000"]);
+    let mut slow = server.connect();
+    let mut publisher = server.connect();
+
+    let slow_id = client_id(&mut slow);
+    slow.call(&["SUBSCRIBE", "firehose"]);
+
+    // far more than the socket buffers take, the rest has to wait on the server
+    let payload = "x".repeat(64 * 1024);
+    for _ in 0..200 {
+        assert_eq!(
+            publisher.call(&["PUBLISH", "firehose", &payload]),
+            RespValue::Integer(1)
+        );
+    }
+
+    let clients = client_list(&mut publisher);
+    let fields = &clients[&slow_id];
+    let oll: usize = fields["oll"].parse().unwrap();
+    let omem: usize = fields["omem"].parse().unwrap();
+
+    assert!(oll > 0, "{:?}", fields);
+    assert!(omem > oll * payload.len(), "{:?}", fields);
+    assert_eq!(fields["tot-mem"], fields["omem"]);
+}
+
+#[test]
+fn maxmemory_clients_disconnects_the_biggest_buffer() {
+    let server = Server::start(&[
+        "--pubsub-queue-limit",
+        "100000",
+        "--maxmemory-clients",
+        "1048576",
+    ]);
+    let mut slow = server.connect();
+    let mut idle = server.connect();
+    let mut publisher = server.connect();
+
+    slow.call(&["SUBSCRIBE", "firehose"]);
+
+    // slow never reads, its buffer grows until it alone is over the limit
+    let payload = "x".repeat(64 * 1024);
+    let mut published = 0;
+    while publisher.call(&["PUBLISH", "firehose", &payload]) == RespValue::Integer(1) {
+        published += 1;
+        assert!(published < 2000, "the slow subscriber was never dropped");
+    }
+
+    slow.read_until_closed();
+
+    // nobody else had anything waiting
+    assert_eq!(idle.call(&["PING"]), simple("PONG"));
+    assert_eq!(publisher.call(&["PING"]), simple("PONG"));
+}
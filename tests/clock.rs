@@ -0,0 +1,57 @@
+// A store opened with a ManualClock holds deadlines against it, so expiry only happens when the
+// test moves the clock on, to the millisecond, and never because the test machine was slow.
+
+use std::{sync::Arc, time::Duration};
+
+use redis_starter_rust::{clock::ManualClock, engine::Engine};
+
+#[tokio::test]
+async fn keys_expire_when_the_clock_reaches_their_deadline() {
+    let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+    let engine = Engine::open_with_clock(clock.clone(), None, None)
+        .await
+        .unwrap();
+
+    engine.set("foo", "bar").await.unwrap();
+    assert!(engine.expire("foo", Duration::from_secs(10)).await.unwrap());
+    assert_eq!(
+        engine.ttl("foo").await.unwrap(),
+        Some(Duration::from_secs(10))
+    );
+
+    clock.advance(Duration::from_millis(9_999));
+    assert_eq!(engine.get("foo").await.unwrap(), Some(b"bar".to_vec()));
+    assert_eq!(
+        engine.ttl("foo").await.unwrap(),
+        Some(Duration::from_millis(1))
+    );
+
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(engine.get("foo").await.unwrap(), None);
+    assert_eq!(engine.ttl("foo").await.unwrap(), None);
+    assert!(engine.keys("*").await.unwrap().is_empty());
+    assert!(!engine.expire("foo", Duration::from_secs(10)).await.unwrap());
+}
+
+#[tokio::test]
+async fn nothing_expires_while_the_clock_stands_still() {
+    let clock = Arc::new(ManualClock::starting_now());
+    let engine = Engine::open_with_clock(clock.clone(), None, None)
+        .await
+        .unwrap();
+
+    engine.set("soon", "1").await.unwrap();
+    engine.set("kept", "1").await.unwrap();
+    assert!(engine
+        .expire("soon", Duration::from_millis(1))
+        .await
+        .unwrap());
+
+    // well past the deadline by the system clock, and by the active expiry cycle's
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(engine.get("soon").await.unwrap(), Some(b"1".to_vec()));
+    assert_eq!(engine.ttl("kept").await.unwrap(), None);
+
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(engine.keys("*").await.unwrap(), vec!["kept".to_string()]);
+}
@@ -0,0 +1,181 @@
+// INFO commandstats counts the calls to each command, how long they took and which of them were
+// refused or failed. INFO latencystats has their percentiles. CONFIG RESETSTAT starts both over.
+
+mod common;
+
+use common::{ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn section(client: &mut common::Client, name: &str) -> String {
+    let RespValue::BulkString(Some(section)) = client.call(&["INFO", name]) else {
+        panic!("INFO {} replies with a bulk string", name);
+    };
+
+    String::from_utf8(section.to_vec()).expect("INFO is text")
+}
+
+// The fields of the line for command as name=value pairs.
+fn line(section: &str, prefix: &str) -> Option<Vec<(String, String)>> {
+    let line = section.lines().find_map(|line| line.strip_prefix(prefix))?;
+
+    Some(
+        line.split(',')
+            .map(|field| {
+                let (name, value) = field.split_once('=').expect("name=value");
+                (name.to_string(), value.to_string())
+            })
+            .collect(),
+    )
+}
+
+fn field(fields: &[(String, String)], name: &str) -> String {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value.clone())
+        .unwrap_or_else(|| panic!("{} in {:?}", name, fields))
+}
+
+fn cmdstat(client: &mut common::Client, command: &str) -> Option<Vec<(String, String)>> {
+    line(
+        &section(client, "commandstats"),
+        &format!("cmdstat_{}:", command),
+    )
+}
+
+#[test]
+fn calls_are_counted_per_command() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for n in 0..3 {
+        assert_eq!(client.call(&["SET", &format!("key:{}", n), "v"]), ok());
+    }
+    client.call(&["get", "key:0"]);
+
+    let set = cmdstat(&mut client, "set").expect("SET was called");
+    assert_eq!(field(&set, "calls"), "3");
+    assert_eq!(field(&set, "rejected_calls"), "0");
+    assert_eq!(field(&set, "failed_calls"), "0");
+
+    let usec: u64 = field(&set, "usec").parse().expect("a number");
+    let usec_per_call: f64 = field(&set, "usec_per_call").parse().expect("a number");
+    assert!((usec_per_call - usec as f64 / 3.0).abs() < 0.01);
+
+    // counted whatever case it came in
+    let get = cmdstat(&mut client, "get").expect("GET was called");
+    assert_eq!(field(&get, "calls"), "1");
+
+    // nothing for what was never called, or isn't a command
+    client.call(&["NOSUCHCOMMAND"]);
+    let stats = section(&mut client, "commandstats");
+    assert!(stats.starts_with("# Commandstats\r\n"), "{stats}");
+    assert!(!stats.contains("cmdstat_del:"), "{stats}");
+    assert!(!stats.contains("nosuchcommand"), "{stats}");
+}
+
+#[test]
+fn rejected_and_failed_calls_are_told_apart() {
+    let server = Server::start(&["--maxmemory", "10"]);
+    let mut client = server.connect();
+
+    // the wrong number of arguments never runs
+    assert!(matches!(client.call(&["GET"]), RespValue::Error(_)));
+    // a syntax error is the command failing
+    assert!(matches!(
+        client.call(&["SET", "foo", "bar", "EX", "soon"]),
+        RespValue::Error(_)
+    ));
+    // over maxmemory under noeviction the write is refused
+    assert_eq!(client.call(&["SET", "foo", "bar-bar-bar"]), ok());
+    assert!(matches!(
+        client.call(&["SET", "foo", "bar"]),
+        RespValue::Error(_)
+    ));
+
+    let get = cmdstat(&mut client, "get").expect("GET was called");
+    assert_eq!(field(&get, "calls"), "0");
+    assert_eq!(field(&get, "rejected_calls"), "1");
+
+    let set = cmdstat(&mut client, "set").expect("SET was called");
+    assert_eq!(field(&set, "calls"), "2");
+    assert_eq!(field(&set, "rejected_calls"), "1");
+    assert_eq!(field(&set, "failed_calls"), "1");
+
+    // GET never ran, so it has no latencies
+    let latencies = section(&mut client, "latencystats");
+    assert!(
+        !latencies.contains("latency_percentiles_usec_get:"),
+        "{latencies}"
+    );
+}
+
+#[test]
+fn exec_and_the_commands_it_runs_are_each_counted() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    client.call(&["SET", "foo", "bar"]);
+    client.call(&["SET", "bar", "baz"]);
+
+    // queued, not run yet
+    let mut other = server.connect();
+    assert!(cmdstat(&mut other, "set").is_none());
+
+    assert!(matches!(client.call(&["EXEC"]), RespValue::Array(_)));
+
+    assert_eq!(field(&cmdstat(&mut client, "exec").unwrap(), "calls"), "1");
+    assert_eq!(field(&cmdstat(&mut client, "set").unwrap(), "calls"), "2");
+}
+
+#[test]
+fn latencystats_has_percentiles_up_to_the_slowest_call() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for _ in 0..10 {
+        client.call(&["PING"]);
+    }
+
+    let latencies = section(&mut client, "latencystats");
+    assert!(latencies.starts_with("# Latencystats\r\n"), "{latencies}");
+
+    let ping = line(&latencies, "latency_percentiles_usec_ping:").expect("PING was called");
+    let percentiles: Vec<f64> = ["p50", "p99", "p99.9", "p100"]
+        .iter()
+        .map(|name| field(&ping, name).parse().expect("a number"))
+        .collect();
+
+    assert!(
+        percentiles.windows(2).all(|pair| pair[0] <= pair[1]),
+        "{:?}",
+        percentiles
+    );
+}
+
+#[test]
+fn config_resetstat_starts_over() {
+    let server = Server::start(&[
+        "--enable-debug-command",
+        "--maxmemory-policy",
+        "allkeys-random",
+    ]);
+    let mut client = server.connect();
+
+    client.call(&["PING"]);
+    client.call(&["GET"]);
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["DEBUG", "EVICT", "1"]), RespValue::Integer(1));
+    assert!(section(&mut client, "stats").contains("evicted_keys:1\r\n"));
+
+    assert_eq!(client.call(&["CONFIG", "RESETSTAT"]), ok());
+
+    // RESETSTAT itself is counted once done, like redis
+    let stats = section(&mut client, "commandstats");
+    let lines: Vec<&str> = stats.lines().skip(1).collect();
+    assert_eq!(lines.len(), 1, "{stats}");
+    assert!(lines[0].starts_with("cmdstat_config:calls=1,"), "{stats}");
+
+    assert!(section(&mut client, "stats").contains("evicted_keys:0\r\n"));
+}
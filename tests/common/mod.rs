@@ -0,0 +1,233 @@
+// Runs the real server binary, for tests about whole-server behavior (replication, persistence)
+// that a single actor can't show. Each Server gets its own free port, its own --dir unless the test
+// gives one, and is killed on drop.
+
+#![allow(dead_code)]
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use bytes::{Bytes, BytesMut};
+use redis_starter_rust::resp::{codec::RespCodec, value::RespValue};
+use tokio_util::codec::Decoder;
+
+pub struct Server {
+    child: Child,
+    pub port: u16,
+}
+
+impl Server {
+    /// Starts the server with the given extra arguments and waits until it accepts connections.
+    pub fn start(args: &[&str]) -> Self {
+        // the OS hands out a free port, released again right before the server binds it
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port")
+            .port();
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_redis-starter-rust"));
+        command.arg("--port").arg(port.to_string()).args(args);
+
+        // a full resync SAVEs first, and the default dir is shared by every server the tests run
+        if !args.contains(&"--dir") {
+            command
+                .arg("--dir")
+                .arg(temp_dir(&format!("server-{}", port)));
+        }
+
+        let child = command
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("server starts");
+
+        let server = Self { child, port };
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(Instant::now() < deadline, "server on {port} never came up");
+            sleep(Duration::from_millis(20));
+        }
+
+        server
+    }
+
+    /// "127.0.0.1 <port>", the way --replicaof wants it.
+    pub fn address(&self) -> String {
+        format!("127.0.0.1 {}", self.port)
+    }
+
+    pub fn connect(&self) -> Client {
+        Client::connect(self.port)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// One connection, one request at a time. Keeps its connection state (SELECT) between calls.
+pub struct Client {
+    stream: TcpStream,
+    buffer: BytesMut,
+}
+
+impl Client {
+    pub fn connect(port: u16) -> Self {
+        Self::over(TcpStream::connect(("127.0.0.1", port)).expect("connects"))
+    }
+
+    /// The next connection to listener, for a test that plays the server, a master to a replica.
+    pub fn accept(listener: &TcpListener) -> Self {
+        Self::over(listener.accept().expect("accepts").0)
+    }
+
+    fn over(stream: TcpStream) -> Self {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .expect("read timeout");
+
+        Self {
+            stream,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Sends the command and returns the reply.
+    pub fn call(&mut self, args: &[&str]) -> RespValue {
+        self.send(args);
+        self.receive()
+    }
+
+    /// Sends the command without waiting for anything to come back.
+    pub fn send(&mut self, args: &[&str]) {
+        let request = RespValue::array_from_slice(args)
+            .to_encoded_string()
+            .expect("request encodes");
+        self.stream
+            .write_all(request.as_bytes())
+            .expect("request is sent");
+    }
+
+    /// call() with arguments that may be any bytes, not only UTF-8.
+    pub fn call_bytes(&mut self, args: &[&[u8]]) -> RespValue {
+        let request = RespValue::Array(
+            args.iter()
+                .map(|arg| RespValue::BulkString(Some(Bytes::copy_from_slice(arg))))
+                .collect(),
+        );
+        self.send_bytes(&request.encode());
+        self.receive()
+    }
+
+    /// Writes bytes as they are, for requests sent in pieces or not sent whole.
+    pub fn send_bytes(&mut self, bytes: &[u8]) {
+        self.stream.write_all(bytes).expect("bytes are sent");
+    }
+
+    /// The next frame from the server, a reply or something pushed (a published message).
+    pub fn receive(&mut self) -> RespValue {
+        let mut codec = RespCodec::new();
+        loop {
+            if let Some(reply) = codec.decode(&mut self.buffer).expect("reply decodes") {
+                return reply;
+            }
+
+            let mut chunk = [0; 4096];
+            let read = self.stream.read(&mut chunk).expect("reply arrives");
+            assert!(read > 0, "connection closed while waiting for a reply");
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    /// Sends the command and returns the reply exactly as it came over the wire.
+    pub fn call_raw(&mut self, args: &[&str]) -> Vec<u8> {
+        self.send(args);
+
+        let mut codec = RespCodec::new();
+        loop {
+            // decoded from a copy only to find where the frame ends
+            let mut peek = self.buffer.clone();
+            if codec.decode(&mut peek).expect("reply decodes").is_some() {
+                let length = self.buffer.len() - peek.len();
+                return self.buffer.split_to(length).to_vec();
+            }
+
+            let mut chunk = [0; 4096];
+            let read = self.stream.read(&mut chunk).expect("reply arrives");
+            assert!(read > 0, "connection closed while waiting for a reply");
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    /// The next `count` bytes exactly as they came over the wire, for what isn't a RESP frame.
+    pub fn read_bytes(&mut self, count: usize) -> Vec<u8> {
+        while self.buffer.len() < count {
+            let mut chunk = [0; 4096];
+            let read = self.stream.read(&mut chunk).expect("bytes arrive");
+            assert!(read > 0, "connection closed while waiting for bytes");
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+
+        self.buffer.split_to(count).to_vec()
+    }
+
+    /// Reads and throws away everything until the server closes the connection.
+    pub fn read_until_closed(&mut self) {
+        let mut chunk = [0; 65536];
+        while self.stream.read(&mut chunk).expect("connection is closed") > 0 {}
+    }
+
+    /// Repeats the command until it replies with `expected`, for state that gets there asynchronously.
+    pub fn wait_for(&mut self, args: &[&str], expected: RespValue) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let reply = self.call(args);
+            if reply == expected {
+                return;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "{:?} still replies {:?}, expected {:?}",
+                args,
+                reply,
+                expected
+            );
+            sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+/// A fresh, empty directory for --dir.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("redis-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("temp dir");
+    dir
+}
+
+pub fn ok() -> RespValue {
+    RespValue::SimpleString("OK".to_string())
+}
+
+pub fn bulk(value: &str) -> RespValue {
+    RespValue::BulkString(Some(value.to_string().into()))
+}
+
+/// bulk() for a value that may be any bytes.
+pub fn bulk_bytes(value: &[u8]) -> RespValue {
+    RespValue::BulkString(Some(Bytes::copy_from_slice(value)))
+}
+
+pub fn simple(value: &str) -> RespValue {
+    RespValue::SimpleString(value.to_string())
+}
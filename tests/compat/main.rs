@@ -0,0 +1,208 @@
+// Replays fixtures of commands and the exact bytes redis replies with, so any difference in how
+// this server frames a reply shows up as a failing test instead of a confused client.
+//
+// A fixture in fixtures/ is a plain text script, run on one connection of a fresh server:
+//
+//   # a comment
+//   > SET foo bar          a command, arguments split on whitespace
+//   +OK\r\n                what redis replies, \r \n \\ and \xHH are escapes
+//   ~ +bar\r\n             optional, what this server replies instead (known drift)
+//   sleep 150              pause for that many milliseconds
+//
+// {any} in a reply stands for bytes that differ from run to run, like a replication id.
+//
+// A `~` line pins the current reply, so fixing the drift fails the fixture until the line is
+// removed, and the fixture then holds the server to what redis does.
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use std::{fs, path::Path, thread::sleep, time::Duration};
+
+use common::Server;
+
+const ANY: &[u8] = b"{any}";
+
+enum Step {
+    Call {
+        line: usize,
+        args: Vec<String>,
+        redis: Vec<u8>,
+        drift: Option<Vec<u8>>,
+    },
+    Sleep(Duration),
+}
+
+fn unescape(line: usize, text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut utf8 = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .unwrap_or_else(|_| panic!("line {line}: bad escape \\x{hex}"));
+                bytes.push(byte);
+            }
+            other => panic!("line {line}: unknown escape \\{:?}", other),
+        }
+    }
+
+    bytes
+}
+
+fn parse(fixture: &str) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut lines = fixture
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.trim_end()))
+        .filter(|(_, text)| !text.is_empty() && !text.starts_with('#'))
+        .peekable();
+
+    while let Some((line, text)) = lines.next() {
+        if let Some(milliseconds) = text.strip_prefix("sleep ") {
+            let milliseconds = milliseconds
+                .parse()
+                .unwrap_or_else(|_| panic!("line {line}: bad sleep"));
+            steps.push(Step::Sleep(Duration::from_millis(milliseconds)));
+            continue;
+        }
+
+        let command = text
+            .strip_prefix("> ")
+            .unwrap_or_else(|| panic!("line {line}: expected a command, got {text:?}"));
+
+        let (reply_line, reply) = lines
+            .next()
+            .unwrap_or_else(|| panic!("line {line}: command without a reply"));
+        assert!(
+            !reply.starts_with("> ") && !reply.starts_with("~ "),
+            "line {reply_line}: expected a reply, got {reply:?}"
+        );
+
+        let drift = match lines.peek() {
+            Some((drift_line, drift)) if drift.starts_with("~ ") => {
+                let drift = unescape(*drift_line, &drift[2..]);
+                lines.next();
+                Some(drift)
+            }
+            _ => None,
+        };
+
+        steps.push(Step::Call {
+            line,
+            args: command.split_whitespace().map(String::from).collect(),
+            redis: unescape(reply_line, reply),
+            drift,
+        });
+    }
+
+    steps
+}
+
+// Whether the reply matches the expected bytes, with {any} standing for one or more bytes.
+fn matches(expected: &[u8], reply: &[u8]) -> bool {
+    let mut pieces = split_any(expected);
+    let first = pieces.remove(0);
+
+    let Some(mut rest) = reply.strip_prefix(first) else {
+        return false;
+    };
+
+    let Some(last) = pieces.pop() else {
+        return rest.is_empty();
+    };
+
+    for piece in pieces {
+        // at least one byte for the {any} in front
+        match find(rest.get(1..).unwrap_or_default(), piece) {
+            Some(position) => rest = &rest[1 + position + piece.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() > last.len() && rest.ends_with(last)
+}
+
+fn split_any(expected: &[u8]) -> Vec<&[u8]> {
+    let mut pieces = Vec::new();
+    let mut rest = expected;
+
+    while let Some(position) = find(rest, ANY) {
+        pieces.push(&rest[..position]);
+        rest = &rest[position + ANY.len()..];
+    }
+    pieces.push(rest);
+
+    pieces
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn replay(name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/compat/fixtures")
+        .join(format!("{name}.txt"));
+    let fixture = fs::read_to_string(&path).expect("fixture is readable");
+
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for step in parse(&fixture) {
+        match step {
+            Step::Sleep(duration) => sleep(duration),
+            Step::Call {
+                line,
+                args,
+                redis,
+                drift,
+            } => {
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                let reply = client.call_raw(&args);
+                let expected = drift.as_ref().unwrap_or(&redis);
+
+                assert!(
+                    matches(expected, &reply),
+                    "{name}.txt line {line}: {:?} replied {:?}, expected {:?}{}",
+                    args,
+                    String::from_utf8_lossy(&reply),
+                    String::from_utf8_lossy(expected),
+                    match (&drift, matches(&redis, &reply)) {
+                        (Some(_), true) => ", it now matches redis so the ~ line can go",
+                        _ => "",
+                    }
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn strings() {
+    replay("strings");
+}
+
+#[test]
+fn expiry() {
+    replay("expiry");
+}
+
+#[test]
+fn replication_handshake() {
+    replay("replication_handshake");
+}
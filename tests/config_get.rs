@@ -0,0 +1,116 @@
+// CONFIG GET with several names, aliases and globs: one flat list of name/value pairs,
+// every parameter at most once, in the order the patterns matched them.
+
+use redis_starter_rust::{
+    handlers::config_command::ConfigCommandActorHandle, protocol::ConfigCommandParameter,
+};
+
+async fn config() -> ConfigCommandActorHandle {
+    let config = ConfigCommandActorHandle::new();
+
+    for (parameter, value) in [
+        (ConfigCommandParameter::Dir, "/tmp"),
+        (ConfigCommandParameter::DbFilename, "dump.rdb"),
+        (ConfigCommandParameter::Databases, "16"),
+        (ConfigCommandParameter::Replicaof, "127.0.0.1 6379"),
+    ] {
+        config
+            .set_value(parameter, value)
+            .await
+            .expect("config actor runs");
+    }
+
+    config
+}
+
+async fn get(config: &ConfigCommandActorHandle, patterns: &[&str]) -> Vec<(String, String)> {
+    config
+        .get_matching_values(patterns.iter().map(|p| p.to_string()).collect())
+        .await
+        .expect("config actor runs")
+}
+
+fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn replies_follow_pattern_order() {
+    let config = config().await;
+
+    assert_eq!(
+        get(&config, &["dbfilename", "dir"]).await,
+        pairs(&[("dbfilename", "dump.rdb"), ("dir", "/tmp")])
+    );
+    assert_eq!(
+        get(&config, &["dir", "dbfilename"]).await,
+        pairs(&[("dir", "/tmp"), ("dbfilename", "dump.rdb")])
+    );
+}
+
+#[tokio::test]
+async fn globs_are_merged_and_deduplicated() {
+    let config = config().await;
+
+    // d* matches dir, dbfilename and databases; the explicit names after it add nothing.
+    assert_eq!(
+        get(&config, &["d*", "dir", "databases", "appendonly"]).await,
+        pairs(&[
+            ("dir", "/tmp"),
+            ("dbfilename", "dump.rdb"),
+            ("databases", "16"),
+            ("appendonly", "no"),
+        ])
+    );
+
+    assert_eq!(
+        get(&config, &["appendonly", "*base?"]).await,
+        pairs(&[("appendonly", "no"), ("databases", "16")])
+    );
+}
+
+#[tokio::test]
+async fn aliases() {
+    let config = config().await;
+
+    // asked for by its old name, answered under that name
+    assert_eq!(
+        get(&config, &["slaveof"]).await,
+        pairs(&[("slaveof", "127.0.0.1 6379")])
+    );
+
+    // an alias and the name it stands for are still one parameter
+    assert_eq!(
+        get(&config, &["replicaof", "slaveof"]).await,
+        pairs(&[("replicaof", "127.0.0.1 6379")])
+    );
+
+    // a glob matching only the alias reports the current name
+    assert_eq!(
+        get(&config, &["slave*"]).await,
+        pairs(&[("replicaof", "127.0.0.1 6379")])
+    );
+}
+
+#[tokio::test]
+async fn names_are_case_insensitive() {
+    let config = config().await;
+
+    assert_eq!(get(&config, &["DIR"]).await, pairs(&[("dir", "/tmp")]));
+    assert_eq!(
+        get(&config, &["DB*"]).await,
+        pairs(&[("dbfilename", "dump.rdb")])
+    );
+}
+
+#[tokio::test]
+async fn no_match_is_empty() {
+    let config = config().await;
+
+    assert!(get(&config, &["maxmemory*", "nosuchparameter"])
+        .await
+        .is_empty());
+}
@@ -0,0 +1,190 @@
+// Memory sizes with units, on the command line and through CONFIG SET, always kept and reported
+// in bytes. CONFIG SET checks every value before it sets any.
+
+mod common;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::{
+    resp::value::RespValue,
+    units::{parse_memory, parse_nonzero_memory},
+};
+
+#[test]
+fn units() {
+    let cases: &[(&str, u64)] = &[
+        ("0", 0),
+        ("100", 100),
+        ("100b", 100),
+        ("1k", 1000),
+        ("1kb", 1024),
+        ("100mb", 100 * 1024 * 1024),
+        ("100m", 100_000_000),
+        ("2gb", 2 * 1024 * 1024 * 1024),
+        ("2g", 2_000_000_000),
+        // in any case
+        ("2GB", 2 * 1024 * 1024 * 1024),
+        ("64Kb", 64 * 1024),
+    ];
+
+    for (value, bytes) in cases {
+        assert_eq!(parse_memory(value), Ok(*bytes), "{}", value);
+    }
+}
+
+#[test]
+fn malformed_sizes() {
+    for value in [
+        "",
+        "mb",
+        "-1",
+        "+1",
+        "1.5gb",
+        "10 mb",
+        "10tb",
+        "10bytes",
+        "gb10",
+        // past what a u64 holds, with and without the unit
+        "18446744073709551616",
+        "17179869184gb",
+    ] {
+        assert_eq!(
+            parse_memory(value),
+            Err("argument must be a memory value".to_string()),
+            "{}",
+            value
+        );
+    }
+
+    assert!(parse_nonzero_memory("0kb").is_err());
+    assert_eq!(parse_nonzero_memory("1"), Ok(1));
+}
+
+#[test]
+fn command_line_sizes_are_reported_in_bytes() {
+    let server = Server::start(&["--maxmemory", "100mb", "--proto-max-bulk-len", "1k"]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxmemory", "proto-max-bulk-len"]),
+        RespValue::Array(vec![
+            bulk("maxmemory"),
+            bulk("104857600"),
+            bulk("proto-max-bulk-len"),
+            bulk("1000"),
+        ])
+    );
+
+    // the defaults the same way
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "client-query-buffer-limit"]),
+        RespValue::Array(vec![bulk("client-query-buffer-limit"), bulk("1073741824")])
+    );
+}
+
+#[test]
+fn config_set_takes_units() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["CONFIG", "SET", "maxmemory", "2gb"]), ok());
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxmemory"]),
+        RespValue::Array(vec![bulk("maxmemory"), bulk("2147483648")])
+    );
+
+    assert_eq!(
+        client.call(&[
+            "CONFIG",
+            "SET",
+            "MAXMEMORY",
+            "0",
+            "maxmemory-policy",
+            "ALLKEYS-RANDOM"
+        ]),
+        ok()
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxmemory", "maxmemory-policy"]),
+        RespValue::Array(vec![
+            bulk("maxmemory"),
+            bulk("0"),
+            bulk("maxmemory-policy"),
+            bulk("allkeys-random"),
+        ])
+    );
+}
+
+#[test]
+fn config_set_limits_apply_right_away() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "proto-max-bulk-len", "4b"]),
+        ok()
+    );
+    assert!(matches!(
+        client.call(&["SET", "foo", "too long"]),
+        RespValue::Error(_)
+    ));
+    assert_eq!(client.call(&["SET", "foo", "fits"]), ok());
+}
+
+#[test]
+fn config_set_refusals() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "maxmemory", "100tb"]),
+        RespValue::Error(
+            "ERR CONFIG SET failed (possibly related to argument 'maxmemory') - argument must be a memory value"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "proto-max-bulk-len", "0"]),
+        RespValue::Error(
+            "ERR CONFIG SET failed (possibly related to argument 'proto-max-bulk-len') - 0 is not allowed, the size has to be at least 1 byte"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "no-such-thing", "1"]),
+        RespValue::Error(
+            "ERR Unknown option or number of arguments for CONFIG SET - 'no-such-thing'"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "databases", "32"]),
+        RespValue::Error(
+            "ERR CONFIG SET failed (possibly related to argument 'databases') - can't set immutable config"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "maxmemory", "1mb", "maxmemory", "2mb"]),
+        RespValue::Error(
+            "ERR CONFIG SET failed (possibly related to argument 'maxmemory') - duplicate parameter"
+                .to_string()
+        )
+    );
+
+    // one bad value and nothing is set
+    assert!(matches!(
+        client.call(&[
+            "CONFIG",
+            "SET",
+            "maxmemory",
+            "1mb",
+            "maxmemory-policy",
+            "lru"
+        ]),
+        RespValue::Error(_)
+    ));
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxmemory"]),
+        RespValue::Array(vec![bulk("maxmemory"), bulk("0")])
+    );
+}
@@ -0,0 +1,108 @@
+// COPY leaves an existing destination alone unless REPLACE is given, and the store decides that
+// in the same step as the write, so two connections can't both see the destination free.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn copy_keeps_an_existing_destination_without_replace() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "src", "one"]), ok());
+    assert_eq!(client.call(&["COPY", "src", "dst"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["GET", "dst"]), bulk("one"));
+
+    assert_eq!(client.call(&["SET", "src", "two"]), ok());
+    assert_eq!(client.call(&["COPY", "src", "dst"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["GET", "dst"]), bulk("one"));
+
+    assert_eq!(
+        client.call(&["COPY", "src", "dst", "REPLACE"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["GET", "dst"]), bulk("two"));
+
+    assert_eq!(
+        client.call(&["COPY", "missing", "dst"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(
+        client.call(&["COPY", "src", "src"]),
+        RespValue::Error("ERR source and destination objects are the same".to_string())
+    );
+}
+
+#[test]
+fn copy_to_another_database() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "src", "v"]), ok());
+    assert_eq!(
+        client.call(&["COPY", "src", "src", "DB", "1"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["COPY", "src", "src", "DB", "16"]),
+        RespValue::Error("ERR DB index is out of range".to_string())
+    );
+
+    assert_eq!(client.call(&["SELECT", "1"]), ok());
+    assert_eq!(client.call(&["GET", "src"]), bulk("v"));
+}
+
+#[test]
+fn copy_carries_the_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "src", "v", "PX", "100"]), ok());
+    assert_eq!(client.call(&["COPY", "src", "dst"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["GET", "dst"]), bulk("v"));
+
+    thread::sleep(Duration::from_millis(200));
+
+    assert_eq!(client.call(&["GET", "dst"]), RespValue::Null);
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(0));
+}
+
+#[test]
+fn concurrent_copies_write_the_destination_once() {
+    let server = Server::start(&[]);
+    let mut setup = server.connect();
+
+    let mut clients: Vec<_> = (0..8).map(|_| server.connect()).collect();
+    for (i, client) in clients.iter_mut().enumerate() {
+        assert_eq!(
+            client.call(&["SET", &format!("src{i}"), &i.to_string()]),
+            ok()
+        );
+    }
+
+    let copied: i64 = thread::scope(|scope| {
+        clients
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut client)| {
+                scope.spawn(move || client.call(&["COPY", &format!("src{i}"), "dst"]))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|copy| match copy.join().unwrap() {
+                RespValue::Integer(copied) => copied,
+                other => panic!("COPY replied {:?}", other),
+            })
+            .sum()
+    });
+
+    assert_eq!(copied, 1);
+    assert!(matches!(
+        setup.call(&["GET", "dst"]),
+        RespValue::BulkString(Some(_))
+    ));
+}
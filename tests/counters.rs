@@ -0,0 +1,132 @@
+// INCR, DECR, INCRBY and DECRBY count in the string value of a key, from 0 when there is none.
+// A value that isn't an integer, or a sum that doesn't fit in 64 bits, is refused and left alone.
+
+mod common;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn not_an_integer() -> RespValue {
+    RespValue::Error("ERR value is not an integer or out of range".to_string())
+}
+
+fn overflow() -> RespValue {
+    RespValue::Error("ERR increment or decrement would overflow".to_string())
+}
+
+#[test]
+fn counting_up_and_down() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["INCR", "counter"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["INCR", "counter"]), RespValue::Integer(2));
+    assert_eq!(
+        client.call(&["INCRBY", "counter", "40"]),
+        RespValue::Integer(42)
+    );
+    assert_eq!(client.call(&["DECR", "counter"]), RespValue::Integer(41));
+    assert_eq!(
+        client.call(&["DECRBY", "counter", "50"]),
+        RespValue::Integer(-9)
+    );
+    assert_eq!(
+        client.call(&["INCRBY", "counter", "-1"]),
+        RespValue::Integer(-10)
+    );
+    assert_eq!(client.call(&["GET", "counter"]), bulk("-10"));
+
+    assert_eq!(client.call(&["DECR", "other"]), RespValue::Integer(-1));
+
+    // what SET stored counts too
+    assert_eq!(client.call(&["SET", "set", "100"]), ok());
+    assert_eq!(client.call(&["INCR", "set"]), RespValue::Integer(101));
+}
+
+#[test]
+fn only_integers_count() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for value in [
+        "foo",
+        "1.5",
+        " 1",
+        "1 ",
+        "+1",
+        "01",
+        "",
+        "9223372036854775808",
+    ] {
+        assert_eq!(client.call(&["SET", "foo", value]), ok());
+        assert_eq!(
+            client.call(&["INCR", "foo"]),
+            not_an_integer(),
+            "{:?}",
+            value
+        );
+        assert_eq!(client.call(&["GET", "foo"]), bulk(value));
+    }
+
+    assert_eq!(client.call(&["INCRBY", "counter", "one"]), not_an_integer());
+    assert_eq!(
+        client.call(&["DECRBY", "counter", "-9223372036854775808"]),
+        not_an_integer()
+    );
+    assert_eq!(client.call(&["GET", "counter"]), RespValue::Null);
+}
+
+#[test]
+fn overflowing_is_refused() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "max", "9223372036854775807"]), ok());
+    assert_eq!(client.call(&["INCR", "max"]), overflow());
+    assert_eq!(client.call(&["GET", "max"]), bulk("9223372036854775807"));
+
+    assert_eq!(client.call(&["SET", "min", "-9223372036854775808"]), ok());
+    assert_eq!(client.call(&["DECRBY", "min", "1"]), overflow());
+    assert_eq!(
+        client.call(&["INCRBY", "min", "9223372036854775807"]),
+        RespValue::Integer(-1)
+    );
+}
+
+#[test]
+fn the_deadline_stays() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "counter", "1", "PX", "100000"]), ok());
+    assert_eq!(client.call(&["INCR", "counter"]), RespValue::Integer(2));
+    assert_eq!(client.call(&["TTL", "counter"]), RespValue::Integer(100));
+}
+
+#[test]
+fn replicas_count_along() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["INCR", "counter"]), RespValue::Integer(1));
+    assert_eq!(
+        to_master.call(&["INCRBY", "counter", "9"]),
+        RespValue::Integer(10)
+    );
+    assert_eq!(
+        to_master.call(&["DECRBY", "counter", "3"]),
+        RespValue::Integer(7)
+    );
+
+    // refused, so not sent on
+    assert_eq!(to_master.call(&["SET", "text", "foo"]), ok());
+    assert_eq!(to_master.call(&["INCR", "text"]), not_an_integer());
+
+    assert_eq!(to_master.call(&["SET", "done", "1"]), ok());
+    to_replica.wait_for(&["GET", "done"], bulk("1"));
+    assert_eq!(to_replica.call(&["GET", "counter"]), bulk("7"));
+    assert_eq!(to_replica.call(&["GET", "text"]), bulk("foo"));
+}
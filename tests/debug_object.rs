@@ -0,0 +1,152 @@
+// DEBUG OBJECT reports serializedlength from the same encoder SAVE writes the RDB file with,
+// so the two have to agree byte for byte.
+
+mod common;
+
+use common::{ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn serializedlength(reply: RespValue) -> usize {
+    let RespValue::SimpleString(object) = reply else {
+        panic!("unexpected DEBUG OBJECT reply {:?}", reply);
+    };
+
+    object
+        .split(' ')
+        .find_map(|field| field.strip_prefix("serializedlength:"))
+        .expect("serializedlength field")
+        .parse()
+        .expect("serializedlength is a number")
+}
+
+#[test]
+fn matches_the_size_in_the_rdb_file() {
+    for value in ["bar", "12345", &"x".repeat(100), &"y".repeat(20000)] {
+        let dir = temp_dir("debug-object");
+        let server = Server::start(&[
+            "--dir",
+            dir.to_str().unwrap(),
+            "--dbfilename",
+            "dump.rdb",
+            "--enable-debug-command",
+        ]);
+        let mut client = server.connect();
+
+        assert_eq!(client.call(&["SET", "foo", value]), ok());
+        assert_eq!(client.call(&["SAVE"]), ok());
+
+        let length = serializedlength(client.call(&["DEBUG", "OBJECT", "foo"]));
+        let file = std::fs::read(dir.join("dump.rdb")).unwrap();
+
+        // header, SELECTDB 0, RESIZEDB 1 0, the value type, the key, and EOF with its checksum
+        let everything_else = 9 + 2 + 3 + 1 + 4 + 9;
+        assert_eq!(
+            file.len(),
+            everything_else + length,
+            "{} bytes",
+            value.len()
+        );
+    }
+}
+
+#[test]
+fn matches_the_size_in_the_rdb_file_for_collections() {
+    let big = "z".repeat(100);
+    let writes: [&[&str]; 5] = [
+        &["HSET", "foo", "a", "1", "b", &big],
+        &["RPUSH", "foo", "a", "b", "c"],
+        &["SADD", "foo", "1", "two"],
+        &["ZADD", "foo", "1.5", "a", "2", "b"],
+        &["XADD", "foo", "1-1", "a", "1"],
+    ];
+
+    for write in writes {
+        let dir = temp_dir("debug-object");
+        let server = Server::start(&[
+            "--dir",
+            dir.to_str().unwrap(),
+            "--dbfilename",
+            "dump.rdb",
+            "--enable-debug-command",
+        ]);
+        let mut client = server.connect();
+
+        assert!(!matches!(client.call(write), RespValue::Error(_)));
+        assert_eq!(client.call(&["SAVE"]), ok());
+
+        let length = serializedlength(client.call(&["DEBUG", "OBJECT", "foo"]));
+        let file = std::fs::read(dir.join("dump.rdb")).unwrap();
+
+        // header, SELECTDB 0, RESIZEDB 1 0, the value type, the key, and EOF with its checksum
+        let everything_else = 9 + 2 + 3 + 1 + 4 + 9;
+        assert_eq!(file.len(), everything_else + length, "{}", write[0]);
+    }
+}
+
+#[test]
+fn reports_the_encoding_of_collections() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+
+    let long = "x".repeat(65);
+    let many: Vec<String> = (0..600).map(|n| n.to_string()).collect();
+    let many: Vec<&str> = many.iter().map(String::as_str).collect();
+    let huge = "y".repeat(9000);
+
+    let cases: Vec<(Vec<&str>, &str)> = vec![
+        (vec!["HSET", "foo", "a", "1"], "encoding:listpack"),
+        (vec!["HSET", "foo", "a", &long], "encoding:hashtable"),
+        (vec!["RPUSH", "foo", "a", "b"], "encoding:listpack"),
+        (vec!["RPUSH", "foo", &huge], "encoding:quicklist"),
+        (vec!["SADD", "foo", "1", "2"], "encoding:intset"),
+        (vec!["SADD", "foo", "1", "a"], "encoding:listpack"),
+        ([&["SADD", "foo"][..], &many].concat(), "encoding:hashtable"),
+        (vec!["ZADD", "foo", "1", "a"], "encoding:listpack"),
+        (vec!["ZADD", "foo", "1", &long], "encoding:skiplist"),
+        (vec!["XADD", "foo", "*", "a", "1"], "encoding:stream"),
+    ];
+
+    for (write, encoding) in cases {
+        assert!(!matches!(client.call(&["DEL", "foo"]), RespValue::Error(_)));
+        assert!(!matches!(client.call(&write), RespValue::Error(_)));
+
+        let RespValue::SimpleString(object) = client.call(&["DEBUG", "OBJECT", "foo"]) else {
+            panic!("DEBUG OBJECT did not reply with a simple string");
+        };
+        assert!(
+            object.split(' ').any(|field| field == encoding),
+            "{:?}: {}",
+            write.get(..4),
+            object
+        );
+    }
+}
+
+#[test]
+fn reports_the_encoding() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+
+    for (value, encoding) in [
+        ("12345", "encoding:int"),
+        ("012345", "encoding:embstr"),
+        ("bar", "encoding:embstr"),
+        (&"x".repeat(45), "encoding:raw"),
+    ] {
+        assert_eq!(client.call(&["SET", "foo", value]), ok());
+
+        let RespValue::SimpleString(object) = client.call(&["DEBUG", "OBJECT", "foo"]) else {
+            panic!("DEBUG OBJECT did not reply with a simple string");
+        };
+        assert!(
+            object.split(' ').any(|field| field == encoding),
+            "{}",
+            object
+        );
+    }
+
+    assert_eq!(
+        client.call(&["DEBUG", "OBJECT", "missing"]),
+        RespValue::Error("ERR no such key".to_string())
+    );
+}
@@ -0,0 +1,88 @@
+// DEBUG RELOAD saves the RDB and loads it back in place, everything comes out of the round trip
+// the way it went in.
+
+mod common;
+
+use common::{bulk, bulk_bytes, ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn keyspace(client: &mut common::Client) -> String {
+    let RespValue::BulkString(Some(keyspace)) = client.call(&["INFO", "keyspace"]) else {
+        panic!("INFO keyspace replies with a bulk string");
+    };
+
+    let keyspace = String::from_utf8_lossy(&keyspace).to_string();
+
+    // avg_ttl moves with the clock, the rest of a line has to survive the reload
+    keyspace
+        .lines()
+        .map(|line| line.split(",avg_ttl").next().unwrap_or(line).to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn values_survive_a_reload() {
+    let dir = temp_dir("debug-reload");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "plain", "value"]), ok());
+    assert_eq!(client.call(&["SET", "number", "12345"]), ok());
+    assert_eq!(client.call(&["SET", "long", &"x".repeat(20000)]), ok());
+    assert_eq!(client.call(&["SET", "utf8", "caf\u{e9} \u{1f980}"]), ok());
+    assert_eq!(
+        client.call_bytes(&[b"SET", b"binary", b"\xff\x00\xfe"]),
+        ok()
+    );
+    assert_eq!(client.call(&["SET", "expiring", "soon", "EX", "100"]), ok());
+    assert_eq!(
+        client.call(&["SETRANGE", "padded", "3", "x"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(client.call(&["SELECT", "7"]), ok());
+    assert_eq!(client.call(&["SET", "elsewhere", "seven"]), ok());
+    assert_eq!(client.call(&["SELECT", "0"]), ok());
+
+    let before = keyspace(&mut client);
+
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+
+    assert_eq!(keyspace(&mut client), before);
+    assert_eq!(client.call(&["GET", "plain"]), bulk("value"));
+    assert_eq!(client.call(&["GET", "number"]), bulk("12345"));
+    assert_eq!(client.call(&["GET", "long"]), bulk(&"x".repeat(20000)));
+    assert_eq!(client.call(&["GET", "utf8"]), bulk("caf\u{e9} \u{1f980}"));
+    assert_eq!(client.call(&["GET", "binary"]), bulk_bytes(b"\xff\x00\xfe"));
+    assert_eq!(client.call(&["GET", "expiring"]), bulk("soon"));
+    assert_eq!(client.call(&["GET", "padded"]), bulk("\0\0\0x"));
+    assert_eq!(client.call(&["SELECT", "7"]), ok());
+    assert_eq!(client.call(&["GET", "elsewhere"]), bulk("seven"));
+}
+
+#[test]
+fn reload_replaces_what_is_in_memory() {
+    let dir = temp_dir("debug-reload-replace");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+    assert_eq!(client.call(&["DEL", "foo"]), RespValue::Integer(1));
+
+    // the file written by the first reload is saved over, foo doesn't come back
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(0));
+}
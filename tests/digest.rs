@@ -0,0 +1,133 @@
+// DEBUG DIGEST and DEBUG DIGEST-VALUE: a digest of the dataset and of single values that two
+// servers holding the same keys agree on, however they got there.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+const EMPTY: &str = "0000000000000000";
+
+fn digest(client: &mut common::Client) -> RespValue {
+    client.call(&["DEBUG", "DIGEST"])
+}
+
+#[test]
+fn same_keys_same_digest() {
+    let one = Server::start(&["--enable-debug-command"]);
+    let other = Server::start(&["--enable-debug-command"]);
+    let mut one = one.connect();
+    let mut other = other.connect();
+
+    assert_eq!(digest(&mut one), simple(EMPTY));
+
+    // the same keys, fields and elements, written in another order
+    assert_eq!(one.call(&["SET", "a", "1"]), ok());
+    assert_eq!(one.call(&["SET", "b", "2"]), ok());
+    assert_eq!(
+        one.call(&["HSET", "h", "x", "1", "y", "2"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(one.call(&["RPUSH", "l", "a", "b"]), RespValue::Integer(2));
+
+    assert_eq!(other.call(&["RPUSH", "l", "a", "b"]), RespValue::Integer(2));
+    assert_eq!(
+        other.call(&["HSET", "h", "y", "2", "x", "1"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(other.call(&["SET", "b", "2"]), ok());
+    assert_eq!(other.call(&["SET", "a", "1"]), ok());
+
+    let same = digest(&mut one);
+    assert_ne!(same, simple(EMPTY));
+    assert_eq!(digest(&mut other), same);
+
+    // a list's order is part of it
+    assert_eq!(other.call(&["DEL", "l"]), RespValue::Integer(1));
+    assert_eq!(other.call(&["RPUSH", "l", "b", "a"]), RespValue::Integer(2));
+    assert_ne!(digest(&mut other), same);
+    assert_eq!(other.call(&["DEL", "l"]), RespValue::Integer(1));
+    assert_eq!(other.call(&["RPUSH", "l", "a", "b"]), RespValue::Integer(2));
+    assert_eq!(digest(&mut other), same);
+
+    // so are deadlines
+    assert_eq!(
+        other.call(&["PEXPIREAT", "a", "99999999999999"]),
+        RespValue::Integer(1)
+    );
+    assert_ne!(digest(&mut other), same);
+
+    // and the database a key is in
+    assert_eq!(other.call(&["PERSIST", "a"]), RespValue::Integer(1));
+    assert_eq!(digest(&mut other), same);
+    assert_eq!(other.call(&["SWAPDB", "0", "1"]), ok());
+    assert_ne!(digest(&mut other), same);
+
+    // back to nothing once every key is gone
+    assert_eq!(
+        one.call(&["DEL", "a", "b", "h", "l"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(digest(&mut one), simple(EMPTY));
+}
+
+#[test]
+fn digest_value() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "a", "same"]), ok());
+    assert_eq!(client.call(&["SET", "b", "same"]), ok());
+    assert_eq!(client.call(&["EXPIRE", "b", "100"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["RPUSH", "l", "same"]), RespValue::Integer(1));
+
+    let RespValue::Array(digests) =
+        client.call(&["DEBUG", "DIGEST-VALUE", "a", "b", "l", "missing"])
+    else {
+        panic!("DEBUG DIGEST-VALUE replies with an array");
+    };
+
+    // only the value counts, not the key or its deadline, but a list isn't a string
+    assert_eq!(digests[0], digests[1]);
+    assert_ne!(digests[0], digests[2]);
+    assert_ne!(digests[0], simple(EMPTY));
+    assert_eq!(digests[3], simple(EMPTY));
+
+    assert_eq!(
+        client.call(&["DEBUG", "DIGEST-VALUE"]),
+        RespValue::Array(vec![])
+    );
+}
+
+#[test]
+fn a_replica_has_its_masters_digest() {
+    let master = Server::start(&["--enable-debug-command"]);
+    let mut to_master = master.connect();
+
+    // some in the snapshot of the full resync, the rest as commands after it
+    assert_eq!(to_master.call(&["SET", "before", "1", "EX", "100"]), ok());
+    assert_eq!(
+        to_master.call(&["HSET", "hash", "field", "value"]),
+        RespValue::Integer(1)
+    );
+
+    let replica = Server::start(&["--replicaof", &master.address(), "--enable-debug-command"]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["DEBUG", "DIGEST"], digest(&mut to_master));
+
+    assert_eq!(to_master.call(&["SELECT", "2"]), ok());
+    assert_eq!(to_master.call(&["SET", "after", "2"]), ok());
+    assert_eq!(
+        to_master.call(&["RPUSH", "list", "a", "b", "c"]),
+        RespValue::Integer(3)
+    );
+    assert_eq!(to_master.call(&["LPOP", "list"]), bulk("a"));
+    assert_eq!(
+        to_master.call(&["PEXPIRE", "after", "100000"]),
+        RespValue::Integer(1)
+    );
+
+    let expected = digest(&mut to_master);
+    assert_ne!(expected, simple(EMPTY));
+    to_replica.wait_for(&["DEBUG", "DIGEST"], expected);
+}
@@ -0,0 +1,133 @@
+// A replica loads the master's RDB as it comes off the link, however it is split up, and goes on
+// with the commands the master sends after it.
+
+mod common;
+
+use bytes::BytesMut;
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::{
+    handlers::set_command::SetCommandActorHandle, rdb::load::load_rdb_transfer,
+    resp::value::RespValue,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn rdb_arriving_a_byte_at_a_time() {
+    let set_command_actor_handle = SetCommandActorHandle::new();
+
+    // header, SELECTDB 1, foo => bar, EOF and a checksum of zeroes
+    let rdb = [
+        &b"REDIS0011"[..],
+        &[0xFE, 0x01],
+        &[0x00, 3],
+        b"foo",
+        &[3],
+        b"bar",
+        &[0xFF],
+        &[0; 8],
+    ]
+    .concat();
+
+    let command = b"*1\r\n$4\r\nPING\r\n";
+    let transfer = [format!("${}\r\n", rdb.len()).as_bytes(), &rdb, command].concat();
+
+    // a one byte pipe, so every read gets a single byte
+    let (mut master, mut link) = tokio::io::duplex(1);
+    tokio::spawn(async move { master.write_all(&transfer).await });
+
+    let mut buffered = BytesMut::new();
+    let loaded = load_rdb_transfer(&mut buffered, &mut link, &set_command_actor_handle)
+        .await
+        .expect("the RDB loads");
+
+    assert_eq!(loaded, 1);
+    assert_eq!(
+        set_command_actor_handle.get_value(1, "foo").await.unwrap(),
+        Some(b"bar".to_vec())
+    );
+
+    // what came after the RDB was left for the command stream
+    let mut rest = buffered.to_vec();
+    link.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, command);
+}
+
+#[tokio::test]
+async fn rdb_delimited_by_a_mark() {
+    let set_command_actor_handle = SetCommandActorHandle::new();
+
+    // header, foo => bar, EOF and a checksum of zeroes
+    let rdb = [
+        &b"REDIS0011"[..],
+        &[0x00, 3],
+        b"foo",
+        &[3],
+        b"bar",
+        &[0xFF],
+        &[0; 8],
+    ]
+    .concat();
+
+    let mark = "0123456789abcdefghijABCDEFGHIJ0123456789";
+    let command = b"*1\r\n$4\r\nPING\r\n";
+    let transfer = [
+        format!("$EOF:{}\r\n", mark).as_bytes(),
+        &rdb,
+        mark.as_bytes(),
+        command,
+    ]
+    .concat();
+
+    let (mut master, mut link) = tokio::io::duplex(1);
+    tokio::spawn(async move { master.write_all(&transfer).await });
+
+    let mut buffered = BytesMut::new();
+    let loaded = load_rdb_transfer(&mut buffered, &mut link, &set_command_actor_handle)
+        .await
+        .expect("the RDB loads");
+
+    assert_eq!(loaded, 1);
+    assert_eq!(
+        set_command_actor_handle.get_value(0, "foo").await.unwrap(),
+        Some(b"bar".to_vec())
+    );
+
+    // the mark is gone, the command is still there
+    let mut rest = buffered.to_vec();
+    link.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, command);
+}
+
+#[test]
+fn replica_loads_every_database_and_follows_on() {
+    let master_dir = temp_dir("diskless-master");
+    let master = Server::start(&[
+        "--dir",
+        master_dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+    ]);
+    let mut to_master = master.connect();
+
+    for n in 0..100 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("key:{}", n), "value"]),
+            ok()
+        );
+    }
+    assert_eq!(to_master.call(&["SELECT", "5"]), ok());
+    assert_eq!(to_master.call(&["SET", "other", "db"]), ok());
+    assert_eq!(to_master.call(&["SAVE"]), ok());
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(100));
+    assert_eq!(to_replica.call(&["GET", "key:99"]), bulk("value"));
+    assert_eq!(to_replica.call(&["SELECT", "5"]), ok());
+    assert_eq!(to_replica.call(&["GET", "other"]), bulk("db"));
+
+    // the link carries on with the command stream once the RDB is in
+    assert_eq!(to_master.call(&["SET", "after", "sync"]), ok());
+    to_replica.wait_for(&["GET", "after"], bulk("sync"));
+}
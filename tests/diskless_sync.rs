@@ -0,0 +1,150 @@
+// With repl-diskless-sync the master sends the replicas a snapshot of its memory, nothing has to
+// be SAVEd first, and replicas arriving within the delay share one snapshot. Replicas that
+// announced capa eof get it delimited by a mark, the others with its length in front.
+
+mod common;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn replica_syncs_from_memory() {
+    let master = Server::start(&["--repl-diskless-sync", "--repl-diskless-sync-delay", "0"]);
+    let mut to_master = master.connect();
+
+    assert_eq!(
+        to_master.call(&["CONFIG", "GET", "repl-diskless-sync*"]),
+        RespValue::Array(vec![
+            bulk("repl-diskless-sync"),
+            bulk("yes"),
+            bulk("repl-diskless-sync-delay"),
+            bulk("0"),
+        ])
+    );
+
+    // never SAVEd, the RDB on disk knows nothing of these
+    for n in 0..50 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("key:{}", n), "value"]),
+            ok()
+        );
+    }
+    assert_eq!(to_master.call(&["SELECT", "2"]), ok());
+    assert_eq!(to_master.call(&["SET", "other", "db"]), ok());
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(50));
+    assert_eq!(to_replica.call(&["GET", "key:49"]), bulk("value"));
+    assert_eq!(to_replica.call(&["SELECT", "2"]), ok());
+    assert_eq!(to_replica.call(&["GET", "other"]), bulk("db"));
+
+    // writes after the snapshot follow as commands, in whatever database they were made
+    assert_eq!(to_master.call(&["SET", "after", "sync"]), ok());
+    to_replica.wait_for(&["GET", "after"], bulk("sync"));
+
+    assert_eq!(to_master.call(&["SELECT", "0"]), ok());
+    assert_eq!(to_master.call(&["SET", "back", "in 0"]), ok());
+    assert_eq!(to_replica.call(&["SELECT", "0"]), ok());
+    to_replica.wait_for(&["GET", "back"], bulk("in 0"));
+}
+
+#[test]
+fn replicas_within_the_delay_share_a_snapshot() {
+    let master = Server::start(&["--repl-diskless-sync", "--repl-diskless-sync-delay", "1"]);
+    let mut to_master = master.connect();
+
+    assert_eq!(to_master.call(&["SET", "foo", "bar"]), ok());
+
+    let first = Server::start(&["--replicaof", &master.address()]);
+    let second = Server::start(&["--replicaof", &master.address()]);
+
+    // made while both wait, so it comes to them in the snapshot and not as a command
+    assert_eq!(to_master.call(&["SET", "waiting", "yes"]), ok());
+
+    for replica in [&first, &second] {
+        let mut to_replica = replica.connect();
+
+        to_replica.wait_for(&["DBSIZE"], RespValue::Integer(2));
+        assert_eq!(to_replica.call(&["GET", "foo"]), bulk("bar"));
+        assert_eq!(to_replica.call(&["GET", "waiting"]), bulk("yes"));
+    }
+
+    assert_eq!(to_master.call(&["SET", "after", "sync"]), ok());
+
+    for replica in [&first, &second] {
+        replica.connect().wait_for(&["GET", "after"], bulk("sync"));
+    }
+}
+
+#[test]
+fn off_by_default() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "repl-diskless-sync"]),
+        RespValue::Array(vec![bulk("repl-diskless-sync"), bulk("no")])
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "repl-diskless-sync-delay"]),
+        RespValue::Array(vec![bulk("repl-diskless-sync-delay"), bulk("5")])
+    );
+}
+
+// Goes through the handshake as a replica announcing `capabilities`, up to the +FULLRESYNC.
+fn full_resync(master: &Server, capabilities: &[&str]) -> common::Client {
+    let mut replica = master.connect();
+
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+    assert_eq!(
+        replica.call(&[&["REPLCONF"][..], capabilities].concat()),
+        ok()
+    );
+
+    let RespValue::SimpleString(reply) = replica.call(&["PSYNC", "?", "-1"]) else {
+        panic!("PSYNC replies with a simple string");
+    };
+    assert!(reply.starts_with("FULLRESYNC "), "{}", reply);
+
+    replica
+}
+
+#[test]
+fn transfer_is_delimited_for_replicas_with_capa_eof() {
+    let master = Server::start(&["--repl-diskless-sync", "--repl-diskless-sync-delay", "0"]);
+
+    let mut replica = full_resync(&master, &["capa", "eof", "capa", "psync2"]);
+
+    let header = replica.read_bytes(47);
+    assert_eq!(&header[..5], b"$EOF:");
+    assert_eq!(&header[45..], b"\r\n");
+    let mark = &header[5..45];
+
+    // the file, then the mark again
+    let mut transfer = Vec::new();
+    while !transfer.ends_with(mark) {
+        transfer.extend(replica.read_bytes(1));
+    }
+    assert!(transfer.starts_with(b"REDIS"));
+}
+
+#[test]
+fn transfer_has_its_length_for_other_replicas() {
+    let master = Server::start(&["--repl-diskless-sync", "--repl-diskless-sync-delay", "0"]);
+
+    // capabilities this server doesn't know are ignored
+    let mut replica = full_resync(&master, &["capa", "psync2", "capa", "shiny"]);
+
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n") {
+        header.extend(replica.read_bytes(1));
+    }
+    let length: usize = String::from_utf8_lossy(&header[1..header.len() - 2])
+        .parse()
+        .expect("$<length>\\r\\n");
+    assert_eq!(header[0], b'$');
+
+    assert!(replica.read_bytes(length).starts_with(b"REDIS"));
+}
@@ -0,0 +1,48 @@
+// ECHO replies with a bulk string of exactly the bytes it was sent: CRLF, nothing at all, or bytes
+// that aren't UTF-8.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn echo_keeps_crlf_and_empty_messages() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call_raw(&["ECHO", "line one\r\nline two"]),
+        b"$18\r\nline one\r\nline two\r\n"
+    );
+    assert_eq!(client.call_raw(&["ECHO", ""]), b"$0\r\n\r\n");
+    assert_eq!(client.call(&["ECHO", "héllo wörld"]), bulk("héllo wörld"));
+}
+
+#[test]
+fn echo_is_binary_safe() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    client.send_bytes(b"*2\r\n$4\r\nECHO\r\n$4\r\n\xff\x00\xfe\n\r\n");
+    assert_eq!(client.read_bytes(10), b"$4\r\n\xff\x00\xfe\n\r\n");
+
+    // queued as it was sent, too
+    assert_eq!(client.call(&["MULTI"]), ok());
+    client.send_bytes(b"*2\r\n$4\r\necho\r\n$2\r\n\xc3\x28\r\n");
+    assert_eq!(client.receive(), simple("QUEUED"));
+    assert_eq!(client.call_raw(&["EXEC"]), b"*1\r\n$2\r\n\xc3\x28\r\n");
+
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+}
+
+#[test]
+fn echo_needs_exactly_one_message() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["ECHO", "a", "b"]),
+        RespValue::Error("ERR wrong number of arguments for 'echo' command".to_string())
+    );
+}
@@ -0,0 +1,262 @@
+// Past maxmemory a master evicts keys by maxmemory-policy and sends its replicas a DEL for each.
+// Replicas don't evict on their own unless replica-ignore-maxmemory is no. DEBUG EVICT evicts
+// by the policy on demand, and MEMORY USAGE tells what a key counts for.
+
+mod common;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn info_field(client: &mut common::Client, section: &str, field: &str) -> String {
+    let RespValue::BulkString(Some(lines)) = client.call(&["INFO", section]) else {
+        panic!("INFO {} replies with a bulk string", section);
+    };
+
+    let lines = String::from_utf8(lines.to_vec()).expect("INFO is text");
+
+    lines
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{}:", field)))
+        .unwrap_or_else(|| panic!("INFO {} has {}", section, field))
+        .to_string()
+}
+
+fn used_memory(client: &mut common::Client) -> usize {
+    info_field(client, "memory", "used_memory")
+        .parse()
+        .expect("a number")
+}
+
+fn sorted_keys(client: &mut common::Client) -> Vec<RespValue> {
+    let RespValue::Array(mut keys) = client.call(&["KEYS", "*"]) else {
+        panic!("KEYS replies with an array");
+    };
+    keys.sort_by_key(|key| format!("{:?}", key));
+    keys
+}
+
+// 10 bytes of key and 90 of value, 100 bytes of used_memory each
+fn set_keys(client: &mut common::Client, count: usize) {
+    let value = "v".repeat(90);
+    for n in 0..count {
+        assert_eq!(
+            client.call(&["SET", &format!("key:{:06}", n), &value]),
+            ok()
+        );
+    }
+}
+
+#[test]
+fn master_evicts_and_replicas_follow() {
+    let master = Server::start(&[
+        "--maxmemory",
+        "2000",
+        "--maxmemory-policy",
+        "allkeys-random",
+    ]);
+    // a replica evicting on its own would have next to nothing left
+    let replica = Server::start(&["--replicaof", &master.address(), "--maxmemory", "100"]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(
+        to_master.call(&["CONFIG", "GET", "maxmemory", "maxmemory-policy"]),
+        RespValue::Array(vec![
+            bulk("maxmemory"),
+            bulk("2000"),
+            bulk("maxmemory-policy"),
+            bulk("allkeys-random"),
+        ])
+    );
+
+    // synced before the writes, so the evictions reach it as DELs and not in a snapshot
+    assert_eq!(to_master.call(&["SET", "ready", "1"]), ok());
+    to_replica.wait_for(&["GET", "ready"], bulk("1"));
+
+    set_keys(&mut to_master, 60);
+
+    // evicted before each write, so the last one may still be over
+    assert!(used_memory(&mut to_master) <= 2100);
+    let RespValue::Integer(kept) = to_master.call(&["DBSIZE"]) else {
+        panic!("DBSIZE replies with an integer");
+    };
+    assert!(kept <= 21, "{} keys kept", kept);
+    assert_eq!(
+        info_field(&mut to_master, "stats", "evicted_keys"),
+        (61 - kept).to_string()
+    );
+
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(kept));
+    assert_eq!(sorted_keys(&mut to_replica), sorted_keys(&mut to_master));
+    assert_eq!(info_field(&mut to_replica, "stats", "evicted_keys"), "0");
+}
+
+#[test]
+fn replica_evicts_when_it_does_not_ignore_maxmemory() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&[
+        "--replicaof",
+        &master.address(),
+        "--maxmemory",
+        "500",
+        "--maxmemory-policy",
+        "allkeys-random",
+        "--replica-ignore-maxmemory",
+        "no",
+    ]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    set_keys(&mut to_master, 30);
+    assert_eq!(to_master.call(&["SET", "done", "yes"]), ok());
+
+    // the master keeps everything, the replica what fits
+    to_replica.wait_for(&["GET", "done"], bulk("yes"));
+    assert_eq!(to_master.call(&["DBSIZE"]), RespValue::Integer(31));
+    // evicted before done was written
+    let used = used_memory(&mut to_replica);
+    assert!(used <= 500 + "doneyes".len(), "{} bytes used", used);
+
+    let RespValue::Integer(kept) = to_replica.call(&["DBSIZE"]) else {
+        panic!("DBSIZE replies with an integer");
+    };
+    assert!(kept < 31, "{} keys kept", kept);
+}
+
+#[test]
+fn volatile_policies_evict_keys_with_a_deadline_only() {
+    let server = Server::start(&["--maxmemory", "1000", "--maxmemory-policy", "volatile-ttl"]);
+    let mut client = server.connect();
+
+    let value = "v".repeat(90);
+    for n in 0..5 {
+        assert_eq!(
+            client.call(&["SET", &format!("persist:{}", n), &value]),
+            ok()
+        );
+    }
+    for n in 0..20 {
+        assert_eq!(
+            client.call(&["SET", &format!("volatile{}", n), &value, "EX", "1000"]),
+            ok()
+        );
+    }
+
+    assert!(used_memory(&mut client) <= 1100);
+    for n in 0..5 {
+        assert_eq!(
+            client.call(&["GET", &format!("persist:{}", n)]),
+            bulk(&value)
+        );
+    }
+}
+
+#[test]
+fn volatile_policies_leave_persistent_keys() {
+    let server = Server::start(&["--maxmemory", "1000", "--maxmemory-policy", "volatile-ttl"]);
+    let mut client = server.connect();
+
+    // with no key to evict, the dataset grows past maxmemory
+    set_keys(&mut client, 20);
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(20));
+    assert_eq!(used_memory(&mut client), 2000);
+}
+
+#[test]
+fn noeviction_by_default() {
+    let server = Server::start(&["--maxmemory", "100"]);
+    let mut client = server.connect();
+
+    // the second one takes it over maxmemory, nothing is evicted to make room for a third
+    set_keys(&mut client, 2);
+    assert_eq!(
+        client.call(&["SET", "third", "v"]),
+        RespValue::Error("OOM command not allowed when used memory > 'maxmemory'.".to_string())
+    );
+
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(2));
+    assert_eq!(info_field(&mut client, "memory", "maxmemory"), "100");
+    assert_eq!(
+        info_field(&mut client, "memory", "maxmemory_policy"),
+        "noeviction"
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "replica-ignore-maxmemory"]),
+        RespValue::Array(vec![bulk("replica-ignore-maxmemory"), bulk("yes")])
+    );
+}
+
+#[test]
+fn debug_evict_evicts_by_policy_under_maxmemory() {
+    let master = Server::start(&[
+        "--enable-debug-command",
+        "--maxmemory-policy",
+        "volatile-random",
+    ]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    set_keys(&mut to_master, 5);
+    for n in 0..3 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("volatile{}", n), "v", "EX", "1000"]),
+            ok()
+        );
+    }
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(8));
+
+    // no maxmemory, nothing would go by itself
+    assert_eq!(
+        to_master.call(&["DEBUG", "EVICT", "2"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(to_master.call(&["DBSIZE"]), RespValue::Integer(6));
+    assert_eq!(info_field(&mut to_master, "stats", "evicted_keys"), "2");
+
+    // one volatile key left, the persistent ones stay
+    assert_eq!(
+        to_master.call(&["DEBUG", "EVICT", "10"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(to_master.call(&["DBSIZE"]), RespValue::Integer(5));
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(5));
+    assert_eq!(sorted_keys(&mut to_replica), sorted_keys(&mut to_master));
+}
+
+#[test]
+fn debug_evict_under_noeviction_evicts_nothing() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+
+    set_keys(&mut client, 3);
+
+    assert_eq!(client.call(&["DEBUG", "EVICT", "3"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(3));
+}
+
+#[test]
+fn memory_usage_is_what_used_memory_counts() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    set_keys(&mut client, 3);
+
+    assert_eq!(
+        client.call(&["MEMORY", "USAGE", "key:000001"]),
+        RespValue::Integer(100)
+    );
+    // a string has no elements to sample
+    assert_eq!(
+        client.call(&["MEMORY", "USAGE", "key:000001", "SAMPLES", "0"]),
+        RespValue::Integer(100)
+    );
+    assert_eq!(
+        client.call(&["MEMORY", "USAGE", "missing"]),
+        RespValue::Null
+    );
+    assert_eq!(used_memory(&mut client), 300);
+}
@@ -0,0 +1,60 @@
+// EXISTS counts the keys there are, a key given twice twice, and none that are past their deadline
+// whether or not anything has removed them yet.
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{ok, Server};
+use redis_starter_rust::{clock::ManualClock, engine::Engine, resp::value::RespValue};
+
+#[test]
+fn counts_keys() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["EXISTS", "foo"]), RespValue::Integer(0));
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["SET", "empty", ""]), ok());
+
+    assert_eq!(client.call(&["EXISTS", "foo"]), RespValue::Integer(1));
+    assert_eq!(
+        client.call(&["EXISTS", "foo", "empty", "missing"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        client.call(&["EXISTS", "foo", "foo", "foo"]),
+        RespValue::Integer(3)
+    );
+
+    // in the selected database only
+    assert_eq!(client.call(&["SELECT", "1"]), ok());
+    assert_eq!(client.call(&["EXISTS", "foo"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["EXISTS"]),
+        RespValue::Error("ERR wrong number of arguments for 'exists' command".to_string())
+    );
+}
+
+#[tokio::test]
+async fn expired_keys_do_not_count() {
+    let clock = Arc::new(ManualClock::starting_now());
+    let engine = Engine::open_with_clock(clock.clone(), None, None)
+        .await
+        .unwrap();
+
+    engine.set("soon", "1").await.unwrap();
+    engine.set("kept", "1").await.unwrap();
+    assert!(engine
+        .expire("soon", Duration::from_millis(10))
+        .await
+        .unwrap());
+    assert_eq!(engine.exists(&["soon", "kept"]).await.unwrap(), 2);
+
+    // nothing has removed it, it is just past its deadline
+    clock.advance(Duration::from_millis(10));
+    assert_eq!(engine.exists(&["soon", "kept"]).await.unwrap(), 1);
+    assert_eq!(engine.exists(&["soon"]).await.unwrap(), 0);
+}
@@ -0,0 +1,178 @@
+// EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT give an existing key a deadline without touching its
+// value, PERSIST drops it again. TTL and PTTL read back what is left of it, -1 without one and -2
+// without a key.
+
+mod common;
+
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn expire_sets_a_deadline_on_an_existing_key() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["TTL", "foo"]), RespValue::Integer(-2));
+    assert_eq!(client.call(&["EXPIRE", "foo", "10"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["TTL", "foo"]), RespValue::Integer(-2));
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["TTL", "foo"]), RespValue::Integer(-1));
+    assert_eq!(client.call(&["PTTL", "foo"]), RespValue::Integer(-1));
+
+    assert_eq!(
+        client.call(&["EXPIRE", "foo", "100"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["TTL", "foo"]), RespValue::Integer(100));
+    let RespValue::Integer(pttl) = client.call(&["PTTL", "foo"]) else {
+        panic!("PTTL replies with an integer");
+    };
+    assert!((99_000..=100_000).contains(&pttl), "{}", pttl);
+    assert_eq!(client.call(&["GET", "foo"]), bulk("bar"));
+
+    // a plain SET makes it persistent again
+    assert_eq!(client.call(&["SET", "foo", "baz"]), ok());
+    assert_eq!(client.call(&["TTL", "foo"]), RespValue::Integer(-1));
+}
+
+#[test]
+fn the_key_is_gone_after_the_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(
+        client.call(&["PEXPIRE", "foo", "20"]),
+        RespValue::Integer(1)
+    );
+    thread::sleep(Duration::from_millis(40));
+
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+    assert_eq!(client.call(&["PTTL", "foo"]), RespValue::Integer(-2));
+    assert_eq!(
+        client.call(&["PEXPIRE", "foo", "20"]),
+        RespValue::Integer(0)
+    );
+}
+
+#[test]
+fn a_deadline_in_the_past_deletes_the_key() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "a", "1"]), ok());
+    assert_eq!(client.call(&["SET", "b", "1"]), ok());
+    assert_eq!(client.call(&["SET", "c", "1"]), ok());
+
+    assert_eq!(client.call(&["EXPIRE", "a", "0"]), RespValue::Integer(1));
+    assert_eq!(
+        client.call(&["PEXPIRE", "b", "-100"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["EXPIREAT", "c", "1"]), RespValue::Integer(1));
+
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(0));
+}
+
+#[test]
+fn absolute_deadlines_are_kept_as_given() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(
+        client.call(&["EXPIREAT", "foo", &(now + 60).to_string()]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["DEBUG", "JMAP"]),
+        RespValue::Array(vec![
+            common::bulk("foo"),
+            RespValue::Integer(((now + 60) * 1000) as i64)
+        ])
+    );
+}
+
+#[test]
+fn replicas_get_the_deadline_the_master_worked_out() {
+    let master = Server::start(&["--enable-debug-command"]);
+    let replica = Server::start(&["--replicaof", &master.address(), "--enable-debug-command"]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "kept", "1"]), ok());
+    assert_eq!(to_master.call(&["SET", "gone", "1"]), ok());
+    assert_eq!(
+        to_master.call(&["EXPIRE", "kept", "100"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        to_master.call(&["EXPIRE", "gone", "0"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(to_master.call(&["SET", "done", "1"]), ok());
+
+    to_replica.wait_for(&["GET", "done"], bulk("1"));
+    assert_eq!(to_replica.call(&["GET", "gone"]), RespValue::Null);
+    assert_eq!(
+        to_replica.call(&["DEBUG", "JMAP"]),
+        to_master.call(&["DEBUG", "JMAP"])
+    );
+}
+
+#[test]
+fn persist_drops_the_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["PERSIST", "foo"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["PERSIST", "foo"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["PEXPIRE", "foo", "50"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["PERSIST", "foo"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["TTL", "foo"]), RespValue::Integer(-1));
+
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(client.call(&["GET", "foo"]), bulk("bar"));
+
+    // and an expired key is not brought back
+    assert_eq!(
+        client.call(&["PEXPIRE", "foo", "10"]),
+        RespValue::Integer(1)
+    );
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(client.call(&["PERSIST", "foo"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+}
+
+#[test]
+fn replicas_persist_the_key_too() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "foo", "bar", "EX", "100"]), ok());
+    assert_eq!(to_master.call(&["PERSIST", "foo"]), RespValue::Integer(1));
+    assert_eq!(to_master.call(&["SET", "done", "1"]), ok());
+
+    to_replica.wait_for(&["GET", "done"], bulk("1"));
+    assert_eq!(to_replica.call(&["TTL", "foo"]), RespValue::Integer(-1));
+}
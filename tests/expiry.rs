@@ -0,0 +1,85 @@
+// Reads check the deadline themselves, a key past it is gone whether or not the active expiry
+// cycle has removed it yet. A plain SET clears the deadline, SET ... KEEPTTL leaves it where it was.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn get_returns_nil_after_the_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar", "PX", "10"]), ok());
+    thread::sleep(Duration::from_millis(20));
+
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+}
+
+#[test]
+fn every_read_path_skips_expired_keys() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "gone", "value", "PX", "10"]), ok());
+    assert_eq!(client.call(&["SET", "kept", "value"]), ok());
+    thread::sleep(Duration::from_millis(20));
+
+    assert_eq!(
+        client.call(&["MGET", "gone", "kept"]),
+        RespValue::Array(vec![RespValue::Null, bulk("value")])
+    );
+    assert_eq!(client.call(&["STRLEN", "gone"]), RespValue::Integer(0));
+    assert_eq!(
+        client.call(&["KEYS", "*"]),
+        RespValue::Array(vec![RespValue::BulkString(Some("kept".into()))])
+    );
+
+    // APPEND starts over instead of extending the expired value
+    assert_eq!(client.call(&["SET", "gone", "value", "PX", "10"]), ok());
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(
+        client.call(&["APPEND", "gone", "new"]),
+        RespValue::Integer(3)
+    );
+}
+
+#[test]
+fn plain_set_drops_the_old_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "old", "PX", "100"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "new"]), ok());
+
+    // well past the old deadline, the active expiry cycle has seen it by now
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(client.call(&["GET", "foo"]), bulk("new"));
+
+    // a new deadline replaces the old one too, in both directions
+    assert_eq!(client.call(&["SET", "foo", "old", "PX", "100"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "new", "EX", "100"]), ok());
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(client.call(&["GET", "foo"]), bulk("new"));
+}
+
+#[test]
+fn keepttl_keeps_the_old_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "old", "PX", "100"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "new", "KEEPTTL"]), ok());
+    assert_eq!(client.call(&["GET", "foo"]), bulk("new"));
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+
+    // without a deadline to keep the key stays
+    assert_eq!(client.call(&["SET", "bar", "value", "KEEPTTL"]), ok());
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(client.call(&["GET", "bar"]), bulk("value"));
+}
@@ -0,0 +1,74 @@
+// GET is answered by the connection from the store's databases, without the processor.
+// It has to agree with everything that did go through the processor, bulk strings and all.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn reads_its_own_writes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for i in 0..200 {
+        let value = i.to_string();
+        assert_eq!(client.call(&["SET", "k", &value]), ok());
+        assert_eq!(client.call(&["GET", "k"]), bulk(&value));
+    }
+
+    assert_eq!(client.call(&["DEL", "k"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["GET", "k"]), RespValue::Null);
+}
+
+#[test]
+fn follows_select() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "k", "0"]), ok());
+    assert_eq!(client.call(&["SELECT", "5"]), ok());
+    assert_eq!(client.call(&["GET", "k"]), RespValue::Null);
+    assert_eq!(client.call(&["SET", "k", "5"]), ok());
+
+    // a SELECT that fails leaves the connection where it was
+    assert!(matches!(
+        client.call(&["SELECT", "16"]),
+        RespValue::Error(_)
+    ));
+    assert_eq!(client.call(&["GET", "k"]), bulk("5"));
+
+    // other connections start out in database 0
+    assert_eq!(server.connect().call(&["GET", "k"]), bulk("0"));
+}
+
+#[test]
+fn argument_errors_still_come_from_the_parser() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["GET"]),
+        RespValue::Error("ERR wrong number of arguments for 'get' command".to_string())
+    );
+    assert_eq!(
+        client.call(&["GET", "a", "b"]),
+        RespValue::Error("ERR wrong number of arguments for 'get' command".to_string())
+    );
+}
+
+#[test]
+fn values_come_back_whole() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // a simple string would end at the \r\n and the rest would read as another reply
+    let value = "first\r\n+OK";
+    assert_eq!(client.call(&["SET", "k", value]), ok());
+    assert_eq!(client.call(&["GET", "k"]), bulk(value));
+    assert_eq!(
+        client.call(&["MGET", "k", "missing"]),
+        RespValue::Array(vec![bulk(value), RespValue::Null])
+    );
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+}
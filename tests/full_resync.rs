@@ -0,0 +1,99 @@
+// Without repl-diskless-sync a full resync SAVEs to dir/dbfilename and sends the replicas that
+// file, like redis. Either way, what the master takes while a replica is in the middle of its
+// handshake has to reach it, in the snapshot or as a command after it, and never twice.
+
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn disk_based_sync_saves_the_file_it_sends() {
+    let dir = temp_dir("full-resync-disk");
+    let master = Server::start(&["--dir", dir.to_str().unwrap(), "--dbfilename", "dump.rdb"]);
+    let mut to_master = master.connect();
+
+    assert_eq!(to_master.call(&["SET", "foo", "bar"]), ok());
+    // whatever was there from startup goes, the sync has to write its own
+    let _ = std::fs::remove_file(dir.join("dump.rdb"));
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+
+    to_replica.wait_for(&["GET", "foo"], bulk("bar"));
+    let saved = std::fs::read(dir.join("dump.rdb")).expect("the sync saved the RDB");
+    assert!(saved.starts_with(b"REDIS"));
+    assert!(saved.windows(3).any(|window| window == b"foo"));
+}
+
+#[test]
+fn writes_during_a_disk_based_handshake_reach_the_replica() {
+    let dir = temp_dir("full-resync-handshake");
+    writes_during_the_handshake_reach_the_replica(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+    ]);
+}
+
+#[test]
+fn writes_during_an_in_memory_handshake_reach_the_replica() {
+    writes_during_the_handshake_reach_the_replica(&[]);
+}
+
+#[test]
+fn writes_during_a_diskless_handshake_reach_the_replica() {
+    writes_during_the_handshake_reach_the_replica(&[
+        "--repl-diskless-sync",
+        "--repl-diskless-sync-delay",
+        "0",
+    ]);
+}
+
+fn writes_during_the_handshake_reach_the_replica(master_args: &[&str]) {
+    let master = Server::start(master_args);
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let writer = {
+        let mut to_master = master.connect();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut n = 0;
+            while !stop.load(Ordering::Relaxed) {
+                assert_eq!(
+                    to_master.call(&["SET", &format!("key:{}", n), "value"]),
+                    ok()
+                );
+                assert_eq!(
+                    to_master.call(&["INCR", "counter"]),
+                    RespValue::Integer(n + 1)
+                );
+                n += 1;
+            }
+            n
+        })
+    };
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+
+    // the writer keeps going until the replica has its snapshot
+    to_replica.wait_for(&["EXISTS", "counter"], RespValue::Integer(1));
+    stop.store(true, Ordering::Relaxed);
+    let written = writer.join().expect("writer finishes");
+
+    to_replica.wait_for(&["GET", "counter"], bulk(&written.to_string()));
+    assert_eq!(
+        to_replica.call(&["DBSIZE"]),
+        RespValue::Integer(written + 1)
+    );
+}
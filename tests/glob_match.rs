@@ -0,0 +1,45 @@
+// glob_match follows redis' stringmatchlen(), these are the cases KEYS and CONFIG GET rely on.
+
+use redis_starter_rust::utils::glob_match;
+
+#[test]
+fn wildcards() {
+    assert!(glob_match("*", "", false));
+    assert!(glob_match("*", "anything", false));
+    assert!(glob_match("h*llo", "hllo", false));
+    assert!(glob_match("h*llo", "heeeello", false));
+    assert!(glob_match("h?llo", "hello", false));
+    assert!(!glob_match("h?llo", "hllo", false));
+    assert!(glob_match("**a**", "banana", false));
+    assert!(!glob_match("a*", "banana", false));
+    assert!(!glob_match("", "a", false));
+}
+
+#[test]
+fn classes() {
+    assert!(glob_match("h[ae]llo", "hallo", false));
+    assert!(!glob_match("h[ae]llo", "hillo", false));
+    assert!(glob_match("h[^e]llo", "hallo", false));
+    assert!(!glob_match("h[^e]llo", "hello", false));
+    assert!(glob_match("h[a-b]llo", "hbllo", false));
+    assert!(glob_match("h[b-a]llo", "hallo", false));
+    assert!(!glob_match("h[a-b]llo", "hcllo", false));
+    assert!(glob_match("[\\]]", "]", false));
+    // unterminated class
+    assert!(glob_match("[ab", "b", false));
+}
+
+#[test]
+fn escapes() {
+    assert!(glob_match("h\\*llo", "h*llo", false));
+    assert!(!glob_match("h\\*llo", "hello", false));
+    assert!(glob_match("\\?", "?", false));
+    assert!(glob_match("a\\", "a\\", false));
+}
+
+#[test]
+fn case_folding() {
+    assert!(!glob_match("DIR", "dir", false));
+    assert!(glob_match("DIR", "dir", true));
+    assert!(glob_match("D[H-J]*", "dir", true));
+}
@@ -0,0 +1,265 @@
+// Hashes: HSET, HGET, HDEL, HGETALL, HLEN, HKEYS and HVALS. A hash is a key like any other to
+// DEL, EXISTS and EXPIRE, string commands refuse it with WRONGTYPE, and it reaches replicas and
+// RDB files along with the strings.
+
+mod common;
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::{engine::Engine, resp::value::RespValue};
+
+fn wrong_type() -> RespValue {
+    RespValue::Error(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    )
+}
+
+// The bulk strings of an array reply, sorted, since a hash keeps no order.
+fn sorted(reply: RespValue) -> Vec<String> {
+    let RespValue::Array(items) = reply else {
+        panic!("expected an array, got {:?}", reply);
+    };
+
+    let mut items: Vec<String> = items
+        .into_iter()
+        .map(|item| match item {
+            RespValue::BulkString(Some(item)) => String::from_utf8(item.to_vec()).unwrap(),
+            other => panic!("expected a bulk string, got {:?}", other),
+        })
+        .collect();
+    items.sort();
+
+    items
+}
+
+// HGETALL's flat field, value, field, value array as pairs, sorted by field.
+fn pairs(reply: RespValue) -> Vec<(String, String)> {
+    let RespValue::Array(items) = reply else {
+        panic!("expected an array, got {:?}", reply);
+    };
+
+    let mut pairs: Vec<(String, String)> = items
+        .chunks(2)
+        .map(|pair| match pair {
+            [RespValue::BulkString(Some(field)), RespValue::BulkString(Some(value))] => (
+                String::from_utf8(field.to_vec()).unwrap(),
+                String::from_utf8(value.to_vec()).unwrap(),
+            ),
+            other => panic!("expected a field and a value, got {:?}", other),
+        })
+        .collect();
+    pairs.sort();
+
+    pairs
+}
+
+#[test]
+fn hash_commands() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["HSET", "user", "name", "ada", "lang", "rust"]),
+        RespValue::Integer(2)
+    );
+    // only new fields count
+    assert_eq!(
+        client.call(&["HSET", "user", "lang", "ocaml", "born", "1815"]),
+        RespValue::Integer(1)
+    );
+
+    assert_eq!(client.call(&["HGET", "user", "lang"]), bulk("ocaml"));
+    assert_eq!(client.call(&["HGET", "user", "missing"]), RespValue::Null);
+    assert_eq!(client.call(&["HGET", "missing", "name"]), RespValue::Null);
+    assert_eq!(client.call(&["HLEN", "user"]), RespValue::Integer(3));
+    assert_eq!(client.call(&["HLEN", "missing"]), RespValue::Integer(0));
+
+    assert_eq!(
+        pairs(client.call(&["HGETALL", "user"])),
+        vec![
+            ("born".to_string(), "1815".to_string()),
+            ("lang".to_string(), "ocaml".to_string()),
+            ("name".to_string(), "ada".to_string()),
+        ]
+    );
+    assert_eq!(
+        client.call(&["HGETALL", "missing"]),
+        RespValue::Array(vec![])
+    );
+    assert_eq!(
+        sorted(client.call(&["HKEYS", "user"])),
+        vec!["born", "lang", "name"]
+    );
+    assert_eq!(
+        sorted(client.call(&["HVALS", "user"])),
+        vec!["1815", "ada", "ocaml"]
+    );
+
+    assert_eq!(
+        client.call(&["HDEL", "user", "born", "missing", "lang"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(client.call(&["EXISTS", "user"]), RespValue::Integer(1));
+
+    // the last field takes the key with it
+    assert_eq!(
+        client.call(&["HDEL", "user", "name"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["EXISTS", "user"]), RespValue::Integer(0));
+    assert_eq!(
+        client.call(&["HDEL", "user", "name"]),
+        RespValue::Integer(0)
+    );
+
+    // a field without a value
+    assert_eq!(
+        client.call(&["HSET", "user", "name", "ada", "lang"]),
+        RespValue::Error("ERR wrong number of arguments for 'hset' command".to_string())
+    );
+    assert_eq!(client.call(&["EXISTS", "user"]), RespValue::Integer(0));
+}
+
+#[test]
+fn a_hash_is_a_key_like_any_other() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["HSET", "h", "field", "value"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(2));
+    assert_eq!(sorted(client.call(&["KEYS", "*"])), vec!["h", "s"]);
+
+    // each kind of command refuses the other kind of key
+    assert_eq!(client.call(&["GET", "h"]), wrong_type());
+    assert_eq!(client.call(&["STRLEN", "h"]), wrong_type());
+    assert_eq!(client.call(&["APPEND", "h", "x"]), wrong_type());
+    assert_eq!(client.call(&["INCR", "h"]), wrong_type());
+    assert_eq!(client.call(&["HGET", "s", "field"]), wrong_type());
+    assert_eq!(client.call(&["HSET", "s", "field", "value"]), wrong_type());
+    assert_eq!(client.call(&["HDEL", "s", "field"]), wrong_type());
+    assert_eq!(client.call(&["HGETALL", "s"]), wrong_type());
+    assert_eq!(client.call(&["GET", "s"]), bulk("string"));
+
+    // MGET has nil for anything that isn't a string
+    assert_eq!(
+        client.call(&["MGET", "h", "s"]),
+        RespValue::Array(vec![RespValue::Null, bulk("string")])
+    );
+
+    assert_eq!(client.call(&["COPY", "h", "copy"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["HGET", "copy", "field"]), bulk("value"));
+
+    // SET replaces a hash like any value
+    assert_eq!(client.call(&["SET", "h", "now a string"]), ok());
+    assert_eq!(client.call(&["GET", "h"]), bulk("now a string"));
+    assert_eq!(client.call(&["HGET", "h", "field"]), wrong_type());
+
+    assert_eq!(
+        client.call(&["PEXPIRE", "copy", "50"]),
+        RespValue::Integer(1)
+    );
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(client.call(&["HGET", "copy", "field"]), RespValue::Null);
+    assert_eq!(client.call(&["EXISTS", "copy"]), RespValue::Integer(0));
+
+    assert_eq!(client.call(&["DEL", "h", "s"]), RespValue::Integer(2));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(0));
+}
+
+#[test]
+fn hashes_reach_replicas() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    // one in the snapshot of the full resync, the others as commands after it
+    assert_eq!(
+        to_master.call(&["HSET", "before", "field", "value"]),
+        RespValue::Integer(1)
+    );
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["HGET", "before", "field"], bulk("value"));
+
+    assert_eq!(
+        to_master.call(&["HSET", "after", "a", "1", "b", "2"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        to_master.call(&["HDEL", "after", "a"]),
+        RespValue::Integer(1)
+    );
+    to_replica.wait_for(&["HLEN", "after"], RespValue::Integer(1));
+    assert_eq!(to_replica.call(&["HGET", "after", "b"]), bulk("2"));
+
+    // a replica takes no writes of its own
+    assert!(matches!(
+        to_replica.call(&["HSET", "after", "c", "3"]),
+        RespValue::Error(e) if e.starts_with("READONLY")
+    ));
+}
+
+#[test]
+fn hashes_survive_a_reload() {
+    let dir = temp_dir("hashes-reload");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    let fields: Vec<String> = (0..100)
+        .flat_map(|i| [format!("field{}", i), "x".repeat(i)])
+        .collect();
+    let mut args = vec!["HSET", "big"];
+    args.extend(fields.iter().map(String::as_str));
+    assert_eq!(client.call(&args), RespValue::Integer(100));
+
+    assert_eq!(
+        client.call(&["HSET", "expiring", "field", "value"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["EXPIRE", "expiring", "100"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["SET", "plain", "value"]), ok());
+
+    let before = pairs(client.call(&["HGETALL", "big"]));
+
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+
+    assert_eq!(pairs(client.call(&["HGETALL", "big"])), before);
+    assert_eq!(client.call(&["HGET", "expiring", "field"]), bulk("value"));
+    assert!(matches!(
+        client.call(&["TTL", "expiring"]),
+        RespValue::Integer(ttl) if ttl > 0 && ttl <= 100
+    ));
+    assert_eq!(client.call(&["GET", "plain"]), bulk("value"));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(3));
+}
+
+#[tokio::test]
+async fn engine_hashes() {
+    let engine = Engine::open(None, None).await.unwrap();
+
+    assert_eq!(
+        engine.hset("h", &[("a", "1"), ("b", "2")]).await.unwrap(),
+        2
+    );
+    assert_eq!(engine.hget("h", "a").await.unwrap(), Some("1".to_string()));
+    assert_eq!(engine.hget("h", "c").await.unwrap(), None);
+    assert_eq!(engine.hgetall("h").await.unwrap().len(), 2);
+    assert!(engine.hgetall("missing").await.unwrap().is_empty());
+
+    // a string isn't a hash
+    engine.set("s", "1").await.unwrap();
+    assert!(engine.hget("s", "a").await.is_err());
+}
@@ -0,0 +1,173 @@
+// HELLO switches a connection between RESP2 and RESP3, and may authenticate it and name it on the
+// way. All of it happens or none of it does: a HELLO that fails changes nothing.
+
+mod common;
+
+use common::{bulk, ok, Client, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The value HELLO gave for field, in a RESP3 map or a RESP2 array of names and values.
+fn hello_field(reply: &RespValue, field: &str) -> RespValue {
+    let pairs: Vec<(RespValue, RespValue)> = match reply {
+        RespValue::Map(pairs) => pairs.clone(),
+        RespValue::Array(items) => items
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect(),
+        other => panic!("HELLO replies with a map or an array, not {:?}", other),
+    };
+
+    pairs
+        .into_iter()
+        .find(|(name, _)| *name == bulk(field))
+        .map(|(_, value)| value)
+        .unwrap_or_else(|| panic!("HELLO has {}", field))
+}
+
+fn client_name(client: &mut Client) -> String {
+    let id = client_id(client).to_string();
+    let RespValue::BulkString(Some(list)) = client.call(&["CLIENT", "LIST", "ID", &id]) else {
+        panic!("CLIENT LIST replies with a bulk string");
+    };
+
+    std::str::from_utf8(&list)
+        .expect("CLIENT LIST is text")
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("name="))
+        .expect("CLIENT LIST has name")
+        .to_string()
+}
+
+fn client_id(client: &mut Client) -> i64 {
+    match client.call(&["CLIENT", "ID"]) {
+        RespValue::Integer(id) => id,
+        other => panic!("unexpected CLIENT ID reply: {:?}", other),
+    }
+}
+
+#[test]
+fn hello_3_switches_to_resp3_and_hello_2_back() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // no protover only tells what the connection has
+    let reply = client.call(&["HELLO"]);
+    assert!(matches!(reply, RespValue::Array(_)), "{:?}", reply);
+    assert_eq!(hello_field(&reply, "proto"), RespValue::Integer(2));
+
+    let reply = client.call(&["HELLO", "3"]);
+    assert!(matches!(reply, RespValue::Map(_)), "{:?}", reply);
+    assert_eq!(hello_field(&reply, "proto"), RespValue::Integer(3));
+    assert_eq!(hello_field(&reply, "server"), bulk("redis"));
+    assert_eq!(hello_field(&reply, "role"), bulk("master"));
+    assert_eq!(
+        hello_field(&reply, "id"),
+        RespValue::Integer(client_id(&mut client))
+    );
+
+    // a subscription is confirmed with a push in RESP3
+    assert!(matches!(
+        client.call(&["SUBSCRIBE", "news"]),
+        RespValue::Push(_)
+    ));
+    assert!(matches!(
+        client.call(&["UNSUBSCRIBE", "news"]),
+        RespValue::Push(_)
+    ));
+
+    let reply = client.call(&["HELLO", "2"]);
+    assert!(matches!(reply, RespValue::Array(_)), "{:?}", reply);
+    assert!(matches!(
+        client.call(&["SUBSCRIBE", "news"]),
+        RespValue::Array(_)
+    ));
+}
+
+#[test]
+fn unsupported_protocol_versions_are_refused() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["HELLO", "4", "SETNAME", "four"]),
+        RespValue::Error("NOPROTO unsupported protocol version".to_string())
+    );
+    assert_eq!(
+        client.call(&["HELLO", "1"]),
+        RespValue::Error("NOPROTO unsupported protocol version".to_string())
+    );
+
+    // still RESP2, and not named
+    assert_eq!(
+        hello_field(&client.call(&["HELLO"]), "proto"),
+        RespValue::Integer(2)
+    );
+    assert_eq!(client_name(&mut client), "");
+}
+
+#[test]
+fn auth_and_setname_with_the_protocol() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    let reply = client.call(&[
+        "HELLO", "3", "AUTH", "default", "secret", "SETNAME", "worker-1",
+    ]);
+    assert_eq!(hello_field(&reply, "proto"), RespValue::Integer(3));
+    assert_eq!(client_name(&mut client), "worker-1");
+
+    // an empty name clears it
+    client.call(&["HELLO", "3", "SETNAME", ""]);
+    assert_eq!(client_name(&mut client), "");
+    assert_eq!(client.call(&["SET", "still", "works"]), ok());
+}
+
+#[test]
+fn a_failed_hello_changes_nothing() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["HELLO", "3", "AUTH", "nobody", "secret", "SETNAME", "worker-1"]),
+        RespValue::Error(
+            "WRONGPASS invalid username-password pair or user is disabled.".to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["HELLO", "3", "SETNAME", "has space"]),
+        RespValue::Error(
+            "ERR Client names cannot contain spaces, newlines or special characters.".to_string()
+        )
+    );
+
+    assert_eq!(
+        hello_field(&client.call(&["HELLO"]), "proto"),
+        RespValue::Integer(2)
+    );
+    assert_eq!(client_name(&mut client), "");
+}
+
+#[test]
+fn nulls_are_resp3_nulls_after_hello_3() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call_raw(&["GET", "missing"]), b"$-1\r\n");
+
+    client.call(&["HELLO", "3"]);
+    assert_eq!(client.call_raw(&["GET", "missing"]), b"_\r\n");
+    assert_eq!(
+        client.call_raw(&["MGET", "foo", "missing"]),
+        b"*2\r\n$3\r\nbar\r\n_\r\n"
+    );
+
+    // a blocking pop in a transaction doesn't wait, and has nothing for the null array
+    assert_eq!(client.call(&["MULTI"]), ok());
+    client.call(&["BLPOP", "missing", "0"]);
+    assert_eq!(client.call_raw(&["EXEC"]), b"*1\r\n_\r\n");
+
+    client.call(&["HELLO", "2"]);
+    assert_eq!(client.call_raw(&["GET", "missing"]), b"$-1\r\n");
+    assert_eq!(client.call_raw(&["BLPOP", "missing", "0.01"]), b"*-1\r\n");
+}
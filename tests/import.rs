@@ -0,0 +1,109 @@
+// --import: a file of RESP commands applied at startup, on top of the RDB file and before the
+// first client gets in. A command that fails doesn't stop the rest, a file that isn't RESP stops
+// the server.
+
+mod common;
+
+use std::process::{Command, Stdio};
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The commands one after the other, the way redis-cli --pipe takes them.
+fn commands(commands: &[&[&str]]) -> Vec<u8> {
+    commands
+        .iter()
+        .flat_map(|args| {
+            RespValue::array_from_slice(args)
+                .to_encoded_string()
+                .unwrap()
+                .into_bytes()
+        })
+        .collect()
+}
+
+#[test]
+fn import_applies_the_commands_before_clients_connect() {
+    let dir = temp_dir("import");
+    let import = dir.join("commands.resp");
+    std::fs::write(
+        &import,
+        commands(&[
+            &["SET", "a", "1"],
+            &["INCR", "a"],
+            &["RPUSH", "l", "x", "y"],
+            &["SADD", "s", "member"],
+            &["INCR", "l"],
+            &["NOSUCHCOMMAND"],
+            &["SET", "b", "2"],
+            &["DEL", "b"],
+            &["SELECT", "1"],
+            &["SET", "elsewhere", "value"],
+        ]),
+    )
+    .unwrap();
+
+    let server = Server::start(&["--import", import.to_str().unwrap()]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["GET", "a"]), bulk("2"));
+    assert_eq!(client.call(&["LLEN", "l"]), RespValue::Integer(2));
+    assert_eq!(client.call(&["SCARD", "s"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["EXISTS", "b"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(3));
+
+    assert_eq!(client.call(&["SELECT", "1"]), ok());
+    assert_eq!(client.call(&["GET", "elsewhere"]), bulk("value"));
+}
+
+#[test]
+fn import_goes_on_top_of_the_rdb_file() {
+    let dir = temp_dir("import-rdb");
+    let rdb_args = ["--dir", dir.to_str().unwrap(), "--dbfilename", "dump.rdb"];
+
+    {
+        let server = Server::start(&rdb_args);
+        let mut client = server.connect();
+
+        assert_eq!(client.call(&["SET", "saved", "old"]), ok());
+        assert_eq!(client.call(&["SET", "kept", "value"]), ok());
+        assert_eq!(client.call(&["SAVE"]), ok());
+    }
+
+    let import = dir.join("commands.resp");
+    std::fs::write(
+        &import,
+        commands(&[&["SET", "saved", "new"], &["HSET", "h", "f", "v"]]),
+    )
+    .unwrap();
+
+    let mut args = rdb_args.to_vec();
+    args.extend(["--import", import.to_str().unwrap()]);
+    let server = Server::start(&args);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["GET", "saved"]), bulk("new"));
+    assert_eq!(client.call(&["GET", "kept"]), bulk("value"));
+    assert_eq!(client.call(&["HGET", "h", "f"]), bulk("v"));
+}
+
+#[test]
+fn a_file_that_isnt_resp_stops_the_server() {
+    let dir = temp_dir("import-broken");
+
+    let truncated = dir.join("truncated.resp");
+    let mut bytes = commands(&[&["SET", "a", "1"]]);
+    bytes.extend_from_slice(b"*3\r\n$3\r\nSET\r\n$1\r\nb");
+    std::fs::write(&truncated, bytes).unwrap();
+
+    for import in [truncated, dir.join("missing.resp")] {
+        let status = Command::new(env!("CARGO_BIN_EXE_redis-starter-rust"))
+            .args(["--port", "0", "--import", import.to_str().unwrap()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+
+        assert!(!status.success(), "started with {}", import.display());
+    }
+}
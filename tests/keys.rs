@@ -0,0 +1,94 @@
+// KEYS returns the keys matching its pattern. Past keys-warn-threshold it logs a warning, and with
+// keys-by-scan it gathers them with SCAN steps instead, which must not change the reply. Either way
+// the keys come from the store without a message to its actor, and are matched elsewhere.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::{bulk, ok, Client, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn sorted_keys(client: &mut Client, pattern: &str) -> Vec<String> {
+    let RespValue::Array(keys) = client.call(&["KEYS", pattern]) else {
+        panic!("KEYS replies with an array");
+    };
+
+    let mut keys: Vec<String> = keys
+        .into_iter()
+        .filter_map(|key| match key {
+            RespValue::BulkString(Some(key)) => Some(String::from_utf8_lossy(&key).into_owned()),
+            _ => None,
+        })
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn fill(client: &mut Client, count: usize) -> Vec<String> {
+    let mut keys: Vec<String> = (0..count).map(|i| format!("key:{i}")).collect();
+    for key in &keys {
+        assert_eq!(client.call(&["SET", key, "v"]), ok());
+    }
+    assert_eq!(client.call(&["SET", "other", "v"]), ok());
+
+    keys.sort();
+    keys
+}
+
+#[test]
+fn keys_applies_the_pattern() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    let keys = fill(&mut client, 5);
+
+    assert_eq!(sorted_keys(&mut client, "key:*"), keys);
+    assert_eq!(sorted_keys(&mut client, "oth?r"), vec!["other".to_string()]);
+    assert_eq!(sorted_keys(&mut client, "nothing*"), Vec::<String>::new());
+}
+
+#[test]
+fn keys_by_scan_returns_the_same_keys() {
+    let server = Server::start(&["--keys-warn-threshold", "10", "--keys-by-scan"]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "keys-*"]),
+        RespValue::Array(vec![
+            bulk("keys-warn-threshold"),
+            bulk("10"),
+            bulk("keys-by-scan"),
+            bulk("yes"),
+        ])
+    );
+
+    let keys = fill(&mut client, 50);
+
+    assert_eq!(sorted_keys(&mut client, "key:*"), keys);
+    assert_eq!(sorted_keys(&mut client, "oth?r"), vec!["other".to_string()]);
+
+    // inside EXEC it is gathered the same way, just without leaving the transaction
+    assert_eq!(client.call(&["MULTI"]), ok());
+    client.call(&["KEYS", "oth?r"]);
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Array(vec![RespValue::Array(vec![bulk("other")])])
+    );
+}
+
+#[test]
+fn keys_lists_only_live_keys_of_the_selected_database() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "kept", "v"]), ok());
+    assert_eq!(client.call(&["SET", "gone", "v", "PX", "10"]), ok());
+    assert_eq!(client.call(&["SELECT", "1"]), ok());
+    assert_eq!(client.call(&["SET", "elsewhere", "v"]), ok());
+    assert_eq!(client.call(&["SELECT", "0"]), ok());
+
+    thread::sleep(Duration::from_millis(20));
+
+    assert_eq!(sorted_keys(&mut client, "*"), vec!["kept".to_string()]);
+}
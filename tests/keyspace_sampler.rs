@@ -0,0 +1,137 @@
+// INFO keyspace's avg_ttl is estimated in the background from samples of the keys with a
+// deadline, the live ones only. MEMORY DOCTOR reports on what the counters say.
+
+mod common;
+
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use common::{ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The db0 line of INFO keyspace as keys, expires and avg_ttl.
+fn db0(client: &mut common::Client) -> (u64, u64, u64) {
+    let RespValue::BulkString(Some(section)) = client.call(&["INFO", "keyspace"]) else {
+        panic!("INFO keyspace replies with a bulk string");
+    };
+    let section = String::from_utf8(section.to_vec()).expect("INFO is text");
+
+    let line = section
+        .lines()
+        .find_map(|line| line.strip_prefix("db0:"))
+        .unwrap_or_else(|| panic!("INFO keyspace has db0: {}", section));
+    let fields: Vec<u64> = line
+        .split(',')
+        .map(|field| {
+            field
+                .split_once('=')
+                .and_then(|(_, value)| value.parse().ok())
+                .expect("name=number")
+        })
+        .collect();
+
+    (fields[0], fields[1], fields[2])
+}
+
+fn doctor(client: &mut common::Client) -> String {
+    let RespValue::BulkString(Some(report)) = client.call(&["MEMORY", "DOCTOR"]) else {
+        panic!("MEMORY DOCTOR replies with a bulk string");
+    };
+
+    String::from_utf8(report.to_vec()).expect("the report is text")
+}
+
+#[test]
+fn avg_ttl_is_sampled_from_keys_with_a_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for n in 0..30 {
+        assert_eq!(
+            client.call(&["SET", &format!("volatile:{}", n), "v", "EX", "1000"]),
+            ok()
+        );
+    }
+    for n in 0..5 {
+        assert_eq!(
+            client.call(&["SET", &format!("persistent:{}", n), "v"]),
+            ok()
+        );
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let avg_ttl = loop {
+        let (keys, expires, avg_ttl) = db0(&mut client);
+        assert_eq!((keys, expires), (35, 30));
+
+        if avg_ttl > 0 {
+            break avg_ttl;
+        }
+        assert!(Instant::now() < deadline, "avg_ttl was never sampled");
+        sleep(Duration::from_millis(20));
+    };
+    assert!((990_000..=1_000_000).contains(&avg_ttl), "{}", avg_ttl);
+
+    // without a deadline left there is nothing to average
+    for n in 0..30 {
+        assert_eq!(client.call(&["SET", &format!("volatile:{}", n), "v"]), ok());
+    }
+    assert_eq!(db0(&mut client), (35, 0, 0));
+}
+
+#[test]
+fn memory_doctor_finds_nothing_by_default() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+
+    assert!(
+        doctor(&mut client).starts_with("Hi Sam, I can't find any memory issue"),
+        "{}",
+        doctor(&mut client)
+    );
+}
+
+#[test]
+fn memory_doctor_warns_near_maxmemory_under_noeviction() {
+    let server = Server::start(&["--maxmemory", "100"]);
+    let mut client = server.connect();
+
+    // 3 bytes of key and 92 of value
+    assert_eq!(client.call(&["SET", "foo", &"v".repeat(92)]), ok());
+
+    let report = doctor(&mut client);
+    assert!(
+        report.starts_with("Sam, I detected a few issues"),
+        "{report}"
+    );
+    assert!(
+        report.contains(" * Near maxmemory: used_memory is 95% of maxmemory"),
+        "{report}"
+    );
+}
+
+#[test]
+fn memory_doctor_warns_when_a_volatile_policy_has_nothing_to_evict() {
+    let server = Server::start(&["--maxmemory", "100", "--maxmemory-policy", "volatile-ttl"]);
+    let mut client = server.connect();
+
+    for n in 0..3 {
+        assert_eq!(
+            client.call(&["SET", &format!("key:{}", n), &"v".repeat(90)]),
+            ok()
+        );
+    }
+
+    let report = doctor(&mut client);
+    assert!(
+        report.contains(
+            " * Nothing to evict: used_memory is over maxmemory, and maxmemory-policy volatile-ttl"
+        ),
+        "{report}"
+    );
+    assert!(!report.contains("Near maxmemory"), "{report}");
+}
@@ -0,0 +1,189 @@
+// Lists: LPUSH, RPUSH, LPOP, RPOP, LRANGE and LLEN. Like a hash, a list is a key like any other,
+// goes with its last element and reaches replicas and RDB files.
+
+mod common;
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::{engine::Engine, resp::value::RespValue};
+
+fn bulks(values: &[&str]) -> RespValue {
+    RespValue::Array(values.iter().map(|value| bulk(value)).collect())
+}
+
+#[test]
+fn list_commands() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["RPUSH", "list", "b", "c"]),
+        RespValue::Integer(2)
+    );
+    // one at a time, so the last one given ends up at the head
+    assert_eq!(
+        client.call(&["LPUSH", "list", "a", "z"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(client.call(&["LLEN", "list"]), RespValue::Integer(4));
+    assert_eq!(client.call(&["LLEN", "missing"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["LRANGE", "list", "0", "-1"]),
+        bulks(&["z", "a", "b", "c"])
+    );
+    assert_eq!(
+        client.call(&["LRANGE", "list", "1", "2"]),
+        bulks(&["a", "b"])
+    );
+    assert_eq!(
+        client.call(&["LRANGE", "list", "-2", "100"]),
+        bulks(&["b", "c"])
+    );
+    assert_eq!(client.call(&["LRANGE", "list", "-100", "0"]), bulks(&["z"]));
+    assert_eq!(client.call(&["LRANGE", "list", "3", "1"]), bulks(&[]));
+    assert_eq!(client.call(&["LRANGE", "list", "5", "10"]), bulks(&[]));
+    assert_eq!(client.call(&["LRANGE", "missing", "0", "-1"]), bulks(&[]));
+
+    assert_eq!(client.call(&["LPOP", "list"]), bulk("z"));
+    assert_eq!(client.call(&["RPOP", "list"]), bulk("c"));
+    assert_eq!(client.call(&["LPOP", "missing"]), RespValue::Null);
+
+    // with a count the reply is an array, nil for no such key
+    assert_eq!(
+        client.call(&["RPUSH", "list", "d", "e"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(client.call(&["RPOP", "list", "2"]), bulks(&["e", "d"]));
+    assert_eq!(client.call(&["LPOP", "list", "0"]), bulks(&[]));
+    assert_eq!(client.call(&["LPOP", "missing", "2"]), RespValue::NullArray);
+
+    // the last element takes the key with it
+    assert_eq!(client.call(&["LPOP", "list", "10"]), bulks(&["a", "b"]));
+    assert_eq!(client.call(&["EXISTS", "list"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["LPOP", "list", "-1"]),
+        RespValue::Error("ERR value is not an integer or out of range".to_string())
+    );
+    assert_eq!(
+        client.call(&["LPOP", "list", "1", "2"]),
+        RespValue::Error("ERR wrong number of arguments for 'lpop' command".to_string())
+    );
+}
+
+#[test]
+fn a_list_is_a_key_like_any_other() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["RPUSH", "l", "a"]), RespValue::Integer(1));
+    assert_eq!(
+        client.call(&["HSET", "h", "field", "value"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(3));
+
+    let wrong_type = RespValue::Error(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    );
+    assert_eq!(client.call(&["GET", "l"]), wrong_type);
+    assert_eq!(client.call(&["HGET", "l", "a"]), wrong_type);
+    assert_eq!(client.call(&["RPUSH", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["LPUSH", "h", "a"]), wrong_type);
+    assert_eq!(client.call(&["LPOP", "h"]), wrong_type);
+    assert_eq!(client.call(&["LLEN", "s"]), wrong_type);
+    assert_eq!(client.call(&["LRANGE", "h", "0", "-1"]), wrong_type);
+    assert_eq!(client.call(&["HSET", "l", "field", "value"]), wrong_type);
+
+    assert_eq!(client.call(&["COPY", "l", "copy"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["RPUSH", "copy", "b"]), RespValue::Integer(2));
+    assert_eq!(client.call(&["LLEN", "l"]), RespValue::Integer(1));
+
+    assert_eq!(
+        client.call(&["PEXPIRE", "copy", "50"]),
+        RespValue::Integer(1)
+    );
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(client.call(&["LLEN", "copy"]), RespValue::Integer(0));
+
+    assert_eq!(client.call(&["DEL", "l"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(2));
+}
+
+#[test]
+fn lists_reach_replicas() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    assert_eq!(
+        to_master.call(&["RPUSH", "before", "a", "b"]),
+        RespValue::Integer(2)
+    );
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["LRANGE", "before", "0", "-1"], bulks(&["a", "b"]));
+
+    assert_eq!(
+        to_master.call(&["LPUSH", "after", "1", "2", "3"]),
+        RespValue::Integer(3)
+    );
+    assert_eq!(to_master.call(&["RPOP", "after"]), bulk("1"));
+    assert_eq!(to_master.call(&["LPOP", "before", "2"]), bulks(&["a", "b"]));
+    to_replica.wait_for(&["EXISTS", "before"], RespValue::Integer(0));
+    assert_eq!(
+        to_replica.call(&["LRANGE", "after", "0", "-1"]),
+        bulks(&["3", "2"])
+    );
+}
+
+#[test]
+fn lists_survive_a_reload() {
+    let dir = temp_dir("lists-reload");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    let values: Vec<String> = (0..100).map(|i| "x".repeat(i)).collect();
+    let mut args = vec!["RPUSH", "big"];
+    args.extend(values.iter().map(String::as_str));
+    assert_eq!(client.call(&args), RespValue::Integer(100));
+    assert_eq!(
+        client.call(&["RPUSH", "expiring", "a"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["EXPIRE", "expiring", "100"]),
+        RespValue::Integer(1)
+    );
+
+    let before = client.call(&["LRANGE", "big", "0", "-1"]);
+
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+
+    assert_eq!(client.call(&["LRANGE", "big", "0", "-1"]), before);
+    assert!(matches!(
+        client.call(&["TTL", "expiring"]),
+        RespValue::Integer(ttl) if ttl > 0 && ttl <= 100
+    ));
+}
+
+#[tokio::test]
+async fn engine_lists() {
+    let engine = Engine::open(None, None).await.unwrap();
+
+    assert_eq!(engine.rpush("l", &["b", "c"]).await.unwrap(), 2);
+    assert_eq!(engine.lpush("l", &["a"]).await.unwrap(), 3);
+    assert_eq!(engine.llen("l").await.unwrap(), 3);
+    assert_eq!(engine.lpop("l").await.unwrap(), Some("a".to_string()));
+    assert_eq!(engine.rpop("l").await.unwrap(), Some("c".to_string()));
+    assert_eq!(engine.rpop("l").await.unwrap(), Some("b".to_string()));
+    assert_eq!(engine.lpop("l").await.unwrap(), None);
+    assert_eq!(engine.llen("l").await.unwrap(), 0);
+}
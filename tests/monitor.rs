@@ -0,0 +1,210 @@
+// MONITOR and the audit log, both fed the commands the processor runs: what each shows, that
+// neither shows a password, and the audit log moving on to a new file once it is too big.
+
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The next line MONITOR sent, without the time in front of it.
+fn monitored(client: &mut common::Client) -> String {
+    let RespValue::SimpleString(line) = client.receive() else {
+        panic!("not a MONITOR line");
+    };
+    let (time, rest) = line.split_once(' ').expect("a time in front");
+    assert!(time.parse::<f64>().is_ok(), "{:?} isn't a time", time);
+
+    rest.to_string()
+}
+
+// The audit log's lines once there are at least count of them.
+fn audited(path: &Path, count: usize) -> Vec<String> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let lines: Vec<String> = fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if lines.len() >= count {
+            return lines;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "only {:?} in the audit log",
+            lines
+        );
+        sleep(Duration::from_millis(20));
+    }
+}
+
+// What a line says past its time, the part that is the same every run.
+fn without_time(line: &str) -> &str {
+    let (_, rest) = line.split_once(",\"id\":").expect("an id after the time");
+    rest
+}
+
+// This connection's ip:port, as CLIENT INFO has it.
+fn addr(client: &mut common::Client) -> String {
+    let RespValue::BulkString(Some(info)) = client.call(&["CLIENT", "INFO"]) else {
+        panic!("no client info");
+    };
+
+    String::from_utf8(info.to_vec())
+        .unwrap()
+        .split(' ')
+        .find_map(|field| field.strip_prefix("addr="))
+        .expect("an addr")
+        .to_string()
+}
+
+#[test]
+fn monitor_shows_other_clients_commands() {
+    let server = Server::start(&[]);
+    let mut monitor = server.connect();
+    let mut client = server.connect();
+
+    let addr = addr(&mut client);
+    assert_eq!(monitor.call(&["MONITOR"]), ok());
+
+    assert_eq!(client.call(&["SET", "key", "two words"]), ok());
+    assert_eq!(client.call(&["SELECT", "1"]), ok());
+    assert_eq!(client.call(&["GET", "key\n"]), RespValue::Null);
+    // administrative commands aren't shown
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "auditlog"]),
+        RespValue::Array(vec![bulk("auditlog"), bulk("")])
+    );
+    assert_eq!(client.call(&["AUTH", "default", "secret"]), ok());
+    assert_eq!(client.call(&["INCR", "key"]), RespValue::Integer(1));
+
+    for line in [
+        format!("[0 {}] \"SET\" \"key\" \"two words\"", addr),
+        format!("[0 {}] \"SELECT\" \"1\"", addr),
+        format!("[1 {}] \"GET\" \"key\\n\"", addr),
+        format!("[1 {}] \"AUTH\" \"(redacted)\" \"(redacted)\"", addr),
+        format!("[1 {}] \"INCR\" \"key\"", addr),
+    ] {
+        assert_eq!(monitored(&mut monitor), line);
+    }
+}
+
+#[test]
+fn the_audit_log_keeps_writes_and_administrative_commands() {
+    let dir = temp_dir("auditlog");
+    let path = dir.join("audit.log");
+    let server = Server::start(&["--auditlog", path.to_str().unwrap()]);
+    let mut client = server.connect();
+
+    let RespValue::Integer(id) = client.call(&["CLIENT", "ID"]) else {
+        panic!("no client id");
+    };
+    let addr = addr(&mut client);
+    let who = format!("{},\"addr\":\"{}\"", id, addr);
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "auditlog-max-size"]),
+        RespValue::Array(vec![bulk("auditlog-max-size"), bulk("104857600")])
+    );
+    // HELLO isn't kept, but the name it gives the connection is in what comes after
+    assert!(matches!(
+        client.call(&["HELLO", "2", "SETNAME", "auditor"]),
+        RespValue::Array(_)
+    ));
+    assert_eq!(client.call(&["SET", "key", "value"]), ok());
+    // reads aren't kept
+    assert_eq!(client.call(&["GET", "key"]), bulk("value"));
+    assert_eq!(client.call(&["EXISTS", "key"]), RespValue::Integer(1));
+    assert_eq!(
+        client.call(&["LPUSH", "key", "x"]),
+        RespValue::Error(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+        )
+    );
+    assert_eq!(client.call(&["SELECT", "2"]), ok());
+    assert_eq!(client.call(&["SET", "key", "other"]), ok());
+
+    let lines = audited(&path, 6);
+    for line in &lines {
+        assert!(line.starts_with("{\"time\":"), "{}", line);
+    }
+    assert_eq!(
+        lines.iter().map(|line| without_time(line)).collect::<Vec<_>>(),
+        vec![
+            format!("{},\"name\":\"\",\"user\":\"default\",\"db\":0,\"command\":[\"CLIENT\",\"ID\"],\"outcome\":\"ok\"}}", who),
+            format!("{},\"name\":\"\",\"user\":\"default\",\"db\":0,\"command\":[\"CLIENT\",\"INFO\"],\"outcome\":\"ok\"}}", who),
+            format!("{},\"name\":\"\",\"user\":\"default\",\"db\":0,\"command\":[\"CONFIG\",\"GET\",\"auditlog-max-size\"],\"outcome\":\"ok\"}}", who),
+            format!("{},\"name\":\"auditor\",\"user\":\"default\",\"db\":0,\"command\":[\"SET\",\"key\",\"value\"],\"outcome\":\"ok\"}}", who),
+            format!("{},\"name\":\"auditor\",\"user\":\"default\",\"db\":0,\"command\":[\"LPUSH\",\"key\",\"x\"],\"outcome\":\"error\",\"error\":\"WRONGTYPE Operation against a key holding the wrong kind of value\"}}", who),
+            format!("{},\"name\":\"auditor\",\"user\":\"default\",\"db\":2,\"command\":[\"SET\",\"key\",\"other\"],\"outcome\":\"ok\"}}", who),
+        ]
+    );
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "auditlog"]),
+        RespValue::Array(vec![bulk("auditlog"), bulk(path.to_str().unwrap())])
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "auditlog", "elsewhere"]),
+        RespValue::Error(
+            "ERR CONFIG SET failed (possibly related to argument 'auditlog') - can't set immutable config"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn the_audit_log_rotates_past_auditlog_max_size() {
+    let dir = temp_dir("auditlog-rotation");
+    let path = dir.join("audit.log");
+    let server = Server::start(&[
+        "--auditlog",
+        path.to_str().unwrap(),
+        "--auditlog-max-size",
+        "1kb",
+    ]);
+    let mut client = server.connect();
+
+    for i in 0..40 {
+        assert_eq!(client.call(&["SET", &format!("key:{}", i), "value"]), ok());
+    }
+    assert_eq!(client.call(&["SET", "last", "value"]), ok());
+
+    // every SET is in one of the files, the full ones renamed with a suffix
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let mut rotated = 0;
+        let mut sets = 0;
+        for entry in fs::read_dir(&dir).unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().into_string().unwrap();
+            let contents = fs::read_to_string(entry.path()).unwrap();
+
+            if name != "audit.log" {
+                assert!(name.starts_with("audit.log."), "{}", name);
+                assert!(
+                    contents.len() >= 1024,
+                    "{} has {} bytes",
+                    name,
+                    contents.len()
+                );
+                rotated += 1;
+            }
+            sets += contents.lines().count();
+        }
+
+        if sets == 41 {
+            assert!(rotated >= 2, "only {} rotated files", rotated);
+            break;
+        }
+        assert!(Instant::now() < deadline, "{} SETs in the audit log", sets);
+        sleep(Duration::from_millis(20));
+    }
+}
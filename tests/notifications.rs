@@ -0,0 +1,97 @@
+// Keyspace notifications go out on the channels of the database the key lives in, so a subscriber
+// to db 1 doesn't hear about the same key name changing in db 0.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn message(channel: &str, payload: &str) -> RespValue {
+    RespValue::Array(vec![bulk("message"), bulk(channel), bulk(payload)])
+}
+
+fn subscribed(channel: &str, count: i64) -> RespValue {
+    RespValue::Array(vec![
+        bulk("subscribe"),
+        bulk(channel),
+        RespValue::Integer(count),
+    ])
+}
+
+#[test]
+fn events_carry_the_database_of_the_key() {
+    let server = Server::start(&["--notify-keyspace-events", "KEA"]);
+    let mut subscriber = server.connect();
+    let mut client = server.connect();
+
+    assert_eq!(
+        subscriber.call(&["SUBSCRIBE", "__keyspace@1__:foo", "__keyevent@1__:set"]),
+        subscribed("__keyspace@1__:foo", 1)
+    );
+    assert_eq!(subscriber.receive(), subscribed("__keyevent@1__:set", 2));
+
+    // the same key in db 0 is not what the subscriber asked about
+    assert_eq!(client.call(&["SET", "foo", "zero"]), ok());
+    assert_eq!(client.call(&["SELECT", "1"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "one"]), ok());
+
+    assert_eq!(subscriber.receive(), message("__keyspace@1__:foo", "set"));
+    assert_eq!(subscriber.receive(), message("__keyevent@1__:set", "foo"));
+}
+
+#[test]
+fn copy_notifies_in_the_destination_database() {
+    let server = Server::start(&["--notify-keyspace-events", "KEA"]);
+    let mut subscriber = server.connect();
+    let mut client = server.connect();
+
+    assert_eq!(
+        subscriber.call(&["SUBSCRIBE", "__keyevent@2__:copy_to"]),
+        subscribed("__keyevent@2__:copy_to", 1)
+    );
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(
+        client.call(&["COPY", "foo", "baz", "DB", "2"]),
+        RespValue::Integer(1)
+    );
+
+    assert_eq!(
+        subscriber.receive(),
+        message("__keyevent@2__:copy_to", "baz")
+    );
+}
+
+#[test]
+fn expired_keys_are_announced() {
+    let server = Server::start(&["--notify-keyspace-events", "Ex"]);
+    let mut subscriber = server.connect();
+    let mut client = server.connect();
+
+    assert_eq!(
+        subscriber.call(&["SUBSCRIBE", "__keyevent@0__:expired"]),
+        subscribed("__keyevent@0__:expired", 1)
+    );
+
+    assert_eq!(client.call(&["SET", "foo", "bar", "PX", "10"]), ok());
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+
+    assert_eq!(
+        subscriber.receive(),
+        message("__keyevent@0__:expired", "foo")
+    );
+}
+
+#[test]
+fn nothing_is_published_by_default() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "notify-keyspace-events"]),
+        RespValue::Array(vec![bulk("notify-keyspace-events"), bulk("")])
+    );
+}
@@ -0,0 +1,79 @@
+// Over maxmemory under noeviction, writes that may grow the dataset are refused with -OOM. Reads
+// and DEL still go through, so the dataset can be brought back under, and a replica still applies
+// what its master sends.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn oom() -> RespValue {
+    RespValue::Error("OOM command not allowed when used memory > 'maxmemory'.".to_string())
+}
+
+#[test]
+fn writes_are_refused_until_the_dataset_is_back_under() {
+    let server = Server::start(&["--maxmemory", "100"]);
+    let mut client = server.connect();
+
+    // 4 bytes of key and 100 of value, over by itself
+    assert_eq!(client.call(&["SET", "big1", &"v".repeat(100)]), ok());
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), oom());
+    assert_eq!(client.call(&["APPEND", "big1", "more"]), oom());
+    assert_eq!(client.call(&["SETRANGE", "big1", "0", "x"]), oom());
+    assert_eq!(client.call(&["COPY", "big1", "big2"]), oom());
+
+    // reads go on
+    assert_eq!(client.call(&["GET", "big1"]), bulk(&"v".repeat(100)));
+    assert_eq!(client.call(&["STRLEN", "big1"]), RespValue::Integer(100));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(1));
+
+    // DEL is how to get out of it
+    assert_eq!(client.call(&["DEL", "big1"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+}
+
+#[test]
+fn a_refused_write_dooms_its_transaction() {
+    let server = Server::start(&["--maxmemory", "100"]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "big", &"v".repeat(100)]), ok());
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["GET", "big"]), simple("QUEUED"));
+    assert_eq!(client.call(&["SET", "foo", "bar"]), oom());
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Error("EXECABORT Transaction discarded because of previous errors.".to_string())
+    );
+}
+
+#[test]
+fn other_policies_evict_instead() {
+    let server = Server::start(&["--maxmemory", "100", "--maxmemory-policy", "allkeys-random"]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "big", &"v".repeat(100)]), ok());
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(client.call(&["GET", "big"]), RespValue::Null);
+}
+
+#[test]
+fn replicas_apply_what_their_master_sends() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address(), "--maxmemory", "100"]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    for n in 0..5 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("key:{}", n), &"v".repeat(100)]),
+            ok()
+        );
+    }
+
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(5));
+}
@@ -0,0 +1,518 @@
+// Conformance matrix for parse_command: command name casing, argument counts and
+// declared bulk lengths. Error cases assert the exact text the server replies with.
+
+use std::time::Duration;
+
+use redis_starter_rust::{
+    parsers::parse_command,
+    protocol::{
+        ClientCommandParameter, ClientListFilter, CopyCommandParameter, DebugCommandParameter,
+        Failpoint, InfoCommandParameter, RedisCommand, ReplConfCommandParameter,
+        ScanCommandParameter, SetCommandExpireOption, SetCommandParameter, SetCommandSetOption,
+        ShutdownCommandParameter,
+    },
+    resp::value::RespValue,
+};
+
+// Encodes a request the same way the processor hands it to the parser.
+fn request(args: &[&str]) -> String {
+    RespValue::array_from_slice(args)
+        .to_encoded_string()
+        .expect("request encodes")
+}
+
+fn reply(input: &str) -> String {
+    match parse_command(input) {
+        Ok(command) => format!("{:?}", command),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn set(key: &str, value: &str) -> SetCommandParameter {
+    SetCommandParameter {
+        key: key.to_string(),
+        value: value.as_bytes().to_vec(),
+        option: None,
+        get: None,
+        expire: None,
+    }
+}
+
+#[test]
+fn command_names_are_case_insensitive() {
+    for name in ["PING", "ping", "Ping", "pInG"] {
+        assert_eq!(
+            parse_command(&request(&[name])).unwrap(),
+            RedisCommand::Ping(None)
+        );
+    }
+    assert_eq!(
+        parse_command(&request(&["ping", "Hello"])).unwrap(),
+        RedisCommand::Ping(Some("Hello".to_string()))
+    );
+
+    for name in ["GET", "get", "gEt"] {
+        assert_eq!(
+            parse_command(&request(&[name, "foo"])).unwrap(),
+            RedisCommand::Get("foo".to_string())
+        );
+    }
+
+    assert_eq!(
+        parse_command(&request(&["echo", "Hello World"])).unwrap(),
+        RedisCommand::Echo("Hello World".to_string())
+    );
+    assert_eq!(
+        parse_command(&request(&["command", "docs"])).unwrap(),
+        RedisCommand::Command
+    );
+    assert_eq!(
+        parse_command(&request(&["wait", "1", "500"])).unwrap(),
+        RedisCommand::Wait(1, 500)
+    );
+    assert_eq!(
+        parse_command(&request(&["waitaof", "0", "1", "100"])).unwrap(),
+        RedisCommand::WaitAof(0, 1, 100)
+    );
+    assert_eq!(
+        parse_command(&request(&["role"])).unwrap(),
+        RedisCommand::Role
+    );
+    assert_eq!(
+        parse_command(&request(&["Subscribe", "News", "weather"])).unwrap(),
+        RedisCommand::Subscribe(vec!["News".to_string(), "weather".to_string()])
+    );
+    assert_eq!(
+        parse_command(&request(&["unsubscribe"])).unwrap(),
+        RedisCommand::Unsubscribe(vec![])
+    );
+    assert_eq!(
+        parse_command(&request(&["publish", "news", "Hello"])).unwrap(),
+        RedisCommand::Publish("news".to_string(), "Hello".to_string())
+    );
+    assert_eq!(
+        parse_command(&request(&["multi"])).unwrap(),
+        RedisCommand::Multi
+    );
+    assert_eq!(
+        parse_command(&request(&["Exec"])).unwrap(),
+        RedisCommand::Exec
+    );
+    assert_eq!(
+        parse_command(&request(&["client", "List"])).unwrap(),
+        RedisCommand::Client(ClientCommandParameter::List(None))
+    );
+    assert_eq!(
+        parse_command(&request(&["CLIENT", "LIST", "type", "pubsub"])).unwrap(),
+        RedisCommand::Client(ClientCommandParameter::List(Some(ClientListFilter::Type(
+            "pubsub".to_string()
+        ))))
+    );
+    assert_eq!(
+        parse_command(&request(&["CLIENT", "LIST", "ID", "3", "5"])).unwrap(),
+        RedisCommand::Client(ClientCommandParameter::List(Some(ClientListFilter::Ids(
+            vec![3, 5]
+        ))))
+    );
+}
+
+#[test]
+fn keywords_are_case_insensitive() {
+    let mut expected = set("foo", "bar");
+    expected.option = Some(SetCommandSetOption::NX);
+    expected.get = Some(true);
+
+    for (nx, get) in [("NX", "GET"), ("nx", "get"), ("Nx", "gEt")] {
+        assert_eq!(
+            parse_command(&request(&["set", "foo", "bar", nx, get])).unwrap(),
+            RedisCommand::Set(expected.clone())
+        );
+    }
+
+    for get in ["get", "GET", "Get"] {
+        assert_eq!(
+            parse_command(&request(&["config", get, "dir", "max*"])).unwrap(),
+            RedisCommand::Config(vec!["dir".to_string(), "max*".to_string()])
+        );
+    }
+    assert_eq!(
+        parse_command(&request(&["CONFIG", "resetstat"])).unwrap(),
+        RedisCommand::ConfigResetstat
+    );
+    assert_eq!(
+        parse_command(&request(&[
+            "CONFIG",
+            "set",
+            "maxmemory",
+            "100mb",
+            "hz",
+            "20"
+        ]))
+        .unwrap(),
+        RedisCommand::ConfigSet(vec![
+            ("maxmemory".to_string(), "100mb".to_string()),
+            ("hz".to_string(), "20".to_string()),
+        ])
+    );
+
+    assert_eq!(
+        parse_command(&request(&["INFO", "Replication"])).unwrap(),
+        RedisCommand::Info(Some(InfoCommandParameter::Replication))
+    );
+    assert_eq!(
+        parse_command(&request(&["REPLCONF", "GETACK", "*"])).unwrap(),
+        RedisCommand::ReplConf(ReplConfCommandParameter::Getack("*".to_string()))
+    );
+    assert_eq!(
+        parse_command(&request(&["replconf", "Listening-Port", "6380"])).unwrap(),
+        RedisCommand::ReplConf(ReplConfCommandParameter::ListeningPort(6380))
+    );
+    assert_eq!(
+        parse_command(&request(&["debug", "FailPoint", "drop", "2"])).unwrap(),
+        RedisCommand::Debug(DebugCommandParameter::Failpoint(Failpoint::Drop(2)))
+    );
+    assert_eq!(
+        parse_command(&request(&["DEBUG", "object", "Foo"])).unwrap(),
+        RedisCommand::Debug(DebugCommandParameter::Object("Foo".to_string()))
+    );
+    assert_eq!(
+        parse_command(&request(&["DEBUG", "sleep", "0.5"])).unwrap(),
+        RedisCommand::Debug(DebugCommandParameter::Sleep(Duration::from_millis(500)))
+    );
+    assert_eq!(
+        parse_command(&request(&["debug", "panic"])).unwrap(),
+        RedisCommand::Debug(DebugCommandParameter::Panic)
+    );
+    assert_eq!(
+        parse_command(&request(&["COPY", "a", "b", "replace", "db", "3"])).unwrap(),
+        RedisCommand::Copy(CopyCommandParameter {
+            source: "a".to_string(),
+            destination: "b".to_string(),
+            db: Some(3),
+            replace: true,
+        })
+    );
+    assert_eq!(
+        parse_command(&request(&["shutdown", "now", "Save"])).unwrap(),
+        RedisCommand::Shutdown(ShutdownCommandParameter {
+            save: true,
+            now: true,
+        })
+    );
+    assert_eq!(
+        parse_command(&request(&["SHUTDOWN", "nosave"])).unwrap(),
+        RedisCommand::Shutdown(ShutdownCommandParameter::default())
+    );
+    assert_eq!(
+        parse_command(&request(&["scan", "17", "count", "5", "Match", "user:*"])).unwrap(),
+        RedisCommand::Scan(ScanCommandParameter {
+            cursor: 17,
+            pattern: Some("user:*".to_string()),
+            value_type: None,
+            count: 5,
+        })
+    );
+}
+
+#[test]
+fn keys_and_values_keep_their_case() {
+    assert_eq!(
+        parse_command(&request(&["SET", "Foo", "BaR"])).unwrap(),
+        RedisCommand::Set(set("Foo", "BaR"))
+    );
+    assert_eq!(
+        parse_command(&request(&["DEL", "a", "B", "c"])).unwrap(),
+        RedisCommand::Del(vec!["a".to_string(), "B".to_string(), "c".to_string()])
+    );
+}
+
+#[test]
+fn set_options_in_any_order() {
+    for args in [
+        ["SET", "foo", "bar", "PX", "100", "NX"],
+        ["SET", "foo", "bar", "NX", "PX", "100"],
+    ] {
+        match parse_command(&request(&args)).unwrap() {
+            RedisCommand::Set(SetCommandParameter {
+                option: Some(SetCommandSetOption::NX),
+                expire: Some(SetCommandExpireOption::PX(_)),
+                ..
+            }) => {}
+            other => panic!("unexpected parse of {:?}: {:?}", args, other),
+        }
+    }
+}
+
+#[test]
+fn set_keepttl() {
+    assert_eq!(
+        parse_command(&request(&["SET", "foo", "bar", "KEEPTTL"])).unwrap(),
+        RedisCommand::Set(SetCommandParameter {
+            expire: Some(SetCommandExpireOption::KEEPTTL),
+            ..set("foo", "bar")
+        })
+    );
+}
+
+#[test]
+fn set_absolute_deadlines() {
+    assert_eq!(
+        parse_command(&request(&["SET", "foo", "bar", "pxat", "1700000000000"])).unwrap(),
+        RedisCommand::Set(SetCommandParameter {
+            expire: Some(SetCommandExpireOption::PXAT(1_700_000_000_000)),
+            ..set("foo", "bar")
+        })
+    );
+    assert_eq!(
+        parse_command(&request(&["SET", "foo", "bar", "EXAT", "1700000000"])).unwrap(),
+        RedisCommand::Set(SetCommandParameter {
+            expire: Some(SetCommandExpireOption::EXAT(1_700_000_000)),
+            ..set("foo", "bar")
+        })
+    );
+}
+
+#[test]
+fn argument_counts() {
+    let cases: &[(&[&str], &str)] = &[
+        (&["GET"], "ERR wrong number of arguments for 'get' command"),
+        (
+            &["GET", "a", "b"],
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        (
+            &["get", "a", "b"],
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        (
+            &["SET", "a"],
+            "ERR wrong number of arguments for 'set' command",
+        ),
+        (&["DEL"], "ERR wrong number of arguments for 'del' command"),
+        (
+            &["MGET"],
+            "ERR wrong number of arguments for 'mget' command",
+        ),
+        (
+            &["ECHO"],
+            "ERR wrong number of arguments for 'echo' command",
+        ),
+        (
+            &["ECHO", "a", "b"],
+            "ERR wrong number of arguments for 'echo' command",
+        ),
+        (
+            &["APPEND", "a"],
+            "ERR wrong number of arguments for 'append' command",
+        ),
+        (
+            &["STRLEN"],
+            "ERR wrong number of arguments for 'strlen' command",
+        ),
+        (
+            &["KEYS"],
+            "ERR wrong number of arguments for 'keys' command",
+        ),
+        (
+            &["CONFIG"],
+            "ERR wrong number of arguments for 'config' command",
+        ),
+        (
+            &["PSYNC", "?"],
+            "ERR wrong number of arguments for 'psync' command",
+        ),
+        (
+            &["WAIT", "1"],
+            "ERR wrong number of arguments for 'wait' command",
+        ),
+        (
+            &["WAITAOF", "0", "1"],
+            "ERR wrong number of arguments for 'waitaof' command",
+        ),
+        (
+            &["ROLE", "master"],
+            "ERR wrong number of arguments for 'role' command",
+        ),
+        (
+            &["SCAN"],
+            "ERR wrong number of arguments for 'scan' command",
+        ),
+        (
+            &["SUBSCRIBE"],
+            "ERR wrong number of arguments for 'subscribe' command",
+        ),
+        (
+            &["PUBLISH", "news"],
+            "ERR wrong number of arguments for 'publish' command",
+        ),
+        (
+            &["EXEC", "now"],
+            "ERR wrong number of arguments for 'exec' command",
+        ),
+        (
+            &["PING", "a", "b"],
+            "ERR wrong number of arguments for 'ping' command",
+        ),
+    ];
+
+    for (args, expected) in cases {
+        assert_eq!(reply(&request(args)), *expected, "{:?}", args);
+    }
+}
+
+#[test]
+fn expire_deadlines() {
+    assert_eq!(
+        parse_command(&request(&["PEXPIREAT", "foo", "1700000000000"])).unwrap(),
+        RedisCommand::Expire("foo".to_string(), 1_700_000_000_000)
+    );
+    assert_eq!(
+        parse_command(&request(&["expireat", "foo", "-1"])).unwrap(),
+        RedisCommand::Expire("foo".to_string(), -1000)
+    );
+    assert_eq!(
+        parse_command(&request(&["TTL", "foo"])).unwrap(),
+        RedisCommand::Ttl("foo".to_string())
+    );
+}
+
+#[test]
+fn bad_arguments() {
+    let cases: &[(&[&str], &str)] = &[
+        (&["SET", "a", "b", "NX", "XX"], "ERR syntax error"),
+        (
+            &["SET", "a", "b", "EX", "10", "PX", "10"],
+            "ERR syntax error",
+        ),
+        (
+            &["SET", "a", "b", "PX", "10", "KEEPTTL"],
+            "ERR syntax error",
+        ),
+        (
+            &["SET", "a", "b", "PX", "10", "PXAT", "10"],
+            "ERR syntax error",
+        ),
+        (&["SET", "a", "b", "EX"], "ERR syntax error"),
+        (&["SET", "a", "b", "FOO"], "ERR syntax error"),
+        (
+            &["SET", "a", "b", "EX", "ten"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["SET", "a", "b", "px", "1.5"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["WAIT", "one", "0"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["EXPIRE", "a", "ten"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["EXPIRE", "a", "9223372036854775807"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["PTTL", "a", "b"],
+            "ERR wrong number of arguments for 'pttl' command",
+        ),
+        (&["CONFIG", "REWRITE"], "ERR syntax error"),
+        (&["CONFIG", "SET", "maxmemory"], "ERR syntax error"),
+        (
+            &["CONFIG", "SET", "maxmemory", "1mb", "maxmemory-policy"],
+            "ERR syntax error",
+        ),
+        (&["CLIENT", "KILL", "1"], "ERR syntax error"),
+        (&["SHUTDOWN", "NOSAVE", "SAVE"], "ERR syntax error"),
+        (&["SHUTDOWN", "ABORT"], "ERR syntax error"),
+        (
+            &["SCAN", "-1"],
+            "ERR value is not an integer or out of range",
+        ),
+        (&["SCAN", "0", "COUNT", "0"], "ERR syntax error"),
+        (
+            &["REPLCONF", "listening-port", "port"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["FOO", "a", "b"],
+            "ERR unknown command 'FOO', with args beginning with: 'a' 'b' ",
+        ),
+        (
+            &["foo"],
+            "ERR unknown command 'foo', with args beginning with: ",
+        ),
+    ];
+
+    for (args, expected) in cases {
+        assert_eq!(reply(&request(args)), *expected, "{:?}", args);
+    }
+}
+
+#[test]
+fn repeating_an_option_is_not_a_conflict() {
+    let mut expected = set("a", "b");
+    expected.option = Some(SetCommandSetOption::XX);
+    expected.get = Some(true);
+
+    assert_eq!(
+        parse_command(&request(&["SET", "a", "b", "XX", "GET", "xx", "get"])).unwrap(),
+        RedisCommand::Set(expected)
+    );
+}
+
+#[test]
+fn declared_lengths() {
+    let cases = [
+        // declared length shorter than the data
+        (
+            "*2\r\n$3\r\nGET\r\n$2\r\nfoo\r\n",
+            "ERR Protocol error: invalid bulk length",
+        ),
+        // declared length longer than the data
+        (
+            "*2\r\n$3\r\nGET\r\n$5\r\nfoo\r\n",
+            "ERR Protocol error: invalid bulk length",
+        ),
+        // the command name itself
+        (
+            "*2\r\n$3\r\nGETX\r\n$3\r\nfoo\r\n",
+            "ERR Protocol error: invalid bulk length",
+        ),
+        (
+            "*1\r\n$5\r\nPING\r\n",
+            "ERR Protocol error: invalid bulk length",
+        ),
+        // fewer elements than the array declares
+        (
+            "*3\r\n$3\r\nGET\r\n$3\r\nfoo\r\n",
+            "ERR wrong number of arguments for 'get' command",
+        ),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(reply(input), expected, "{:?}", input);
+    }
+}
+
+#[test]
+fn data_is_taken_by_declared_length() {
+    // \r\n inside a bulk string is data, not a terminator.
+    assert_eq!(
+        parse_command("*2\r\n$4\r\nECHO\r\n$6\r\nab\r\ncd\r\n").unwrap(),
+        RedisCommand::Echo("ab\r\ncd".to_string())
+    );
+
+    // lengths are in bytes, not chars
+    assert_eq!(
+        parse_command(&request(&["ECHO", "héllo wörld"])).unwrap(),
+        RedisCommand::Echo("héllo wörld".to_string())
+    );
+
+    assert_eq!(
+        parse_command(&request(&["ECHO", ""])).unwrap(),
+        RedisCommand::Echo(String::new())
+    );
+}
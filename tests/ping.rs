@@ -0,0 +1,53 @@
+// PING replies PONG, or the message it was given as a bulk string. A RESP2 connection that is
+// subscribed gets a pong message instead, the only kind of frame it expects.
+
+mod common;
+
+use common::{bulk, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn ping_echoes_its_message() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+    assert_eq!(client.call(&["PING", "hello world"]), bulk("hello world"));
+    assert_eq!(
+        client.call(&["PING", "a", "b"]),
+        RespValue::Error("ERR wrong number of arguments for 'ping' command".to_string())
+    );
+}
+
+#[test]
+fn subscribed_ping_gets_a_pong_message() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    client.call(&["SUBSCRIBE", "news"]);
+
+    assert_eq!(
+        client.call(&["PING"]),
+        RespValue::Array(vec![bulk("pong"), bulk("")])
+    );
+    assert_eq!(
+        client.call(&["PING", "still there?"]),
+        RespValue::Array(vec![bulk("pong"), bulk("still there?")])
+    );
+
+    // back to plain replies once it has no subscriptions left
+    client.call(&["UNSUBSCRIBE"]);
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+}
+
+#[test]
+fn subscribed_resp3_ping_gets_a_reply() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    client.call(&["HELLO", "3"]);
+    client.call(&["SUBSCRIBE", "news"]);
+
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+    assert_eq!(client.call(&["PING", "hi"]), bulk("hi"));
+}
@@ -0,0 +1,122 @@
+// Bulk loading the way redis-cli --pipe does it: a stream of commands written without waiting on
+// a single reply, RESP and inline mixed, then an ECHO of a random sentinel. Every reply up to the
+// sentinel's is one of the commands', so seeing it come back means they have all been applied.
+
+mod common;
+
+use std::{
+    io::{BufWriter, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use bytes::BytesMut;
+use common::{bulk, simple, Server};
+use rand::{distributions::Alphanumeric, Rng};
+use redis_starter_rust::resp::{codec::RespCodec, value::RespValue};
+use tokio_util::codec::Decoder;
+
+// enough to fill the socket buffers many times over, few enough to load in a moment
+const KEYS: usize = 10_000;
+
+// Writes the commands and the ECHO on a thread of its own, and reads the replies until the
+// sentinel's, the way redis-cli does. Returns the replies to the commands.
+fn pipe(port: u16, commands: Vec<u8>) -> Vec<RespValue> {
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+
+    let sentinel: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect();
+    let echo = RespValue::array_from_slice(&["ECHO", &sentinel])
+        .to_encoded_string()
+        .unwrap();
+
+    let mut writer = BufWriter::new(stream.try_clone().unwrap());
+    let writing = std::thread::spawn(move || {
+        writer.write_all(&commands).unwrap();
+        writer.write_all(echo.as_bytes()).unwrap();
+        writer.flush().unwrap();
+    });
+
+    let mut reader = stream;
+    let mut buffer = BytesMut::new();
+    let mut codec = RespCodec::new();
+    let mut replies = Vec::new();
+
+    loop {
+        match codec.decode(&mut buffer).unwrap() {
+            Some(RespValue::BulkString(Some(reply))) if reply == sentinel.as_bytes() => break,
+            Some(reply) => replies.push(reply),
+            None => {
+                let mut chunk = [0; 65536];
+                let read = reader.read(&mut chunk).unwrap();
+                assert!(read > 0, "closed before the sentinel came back");
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+        }
+    }
+
+    writing.join().unwrap();
+
+    replies
+}
+
+#[test]
+fn pipe_loads_10k_keys() {
+    let server = Server::start(&[]);
+
+    // every other one inline, as a file of redis-cli commands would have them
+    let commands: Vec<u8> = (0..KEYS)
+        .flat_map(|i| {
+            let (key, value) = (format!("key:{}", i), format!("value:{}", i));
+
+            if i % 2 == 0 {
+                RespValue::array_from_slice(&["SET", &key, &value])
+                    .to_encoded_string()
+                    .unwrap()
+                    .into_bytes()
+            } else {
+                format!("SET {} {}\r\n", key, value).into_bytes()
+            }
+        })
+        .collect();
+
+    let replies = pipe(server.port, commands);
+
+    assert_eq!(replies.len(), KEYS);
+    assert!(replies.iter().all(|reply| *reply == simple("OK")));
+
+    let mut client = server.connect();
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(KEYS as i64));
+    assert_eq!(client.call(&["GET", "key:0"]), bulk("value:0"));
+    assert_eq!(client.call(&["GET", "key:9999"]), bulk("value:9999"));
+}
+
+#[test]
+fn pipe_errors_are_replies_like_any_other() {
+    let server = Server::start(&[]);
+
+    let commands = [
+        "SET counter 1\r\n",
+        "INCR counter\r\n",
+        "LPUSH counter x\r\n",
+        "NOSUCHCOMMAND\r\n",
+        "INCRBY counter 10\r\n",
+    ]
+    .concat()
+    .into_bytes();
+
+    let replies = pipe(server.port, commands);
+
+    let errors = replies
+        .iter()
+        .filter(|reply| matches!(reply, RespValue::Error(_)))
+        .count();
+    assert_eq!((replies.len(), errors), (5, 2));
+    assert_eq!(replies[4], RespValue::Integer(12));
+}
@@ -0,0 +1,332 @@
+// Which commands reach the replicas is decided by the write flag in the command table, and a write
+// only goes out if it changed something. A TTL reaches them as the absolute deadline the master
+// worked out, never as one relative to now.
+
+mod common;
+
+use common::{bulk, bulk_bytes, ok, simple, Server};
+use redis_starter_rust::{parsers::command_flags, protocol::CommandFlag, resp::value::RespValue};
+
+fn flags(args: &[&str]) -> &'static [CommandFlag] {
+    command_flags(
+        &RespValue::array_from_slice(args)
+            .to_encoded_string()
+            .expect("request encodes"),
+    )
+}
+
+#[test]
+fn flags_come_from_the_command_table() {
+    assert_eq!(
+        flags(&["set", "a", "b"]),
+        &[CommandFlag::Write, CommandFlag::DenyOom]
+    );
+    assert_eq!(
+        flags(&["APPEND", "a", "b"]),
+        &[CommandFlag::Write, CommandFlag::DenyOom]
+    );
+    assert_eq!(
+        flags(&["SETRANGE", "a", "0", "b"]),
+        &[CommandFlag::Write, CommandFlag::DenyOom]
+    );
+    // it only frees memory, so it still goes past maxmemory
+    assert_eq!(flags(&["DEL", "a"]), &[CommandFlag::Write]);
+    assert_eq!(flags(&["GET", "a"]), &[CommandFlag::Readonly]);
+    assert_eq!(flags(&["WAIT", "0", "0"]), &[CommandFlag::Blocking]);
+    assert_eq!(flags(&["PING"]), &[]);
+    assert_eq!(flags(&["NOSUCHCOMMAND"]), &[]);
+}
+
+#[test]
+fn writes_reach_the_replica() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "a", "1"]), ok());
+    assert_eq!(to_master.call(&["APPEND", "a", "2"]), RespValue::Integer(2));
+    assert_eq!(to_master.call(&["SET", "b", "1"]), ok());
+    assert_eq!(to_master.call(&["DEL", "b"]), RespValue::Integer(1));
+
+    // a write that fails to parse is not propagated
+    assert!(matches!(
+        to_master.call(&["SET", "b", "1", "EX"]),
+        RespValue::Error(_)
+    ));
+    assert_eq!(to_master.call(&["SET", "done", "1"]), ok());
+
+    to_replica.wait_for(&["GET", "done"], bulk("1"));
+    assert_eq!(to_replica.call(&["GET", "a"]), bulk("12"));
+    assert_eq!(to_replica.call(&["GET", "b"]), RespValue::Null);
+}
+
+#[test]
+fn partial_writes_go_out_as_sent() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    // the handshake by hand, to see the stream exactly as the master sends it
+    let mut replica = master.connect();
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+    assert!(matches!(
+        replica.call(&["PSYNC", "?", "-1"]),
+        RespValue::SimpleString(reply) if reply.starts_with("FULLRESYNC ")
+    ));
+    assert!(matches!(replica.receive(), RespValue::Rdb(_)));
+
+    let writes: [&[&str]; 3] = [
+        &["APPEND", "a", "hello"],
+        &["SETRANGE", "a", "1", "ipp"],
+        &["SETRANGE", "b", "2", "x"],
+    ];
+    for write in writes {
+        assert!(matches!(to_master.call(write), RespValue::Integer(_)));
+    }
+
+    // not turned into SETs of the whole value
+    for write in writes {
+        assert_eq!(replica.receive(), RespValue::array_from_slice(write));
+    }
+}
+
+#[test]
+fn replica_values_match_after_partial_writes() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    let writes: [&[&str]; 10] = [
+        &["SET", "a", "hello world"],
+        &["SETRANGE", "a", "6", "redis"],
+        &["APPEND", "a", "!"],
+        &["SETRANGE", "a", "20", "padded"],
+        &["SETRANGE", "b", "3", "new"],
+        &["APPEND", "b", "er"],
+        &["SET", "c", "caf\u{e9}"],
+        &["SETRANGE", "c", "3", "\u{e8}s"],
+        &["SETRANGE", "c", "0", ""],
+        // values are bytes, this one leaves half of the \u{e8}
+        &["SETRANGE", "c", "4", "x"],
+    ];
+    for write in writes {
+        assert!(!matches!(to_master.call(write), RespValue::Error(_)));
+    }
+
+    let binary_writes: [&[&[u8]]; 3] = [
+        &[b"SET", b"d", b"\xff\xfe"],
+        &[b"APPEND", b"d", b"\x00\x80"],
+        &[b"SETRANGE", b"d", b"1", b"\xc0"],
+    ];
+    for write in binary_writes {
+        assert!(!matches!(to_master.call_bytes(write), RespValue::Error(_)));
+    }
+
+    // refused, so nothing for the replica either
+    assert!(matches!(
+        to_master.call(&["SETRANGE", "c", "-1", "x"]),
+        RespValue::Error(_)
+    ));
+
+    assert_eq!(to_master.call(&["SET", "done", "1"]), ok());
+    to_replica.wait_for(&["GET", "done"], bulk("1"));
+
+    for key in ["a", "b", "c", "d"] {
+        assert_eq!(
+            to_replica.call(&["GET", key]),
+            to_master.call(&["GET", key])
+        );
+    }
+    assert_eq!(
+        to_master.call(&["GET", "a"]),
+        bulk("hello redis!\0\0\0\0\0\0\0\0padded")
+    );
+    assert_eq!(to_master.call(&["GET", "b"]), bulk("\0\0\0newer"));
+    assert_eq!(to_master.call(&["GET", "c"]), bulk_bytes(b"caf\xc3xs"));
+    assert_eq!(
+        to_master.call(&["GET", "d"]),
+        bulk_bytes(b"\xff\xc0\x00\x80")
+    );
+}
+
+// ROLE on a master with no replicas attached.
+fn master_role(offset: i64) -> RespValue {
+    RespValue::Array(vec![
+        bulk("master"),
+        RespValue::Integer(offset),
+        RespValue::Array(vec![]),
+    ])
+}
+
+#[test]
+fn only_writes_move_the_offset() {
+    let master = Server::start(&[]);
+    let mut client = master.connect();
+
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+    client.call(&["INFO", "replication"]);
+    client.call(&["CONFIG", "GET", "dir"]);
+    client.call(&["ECHO", "hello"]);
+    client.call(&["GET", "a"]);
+    client.call(&["DBSIZE"]);
+    client.call(&["PUBLISH", "news", "hello"]);
+    assert_eq!(client.call(&["ROLE"]), master_role(0));
+
+    // the offset moves asynchronously, but by exactly the SET and not a byte more for anything above
+    assert_eq!(client.call(&["SET", "a", "1"]), ok());
+    let set_length = "*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n".len() as i64;
+    client.wait_for(&["ROLE"], master_role(set_length));
+}
+
+// The deadline of each key DEBUG JMAP lists, in unix milliseconds.
+fn deadlines(client: &mut common::Client) -> Vec<(RespValue, i64)> {
+    let RespValue::Array(jmap) = client.call(&["DEBUG", "JMAP"]) else {
+        panic!("DEBUG JMAP replies with an array");
+    };
+
+    jmap.chunks(2)
+        .map(|pair| match pair {
+            [key, RespValue::Integer(deadline)] => (key.clone(), *deadline),
+            _ => panic!("key, deadline pairs: {:?}", jmap),
+        })
+        .collect()
+}
+
+#[test]
+fn ttls_go_out_as_absolute_deadlines() {
+    let master = Server::start(&["--enable-debug-command"]);
+    let mut to_master = master.connect();
+
+    let mut replica = master.connect();
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+    assert!(matches!(
+        replica.call(&["PSYNC", "?", "-1"]),
+        RespValue::SimpleString(reply) if reply.starts_with("FULLRESYNC ")
+    ));
+    assert!(matches!(replica.receive(), RespValue::Rdb(_)));
+
+    assert_eq!(to_master.call(&["SET", "a", "1", "EX", "100"]), ok());
+    assert_eq!(
+        to_master.call(&["SET", "b", "2", "px", "100000", "NX"]),
+        ok()
+    );
+    assert_eq!(
+        to_master.call(&["SET", "c", "3", "EXAT", "4102444800"]),
+        ok()
+    );
+    // nothing of its own to make absolute
+    assert_eq!(to_master.call(&["SET", "c", "4", "KEEPTTL"]), ok());
+    assert_eq!(to_master.call(&["SET", "d", "5"]), ok());
+
+    let deadlines = deadlines(&mut to_master);
+    assert_eq!(
+        deadlines
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>(),
+        [bulk("a"), bulk("b"), bulk("c")]
+    );
+    let [a, b, c] = [deadlines[0].1, deadlines[1].1, deadlines[2].1].map(|ms| ms.to_string());
+    assert_eq!(c, "4102444800000");
+
+    // byte for byte, the options the TTL didn't replace kept where they were
+    let stream: [&[&str]; 5] = [
+        &["SET", "a", "1", "PXAT", &a],
+        &["SET", "b", "2", "NX", "PXAT", &b],
+        &["SET", "c", "3", "PXAT", &c],
+        &["SET", "c", "4", "KEEPTTL"],
+        &["SET", "d", "5"],
+    ];
+    for frame in stream {
+        let expected = RespValue::array_from_slice(frame)
+            .to_encoded_string()
+            .expect("frame encodes");
+
+        assert_eq!(
+            String::from_utf8(replica.read_bytes(expected.len())).expect("RESP is text here"),
+            expected
+        );
+    }
+}
+
+#[test]
+fn replica_deadlines_match_the_master() {
+    let master = Server::start(&["--enable-debug-command"]);
+    let replica = Server::start(&["--replicaof", &master.address(), "--enable-debug-command"]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "ready", "1"]), ok());
+    to_replica.wait_for(&["GET", "ready"], bulk("1"));
+
+    assert_eq!(to_master.call(&["SET", "a", "1", "EX", "1000"]), ok());
+    assert_eq!(to_master.call(&["SET", "b", "2", "PX", "500000"]), ok());
+    assert_eq!(to_master.call(&["SET", "done", "1"]), ok());
+    to_replica.wait_for(&["GET", "done"], bulk("1"));
+
+    assert_eq!(deadlines(&mut to_replica), deadlines(&mut to_master));
+}
+
+#[test]
+fn writes_that_change_nothing_stay_off_the_stream() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    let mut replica = master.connect();
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+    assert!(matches!(
+        replica.call(&["PSYNC", "?", "-1"]),
+        RespValue::SimpleString(reply) if reply.starts_with("FULLRESYNC ")
+    ));
+    assert!(matches!(replica.receive(), RespValue::Rdb(_)));
+
+    let setup: [&[&str]; 3] = [
+        &["SET", "string", "1"],
+        &["HSET", "hash", "field", "1"],
+        &["SADD", "set", "member"],
+    ];
+    for write in setup {
+        assert!(!matches!(to_master.call(write), RespValue::Error(_)));
+        assert_eq!(replica.receive(), RespValue::array_from_slice(write));
+    }
+
+    let no_ops: [&[&str]; 13] = [
+        &["DEL", "missing"],
+        &["HDEL", "missing", "field"],
+        &["HDEL", "hash", "missing"],
+        &["SREM", "missing", "member"],
+        &["SREM", "set", "missing"],
+        &["SADD", "set", "member"],
+        &["LPOP", "missing"],
+        &["ZREM", "missing", "member"],
+        &["PERSIST", "missing"],
+        &["PERSIST", "string"],
+        &["EXPIRE", "missing", "10"],
+        &["SET", "string", "2", "NX"],
+        &["SETRANGE", "string", "0", ""],
+    ];
+    for write in no_ops {
+        assert!(!matches!(to_master.call(write), RespValue::Error(_)));
+    }
+
+    // refused, so nothing changed either
+    assert!(matches!(
+        to_master.call(&["LPUSH", "string", "a"]),
+        RespValue::Error(_)
+    ));
+    assert!(matches!(
+        to_master.call(&["INCR", "hash"]),
+        RespValue::Error(_)
+    ));
+
+    // the next thing on the stream is the next write that did something
+    assert_eq!(to_master.call(&["DEL", "string"]), RespValue::Integer(1));
+    assert_eq!(
+        replica.receive(),
+        RespValue::array_from_slice(&["DEL", "string"])
+    );
+}
@@ -0,0 +1,155 @@
+// A full resync hands the replica the master's offset as of the snapshot, so the two count the
+// stream from the same place however much the master wrote before the replica came along.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn master_repl_offset(client: &mut common::Client) -> i64 {
+    let RespValue::Array(role) = client.call(&["ROLE"]) else {
+        panic!("ROLE replies with an array");
+    };
+
+    // master: ["master", offset, replicas], replica: ["slave", host, port, state, offset]
+    match role.as_slice() {
+        [_, RespValue::Integer(offset), _] | [_, _, _, _, RespValue::Integer(offset)] => *offset,
+        _ => panic!("ROLE has an offset: {:?}", role),
+    }
+}
+
+fn master_replid(client: &mut common::Client) -> String {
+    let RespValue::SimpleString(info) = client.call(&["INFO", "replication"]) else {
+        panic!("INFO replication replies with a simple string");
+    };
+
+    info.split(':')
+        .skip_while(|field| *field != "master_replid")
+        .nth(1)
+        .expect("INFO replication has master_replid")
+        .to_string()
+}
+
+// Goes through the handshake as a replica, returning the offset of the +FULLRESYNC.
+fn full_resync(master: &Server) -> (common::Client, i64) {
+    let mut replica = master.connect();
+
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+
+    let RespValue::SimpleString(reply) = replica.call(&["PSYNC", "?", "-1"]) else {
+        panic!("PSYNC replies with a simple string");
+    };
+    let offset = reply
+        .strip_prefix("FULLRESYNC ")
+        .and_then(|reply| reply.split_once(' '))
+        .and_then(|(_, offset)| offset.parse().ok())
+        .unwrap_or_else(|| panic!("+FULLRESYNC <replid> <offset>, not {}", reply));
+
+    (replica, offset)
+}
+
+fn set_keys(client: &mut common::Client, count: usize) {
+    for n in 0..count {
+        assert_eq!(client.call(&["SET", &format!("key:{}", n), "value"]), ok());
+    }
+}
+
+#[test]
+fn fullresync_gives_the_master_offset() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    set_keys(&mut to_master, 10);
+    let written = master_repl_offset(&mut to_master);
+    assert!(written > 0);
+
+    let (mut replica, offset) = full_resync(&master);
+    assert_eq!(offset, written);
+    assert!(matches!(replica.receive(), RespValue::Rdb(_)));
+
+    // counted from there until it ACKs
+    let RespValue::Array(role) = to_master.call(&["ROLE"]) else {
+        panic!("ROLE replies with an array");
+    };
+    assert_eq!(
+        role[2],
+        RespValue::Array(vec![RespValue::Array(vec![
+            common::bulk("127.0.0.1"),
+            common::bulk("6380"),
+            common::bulk(&written.to_string()),
+        ])])
+    );
+}
+
+#[test]
+fn diskless_fullresync_gives_the_offset_of_the_snapshot() {
+    let master = Server::start(&["--repl-diskless-sync", "--repl-diskless-sync-delay", "1"]);
+    let mut to_master = master.connect();
+
+    set_keys(&mut to_master, 5);
+
+    let mut replica = master.connect();
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+    replica.send(&["PSYNC", "?", "-1"]);
+
+    // written while the sync waits, so in the snapshot and in the offset it is taken at
+    set_keys(&mut to_master, 10);
+    let written = master_repl_offset(&mut to_master);
+
+    assert_eq!(
+        replica.receive(),
+        RespValue::SimpleString(format!(
+            "FULLRESYNC {} {}",
+            master_replid(&mut to_master),
+            written
+        ))
+    );
+    assert!(matches!(replica.receive(), RespValue::Rdb(_)));
+
+    // the writes in the snapshot don't come again
+    assert_eq!(to_master.call(&["SET", "after", "sync"]), ok());
+    assert_eq!(
+        replica.receive(),
+        RespValue::array_from_slice(&["SET", "after", "sync"])
+    );
+}
+
+#[test]
+fn wait_counts_a_replica_that_synced_after_writes() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    set_keys(&mut to_master, 20);
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "after", "sync"]), ok());
+    to_replica.wait_for(&["GET", "after"], bulk("sync"));
+
+    assert_eq!(
+        master_repl_offset(&mut to_replica),
+        master_repl_offset(&mut to_master)
+    );
+    assert_eq!(
+        to_master.call(&["WAIT", "1", "2000"]),
+        RespValue::Integer(1)
+    );
+}
+
+#[test]
+fn offset_goes_past_32k() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    let value = "v".repeat(1000);
+    for n in 0..40 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("key:{}", n), &value]),
+            ok()
+        );
+    }
+
+    assert!(master_repl_offset(&mut to_master) > 40_000);
+    assert_eq!(to_master.call(&["PING"]), simple("PONG"));
+}
@@ -0,0 +1,280 @@
+// Published messages reach every subscriber of the channel and of every pattern matching it, and a
+// subscriber that stops reading is disconnected once its queue is full instead of making the server
+// hold on to everything. CLIENT LIST and INFO clients count the subscriptions.
+
+mod common;
+
+use common::{bulk, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn event(kind: &str, channel: &str, value: RespValue) -> RespValue {
+    RespValue::Array(vec![bulk(kind), bulk(channel), value])
+}
+
+// The value of field in the client's CLIENT LIST line, as observer sees it. A RESP2 subscriber
+// can't ask for its own.
+fn client_field(observer: &mut common::Client, id: &str, field: &str) -> String {
+    let RespValue::BulkString(Some(line)) = observer.call(&["CLIENT", "LIST", "ID", id]) else {
+        panic!("CLIENT LIST replies with a bulk string");
+    };
+
+    std::str::from_utf8(&line)
+        .expect("CLIENT LIST is text")
+        .trim_end()
+        .split(' ')
+        .find_map(|pair| pair.strip_prefix(field)?.strip_prefix('='))
+        .unwrap_or_else(|| panic!("CLIENT LIST has {}", field))
+        .to_string()
+}
+
+// INFO clients, as it reads.
+fn info_clients(client: &mut common::Client) -> String {
+    let RespValue::BulkString(Some(info)) = client.call(&["INFO", "clients"]) else {
+        panic!("INFO clients replies with a bulk string");
+    };
+
+    String::from_utf8(info.to_vec()).unwrap()
+}
+
+#[test]
+fn subscribers_get_what_is_published() {
+    let server = Server::start(&[]);
+    let mut subscriber = server.connect();
+    let mut publisher = server.connect();
+
+    assert_eq!(
+        subscriber.call(&["SUBSCRIBE", "news", "weather"]),
+        event("subscribe", "news", RespValue::Integer(1))
+    );
+    assert_eq!(
+        subscriber.receive(),
+        event("subscribe", "weather", RespValue::Integer(2))
+    );
+
+    assert_eq!(
+        publisher.call(&["PUBLISH", "news", "hello"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        publisher.call(&["PUBLISH", "sports", "nobody listens"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(
+        publisher.call(&["PUBLISH", "weather", "rain"]),
+        RespValue::Integer(1)
+    );
+
+    assert_eq!(
+        subscriber.receive(),
+        event("message", "news", bulk("hello"))
+    );
+    assert_eq!(
+        subscriber.receive(),
+        event("message", "weather", bulk("rain"))
+    );
+}
+
+#[test]
+fn unsubscribe_stops_delivery() {
+    let server = Server::start(&[]);
+    let mut subscriber = server.connect();
+    let mut publisher = server.connect();
+
+    subscriber.call(&["SUBSCRIBE", "a"]);
+    assert_eq!(
+        subscriber.call(&["UNSUBSCRIBE", "a"]),
+        event("unsubscribe", "a", RespValue::Integer(0))
+    );
+    assert_eq!(
+        publisher.call(&["PUBLISH", "a", "x"]),
+        RespValue::Integer(0)
+    );
+
+    // nothing to unsubscribe from is still confirmed, with a nil channel
+    assert_eq!(
+        subscriber.call(&["UNSUBSCRIBE"]),
+        RespValue::Array(vec![
+            bulk("unsubscribe"),
+            RespValue::Null,
+            RespValue::Integer(0)
+        ])
+    );
+
+    // and neither does hanging up
+    let mut gone = server.connect();
+    gone.call(&["SUBSCRIBE", "a"]);
+    assert_eq!(
+        publisher.call(&["PUBLISH", "a", "x"]),
+        RespValue::Integer(1)
+    );
+    drop(gone);
+    publisher.wait_for(&["PUBLISH", "a", "x"], RespValue::Integer(0));
+}
+
+#[test]
+fn a_subscriber_that_does_not_read_is_disconnected() {
+    let server = Server::start(&["--pubsub-queue-limit", "8"]);
+    let mut slow = server.connect();
+    let mut fast = server.connect();
+    let mut publisher = server.connect();
+
+    slow.call(&["SUBSCRIBE", "firehose"]);
+    fast.call(&["SUBSCRIBE", "firehose"]);
+
+    // slow never reads, so once the socket buffers are full its messages pile up on the server
+    let payload = "x".repeat(64 * 1024);
+    let mut published = 0;
+    loop {
+        let receivers = publisher.call(&["PUBLISH", "firehose", &payload]);
+        published += 1;
+
+        assert_eq!(fast.receive(), event("message", "firehose", bulk(&payload)));
+
+        if receivers == RespValue::Integer(1) {
+            break;
+        }
+        assert_eq!(receivers, RespValue::Integer(2));
+        assert!(published < 2000, "the slow subscriber was never dropped");
+    }
+
+    // what was already on its way arrives, then the connection closes
+    slow.read_until_closed();
+
+    assert_eq!(
+        publisher.call(&["PUBLISH", "firehose", "still here"]),
+        RespValue::Integer(1)
+    );
+}
+
+#[test]
+fn pattern_subscribers_get_every_matching_channel() {
+    let server = Server::start(&[]);
+    let mut subscriber = server.connect();
+    let mut publisher = server.connect();
+
+    assert_eq!(
+        subscriber.call(&["PSUBSCRIBE", "news.*"]),
+        event("psubscribe", "news.*", RespValue::Integer(1))
+    );
+    // channels and patterns are counted together
+    assert_eq!(
+        subscriber.call(&["SUBSCRIBE", "news.art"]),
+        event("subscribe", "news.art", RespValue::Integer(2))
+    );
+
+    // once for the channel, once for the pattern
+    assert_eq!(
+        publisher.call(&["PUBLISH", "news.art", "hello"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        subscriber.receive(),
+        event("message", "news.art", bulk("hello"))
+    );
+    assert_eq!(
+        subscriber.receive(),
+        RespValue::Array(vec![
+            bulk("pmessage"),
+            bulk("news.*"),
+            bulk("news.art"),
+            bulk("hello")
+        ])
+    );
+    assert_eq!(
+        publisher.call(&["PUBLISH", "weather", "rain"]),
+        RespValue::Integer(0)
+    );
+
+    assert_eq!(
+        subscriber.call(&["PUNSUBSCRIBE"]),
+        event("punsubscribe", "news.*", RespValue::Integer(1))
+    );
+    assert_eq!(
+        publisher.call(&["PUBLISH", "news.sports", "goal"]),
+        RespValue::Integer(0)
+    );
+}
+
+#[test]
+fn subscriptions_are_counted() {
+    let server = Server::start(&[]);
+    let mut subscriber = server.connect();
+    let mut observer = server.connect();
+
+    let id = match subscriber.call(&["CLIENT", "ID"]) {
+        RespValue::Integer(id) => id.to_string(),
+        other => panic!("unexpected CLIENT ID reply: {:?}", other),
+    };
+    assert_eq!(client_field(&mut observer, &id, "sub"), "0");
+    assert_eq!(client_field(&mut observer, &id, "psub"), "0");
+
+    subscriber.call(&["SUBSCRIBE", "a", "b"]);
+    subscriber.receive();
+    subscriber.call(&["PSUBSCRIBE", "c*"]);
+
+    assert_eq!(client_field(&mut observer, &id, "sub"), "2");
+    assert_eq!(client_field(&mut observer, &id, "psub"), "1");
+
+    // a second subscriber to the same channel is not another channel
+    let mut other = server.connect();
+    other.call(&["SUBSCRIBE", "a"]);
+
+    let info = info_clients(&mut observer);
+    assert!(info.contains("connected_clients:3\r\n"), "{}", info);
+    assert!(info.contains("pubsub_clients:2\r\n"), "{}", info);
+    assert!(info.contains("pubsub_channels:2\r\n"), "{}", info);
+    assert!(info.contains("pubsub_patterns:1\r\n"), "{}", info);
+
+    subscriber.call(&["UNSUBSCRIBE", "b"]);
+    assert_eq!(client_field(&mut observer, &id, "sub"), "1");
+    assert_eq!(client_field(&mut observer, &id, "psub"), "1");
+
+    subscriber.call(&["PUNSUBSCRIBE", "c*"]);
+    assert_eq!(client_field(&mut observer, &id, "psub"), "0");
+    assert!(info_clients(&mut observer).contains("pubsub_patterns:0\r\n"));
+
+    // hanging up takes the subscriptions with it
+    drop(subscriber);
+    drop(other);
+    for _ in 0..50 {
+        let info = info_clients(&mut observer);
+        if info.contains("connected_clients:1\r\n") && info.contains("pubsub_channels:0\r\n") {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    let info = info_clients(&mut observer);
+    assert!(info.contains("connected_clients:1\r\n"), "{}", info);
+    assert!(info.contains("pubsub_clients:0\r\n"), "{}", info);
+    assert!(info.contains("pubsub_channels:0\r\n"), "{}", info);
+}
+
+#[test]
+fn a_resp2_subscriber_can_only_subscribe_and_ping() {
+    let server = Server::start(&[]);
+    let mut subscriber = server.connect();
+
+    subscriber.call(&["SUBSCRIBE", "a"]);
+    assert_eq!(
+        subscriber.call(&["GET", "key"]),
+        RespValue::Error(
+            "ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        subscriber.call(&["PING"]),
+        RespValue::Array(vec![bulk("pong"), bulk("")])
+    );
+
+    // once it has no subscriptions left, it is an ordinary client again
+    subscriber.call(&["UNSUBSCRIBE"]);
+    assert_eq!(subscriber.call(&["GET", "key"]), RespValue::Null);
+
+    // in RESP3 replies can't be taken for messages, so anything goes
+    let mut resp3 = server.connect();
+    assert!(matches!(resp3.call(&["HELLO", "3"]), RespValue::Map(_)));
+    resp3.call(&["SUBSCRIBE", "a"]);
+    assert_eq!(resp3.call(&["GET", "key"]), RespValue::Null);
+}
@@ -0,0 +1,126 @@
+// Requests are read into a per-connection query buffer. CLIENT LIST shows how much of it holds
+// unparsed bytes (qbuf) and how much is free (qbuf-free). After a burst it shrinks back to
+// client-query-buffer-initial-size, and a client that sends more than client-query-buffer-limit
+// without finishing a request is disconnected.
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use common::{bulk, ok, simple, Client, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The CLIENT LIST fields of the client with the given id.
+fn client_fields(client: &mut Client, id: i64) -> HashMap<String, usize> {
+    let RespValue::BulkString(Some(list)) = client.call(&["CLIENT", "LIST", "ID", &id.to_string()])
+    else {
+        panic!("CLIENT LIST replies with a bulk string");
+    };
+
+    std::str::from_utf8(&list)
+        .expect("CLIENT LIST is text")
+        .split_whitespace()
+        .filter_map(|field| field.split_once('='))
+        .filter_map(|(name, value)| Some((name.to_string(), value.parse().ok()?)))
+        .collect()
+}
+
+fn client_id(client: &mut Client) -> i64 {
+    match client.call(&["CLIENT", "ID"]) {
+        RespValue::Integer(id) => id,
+        other => panic!("unexpected CLIENT ID reply: {:?}", other),
+    }
+}
+
+#[test]
+fn config_get_has_the_sizes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "client-query-buffer-*"]),
+        RespValue::Array(vec![
+            bulk("client-query-buffer-initial-size"),
+            bulk("16384"),
+            bulk("client-query-buffer-limit"),
+            bulk("1073741824"),
+        ])
+    );
+}
+
+#[test]
+fn client_list_shows_a_request_still_coming_in() {
+    let server = Server::start(&["--client-query-buffer-initial-size", "1024"]);
+    let mut sender = server.connect();
+    let mut observer = server.connect();
+
+    let sender_id = client_id(&mut sender);
+
+    let idle = client_fields(&mut observer, sender_id);
+    assert_eq!(idle["qbuf"], 0);
+    assert!(idle["qbuf-free"] <= 1024, "{:?}", idle);
+
+    // half of a 100KB value
+    let header = b"*3\r\n$3\r\nSET\r\n$3\r\nbig\r\n$100000\r\n";
+    sender.send_bytes(header);
+    sender.send_bytes(&[b'x'; 50_000]);
+
+    let expected = header.len() + 50_000;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client_fields(&mut observer, sender_id)["qbuf"] < expected {
+        assert!(Instant::now() < deadline, "qbuf never grew");
+        sleep(Duration::from_millis(20));
+    }
+
+    // the rest of it, then the buffer goes back to its initial size
+    sender.send_bytes(&[b'x'; 50_000]);
+    sender.send_bytes(b"\r\n");
+    assert_eq!(sender.receive(), ok());
+
+    let drained = client_fields(&mut observer, sender_id);
+    assert_eq!(drained["qbuf"], 0);
+    assert!(drained["qbuf-free"] <= 1024, "{:?}", drained);
+    assert_eq!(
+        observer.call(&["STRLEN", "big"]),
+        RespValue::Integer(100_000)
+    );
+}
+
+#[test]
+fn pipelined_burst_leaves_the_buffer_at_its_initial_size() {
+    let server = Server::start(&["--client-query-buffer-initial-size", "1024"]);
+    let mut client = server.connect();
+
+    let client_id = client_id(&mut client);
+
+    let value = "v".repeat(10_000);
+    for n in 0..100 {
+        client.send(&["SET", &format!("key:{}", n), &value]);
+    }
+    for _ in 0..100 {
+        assert_eq!(client.receive(), ok());
+    }
+
+    let fields = client_fields(&mut client, client_id);
+    assert_eq!(fields["qbuf"], 0);
+    assert!(fields["qbuf-free"] <= 1024, "{:?}", fields);
+}
+
+#[test]
+fn client_over_the_limit_is_disconnected() {
+    let server = Server::start(&["--client-query-buffer-limit", "65536"]);
+    let mut sender = server.connect();
+    let mut other = server.connect();
+
+    // never finished, and more than the limit
+    sender.send_bytes(b"*3\r\n$3\r\nSET\r\n$3\r\nbig\r\n$200000\r\n");
+    sender.send_bytes(&[b'x'; 100_000]);
+    sender.read_until_closed();
+
+    assert_eq!(other.call(&["PING"]), simple("PONG"));
+    assert_eq!(other.call(&["GET", "big"]), RespValue::Null);
+}
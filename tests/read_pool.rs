@@ -0,0 +1,215 @@
+// The reads that go through the read pool: SUNION, SINTER and SINTERCARD, what happens when one
+// takes longer than read-time-limit, and that their replies still come in the order asked for.
+
+mod common;
+
+use std::collections::HashSet;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn members(reply: RespValue) -> HashSet<String> {
+    let RespValue::Array(members) = reply else {
+        panic!("not an array: {:?}", reply);
+    };
+
+    members
+        .into_iter()
+        .map(|member| match member {
+            RespValue::BulkString(Some(member)) => String::from_utf8(member.to_vec()).unwrap(),
+            member => panic!("not a member: {:?}", member),
+        })
+        .collect()
+}
+
+fn set(members: &[&str]) -> HashSet<String> {
+    members.iter().map(|member| member.to_string()).collect()
+}
+
+// SADDs count members named prefix:0 and on, some thousands at a time.
+fn fill(client: &mut common::Client, key: &str, prefix: &str, count: usize) {
+    for chunk in (0..count).collect::<Vec<_>>().chunks(10_000) {
+        let members: Vec<String> = chunk.iter().map(|i| format!("{}:{}", prefix, i)).collect();
+        let mut args = vec!["SADD", key];
+        args.extend(members.iter().map(String::as_str));
+
+        assert_eq!(client.call(&args), RespValue::Integer(chunk.len() as i64));
+    }
+}
+
+#[test]
+fn sunion_sinter_and_sintercard() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["SADD", "a", "1", "2", "3", "4"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(
+        client.call(&["SADD", "b", "3", "4", "5"]),
+        RespValue::Integer(3)
+    );
+    assert_eq!(
+        client.call(&["SADD", "c", "4", "5", "6"]),
+        RespValue::Integer(3)
+    );
+
+    assert_eq!(
+        members(client.call(&["SUNION", "a", "b", "missing"])),
+        set(&["1", "2", "3", "4", "5"])
+    );
+    assert_eq!(members(client.call(&["SUNION", "missing"])), HashSet::new());
+
+    assert_eq!(
+        members(client.call(&["SINTER", "a", "b"])),
+        set(&["3", "4"])
+    );
+    assert_eq!(
+        members(client.call(&["SINTER", "a", "b", "c"])),
+        set(&["4"])
+    );
+    assert_eq!(
+        members(client.call(&["SINTER", "c"])),
+        set(&["4", "5", "6"])
+    );
+    assert_eq!(
+        members(client.call(&["SINTER", "a", "missing"])),
+        HashSet::new()
+    );
+
+    assert_eq!(
+        client.call(&["SINTERCARD", "2", "a", "b"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        client.call(&["SINTERCARD", "2", "a", "b", "LIMIT", "1"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["SINTERCARD", "2", "a", "b", "LIMIT", "0"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        client.call(&["SINTERCARD", "1", "missing"]),
+        RespValue::Integer(0)
+    );
+
+    assert_eq!(
+        client.call(&["SINTERCARD", "0", "a"]),
+        RespValue::Error("ERR numkeys should be greater than 0".to_string())
+    );
+    assert_eq!(
+        client.call(&["SINTERCARD", "3", "a", "b"]),
+        RespValue::Error("ERR Number of keys can't be greater than number of args".to_string())
+    );
+    assert_eq!(
+        client.call(&["SINTERCARD", "1", "a", "b"]),
+        RespValue::Error("ERR syntax error".to_string())
+    );
+
+    let wrong_type = RespValue::Error(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    );
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+    assert_eq!(client.call(&["RPUSH", "l", "x"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SUNION", "a", "s"]), wrong_type);
+    assert_eq!(client.call(&["SINTER", "a", "l"]), wrong_type);
+    assert_eq!(client.call(&["SINTERCARD", "2", "l", "a"]), wrong_type);
+    assert_eq!(client.call(&["SMEMBERS", "l"]), wrong_type);
+    assert_eq!(client.call(&["LRANGE", "a", "0", "-1"]), wrong_type);
+}
+
+#[test]
+fn reads_past_read_time_limit_time_out() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "read-time-limit"]),
+        RespValue::Array(vec![bulk("read-time-limit"), bulk("5000")])
+    );
+
+    fill(&mut client, "big", "a", 200_000);
+    fill(&mut client, "other", "b", 200_000);
+
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "read-time-limit", "1"]),
+        ok()
+    );
+
+    let timed_out = RespValue::Error("ERR operation timed out".to_string());
+    assert_eq!(client.call(&["SUNION", "big", "other"]), timed_out);
+    assert_eq!(client.call(&["SINTERCARD", "2", "big", "big"]), timed_out);
+
+    // the connection goes on as before
+    assert_eq!(client.call(&["SCARD", "big"]), RespValue::Integer(200_000));
+    assert_eq!(
+        client.call(&["SINTERCARD", "1", "big", "LIMIT", "10"]),
+        RespValue::Integer(10)
+    );
+
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "read-time-limit", "0"]),
+        ok()
+    );
+    // all of it this time, counted rather than sent back
+    assert_eq!(
+        client.call(&["SINTERCARD", "2", "big", "big"]),
+        RespValue::Integer(200_000)
+    );
+
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "read-time-limit", "soon"]),
+        RespValue::Error(
+            "ERR CONFIG SET failed (possibly related to argument 'read-time-limit') - argument couldn't be parsed into an integer"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn pooled_reads_keep_their_place_in_a_pipeline_and_a_transaction() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["RPUSH", "l", "a", "b", "c"]),
+        RespValue::Integer(3)
+    );
+
+    client.send(&["LRANGE", "l", "0", "-1"]);
+    client.send(&["RPUSH", "l", "d"]);
+    client.send(&["LRANGE", "l", "-2", "-1"]);
+    assert_eq!(
+        client.receive(),
+        RespValue::Array(vec![bulk("a"), bulk("b"), bulk("c")])
+    );
+    assert_eq!(client.receive(), RespValue::Integer(4));
+    assert_eq!(
+        client.receive(),
+        RespValue::Array(vec![bulk("c"), bulk("d")])
+    );
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    for args in [
+        &["SADD", "s", "x"][..],
+        &["SMEMBERS", "s"],
+        &["SADD", "s", "y"],
+        &["SINTERCARD", "1", "s"],
+    ] {
+        assert_eq!(
+            client.call(args),
+            RespValue::SimpleString("QUEUED".to_string())
+        );
+    }
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Array(vec![
+            RespValue::Integer(1),
+            RespValue::Array(vec![bulk("x")]),
+            RespValue::Integer(1),
+            RespValue::Integer(2),
+        ])
+    );
+}
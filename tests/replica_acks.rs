@@ -0,0 +1,112 @@
+// How often a replica tells its master how far it has got: every repl-ack-interval, and once for
+// a GETACK, or for a run of them read together. The test plays the master.
+
+mod common;
+
+use std::net::TcpListener;
+
+use common::{bulk, Client, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+const GETACK: &[&str] = &["REPLCONF", "GETACK", "*"];
+
+fn encoded(args: &[&str]) -> String {
+    RespValue::array_from_slice(args)
+        .to_encoded_string()
+        .unwrap()
+}
+
+// The offset of a REPLCONF ACK <offset> from the replica.
+fn acked_offset(ack: RespValue) -> usize {
+    let RespValue::Array(parts) = ack else {
+        panic!("expected REPLCONF ACK, got {:?}", ack);
+    };
+    assert_eq!(parts[..2], [bulk("REPLCONF"), bulk("ACK")]);
+
+    let RespValue::BulkString(Some(offset)) = &parts[2] else {
+        panic!("expected an offset, got {:?}", parts[2]);
+    };
+    String::from_utf8(offset.to_vec()).unwrap().parse().unwrap()
+}
+
+// Starts a replica of the listener and takes it through the handshake to an empty full resync.
+fn synced_replica(listener: &TcpListener, ack_interval: &str) -> (Server, Client) {
+    let address = format!("127.0.0.1 {}", listener.local_addr().unwrap().port());
+    let replica = Server::start(&["--replicaof", &address, "--repl-ack-interval", ack_interval]);
+    let mut master = Client::accept(listener);
+
+    assert_eq!(master.receive(), RespValue::array_from_slice(&["PING"]));
+    master.send_bytes(b"+PONG\r\n");
+    assert!(matches!(master.receive(), RespValue::Array(_)));
+    master.send_bytes(b"+OK\r\n");
+    assert!(matches!(master.receive(), RespValue::Array(_)));
+    master.send_bytes(b"+OK\r\n");
+    assert_eq!(
+        master.receive(),
+        RespValue::array_from_slice(&["PSYNC", "?", "-1"])
+    );
+
+    // header, EOF and a checksum of zeroes
+    let rdb = [&b"REDIS0011"[..], &[0xFF], &[0; 8]].concat();
+    master.send_bytes(
+        format!(
+            "+FULLRESYNC {} 0\r\n${}\r\n",
+            "0123456789abcdefghijABCDEFGHIJ0123456789",
+            rdb.len()
+        )
+        .as_bytes(),
+    );
+    master.send_bytes(&rdb);
+
+    (replica, master)
+}
+
+#[test]
+fn getacks_read_together_get_one_ack() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    // no ACKs of its own during the test
+    let (_replica, mut master) = synced_replica(&listener, "60000");
+
+    master.send(GETACK);
+    let before = acked_offset(master.receive());
+
+    // a write and three GETACKs in one go
+    let set = encoded(&["SET", "key", "value"]);
+    let burst = [set.clone(), encoded(GETACK).repeat(3)].concat();
+    master.send_bytes(burst.as_bytes());
+
+    // the one ACK is the last GETACK's, covering the write and the two before it
+    let after = acked_offset(master.receive());
+    assert_eq!(after, before + encoded(GETACK).len() * 3 + set.len());
+
+    // and it was the only one, the next is for the next GETACK
+    master.send(GETACK);
+    assert_eq!(
+        acked_offset(master.receive()),
+        after + encoded(GETACK).len()
+    );
+}
+
+#[test]
+fn acks_come_every_interval() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let (replica, mut master) = synced_replica(&listener, "50");
+
+    let first = acked_offset(master.receive());
+
+    let set = encoded(&["SET", "key", "value"]);
+    master.send_bytes(set.as_bytes());
+
+    // a later ACK has the write in it, without a GETACK asking
+    let mut offset = first;
+    while offset == first {
+        offset = acked_offset(master.receive());
+    }
+    assert_eq!(offset, first + set.len());
+
+    let mut client = replica.connect();
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "repl-ack-interval"]),
+        RespValue::Array(vec![bulk("repl-ack-interval"), bulk("50")])
+    );
+}
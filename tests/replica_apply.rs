@@ -0,0 +1,81 @@
+// A replica tells in INFO replication how it keeps up with applying its master's stream: what is
+// waiting, how long the writes take, and how many took repl-apply-warn-threshold or longer.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The value of field in INFO replication.
+fn info_field(client: &mut common::Client, field: &str) -> u64 {
+    let RespValue::SimpleString(info) = client.call(&["INFO", "replication"]) else {
+        panic!("INFO replication replies with a simple string");
+    };
+
+    info.split(':')
+        .skip_while(|name| *name != field)
+        .nth(1)
+        .unwrap_or_else(|| panic!("INFO replication has {}: {}", field, info))
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn keeping_up() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "foo", "bar"]), ok());
+    to_replica.wait_for(&["GET", "foo"], bulk("bar"));
+
+    // at most part of a frame still on its way
+    assert!(info_field(&mut to_replica, "replica_repl_backlog_bytes") < 1024);
+    assert!(
+        info_field(&mut to_replica, "replica_repl_apply_ms")
+            <= info_field(&mut to_replica, "replica_repl_apply_max_ms")
+    );
+
+    // a master has nothing to apply
+    let RespValue::SimpleString(info) = to_master.call(&["INFO", "replication"]) else {
+        panic!("INFO replication replies with a simple string");
+    };
+    assert!(!info.contains("replica_repl_"), "{}", info);
+}
+
+#[test]
+fn slow_writes_are_counted() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&[
+        "--replicaof",
+        &master.address(),
+        "--enable-debug-command",
+        "--repl-apply-warn-threshold",
+        "50",
+    ]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(
+        to_replica.call(&["CONFIG", "GET", "repl-apply-warn-threshold"]),
+        RespValue::Array(vec![bulk("repl-apply-warn-threshold"), bulk("50")])
+    );
+
+    assert_eq!(to_master.call(&["SET", "foo", "bar"]), ok());
+    to_replica.wait_for(&["GET", "foo"], bulk("bar"));
+
+    // the replica's processor is stuck, the master's write waits behind it
+    let mut stalled = replica.connect();
+    stalled.send(&["DEBUG", "SLEEP", "0.3"]);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    assert_eq!(to_master.call(&["SET", "foo", "baz"]), ok());
+    assert_eq!(stalled.receive(), simple("OK"));
+    to_replica.wait_for(&["GET", "foo"], bulk("baz"));
+
+    assert!(info_field(&mut to_replica, "replica_repl_slow_applies") >= 1);
+    assert!(info_field(&mut to_replica, "replica_repl_apply_max_ms") >= 50);
+}
@@ -0,0 +1,135 @@
+// Replicas acknowledge the replication stream once a second on their own, and INFO replication
+// shows the master how long ago each of them last did as lag=.
+
+mod common;
+
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// The master's offset and the offset its only replica has acknowledged, from ROLE.
+fn offsets(client: &mut common::Client) -> (i64, i64) {
+    let RespValue::Array(role) = client.call(&["ROLE"]) else {
+        panic!("ROLE replies with an array");
+    };
+
+    match role.as_slice() {
+        [_, RespValue::Integer(offset), RespValue::Array(replicas)] => match replicas.as_slice() {
+            [RespValue::Array(replica)] => match replica.as_slice() {
+                [_, _, RespValue::BulkString(Some(acked))] => (
+                    *offset,
+                    std::str::from_utf8(acked).unwrap().parse().unwrap(),
+                ),
+                _ => panic!("a replica is [ip, port, offset]: {:?}", replica),
+            },
+            _ => panic!("one replica: {:?}", replicas),
+        },
+        _ => panic!("ROLE of a master: {:?}", role),
+    }
+}
+
+// The lag= of slave0 in INFO replication.
+fn lag(client: &mut common::Client) -> u64 {
+    let RespValue::SimpleString(info) = client.call(&["INFO", "replication"]) else {
+        panic!("INFO replication replies with a simple string");
+    };
+
+    info.split(':')
+        .skip_while(|field| *field != "slave0")
+        .nth(1)
+        .and_then(|slave| {
+            slave
+                .split(',')
+                .find_map(|field| field.strip_prefix("lag="))
+        })
+        .unwrap_or_else(|| panic!("INFO replication has lag= for slave0: {}", info))
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn replicas_acknowledge_without_being_asked() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    for n in 0..10 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("key:{}", n), "value"]),
+            ok()
+        );
+    }
+    to_replica.wait_for(&["GET", "key:9"], bulk("value"));
+
+    // no WAIT or GETACK, the replica's own ACKs catch up with the master
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let (offset, acked) = offsets(&mut to_master);
+        if offset == acked {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "the replica acknowledged {} of {}",
+            acked,
+            offset
+        );
+        sleep(Duration::from_millis(100));
+    }
+
+    // and keep the lag down to the second in between
+    for _ in 0..3 {
+        assert!(lag(&mut to_master) <= 1);
+        sleep(Duration::from_millis(500));
+    }
+}
+
+#[test]
+fn lag_grows_while_a_replica_is_silent() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    // a replica that syncs and then never sends REPLCONF ACK
+    let mut replica = master.connect();
+    assert_eq!(replica.call(&["REPLCONF", "listening-port", "6380"]), ok());
+    replica.send(&["PSYNC", "?", "-1"]);
+    assert!(matches!(replica.receive(), RespValue::SimpleString(_)));
+    assert!(matches!(replica.receive(), RespValue::Rdb(_)));
+
+    assert_eq!(lag(&mut to_master), 0);
+    sleep(Duration::from_millis(2100));
+    assert!(lag(&mut to_master) >= 2);
+
+    // one ACK and it is back at 0
+    let (offset, _) = offsets(&mut to_master);
+    replica.send(&["REPLCONF", "ACK", &offset.to_string()]);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while lag(&mut to_master) != 0 {
+        assert!(
+            Instant::now() < deadline,
+            "lag= is still not 0 after an ACK"
+        );
+        sleep(Duration::from_millis(20));
+    }
+    assert_eq!(offsets(&mut to_master), (offset, offset));
+}
+
+#[test]
+fn the_threshold_is_a_config_parameter() {
+    let master = Server::start(&["--repl-lag-warn-threshold", "3"]);
+    let mut client = master.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "repl-lag-warn-threshold"]),
+        RespValue::Array(vec![
+            common::bulk("repl-lag-warn-threshold"),
+            common::bulk("3")
+        ])
+    );
+}
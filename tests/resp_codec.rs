@@ -0,0 +1,201 @@
+// Decoding frames off a connection buffer: partial frames wait for more data, pipelined frames
+// come out one at a time and bulk payloads point into the buffer they were read into.
+// Encoding goes through the shared replies where it can and must give the same bytes.
+
+use bytes::{Bytes, BytesMut};
+use redis_starter_rust::resp::{
+    codec::{RespCodec, RespProtocol},
+    shared::{SHARED_HEADERS, SHARED_INTEGERS},
+    value::RespValue,
+};
+use tokio_util::codec::{Decoder, Encoder};
+
+fn bulk(value: &str) -> RespValue {
+    RespValue::BulkString(Some(Bytes::copy_from_slice(value.as_bytes())))
+}
+
+#[test]
+fn partial_frames_wait_for_the_rest() {
+    let mut codec = RespCodec::new();
+    let mut buffer = BytesMut::from(&b"*2\r\n$3\r\nGET\r\n$3\r\nf"[..]);
+
+    assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+
+    buffer.extend_from_slice(b"oo\r\n");
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(RespValue::Array(vec![bulk("GET"), bulk("foo")]))
+    );
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn a_frame_split_anywhere_waits_for_the_rest() {
+    let request = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+
+    for split in 1..request.len() {
+        let mut codec = RespCodec::new();
+        let mut buffer = BytesMut::from(&request[..split]);
+
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            None,
+            "split at {}",
+            split
+        );
+
+        buffer.extend_from_slice(&request[split..]);
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(RespValue::Array(vec![
+                bulk("SET"),
+                bulk("foo"),
+                bulk("bar")
+            ])),
+            "split at {}",
+            split
+        );
+    }
+}
+
+#[test]
+fn the_rdb_payload_has_no_trailing_crlf() {
+    let mut codec = RespCodec::new();
+    let mut buffer = BytesMut::from(&b"$5\r\nREDIS"[..]);
+
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(RespValue::Rdb(Bytes::from_static(b"REDIS")))
+    );
+}
+
+#[test]
+fn pipelined_frames_come_out_in_order() {
+    let mut codec = RespCodec::new();
+    let mut buffer =
+        BytesMut::from(&b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nECHO\r\n$2\r\nhi\r\nPING\r\n"[..]);
+
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(RespValue::Array(vec![bulk("PING")]))
+    );
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(RespValue::Array(vec![bulk("ECHO"), bulk("hi")]))
+    );
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(RespValue::Array(vec![bulk("PING")]))
+    );
+    assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+}
+
+#[test]
+fn payloads_are_not_copied_out_of_the_buffer() {
+    let mut codec = RespCodec::new();
+    let mut buffer = BytesMut::from(&b"*2\r\n$4\r\nECHO\r\n$5\r\nhello\r\n"[..]);
+    let received = buffer.as_ptr_range();
+
+    let Some(RespValue::Array(args)) = codec.decode(&mut buffer).unwrap() else {
+        panic!("expected an array");
+    };
+    let RespValue::BulkString(Some(payload)) = &args[1] else {
+        panic!("expected a bulk string");
+    };
+
+    assert_eq!(&payload[..], b"hello");
+    assert!(received.contains(&payload.as_ptr()));
+}
+
+#[test]
+fn encoding_round_trips() {
+    let mut codec = RespCodec::new();
+    let reply = RespValue::Array(vec![
+        bulk("a\r\nb"),
+        RespValue::Integer(42),
+        RespValue::BulkString(None),
+        RespValue::SimpleString("OK".to_string()),
+    ]);
+
+    let mut buffer = BytesMut::new();
+    codec.encode(reply.clone(), &mut buffer).unwrap();
+    assert_eq!(&buffer[..], b"*4\r\n$4\r\na\r\nb\r\n:42\r\n$-1\r\n+OK\r\n");
+
+    // $-1 decodes as Null, the rest comes back as it was
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(RespValue::Array(vec![
+            bulk("a\r\nb"),
+            RespValue::Integer(42),
+            RespValue::Null,
+            RespValue::SimpleString("OK".to_string()),
+        ]))
+    );
+}
+
+#[test]
+fn integers_may_be_signed() {
+    let mut codec = RespCodec::new();
+
+    for (encoded, integer) in [(":-2\r\n", -2), (":+3\r\n", 3), (":0\r\n", 0)] {
+        let mut buffer = BytesMut::from(encoded);
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(RespValue::Integer(integer))
+        );
+    }
+}
+
+fn encode(value: RespValue) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+    RespCodec::new().encode(value, &mut buffer).unwrap();
+    buffer.to_vec()
+}
+
+#[test]
+fn shared_replies_match_the_general_encoding() {
+    assert_eq!(
+        encode(RespValue::SimpleString("OK".to_string())),
+        b"+OK\r\n"
+    );
+    assert_eq!(
+        encode(RespValue::SimpleString("PONG".to_string())),
+        b"+PONG\r\n"
+    );
+    assert_eq!(encode(RespValue::Null), b"$-1\r\n");
+    assert_eq!(encode(RespValue::NullArray), b"*-1\r\n");
+
+    // RESP3 has the one null for both
+    for null in [RespValue::Null, RespValue::NullArray] {
+        let mut buffer = BytesMut::new();
+        RespCodec::with_protocol(RespProtocol::Resp3)
+            .encode(null, &mut buffer)
+            .unwrap();
+        assert_eq!(&buffer[..], b"_\r\n");
+        assert_eq!(
+            RespCodec::new().decode(&mut buffer).unwrap(),
+            Some(RespValue::Null)
+        );
+    }
+
+    // either side of the small integer table
+    for i in [0, 1, SHARED_INTEGERS - 1, SHARED_INTEGERS, 123456] {
+        assert_eq!(
+            encode(RespValue::Integer(i)),
+            format!(":{}\r\n", i).into_bytes()
+        );
+    }
+
+    // either side of the shared headers
+    for len in [0, 1, SHARED_HEADERS - 1, SHARED_HEADERS, 1000] {
+        let data = "x".repeat(len);
+        assert_eq!(
+            encode(bulk(&data)),
+            format!("${}\r\n{}\r\n", len, data).into_bytes()
+        );
+        assert_eq!(
+            encode(RespValue::Array(vec![RespValue::Integer(1); len])),
+            format!("*{}\r\n{}", len, ":1\r\n".repeat(len)).into_bytes()
+        );
+    }
+}
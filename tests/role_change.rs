@@ -0,0 +1,98 @@
+// What happens to the dataset when an instance changes role: a full resync replaces it,
+// REPLICAOF NO ONE keeps it.
+
+mod common;
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn full_resync_replaces_the_replica_dataset() {
+    // the master's RDB on disk is what a full resync sends
+    let master_dir = temp_dir("resync-master");
+    let master = Server::start(&[
+        "--dir",
+        master_dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+    ]);
+    let mut to_master = master.connect();
+    assert_eq!(to_master.call(&["SET", "fresh", "1"]), ok());
+    assert_eq!(to_master.call(&["SAVE"]), ok());
+
+    // the replica-to-be starts out with keys of its own, in more than one database
+    let replica_dir = temp_dir("resync-replica");
+    let replica_args = [
+        "--dir",
+        replica_dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+    ];
+    {
+        let standalone = Server::start(&replica_args);
+        let mut client = standalone.connect();
+        assert_eq!(client.call(&["SET", "stale", "1"]), ok());
+        assert_eq!(client.call(&["SELECT", "2"]), ok());
+        assert_eq!(client.call(&["SET", "stale", "2"]), ok());
+        assert_eq!(client.call(&["SAVE"]), ok());
+    }
+
+    let master_address = master.address();
+    let replica = Server::start(&[&replica_args[..], &["--replicaof", &master_address]].concat());
+    let mut to_replica = replica.connect();
+
+    to_replica.wait_for(&["GET", "fresh"], bulk("1"));
+    assert_eq!(to_replica.call(&["GET", "stale"]), RespValue::Null);
+    assert_eq!(to_replica.call(&["DBSIZE"]), RespValue::Integer(1));
+    assert_eq!(to_replica.call(&["SELECT", "2"]), ok());
+    assert_eq!(to_replica.call(&["DBSIZE"]), RespValue::Integer(0));
+}
+
+#[test]
+fn replicaof_no_one_keeps_the_dataset() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "a", "1"]), ok());
+    to_replica.wait_for(&["GET", "a"], bulk("1"));
+
+    assert_eq!(
+        to_replica.call(&["CONFIG", "GET", "replicaof"]),
+        RespValue::Array(vec![bulk("replicaof"), bulk(&master.address())])
+    );
+
+    // clients of a replica only read
+    assert_eq!(
+        to_replica.call(&["SET", "c", "3"]),
+        RespValue::Error("READONLY You can't write against a read only replica.".to_string())
+    );
+
+    assert_eq!(to_replica.call(&["REPLICAOF", "NO", "ONE"]), ok());
+
+    // everything replicated so far stays
+    assert_eq!(to_replica.call(&["GET", "a"]), bulk("1"));
+    assert_eq!(
+        to_replica.call(&["CONFIG", "GET", "replicaof"]),
+        RespValue::Array(vec![bulk("replicaof"), bulk("")])
+    );
+    match to_replica.call(&["INFO", "replication"]) {
+        RespValue::SimpleString(info) => assert!(info.contains("role:master"), "{info}"),
+        other => panic!("unexpected INFO reply {:?}", other),
+    }
+
+    // the next write from the master makes the former replica hang up instead of applying it
+    assert_eq!(to_master.call(&["SET", "b", "2"]), ok());
+    to_master.wait_for(&["WAIT", "0", "0"], RespValue::Integer(0));
+    assert_eq!(to_replica.call(&["GET", "b"]), RespValue::Null);
+
+    // and it is a master in its own right
+    assert_eq!(to_replica.call(&["SET", "c", "3"]), ok());
+    assert_eq!(to_replica.call(&["GET", "c"]), bulk("3"));
+
+    // promoting a master is a no-op
+    assert_eq!(to_master.call(&["REPLICAOF", "NO", "ONE"]), ok());
+    assert_eq!(to_master.call(&["GET", "b"]), bulk("2"));
+}
@@ -0,0 +1,199 @@
+// SCAN hands out keys a few at a time against a cursor. Keys that are there from the first call
+// to the last must come back no matter what else is added or deleted in between.
+
+mod common;
+
+use std::collections::HashSet;
+
+use common::{ok, Client, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+// Runs SCAN from cursor 0 until it comes back to 0, calling between() after every step.
+fn scan_all(
+    client: &mut Client,
+    extra: &[&str],
+    mut between: impl FnMut(&mut Client),
+) -> Vec<String> {
+    let mut cursor = "0".to_string();
+    let mut keys = Vec::new();
+
+    loop {
+        let mut args = vec!["SCAN", cursor.as_str()];
+        args.extend_from_slice(extra);
+
+        let RespValue::Array(reply) = client.call(&args) else {
+            panic!("SCAN did not reply with an array");
+        };
+
+        let [RespValue::BulkString(Some(next)), RespValue::Array(batch)] = reply.as_slice() else {
+            panic!("unexpected SCAN reply {:?}", reply);
+        };
+
+        for key in batch {
+            let RespValue::BulkString(Some(key)) = key else {
+                panic!("unexpected key {:?}", key);
+            };
+            keys.push(String::from_utf8(key.to_vec()).unwrap());
+        }
+
+        cursor = String::from_utf8(next.to_vec()).unwrap();
+        if cursor == "0" {
+            return keys;
+        }
+
+        between(client);
+    }
+}
+
+#[test]
+fn returns_every_key_once() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for i in 0..100 {
+        assert_eq!(client.call(&["SET", &format!("key:{}", i), "v"]), ok());
+    }
+
+    let keys = scan_all(&mut client, &["COUNT", "7"], |_| {});
+    let unique: HashSet<_> = keys.iter().cloned().collect();
+
+    assert_eq!(keys.len(), 100);
+    assert_eq!(unique.len(), 100);
+}
+
+#[test]
+fn match_filters_each_step() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for i in 0..30 {
+        assert_eq!(client.call(&["SET", &format!("user:{}", i), "v"]), ok());
+        assert_eq!(client.call(&["SET", &format!("session:{}", i), "v"]), ok());
+    }
+
+    let keys = scan_all(&mut client, &["MATCH", "user:*", "COUNT", "5"], |_| {});
+
+    assert_eq!(keys.len(), 30);
+    assert!(keys.iter().all(|key| key.starts_with("user:")));
+}
+
+#[test]
+fn type_keeps_only_keys_holding_that_type() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for i in 0..20 {
+        assert_eq!(client.call(&["SET", &format!("string:{}", i), "v"]), ok());
+    }
+    for i in 0..5 {
+        let key = |kind: &str| format!("{}:{}", kind, i);
+        client.call(&["HSET", &key("hash"), "field", "value"]);
+        client.call(&["RPUSH", &key("list"), "element"]);
+        client.call(&["SADD", &key("set"), "member"]);
+        client.call(&["ZADD", &key("zset"), "1", "member"]);
+        client.call(&["XADD", &key("stream"), "*", "field", "value"]);
+    }
+
+    for (value_type, count) in [
+        ("string", 20),
+        ("hash", 5),
+        ("list", 5),
+        ("set", 5),
+        ("zset", 5),
+        ("stream", 5),
+    ] {
+        let keys = scan_all(&mut client, &["TYPE", value_type, "COUNT", "3"], |_| {});
+
+        assert_eq!(keys.len(), count, "{}", value_type);
+        assert!(
+            keys.iter()
+                .all(|key| key.starts_with(&format!("{}:", value_type))),
+            "{}: {:?}",
+            value_type,
+            keys
+        );
+    }
+
+    // the name in any case, and a type redis doesn't have matches nothing
+    assert_eq!(
+        scan_all(&mut client, &["TYPE", "String", "COUNT", "3"], |_| {}).len(),
+        20
+    );
+    assert!(scan_all(&mut client, &["TYPE", "nosuchtype", "COUNT", "3"], |_| {}).is_empty());
+}
+
+#[test]
+fn an_empty_database_ends_at_once() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["SCAN", "0"]),
+        RespValue::Array(vec![
+            RespValue::BulkString(Some("0".into())),
+            RespValue::Array(vec![])
+        ])
+    );
+}
+
+#[test]
+fn survives_the_keyspace_growing_and_shrinking() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for i in 0..50 {
+        assert_eq!(client.call(&["SET", &format!("stable:{}", i), "v"]), ok());
+    }
+
+    // the first steps each add a burst of keys, the next ones take them away again
+    let mut step = 0;
+    let keys = scan_all(&mut client, &["COUNT", "3"], |client| {
+        let (command, burst) = match step {
+            0..10 => ("SET", step),
+            10..20 => ("DEL", step - 10),
+            _ => return,
+        };
+        for i in 0..30 {
+            let key = format!("burst:{}:{}", burst, i);
+            let mut args = vec![command, key.as_str()];
+            if command == "SET" {
+                args.push("v");
+            }
+            client.call(&args);
+        }
+        step += 1;
+    });
+
+    let stable: HashSet<_> = keys
+        .iter()
+        .filter(|key| key.starts_with("stable:"))
+        .collect();
+    let stable_returned = keys.iter().filter(|key| key.starts_with("stable:")).count();
+
+    assert_eq!(stable.len(), 50);
+    assert_eq!(stable_returned, 50);
+}
+
+#[test]
+fn bad_arguments() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for (args, expected) in [
+        (
+            &["SCAN", "x"][..],
+            "ERR value is not an integer or out of range",
+        ),
+        (&["SCAN", "0", "COUNT", "0"], "ERR syntax error"),
+        (&["SCAN", "0", "MATCH"], "ERR syntax error"),
+        (&["SCAN", "0", "TYPE"], "ERR syntax error"),
+        (&["SCAN", "0", "FOO", "bar"], "ERR syntax error"),
+    ] {
+        assert_eq!(
+            client.call(args),
+            RespValue::Error(expected.to_string()),
+            "{:?}",
+            args
+        );
+    }
+}
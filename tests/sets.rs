@@ -0,0 +1,205 @@
+// Sets: SADD, SREM, SMEMBERS, SISMEMBER and SCARD. Like a hash, a set is a key like any other,
+// goes with its last member and reaches replicas and RDB files.
+
+mod common;
+
+use common::{ok, temp_dir, Server};
+use redis_starter_rust::{engine::Engine, resp::value::RespValue};
+
+// The members of an SMEMBERS reply, sorted, since a set keeps no order.
+fn sorted(reply: RespValue) -> Vec<String> {
+    let RespValue::Array(members) = reply else {
+        panic!("expected an array, got {:?}", reply);
+    };
+
+    let mut members: Vec<String> = members
+        .into_iter()
+        .map(|member| match member {
+            RespValue::BulkString(Some(member)) => String::from_utf8(member.to_vec()).unwrap(),
+            other => panic!("expected a bulk string, got {:?}", other),
+        })
+        .collect();
+    members.sort();
+
+    members
+}
+
+#[test]
+fn set_commands() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["SADD", "set", "a", "b", "a"]),
+        RespValue::Integer(2)
+    );
+    // only new members count
+    assert_eq!(
+        client.call(&["SADD", "set", "b", "c"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["SCARD", "set"]), RespValue::Integer(3));
+    assert_eq!(client.call(&["SCARD", "missing"]), RespValue::Integer(0));
+
+    assert_eq!(
+        sorted(client.call(&["SMEMBERS", "set"])),
+        vec!["a", "b", "c"]
+    );
+    assert_eq!(
+        client.call(&["SMEMBERS", "missing"]),
+        RespValue::Array(vec![])
+    );
+
+    assert_eq!(
+        client.call(&["SISMEMBER", "set", "a"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["SISMEMBER", "set", "z"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(
+        client.call(&["SISMEMBER", "missing", "a"]),
+        RespValue::Integer(0)
+    );
+
+    assert_eq!(
+        client.call(&["SREM", "set", "a", "missing", "b"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(client.call(&["EXISTS", "set"]), RespValue::Integer(1));
+
+    // the last member takes the key with it
+    assert_eq!(client.call(&["SREM", "set", "c"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["EXISTS", "set"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["SREM", "set", "c"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["SADD", "set"]),
+        RespValue::Error("ERR wrong number of arguments for 'sadd' command".to_string())
+    );
+    assert_eq!(
+        client.call(&["SISMEMBER", "set", "a", "b"]),
+        RespValue::Error("ERR wrong number of arguments for 'sismember' command".to_string())
+    );
+}
+
+#[test]
+fn a_set_is_a_key_like_any_other() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SADD", "set", "a"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["RPUSH", "l", "a"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(3));
+
+    let wrong_type = RespValue::Error(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    );
+    assert_eq!(client.call(&["GET", "set"]), wrong_type);
+    assert_eq!(client.call(&["LLEN", "set"]), wrong_type);
+    assert_eq!(client.call(&["HGET", "set", "a"]), wrong_type);
+    assert_eq!(client.call(&["SADD", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["SADD", "l", "a"]), wrong_type);
+    assert_eq!(client.call(&["SREM", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["SMEMBERS", "l"]), wrong_type);
+    assert_eq!(client.call(&["SISMEMBER", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["SCARD", "l"]), wrong_type);
+
+    assert_eq!(client.call(&["COPY", "set", "copy"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SADD", "copy", "b"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SCARD", "set"]), RespValue::Integer(1));
+
+    assert_eq!(
+        client.call(&["PEXPIRE", "copy", "50"]),
+        RespValue::Integer(1)
+    );
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(client.call(&["SCARD", "copy"]), RespValue::Integer(0));
+
+    assert_eq!(client.call(&["DEL", "set"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(2));
+}
+
+#[test]
+fn sets_reach_replicas() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    assert_eq!(
+        to_master.call(&["SADD", "before", "a", "b"]),
+        RespValue::Integer(2)
+    );
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["SCARD", "before"], RespValue::Integer(2));
+
+    assert_eq!(
+        to_master.call(&["SADD", "after", "1", "2", "3"]),
+        RespValue::Integer(3)
+    );
+    assert_eq!(
+        to_master.call(&["SREM", "after", "2"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        to_master.call(&["SREM", "before", "a", "b"]),
+        RespValue::Integer(2)
+    );
+    to_replica.wait_for(&["EXISTS", "before"], RespValue::Integer(0));
+    assert_eq!(
+        sorted(to_replica.call(&["SMEMBERS", "after"])),
+        vec!["1", "3"]
+    );
+}
+
+#[test]
+fn sets_survive_a_reload() {
+    let dir = temp_dir("sets-reload");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    let members: Vec<String> = (0..100).map(|i| "x".repeat(i)).collect();
+    let mut args = vec!["SADD", "big"];
+    args.extend(members.iter().map(String::as_str));
+    assert_eq!(client.call(&args), RespValue::Integer(100));
+    assert_eq!(
+        client.call(&["SADD", "expiring", "a"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["EXPIRE", "expiring", "100"]),
+        RespValue::Integer(1)
+    );
+
+    let before = sorted(client.call(&["SMEMBERS", "big"]));
+
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+
+    assert_eq!(sorted(client.call(&["SMEMBERS", "big"])), before);
+    assert!(matches!(
+        client.call(&["TTL", "expiring"]),
+        RespValue::Integer(ttl) if ttl > 0 && ttl <= 100
+    ));
+}
+
+#[tokio::test]
+async fn engine_sets() {
+    let engine = Engine::open(None, None).await.unwrap();
+
+    assert_eq!(engine.sadd("s", &["a", "b"]).await.unwrap(), 2);
+    assert_eq!(engine.sadd("s", &["b", "c"]).await.unwrap(), 1);
+    assert!(engine.sismember("s", "c").await.unwrap());
+    assert_eq!(engine.srem("s", &["a", "z"]).await.unwrap(), 1);
+    assert_eq!(engine.smembers("s").await.unwrap().len(), 2);
+    assert!(!engine.sismember("s", "a").await.unwrap());
+    assert!(engine.smembers("missing").await.unwrap().is_empty());
+}
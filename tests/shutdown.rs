@@ -0,0 +1,86 @@
+// SHUTDOWN waits for the replicas to apply everything they were sent before the master goes away,
+// refusing writes in the meantime, and SHUTDOWN SAVE leaves the dataset on disk.
+
+mod common;
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn replicas_are_caught_up_before_the_master_exits() {
+    let master = Server::start(&["--enable-debug-command"]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "ready", "1"]), ok());
+    to_replica.wait_for(&["GET", "ready"], bulk("1"));
+
+    // every frame takes its time getting to the replica, so the writes are still on their way
+    assert_eq!(
+        to_master.call(&["DEBUG", "FAILPOINT", "LATENCY", "100"]),
+        ok()
+    );
+    for n in 0..5 {
+        assert_eq!(
+            to_master.call(&["SET", &format!("key:{}", n), "value"]),
+            ok()
+        );
+    }
+
+    to_master.send(&["SHUTDOWN"]);
+
+    // still waiting for the replica, writes are turned away
+    let mut other = master.connect();
+    assert_eq!(
+        other.call(&["SET", "late", "1"]),
+        RespValue::Error("ERR The server is shutting down".to_string())
+    );
+    assert_eq!(other.call(&["GET", "ready"]), bulk("1"));
+
+    // no reply, the connection goes with the process
+    to_master.read_until_closed();
+
+    // everything written before SHUTDOWN is there, without waiting for it
+    assert_eq!(to_replica.call(&["DBSIZE"]), RespValue::Integer(6));
+    assert_eq!(to_replica.call(&["GET", "key:4"]), bulk("value"));
+    assert_eq!(to_replica.call(&["GET", "late"]), RespValue::Null);
+}
+
+#[test]
+fn shutdown_save_keeps_the_dataset() {
+    let dir = temp_dir("shutdown-save");
+    let args = ["--dir", dir.to_str().unwrap(), "--dbfilename", "dump.rdb"];
+
+    {
+        let server = Server::start(&args);
+        let mut client = server.connect();
+
+        assert_eq!(client.call(&["SET", "kept", "1"]), ok());
+        client.send(&["SHUTDOWN", "SAVE"]);
+        client.read_until_closed();
+    }
+
+    let server = Server::start(&args);
+    let mut client = server.connect();
+    assert_eq!(client.call(&["GET", "kept"]), bulk("1"));
+}
+
+#[test]
+fn shutdown_is_not_queued() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(
+        client.call(&["SHUTDOWN"]),
+        RespValue::Error("ERR Command not allowed inside a transaction".to_string())
+    );
+    assert_eq!(client.call(&["DISCARD"]), ok());
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "shutdown-timeout"]),
+        RespValue::Array(vec![bulk("shutdown-timeout"), bulk("10")])
+    );
+}
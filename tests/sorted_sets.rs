@@ -0,0 +1,442 @@
+// Sorted sets: ZADD with its flags, ZSCORE, ZRANGE, ZRANK, ZCARD, ZREM, ZINCRBY and the score
+// ranges of ZCOUNT and ZRANGEBYSCORE. Members are kept in order of score, then of member, and a
+// sorted set is a key like any other.
+
+mod common;
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::{engine::Engine, resp::value::RespValue};
+
+fn members(members: &[&str]) -> RespValue {
+    RespValue::Array(members.iter().map(|member| bulk(member)).collect())
+}
+
+#[test]
+fn sorted_set_commands() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "2", "b", "1", "a", "3", "c"]),
+        RespValue::Integer(3)
+    );
+    // ties are ordered by member
+    assert_eq!(
+        client.call(&["ZADD", "z", "2", "bb", "2", "b"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["ZCARD", "z"]), RespValue::Integer(4));
+    assert_eq!(client.call(&["ZCARD", "missing"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["ZRANGE", "z", "0", "-1"]),
+        members(&["a", "b", "bb", "c"])
+    );
+    assert_eq!(
+        client.call(&["ZRANGE", "z", "1", "2", "WITHSCORES"]),
+        members(&["b", "2", "bb", "2"])
+    );
+    assert_eq!(client.call(&["ZRANGE", "z", "-1", "-1"]), members(&["c"]));
+    assert_eq!(client.call(&["ZRANGE", "z", "5", "10"]), members(&[]));
+    assert_eq!(client.call(&["ZRANGE", "missing", "0", "-1"]), members(&[]));
+
+    assert_eq!(client.call(&["ZSCORE", "z", "c"]), bulk("3"));
+    assert_eq!(client.call(&["ZSCORE", "z", "missing"]), RespValue::Null);
+    assert_eq!(client.call(&["ZSCORE", "missing", "a"]), RespValue::Null);
+
+    assert_eq!(client.call(&["ZRANK", "z", "a"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["ZRANK", "z", "bb"]), RespValue::Integer(2));
+    assert_eq!(client.call(&["ZRANK", "z", "missing"]), RespValue::Null);
+
+    // a new score moves the member
+    assert_eq!(
+        client.call(&["ZADD", "z", "-inf", "c", "1.5", "a"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(
+        client.call(&["ZRANGE", "z", "0", "-1", "WITHSCORES"]),
+        members(&["c", "-inf", "a", "1.5", "b", "2", "bb", "2"])
+    );
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "one", "a"]),
+        RespValue::Error("ERR value is not a valid float".to_string())
+    );
+    assert_eq!(
+        client.call(&["ZADD", "z", "nan", "a"]),
+        RespValue::Error("ERR value is not a valid float".to_string())
+    );
+    assert_eq!(
+        client.call(&["ZADD", "z", "1", "a", "2"]),
+        RespValue::Error("ERR syntax error".to_string())
+    );
+    assert_eq!(
+        client.call(&["ZADD", "z", "1"]),
+        RespValue::Error("ERR wrong number of arguments for 'zadd' command".to_string())
+    );
+}
+
+#[test]
+fn zadd_flags() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "1", "a", "5", "b"]),
+        RespValue::Integer(2)
+    );
+
+    // NX only adds, XX only updates
+    assert_eq!(
+        client.call(&["ZADD", "z", "NX", "9", "a", "3", "c"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["ZSCORE", "z", "a"]), bulk("1"));
+    assert_eq!(
+        client.call(&["ZADD", "z", "XX", "2", "a", "4", "d"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(client.call(&["ZSCORE", "z", "a"]), bulk("2"));
+    assert_eq!(client.call(&["ZSCORE", "z", "d"]), RespValue::Null);
+
+    // CH counts the new scores too
+    assert_eq!(
+        client.call(&["ZADD", "z", "CH", "3", "a", "5", "b", "6", "e"]),
+        RespValue::Integer(2)
+    );
+
+    // GT and LT only move a score one way, and still add
+    assert_eq!(
+        client.call(&["ZADD", "z", "GT", "CH", "1", "a", "7", "b", "0", "f"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(client.call(&["ZSCORE", "z", "a"]), bulk("3"));
+    assert_eq!(client.call(&["ZSCORE", "z", "b"]), bulk("7"));
+    assert_eq!(
+        client.call(&["ZADD", "z", "lt", "ch", "1", "a", "8", "b"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["ZSCORE", "z", "a"]), bulk("1"));
+    assert_eq!(client.call(&["ZSCORE", "z", "b"]), bulk("7"));
+
+    // XX on a missing key leaves no key behind
+    assert_eq!(
+        client.call(&["ZADD", "missing", "XX", "1", "a"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(client.call(&["EXISTS", "missing"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "NX", "XX", "1", "a"]),
+        RespValue::Error("ERR XX and NX options at the same time are not compatible".to_string())
+    );
+    assert_eq!(
+        client.call(&["ZADD", "z", "GT", "LT", "1", "a"]),
+        RespValue::Error(
+            "ERR GT, LT, and/or NX options at the same time are not compatible".to_string()
+        )
+    );
+    assert_eq!(
+        client.call(&["ZADD", "z", "NX", "GT", "1", "a"]),
+        RespValue::Error(
+            "ERR GT, LT, and/or NX options at the same time are not compatible".to_string()
+        )
+    );
+}
+
+#[test]
+fn score_ranges() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "1", "a", "2", "b", "3", "c", "4", "d", "-inf", "low"]),
+        RespValue::Integer(5)
+    );
+
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "2", "3"]),
+        members(&["b", "c"])
+    );
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "(1", "(4", "WITHSCORES"]),
+        members(&["b", "2", "c", "3"])
+    );
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "-inf", "+inf"]),
+        members(&["low", "a", "b", "c", "d"])
+    );
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "(-inf", "1"]),
+        members(&["a"])
+    );
+    assert_eq!(client.call(&["ZRANGEBYSCORE", "z", "3", "2"]), members(&[]));
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "missing", "-inf", "inf"]),
+        members(&[])
+    );
+
+    // LIMIT skips offset of them, a negative count takes the rest
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "-inf", "inf", "LIMIT", "1", "2"]),
+        members(&["a", "b"])
+    );
+    assert_eq!(
+        client.call(&[
+            "ZRANGEBYSCORE",
+            "z",
+            "1",
+            "inf",
+            "WITHSCORES",
+            "LIMIT",
+            "2",
+            "-1"
+        ]),
+        members(&["c", "3", "d", "4"])
+    );
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "-inf", "inf", "LIMIT", "-1", "2"]),
+        members(&[])
+    );
+
+    assert_eq!(
+        client.call(&["ZCOUNT", "z", "-inf", "+inf"]),
+        RespValue::Integer(5)
+    );
+    assert_eq!(
+        client.call(&["ZCOUNT", "z", "(1", "3"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        client.call(&["ZCOUNT", "missing", "0", "1"]),
+        RespValue::Integer(0)
+    );
+
+    let not_a_bound = RespValue::Error("ERR min or max is not a float".to_string());
+    assert_eq!(client.call(&["ZCOUNT", "z", "one", "2"]), not_a_bound);
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "1", "(nan"]),
+        not_a_bound
+    );
+    assert_eq!(
+        client.call(&["ZRANGEBYSCORE", "z", "1", "2", "LIMIT", "1"]),
+        RespValue::Error("ERR syntax error".to_string())
+    );
+}
+
+#[test]
+fn zincrby_and_zrem() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // a missing key and member start from 0
+    assert_eq!(client.call(&["ZINCRBY", "z", "5", "a"]), bulk("5"));
+    assert_eq!(client.call(&["ZINCRBY", "z", "-1.5", "a"]), bulk("3.5"));
+    assert_eq!(client.call(&["ZINCRBY", "z", "2", "b"]), bulk("2"));
+    assert_eq!(
+        client.call(&["ZRANGE", "z", "0", "-1"]),
+        members(&["b", "a"])
+    );
+
+    assert_eq!(client.call(&["ZINCRBY", "z", "inf", "a"]), bulk("inf"));
+    assert_eq!(
+        client.call(&["ZINCRBY", "z", "-inf", "a"]),
+        RespValue::Error("ERR resulting score is not a number (NaN)".to_string())
+    );
+    assert_eq!(client.call(&["ZSCORE", "z", "a"]), bulk("inf"));
+    assert_eq!(
+        client.call(&["ZINCRBY", "z", "one", "a"]),
+        RespValue::Error("ERR value is not a valid float".to_string())
+    );
+
+    assert_eq!(
+        client.call(&["ZREM", "z", "a", "missing"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["ZRANK", "z", "a"]), RespValue::Null);
+    assert_eq!(client.call(&["EXISTS", "z"]), RespValue::Integer(1));
+
+    // the last member takes the key with it
+    assert_eq!(client.call(&["ZREM", "z", "b"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["EXISTS", "z"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["ZREM", "z", "b"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["ZREM", "z"]),
+        RespValue::Error("ERR wrong number of arguments for 'zrem' command".to_string())
+    );
+
+    let wrong_type = RespValue::Error(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    );
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+    assert_eq!(client.call(&["ZINCRBY", "s", "1", "a"]), wrong_type);
+    assert_eq!(client.call(&["ZREM", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["ZCOUNT", "s", "0", "1"]), wrong_type);
+    assert_eq!(client.call(&["ZRANGEBYSCORE", "s", "0", "1"]), wrong_type);
+}
+
+#[test]
+fn withscores_pairs_up_on_resp3() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "1", "a", "2.5", "b"]),
+        RespValue::Integer(2)
+    );
+
+    // flat in RESP2, the scores as bulk strings
+    assert_eq!(
+        client.call_raw(&["ZRANGE", "z", "0", "-1", "WITHSCORES"]),
+        b"*4\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$3\r\n2.5\r\n"
+    );
+
+    // [member, score] pairs in RESP3, the scores as doubles
+    client.call(&["HELLO", "3"]);
+    assert_eq!(
+        client.call_raw(&["ZRANGE", "z", "0", "-1", "WITHSCORES"]),
+        b"*2\r\n*2\r\n$1\r\na\r\n,1\r\n*2\r\n$1\r\nb\r\n,2.5\r\n"
+    );
+    assert_eq!(
+        client.call_raw(&["ZRANGEBYSCORE", "z", "2", "+inf", "WITHSCORES"]),
+        b"*1\r\n*2\r\n$1\r\nb\r\n,2.5\r\n"
+    );
+    assert_eq!(
+        client.call_raw(&["ZRANGE", "z", "0", "-1"]),
+        b"*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+    );
+}
+
+#[test]
+fn a_sorted_set_is_a_key_like_any_other() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["ZADD", "z", "1", "a"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SADD", "set", "a"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+
+    let wrong_type = RespValue::Error(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    );
+    assert_eq!(client.call(&["GET", "z"]), wrong_type);
+    assert_eq!(client.call(&["SCARD", "z"]), wrong_type);
+    assert_eq!(client.call(&["ZADD", "s", "1", "a"]), wrong_type);
+    assert_eq!(client.call(&["ZADD", "set", "1", "a"]), wrong_type);
+    assert_eq!(client.call(&["ZSCORE", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["ZRANGE", "set", "0", "-1"]), wrong_type);
+    assert_eq!(client.call(&["ZRANK", "s", "a"]), wrong_type);
+    assert_eq!(client.call(&["ZCARD", "set"]), wrong_type);
+
+    assert_eq!(client.call(&["COPY", "z", "copy"]), RespValue::Integer(1));
+    assert_eq!(
+        client.call(&["ZADD", "copy", "2", "b"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(client.call(&["ZCARD", "z"]), RespValue::Integer(1));
+
+    assert_eq!(
+        client.call(&["PEXPIRE", "copy", "50"]),
+        RespValue::Integer(1)
+    );
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(client.call(&["ZCARD", "copy"]), RespValue::Integer(0));
+
+    assert_eq!(client.call(&["DEL", "z"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(2));
+}
+
+#[test]
+fn sorted_sets_reach_replicas() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    assert_eq!(
+        to_master.call(&["ZADD", "before", "1", "a", "2", "b"]),
+        RespValue::Integer(2)
+    );
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["ZCARD", "before"], RespValue::Integer(2));
+
+    assert_eq!(
+        to_master.call(&["ZADD", "after", "3", "x", "1", "y"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        to_master.call(&["ZADD", "before", "GT", "0", "a", "3", "b"]),
+        RespValue::Integer(0)
+    );
+    to_replica.wait_for(&["ZSCORE", "before", "b"], bulk("3"));
+    assert_eq!(to_replica.call(&["ZSCORE", "before", "a"]), bulk("1"));
+    assert_eq!(
+        to_replica.call(&["ZRANGE", "after", "0", "-1"]),
+        members(&["y", "x"])
+    );
+}
+
+#[test]
+fn sorted_sets_survive_a_reload() {
+    let dir = temp_dir("sorted-sets-reload");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["ZADD", "z", "0.1", "a", "-inf", "b", "1e300", "c", "-7", "d"]),
+        RespValue::Integer(4)
+    );
+    assert_eq!(
+        client.call(&["ZADD", "expiring", "1", "a"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        client.call(&["EXPIRE", "expiring", "100"]),
+        RespValue::Integer(1)
+    );
+
+    let before = client.call(&["ZRANGE", "z", "0", "-1", "WITHSCORES"]);
+
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+
+    assert_eq!(
+        client.call(&["ZRANGE", "z", "0", "-1", "WITHSCORES"]),
+        before
+    );
+    assert!(matches!(
+        client.call(&["TTL", "expiring"]),
+        RespValue::Integer(ttl) if ttl > 0 && ttl <= 100
+    ));
+}
+
+#[tokio::test]
+async fn engine_sorted_sets() {
+    let engine = Engine::open(None, None).await.unwrap();
+
+    assert_eq!(
+        engine.zadd("z", &[(2.0, "b"), (1.0, "a")]).await.unwrap(),
+        2
+    );
+    assert_eq!(engine.zadd("z", &[(0.5, "b")]).await.unwrap(), 0);
+    assert_eq!(engine.zscore("z", "b").await.unwrap(), Some(0.5));
+    assert_eq!(engine.zscore("z", "c").await.unwrap(), None);
+    assert_eq!(
+        engine.zrange("z").await.unwrap(),
+        vec![("b".to_string(), 0.5), ("a".to_string(), 1.0)]
+    );
+    assert!(engine.zrange("missing").await.unwrap().is_empty());
+
+    assert_eq!(engine.zincrby("z", 2.0, "b").await.unwrap(), 2.5);
+    assert_eq!(engine.zrem("z", &["a", "c"]).await.unwrap(), 1);
+    assert_eq!(
+        engine.zrange("z").await.unwrap(),
+        vec![("b".to_string(), 2.5)]
+    );
+}
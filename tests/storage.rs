@@ -0,0 +1,188 @@
+// The store keeps keys and values in whatever KeyValueStore it is given, collections included,
+// and tells it about deadlines as they change.
+
+mod common;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{bulk, Server};
+use redis_starter_rust::{
+    collections::Collection,
+    engine::Engine,
+    resp::value::RespValue,
+    storage::{CollectionEntries, Entries, KeyValueStore, OpenStore},
+};
+
+// What a backend was told, shared with the test.
+#[derive(Debug, Default)]
+struct Recorded {
+    values: HashMap<String, Vec<u8>>,
+    collections: HashMap<String, Collection>,
+    deadlines: HashMap<String, u64>,
+}
+
+#[derive(Debug)]
+struct RecordingStore(Arc<Mutex<Recorded>>);
+
+impl RecordingStore {
+    fn recorded(&self) -> std::sync::MutexGuard<'_, Recorded> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl KeyValueStore for RecordingStore {
+    fn get(&self, key: &str) -> Option<Cow<'_, [u8]>> {
+        self.recorded().values.get(key).cloned().map(Cow::Owned)
+    }
+
+    fn set(&mut self, key: String, value: Vec<u8>) {
+        let mut recorded = self.recorded();
+        recorded.collections.remove(&key);
+        recorded.values.insert(key, value);
+    }
+
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Vec<u8>)) -> bool {
+        self.recorded().values.get_mut(key).map(update).is_some()
+    }
+
+    fn get_collection(&self, key: &str) -> Option<Arc<Collection>> {
+        self.recorded().collections.get(key).cloned().map(Arc::new)
+    }
+
+    fn set_collection(&mut self, key: String, collection: Collection) {
+        let mut recorded = self.recorded();
+        recorded.values.remove(&key);
+        recorded.collections.insert(key, collection);
+    }
+
+    fn update_collection(&mut self, key: &str, update: &mut dyn FnMut(&mut Collection)) -> bool {
+        self.recorded()
+            .collections
+            .get_mut(key)
+            .map(update)
+            .is_some()
+    }
+
+    fn del(&mut self, key: &str) -> bool {
+        let mut recorded = self.recorded();
+        recorded.values.remove(key).is_some() || recorded.collections.remove(key).is_some()
+    }
+
+    fn scan(&self) -> Entries<'_> {
+        let entries: Vec<_> = self
+            .recorded()
+            .values
+            .iter()
+            .map(|(key, value)| (Cow::Owned(key.clone()), Cow::Owned(value.clone())))
+            .collect();
+
+        Box::new(entries.into_iter())
+    }
+
+    fn scan_collections(&self) -> CollectionEntries<'_> {
+        let entries: Vec<_> = self
+            .recorded()
+            .collections
+            .iter()
+            .map(|(key, collection)| (Cow::Owned(key.clone()), Arc::new(collection.clone())))
+            .collect();
+
+        Box::new(entries.into_iter())
+    }
+
+    fn len(&self) -> usize {
+        let recorded = self.recorded();
+        recorded.values.len() + recorded.collections.len()
+    }
+
+    fn expire(&mut self, key: &str, deadline_ms: Option<u64>) {
+        let deadlines = &mut self.recorded().deadlines;
+        match deadline_ms {
+            Some(deadline_ms) => deadlines.insert(key.to_string(), deadline_ms),
+            None => deadlines.remove(key),
+        };
+    }
+
+    fn clear(&mut self) {
+        let mut recorded = self.recorded();
+        recorded.values.clear();
+        recorded.collections.clear();
+    }
+}
+
+#[tokio::test]
+async fn engine_keeps_its_keys_in_the_backend() {
+    // database 0 already holds a key, the others start out empty
+    let recorded = Arc::new(Mutex::new(Recorded::default()));
+    recorded
+        .lock()
+        .unwrap()
+        .values
+        .insert("kept".to_string(), b"from before".to_vec());
+
+    let shared = Arc::clone(&recorded);
+    let open_store: OpenStore = Arc::new(move |db| -> Box<dyn KeyValueStore> {
+        match db {
+            0 => Box::new(RecordingStore(Arc::clone(&shared))),
+            _ => Box::new(RecordingStore(Arc::default())),
+        }
+    });
+
+    let engine = Engine::open_with_backend(open_store, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        engine.get("kept").await.unwrap(),
+        Some(b"from before".to_vec())
+    );
+    assert_eq!(engine.keys("*").await.unwrap(), vec!["kept".to_string()]);
+
+    engine.set("foo", "bar").await.unwrap();
+    assert!(engine
+        .expire("foo", Duration::from_secs(100))
+        .await
+        .unwrap());
+
+    {
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.values.get("foo"), Some(&b"bar".to_vec()));
+        assert!(recorded.deadlines.contains_key("foo"));
+    }
+
+    // collections go to the backend too, and a key holds one thing at a time
+    assert_eq!(engine.rpush("list", &["a", "b"]).await.unwrap(), 2);
+    assert_eq!(engine.lpop("list").await.unwrap(), Some("a".to_string()));
+    assert!(engine.sadd("foo", &["member"]).await.is_err());
+    {
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(
+            recorded.collections.get("list"),
+            Some(&Collection::List(["b".to_string()].into()))
+        );
+        assert!(!recorded.collections.contains_key("foo"));
+    }
+
+    assert_eq!(engine.del(&["foo", "kept", "list"]).await.unwrap(), 3);
+
+    let recorded = recorded.lock().unwrap();
+    assert!(recorded.values.is_empty());
+    assert!(recorded.collections.is_empty());
+    assert!(recorded.deadlines.is_empty());
+}
+
+#[test]
+fn memory_is_the_default() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "storage-backend"]),
+        RespValue::Array(vec![bulk("storage-backend"), bulk("memory")])
+    );
+}
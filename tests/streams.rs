@@ -0,0 +1,366 @@
+// Streams: XADD with generated and explicit IDs, XRANGE, XLEN and XREAD. The IDs of a stream only
+// ever go up, and a stream is a key like any other.
+
+mod common;
+
+use common::{bulk, ok, temp_dir, Server};
+use redis_starter_rust::{
+    engine::Engine, handlers::set_command::SetCommandActorHandle, rdb::load::load_rdb,
+    resp::value::RespValue,
+};
+
+fn error(message: &str) -> RespValue {
+    RespValue::Error(message.to_string())
+}
+
+// An entry the way XRANGE and XREAD reply with it.
+fn entry(id: &str, fields: &[&str]) -> RespValue {
+    RespValue::Array(vec![
+        bulk(id),
+        RespValue::Array(fields.iter().map(|field| bulk(field)).collect()),
+    ])
+}
+
+fn id_of(reply: RespValue) -> (u64, u64) {
+    let RespValue::BulkString(Some(id)) = reply else {
+        panic!("not an ID: {:?}", reply);
+    };
+    let id = String::from_utf8(id.to_vec()).unwrap();
+    let (ms, seq) = id.split_once('-').unwrap();
+
+    (ms.parse().unwrap(), seq.parse().unwrap())
+}
+
+#[test]
+fn xadd_ids() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    let (ms, seq) = id_of(client.call(&["XADD", "s", "*", "a", "1"]));
+    assert_eq!(seq, 0);
+    assert!(ms > 0);
+    // within the same millisecond or later, always after the one before
+    let next = id_of(client.call(&["XADD", "s", "*", "a", "2"]));
+    assert!(next > (ms, seq));
+
+    assert_eq!(
+        client.call(&["XADD", "explicit", "1-1", "f", "v"]),
+        bulk("1-1")
+    );
+    assert_eq!(
+        client.call(&["XADD", "explicit", "1-*", "f", "v"]),
+        bulk("1-2")
+    );
+    assert_eq!(
+        client.call(&["XADD", "explicit", "5-*", "f", "v"]),
+        bulk("5-0")
+    );
+    assert_eq!(
+        client.call(&["XADD", "explicit", "7", "f", "v"]),
+        bulk("7-0")
+    );
+
+    let too_small =
+        error("ERR The ID specified in XADD is equal or smaller than the target stream top item");
+    assert_eq!(
+        client.call(&["XADD", "explicit", "7-0", "f", "v"]),
+        too_small
+    );
+    assert_eq!(
+        client.call(&["XADD", "explicit", "6-5", "f", "v"]),
+        too_small
+    );
+    assert_eq!(
+        client.call(&["XADD", "explicit", "6-*", "f", "v"]),
+        too_small
+    );
+    // the clock is well past 7, so * goes on from there
+    assert!(id_of(client.call(&["XADD", "explicit", "*", "f", "v"])) > (7, 0));
+
+    // a stream far ahead of the clock counts up from its last ID
+    assert_eq!(
+        client.call(&["XADD", "future", "99999999999999-3", "f", "v"]),
+        bulk("99999999999999-3")
+    );
+    assert_eq!(
+        client.call(&["XADD", "future", "*", "f", "v"]),
+        bulk("99999999999999-4")
+    );
+
+    assert_eq!(
+        client.call(&["XADD", "zero", "0-0", "f", "v"]),
+        error("ERR The ID specified in XADD must be greater than 0-0")
+    );
+    // nothing was left behind by the ones that failed
+    assert_eq!(client.call(&["EXISTS", "zero"]), RespValue::Integer(0));
+    assert_eq!(client.call(&["XLEN", "explicit"]), RespValue::Integer(5));
+
+    let invalid = error("ERR Invalid stream ID specified as stream command argument");
+    assert_eq!(client.call(&["XADD", "s", "one", "f", "v"]), invalid);
+    assert_eq!(client.call(&["XADD", "s", "1-x", "f", "v"]), invalid);
+    assert_eq!(client.call(&["XADD", "s", "-1", "f", "v"]), invalid);
+
+    let arity = error("ERR wrong number of arguments for 'xadd' command");
+    assert_eq!(client.call(&["XADD", "s", "*", "f"]), arity);
+    assert_eq!(client.call(&["XADD", "s", "*", "f", "v", "g"]), arity);
+}
+
+#[test]
+fn xrange_and_xlen() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for id in ["1-1", "1-2", "2-0", "3-5"] {
+        assert_eq!(client.call(&["XADD", "s", id, "id", id]), bulk(id));
+    }
+    assert_eq!(
+        client.call(&["XADD", "s", "4-0", "a", "1", "b", "2"]),
+        bulk("4-0")
+    );
+
+    assert_eq!(client.call(&["XLEN", "s"]), RespValue::Integer(5));
+    assert_eq!(client.call(&["XLEN", "missing"]), RespValue::Integer(0));
+
+    assert_eq!(
+        client.call(&["XRANGE", "s", "-", "+"]),
+        RespValue::Array(vec![
+            entry("1-1", &["id", "1-1"]),
+            entry("1-2", &["id", "1-2"]),
+            entry("2-0", &["id", "2-0"]),
+            entry("3-5", &["id", "3-5"]),
+            entry("4-0", &["a", "1", "b", "2"]),
+        ])
+    );
+    // ms alone is the whole of that millisecond
+    assert_eq!(
+        client.call(&["XRANGE", "s", "1", "1"]),
+        RespValue::Array(vec![
+            entry("1-1", &["id", "1-1"]),
+            entry("1-2", &["id", "1-2"]),
+        ])
+    );
+    assert_eq!(
+        client.call(&["XRANGE", "s", "1-2", "3-5", "COUNT", "2"]),
+        RespValue::Array(vec![
+            entry("1-2", &["id", "1-2"]),
+            entry("2-0", &["id", "2-0"]),
+        ])
+    );
+    assert_eq!(
+        client.call(&["XRANGE", "s", "-", "+", "COUNT", "-1"]),
+        RespValue::Array(vec![])
+    );
+    assert_eq!(
+        client.call(&["XRANGE", "s", "+", "-"]),
+        RespValue::Array(vec![])
+    );
+    assert_eq!(
+        client.call(&["XRANGE", "missing", "-", "+"]),
+        RespValue::Array(vec![])
+    );
+
+    assert_eq!(
+        client.call(&["XRANGE", "s", "x", "+"]),
+        error("ERR Invalid stream ID specified as stream command argument")
+    );
+}
+
+#[test]
+fn xread() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    for id in ["1-0", "2-0", "3-0"] {
+        assert_eq!(client.call(&["XADD", "a", id, "f", id]), bulk(id));
+    }
+    assert_eq!(client.call(&["XADD", "b", "5-0", "f", "b"]), bulk("5-0"));
+
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "a", "b", "1-0", "0"]),
+        RespValue::Array(vec![
+            RespValue::Array(vec![
+                bulk("a"),
+                RespValue::Array(vec![
+                    entry("2-0", &["f", "2-0"]),
+                    entry("3-0", &["f", "3-0"])
+                ]),
+            ]),
+            RespValue::Array(vec![
+                bulk("b"),
+                RespValue::Array(vec![entry("5-0", &["f", "b"])]),
+            ]),
+        ])
+    );
+    assert_eq!(
+        client.call(&["XREAD", "COUNT", "1", "STREAMS", "a", "0-0"]),
+        RespValue::Array(vec![RespValue::Array(vec![
+            bulk("a"),
+            RespValue::Array(vec![entry("1-0", &["f", "1-0"])]),
+        ])])
+    );
+    // only the streams with something new are in the reply
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "a", "missing", "b", "2", "0", "5"]),
+        RespValue::Array(vec![RespValue::Array(vec![
+            bulk("a"),
+            RespValue::Array(vec![entry("3-0", &["f", "3-0"])]),
+        ])])
+    );
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "a", "b", "$", "$"]),
+        RespValue::NullArray
+    );
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "missing", "0"]),
+        RespValue::NullArray
+    );
+
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "a", "b", "0"]),
+        error(
+            "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified."
+        )
+    );
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "a", "+"]),
+        error("ERR Invalid stream ID specified as stream command argument")
+    );
+}
+
+#[test]
+fn a_stream_is_a_key_like_any_other() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["XADD", "x", "1-0", "f", "v"]), bulk("1-0"));
+    assert_eq!(client.call(&["SET", "s", "string"]), ok());
+
+    let wrong_type = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    assert_eq!(client.call(&["GET", "x"]), wrong_type);
+    assert_eq!(client.call(&["LLEN", "x"]), wrong_type);
+    assert_eq!(client.call(&["XADD", "s", "*", "f", "v"]), wrong_type);
+    assert_eq!(client.call(&["XLEN", "s"]), wrong_type);
+    assert_eq!(client.call(&["XRANGE", "s", "-", "+"]), wrong_type);
+    assert_eq!(
+        client.call(&["XREAD", "STREAMS", "x", "s", "0", "0"]),
+        wrong_type
+    );
+
+    assert_eq!(client.call(&["COPY", "x", "copy"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["XADD", "copy", "2-0", "f", "v"]), bulk("2-0"));
+    assert_eq!(client.call(&["XLEN", "x"]), RespValue::Integer(1));
+
+    assert_eq!(client.call(&["DEL", "x"]), RespValue::Integer(1));
+    assert_eq!(client.call(&["DBSIZE"]), RespValue::Integer(2));
+}
+
+#[test]
+fn streams_reach_replicas_with_the_ids_the_master_gave() {
+    let master = Server::start(&[]);
+    let mut to_master = master.connect();
+
+    assert_eq!(
+        to_master.call(&["XADD", "before", "1-0", "f", "v"]),
+        bulk("1-0")
+    );
+
+    let replica = Server::start(&["--replicaof", &master.address()]);
+    let mut to_replica = replica.connect();
+    to_replica.wait_for(&["XLEN", "before"], RespValue::Integer(1));
+
+    let generated = to_master.call(&["XADD", "after", "*", "a", "1", "b", "2"]);
+    assert_eq!(
+        to_master.call(&["XADD", "before", "1-*", "f", "w"]),
+        bulk("1-1")
+    );
+
+    to_replica.wait_for(&["XLEN", "before"], RespValue::Integer(2));
+    let RespValue::BulkString(Some(id)) = generated else {
+        panic!("not an ID: {:?}", generated);
+    };
+    let id = String::from_utf8(id.to_vec()).unwrap();
+    assert_eq!(
+        to_replica.call(&["XRANGE", "after", "-", "+"]),
+        RespValue::Array(vec![entry(&id, &["a", "1", "b", "2"])])
+    );
+}
+
+#[test]
+fn streams_survive_a_reload() {
+    let dir = temp_dir("streams-reload");
+    let server = Server::start(&[
+        "--dir",
+        dir.to_str().unwrap(),
+        "--dbfilename",
+        "dump.rdb",
+        "--enable-debug-command",
+    ]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["XADD", "s", "1-1", "a", "1"]), bulk("1-1"));
+    assert_eq!(
+        client.call(&["XADD", "s", "18446744073709551615-0", "b", "2", "c", ""]),
+        bulk("18446744073709551615-0")
+    );
+
+    let before = client.call(&["XRANGE", "s", "-", "+"]);
+
+    assert_eq!(client.call(&["DEBUG", "RELOAD"]), ok());
+
+    // as 0x80, not redis' own type for streams, which would have it read our layout as listpacks
+    let saved = std::fs::read(dir.join("dump.rdb")).unwrap();
+    assert!(saved.windows(3).any(|window| window == [0x80, 1, b's']));
+
+    assert_eq!(client.call(&["XRANGE", "s", "-", "+"]), before);
+    // the top item is remembered with the entries
+    assert_eq!(
+        client.call(&["XADD", "s", "*", "d", "4"]),
+        bulk("18446744073709551615-1")
+    );
+}
+
+#[tokio::test]
+async fn a_stream_redis_saved_is_refused() {
+    let set_command_actor_handle = SetCommandActorHandle::new();
+
+    // foo => bar, then a key of STREAM_LISTPACKS_3, whose listpacks can't be read
+    let rdb = [
+        &b"REDIS0011"[..],
+        &[0xFE, 0x00],
+        &[0x00, 3],
+        b"foo",
+        &[3],
+        b"bar",
+        &[0x15, 1],
+        b"s",
+        &[1, 0, 0xFF],
+        &[0; 8],
+    ]
+    .concat();
+
+    let error = load_rdb(&rdb[..], &set_command_actor_handle)
+        .await
+        .expect_err("the stream is refused");
+
+    assert!(
+        error.to_string().contains("unknown RDB value type 0x15"),
+        "{}",
+        error
+    );
+    assert_eq!(
+        set_command_actor_handle.get_value(0, "foo").await.unwrap(),
+        Some(b"bar".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn engine_streams() {
+    let engine = Engine::open(None, None).await.unwrap();
+
+    let first = engine.xadd("s", &[("a", "1")]).await.unwrap();
+    let second = engine.xadd("s", &[("b", "2"), ("c", "3")]).await.unwrap();
+    assert!(second > first);
+
+    assert_eq!(engine.xlen("s").await.unwrap(), 2);
+    assert_eq!(engine.xlen("missing").await.unwrap(), 0);
+}
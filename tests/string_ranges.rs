@@ -0,0 +1,238 @@
+// GETRANGE and SETRANGE at the edges, checked byte for byte as they come over the wire: negative
+// and out of range indexes, empty values, missing keys and the zero bytes SETRANGE pads with.
+// Ranges are in bytes, whether or not those make up characters.
+
+mod common;
+
+use common::{ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn getrange_indexes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "Hello World"]), ok());
+
+    let cases: &[(&str, &str, &[u8])] = &[
+        ("0", "3", b"$4\r\nHell\r\n"),
+        ("0", "0", b"$1\r\nH\r\n"),
+        ("0", "-1", b"$11\r\nHello World\r\n"),
+        ("-3", "-1", b"$3\r\nrld\r\n"),
+        ("6", "-1", b"$5\r\nWorld\r\n"),
+        // clamped to the value
+        ("-100", "-1", b"$11\r\nHello World\r\n"),
+        ("10", "100", b"$1\r\nd\r\n"),
+        ("-100", "-100", b"$1\r\nH\r\n"),
+        ("0", "9223372036854775807", b"$11\r\nHello World\r\n"),
+        // nothing left
+        ("11", "20", b"$0\r\n\r\n"),
+        ("5", "3", b"$0\r\n\r\n"),
+        ("-1", "-5", b"$0\r\n\r\n"),
+        ("3", "-100", b"$0\r\n\r\n"),
+    ];
+
+    for (start, end, expected) in cases {
+        assert_eq!(
+            client.call_raw(&["GETRANGE", "foo", start, end]),
+            *expected,
+            "GETRANGE foo {} {}",
+            start,
+            end
+        );
+    }
+}
+
+#[test]
+fn getrange_of_nothing() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "missing", "0", "-1"]),
+        b"$0\r\n\r\n"
+    );
+
+    assert_eq!(client.call(&["SET", "empty", ""]), ok());
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "empty", "0", "-1"]),
+        b"$0\r\n\r\n"
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "empty", "-1", "-1"]),
+        b"$0\r\n\r\n"
+    );
+}
+
+#[test]
+fn getrange_counts_bytes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // two bytes each
+    assert_eq!(client.call(&["SET", "foo", "éé"]), ok());
+
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "1"]),
+        b"$2\r\n\xc3\xa9\r\n"
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "1", "2"]),
+        b"$2\r\n\xa9\xc3\r\n"
+    );
+}
+
+#[test]
+fn setrange_counts_bytes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // two bytes, and the second one goes like any other
+    assert_eq!(client.call(&["SET", "foo", "é"]), ok());
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "1", "x"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        b"$2\r\n\xc3x\r\n"
+    );
+
+    // half a character on its own, put back where it was
+    assert_eq!(
+        client.call_bytes(&[b"SETRANGE", b"foo", b"1", b"\xa9"]),
+        RespValue::Integer(2)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        "$2\r\né\r\n".as_bytes()
+    );
+}
+
+#[test]
+fn ranges_of_bytes_that_are_no_characters() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call_bytes(&[b"SET", b"foo", b"\x00\xff\x80"]), ok());
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "-2", "-1"]),
+        b"$2\r\n\xff\x80\r\n"
+    );
+
+    assert_eq!(
+        client.call_bytes(&[b"SETRANGE", b"foo", b"5", b"\xfe"]),
+        RespValue::Integer(6)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        b"$6\r\n\x00\xff\x80\0\0\xfe\r\n"
+    );
+}
+
+#[test]
+fn getrange_arguments() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["GETRANGE", "foo", "a", "1"]),
+        RespValue::Error("ERR value is not an integer or out of range".to_string())
+    );
+    assert_eq!(
+        client.call(&["GETRANGE", "foo", "0"]),
+        RespValue::Error("ERR wrong number of arguments for 'getrange' command".to_string())
+    );
+}
+
+#[test]
+fn setrange_pads_with_zero_bytes() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "Hello"]), ok());
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "7", "!"]),
+        RespValue::Integer(8)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        b"$8\r\nHello\0\0!\r\n"
+    );
+
+    // a missing key is all padding up to the offset
+    assert_eq!(
+        client.call(&["SETRANGE", "bar", "3", "ab"]),
+        RespValue::Integer(5)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "bar", "0", "-1"]),
+        b"$5\r\n\0\0\0ab\r\n"
+    );
+}
+
+#[test]
+fn setrange_overwrites_in_place() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "Hello World"]), ok());
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "6", "Redis"]),
+        RespValue::Integer(11)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        b"$11\r\nHello Redis\r\n"
+    );
+
+    // running past the end grows the value
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "10", "sss"]),
+        RespValue::Integer(13)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        b"$13\r\nHello Redisss\r\n"
+    );
+}
+
+#[test]
+fn setrange_with_an_empty_value() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // not created
+    assert_eq!(
+        client.call(&["SETRANGE", "missing", "5", ""]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(client.call(&["GET", "missing"]), RespValue::Null);
+
+    // not padded
+    assert_eq!(client.call(&["SET", "foo", "Hello"]), ok());
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "10", ""]),
+        RespValue::Integer(5)
+    );
+    assert_eq!(
+        client.call_raw(&["GETRANGE", "foo", "0", "-1"]),
+        b"$5\r\nHello\r\n"
+    );
+}
+
+#[test]
+fn setrange_refusals() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "-1", "a"]),
+        RespValue::Error("ERR offset is out of range".to_string())
+    );
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "one", "a"]),
+        RespValue::Error("ERR value is not an integer or out of range".to_string())
+    );
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+}
@@ -0,0 +1,144 @@
+// A panic costs only the connection it happened on: the request processor drops the request that
+// panicked and goes on with the others, INFO actors shows it, and that client is closed. DEBUG PANIC
+// panics the processor on a request, DEBUG SEGFAULT the connection's own task.
+
+mod common;
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn actors_section(client: &mut common::Client) -> String {
+    let RespValue::BulkString(Some(section)) = client.call(&["INFO", "actors"]) else {
+        panic!("INFO actors replies with a bulk string");
+    };
+
+    String::from_utf8(section.to_vec()).expect("INFO is text")
+}
+
+#[test]
+fn panicked_request_closes_its_connection_only() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+    let mut other = server.connect();
+
+    let before = actors_section(&mut client);
+    assert!(before.starts_with("# Actors\r\n"), "{before}");
+    for name in [
+        "store",
+        "config",
+        "processor",
+        "pubsub",
+        "clients",
+        "failpoints",
+    ] {
+        assert!(
+            before.contains(&format!("actor_{name}:running=")),
+            "{before}"
+        );
+    }
+    assert!(
+        before.contains("actor_processor:running=1,restarts=0,last_panic=none\r\n"),
+        "{before}"
+    );
+
+    assert_eq!(client.call(&["SET", "foo", "bar"]), ok());
+    assert_eq!(other.call(&["SELECT", "1"]), ok());
+    assert_eq!(other.call(&["MULTI"]), ok());
+    assert_eq!(other.call(&["SET", "foo", "in 1"]), simple("QUEUED"));
+
+    assert_eq!(
+        client.call(&["DEBUG", "PANIC"]),
+        RespValue::Error("ERR internal error, the request processor is not running".to_string())
+    );
+    client.read_until_closed();
+
+    // the other connection keeps its database and its open MULTI
+    assert_eq!(other.call(&["EXEC"]), RespValue::Array(vec![simple("OK")]));
+    assert_eq!(other.call(&["GET", "foo"]), bulk("in 1"));
+    assert_eq!(other.call(&["SELECT", "0"]), ok());
+    assert_eq!(other.call(&["GET", "foo"]), bulk("bar"));
+
+    // caught with the request, the processor itself never stopped
+    let after = actors_section(&mut other);
+    assert!(
+        after.contains("actor_processor:running=1,restarts=0,last_panic=DEBUG PANIC\r\n"),
+        "{after}"
+    );
+
+    let mut client = server.connect();
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+}
+
+#[test]
+fn panicked_connection_is_closed_and_forgotten() {
+    let server = Server::start(&["--enable-debug-command"]);
+    let mut client = server.connect();
+    let mut other = server.connect();
+
+    assert!(matches!(
+        client.call(&["HELLO", "2", "SETNAME", "doomed"]),
+        RespValue::Array(_)
+    ));
+    assert_eq!(
+        other.call(&["SUBSCRIBE", "news"]),
+        RespValue::Array(vec![
+            common::bulk("subscribe"),
+            common::bulk("news"),
+            RespValue::Integer(1),
+        ])
+    );
+
+    client.send(&["DEBUG", "SEGFAULT"]);
+    client.read_until_closed();
+
+    // the subscriber still gets what is published
+    let mut publisher = server.connect();
+    assert_eq!(
+        publisher.call(&["PUBLISH", "news", "still here"]),
+        RespValue::Integer(1)
+    );
+    assert_eq!(
+        other.receive(),
+        RespValue::Array(vec![
+            common::bulk("message"),
+            common::bulk("news"),
+            common::bulk("still here"),
+        ])
+    );
+
+    let RespValue::BulkString(Some(list)) = publisher.call(&["CLIENT", "LIST"]) else {
+        panic!("CLIENT LIST replies with a bulk string");
+    };
+    let list = String::from_utf8(list.to_vec()).expect("CLIENT LIST is text");
+    assert!(!list.contains("name=doomed"), "{list}");
+    assert_eq!(list.lines().count(), 2, "{list}");
+}
+
+#[test]
+fn debug_segfault_needs_the_debug_command() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert!(matches!(
+        client.call(&["DEBUG", "SEGFAULT"]),
+        RespValue::Error(_)
+    ));
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+}
+
+#[test]
+fn failed_request_leaves_its_connection_open() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    // a key that isn't UTF-8, refused without panicking
+    client.send_bytes(b"*3\r\n$3\r\nSET\r\n$2\r\n\xff\xfe\r\n$1\r\nv\r\n");
+    assert_eq!(
+        client.receive(),
+        RespValue::Error(
+            "ERR invalid UTF-8 in an argument, only string values may hold any bytes".to_string()
+        )
+    );
+
+    assert_eq!(client.call(&["PING"]), simple("PONG"));
+}
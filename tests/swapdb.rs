@@ -0,0 +1,92 @@
+// SWAPDB trades the contents of two databases, deadlines included, for every connection at once,
+// and the replicas do the same.
+
+mod common;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn error(message: &str) -> RespValue {
+    RespValue::Error(message.to_string())
+}
+
+#[test]
+fn databases_trade_places() {
+    let server = Server::start(&[]);
+    let mut zero = server.connect();
+    let mut one = server.connect();
+
+    assert_eq!(zero.call(&["SET", "foo", "in zero"]), ok());
+    assert_eq!(zero.call(&["SET", "ttl", "x", "EX", "100"]), ok());
+    assert_eq!(one.call(&["SELECT", "1"]), ok());
+    assert_eq!(one.call(&["SET", "foo", "in one"]), ok());
+
+    assert_eq!(zero.call(&["SWAPDB", "0", "1"]), ok());
+
+    // each connection stays in its database, which now holds the other's keys
+    assert_eq!(zero.call(&["GET", "foo"]), bulk("in one"));
+    assert_eq!(zero.call(&["DBSIZE"]), RespValue::Integer(1));
+    assert_eq!(one.call(&["GET", "foo"]), bulk("in zero"));
+    assert_eq!(one.call(&["DBSIZE"]), RespValue::Integer(2));
+
+    let RespValue::BulkString(Some(keyspace)) = one.call(&["INFO", "keyspace"]) else {
+        panic!("INFO keyspace replies with a bulk string");
+    };
+    let keyspace = String::from_utf8_lossy(&keyspace).to_string();
+    assert!(keyspace.contains("db0:keys=1,expires=0,"), "{}", keyspace);
+    assert!(keyspace.contains("db1:keys=2,expires=1,"), "{}", keyspace);
+
+    // a database with itself is fine and changes nothing
+    assert_eq!(zero.call(&["SWAPDB", "0", "0"]), ok());
+    assert_eq!(zero.call(&["GET", "foo"]), bulk("in one"));
+}
+
+#[test]
+fn bad_indexes() {
+    let server = Server::start(&["--databases", "4"]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["SWAPDB", "a", "1"]),
+        error("ERR invalid first DB index")
+    );
+    assert_eq!(
+        client.call(&["SWAPDB", "0", "b"]),
+        error("ERR invalid second DB index")
+    );
+    assert_eq!(
+        client.call(&["SWAPDB", "0", "4"]),
+        error("ERR DB index is out of range")
+    );
+    assert_eq!(
+        client.call(&["SWAPDB", "-1", "0"]),
+        error("ERR DB index is out of range")
+    );
+    assert_eq!(
+        client.call(&["SWAPDB", "0"]),
+        error("ERR wrong number of arguments for 'swapdb' command")
+    );
+}
+
+#[test]
+fn replicas_swap_too() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    assert_eq!(to_master.call(&["SET", "foo", "bar"]), ok());
+    to_replica.wait_for(&["GET", "foo"], bulk("bar"));
+
+    assert_eq!(to_master.call(&["SWAPDB", "0", "3"]), ok());
+    // refused, so not sent on
+    assert!(matches!(
+        to_master.call(&["SWAPDB", "0", "99"]),
+        RespValue::Error(_)
+    ));
+
+    to_replica.wait_for(&["GET", "foo"], RespValue::Null);
+    assert_eq!(to_replica.call(&["SELECT", "3"]), ok());
+    assert_eq!(to_replica.call(&["GET", "foo"]), bulk("bar"));
+}
@@ -0,0 +1,34 @@
+// A request the processor does not answer within request-timeout gets an error, and the
+// connection that sent it keeps working. DEBUG SLEEP stalls the processor to get there.
+
+mod common;
+
+use common::{bulk, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+#[test]
+fn stalled_request_times_out() {
+    let server = Server::start(&["--enable-debug-command", "--request-timeout", "200"]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "request-timeout"]),
+        RespValue::Array(vec![bulk("request-timeout"), bulk("200")])
+    );
+
+    assert_eq!(
+        client.call(&["DEBUG", "SLEEP", "1"]),
+        RespValue::Error("ERR internal timeout".to_string())
+    );
+
+    // the sleep still finishes on the processor, after which it answers again
+    client.wait_for(&["PING"], simple("PONG"));
+}
+
+#[test]
+fn zero_waits_forever() {
+    let server = Server::start(&["--enable-debug-command", "--request-timeout", "0"]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["DEBUG", "SLEEP", "0.3"]), simple("OK"));
+}
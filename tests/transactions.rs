@@ -0,0 +1,160 @@
+// MULTI queues commands until EXEC runs them back to back. Commands that block or change the
+// connection's mode have no place inside one: WAIT answers right away and SUBSCRIBE is refused.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{bulk, ok, simple, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn queued() -> RespValue {
+    simple("QUEUED")
+}
+
+fn error(message: &str) -> RespValue {
+    RespValue::Error(message.to_string())
+}
+
+#[test]
+fn exec_runs_what_was_queued() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+    let mut other = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "bar"]), queued());
+    assert_eq!(client.call(&["GET", "foo"]), queued());
+    assert_eq!(client.call(&["APPEND", "foo", "baz"]), queued());
+
+    // nothing has run yet
+    assert_eq!(other.call(&["GET", "foo"]), RespValue::Null);
+
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Array(vec![ok(), bulk("bar"), RespValue::Integer(6)])
+    );
+    assert_eq!(client.call(&["GET", "foo"]), bulk("barbaz"));
+
+    // an empty transaction is fine too
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["EXEC"]), RespValue::Array(vec![]));
+}
+
+#[test]
+fn discard_drops_the_queue() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "bar"]), queued());
+    assert_eq!(client.call(&["DISCARD"]), ok());
+
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+}
+
+#[test]
+fn transaction_commands_out_of_place() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["EXEC"]), error("ERR EXEC without MULTI"));
+    assert_eq!(
+        client.call(&["DISCARD"]),
+        error("ERR DISCARD without MULTI")
+    );
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(
+        client.call(&["MULTI"]),
+        error("ERR MULTI calls can not be nested")
+    );
+
+    // a nested MULTI doesn't spoil the transaction
+    assert_eq!(client.call(&["SET", "foo", "bar"]), queued());
+    assert_eq!(client.call(&["EXEC"]), RespValue::Array(vec![ok()]));
+}
+
+#[test]
+fn a_bad_command_aborts_the_transaction() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "bar"]), queued());
+    assert_eq!(
+        client.call(&["SET", "foo"]),
+        error("ERR wrong number of arguments for 'set' command")
+    );
+    assert_eq!(
+        client.call(&["EXEC"]),
+        error("EXECABORT Transaction discarded because of previous errors.")
+    );
+
+    // nothing ran, and the connection is out of the transaction
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+    assert_eq!(client.call(&["EXEC"]), error("ERR EXEC without MULTI"));
+}
+
+#[test]
+fn subscribe_is_refused_inside_multi() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "bar"]), queued());
+    assert_eq!(
+        client.call(&["SUBSCRIBE", "news"]),
+        error("ERR Command not allowed inside a transaction")
+    );
+    assert_eq!(
+        client.call(&["EXEC"]),
+        error("EXECABORT Transaction discarded because of previous errors.")
+    );
+
+    // never subscribed, so publishing reaches nobody
+    assert_eq!(
+        client.call(&["PUBLISH", "news", "hello"]),
+        RespValue::Integer(0)
+    );
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+}
+
+#[test]
+fn wait_inside_multi_does_not_block() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SET", "foo", "bar"]), queued());
+    assert_eq!(client.call(&["WAIT", "1", "5000"]), queued());
+
+    // no replica will ever acknowledge, on its own this WAIT would sit out the whole timeout
+    let start = Instant::now();
+    assert_eq!(
+        client.call(&["EXEC"]),
+        RespValue::Array(vec![ok(), RespValue::Integer(0)])
+    );
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn select_inside_multi_takes_effect_on_exec() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SELECT", "1"]), queued());
+    assert_eq!(client.call(&["SET", "foo", "one"]), queued());
+    assert_eq!(client.call(&["EXEC"]), RespValue::Array(vec![ok(), ok()]));
+
+    assert_eq!(client.call(&["GET", "foo"]), bulk("one"));
+    assert_eq!(client.call(&["SELECT", "0"]), ok());
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+
+    // discarded, so still in database 0
+    assert_eq!(client.call(&["MULTI"]), ok());
+    assert_eq!(client.call(&["SELECT", "1"]), queued());
+    assert_eq!(client.call(&["DISCARD"]), ok());
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+}
@@ -0,0 +1,74 @@
+// SET, APPEND and SETRANGE refuse to leave a value longer than proto-max-bulk-len, and APPEND
+// extends the value where it is, keeping its deadline.
+
+mod common;
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn too_long() -> RespValue {
+    RespValue::Error("ERR string exceeds maximum allowed size (proto-max-bulk-len)".to_string())
+}
+
+#[test]
+fn values_stop_at_proto_max_bulk_len() {
+    let server = Server::start(&["--proto-max-bulk-len", "8"]);
+    let mut client = server.connect();
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "proto-max-bulk-len"]),
+        RespValue::Array(vec![bulk("proto-max-bulk-len"), bulk("8")])
+    );
+
+    assert_eq!(client.call(&["SET", "foo", "123456789"]), too_long());
+    assert_eq!(client.call(&["GET", "foo"]), RespValue::Null);
+
+    assert_eq!(client.call(&["SET", "foo", "1234"]), ok());
+    assert_eq!(
+        client.call(&["APPEND", "foo", "5678"]),
+        RespValue::Integer(8)
+    );
+
+    // one byte over leaves the value as it was
+    assert_eq!(client.call(&["APPEND", "foo", "9"]), too_long());
+    assert_eq!(client.call(&["GET", "foo"]), bulk("12345678"));
+
+    // SETRANGE counts its padding
+    assert_eq!(client.call(&["SETRANGE", "foo", "8", "9"]), too_long());
+    assert_eq!(client.call(&["SETRANGE", "baz", "7", "12"]), too_long());
+    assert_eq!(
+        client.call(&["SETRANGE", "foo", "0", "abc"]),
+        RespValue::Integer(8)
+    );
+    assert_eq!(client.call(&["GET", "foo"]), bulk("abc45678"));
+
+    // a new key too
+    assert_eq!(client.call(&["APPEND", "bar", "123456789"]), too_long());
+    assert_eq!(client.call(&["GET", "bar"]), RespValue::Null);
+}
+
+#[test]
+fn append_keeps_the_deadline() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    assert_eq!(client.call(&["SET", "foo", "bar", "EX", "100"]), ok());
+    assert_eq!(
+        client.call(&["APPEND", "foo", "baz"]),
+        RespValue::Integer(6)
+    );
+
+    let RespValue::BulkString(Some(keyspace)) = client.call(&["INFO", "keyspace"]) else {
+        panic!("INFO keyspace replies with a bulk string");
+    };
+    assert!(String::from_utf8_lossy(&keyspace).contains("db0:keys=1,expires=1,"));
+
+    // many small appends add up to the whole value
+    for _ in 0..200 {
+        client.call(&["APPEND", "log", "0123456789"]);
+    }
+    assert_eq!(
+        client.call(&["APPEND", "log", "!"]),
+        RespValue::Integer(2001)
+    );
+}
@@ -0,0 +1,68 @@
+// MGET and DEL hand the store all their keys at once. The replies still line up with the keys as
+// given, expired and missing ones included.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::{bulk, ok, Server};
+use redis_starter_rust::resp::value::RespValue;
+
+fn key_names(count: usize) -> Vec<String> {
+    (0..count).map(|n| format!("key:{}", n)).collect()
+}
+
+#[test]
+fn mget_replies_in_the_order_of_the_keys() {
+    let server = Server::start(&[]);
+    let mut client = server.connect();
+
+    let keys = key_names(200);
+    // every other key, the last of them about to expire
+    for key in keys.iter().step_by(2) {
+        assert_eq!(client.call(&["SET", key, key]), ok());
+    }
+    assert_eq!(client.call(&["SET", "key:198", "soon", "PX", "10"]), ok());
+    thread::sleep(Duration::from_millis(20));
+
+    let mut mget = vec!["MGET"];
+    mget.extend(keys.iter().map(String::as_str));
+
+    let expected = keys
+        .iter()
+        .enumerate()
+        .map(|(n, key)| {
+            if n % 2 == 0 && n != 198 {
+                bulk(key)
+            } else {
+                RespValue::Null
+            }
+        })
+        .collect();
+    assert_eq!(client.call(&mget), RespValue::Array(expected));
+}
+
+#[test]
+fn del_removes_every_key_it_names() {
+    let master = Server::start(&[]);
+    let replica = Server::start(&["--replicaof", &master.address()]);
+
+    let mut to_master = master.connect();
+    let mut to_replica = replica.connect();
+
+    let keys = key_names(100);
+    for key in &keys {
+        assert_eq!(to_master.call(&["SET", key, "value"]), ok());
+    }
+    assert_eq!(to_master.call(&["SET", "kept", "value"]), ok());
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(101));
+
+    // named twice, or not there at all
+    let mut del = vec!["DEL", "key:0", "missing"];
+    del.extend(keys.iter().map(String::as_str));
+    assert_eq!(to_master.call(&del), RespValue::Integer(100));
+
+    assert_eq!(to_master.call(&["DBSIZE"]), RespValue::Integer(1));
+    assert_eq!(to_master.call(&["GET", "kept"]), bulk("value"));
+    to_replica.wait_for(&["DBSIZE"], RespValue::Integer(1));
+}